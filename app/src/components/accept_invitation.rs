@@ -0,0 +1,287 @@
+use crate::{
+    components::{
+        form::{field::Field, static_value::StaticValue, submit::Submit},
+        router::{AppRoute, Link},
+    },
+    infra::{
+        api::HostService,
+        common_component::{CommonComponent, CommonComponentParts},
+    },
+};
+use anyhow::{Result, bail};
+use lldap_auth::{
+    invitation::{
+        ClientInvitationRegistrationFinishRequest, ClientInvitationRegistrationStartRequest,
+        ServerInvitationRegistrationStartResponse, ServerInvitationResponse,
+    },
+    opaque::client::registration as opaque_registration,
+    registration,
+};
+use lldap_validation::users::{
+    USER_ID_ALLOWED_CHARACTERS_DESCRIPTION, USER_ID_MAX_LENGTH, validate_user_id,
+};
+use validator_derive::Validate;
+use yew::prelude::*;
+use yew_form::Form;
+use yew_form_derive::Model;
+use yew_router::{prelude::History, scope_ext::RouterScopeExt};
+
+/// The fields of the form, with the constraints.
+#[derive(Model, Validate, PartialEq, Eq, Clone, Default)]
+pub struct FormModel {
+    #[validate(length(min = 1, max = 64, message = "Invalid user ID. Max length: 64"))]
+    user_id: String,
+    #[validate(email(message = "A valid email is required"))]
+    email: String,
+    display_name: String,
+    #[validate(length(min = 8, message = "Invalid password. Min length: 8"))]
+    password: String,
+    #[validate(must_match(other = "password", message = "Passwords must match"))]
+    confirm_password: String,
+}
+
+/// Lets an invited user pick their user ID and password, and creates the account.
+pub struct AcceptInvitationForm {
+    common: CommonComponentParts<Self>,
+    form: Form<FormModel>,
+    invitation: Option<ServerInvitationResponse>,
+    opaque_data: Option<opaque_registration::ClientRegistration>,
+}
+
+#[derive(Clone, PartialEq, Eq, Properties)]
+pub struct Props {
+    pub token: String,
+}
+
+pub enum Msg {
+    GetInvitationResponse(Result<ServerInvitationResponse>),
+    FormUpdate,
+    Submit,
+    RegistrationStartResponse(Result<Box<ServerInvitationRegistrationStartResponse>>),
+    RegistrationFinishResponse(Result<()>),
+}
+
+impl CommonComponent<AcceptInvitationForm> for AcceptInvitationForm {
+    fn handle_msg(
+        &mut self,
+        ctx: &Context<Self>,
+        msg: <Self as Component>::Message,
+    ) -> Result<bool> {
+        use anyhow::Context;
+        match msg {
+            Msg::GetInvitationResponse(response) => {
+                let invitation = response?;
+                self.form = Form::new(FormModel {
+                    user_id: invitation.user_id.clone().unwrap_or_default(),
+                    email: invitation.email.clone().unwrap_or_default(),
+                    ..Default::default()
+                });
+                self.invitation = Some(invitation);
+                Ok(true)
+            }
+            Msg::FormUpdate => Ok(true),
+            Msg::Submit => {
+                if !self.form.validate() {
+                    bail!("Check the form for errors");
+                }
+                let model = self.form.model();
+                let user_id_is_imposed = self
+                    .invitation
+                    .as_ref()
+                    .is_some_and(|i| i.user_id.is_some());
+                if !user_id_is_imposed {
+                    if let Err(invalid_chars) = validate_user_id(&model.user_id) {
+                        bail!(
+                            "Invalid characters in the user ID: {:?}. Allowed characters: {}",
+                            invalid_chars,
+                            USER_ID_ALLOWED_CHARACTERS_DESCRIPTION
+                        );
+                    }
+                }
+                let mut rng = rand::rngs::OsRng;
+                let registration_start_request =
+                    opaque_registration::start_registration(model.password.as_bytes(), &mut rng)
+                        .context("Could not initiate registration")?;
+                let req = ClientInvitationRegistrationStartRequest {
+                    user_id: model.user_id,
+                    email: model.email,
+                    display_name: Some(model.display_name).filter(|n| !n.is_empty()),
+                    registration_start_request: registration_start_request.message,
+                };
+                self.opaque_data = Some(registration_start_request.state);
+                self.common.call_backend(
+                    ctx,
+                    HostService::invitation_register_start(ctx.props().token.clone(), req),
+                    Msg::RegistrationStartResponse,
+                );
+                Ok(true)
+            }
+            Msg::RegistrationStartResponse(res) => {
+                let res = res.context("Could not initiate registration")?;
+                let registration = self.opaque_data.take().expect("Missing registration data");
+                let mut rng = rand::rngs::OsRng;
+                let registration_finish = opaque_registration::finish_registration(
+                    registration,
+                    res.registration.registration_response,
+                    &mut rng,
+                )
+                .context("Error during registration")?;
+                let model = self.form.model();
+                let req = ClientInvitationRegistrationFinishRequest {
+                    user_id: model.user_id,
+                    email: model.email,
+                    display_name: Some(model.display_name).filter(|n| !n.is_empty()),
+                    signature: res.signature,
+                    registration: registration::ClientRegistrationFinishRequest {
+                        server_data: res.registration.server_data,
+                        registration_upload: registration_finish.message,
+                    },
+                };
+                self.common.call_backend(
+                    ctx,
+                    HostService::invitation_register_finish(ctx.props().token.clone(), req),
+                    Msg::RegistrationFinishResponse,
+                );
+                Ok(false)
+            }
+            Msg::RegistrationFinishResponse(response) => {
+                if response.is_ok() {
+                    ctx.link().history().unwrap().push(AppRoute::Login);
+                }
+                response?;
+                Ok(true)
+            }
+        }
+    }
+
+    fn mut_common(&mut self) -> &mut CommonComponentParts<Self> {
+        &mut self.common
+    }
+}
+
+impl Component for AcceptInvitationForm {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let mut component = AcceptInvitationForm {
+            common: CommonComponentParts::<Self>::create(),
+            form: Form::<FormModel>::new(FormModel::default()),
+            invitation: None,
+            opaque_data: None,
+        };
+        component.common.call_backend(
+            ctx,
+            HostService::get_invitation(ctx.props().token.clone()),
+            Msg::GetInvitationResponse,
+        );
+        component
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        CommonComponentParts::<Self>::update(self, ctx, msg)
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = &ctx.link();
+        let invitation = match (&self.invitation, &self.common.error) {
+            (None, None) => {
+                return html! {
+                  {"Validating invitation"}
+                };
+            }
+            (None, Some(e)) => {
+                return html! {
+                  <>
+                    <div class="alert alert-danger">
+                      {e.to_string() }
+                    </div>
+                    <Link
+                      classes="btn-link btn"
+                      disabled={self.common.is_task_running()}
+                      to={AppRoute::Login}>
+                      {"Back"}
+                    </Link>
+                  </>
+                };
+            }
+            (Some(invitation), _) => invitation,
+        };
+        html! {
+          <>
+            <h2>{"Create your account"}</h2>
+            <form class="form">
+              {match &invitation.user_id {
+                Some(user_id) => html! {
+                  <StaticValue label="User ID" id="user_id">{user_id}</StaticValue>
+                },
+                None => html! {
+                  <>
+                    <Field<FormModel>
+                      label="User ID"
+                      required=true
+                      form={&self.form}
+                      field_name="user_id"
+                      autocomplete="username"
+                      oninput={link.callback(|_| Msg::FormUpdate)} />
+                    <div class="form-text mb-3">
+                      {format!(
+                        "Up to {USER_ID_MAX_LENGTH} characters among {USER_ID_ALLOWED_CHARACTERS_DESCRIPTION}."
+                      )}
+                    </div>
+                  </>
+                },
+              }}
+              {match &invitation.email {
+                Some(email) => html! {
+                  <StaticValue label="Email" id="email">{email}</StaticValue>
+                },
+                None => html! {
+                  <Field<FormModel>
+                    label="Email"
+                    required=true
+                    form={&self.form}
+                    field_name="email"
+                    input_type="email"
+                    oninput={link.callback(|_| Msg::FormUpdate)} />
+                },
+              }}
+              <Field<FormModel>
+                label="Display name"
+                form={&self.form}
+                field_name="display_name"
+                autocomplete="name"
+                oninput={link.callback(|_| Msg::FormUpdate)} />
+              <Field<FormModel>
+                label="Password"
+                required=true
+                form={&self.form}
+                field_name="password"
+                autocomplete="new-password"
+                input_type="password"
+                oninput={link.callback(|_| Msg::FormUpdate)} />
+              <Field<FormModel>
+                label="Confirm password"
+                required=true
+                form={&self.form}
+                field_name="confirm_password"
+                autocomplete="new-password"
+                input_type="password"
+                oninput={link.callback(|_| Msg::FormUpdate)} />
+              <Submit
+                text="Create account"
+                disabled={self.common.is_task_running()}
+                onclick={link.callback(|e: MouseEvent| {e.prevent_default(); Msg::Submit})} />
+            </form>
+            { if let Some(e) = &self.common.error {
+                html! {
+                  <div class="alert alert-danger">
+                    {e.to_string() }
+                  </div>
+                }
+              } else { html! {} }
+            }
+          </>
+        }
+    }
+}
@@ -5,6 +5,7 @@ use crate::{
 use anyhow::{Error, Result};
 use graphql_client::GraphQLQuery;
 use std::collections::HashSet;
+use web_sys::HtmlInputElement;
 use yew::prelude::*;
 
 #[derive(GraphQLQuery)]
@@ -30,7 +31,8 @@ pub type User = list_user_names::ListUserNamesUsers;
 
 pub struct AddGroupMemberComponent {
     common: CommonComponentParts<Self>,
-    /// The list of existing users, initially not loaded.
+    /// The list of existing users, initially not loaded. Only the admins can list the users: the
+    /// owners of the group type the ID of the user instead.
     user_list: Option<Vec<User>>,
     /// The currently selected user.
     selected_user: Option<User>,
@@ -41,12 +43,14 @@ pub enum Msg {
     SubmitAddMember,
     AddMemberResponse(Result<add_user_to_group::ResponseData>),
     SelectionChanged(Option<SelectOptionProps>),
+    UserIdChanged(String),
 }
 
 #[derive(yew::Properties, Clone, PartialEq)]
 pub struct Props {
     pub group_id: i64,
     pub users: Vec<User>,
+    pub is_admin: bool,
     pub on_user_added_to_group: Callback<User>,
     pub on_error: Callback<Error>,
 }
@@ -80,6 +84,15 @@ impl CommonComponent<AddGroupMemberComponent> for AddGroupMemberComponent {
                 });
                 return Ok(self.selected_user.is_some() != was_some);
             }
+            Msg::UserIdChanged(user_id) => {
+                let was_some = self.selected_user.is_some();
+                let user_id = user_id.trim();
+                self.selected_user = (!user_id.is_empty()).then(|| User {
+                    id: user_id.to_owned(),
+                    display_name: String::new(),
+                });
+                return Ok(self.selected_user.is_some() != was_some);
+            }
         }
         Ok(true)
     }
@@ -136,7 +149,9 @@ impl Component for AddGroupMemberComponent {
             user_list: None,
             selected_user: None,
         };
-        res.get_user_list(ctx);
+        if ctx.props().is_admin {
+            res.get_user_list(ctx);
+        }
         res
     }
 
@@ -151,7 +166,36 @@ impl Component for AddGroupMemberComponent {
 
     fn view(&self, ctx: &Context<Self>) -> Html {
         let link = ctx.link();
-        if let Some(user_list) = &self.user_list {
+        let add_button = html! {
+          <div class="col-3">
+            <button
+              class="btn btn-secondary"
+              disabled={self.selected_user.is_none() || self.common.is_task_running()}
+              onclick={link.callback(|_| Msg::SubmitAddMember)}>
+               <i class="bi-person-plus me-2"></i>
+              {"Add to group"}
+            </button>
+          </div>
+        };
+        if !ctx.props().is_admin {
+            html! {
+            <div class="row">
+              <div class="col-sm-3">
+                <input
+                  class="form-control"
+                  type="text"
+                  placeholder="User ID"
+                  aria-label="User ID"
+                  autocomplete="off"
+                  oninput={link.callback(|e: InputEvent| {
+                      let input: HtmlInputElement = e.target_unchecked_into();
+                      Msg::UserIdChanged(input.value())
+                  })} />
+              </div>
+              {add_button}
+            </div>
+            }
+        } else if let Some(user_list) = &self.user_list {
             let to_add_user_list = self.get_selectable_user_list(ctx, user_list);
             #[allow(unused_braces)]
             let make_select_option = |user: User| {
@@ -176,15 +220,7 @@ impl Component for AddGroupMemberComponent {
                   }
                 </Select>
               </div>
-              <div class="col-3">
-                <button
-                  class="btn btn-secondary"
-                  disabled={self.selected_user.is_none() || self.common.is_task_running()}
-                  onclick={link.callback(|_| Msg::SubmitAddMember)}>
-                   <i class="bi-person-plus me-2"></i>
-                  {"Add to group"}
-                </button>
-              </div>
+              {add_button}
             </div>
             }
         } else {
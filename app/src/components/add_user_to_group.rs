@@ -71,7 +71,15 @@ impl CommonComponent<AddUserToGroupComponent> for AddUserToGroupComponent {
     ) -> Result<bool> {
         match msg {
             Msg::GroupListResponse(response) => {
-                self.group_list = Some(response?.groups.into_iter().map(Into::into).collect());
+                // The members of the dynamic groups can't be added directly.
+                self.group_list = Some(
+                    response?
+                        .groups
+                        .into_iter()
+                        .filter(|g| g.dynamic_filter.is_none())
+                        .map(Into::into)
+                        .collect(),
+                );
             }
             Msg::SubmitAddGroup => return self.submit_add_group(ctx),
             Msg::AddGroupResponse(response) => {
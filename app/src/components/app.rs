@@ -1,14 +1,17 @@
 use crate::{
     components::{
+        accept_invitation::AcceptInvitationForm,
         banner::Banner,
         change_password::ChangePasswordForm,
         create_group::CreateGroupForm,
         create_group_attribute::CreateGroupAttributeForm,
+        create_invitation::CreateInvitationForm,
         create_user::CreateUserForm,
         create_user_attribute::CreateUserAttributeForm,
         group_details::GroupDetails,
         group_schema_table::ListGroupSchema,
         group_table::GroupTable,
+        import_users::ImportUsersForm,
         login::LoginForm,
         reset_password_step1::ResetPasswordStep1Form,
         reset_password_step2::ResetPasswordStep2Form,
@@ -16,6 +19,7 @@ use crate::{
         user_details::UserDetails,
         user_schema_table::ListUserSchema,
         user_table::UserTable,
+        verify_email::VerifyEmail,
     },
     infra::{api::HostService, cookies::get_cookie},
 };
@@ -146,6 +150,8 @@ impl App {
                     | AppRoute::Login
                     | AppRoute::StartResetPassword
                     | AppRoute::FinishResetPassword { token: _ }
+                    | AppRoute::AcceptInvitation { token: _ }
+                    | AppRoute::VerifyEmail { token: _ }
             )
         })
     }
@@ -165,6 +171,10 @@ impl App {
                     None
                 }
             }
+            // Invitees don't have an account yet.
+            (Some(AppRoute::AcceptInvitation { token: _ }), _, _) => None,
+            // The link can be opened from another browser.
+            (Some(AppRoute::VerifyEmail { token: _ }), _, _) => None,
             (None, _, _) | (_, None, _) => Some(AppRoute::Login),
             // User is logged in, a URL was given, don't redirect.
             (_, Some(_), Some(_)) => None,
@@ -196,13 +206,35 @@ impl App {
             AppRoute::CreateUser => html! {
                 <CreateUserForm/>
             },
+            AppRoute::InviteUser => html! {
+                <CreateInvitationForm/>
+            },
+            AppRoute::ImportUsers => html! {
+                <ImportUsersForm/>
+            },
+            AppRoute::AcceptInvitation { token } => html! {
+                <AcceptInvitationForm token={token.clone()} />
+            },
+            AppRoute::VerifyEmail { token } => html! {
+                <VerifyEmail token={token.clone()} />
+            },
             AppRoute::Index | AppRoute::ListUsers => {
                 let user_button = |key| {
                     html! {
-                      <Link classes="btn btn-primary" key={key} to={AppRoute::CreateUser}>
-                        <i class="bi-person-plus me-2"></i>
-                        {"Create a user"}
-                      </Link>
+                      <div key={key}>
+                        <Link classes="btn btn-primary" to={AppRoute::CreateUser}>
+                          <i class="bi-person-plus me-2"></i>
+                          {"Create a user"}
+                        </Link>
+                        <Link classes="btn btn-secondary ms-2" to={AppRoute::InviteUser}>
+                          <i class="bi-envelope-plus me-2"></i>
+                          {"Invite a user"}
+                        </Link>
+                        <Link classes="btn btn-secondary ms-2" to={AppRoute::ImportUsers}>
+                          <i class="bi-file-earmark-arrow-up me-2"></i>
+                          {"Import / export"}
+                        </Link>
+                      </div>
                     }
                 };
                 html! {
@@ -249,7 +281,10 @@ impl App {
                 <GroupDetails group_id={*group_id} is_admin={is_admin} />
             },
             AppRoute::UserDetails { user_id } => html! {
-                <UserDetails username={user_id.clone()} is_admin={is_admin} />
+                <UserDetails
+                  username={user_id.clone()}
+                  is_admin={is_admin}
+                  confirm_email_changes={password_reset_enabled.unwrap_or(false)} />
             },
             AppRoute::ChangePassword { user_id } => html! {
                 <ChangePasswordForm username={user_id.clone()} is_admin={is_admin} />
@@ -10,7 +10,7 @@ use crate::{
     infra::{
         common_component::{CommonComponent, CommonComponentParts},
         form_utils::{
-            AttributeValue, EmailIsRequired, GraphQlAttributeSchema, IsAdmin,
+            AttributeValidation, AttributeValue, EmailIsRequired, GraphQlAttributeSchema, IsAdmin,
             read_all_form_attributes,
         },
         schema::AttributeType,
@@ -46,6 +46,15 @@ impl From<&Attribute> for GraphQlAttributeSchema {
             is_list: attr.is_list,
             is_readonly: attr.is_readonly,
             is_editable: false, // Need to be admin to edit it.
+            validation: AttributeValidation::new(
+                attr.validation.regex.clone(),
+                attr.validation.min_length,
+                attr.validation.max_length,
+                attr.validation.min_value.as_deref(),
+                attr.validation.max_value.as_deref(),
+                attr.validation.allowed_values.clone(),
+                attr.validation.is_required,
+            ),
         }
     }
 }
@@ -70,6 +79,8 @@ pub struct CreateGroupForm {
 pub struct CreateGroupModel {
     #[validate(length(min = 1, message = "Groupname is required"))]
     groupname: String,
+    /// If set, the group is dynamic: its members are the users matching this LDAP filter.
+    dynamic_filter: String,
 }
 
 pub enum Msg {
@@ -114,6 +125,8 @@ impl CommonComponent<CreateGroupForm> for CreateGroupForm {
                     group: create_group::CreateGroupInput {
                         displayName: model.groupname,
                         attributes,
+                        dynamicFilter: Some(model.dynamic_filter.trim().to_owned())
+                            .filter(|f| !f.is_empty()),
                     },
                 };
                 self.common.call_graphql::<CreateGroup, _>(
@@ -186,6 +199,12 @@ impl Component for CreateGroupForm {
                 label="Group name"
                 field_name="groupname"
                 oninput={link.callback(|_| Msg::Update)} />
+              <Field<CreateGroupModel>
+                form={&self.form}
+                label="Dynamic filter"
+                field_name="dynamic_filter"
+                autocomplete="off"
+                oninput={link.callback(|_| Msg::Update)} />
               {
                   self.attributes_schema
                       .iter()
@@ -217,6 +236,7 @@ fn get_custom_attribute_input(attribute_schema: &Attribute) -> Html {
             <ListAttributeInput
                 name={attribute_schema.name.clone()}
                 attribute_type={attribute_schema.attribute_type}
+                validation={GraphQlAttributeSchema::from(attribute_schema).validation}
             />
         }
     } else {
@@ -224,6 +244,7 @@ fn get_custom_attribute_input(attribute_schema: &Attribute) -> Html {
             <SingleAttributeInput
                 name={attribute_schema.name.clone()}
                 attribute_type={attribute_schema.attribute_type}
+                validation={GraphQlAttributeSchema::from(attribute_schema).validation}
             />
         }
     }
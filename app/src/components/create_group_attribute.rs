@@ -8,10 +8,10 @@ use crate::{
         schema::{AttributeType, validate_attribute_type},
     },
 };
-use anyhow::{Result, bail};
+use anyhow::{Result, anyhow, bail};
 use gloo_console::log;
 use graphql_client::GraphQLQuery;
-use lldap_validation::attributes::validate_attribute_name;
+use lldap_validation::attributes::{validate_attribute_name, validate_attribute_regex};
 use validator_derive::Validate;
 use yew::prelude::*;
 use yew_form_derive::Model;
@@ -40,6 +40,10 @@ pub struct CreateGroupAttributeModel {
     attribute_type: String,
     is_list: bool,
     is_visible: bool, // remove when backend doesn't return group attributes for normal users
+    regex: String,
+    allowed_values: String,
+    is_unique: bool,
+    is_required: bool,
 }
 
 pub enum Msg {
@@ -68,13 +72,35 @@ impl CommonComponent<CreateGroupAttributeForm> for CreateGroupAttributeForm {
                         invalid
                     );
                 })?;
+                if !model.regex.is_empty() {
+                    validate_attribute_regex(&model.regex).map_err(|e| anyhow!("Pattern: {e}"))?;
+                }
                 let attribute_type =
                     AttributeType::try_from(model.attribute_type.as_str()).unwrap();
+                let validation = create_group_attribute::AttributeValidationInput {
+                    regex: Some(model.regex).filter(|r| !r.is_empty()),
+                    min_length: None,
+                    max_length: None,
+                    min_value: None,
+                    max_value: None,
+                    allowed_values: Some(
+                        model
+                            .allowed_values
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|v| !v.is_empty())
+                            .map(str::to_owned)
+                            .collect(),
+                    ),
+                    is_unique: Some(model.is_unique),
+                    is_required: Some(model.is_required),
+                };
                 let req = create_group_attribute::Variables {
                     name: model.attribute_name,
                     attribute_type,
                     is_list: model.is_list,
                     is_visible: model.is_visible,
+                    validation: Some(validation),
                 };
                 self.common.call_graphql::<CreateGroupAttribute, _>(
                     ctx,
@@ -157,6 +183,26 @@ impl Component for CreateGroupAttributeForm {
                 form={&self.form}
                 field_name="is_visible"
                 ontoggle={link.callback(|_| Msg::Update)} />
+              <Field<CreateGroupAttributeModel>
+                label="Pattern"
+                form={&self.form}
+                field_name="regex"
+                oninput={link.callback(|_| Msg::Update)} />
+              <Field<CreateGroupAttributeModel>
+                label="Allowed values (comma-separated)"
+                form={&self.form}
+                field_name="allowed_values"
+                oninput={link.callback(|_| Msg::Update)} />
+              <CheckBox<CreateGroupAttributeModel>
+                label="Unique"
+                form={&self.form}
+                field_name="is_unique"
+                ontoggle={link.callback(|_| Msg::Update)} />
+              <CheckBox<CreateGroupAttributeModel>
+                label="Required"
+                form={&self.form}
+                field_name="is_required"
+                ontoggle={link.callback(|_| Msg::Update)} />
               <Submit
                 disabled={self.common.is_task_running()}
                 onclick={link.callback(|e: MouseEvent| {e.prevent_default(); Msg::SubmitForm})}/>
@@ -0,0 +1,313 @@
+use crate::{
+    components::{
+        add_user_to_group::{GetGroupList, get_group_list},
+        create_user::{
+            Attribute, GetUserAttributesSchema, get_custom_attribute_input,
+            get_user_attributes_schema,
+        },
+        form::{field::Field, static_value::StaticValue, submit::Submit},
+        router::{AppRoute, Link},
+    },
+    infra::{
+        api::HostService,
+        common_component::{CommonComponent, CommonComponentParts},
+        form_utils::{AttributeValue, EmailIsRequired, IsAdmin, read_all_form_attributes},
+    },
+};
+use anyhow::{Result, ensure};
+use lldap_auth::invitation::{
+    ClientCreateInvitationRequest, InvitationAttribute, ServerCreateInvitationResponse,
+};
+use std::collections::BTreeSet;
+use validator_derive::Validate;
+use yew::prelude::*;
+use yew_form::Form;
+use yew_form_derive::Model;
+
+#[derive(Model, Validate, PartialEq, Eq, Clone)]
+pub struct CreateInvitationModel {
+    user_id: String,
+    #[validate(custom(
+        function = "empty_or_email",
+        message = "Invalid email address (or leave it empty)"
+    ))]
+    email: String,
+    #[validate(custom(function = "validity_days", message = "Must be between 1 and 30 days"))]
+    validity_days: String,
+}
+
+impl Default for CreateInvitationModel {
+    fn default() -> Self {
+        Self {
+            user_id: String::new(),
+            email: String::new(),
+            validity_days: "7".to_owned(),
+        }
+    }
+}
+
+fn empty_or_email(value: &str) -> Result<(), validator::ValidationError> {
+    if value.is_empty() || validator::validate_email(value) {
+        Ok(())
+    } else {
+        Err(validator::ValidationError::new(""))
+    }
+}
+
+fn validity_days(value: &str) -> Result<(), validator::ValidationError> {
+    match value.parse::<u32>() {
+        Ok(1..=30) => Ok(()),
+        _ => Err(validator::ValidationError::new("")),
+    }
+}
+
+/// Lets an admin create an invitation link, optionally sent by email.
+pub struct CreateInvitationForm {
+    common: CommonComponentParts<Self>,
+    form: Form<CreateInvitationModel>,
+    groups: Option<Vec<get_group_list::GetGroupListGroups>>,
+    selected_groups: BTreeSet<i64>,
+    /// The custom attributes, set by the admin since the invitee can't fill them in.
+    attributes_schema: Option<Vec<Attribute>>,
+    invitation: Option<ServerCreateInvitationResponse>,
+    form_ref: NodeRef,
+}
+
+pub enum Msg {
+    Update,
+    GroupListResponse(Result<get_group_list::ResponseData>),
+    ListAttributesResponse(Result<get_user_attributes_schema::ResponseData>),
+    ToggleGroup(i64),
+    SubmitForm,
+    CreateInvitationResponse(Result<ServerCreateInvitationResponse>),
+}
+
+impl CommonComponent<CreateInvitationForm> for CreateInvitationForm {
+    fn handle_msg(
+        &mut self,
+        ctx: &Context<Self>,
+        msg: <Self as Component>::Message,
+    ) -> Result<bool> {
+        match msg {
+            Msg::Update => Ok(true),
+            Msg::GroupListResponse(response) => {
+                // The members of the dynamic groups can't be added directly.
+                self.groups = Some(
+                    response?
+                        .groups
+                        .into_iter()
+                        .filter(|g| g.dynamic_filter.is_none())
+                        .collect(),
+                );
+                Ok(true)
+            }
+            Msg::ListAttributesResponse(schema) => {
+                self.attributes_schema = Some(
+                    schema?
+                        .schema
+                        .user_schema
+                        .attributes
+                        .into_iter()
+                        .filter(|a| !a.is_hardcoded && !a.is_readonly)
+                        .collect(),
+                );
+                Ok(true)
+            }
+            Msg::ToggleGroup(group_id) => {
+                if !self.selected_groups.remove(&group_id) {
+                    self.selected_groups.insert(group_id);
+                }
+                Ok(true)
+            }
+            Msg::SubmitForm => {
+                ensure!(self.form.validate(), "Check the form for errors");
+                let attributes = read_all_form_attributes(
+                    self.attributes_schema.iter().flatten(),
+                    &self.form_ref,
+                    IsAdmin(true),
+                    EmailIsRequired(false),
+                )?
+                .into_iter()
+                .filter(|a| !a.values.is_empty())
+                .map(|AttributeValue { name, values }| InvitationAttribute {
+                    name,
+                    value: values,
+                })
+                .collect();
+                let model = self.form.model();
+                let req = ClientCreateInvitationRequest {
+                    user_id: Some(model.user_id).filter(|u| !u.is_empty()),
+                    email: Some(model.email).filter(|e| !e.is_empty()),
+                    group_ids: self
+                        .selected_groups
+                        .iter()
+                        .map(|id| i32::try_from(*id))
+                        .collect::<Result<_, _>>()?,
+                    validity_days: Some(model.validity_days.parse()?),
+                    attributes,
+                };
+                self.common.call_backend(
+                    ctx,
+                    HostService::create_invitation(req),
+                    Msg::CreateInvitationResponse,
+                );
+                Ok(true)
+            }
+            Msg::CreateInvitationResponse(response) => {
+                self.invitation = Some(response?);
+                Ok(true)
+            }
+        }
+    }
+
+    fn mut_common(&mut self) -> &mut CommonComponentParts<Self> {
+        &mut self.common
+    }
+}
+
+impl CreateInvitationForm {
+    fn view_groups(&self, ctx: &Context<Self>) -> Html {
+        let link = &ctx.link();
+        let groups = match &self.groups {
+            None => return html! { {"Loading groups..."} },
+            Some(groups) => groups,
+        };
+        html! {
+          <div class="row mb-3">
+            <label class="form-label col-4 col-form-label">{"Groups:"}</label>
+            <div class="col-8">
+              {groups.iter().map(|group| {
+                let group_id = group.id;
+                let input_id = format!("group-{group_id}");
+                html! {
+                  <div class="form-check" key={group_id}>
+                    <input
+                      class="form-check-input"
+                      type="checkbox"
+                      id={input_id.clone()}
+                      checked={self.selected_groups.contains(&group_id)}
+                      onchange={link.callback(move |_| Msg::ToggleGroup(group_id))} />
+                    <label class="form-check-label" for={input_id}>
+                      {&group.display_name}
+                    </label>
+                  </div>
+                }
+              }).collect::<Vec<_>>()}
+            </div>
+          </div>
+        }
+    }
+
+    fn view_invitation(invitation: &ServerCreateInvitationResponse) -> Html {
+        html! {
+          <>
+            <div class="alert alert-success">
+              {if invitation.email_sent {
+                "The invitation was sent by email. You can also share the link below."
+              } else {
+                "Share the link below with the person you want to invite."
+              }}
+            </div>
+            <StaticValue label="Invitation link" id="invitation_url">
+              <a href={invitation.url.clone()}>{&invitation.url}</a>
+            </StaticValue>
+            <Link classes="btn btn-secondary" to={AppRoute::ListUsers}>
+              {"Back to the users"}
+            </Link>
+          </>
+        }
+    }
+}
+
+impl Component for CreateInvitationForm {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let mut component = Self {
+            common: CommonComponentParts::<Self>::create(),
+            form: Form::<CreateInvitationModel>::new(CreateInvitationModel::default()),
+            groups: None,
+            selected_groups: BTreeSet::new(),
+            attributes_schema: None,
+            invitation: None,
+            form_ref: NodeRef::default(),
+        };
+        component.common.call_graphql::<GetGroupList, _>(
+            ctx,
+            get_group_list::Variables {},
+            Msg::GroupListResponse,
+            "Error trying to fetch groups",
+        );
+        component.common.call_graphql::<GetUserAttributesSchema, _>(
+            ctx,
+            get_user_attributes_schema::Variables {},
+            Msg::ListAttributesResponse,
+            "Error trying to fetch user schema",
+        );
+        component
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        CommonComponentParts::<Self>::update(self, ctx, msg)
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        if let Some(invitation) = &self.invitation {
+            return Self::view_invitation(invitation);
+        }
+        let link = &ctx.link();
+        html! {
+          <div class="row justify-content-center">
+            <form class="form py-3"
+              ref={self.form_ref.clone()}>
+              <h2>{"Invite a user"}</h2>
+              <Field<CreateInvitationModel>
+                form={&self.form}
+                label="User ID"
+                field_name="user_id"
+                autocomplete="off"
+                oninput={link.callback(|_| Msg::Update)} />
+              <div class="form-text mb-3">
+                {"Leave empty to let the invitee choose their user ID."}
+              </div>
+              <Field<CreateInvitationModel>
+                form={&self.form}
+                label="Email"
+                field_name="email"
+                input_type="email"
+                autocomplete="off"
+                oninput={link.callback(|_| Msg::Update)} />
+              <Field<CreateInvitationModel>
+                form={&self.form}
+                required=true
+                label="Valid for (days)"
+                field_name="validity_days"
+                input_type="number"
+                oninput={link.callback(|_| Msg::Update)} />
+              {
+                  self.attributes_schema
+                      .iter()
+                      .flatten()
+                      .map(get_custom_attribute_input)
+                      .collect::<Vec<_>>()
+              }
+              {self.view_groups(ctx)}
+              <Submit
+                text="Create invitation"
+                disabled={self.common.is_task_running()}
+                onclick={link.callback(|e: MouseEvent| {e.prevent_default(); Msg::SubmitForm})} />
+            </form>
+            {
+              if let Some(e) = &self.common.error {
+                html! {
+                  <div class="alert alert-danger">
+                    {e.to_string() }
+                  </div>
+                }
+              } else { html! {} }
+            }
+          </div>
+        }
+    }
+}
@@ -11,7 +11,7 @@ use crate::{
         api::HostService,
         common_component::{CommonComponent, CommonComponentParts},
         form_utils::{
-            AttributeValue, EmailIsRequired, GraphQlAttributeSchema, IsAdmin,
+            AttributeValidation, AttributeValue, EmailIsRequired, GraphQlAttributeSchema, IsAdmin,
             read_all_form_attributes,
         },
         schema::AttributeType,
@@ -47,6 +47,15 @@ impl From<&Attribute> for GraphQlAttributeSchema {
             is_list: attr.is_list,
             is_readonly: attr.is_readonly,
             is_editable: attr.is_editable,
+            validation: AttributeValidation::new(
+                attr.validation.regex.clone(),
+                attr.validation.min_length,
+                attr.validation.max_length,
+                attr.validation.min_value.as_deref(),
+                attr.validation.max_value.as_deref(),
+                attr.validation.allowed_values.clone(),
+                attr.validation.is_required,
+            ),
         }
     }
 }
@@ -303,7 +312,7 @@ impl Component for CreateUserForm {
     }
 }
 
-fn get_custom_attribute_input(attribute_schema: &Attribute) -> Html {
+pub fn get_custom_attribute_input(attribute_schema: &Attribute) -> Html {
     let mail_is_required = attribute_schema.name.as_str() == "mail";
 
     if attribute_schema.is_list {
@@ -311,6 +320,7 @@ fn get_custom_attribute_input(attribute_schema: &Attribute) -> Html {
             <ListAttributeInput
                 name={attribute_schema.name.clone()}
                 attribute_type={attribute_schema.attribute_type}
+                validation={GraphQlAttributeSchema::from(attribute_schema).validation}
                 required={mail_is_required}
             />
         }
@@ -319,6 +329,7 @@ fn get_custom_attribute_input(attribute_schema: &Attribute) -> Html {
             <SingleAttributeInput
                 name={attribute_schema.name.clone()}
                 attribute_type={attribute_schema.attribute_type}
+                validation={GraphQlAttributeSchema::from(attribute_schema).validation}
                 required={mail_is_required}
             />
         }
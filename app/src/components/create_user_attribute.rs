@@ -8,10 +8,10 @@ use crate::{
         schema::{AttributeType, validate_attribute_type},
     },
 };
-use anyhow::{Result, bail};
+use anyhow::{Result, anyhow, bail};
 use gloo_console::log;
 use graphql_client::GraphQLQuery;
-use lldap_validation::attributes::validate_attribute_name;
+use lldap_validation::attributes::{validate_attribute_name, validate_attribute_regex};
 use validator_derive::Validate;
 use yew::prelude::*;
 use yew_form_derive::Model;
@@ -41,6 +41,10 @@ pub struct CreateUserAttributeModel {
     is_editable: bool,
     is_list: bool,
     is_visible: bool,
+    regex: String,
+    allowed_values: String,
+    is_unique: bool,
+    is_required: bool,
 }
 
 pub enum Msg {
@@ -72,14 +76,36 @@ impl CommonComponent<CreateUserAttributeForm> for CreateUserAttributeForm {
                         invalid
                     );
                 })?;
+                if !model.regex.is_empty() {
+                    validate_attribute_regex(&model.regex).map_err(|e| anyhow!("Pattern: {e}"))?;
+                }
                 let attribute_type =
                     AttributeType::try_from(model.attribute_type.as_str()).unwrap();
+                let validation = create_user_attribute::AttributeValidationInput {
+                    regex: Some(model.regex).filter(|r| !r.is_empty()),
+                    min_length: None,
+                    max_length: None,
+                    min_value: None,
+                    max_value: None,
+                    allowed_values: Some(
+                        model
+                            .allowed_values
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|v| !v.is_empty())
+                            .map(str::to_owned)
+                            .collect(),
+                    ),
+                    is_unique: Some(model.is_unique),
+                    is_required: Some(model.is_required),
+                };
                 let req = create_user_attribute::Variables {
                     name: model.attribute_name,
                     attribute_type,
                     is_editable: model.is_editable,
                     is_list: model.is_list,
                     is_visible: model.is_visible,
+                    validation: Some(validation),
                 };
                 self.common.call_graphql::<CreateUserAttribute, _>(
                     ctx,
@@ -164,6 +190,26 @@ impl Component for CreateUserAttributeForm {
                 form={&self.form}
                 field_name="is_editable"
                 ontoggle={link.callback(|_| Msg::Update)} />
+              <Field<CreateUserAttributeModel>
+                label="Pattern"
+                form={&self.form}
+                field_name="regex"
+                oninput={link.callback(|_| Msg::Update)} />
+              <Field<CreateUserAttributeModel>
+                label="Allowed values (comma-separated)"
+                form={&self.form}
+                field_name="allowed_values"
+                oninput={link.callback(|_| Msg::Update)} />
+              <CheckBox<CreateUserAttributeModel>
+                label="Unique"
+                form={&self.form}
+                field_name="is_unique"
+                ontoggle={link.callback(|_| Msg::Update)} />
+              <CheckBox<CreateUserAttributeModel>
+                label="Required"
+                form={&self.form}
+                field_name="is_required"
+                ontoggle={link.callback(|_| Msg::Update)} />
               <Submit
                 disabled={self.common.is_task_running()}
                 onclick={link.callback(|e: MouseEvent| {e.prevent_default(); Msg::SubmitForm})}/>
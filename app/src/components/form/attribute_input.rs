@@ -1,6 +1,6 @@
 use crate::{
     components::form::{date_input::DateTimeInput, file_input::JpegFileInput},
-    infra::{schema::AttributeType, tooltip::Tooltip},
+    infra::{form_utils::AttributeValidation, schema::AttributeType, tooltip::Tooltip},
 };
 use web_sys::Element;
 use yew::{
@@ -14,11 +14,25 @@ struct AttributeInputProps {
     attribute_type: AttributeType,
     #[prop_or(None)]
     value: Option<String>,
+    #[prop_or_default]
+    validation: AttributeValidation,
 }
 
 #[function_component(AttributeInput)]
 fn attribute_input(props: &AttributeInputProps) -> Html {
+    let validation = &props.validation;
     let input_type = match props.attribute_type {
+        AttributeType::String if !validation.allowed_values.is_empty() => {
+            let selected = props.value.clone().unwrap_or_default();
+            return html! {
+                <select name={props.name.clone()} class="form-select">
+                    <option value="" selected={selected.is_empty()} />
+                    {validation.allowed_values.iter().map(|v| html! {
+                        <option value={v.clone()} selected={*v == selected}>{v.clone()}</option>
+                    }).collect::<Html>()}
+                </select>
+            };
+        }
         AttributeType::String => "text",
         AttributeType::Integer => "number",
         AttributeType::DateTime => {
@@ -33,11 +47,17 @@ fn attribute_input(props: &AttributeInputProps) -> Html {
         }
     };
 
+    let to_attr = |v: Option<i64>| v.map(|v| v.to_string());
     html! {
         <input
             type={input_type}
             name={props.name.clone()}
             class="form-control"
+            minlength={to_attr(validation.min_length)}
+            maxlength={to_attr(validation.max_length)}
+            min={to_attr(validation.min_value)}
+            max={to_attr(validation.max_value)}
+            title={validation.regex.clone()}
             value={props.value.clone()} />
     }
 }
@@ -91,18 +111,23 @@ pub struct SingleAttributeInputProps {
     pub value: Option<String>,
     #[prop_or(false)]
     pub required: bool,
+    #[prop_or_default]
+    pub validation: AttributeValidation,
 }
 
 #[function_component(SingleAttributeInput)]
 pub fn single_attribute_input(props: &SingleAttributeInputProps) -> Html {
     html! {
         <div class="row mb-3">
-            <AttributeLabel name={props.name.clone()} required={props.required} />
+            <AttributeLabel
+                name={props.name.clone()}
+                required={props.required || props.validation.is_required} />
             <div class="col-8">
             <AttributeInput
                 attribute_type={props.attribute_type}
                 name={props.name.clone()}
-                value={props.value.clone()} />
+                value={props.value.clone()}
+                validation={props.validation.clone()} />
             </div>
         </div>
     }
@@ -116,6 +141,8 @@ pub struct ListAttributeInputProps {
     pub values: Vec<String>,
     #[prop_or(false)]
     pub required: bool,
+    #[prop_or_default]
+    pub validation: AttributeValidation,
 }
 
 pub enum ListAttributeInputMsg {
@@ -168,14 +195,17 @@ impl Component for ListAttributeInput {
         let link = &ctx.link();
         html! {
             <div class="row mb-3">
-                <AttributeLabel name={props.name.clone()} required={props.required} />
+                <AttributeLabel
+                    name={props.name.clone()}
+                    required={props.required || props.validation.is_required} />
                 <div class="col-8">
                 {self.indices.iter().map(|&i| html! {
                     <div class="input-group mb-2" key={i}>
                     <AttributeInput
                         attribute_type={props.attribute_type}
                         name={props.name.clone()}
-                        value={props.values.get(i).cloned().unwrap_or_default()} />
+                        value={props.values.get(i).cloned().unwrap_or_default()}
+                        validation={props.validation.clone()} />
                     <button
                         class="btn btn-danger"
                         type="button"
@@ -2,12 +2,13 @@ use crate::{
     components::{
         add_group_member::{self, AddGroupMemberComponent},
         group_details_form::GroupDetailsForm,
+        group_owners::GroupOwnersComponent,
         remove_user_from_group::RemoveUserFromGroupComponent,
         router::{AppRoute, Link},
     },
     infra::{
         common_component::{CommonComponent, CommonComponentParts},
-        form_utils::GraphQlAttributeSchema,
+        form_utils::{AttributeValidation, GraphQlAttributeSchema},
         schema::AttributeType,
     },
 };
@@ -38,6 +39,15 @@ impl From<&AttributeSchema> for GraphQlAttributeSchema {
             is_list: attr.is_list,
             is_readonly: attr.is_readonly,
             is_editable: attr.is_editable,
+            validation: AttributeValidation::new(
+                attr.validation.regex.clone(),
+                attr.validation.min_length,
+                attr.validation.max_length,
+                attr.validation.min_value.as_deref(),
+                attr.validation.max_value.as_deref(),
+                attr.validation.allowed_values.clone(),
+                attr.validation.is_required,
+            ),
         }
     }
 }
@@ -91,6 +101,10 @@ impl GroupDetails {
     }
 
     fn view_details(&self, ctx: &Context<Self>, g: &Group, schema: Vec<AttributeSchema>) -> Html {
+        // The owners of the group can only manage its members.
+        if !ctx.props().is_admin {
+            return html! {<h3>{g.display_name.to_string()}</h3>};
+        }
         html! {
           <>
             <h3>{g.display_name.to_string()}</h3>
@@ -118,11 +132,17 @@ impl GroupDetails {
                 </td>
                 <td>{display_name}</td>
                 <td>
-                  <RemoveUserFromGroupComponent
-                    username={user_id}
-                    group_id={g.id}
-                    on_user_removed_from_group={link.callback(Msg::OnUserRemovedFromGroup)}
-                    on_error={link.callback(Msg::OnError)}/>
+                  {if g.dynamic_filter.is_none() {
+                    html! {
+                      <RemoveUserFromGroupComponent
+                        username={user_id}
+                        group_id={g.id}
+                        on_user_removed_from_group={link.callback(Msg::OnUserRemovedFromGroup)}
+                        on_error={link.callback(Msg::OnError)}/>
+                    }
+                  } else {
+                    html! {}
+                  }}
                 </td>
               </tr>
             }
@@ -158,6 +178,9 @@ impl GroupDetails {
     }
 
     fn view_add_user_button(&self, ctx: &Context<Self>, g: &Group) -> Html {
+        if g.dynamic_filter.is_some() {
+            return html! {};
+        }
         let link = ctx.link();
         let users: Vec<_> = g
             .users
@@ -171,10 +194,23 @@ impl GroupDetails {
             <AddGroupMemberComponent
                 group_id={g.id}
                 users={users}
+                is_admin={ctx.props().is_admin}
                 on_error={link.callback(Msg::OnError)}
                 on_user_added_to_group={link.callback(Msg::OnUserAddedToGroup)}/>
         }
     }
+
+    fn view_owners(&self, ctx: &Context<Self>, g: &Group) -> Html {
+        if ctx.props().is_admin {
+            html! {
+                <GroupOwnersComponent
+                    group_id={g.id}
+                    on_error={ctx.link().callback(Msg::OnError)}/>
+            }
+        } else {
+            html! {}
+        }
+    }
 }
 
 impl CommonComponent<GroupDetails> for GroupDetails {
@@ -246,6 +282,7 @@ impl Component for GroupDetails {
                       {self.view_details(ctx, group, schema.clone())}
                       {self.view_user_list(ctx, group)}
                       {self.view_add_user_button(ctx, group)}
+                      {self.view_owners(ctx, group)}
                       {self.view_messages(error)}
                     </div>
                 }
@@ -9,7 +9,10 @@ use crate::{
     },
     infra::{
         common_component::{CommonComponent, CommonComponentParts},
-        form_utils::{AttributeValue, EmailIsRequired, IsAdmin, read_all_form_attributes},
+        form_utils::{
+            AttributeValue, EmailIsRequired, GraphQlAttributeSchema, IsAdmin,
+            read_all_form_attributes,
+        },
     },
 };
 use anyhow::{Ok, Result};
@@ -120,6 +123,17 @@ impl Component for GroupDetailsForm {
               <StaticValue label="Group ID" id="groupId">
                 <i>{&self.group.id}</i>
               </StaticValue>
+              {
+                  if let Some(filter) = &self.group.dynamic_filter {
+                      html! {
+                        <StaticValue label="Dynamic filter" id="dynamicFilter">
+                          <code>{filter}</code>
+                        </StaticValue>
+                      }
+                  } else {
+                      html! {}
+                  }
+              }
               {
                   ctx
                       .props()
@@ -174,6 +188,7 @@ fn get_custom_attribute_input(
             <ListAttributeInput
                name={attribute_schema.name.clone()}
                attribute_type={attribute_schema.attribute_type}
+               validation={GraphQlAttributeSchema::from(attribute_schema).validation}
                values={values}
             />
         }
@@ -182,6 +197,7 @@ fn get_custom_attribute_input(
             <SingleAttributeInput
                 name={attribute_schema.name.clone()}
                 attribute_type={attribute_schema.attribute_type}
+                validation={GraphQlAttributeSchema::from(attribute_schema).validation}
                 value={values.first().cloned().unwrap_or_default()}
             />
         }
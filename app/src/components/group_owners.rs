@@ -0,0 +1,339 @@
+use crate::{
+    components::{
+        router::{AppRoute, Link},
+        select::{Select, SelectOption, SelectOptionProps},
+    },
+    infra::common_component::{CommonComponent, CommonComponentParts},
+};
+use anyhow::{Error, Result};
+use graphql_client::GraphQLQuery;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "../schema.graphql",
+    query_path = "queries/get_group_owners.graphql",
+    response_derives = "Debug, PartialEq, Eq, Clone",
+    custom_scalars_module = "crate::infra::graphql"
+)]
+pub struct GetGroupOwners;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "../schema.graphql",
+    query_path = "queries/add_group_owner.graphql",
+    response_derives = "Debug",
+    variables_derives = "Clone",
+    custom_scalars_module = "crate::infra::graphql"
+)]
+pub struct AddGroupOwner;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "../schema.graphql",
+    query_path = "queries/remove_group_owner.graphql",
+    response_derives = "Debug",
+    variables_derives = "Clone",
+    custom_scalars_module = "crate::infra::graphql"
+)]
+pub struct RemoveGroupOwner;
+
+pub type OwnerUser = get_group_owners::GetGroupOwnersGroupOwnerUsers;
+pub type OwnerGroup = get_group_owners::GetGroupOwnersGroupOwnerGroups;
+pub type Group = get_group_owners::GetGroupOwnersGroups;
+
+/// An owner of the group: either a user ID, or the ID of a group.
+#[derive(Clone, PartialEq, Eq)]
+pub enum Owner {
+    User(String),
+    Group(i64),
+}
+
+/// Lists the owners of a group, who can manage its members, and lets an admin change them.
+pub struct GroupOwnersComponent {
+    common: CommonComponentParts<Self>,
+    /// The owners and all the groups, initially not loaded.
+    owners: Option<get_group_owners::ResponseData>,
+    /// The user ID typed in the input.
+    user_id: String,
+    /// The currently selected group.
+    selected_group: Option<i64>,
+}
+
+pub enum Msg {
+    OwnersResponse(Result<get_group_owners::ResponseData>),
+    UserIdChanged(String),
+    GroupSelectionChanged(Option<SelectOptionProps>),
+    SubmitAddOwner(Owner),
+    SubmitRemoveOwner(Owner),
+    /// An owner was added or removed.
+    OwnersChanged(Result<()>),
+}
+
+#[derive(yew::Properties, Clone, PartialEq)]
+pub struct Props {
+    pub group_id: i64,
+    pub on_error: Callback<Error>,
+}
+
+impl CommonComponent<GroupOwnersComponent> for GroupOwnersComponent {
+    fn handle_msg(
+        &mut self,
+        ctx: &Context<Self>,
+        msg: <Self as Component>::Message,
+    ) -> Result<bool> {
+        match msg {
+            Msg::OwnersResponse(response) => {
+                self.owners = Some(response?);
+            }
+            Msg::UserIdChanged(user_id) => {
+                let was_empty = self.user_id.is_empty();
+                self.user_id = user_id.trim().to_owned();
+                return Ok(self.user_id.is_empty() != was_empty);
+            }
+            Msg::GroupSelectionChanged(option_props) => {
+                let was_some = self.selected_group.is_some();
+                self.selected_group = option_props.and_then(|g| g.value.parse().ok());
+                return Ok(self.selected_group.is_some() != was_some);
+            }
+            Msg::SubmitAddOwner(owner) => {
+                let (user, owner_group) = Self::owner_variables(owner);
+                self.common.call_graphql::<AddGroupOwner, _>(
+                    ctx,
+                    add_group_owner::Variables {
+                        group: ctx.props().group_id,
+                        user,
+                        owner_group,
+                    },
+                    |response| Msg::OwnersChanged(response.map(|_| ())),
+                    "Error trying to add an owner to the group",
+                );
+            }
+            Msg::SubmitRemoveOwner(owner) => {
+                let (user, owner_group) = Self::owner_variables(owner);
+                self.common.call_graphql::<RemoveGroupOwner, _>(
+                    ctx,
+                    remove_group_owner::Variables {
+                        group: ctx.props().group_id,
+                        user,
+                        owner_group,
+                    },
+                    |response| Msg::OwnersChanged(response.map(|_| ())),
+                    "Error trying to remove an owner from the group",
+                );
+            }
+            Msg::OwnersChanged(response) => {
+                response?;
+                self.get_owners(ctx);
+            }
+        }
+        Ok(true)
+    }
+
+    fn mut_common(&mut self) -> &mut CommonComponentParts<Self> {
+        &mut self.common
+    }
+}
+
+impl GroupOwnersComponent {
+    fn get_owners(&mut self, ctx: &Context<Self>) {
+        self.common.call_graphql::<GetGroupOwners, _>(
+            ctx,
+            get_group_owners::Variables {
+                id: ctx.props().group_id,
+            },
+            Msg::OwnersResponse,
+            "Error trying to fetch the group owners",
+        );
+    }
+
+    fn owner_variables(owner: Owner) -> (Option<String>, Option<i64>) {
+        match owner {
+            Owner::User(user_id) => (Some(user_id), None),
+            Owner::Group(group_id) => (None, Some(group_id)),
+        }
+    }
+
+    fn view_remove_button(&self, ctx: &Context<Self>, owner: Owner) -> Html {
+        html! {
+          <button
+            class="btn btn-danger"
+            disabled={self.common.is_task_running()}
+            onclick={ctx.link().callback(move |_| Msg::SubmitRemoveOwner(owner.clone()))}>
+            <i class="bi-x-circle-fill" aria-label="Remove owner from group" />
+          </button>
+        }
+    }
+
+    fn view_owner_list(
+        &self,
+        ctx: &Context<Self>,
+        owners: &get_group_owners::ResponseData,
+    ) -> Html {
+        let make_user_row = |user: &OwnerUser| {
+            html! {
+              <tr key={"ownerUserRow_".to_string() + &user.id}>
+                <td>
+                  <Link to={AppRoute::UserDetails{user_id: user.id.clone()}}>
+                    {&user.id}
+                  </Link>
+                </td>
+                <td>{"User"}</td>
+                <td>{self.view_remove_button(ctx, Owner::User(user.id.clone()))}</td>
+              </tr>
+            }
+        };
+        let make_group_row = |group: &OwnerGroup| {
+            html! {
+              <tr key={"ownerGroupRow_".to_string() + &group.display_name}>
+                <td>
+                  <Link to={AppRoute::GroupDetails{group_id: group.id}}>
+                    {&group.display_name}
+                  </Link>
+                </td>
+                <td>{"Members of the group"}</td>
+                <td>{self.view_remove_button(ctx, Owner::Group(group.id))}</td>
+              </tr>
+            }
+        };
+        let group = &owners.group;
+        html! {
+          <div class="table-responsive">
+            <table class="table table-hover">
+              <thead>
+                <tr key="headerRow">
+                  <th>{"Owner"}</th>
+                  <th>{"Type"}</th>
+                  <th></th>
+                </tr>
+              </thead>
+              <tbody>
+                {if group.owner_users.is_empty() && group.owner_groups.is_empty() {
+                  html! {
+                    <tr key="EmptyRow">
+                      <td>{"Only the admins can manage the members of this group."}</td>
+                      <td/>
+                    </tr>
+                  }
+                } else {
+                  html! {
+                    <>
+                      {group.owner_users.iter().map(make_user_row).collect::<Vec<_>>()}
+                      {group.owner_groups.iter().map(make_group_row).collect::<Vec<_>>()}
+                    </>
+                  }
+                }}
+              </tbody>
+            </table>
+          </div>
+        }
+    }
+
+    fn view_add_owner(&self, ctx: &Context<Self>, owners: &get_group_owners::ResponseData) -> Html {
+        let link = ctx.link();
+        let user_id = self.user_id.clone();
+        let selected_group = self.selected_group;
+        #[allow(unused_braces)]
+        let make_select_option = |group: &Group| {
+            html_nested! {
+                <SelectOption value={group.id.to_string()} text={group.display_name.clone()} key={group.id} />
+            }
+        };
+        html! {
+          <>
+            <div class="row mb-3">
+              <div class="col-sm-3">
+                <input
+                  class="form-control"
+                  type="text"
+                  placeholder="User ID"
+                  aria-label="Owner user ID"
+                  autocomplete="off"
+                  oninput={link.callback(|e: InputEvent| {
+                      let input: HtmlInputElement = e.target_unchecked_into();
+                      Msg::UserIdChanged(input.value())
+                  })} />
+              </div>
+              <div class="col-3">
+                <button
+                  class="btn btn-secondary"
+                  disabled={self.user_id.is_empty() || self.common.is_task_running()}
+                  onclick={link.callback(move |_| Msg::SubmitAddOwner(Owner::User(user_id.clone())))}>
+                  <i class="bi-person-plus me-2"></i>
+                  {"Add owner"}
+                </button>
+              </div>
+            </div>
+            <div class="row mb-3">
+              <div class="col-sm-3">
+                <Select on_selection_change={link.callback(Msg::GroupSelectionChanged)}>
+                  {
+                    owners
+                        .groups
+                        .iter()
+                        .filter(|g| g.id != ctx.props().group_id)
+                        .map(make_select_option)
+                        .collect::<Vec<_>>()
+                  }
+                </Select>
+              </div>
+              <div class="col-3">
+                <button
+                  class="btn btn-secondary"
+                  disabled={self.selected_group.is_none() || self.common.is_task_running()}
+                  onclick={link.batch_callback(move |_| selected_group.map(|g| Msg::SubmitAddOwner(Owner::Group(g))))}>
+                  <i class="bi-people me-2"></i>
+                  {"Add owner group"}
+                </button>
+              </div>
+            </div>
+          </>
+        }
+    }
+}
+
+impl Component for GroupOwnersComponent {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let mut res = Self {
+            common: CommonComponentParts::<Self>::create(),
+            owners: None,
+            user_id: String::new(),
+            selected_group: None,
+        };
+        res.get_owners(ctx);
+        res
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        CommonComponentParts::<Self>::update_and_report_error(
+            self,
+            ctx,
+            msg,
+            ctx.props().on_error.clone(),
+        )
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+          <>
+            <h5 class="fw-bold">{"Owners"}</h5>
+            <div class="form-text mb-2">
+              {"The owners can add and remove the members of this group."}
+            </div>
+            {match &self.owners {
+              Some(owners) => html! {
+                <>
+                  {self.view_owner_list(ctx, owners)}
+                  {self.view_add_owner(ctx, owners)}
+                </>
+              },
+              None => html! {{"Loading owners"}},
+            }}
+          </>
+        }
+    }
+}
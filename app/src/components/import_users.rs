@@ -0,0 +1,352 @@
+use crate::{
+    components::router::{AppRoute, Link},
+    infra::{
+        api::HostService,
+        common_component::{CommonComponent, CommonComponentParts},
+    },
+};
+use anyhow::{Result, anyhow};
+use gloo_file::{
+    File,
+    callbacks::{FileReader, read_as_text},
+};
+use lldap_auth::import::{ClientImportRequest, ConflictPolicy, ImportFormat, ServerImportResponse};
+use std::collections::BTreeMap;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+/// Lets an admin import users and groups from a CSV or LDIF file, and export them.
+pub struct ImportUsersForm {
+    common: CommonComponentParts<Self>,
+    file_name: Option<String>,
+    content: Option<String>,
+    reader: Option<FileReader>,
+    format: ImportFormat,
+    conflict_policy: ConflictPolicy,
+    attribute_mapping: String,
+    report: Option<(ServerImportResponse, bool)>,
+}
+
+pub enum Msg {
+    FileSelected(File),
+    FileLoaded(String, Result<String>),
+    FormatChanged(String),
+    ConflictPolicyChanged(String),
+    AttributeMappingChanged(String),
+    /// Whether to only preview the changes.
+    Submit(bool),
+    ImportResponse(bool, Result<ServerImportResponse>),
+}
+
+/// Parses the mapping written as "file_attribute=schema_attribute, other=".
+fn parse_attribute_mapping(mapping: &str) -> Result<BTreeMap<String, String>> {
+    mapping
+        .split(',')
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .map(|m| {
+            m.split_once('=')
+                .map(|(from, to)| (from.trim().to_owned(), to.trim().to_owned()))
+                .ok_or_else(|| anyhow!("Invalid attribute mapping \"{m}\", expected a=b"))
+        })
+        .collect()
+}
+
+impl CommonComponent<ImportUsersForm> for ImportUsersForm {
+    fn handle_msg(
+        &mut self,
+        ctx: &Context<Self>,
+        msg: <Self as Component>::Message,
+    ) -> Result<bool> {
+        match msg {
+            Msg::FileSelected(file) => {
+                let file_name = file.name();
+                if file_name.to_ascii_lowercase().ends_with(".ldif") {
+                    self.format = ImportFormat::Ldif;
+                } else if file_name.to_ascii_lowercase().ends_with(".csv") {
+                    self.format = ImportFormat::Csv;
+                }
+                let link = ctx.link().clone();
+                let name = file_name.clone();
+                self.reader = Some(read_as_text(&file, move |res| {
+                    link.send_message(Msg::FileLoaded(
+                        name,
+                        res.map_err(|e| anyhow!("Could not read the file: {:#}", e)),
+                    ))
+                }));
+                self.file_name = Some(file_name);
+                self.content = None;
+                self.report = None;
+                Ok(true)
+            }
+            Msg::FileLoaded(file_name, content) => {
+                self.reader = None;
+                if self.file_name.as_deref() == Some(file_name.as_str()) {
+                    self.content = Some(content?);
+                }
+                Ok(true)
+            }
+            Msg::FormatChanged(format) => {
+                self.format = match format.as_str() {
+                    "ldif" => ImportFormat::Ldif,
+                    _ => ImportFormat::Csv,
+                };
+                Ok(true)
+            }
+            Msg::ConflictPolicyChanged(policy) => {
+                self.conflict_policy = match policy.as_str() {
+                    "update" => ConflictPolicy::Update,
+                    "fail" => ConflictPolicy::Fail,
+                    _ => ConflictPolicy::Skip,
+                };
+                Ok(true)
+            }
+            Msg::AttributeMappingChanged(mapping) => {
+                self.attribute_mapping = mapping;
+                Ok(false)
+            }
+            Msg::Submit(dry_run) => {
+                let content = self
+                    .content
+                    .clone()
+                    .ok_or_else(|| anyhow!("Choose a file to import"))?;
+                let request = ClientImportRequest {
+                    format: self.format,
+                    content,
+                    conflict_policy: self.conflict_policy,
+                    dry_run,
+                    attribute_mapping: parse_attribute_mapping(&self.attribute_mapping)?,
+                };
+                self.report = None;
+                self.common.call_backend(
+                    ctx,
+                    HostService::import_directory(request),
+                    move |response| Msg::ImportResponse(dry_run, response),
+                );
+                Ok(true)
+            }
+            Msg::ImportResponse(dry_run, response) => {
+                self.report = Some((response?, dry_run));
+                Ok(true)
+            }
+        }
+    }
+
+    fn mut_common(&mut self) -> &mut CommonComponentParts<Self> {
+        &mut self.common
+    }
+}
+
+impl ImportUsersForm {
+    fn view_export() -> Html {
+        let base_url = yew_router::utils::base_url().unwrap_or_default();
+        let export_link = |file: &str, text: &str| {
+            html! {
+              <a
+                class="btn btn-secondary me-2"
+                href={format!("{base_url}/api/export/{file}")}
+                download={file.to_owned()}>
+                <i class="bi-download me-2"></i>
+                {text.to_owned()}
+              </a>
+            }
+        };
+        html! {
+          <div class="py-3">
+            <h2>{"Export"}</h2>
+            {export_link("users.csv", "Users (CSV)")}
+            {export_link("groups.csv", "Groups (CSV)")}
+            {export_link("directory.ldif", "Users and groups (LDIF)")}
+          </div>
+        }
+    }
+
+    fn view_report((report, dry_run): &(ServerImportResponse, bool)) -> Html {
+        let summary = if !report.errors.is_empty() {
+            html! {
+              <div class="alert alert-danger">
+                {"Nothing was imported, fix the errors below and try again."}
+              </div>
+            }
+        } else if report.applied {
+            html! {
+              <div class="alert alert-success">
+                {format!("Import done: {} changes were applied.", report.changes.len())}
+              </div>
+            }
+        } else if *dry_run {
+            html! {
+              <div class="alert alert-info">
+                {format!("Preview: {} changes would be applied.", report.changes.len())}
+              </div>
+            }
+        } else {
+            html! {}
+        };
+        html! {
+          <div id="importReport">
+            {summary}
+            <ul class="list-unstyled text-danger">
+              {report.errors.iter().map(|e| html! { <li>{e}</li> }).collect::<Html>()}
+            </ul>
+            <ul>
+              {report.changes.iter().map(|c| html! { <li>{c.to_string()}</li> }).collect::<Html>()}
+            </ul>
+          </div>
+        }
+    }
+}
+
+impl Component for ImportUsersForm {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(_: &Context<Self>) -> Self {
+        Self {
+            common: CommonComponentParts::<Self>::create(),
+            file_name: None,
+            content: None,
+            reader: None,
+            format: ImportFormat::Csv,
+            conflict_policy: ConflictPolicy::Skip,
+            attribute_mapping: String::new(),
+            report: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        CommonComponentParts::<Self>::update(self, ctx, msg)
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = &ctx.link();
+        let disabled = self.common.is_task_running() || self.content.is_none();
+        html! {
+          <div class="row justify-content-center">
+            {Self::view_export()}
+            <form class="form py-3">
+              <h2>{"Import"}</h2>
+              <div class="row mb-3">
+                <label for="importFile" class="form-label col-4 col-form-label">
+                  {"File:"}
+                </label>
+                <div class="col-8">
+                  <input
+                    class="form-control"
+                    id="importFile"
+                    type="file"
+                    accept=".csv,.ldif,text/csv"
+                    oninput={link.batch_callback(|e: InputEvent| {
+                        let input: HtmlInputElement = e.target_unchecked_into();
+                        input
+                            .files()
+                            .and_then(|files| files.item(0))
+                            .map(|file| Msg::FileSelected(File::from(file)))
+                    })} />
+                  <div class="form-text">
+                    {"A CSV file contains either users (with an \"id\" column) or groups (with a \"name\" column). \
+                      Use \";\" to separate the values of a list."}
+                  </div>
+                </div>
+              </div>
+              <div class="row mb-3">
+                <label for="importFormat" class="form-label col-4 col-form-label">
+                  {"Format:"}
+                </label>
+                <div class="col-8">
+                  <select
+                    class="form-select"
+                    id="importFormat"
+                    onchange={link.callback(|e: Event| {
+                        let select: HtmlSelectElement = e.target_unchecked_into();
+                        Msg::FormatChanged(select.value())
+                    })}>
+                    <option value="csv" selected={self.format == ImportFormat::Csv}>{"CSV"}</option>
+                    <option value="ldif" selected={self.format == ImportFormat::Ldif}>{"LDIF"}</option>
+                  </select>
+                </div>
+              </div>
+              <div class="row mb-3">
+                <label for="conflictPolicy" class="form-label col-4 col-form-label">
+                  {"Existing users and groups:"}
+                </label>
+                <div class="col-8">
+                  <select
+                    class="form-select"
+                    id="conflictPolicy"
+                    onchange={link.callback(|e: Event| {
+                        let select: HtmlSelectElement = e.target_unchecked_into();
+                        Msg::ConflictPolicyChanged(select.value())
+                    })}>
+                    <option value="skip" selected={self.conflict_policy == ConflictPolicy::Skip}>
+                      {"Leave them as they are"}
+                    </option>
+                    <option value="update" selected={self.conflict_policy == ConflictPolicy::Update}>
+                      {"Update them with the values of the file"}
+                    </option>
+                    <option value="fail" selected={self.conflict_policy == ConflictPolicy::Fail}>
+                      {"Import nothing"}
+                    </option>
+                  </select>
+                </div>
+              </div>
+              <div class="row mb-3">
+                <label for="attributeMapping" class="form-label col-4 col-form-label">
+                  {"Attribute mapping:"}
+                </label>
+                <div class="col-8">
+                  <input
+                    class="form-control"
+                    id="attributeMapping"
+                    type="text"
+                    placeholder="telephoneNumber=phone, description="
+                    autocomplete="off"
+                    oninput={link.callback(|e: InputEvent| {
+                        let input: HtmlInputElement = e.target_unchecked_into();
+                        Msg::AttributeMappingChanged(input.value())
+                    })} />
+                  <div class="form-text">
+                    {"Renames the attributes of the file to attributes of the schema. \
+                      Map an attribute to nothing to ignore it."}
+                  </div>
+                </div>
+              </div>
+              <button
+                class="btn btn-secondary"
+                type="button"
+                disabled={disabled}
+                onclick={link.callback(|_| Msg::Submit(true))}>
+                <i class="bi-eye me-2"></i>
+                {"Preview"}
+              </button>
+              <button
+                class="btn btn-primary ms-2"
+                type="button"
+                disabled={disabled}
+                onclick={link.callback(|_| Msg::Submit(false))}>
+                <i class="bi-upload me-2"></i>
+                {"Import"}
+              </button>
+              <Link classes="btn btn-secondary ms-2" to={AppRoute::ListUsers}>
+                {"Back to the users"}
+              </Link>
+            </form>
+            {
+              if let Some(e) = &self.common.error {
+                html! {
+                  <div class="alert alert-danger">
+                    {e.to_string() }
+                  </div>
+                }
+              } else { html! {} }
+            }
+            {
+              match &self.report {
+                Some(report) => Self::view_report(report),
+                None => html! {},
+              }
+            }
+          </div>
+        }
+    }
+}
@@ -40,6 +40,7 @@ pub struct Props {
 pub enum Msg {
     Update,
     Submit,
+    PasskeyLogin,
     AuthenticationRefreshResponse(Result<(String, bool)>),
     AuthenticationStartResponse(
         (
@@ -78,6 +79,14 @@ impl CommonComponent<LoginForm> for LoginForm {
                     });
                 Ok(true)
             }
+            Msg::PasskeyLogin => {
+                self.common.call_backend(
+                    ctx,
+                    HostService::passkey_login(),
+                    Msg::AuthenticationFinishResponse,
+                );
+                Ok(true)
+            }
             Msg::AuthenticationStartResponse((login_start, res)) => {
                 let res = res.context("Could not log in (invalid response to login start)")?;
                 let login_finish =
@@ -208,6 +217,16 @@ impl Component for LoginForm {
                     html!{}
                   }}
                 </Submit>
+                <div class="form-group mt-3">
+                  <button
+                    type="button"
+                    class="btn btn-secondary"
+                    disabled={self.common.is_task_running()}
+                    onclick={link.callback(|e: MouseEvent| {e.prevent_default(); Msg::PasskeyLogin})}>
+                    <i class="bi-fingerprint me-2"></i>
+                    {"Sign in with a passkey"}
+                  </button>
+                </div>
                 <div class="form-group">
                 { if let Some(e) = &self.common.error {
                     html! { e.to_string() }
@@ -1,3 +1,4 @@
+pub mod accept_invitation;
 pub mod add_group_member;
 pub mod add_user_to_group;
 pub mod app;
@@ -6,6 +7,7 @@ pub mod banner;
 pub mod change_password;
 pub mod create_group;
 pub mod create_group_attribute;
+pub mod create_invitation;
 pub mod create_user;
 pub mod create_user_attribute;
 pub mod delete_group;
@@ -16,10 +18,13 @@ pub mod form;
 pub mod fragments;
 pub mod group_details;
 pub mod group_details_form;
+pub mod group_owners;
 pub mod group_schema_table;
 pub mod group_table;
+pub mod import_users;
 pub mod login;
 pub mod logout;
+pub mod passkeys;
 pub mod remove_user_from_group;
 pub mod reset_password_step1;
 pub mod reset_password_step2;
@@ -29,3 +34,4 @@ pub mod user_details;
 pub mod user_details_form;
 pub mod user_schema_table;
 pub mod user_table;
+pub mod verify_email;
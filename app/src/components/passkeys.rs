@@ -0,0 +1,215 @@
+use crate::infra::{
+    api::HostService,
+    common_component::{CommonComponent, CommonComponentParts},
+    cookies::get_cookie,
+};
+use anyhow::{Error, Result, bail};
+use lldap_auth::webauthn::PasskeyInfo;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+/// Lists the passkeys of a user, lets the user register new ones, and the user or an admin
+/// remove them.
+pub struct PasskeysTable {
+    common: CommonComponentParts<Self>,
+    passkeys: Option<Vec<PasskeyInfo>>,
+    name_input: NodeRef,
+}
+
+#[derive(yew::Properties, Clone, PartialEq, Eq)]
+pub struct Props {
+    pub username: String,
+}
+
+pub enum Msg {
+    ListResponse(Result<Vec<PasskeyInfo>>),
+    SubmitRegister,
+    RegisterResponse(Result<PasskeyInfo>),
+    SubmitDelete(String),
+    DeleteResponse((String, Result<()>)),
+}
+
+impl CommonComponent<PasskeysTable> for PasskeysTable {
+    fn handle_msg(
+        &mut self,
+        ctx: &Context<Self>,
+        msg: <Self as Component>::Message,
+    ) -> Result<bool> {
+        match msg {
+            Msg::ListResponse(passkeys) => {
+                self.passkeys = Some(passkeys?);
+            }
+            Msg::SubmitRegister => {
+                let name = self
+                    .name_input
+                    .cast::<HtmlInputElement>()
+                    .map(|i| i.value())
+                    .unwrap_or_default();
+                if name.trim().is_empty() {
+                    bail!("Give the passkey a name, e.g. \"Laptop\" or \"Security key\"");
+                }
+                self.common.call_backend(
+                    ctx,
+                    HostService::register_passkey(name),
+                    Msg::RegisterResponse,
+                );
+            }
+            Msg::RegisterResponse(passkey) => {
+                let passkey = passkey?;
+                if let Some(input) = self.name_input.cast::<HtmlInputElement>() {
+                    input.set_value("");
+                }
+                self.passkeys.get_or_insert_with(Vec::new).push(passkey);
+            }
+            Msg::SubmitDelete(credential_id) => {
+                self.common.call_backend(
+                    ctx,
+                    HostService::delete_passkey(
+                        ctx.props().username.clone(),
+                        credential_id.clone(),
+                    ),
+                    move |r| Msg::DeleteResponse((credential_id, r)),
+                );
+            }
+            Msg::DeleteResponse((credential_id, response)) => {
+                response?;
+                if let Some(passkeys) = self.passkeys.as_mut() {
+                    passkeys.retain(|p| p.credential_id != credential_id);
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    fn mut_common(&mut self) -> &mut CommonComponentParts<Self> {
+        &mut self.common
+    }
+}
+
+impl PasskeysTable {
+    fn is_own_account(ctx: &Context<Self>) -> bool {
+        get_cookie("user_id")
+            .ok()
+            .flatten()
+            .is_some_and(|u| u.eq_ignore_ascii_case(&ctx.props().username))
+    }
+
+    fn view_passkey_row(&self, ctx: &Context<Self>, passkey: &PasskeyInfo) -> Html {
+        let link = &ctx.link();
+        let credential_id = passkey.credential_id.clone();
+        html! {
+          <tr key={passkey.credential_id.clone()}>
+            <td>{&passkey.name}</td>
+            <td>{passkey.creation_date.date_naive()}</td>
+            <td>
+              {passkey
+                .last_used_date
+                .map(|d| d.date_naive().to_string())
+                .unwrap_or_else(|| "Never".to_string())}
+            </td>
+            <td>
+              <button
+                class="btn btn-danger"
+                disabled={self.common.is_task_running()}
+                onclick={link.callback(move |_| Msg::SubmitDelete(credential_id.clone()))}>
+                <i class="bi-x-circle-fill" aria-label="Remove passkey" />
+              </button>
+            </td>
+          </tr>
+        }
+    }
+
+    fn view_register_form(&self, ctx: &Context<Self>) -> Html {
+        let link = &ctx.link();
+        if !Self::is_own_account(ctx) {
+            return html! {};
+        }
+        html! {
+          <form class="row g-2 align-items-center">
+            <div class="col-auto">
+              <input
+                ref={self.name_input.clone()}
+                type="text"
+                class="form-control"
+                placeholder="Passkey name" />
+            </div>
+            <div class="col-auto">
+              <button
+                class="btn btn-primary"
+                disabled={self.common.is_task_running()}
+                onclick={link.callback(|e: MouseEvent| {e.prevent_default(); Msg::SubmitRegister})}>
+                <i class="bi-fingerprint me-2"></i>
+                {"Register a passkey"}
+              </button>
+            </div>
+          </form>
+        }
+    }
+}
+
+impl Component for PasskeysTable {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let mut table = Self {
+            common: CommonComponentParts::<Self>::create(),
+            passkeys: None,
+            name_input: NodeRef::default(),
+        };
+        table.common.call_backend(
+            ctx,
+            HostService::list_passkeys(ctx.props().username.clone()),
+            Msg::ListResponse,
+        );
+        table
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        CommonComponentParts::<Self>::update(self, ctx, msg)
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let error: &Option<Error> = &self.common.error;
+        html! {
+          <>
+            <h5 class="row m-3 fw-bold">{"Passkeys"}</h5>
+            <div class="table-responsive">
+              <table class="table table-hover">
+                <thead>
+                  <tr key="headerRow">
+                    <th>{"Name"}</th>
+                    <th>{"Registered"}</th>
+                    <th>{"Last used"}</th>
+                    <th></th>
+                  </tr>
+                </thead>
+                <tbody>
+                  {match &self.passkeys {
+                    None => html! {
+                      <tr key="LoadingRow"><td>{"Loading..."}</td></tr>
+                    },
+                    Some(passkeys) if passkeys.is_empty() => html! {
+                      <tr key="EmptyRow">
+                        <td>{"This user has no registered passkeys."}</td>
+                      </tr>
+                    },
+                    Some(passkeys) => html! {
+                      <>{passkeys.iter().map(|p| self.view_passkey_row(ctx, p)).collect::<Vec<_>>()}</>
+                    },
+                  }}
+                </tbody>
+              </table>
+            </div>
+            {self.view_register_form(ctx)}
+            {if let Some(e) = error {
+              html! {
+                <div class="alert alert-danger">
+                  <span>{"Error: "}{e.to_string()}</span>
+                </div>
+              }
+            } else { html! {} }}
+          </>
+        }
+    }
+}
@@ -8,8 +8,16 @@ pub enum AppRoute {
     StartResetPassword,
     #[at("/reset-password/step2/:token")]
     FinishResetPassword { token: String },
+    #[at("/invitation/:token")]
+    AcceptInvitation { token: String },
+    #[at("/verify-email/:token")]
+    VerifyEmail { token: String },
     #[at("/users/create")]
     CreateUser,
+    #[at("/users/invite")]
+    InviteUser,
+    #[at("/users/import")]
+    ImportUsers,
     #[at("/users")]
     ListUsers,
     #[at("/user/:user_id/password")]
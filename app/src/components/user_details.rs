@@ -1,13 +1,14 @@
 use crate::{
     components::{
         add_user_to_group::AddUserToGroupComponent,
+        passkeys::PasskeysTable,
         remove_user_from_group::RemoveUserFromGroupComponent,
         router::{AppRoute, Link},
         user_details_form::UserDetailsForm,
     },
     infra::{
         common_component::{CommonComponent, CommonComponentParts},
-        form_utils::GraphQlAttributeSchema,
+        form_utils::{AttributeValidation, GraphQlAttributeSchema},
         schema::AttributeType,
     },
 };
@@ -27,6 +28,7 @@ pub struct GetUserDetails;
 
 pub type User = get_user_details::GetUserDetailsUser;
 pub type Group = get_user_details::GetUserDetailsUserGroups;
+pub type OwnedGroup = get_user_details::GetUserDetailsUserOwnedGroups;
 pub type Attribute = get_user_details::GetUserDetailsUserAttributes;
 pub type AttributeSchema = get_user_details::GetUserDetailsSchemaUserSchemaAttributes;
 
@@ -37,6 +39,15 @@ impl From<&AttributeSchema> for GraphQlAttributeSchema {
             is_list: attr.is_list,
             is_readonly: attr.is_readonly,
             is_editable: attr.is_editable,
+            validation: AttributeValidation::new(
+                attr.validation.regex.clone(),
+                attr.validation.min_length,
+                attr.validation.max_length,
+                attr.validation.min_value.as_deref(),
+                attr.validation.max_value.as_deref(),
+                attr.validation.allowed_values.clone(),
+                attr.validation.is_required,
+            ),
         }
     }
 }
@@ -68,6 +79,7 @@ pub enum Msg {
 pub struct Props {
     pub username: String,
     pub is_admin: bool,
+    pub confirm_email_changes: bool,
 }
 
 impl CommonComponent<UserDetails> for UserDetails {
@@ -177,6 +189,40 @@ impl UserDetails {
         }
     }
 
+    fn view_owned_groups(&self, u: &User) -> Html {
+        if u.owned_groups.is_empty() {
+            return html! {};
+        }
+        let make_group_row = |group: &OwnedGroup| {
+            html! {
+              <tr key={"ownedGroupRow_".to_string() + &group.display_name}>
+                <td>
+                  <Link to={AppRoute::GroupDetails{group_id: group.id}}>
+                    {&group.display_name}
+                  </Link>
+                </td>
+              </tr>
+            }
+        };
+        html! {
+          <>
+            <h5 class="row m-3 fw-bold">{"Owned groups"}</h5>
+            <div class="table-responsive">
+              <table class="table table-hover">
+                <thead>
+                  <tr key="headerRow">
+                    <th>{"Group"}</th>
+                  </tr>
+                </thead>
+                <tbody>
+                  {u.owned_groups.iter().map(make_group_row).collect::<Vec<_>>()}
+                </tbody>
+              </table>
+            </div>
+          </>
+        }
+    }
+
     fn view_add_group_button(&self, ctx: &Context<Self>, u: &User) -> Html {
         let link = &ctx.link();
         if ctx.props().is_admin {
@@ -232,9 +278,12 @@ impl Component for UserDetails {
                       user_attributes_schema={schema.clone()}
                       is_admin={ctx.props().is_admin}
                       is_edited_user_admin={u.groups.iter().any(|g| g.display_name == "lldap_admin")}
+                      confirm_email_changes={ctx.props().confirm_email_changes}
                     />
                     {self.view_group_memberships(ctx, u)}
                     {self.view_add_group_button(ctx, u)}
+                    {self.view_owned_groups(u)}
+                    <PasskeysTable username={u.id.clone()} />
                     {self.view_messages(error)}
                   </>
                 }
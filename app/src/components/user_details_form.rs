@@ -8,8 +8,12 @@ use crate::{
         user_details::{Attribute, AttributeSchema, User},
     },
     infra::{
+        api::HostService,
         common_component::{CommonComponent, CommonComponentParts},
-        form_utils::{AttributeValue, EmailIsRequired, IsAdmin, read_all_form_attributes},
+        form_utils::{
+            AttributeValue, EmailIsRequired, GraphQlAttributeSchema, IsAdmin,
+            read_all_form_attributes,
+        },
         schema::AttributeType,
     },
 };
@@ -36,6 +40,16 @@ pub struct UserDetailsForm {
     just_updated: bool,
     user: User,
     form_ref: NodeRef,
+    /// Whether the email currently saved for the user was confirmed.
+    email_verified: bool,
+    /// True if the last update changed the email.
+    email_changed: bool,
+    /// True if we just sent a verification email, to display a message.
+    verification_sent: bool,
+    /// The new email, only saved once confirmed through the link sent to it.
+    new_email: Option<String>,
+    /// True if we just sent the link to confirm the new email, to display a message.
+    email_change_sent: bool,
 }
 
 pub enum Msg {
@@ -45,6 +59,12 @@ pub enum Msg {
     SubmitClicked,
     /// We got the response from the server about our update message.
     UserUpdated(Result<update_user::ResponseData>),
+    /// The "Send verification email" button was clicked.
+    SendVerificationClicked,
+    /// We got the response from the server about sending the verification email.
+    VerificationEmailSent(Result<()>),
+    /// We got the response from the server about sending the link to the new email.
+    EmailChangeRequested(Result<()>),
 }
 
 #[derive(yew::Properties, Clone, PartialEq, Eq)]
@@ -54,6 +74,8 @@ pub struct Props {
     pub user_attributes_schema: Vec<AttributeSchema>,
     pub is_admin: bool,
     pub is_edited_user_admin: bool,
+    /// Whether the users have to confirm a new email before it is saved.
+    pub confirm_email_changes: bool,
 }
 
 impl CommonComponent<UserDetailsForm> for UserDetailsForm {
@@ -68,6 +90,26 @@ impl CommonComponent<UserDetailsForm> for UserDetailsForm {
             Msg::UserUpdated(Err(e)) => Err(e),
             Msg::UserUpdated(Result::Ok(_)) => {
                 self.just_updated = true;
+                if let Some(email) = self.new_email.take() {
+                    self.request_email_change(ctx, email);
+                } else if self.email_changed {
+                    self.email_verified = false;
+                    self.send_verification_email(ctx);
+                }
+                Ok(true)
+            }
+            Msg::SendVerificationClicked => {
+                self.send_verification_email(ctx);
+                Ok(true)
+            }
+            Msg::VerificationEmailSent(response) => {
+                response?;
+                self.verification_sent = true;
+                Ok(true)
+            }
+            Msg::EmailChangeRequested(response) => {
+                response?;
+                self.email_change_sent = true;
                 Ok(true)
             }
         }
@@ -88,11 +130,18 @@ impl Component for UserDetailsForm {
             just_updated: false,
             user: ctx.props().user.clone(),
             form_ref: NodeRef::default(),
+            email_verified: ctx.props().user.email_verified,
+            email_changed: false,
+            verification_sent: false,
+            new_email: None,
+            email_change_sent: false,
         }
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         self.just_updated = false;
+        self.verification_sent = false;
+        self.email_change_sent = false;
         CommonComponentParts::<Self>::update(self, ctx, msg)
     }
 
@@ -151,6 +200,7 @@ impl Component for UserDetailsForm {
             <div hidden={!self.just_updated}>
               <div class="alert alert-success mt-4">{"User successfully updated!"}</div>
             </div>
+            {self.view_email_verification(ctx)}
           </div>
         }
     }
@@ -170,6 +220,7 @@ fn get_custom_attribute_input(
             <ListAttributeInput
                name={attribute_schema.name.clone()}
                attribute_type={attribute_schema.attribute_type}
+               validation={GraphQlAttributeSchema::from(attribute_schema).validation}
                values={values}
             />
         }
@@ -178,6 +229,7 @@ fn get_custom_attribute_input(
             <SingleAttributeInput
                 name={attribute_schema.name.clone()}
                 attribute_type={attribute_schema.attribute_type}
+                validation={GraphQlAttributeSchema::from(attribute_schema).validation}
                 value={values.first().cloned().unwrap_or_default()}
             />
         }
@@ -211,6 +263,53 @@ fn get_custom_attribute_static(
 }
 
 impl UserDetailsForm {
+    fn send_verification_email(&mut self, ctx: &Context<Self>) {
+        self.common.call_backend(
+            ctx,
+            HostService::send_email_verification(self.user.id.clone()),
+            Msg::VerificationEmailSent,
+        );
+    }
+
+    fn request_email_change(&mut self, ctx: &Context<Self>, email: String) {
+        self.common.call_backend(
+            ctx,
+            HostService::change_email(self.user.id.clone(), email),
+            Msg::EmailChangeRequested,
+        );
+    }
+
+    fn view_email_verification(&self, ctx: &Context<Self>) -> Html {
+        let link = &ctx.link();
+        if self.email_change_sent {
+            html! {
+              <div class="alert alert-info mt-4">
+                {"A confirmation link was sent to the new email address. It replaces the current one once confirmed."}
+              </div>
+            }
+        } else if self.verification_sent {
+            html! {
+              <div class="alert alert-info mt-4">
+                {"A confirmation link was sent to the email address."}
+              </div>
+            }
+        } else if !self.email_verified {
+            html! {
+              <div class="alert alert-warning mt-4 d-flex align-items-center">
+                <span class="me-auto">{"The email address is not verified."}</span>
+                <button
+                  class="btn btn-secondary btn-sm"
+                  disabled={self.common.is_task_running()}
+                  onclick={link.callback(|e: MouseEvent| {e.prevent_default(); Msg::SendVerificationClicked})}>
+                  {"Send verification email"}
+                </button>
+              </div>
+            }
+        } else {
+            html! {}
+        }
+    }
+
     fn submit_user_update_form(&mut self, ctx: &Context<Self>) -> Result<bool> {
         // TODO: Handle unloaded files.
         // if let Some(JsFile {
@@ -235,6 +334,16 @@ impl UserDetailsForm {
                 .map(|v| v.value != a.values)
                 .unwrap_or(!a.values.is_empty())
         });
+        // The new email is sent separately, to be confirmed before it is saved.
+        self.new_email = if ctx.props().confirm_email_changes && !ctx.props().is_admin {
+            all_values
+                .iter()
+                .position(|a| a.name == "mail")
+                .and_then(|i| all_values.remove(i).values.into_iter().next())
+        } else {
+            None
+        };
+        self.email_changed = all_values.iter().any(|a| a.name == "mail");
         let remove_attributes: Option<Vec<String>> = if all_values.is_empty() {
             None
         } else {
@@ -272,6 +381,10 @@ impl UserDetailsForm {
         user_input.insertAttributes = insert_attributes;
         // Nothing changed.
         if user_input == default_user_input {
+            if let Some(email) = self.new_email.take() {
+                self.request_email_change(ctx, email);
+                return Ok(true);
+            }
             return Ok(false);
         }
         let req = update_user::Variables { user: user_input };
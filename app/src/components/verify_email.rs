@@ -0,0 +1,92 @@
+use crate::{
+    components::router::{AppRoute, Link},
+    infra::{
+        api::HostService,
+        common_component::{CommonComponent, CommonComponentParts},
+    },
+};
+use anyhow::Result;
+use lldap_auth::email_verification::ServerEmailVerificationResponse;
+use yew::prelude::*;
+
+/// Confirms an email address with the token from the link sent to it.
+pub struct VerifyEmail {
+    common: CommonComponentParts<Self>,
+    verified: Option<ServerEmailVerificationResponse>,
+}
+
+#[derive(Clone, PartialEq, Eq, Properties)]
+pub struct Props {
+    pub token: String,
+}
+
+pub enum Msg {
+    VerifyEmailResponse(Result<ServerEmailVerificationResponse>),
+}
+
+impl CommonComponent<VerifyEmail> for VerifyEmail {
+    fn handle_msg(&mut self, _: &Context<Self>, msg: <Self as Component>::Message) -> Result<bool> {
+        match msg {
+            Msg::VerifyEmailResponse(response) => {
+                self.verified = Some(response?);
+                Ok(true)
+            }
+        }
+    }
+
+    fn mut_common(&mut self) -> &mut CommonComponentParts<Self> {
+        &mut self.common
+    }
+}
+
+impl Component for VerifyEmail {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let mut component = VerifyEmail {
+            common: CommonComponentParts::<Self>::create(),
+            verified: None,
+        };
+        component.common.call_backend(
+            ctx,
+            HostService::verify_email(ctx.props().token.clone()),
+            Msg::VerifyEmailResponse,
+        );
+        component
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        CommonComponentParts::<Self>::update(self, ctx, msg)
+    }
+
+    fn view(&self, _: &Context<Self>) -> Html {
+        let status = match (&self.verified, &self.common.error) {
+            (None, None) => html! {
+              {"Verifying your email address..."}
+            },
+            (None, Some(e)) => html! {
+              <div class="alert alert-danger">
+                {e.to_string() }
+              </div>
+            },
+            (Some(verified), _) => html! {
+              <div class="alert alert-success">
+                {format!(
+                  "The email address {} of {} is now verified.",
+                  verified.email, verified.user_id
+                )}
+              </div>
+            },
+        };
+        html! {
+          <>
+            <h2>{"Email verification"}</h2>
+            {status}
+            <Link classes="btn btn-secondary" to={AppRoute::Index}>
+              {"Back to LLDAP"}
+            </Link>
+          </>
+        }
+    }
+}
@@ -2,7 +2,9 @@ use super::cookies::set_cookie;
 use anyhow::{Context, Result, anyhow};
 use gloo_net::http::{Method, RequestBuilder};
 use graphql_client::GraphQLQuery;
-use lldap_auth::{JWTClaims, login, registration};
+use lldap_auth::{
+    JWTClaims, email_verification, import, invitation, login, registration, webauthn,
+};
 
 use lldap_frontend_options::Options;
 use serde::{Serialize, de::DeserializeOwned};
@@ -20,9 +22,11 @@ fn get_claims_from_jwt(jwt: &str) -> Result<JWTClaims> {
 enum RequestType<Body: Serialize> {
     Get,
     Post(Body),
+    Delete,
 }
 
 const GET_REQUEST: RequestType<()> = RequestType::Get;
+const DELETE_REQUEST: RequestType<()> = RequestType::Delete;
 
 fn base_url() -> String {
     yew_router::utils::base_url().unwrap_or_default()
@@ -36,12 +40,12 @@ async fn call_server<Body: Serialize>(
     let request_builder = RequestBuilder::new(url)
         .header("Content-Type", "application/json")
         .credentials(RequestCredentials::SameOrigin);
-    let request = if let RequestType::Post(b) = body {
-        request_builder
+    let request = match body {
+        RequestType::Post(b) => request_builder
             .method(Method::POST)
-            .body(serde_json::to_string(&b)?)?
-    } else {
-        request_builder.build()?
+            .body(serde_json::to_string(&b)?)?,
+        RequestType::Delete => request_builder.method(Method::DELETE).build()?,
+        RequestType::Get => request_builder.build()?,
     };
     let response = request.send().await?;
     if response.ok() {
@@ -212,4 +216,182 @@ impl HostService {
         )
         .await
     }
+
+    /// Log in with a passkey: the browser picks the credential, so no username is needed.
+    pub async fn passkey_login() -> Result<(String, bool)> {
+        let start = call_server_json_with_error_message::<webauthn::ServerLoginStartResponse, _>(
+            &(base_url() + "/auth/webauthn/login/start"),
+            RequestType::Post(()),
+            "Could not start passkey authentication: ",
+        )
+        .await?;
+        let credential = super::webauthn::get_credential(start.challenge).await?;
+        call_server_json_with_error_message::<login::ServerLoginResponse, _>(
+            &(base_url() + "/auth/webauthn/login/finish"),
+            RequestType::Post(webauthn::ClientLoginFinishRequest {
+                state_id: start.state_id,
+                credential,
+            }),
+            "Could not finish passkey authentication",
+        )
+        .await
+        .and_then(set_cookies_from_jwt)
+    }
+
+    /// Register a new passkey for the logged-in user.
+    pub async fn register_passkey(name: String) -> Result<webauthn::PasskeyInfo> {
+        let start =
+            call_server_json_with_error_message::<webauthn::ServerRegistrationStartResponse, _>(
+                &(base_url() + "/auth/webauthn/register/start"),
+                RequestType::Post(webauthn::ClientRegistrationStartRequest { name: name.clone() }),
+                "Could not start passkey registration: ",
+            )
+            .await?;
+        let credential = super::webauthn::create_credential(start.challenge).await?;
+        call_server_json_with_error_message(
+            &(base_url() + "/auth/webauthn/register/finish"),
+            RequestType::Post(webauthn::ClientRegistrationFinishRequest {
+                state_id: start.state_id,
+                name,
+                credential,
+            }),
+            "Could not finish passkey registration",
+        )
+        .await
+    }
+
+    pub async fn list_passkeys(user_id: String) -> Result<Vec<webauthn::PasskeyInfo>> {
+        call_server_json_with_error_message(
+            &format!(
+                "{}/auth/webauthn/credentials/{}",
+                base_url(),
+                url_escape::encode_component(&user_id)
+            ),
+            GET_REQUEST,
+            "Could not list the passkeys: ",
+        )
+        .await
+    }
+
+    pub async fn delete_passkey(user_id: String, credential_id: String) -> Result<()> {
+        call_server_empty_response_with_error_message(
+            &format!(
+                "{}/auth/webauthn/credentials/{}/{}",
+                base_url(),
+                url_escape::encode_component(&user_id),
+                url_escape::encode_component(&credential_id)
+            ),
+            DELETE_REQUEST,
+            "Could not remove the passkey",
+        )
+        .await
+    }
+
+    pub async fn import_directory(
+        request: import::ClientImportRequest,
+    ) -> Result<import::ServerImportResponse> {
+        call_server_json_with_error_message(
+            &(base_url() + "/api/import"),
+            RequestType::Post(request),
+            "Could not import the file: ",
+        )
+        .await
+    }
+
+    pub async fn create_invitation(
+        request: invitation::ClientCreateInvitationRequest,
+    ) -> Result<invitation::ServerCreateInvitationResponse> {
+        call_server_json_with_error_message(
+            &(base_url() + "/auth/invitation/create"),
+            RequestType::Post(request),
+            "Could not create the invitation: ",
+        )
+        .await
+    }
+
+    pub async fn get_invitation(token: String) -> Result<invitation::ServerInvitationResponse> {
+        call_server_json_with_error_message(
+            &format!(
+                "{}/auth/invitation/{}",
+                base_url(),
+                url_escape::encode_component(&token)
+            ),
+            GET_REQUEST,
+            "Invalid or expired invitation",
+        )
+        .await
+    }
+
+    pub async fn invitation_register_start(
+        token: String,
+        request: invitation::ClientInvitationRegistrationStartRequest,
+    ) -> Result<Box<invitation::ServerInvitationRegistrationStartResponse>> {
+        call_server_json_with_error_message(
+            &format!(
+                "{}/auth/invitation/{}/register/start",
+                base_url(),
+                url_escape::encode_component(&token)
+            ),
+            RequestType::Post(request),
+            "Could not start registration: ",
+        )
+        .await
+    }
+
+    pub async fn invitation_register_finish(
+        token: String,
+        request: invitation::ClientInvitationRegistrationFinishRequest,
+    ) -> Result<()> {
+        call_server_empty_response_with_error_message(
+            &format!(
+                "{}/auth/invitation/{}/register/finish",
+                base_url(),
+                url_escape::encode_component(&token)
+            ),
+            RequestType::Post(request),
+            "Could not finish registration",
+        )
+        .await
+    }
+
+    pub async fn send_email_verification(user_id: String) -> Result<()> {
+        call_server_empty_response_with_error_message(
+            &format!(
+                "{}/auth/email/verification/{}",
+                base_url(),
+                url_escape::encode_component(&user_id)
+            ),
+            RequestType::Post(""),
+            "Could not send the verification email",
+        )
+        .await
+    }
+
+    pub async fn change_email(user_id: String, email: String) -> Result<()> {
+        call_server_empty_response_with_error_message(
+            &format!(
+                "{}/auth/email/change/{}",
+                base_url(),
+                url_escape::encode_component(&user_id)
+            ),
+            RequestType::Post(email_verification::EmailChangeRequest { email }),
+            "Could not send the confirmation email",
+        )
+        .await
+    }
+
+    pub async fn verify_email(
+        token: String,
+    ) -> Result<email_verification::ServerEmailVerificationResponse> {
+        call_server_json_with_error_message(
+            &format!(
+                "{}/auth/email/verify/{}",
+                base_url(),
+                url_escape::encode_component(&token)
+            ),
+            RequestType::Post(""),
+            "Invalid or expired verification link",
+        )
+        .await
+    }
 }
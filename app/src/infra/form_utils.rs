@@ -1,4 +1,5 @@
 use anyhow::{Result, anyhow, ensure};
+use lldap_validation::attributes::{AttributeValueRules, validate_attribute_value};
 use validator::validate_email;
 use web_sys::{FormData, HtmlFormElement};
 use yew::NodeRef;
@@ -14,6 +15,53 @@ pub struct GraphQlAttributeSchema {
     pub is_list: bool,
     pub is_readonly: bool,
     pub is_editable: bool,
+    pub validation: AttributeValidation,
+}
+
+/// The rules from the schema that the values of an attribute must follow.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AttributeValidation {
+    pub regex: Option<String>,
+    pub min_length: Option<i64>,
+    pub max_length: Option<i64>,
+    pub min_value: Option<i64>,
+    pub max_value: Option<i64>,
+    pub allowed_values: Vec<String>,
+    pub is_required: bool,
+}
+
+impl AttributeValidation {
+    /// Builds the rules from the GraphQL fields, where the integers are given as strings.
+    pub fn new(
+        regex: Option<String>,
+        min_length: Option<i64>,
+        max_length: Option<i64>,
+        min_value: Option<&str>,
+        max_value: Option<&str>,
+        allowed_values: Vec<String>,
+        is_required: bool,
+    ) -> Self {
+        Self {
+            regex,
+            min_length,
+            max_length,
+            min_value: min_value.and_then(|v| v.parse().ok()),
+            max_value: max_value.and_then(|v| v.parse().ok()),
+            allowed_values,
+            is_required,
+        }
+    }
+
+    pub fn rules(&self) -> AttributeValueRules<'_> {
+        AttributeValueRules {
+            regex: self.regex.as_deref(),
+            min_length: self.min_length,
+            max_length: self.max_length,
+            min_value: self.min_value,
+            max_value: self.max_value,
+            allowed_values: &self.allowed_values,
+        }
+    }
 }
 
 fn validate_email_attributes(all_values: &[AttributeValue]) -> Result<()> {
@@ -55,6 +103,15 @@ pub fn read_all_form_attributes(
                 "Multiple values supplied for non-list attribute {}",
                 attr.name
             );
+            ensure!(
+                !val.is_empty() || !attr.validation.is_required,
+                "Attribute {} is required",
+                attr.name
+            );
+            for value in &val {
+                validate_attribute_value(value, &attr.validation.rules())
+                    .map_err(|e| anyhow!("Invalid value for attribute {}: {}", attr.name, e))?;
+            }
             Ok(AttributeValue {
                 name: attr.name.clone(),
                 values: val,
@@ -8,3 +8,4 @@ pub mod graphql;
 pub mod modal;
 pub mod schema;
 pub mod tooltip;
+pub mod webauthn;
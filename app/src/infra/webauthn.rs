@@ -0,0 +1,51 @@
+//! Bridge to the browser's WebAuthn API (`navigator.credentials`).
+
+use anyhow::{Result, anyhow};
+use lldap_auth::webauthn::{
+    CreationChallengeResponse, PublicKeyCredential, RegisterPublicKeyCredential,
+    RequestChallengeResponse,
+};
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+fn get_credentials_container() -> Result<web_sys::CredentialsContainer> {
+    web_sys::window()
+        .map(|w| w.navigator().credentials())
+        .ok_or_else(|| anyhow!("Could not get window navigator"))
+}
+
+fn to_public_key_credential(value: wasm_bindgen::JsValue) -> Result<web_sys::PublicKeyCredential> {
+    value
+        .dyn_into::<web_sys::PublicKeyCredential>()
+        .map_err(|_| anyhow!("The authenticator did not return a public key credential"))
+}
+
+/// Ask the browser to create a new credential for the given challenge.
+pub async fn create_credential(
+    challenge: CreationChallengeResponse,
+) -> Result<RegisterPublicKeyCredential> {
+    let options = web_sys::CredentialCreationOptions::from(challenge);
+    let promise = get_credentials_container()?
+        .create_with_options(&options)
+        .map_err(|e| anyhow!("Could not start the authenticator registration: {:?}", e))?;
+    let credential = JsFuture::from(promise)
+        .await
+        .map_err(|e| anyhow!("Authenticator registration failed: {:?}", e))?;
+    Ok(RegisterPublicKeyCredential::from(to_public_key_credential(
+        credential,
+    )?))
+}
+
+/// Ask the browser to sign the given challenge with one of the registered credentials.
+pub async fn get_credential(challenge: RequestChallengeResponse) -> Result<PublicKeyCredential> {
+    let options = web_sys::CredentialRequestOptions::from(challenge);
+    let promise = get_credentials_container()?
+        .get_with_options(&options)
+        .map_err(|e| anyhow!("Could not start the authenticator login: {:?}", e))?;
+    let credential = JsFuture::from(promise)
+        .await
+        .map_err(|e| anyhow!("Authenticator login failed: {:?}", e))?;
+    Ok(PublicKeyCredential::from(to_public_key_credential(
+        credential,
+    )?))
+}
@@ -0,0 +1,96 @@
+use async_trait::async_trait;
+use lldap_auth::access_control::ValidationResults;
+use lldap_domain_handlers::events::{DirectoryEvent, DirectoryEventListener};
+use tokio::sync::broadcast;
+use tracing::warn;
+
+/// How many events a slow subscriber can fall behind before it starts missing some.
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Hands the directory events over to the live subscribers, e.g. the GraphQL subscriptions.
+pub struct DirectoryEventBroadcaster {
+    sender: broadcast::Sender<DirectoryEvent>,
+}
+
+impl Default for DirectoryEventBroadcaster {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DirectoryEventBroadcaster {
+    pub fn new() -> Self {
+        Self {
+            sender: broadcast::channel(CHANNEL_CAPACITY).0,
+        }
+    }
+
+    pub(crate) fn subscribe(
+        &self,
+        validation_result: &ValidationResults,
+    ) -> DirectoryEventSubscription {
+        DirectoryEventSubscription {
+            receiver: self.sender.subscribe(),
+            validation_result: validation_result.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl DirectoryEventListener for DirectoryEventBroadcaster {
+    async fn on_event(&self, event: DirectoryEvent) {
+        // This only fails when nobody is subscribed.
+        let _ = self.sender.send(event);
+    }
+}
+
+/// Whether the user can see the event, with the same rules as for reading the directory:
+/// regular users only see the events about themselves.
+fn is_visible(validation_result: &ValidationResults, event: &DirectoryEvent) -> bool {
+    match event {
+        DirectoryEvent::UserCreated { user_id }
+        | DirectoryEvent::UserUpdated { user_id }
+        | DirectoryEvent::UserDeleted { user_id }
+        | DirectoryEvent::UserAddedToGroup { user_id, .. }
+        | DirectoryEvent::UserRemovedFromGroup { user_id, .. } => {
+            validation_result.can_read(user_id)
+        }
+        DirectoryEvent::GroupCreated { .. }
+        | DirectoryEvent::GroupUpdated { .. }
+        | DirectoryEvent::GroupDeleted { .. } => validation_result.can_read_all(),
+        // Every user can read the schema.
+        DirectoryEvent::AttributeAdded { .. }
+        | DirectoryEvent::AttributeDeleted { .. }
+        | DirectoryEvent::AttributeUpdated { .. }
+        | DirectoryEvent::ObjectClassAdded { .. }
+        | DirectoryEvent::ObjectClassDeleted { .. } => true,
+    }
+}
+
+/// The events visible to one user, from the moment they subscribed.
+pub struct DirectoryEventSubscription {
+    receiver: broadcast::Receiver<DirectoryEvent>,
+    validation_result: ValidationResults,
+}
+
+impl DirectoryEventSubscription {
+    /// Waits for the next visible event. Returns None once the server shuts down.
+    pub async fn next(&mut self) -> Option<DirectoryEvent> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) => {
+                    if is_visible(&self.validation_result, &event) {
+                        return Some(event);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!(
+                        "A subscriber for {} is too slow, it missed {missed} events",
+                        self.validation_result.user
+                    );
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
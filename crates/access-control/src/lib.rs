@@ -1,4 +1,7 @@
+pub mod events;
+
 use async_trait::async_trait;
+use events::{DirectoryEventBroadcaster, DirectoryEventSubscription};
 use lldap_auth::access_control::{Permission, ValidationResults};
 use lldap_domain::{
     public_schema::PublicSchema,
@@ -6,25 +9,29 @@ use lldap_domain::{
         CreateAttributeRequest, CreateGroupRequest, CreateUserRequest, UpdateGroupRequest,
         UpdateUserRequest,
     },
-    schema::{AttributeSchema, Schema},
+    schema::{AttributePermissions, AttributeSchema, Schema},
     types::{
-        AttributeName, Group, GroupDetails, GroupId, GroupName, LdapObjectClass, User,
-        UserAndGroups, UserId,
+        Attribute, AttributeName, Group, GroupDetails, GroupId, GroupName, GroupOwner,
+        LdapObjectClass, User, UserAndGroups, UserId,
     },
 };
+use lldap_domain_handlers::audit::{AuditAction, AuditEvent, AuditLogBackendHandler, AuditSource};
 use lldap_domain_handlers::handler::{
-    BackendHandler, GroupBackendHandler, GroupListerBackendHandler, GroupRequestFilter,
+    BackendHandler, BatchMode, BatchOperation, BatchResult, DynamicGroupFilter,
+    GroupBackendHandler, GroupListerBackendHandler, GroupPageRequest, GroupRequestFilter, Page,
     ReadSchemaBackendHandler, SchemaBackendHandler, UserBackendHandler, UserListerBackendHandler,
-    UserRequestFilter,
+    UserPageRequest, UserRequestFilter,
 };
 use lldap_domain_model::error::Result;
-use std::collections::HashSet;
-use tracing::info;
+use std::{collections::HashSet, sync::Arc};
+use tracing::{info, warn};
 
 #[async_trait]
 pub trait UserReadableBackendHandler: ReadSchemaBackendHandler {
     async fn get_user_details(&self, user_id: &UserId) -> Result<User>;
     async fn get_user_groups(&self, user_id: &UserId) -> Result<HashSet<GroupDetails>>;
+    /// The groups whose members the user can manage, as an owner.
+    async fn list_owned_groups(&self, user_id: &UserId) -> Result<Vec<Group>>;
     async fn get_schema(&self) -> Result<PublicSchema>;
 }
 
@@ -35,7 +42,17 @@ pub trait ReadonlyBackendHandler: UserReadableBackendHandler {
         filters: Option<UserRequestFilter>,
         get_groups: bool,
     ) -> Result<Vec<UserAndGroups>>;
+    async fn list_users_page(
+        &self,
+        filters: Option<UserRequestFilter>,
+        page: UserPageRequest,
+    ) -> Result<Page<UserAndGroups>>;
     async fn list_groups(&self, filters: Option<GroupRequestFilter>) -> Result<Vec<Group>>;
+    async fn list_groups_page(
+        &self,
+        filters: Option<GroupRequestFilter>,
+        page: GroupPageRequest,
+    ) -> Result<Page<Group>>;
     async fn get_group_details(&self, group_id: GroupId) -> Result<GroupDetails>;
 }
 
@@ -55,19 +72,63 @@ pub trait AdminBackendHandler:
     async fn delete_user(&self, user_id: &UserId) -> Result<()>;
     async fn add_user_to_group(&self, user_id: &UserId, group_id: GroupId) -> Result<()>;
     async fn remove_user_from_group(&self, user_id: &UserId, group_id: GroupId) -> Result<()>;
+    async fn apply_batch(
+        &self,
+        operations: Vec<BatchOperation>,
+        mode: BatchMode,
+    ) -> Result<BatchResult>;
     async fn update_group(&self, request: UpdateGroupRequest) -> Result<()>;
     async fn create_group(&self, request: CreateGroupRequest) -> Result<GroupId>;
+    async fn create_dynamic_group(
+        &self,
+        request: CreateGroupRequest,
+        filter: DynamicGroupFilter,
+    ) -> Result<GroupId>;
     async fn delete_group(&self, group_id: GroupId) -> Result<()>;
+    async fn add_group_owner(&self, group_id: GroupId, owner: GroupOwner) -> Result<()>;
+    async fn remove_group_owner(&self, group_id: GroupId, owner: GroupOwner) -> Result<()>;
     async fn add_user_attribute(&self, request: CreateAttributeRequest) -> Result<()>;
     async fn add_group_attribute(&self, request: CreateAttributeRequest) -> Result<()>;
     async fn delete_user_attribute(&self, name: &AttributeName) -> Result<()>;
     async fn delete_group_attribute(&self, name: &AttributeName) -> Result<()>;
+    async fn set_user_attribute_permissions(
+        &self,
+        name: &AttributeName,
+        permissions: AttributePermissions,
+    ) -> Result<()>;
+    async fn set_group_attribute_permissions(
+        &self,
+        name: &AttributeName,
+        permissions: AttributePermissions,
+    ) -> Result<()>;
     async fn add_user_object_class(&self, name: &LdapObjectClass) -> Result<()>;
     async fn add_group_object_class(&self, name: &LdapObjectClass) -> Result<()>;
     async fn delete_user_object_class(&self, name: &LdapObjectClass) -> Result<()>;
     async fn delete_group_object_class(&self, name: &LdapObjectClass) -> Result<()>;
 }
 
+/// The operations allowed on a single group to its owners: they can manage its members, but
+/// nothing else.
+#[async_trait]
+pub trait OwnedGroupBackendHandler {
+    async fn get_group_details(&self) -> Result<GroupDetails>;
+    async fn list_members(&self) -> Result<Vec<UserAndGroups>>;
+    async fn add_member(&self, user_id: &UserId) -> Result<()>;
+    async fn remove_member(&self, user_id: &UserId) -> Result<()>;
+}
+
+/// Updates the attributes of users and groups, as allowed by the attribute permissions: besides
+/// the admins, the users can edit their own editable attributes, and the members of the editor
+/// groups of an attribute can edit it everywhere. It is up to the caller to check the changed
+/// attributes with `can_edit_user_attribute` and `can_edit_group_attribute`.
+#[async_trait]
+pub trait AttributeEditorBackendHandler: ReadSchemaBackendHandler {
+    fn can_edit_user_attribute(&self, attribute: &AttributeSchema, user_id: &UserId) -> bool;
+    fn can_edit_group_attribute(&self, attribute: &AttributeSchema) -> bool;
+    async fn update_user(&self, request: UpdateUserRequest) -> Result<()>;
+    async fn update_group(&self, request: UpdateGroupRequest) -> Result<()>;
+}
+
 #[async_trait]
 impl<Handler: BackendHandler> UserReadableBackendHandler for Handler {
     async fn get_user_details(&self, user_id: &UserId) -> Result<User> {
@@ -76,6 +137,13 @@ impl<Handler: BackendHandler> UserReadableBackendHandler for Handler {
     async fn get_user_groups(&self, user_id: &UserId) -> Result<HashSet<GroupDetails>> {
         <Handler as UserBackendHandler>::get_user_groups(self, user_id).await
     }
+    async fn list_owned_groups(&self, user_id: &UserId) -> Result<Vec<Group>> {
+        <Handler as GroupListerBackendHandler>::list_groups(
+            self,
+            Some(GroupRequestFilter::OwnedBy(user_id.clone())),
+        )
+        .await
+    }
     async fn get_schema(&self) -> Result<PublicSchema> {
         Ok(PublicSchema::from(
             <Handler as ReadSchemaBackendHandler>::get_schema(self).await?,
@@ -92,9 +160,23 @@ impl<Handler: BackendHandler> ReadonlyBackendHandler for Handler {
     ) -> Result<Vec<UserAndGroups>> {
         <Handler as UserListerBackendHandler>::list_users(self, filters, get_groups).await
     }
+    async fn list_users_page(
+        &self,
+        filters: Option<UserRequestFilter>,
+        page: UserPageRequest,
+    ) -> Result<Page<UserAndGroups>> {
+        <Handler as UserListerBackendHandler>::list_users_page(self, filters, page).await
+    }
     async fn list_groups(&self, filters: Option<GroupRequestFilter>) -> Result<Vec<Group>> {
         <Handler as GroupListerBackendHandler>::list_groups(self, filters).await
     }
+    async fn list_groups_page(
+        &self,
+        filters: Option<GroupRequestFilter>,
+        page: GroupPageRequest,
+    ) -> Result<Page<Group>> {
+        <Handler as GroupListerBackendHandler>::list_groups_page(self, filters, page).await
+    }
     async fn get_group_details(&self, group_id: GroupId) -> Result<GroupDetails> {
         <Handler as GroupBackendHandler>::get_group_details(self, group_id).await
     }
@@ -120,15 +202,35 @@ impl<Handler: BackendHandler> AdminBackendHandler for Handler {
     async fn remove_user_from_group(&self, user_id: &UserId, group_id: GroupId) -> Result<()> {
         <Handler as UserBackendHandler>::remove_user_from_group(self, user_id, group_id).await
     }
+    async fn apply_batch(
+        &self,
+        operations: Vec<BatchOperation>,
+        mode: BatchMode,
+    ) -> Result<BatchResult> {
+        <Handler as UserBackendHandler>::apply_batch(self, operations, mode).await
+    }
     async fn update_group(&self, request: UpdateGroupRequest) -> Result<()> {
         <Handler as GroupBackendHandler>::update_group(self, request).await
     }
     async fn create_group(&self, request: CreateGroupRequest) -> Result<GroupId> {
         <Handler as GroupBackendHandler>::create_group(self, request).await
     }
+    async fn create_dynamic_group(
+        &self,
+        request: CreateGroupRequest,
+        filter: DynamicGroupFilter,
+    ) -> Result<GroupId> {
+        <Handler as GroupBackendHandler>::create_dynamic_group(self, request, filter).await
+    }
     async fn delete_group(&self, group_id: GroupId) -> Result<()> {
         <Handler as GroupBackendHandler>::delete_group(self, group_id).await
     }
+    async fn add_group_owner(&self, group_id: GroupId, owner: GroupOwner) -> Result<()> {
+        <Handler as GroupBackendHandler>::add_group_owner(self, group_id, owner).await
+    }
+    async fn remove_group_owner(&self, group_id: GroupId, owner: GroupOwner) -> Result<()> {
+        <Handler as GroupBackendHandler>::remove_group_owner(self, group_id, owner).await
+    }
     async fn add_user_attribute(&self, request: CreateAttributeRequest) -> Result<()> {
         <Handler as SchemaBackendHandler>::add_user_attribute(self, request).await
     }
@@ -141,6 +243,22 @@ impl<Handler: BackendHandler> AdminBackendHandler for Handler {
     async fn delete_group_attribute(&self, name: &AttributeName) -> Result<()> {
         <Handler as SchemaBackendHandler>::delete_group_attribute(self, name).await
     }
+    async fn set_user_attribute_permissions(
+        &self,
+        name: &AttributeName,
+        permissions: AttributePermissions,
+    ) -> Result<()> {
+        <Handler as SchemaBackendHandler>::set_user_attribute_permissions(self, name, permissions)
+            .await
+    }
+    async fn set_group_attribute_permissions(
+        &self,
+        name: &AttributeName,
+        permissions: AttributePermissions,
+    ) -> Result<()> {
+        <Handler as SchemaBackendHandler>::set_group_attribute_permissions(self, name, permissions)
+            .await
+    }
     async fn add_user_object_class(&self, name: &LdapObjectClass) -> Result<()> {
         <Handler as SchemaBackendHandler>::add_user_object_class(self, name).await
     }
@@ -157,12 +275,18 @@ impl<Handler: BackendHandler> AdminBackendHandler for Handler {
 
 pub struct AccessControlledBackendHandler<Handler> {
     handler: Handler,
+    audit_log: Option<Arc<dyn AuditLogBackendHandler>>,
+    audit_source: AuditSource,
+    events: Option<Arc<DirectoryEventBroadcaster>>,
 }
 
 impl<Handler: Clone> Clone for AccessControlledBackendHandler<Handler> {
     fn clone(&self) -> Self {
         Self {
             handler: self.handler.clone(),
+            audit_log: self.audit_log.clone(),
+            audit_source: self.audit_source.clone(),
+            events: self.events.clone(),
         }
     }
 }
@@ -171,11 +295,71 @@ impl<Handler> AccessControlledBackendHandler<Handler> {
     pub fn unsafe_get_handler(&self) -> &Handler {
         &self.handler
     }
+
+    /// Records the write operations and logins in the audit log.
+    pub fn with_audit_log(mut self, audit_log: Arc<dyn AuditLogBackendHandler>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Sets where the requests handled through this handler come from, for the audit log.
+    pub fn with_audit_source(mut self, audit_source: AuditSource) -> Self {
+        self.audit_source = audit_source;
+        self
+    }
+
+    pub fn audit_source(&self) -> &AuditSource {
+        &self.audit_source
+    }
+
+    /// Records an event in the audit log, if enabled. A failure to record is only logged, it
+    /// doesn't fail the operation.
+    pub async fn record_audit_event(&self, event: AuditEvent) {
+        if let Some(audit_log) = &self.audit_log
+            && let Err(e) = audit_log.record_audit_event(event).await
+        {
+            warn!("Could not record the event in the audit log: {e:#}");
+        }
+    }
+
+    /// Makes the directory events available to `subscribe_to_events`. The broadcaster must also
+    /// be registered as a listener of the backend.
+    pub fn with_event_broadcaster(mut self, events: Arc<DirectoryEventBroadcaster>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// The directory events the user is allowed to see, from now on. None if events are not
+    /// enabled.
+    pub fn subscribe_to_events(
+        &self,
+        validation_result: &ValidationResults,
+    ) -> Option<DirectoryEventSubscription> {
+        self.events
+            .as_ref()
+            .map(|events| events.subscribe(validation_result))
+    }
+
+    /// The audit log is only readable by admins.
+    pub fn get_audit_log_handler(
+        &self,
+        validation_result: &ValidationResults,
+    ) -> Option<&dyn AuditLogBackendHandler> {
+        if !validation_result.is_admin() {
+            return None;
+        }
+        self.audit_log.as_deref()
+    }
 }
 
 impl<Handler: BackendHandler> AccessControlledBackendHandler<Handler> {
     pub fn new(handler: Handler) -> Self {
-        Self { handler }
+        Self {
+            handler,
+            audit_log: None,
+            audit_source: AuditSource::default(),
+            events: None,
+        }
     }
 
     pub fn get_schema_only_handler(
@@ -188,8 +372,10 @@ impl<Handler: BackendHandler> AccessControlledBackendHandler<Handler> {
     pub fn get_admin_handler(
         &self,
         validation_result: &ValidationResults,
-    ) -> Option<&(impl AdminBackendHandler + use<Handler>)> {
-        validation_result.is_admin().then_some(&self.handler)
+    ) -> Option<impl AdminBackendHandler + use<'_, Handler>> {
+        validation_result
+            .is_admin()
+            .then(|| self.get_audited_handler(validation_result))
     }
 
     pub fn get_readonly_handler(
@@ -203,10 +389,10 @@ impl<Handler: BackendHandler> AccessControlledBackendHandler<Handler> {
         &self,
         validation_result: &ValidationResults,
         user_id: &UserId,
-    ) -> Option<&(impl UserWriteableBackendHandler + use<Handler>)> {
+    ) -> Option<impl UserWriteableBackendHandler + use<'_, Handler>> {
         validation_result
             .can_write(user_id)
-            .then_some(&self.handler)
+            .then(|| self.get_audited_handler(validation_result))
     }
 
     pub fn get_readable_handler(
@@ -217,6 +403,66 @@ impl<Handler: BackendHandler> AccessControlledBackendHandler<Handler> {
         validation_result.can_read(user_id).then_some(&self.handler)
     }
 
+    /// Admins can manage the members of every group. The owners of a group can manage its
+    /// members, unless it is one of the groups granting permissions: the built-in roles and the
+    /// reader or editor groups of an attribute.
+    pub async fn get_owned_group_handler(
+        &self,
+        validation_result: &ValidationResults,
+        group_id: GroupId,
+    ) -> Result<Option<impl OwnedGroupBackendHandler + use<'_, Handler>>> {
+        if !validation_result.is_admin() {
+            let owned_groups = self
+                .handler
+                .list_groups(Some(GroupRequestFilter::And(vec![
+                    GroupRequestFilter::GroupId(group_id),
+                    GroupRequestFilter::OwnedBy(validation_result.user.clone()),
+                ])))
+                .await?;
+            if owned_groups.is_empty() {
+                return Ok(None);
+            }
+            let schema = <Handler as ReadSchemaBackendHandler>::get_schema(&self.handler).await?;
+            if owned_groups
+                .iter()
+                .any(|g| is_permission_group(&schema, &g.display_name))
+            {
+                return Ok(None);
+            }
+        }
+        Ok(Some(OwnedGroupHandler {
+            handler: self.get_audited_handler(validation_result),
+            group_id,
+        }))
+    }
+
+    /// Every user gets one, to check attribute by attribute what they can edit.
+    pub async fn get_attribute_editor_handler(
+        &self,
+        validation_result: &ValidationResults,
+    ) -> Result<impl AttributeEditorBackendHandler + use<'_, Handler>> {
+        let user_groups = if validation_result.is_admin() {
+            HashSet::new()
+        } else {
+            get_group_names(&self.handler, &validation_result.user).await?
+        };
+        Ok(AttributeEditorHandler {
+            handler: self.get_audited_handler(validation_result),
+            validation_result: validation_result.clone(),
+            user_groups,
+        })
+    }
+
+    fn get_audited_handler(
+        &self,
+        validation_result: &ValidationResults,
+    ) -> AuditedBackendHandler<'_, Handler> {
+        AuditedBackendHandler {
+            access: self,
+            actor: validation_result.user.clone(),
+        }
+    }
+
     pub fn get_user_restricted_lister_handler(
         &self,
         validation_result: &ValidationResults,
@@ -229,6 +475,7 @@ impl<Handler: BackendHandler> AccessControlledBackendHandler<Handler> {
                 info!("Unprivileged search, limiting results");
                 Some(validation_result.user.clone())
             },
+            validation_result: validation_result.clone(),
         }
     }
 
@@ -262,67 +509,786 @@ impl<Handler: BackendHandler> AccessControlledBackendHandler<Handler> {
     }
 }
 
-pub struct UserRestrictedListerBackendHandler<'a, Handler> {
-    handler: &'a Handler,
-    user_filter: Option<UserId>,
+async fn get_group_names<Handler: UserBackendHandler>(
+    handler: &Handler,
+    user_id: &UserId,
+) -> Result<HashSet<GroupName>> {
+    Ok(
+        <Handler as UserBackendHandler>::get_user_groups(handler, user_id)
+            .await?
+            .into_iter()
+            .map(|g| g.display_name)
+            .collect(),
+    )
+}
+
+/// Whether the user can read the attribute, on the users and groups they can see. The regular
+/// users see the visible attributes, the read-only roles the ones that aren't private, and the
+/// members of the reader or editor groups always see it.
+///
+/// This doesn't change which users and groups they can see: a regular user in a reader group
+/// still only sees the attribute on themselves and their groups.
+pub fn can_read_attribute(
+    attribute: &AttributeSchema,
+    validation_result: &ValidationResults,
+    user_groups: &HashSet<GroupName>,
+) -> bool {
+    if validation_result.is_admin() || attribute.permissions.is_readable_by(user_groups) {
+        true
+    } else if validation_result.can_read_all() {
+        !attribute.permissions.is_private
+    } else {
+        attribute.is_visible
+    }
+}
+
+/// Whether the user can edit the attribute, on themselves if `is_self`, or on another user or
+/// group.
+pub fn can_edit_attribute(
+    attribute: &AttributeSchema,
+    validation_result: &ValidationResults,
+    user_groups: &HashSet<GroupName>,
+    is_self: bool,
+) -> bool {
+    validation_result.is_admin()
+        || attribute.permissions.is_editable_by(user_groups)
+        || (is_self && attribute.is_editable)
+}
+
+fn describe_attribute_permissions(kind: &str, permissions: &AttributePermissions) -> String {
+    let join = |groups: &[GroupName]| {
+        groups
+            .iter()
+            .map(GroupName::as_str)
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    format!(
+        "set {kind} attribute permissions: private: {}, readers: [{}], editors: [{}]",
+        permissions.is_private,
+        join(&permissions.reader_groups),
+        join(&permissions.editor_groups)
+    )
+}
+
+fn is_permission_group(schema: &Schema, name: &GroupName) -> bool {
+    [
+        "lldap_admin",
+        "lldap_password_manager",
+        "lldap_strict_readonly",
+    ]
+    .into_iter()
+    .any(|g| *name == GroupName::from(g))
+        || schema
+            .user_attributes
+            .attributes
+            .iter()
+            .chain(&schema.group_attributes.attributes)
+            .any(|a| {
+                a.permissions
+                    .reader_groups
+                    .iter()
+                    .chain(&a.permissions.editor_groups)
+                    .any(|g| g == name)
+            })
+}
+
+/// Forwards the operations to the backend, recording the write ones in the audit log.
+pub struct AuditedBackendHandler<'a, Handler> {
+    access: &'a AccessControlledBackendHandler<Handler>,
+    actor: UserId,
+}
+
+fn describe_changes(
+    fields: &[(&str, bool)],
+    delete_attributes: &[AttributeName],
+    insert_attributes: &[Attribute],
+) -> Option<String> {
+    let changes = fields
+        .iter()
+        .filter(|(_, changed)| *changed)
+        .map(|(name, _)| name.to_string())
+        .chain(delete_attributes.iter().map(|a| format!("-{a}")))
+        .chain(insert_attributes.iter().map(|a| a.name.to_string()))
+        .collect::<Vec<_>>();
+    (!changes.is_empty()).then(|| changes.join(", "))
+}
+
+fn describe_group_owner(owner: &GroupOwner) -> String {
+    match owner {
+        GroupOwner::User(user_id) => format!("user {user_id}"),
+        GroupOwner::Group(name) => format!("group {name}"),
+    }
+}
+
+fn describe_create_user(request: &CreateUserRequest) -> Option<String> {
+    describe_changes(
+        &[
+            ("email", true),
+            ("display_name", request.display_name.is_some()),
+        ],
+        &[],
+        &request.attributes,
+    )
+}
+
+fn describe_update_user(request: &UpdateUserRequest) -> Option<String> {
+    describe_changes(
+        &[
+            ("email", request.email.is_some()),
+            ("display_name", request.display_name.is_some()),
+        ],
+        &request.delete_attributes,
+        &request.insert_attributes,
+    )
+}
+
+fn describe_update_group(request: &UpdateGroupRequest) -> Option<String> {
+    describe_changes(
+        &[("display_name", request.display_name.is_some())],
+        &request.delete_attributes,
+        &request.insert_attributes,
+    )
+}
+
+/// The action, target and details recorded for an operation of a batch.
+fn describe_batch_operation(operation: &BatchOperation) -> (AuditAction, String, Option<String>) {
+    match operation {
+        BatchOperation::CreateUser(request) => (
+            AuditAction::CreateUser,
+            request.user_id.to_string(),
+            describe_create_user(request),
+        ),
+        BatchOperation::UpdateUser(request) => (
+            AuditAction::UpdateUser,
+            request.user_id.to_string(),
+            describe_update_user(request),
+        ),
+        BatchOperation::DeleteUser(user_id) => (AuditAction::DeleteUser, user_id.to_string(), None),
+        BatchOperation::AddUserToGroup(user_id, group_id) => (
+            AuditAction::AddUserToGroup,
+            user_id.to_string(),
+            Some(format!("group {}", group_id.0)),
+        ),
+        BatchOperation::AddUserToGroupByName(user_id, name) => (
+            AuditAction::AddUserToGroup,
+            user_id.to_string(),
+            Some(format!("group {name}")),
+        ),
+        BatchOperation::RemoveUserFromGroup(user_id, group_id) => (
+            AuditAction::RemoveUserFromGroup,
+            user_id.to_string(),
+            Some(format!("group {}", group_id.0)),
+        ),
+        BatchOperation::SetGroupMembers(group_id, user_ids) => (
+            AuditAction::UpdateGroup,
+            group_id.0.to_string(),
+            Some(format!(
+                "members: {}",
+                user_ids
+                    .iter()
+                    .map(UserId::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        ),
+        BatchOperation::CreateGroup(request) => (
+            AuditAction::CreateGroup,
+            request.display_name.to_string(),
+            describe_changes(&[], &[], &request.attributes),
+        ),
+        BatchOperation::UpdateGroup(request) => (
+            AuditAction::UpdateGroup,
+            request.group_id.0.to_string(),
+            describe_update_group(request),
+        ),
+        BatchOperation::DeleteGroup(group_id) => {
+            (AuditAction::DeleteGroup, group_id.0.to_string(), None)
+        }
+    }
+}
+
+impl<Handler: Sync> AuditedBackendHandler<'_, Handler> {
+    async fn record(
+        &self,
+        action: AuditAction,
+        target: String,
+        details: Option<String>,
+        success: bool,
+    ) {
+        self.access
+            .record_audit_event(AuditEvent {
+                actor: Some(self.actor.clone()),
+                action,
+                target: Some(target),
+                details,
+                source: self.access.audit_source.clone(),
+                success,
+            })
+            .await;
+    }
+
+    async fn audit<T: Send>(
+        &self,
+        action: AuditAction,
+        target: String,
+        details: Option<String>,
+        result: Result<T>,
+    ) -> Result<T> {
+        self.record(action, target, details, result.is_ok()).await;
+        result
+    }
 }
 
 #[async_trait]
-impl<Handler: ReadSchemaBackendHandler + Sync> ReadSchemaBackendHandler
-    for UserRestrictedListerBackendHandler<'_, Handler>
-{
+impl<Handler: BackendHandler> ReadSchemaBackendHandler for AuditedBackendHandler<'_, Handler> {
     async fn get_schema(&self) -> Result<Schema> {
-        let mut schema = self.handler.get_schema().await?;
-        if self.user_filter.is_some() {
-            let filter_attributes = |attributes: &mut Vec<AttributeSchema>| {
-                attributes.retain(|a| a.is_visible);
-            };
-            filter_attributes(&mut schema.user_attributes.attributes);
-            filter_attributes(&mut schema.group_attributes.attributes);
-        }
-        Ok(schema)
+        <Handler as ReadSchemaBackendHandler>::get_schema(&self.access.handler).await
     }
 }
 
 #[async_trait]
-impl<Handler: UserListerBackendHandler + Sync> UserListerBackendHandler
-    for UserRestrictedListerBackendHandler<'_, Handler>
-{
+impl<Handler: BackendHandler> UserListerBackendHandler for AuditedBackendHandler<'_, Handler> {
     async fn list_users(
         &self,
         filters: Option<UserRequestFilter>,
         get_groups: bool,
     ) -> Result<Vec<UserAndGroups>> {
+        <Handler as UserListerBackendHandler>::list_users(&self.access.handler, filters, get_groups)
+            .await
+    }
+    async fn list_users_page(
+        &self,
+        filters: Option<UserRequestFilter>,
+        page: UserPageRequest,
+    ) -> Result<Page<UserAndGroups>> {
+        <Handler as UserListerBackendHandler>::list_users_page(&self.access.handler, filters, page)
+            .await
+    }
+}
+
+#[async_trait]
+impl<Handler: BackendHandler> GroupListerBackendHandler for AuditedBackendHandler<'_, Handler> {
+    async fn list_groups(&self, filters: Option<GroupRequestFilter>) -> Result<Vec<Group>> {
+        <Handler as GroupListerBackendHandler>::list_groups(&self.access.handler, filters).await
+    }
+    async fn list_groups_page(
+        &self,
+        filters: Option<GroupRequestFilter>,
+        page: GroupPageRequest,
+    ) -> Result<Page<Group>> {
+        <Handler as GroupListerBackendHandler>::list_groups_page(
+            &self.access.handler,
+            filters,
+            page,
+        )
+        .await
+    }
+}
+
+#[async_trait]
+impl<Handler: BackendHandler> UserBackendHandler for AuditedBackendHandler<'_, Handler> {
+    async fn get_user_details(&self, user_id: &UserId) -> Result<User> {
+        <Handler as UserBackendHandler>::get_user_details(&self.access.handler, user_id).await
+    }
+    async fn create_user(&self, request: CreateUserRequest) -> Result<()> {
+        let target = request.user_id.to_string();
+        let details = describe_create_user(&request);
+        let result =
+            <Handler as UserBackendHandler>::create_user(&self.access.handler, request).await;
+        self.audit(AuditAction::CreateUser, target, details, result)
+            .await
+    }
+    async fn update_user(&self, request: UpdateUserRequest) -> Result<()> {
+        let target = request.user_id.to_string();
+        let details = describe_update_user(&request);
+        let result =
+            <Handler as UserBackendHandler>::update_user(&self.access.handler, request).await;
+        self.audit(AuditAction::UpdateUser, target, details, result)
+            .await
+    }
+    async fn delete_user(&self, user_id: &UserId) -> Result<()> {
+        let result =
+            <Handler as UserBackendHandler>::delete_user(&self.access.handler, user_id).await;
+        self.audit(AuditAction::DeleteUser, user_id.to_string(), None, result)
+            .await
+    }
+    async fn add_user_to_group(&self, user_id: &UserId, group_id: GroupId) -> Result<()> {
+        let result = <Handler as UserBackendHandler>::add_user_to_group(
+            &self.access.handler,
+            user_id,
+            group_id,
+        )
+        .await;
+        self.audit(
+            AuditAction::AddUserToGroup,
+            user_id.to_string(),
+            Some(format!("group {}", group_id.0)),
+            result,
+        )
+        .await
+    }
+    async fn remove_user_from_group(&self, user_id: &UserId, group_id: GroupId) -> Result<()> {
+        let result = <Handler as UserBackendHandler>::remove_user_from_group(
+            &self.access.handler,
+            user_id,
+            group_id,
+        )
+        .await;
+        self.audit(
+            AuditAction::RemoveUserFromGroup,
+            user_id.to_string(),
+            Some(format!("group {}", group_id.0)),
+            result,
+        )
+        .await
+    }
+    async fn get_user_groups(&self, user_id: &UserId) -> Result<HashSet<GroupDetails>> {
+        <Handler as UserBackendHandler>::get_user_groups(&self.access.handler, user_id).await
+    }
+    async fn apply_batch(
+        &self,
+        operations: Vec<BatchOperation>,
+        mode: BatchMode,
+    ) -> Result<BatchResult> {
+        let descriptions = operations
+            .iter()
+            .map(describe_batch_operation)
+            .collect::<Vec<_>>();
+        let batch_result =
+            <Handler as UserBackendHandler>::apply_batch(&self.access.handler, operations, mode)
+                .await?;
+        // Only the attempted operations are recorded, and they only succeeded if the batch was
+        // committed.
+        for ((action, target, details), result) in
+            descriptions.into_iter().zip(&batch_result.results)
+        {
+            self.record(
+                action,
+                target,
+                details,
+                batch_result.committed && result.is_ok(),
+            )
+            .await;
+        }
+        Ok(batch_result)
+    }
+}
+
+#[async_trait]
+impl<Handler: BackendHandler> GroupBackendHandler for AuditedBackendHandler<'_, Handler> {
+    async fn get_group_details(&self, group_id: GroupId) -> Result<GroupDetails> {
+        <Handler as GroupBackendHandler>::get_group_details(&self.access.handler, group_id).await
+    }
+    async fn update_group(&self, request: UpdateGroupRequest) -> Result<()> {
+        let target = request.group_id.0.to_string();
+        let details = describe_update_group(&request);
+        let result =
+            <Handler as GroupBackendHandler>::update_group(&self.access.handler, request).await;
+        self.audit(AuditAction::UpdateGroup, target, details, result)
+            .await
+    }
+    async fn create_group(&self, request: CreateGroupRequest) -> Result<GroupId> {
+        let details = Some(format!("display_name: {}", request.display_name));
+        let result =
+            <Handler as GroupBackendHandler>::create_group(&self.access.handler, request).await;
+        let target = match &result {
+            Ok(group_id) => group_id.0.to_string(),
+            Err(_) => String::new(),
+        };
+        self.audit(AuditAction::CreateGroup, target, details, result)
+            .await
+    }
+    async fn create_dynamic_group(
+        &self,
+        request: CreateGroupRequest,
+        filter: DynamicGroupFilter,
+    ) -> Result<GroupId> {
+        let details = Some(format!(
+            "display_name: {}, dynamic filter: {}",
+            request.display_name, filter.ldap_filter
+        ));
+        let result = <Handler as GroupBackendHandler>::create_dynamic_group(
+            &self.access.handler,
+            request,
+            filter,
+        )
+        .await;
+        let target = match &result {
+            Ok(group_id) => group_id.0.to_string(),
+            Err(_) => String::new(),
+        };
+        self.audit(AuditAction::CreateGroup, target, details, result)
+            .await
+    }
+    async fn delete_group(&self, group_id: GroupId) -> Result<()> {
+        let result =
+            <Handler as GroupBackendHandler>::delete_group(&self.access.handler, group_id).await;
+        self.audit(
+            AuditAction::DeleteGroup,
+            group_id.0.to_string(),
+            None,
+            result,
+        )
+        .await
+    }
+    async fn add_group_owner(&self, group_id: GroupId, owner: GroupOwner) -> Result<()> {
+        let details = Some(format!("add owner: {}", describe_group_owner(&owner)));
+        let result = <Handler as GroupBackendHandler>::add_group_owner(
+            &self.access.handler,
+            group_id,
+            owner,
+        )
+        .await;
+        self.audit(
+            AuditAction::UpdateGroup,
+            group_id.0.to_string(),
+            details,
+            result,
+        )
+        .await
+    }
+    async fn remove_group_owner(&self, group_id: GroupId, owner: GroupOwner) -> Result<()> {
+        let details = Some(format!("remove owner: {}", describe_group_owner(&owner)));
+        let result = <Handler as GroupBackendHandler>::remove_group_owner(
+            &self.access.handler,
+            group_id,
+            owner,
+        )
+        .await;
+        self.audit(
+            AuditAction::UpdateGroup,
+            group_id.0.to_string(),
+            details,
+            result,
+        )
+        .await
+    }
+}
+
+/// The updates allowed by the attribute permissions, made through the audited handler.
+struct AttributeEditorHandler<'a, Handler> {
+    handler: AuditedBackendHandler<'a, Handler>,
+    validation_result: ValidationResults,
+    user_groups: HashSet<GroupName>,
+}
+
+#[async_trait]
+impl<Handler: BackendHandler> ReadSchemaBackendHandler for AttributeEditorHandler<'_, Handler> {
+    async fn get_schema(&self) -> Result<Schema> {
+        ReadSchemaBackendHandler::get_schema(&self.handler).await
+    }
+}
+
+#[async_trait]
+impl<Handler: BackendHandler> AttributeEditorBackendHandler
+    for AttributeEditorHandler<'_, Handler>
+{
+    fn can_edit_user_attribute(&self, attribute: &AttributeSchema, user_id: &UserId) -> bool {
+        can_edit_attribute(
+            attribute,
+            &self.validation_result,
+            &self.user_groups,
+            *user_id == self.validation_result.user,
+        )
+    }
+    fn can_edit_group_attribute(&self, attribute: &AttributeSchema) -> bool {
+        // The members of a group don't edit it.
+        can_edit_attribute(attribute, &self.validation_result, &self.user_groups, false)
+    }
+    async fn update_user(&self, request: UpdateUserRequest) -> Result<()> {
+        UserBackendHandler::update_user(&self.handler, request).await
+    }
+    async fn update_group(&self, request: UpdateGroupRequest) -> Result<()> {
+        GroupBackendHandler::update_group(&self.handler, request).await
+    }
+}
+
+/// The members of a single group, managed through the audited handler.
+struct OwnedGroupHandler<'a, Handler> {
+    handler: AuditedBackendHandler<'a, Handler>,
+    group_id: GroupId,
+}
+
+#[async_trait]
+impl<Handler: BackendHandler> OwnedGroupBackendHandler for OwnedGroupHandler<'_, Handler> {
+    async fn get_group_details(&self) -> Result<GroupDetails> {
+        GroupBackendHandler::get_group_details(&self.handler, self.group_id).await
+    }
+    async fn list_members(&self) -> Result<Vec<UserAndGroups>> {
+        UserListerBackendHandler::list_users(
+            &self.handler,
+            Some(UserRequestFilter::MemberOfId(self.group_id)),
+            false,
+        )
+        .await
+    }
+    async fn add_member(&self, user_id: &UserId) -> Result<()> {
+        UserBackendHandler::add_user_to_group(&self.handler, user_id, self.group_id).await
+    }
+    async fn remove_member(&self, user_id: &UserId) -> Result<()> {
+        UserBackendHandler::remove_user_from_group(&self.handler, user_id, self.group_id).await
+    }
+}
+
+#[async_trait]
+impl<Handler: BackendHandler> SchemaBackendHandler for AuditedBackendHandler<'_, Handler> {
+    async fn add_user_attribute(&self, request: CreateAttributeRequest) -> Result<()> {
+        let target = request.name.to_string();
+        let result =
+            <Handler as SchemaBackendHandler>::add_user_attribute(&self.access.handler, request)
+                .await;
+        self.audit(
+            AuditAction::UpdateSchema,
+            target,
+            Some("add user attribute".to_owned()),
+            result,
+        )
+        .await
+    }
+    async fn add_group_attribute(&self, request: CreateAttributeRequest) -> Result<()> {
+        let target = request.name.to_string();
+        let result =
+            <Handler as SchemaBackendHandler>::add_group_attribute(&self.access.handler, request)
+                .await;
+        self.audit(
+            AuditAction::UpdateSchema,
+            target,
+            Some("add group attribute".to_owned()),
+            result,
+        )
+        .await
+    }
+    async fn delete_user_attribute(&self, name: &AttributeName) -> Result<()> {
+        let result =
+            <Handler as SchemaBackendHandler>::delete_user_attribute(&self.access.handler, name)
+                .await;
+        self.audit(
+            AuditAction::UpdateSchema,
+            name.to_string(),
+            Some("delete user attribute".to_owned()),
+            result,
+        )
+        .await
+    }
+    async fn delete_group_attribute(&self, name: &AttributeName) -> Result<()> {
+        let result =
+            <Handler as SchemaBackendHandler>::delete_group_attribute(&self.access.handler, name)
+                .await;
+        self.audit(
+            AuditAction::UpdateSchema,
+            name.to_string(),
+            Some("delete group attribute".to_owned()),
+            result,
+        )
+        .await
+    }
+    async fn set_user_attribute_permissions(
+        &self,
+        name: &AttributeName,
+        permissions: AttributePermissions,
+    ) -> Result<()> {
+        let details = describe_attribute_permissions("user", &permissions);
+        let result = <Handler as SchemaBackendHandler>::set_user_attribute_permissions(
+            &self.access.handler,
+            name,
+            permissions,
+        )
+        .await;
+        self.audit(
+            AuditAction::UpdateSchema,
+            name.to_string(),
+            Some(details),
+            result,
+        )
+        .await
+    }
+    async fn set_group_attribute_permissions(
+        &self,
+        name: &AttributeName,
+        permissions: AttributePermissions,
+    ) -> Result<()> {
+        let details = describe_attribute_permissions("group", &permissions);
+        let result = <Handler as SchemaBackendHandler>::set_group_attribute_permissions(
+            &self.access.handler,
+            name,
+            permissions,
+        )
+        .await;
+        self.audit(
+            AuditAction::UpdateSchema,
+            name.to_string(),
+            Some(details),
+            result,
+        )
+        .await
+    }
+    async fn add_user_object_class(&self, name: &LdapObjectClass) -> Result<()> {
+        let result =
+            <Handler as SchemaBackendHandler>::add_user_object_class(&self.access.handler, name)
+                .await;
+        self.audit(
+            AuditAction::UpdateSchema,
+            name.to_string(),
+            Some("add user object class".to_owned()),
+            result,
+        )
+        .await
+    }
+    async fn add_group_object_class(&self, name: &LdapObjectClass) -> Result<()> {
+        let result =
+            <Handler as SchemaBackendHandler>::add_group_object_class(&self.access.handler, name)
+                .await;
+        self.audit(
+            AuditAction::UpdateSchema,
+            name.to_string(),
+            Some("add group object class".to_owned()),
+            result,
+        )
+        .await
+    }
+    async fn delete_user_object_class(&self, name: &LdapObjectClass) -> Result<()> {
+        let result =
+            <Handler as SchemaBackendHandler>::delete_user_object_class(&self.access.handler, name)
+                .await;
+        self.audit(
+            AuditAction::UpdateSchema,
+            name.to_string(),
+            Some("delete user object class".to_owned()),
+            result,
+        )
+        .await
+    }
+    async fn delete_group_object_class(&self, name: &LdapObjectClass) -> Result<()> {
+        let result = <Handler as SchemaBackendHandler>::delete_group_object_class(
+            &self.access.handler,
+            name,
+        )
+        .await;
+        self.audit(
+            AuditAction::UpdateSchema,
+            name.to_string(),
+            Some("delete group object class".to_owned()),
+            result,
+        )
+        .await
+    }
+}
+
+impl<Handler: BackendHandler> BackendHandler for AuditedBackendHandler<'_, Handler> {}
+
+pub struct UserRestrictedListerBackendHandler<'a, Handler> {
+    handler: &'a Handler,
+    user_filter: Option<UserId>,
+    validation_result: ValidationResults,
+}
+
+impl<Handler> UserRestrictedListerBackendHandler<'_, Handler> {
+    fn restrict_user_filter(
+        &self,
+        filters: Option<UserRequestFilter>,
+    ) -> Option<UserRequestFilter> {
         let user_filter = self
             .user_filter
             .as_ref()
             .map(|u| UserRequestFilter::UserId(u.clone()));
-        let filters = match (filters, user_filter) {
+        match (filters, user_filter) {
             (None, None) => None,
             (None, u) => u,
             (f, None) => f,
             (Some(f), Some(u)) => Some(UserRequestFilter::And(vec![f, u])),
-        };
-        self.handler.list_users(filters, get_groups).await
+        }
     }
-}
 
-#[async_trait]
-impl<Handler: GroupListerBackendHandler + Sync> GroupListerBackendHandler
-    for UserRestrictedListerBackendHandler<'_, Handler>
-{
-    async fn list_groups(&self, filters: Option<GroupRequestFilter>) -> Result<Vec<Group>> {
+    fn restrict_group_filter(
+        &self,
+        filters: Option<GroupRequestFilter>,
+    ) -> Option<GroupRequestFilter> {
         let group_filter = self
             .user_filter
             .as_ref()
             .map(|u| GroupRequestFilter::Member(u.clone()));
-        let filters = match (filters, group_filter) {
+        match (filters, group_filter) {
             (None, None) => None,
             (None, u) => u,
             (f, None) => f,
             (Some(f), Some(u)) => Some(GroupRequestFilter::And(vec![f, u])),
+        }
+    }
+}
+
+#[async_trait]
+impl<Handler: ReadSchemaBackendHandler + UserBackendHandler + Sync> ReadSchemaBackendHandler
+    for UserRestrictedListerBackendHandler<'_, Handler>
+{
+    /// Only the attributes the user can read, so that both GraphQL and LDAP hide the others.
+    async fn get_schema(&self) -> Result<Schema> {
+        let mut schema = <Handler as ReadSchemaBackendHandler>::get_schema(self.handler).await?;
+        if self.validation_result.is_admin() {
+            return Ok(schema);
+        }
+        // The groups of the user only matter if some attribute grants permissions to groups.
+        let user_groups = if schema
+            .user_attributes
+            .attributes
+            .iter()
+            .chain(&schema.group_attributes.attributes)
+            .any(|a| a.permissions.has_groups())
+        {
+            get_group_names(self.handler, &self.validation_result.user).await?
+        } else {
+            HashSet::new()
+        };
+        let filter_attributes = |attributes: &mut Vec<AttributeSchema>| {
+            attributes.retain(|a| can_read_attribute(a, &self.validation_result, &user_groups));
         };
-        self.handler.list_groups(filters).await
+        filter_attributes(&mut schema.user_attributes.attributes);
+        filter_attributes(&mut schema.group_attributes.attributes);
+        Ok(schema)
+    }
+}
+
+#[async_trait]
+impl<Handler: UserListerBackendHandler + UserBackendHandler + Sync> UserListerBackendHandler
+    for UserRestrictedListerBackendHandler<'_, Handler>
+{
+    async fn list_users(
+        &self,
+        filters: Option<UserRequestFilter>,
+        get_groups: bool,
+    ) -> Result<Vec<UserAndGroups>> {
+        self.handler
+            .list_users(self.restrict_user_filter(filters), get_groups)
+            .await
+    }
+    async fn list_users_page(
+        &self,
+        filters: Option<UserRequestFilter>,
+        page: UserPageRequest,
+    ) -> Result<Page<UserAndGroups>> {
+        self.handler
+            .list_users_page(self.restrict_user_filter(filters), page)
+            .await
+    }
+}
+
+#[async_trait]
+impl<Handler: GroupListerBackendHandler + UserBackendHandler + Sync> GroupListerBackendHandler
+    for UserRestrictedListerBackendHandler<'_, Handler>
+{
+    async fn list_groups(&self, filters: Option<GroupRequestFilter>) -> Result<Vec<Group>> {
+        self.handler
+            .list_groups(self.restrict_group_filter(filters))
+            .await
+    }
+    async fn list_groups_page(
+        &self,
+        filters: Option<GroupRequestFilter>,
+        page: GroupPageRequest,
+    ) -> Result<Page<Group>> {
+        self.handler
+            .list_groups_page(self.restrict_group_filter(filters), page)
+            .await
     }
 }
 
@@ -334,7 +1300,7 @@ pub trait UserAndGroupListerBackendHandler:
 }
 
 #[async_trait]
-impl<Handler: GroupListerBackendHandler + UserListerBackendHandler + Sync>
+impl<Handler: GroupListerBackendHandler + UserListerBackendHandler + UserBackendHandler + Sync>
     UserAndGroupListerBackendHandler for UserRestrictedListerBackendHandler<'_, Handler>
 {
     fn user_filter(&self) -> &Option<UserId> {
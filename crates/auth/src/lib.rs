@@ -106,6 +106,243 @@ pub mod password_reset {
     }
 }
 
+/// The messages for the email address verification.
+pub mod email_verification {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    pub struct ServerEmailVerificationResponse {
+        pub user_id: String,
+        pub email: String,
+    }
+
+    /// Asks for a link to the new address: the email is only changed once it is confirmed.
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    pub struct EmailChangeRequest {
+        pub email: String,
+    }
+}
+
+/// The messages for the invitation-based self-registration.
+/// The password is set through the OPAQUE registration messages.
+pub mod invitation {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    pub struct ClientCreateInvitationRequest {
+        /// If set, the invitee cannot pick another user ID.
+        pub user_id: Option<String>,
+        /// If set, the invitation is sent to this address and the invitee cannot change it.
+        pub email: Option<String>,
+        /// Groups the new user is added to.
+        #[serde(default)]
+        pub group_ids: Vec<i32>,
+        /// Defaults to 7 days.
+        pub validity_days: Option<u32>,
+        /// Attributes of the new user, chosen by the admin. Must include the required ones.
+        #[serde(default)]
+        pub attributes: Vec<InvitationAttribute>,
+    }
+
+    /// The value of an attribute, formatted as in the GraphQL API.
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+    pub struct InvitationAttribute {
+        pub name: String,
+        pub value: Vec<String>,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    pub struct ServerCreateInvitationResponse {
+        pub token: String,
+        /// The link to send to the invitee.
+        pub url: String,
+        pub email_sent: bool,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    pub struct ServerInvitationResponse {
+        pub user_id: Option<String>,
+        pub email: Option<String>,
+        pub expiry_date: DateTime<Utc>,
+    }
+
+    #[derive(Serialize, Deserialize, Clone)]
+    pub struct ClientInvitationRegistrationStartRequest {
+        pub user_id: String,
+        pub email: String,
+        pub display_name: Option<String>,
+        pub registration_start_request: opaque::server::registration::RegistrationRequest,
+    }
+
+    #[derive(Serialize, Deserialize, Clone)]
+    pub struct ServerInvitationRegistrationStartResponse {
+        pub registration: super::registration::ServerRegistrationStartResponse,
+        /// Binds the registration to the invitation and the chosen user details. Must be sent
+        /// back with the finish request.
+        pub signature: String,
+    }
+
+    #[derive(Serialize, Deserialize, Clone)]
+    pub struct ClientInvitationRegistrationFinishRequest {
+        pub user_id: String,
+        pub email: String,
+        pub display_name: Option<String>,
+        pub signature: String,
+        pub registration: super::registration::ClientRegistrationFinishRequest,
+    }
+}
+
+/// The messages for the WebAuthn (passkey) registration and login.
+/// The server keeps the ceremony state in memory, and identifies it by `state_id`.
+pub mod webauthn {
+    use super::*;
+
+    pub use webauthn_rs_proto::{
+        CreationChallengeResponse, PublicKeyCredential, RegisterPublicKeyCredential,
+        RequestChallengeResponse,
+    };
+
+    #[derive(Serialize, Deserialize, Clone)]
+    pub struct ClientRegistrationStartRequest {
+        /// Name given by the user to the new authenticator.
+        pub name: String,
+    }
+
+    #[derive(Serialize, Deserialize, Clone)]
+    pub struct ServerRegistrationStartResponse {
+        pub state_id: String,
+        pub challenge: CreationChallengeResponse,
+    }
+
+    #[derive(Serialize, Deserialize, Clone)]
+    pub struct ClientRegistrationFinishRequest {
+        pub state_id: String,
+        pub name: String,
+        pub credential: RegisterPublicKeyCredential,
+    }
+
+    #[derive(Serialize, Deserialize, Clone)]
+    pub struct ServerLoginStartResponse {
+        pub state_id: String,
+        pub challenge: RequestChallengeResponse,
+    }
+
+    #[derive(Serialize, Deserialize, Clone)]
+    pub struct ClientLoginFinishRequest {
+        pub state_id: String,
+        pub credential: PublicKeyCredential,
+    }
+
+    /// A registered authenticator, as listed to the user or an admin.
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+    pub struct PasskeyInfo {
+        pub credential_id: String,
+        pub name: String,
+        pub creation_date: DateTime<Utc>,
+        pub last_used_date: Option<DateTime<Utc>>,
+    }
+}
+
+/// The messages for the bulk import of users and groups from a CSV or LDIF file.
+pub mod import {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+    #[serde(rename_all = "snake_case")]
+    pub enum ImportFormat {
+        Csv,
+        Ldif,
+    }
+
+    /// What to do with the users and groups of the file that already exist.
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+    #[serde(rename_all = "snake_case")]
+    pub enum ConflictPolicy {
+        /// Leave them as they are.
+        #[default]
+        Skip,
+        /// Overwrite the fields and attributes present in the file. The others are kept.
+        Update,
+        /// Import nothing.
+        Fail,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    pub struct ClientImportRequest {
+        pub format: ImportFormat,
+        /// The content of the file.
+        pub content: String,
+        #[serde(default)]
+        pub conflict_policy: ConflictPolicy,
+        /// Only report the changes, without applying them.
+        #[serde(default)]
+        pub dry_run: bool,
+        /// Renames the attributes of the file to attributes of the schema. Mapping an
+        /// attribute to an empty name ignores it.
+        #[serde(default)]
+        pub attribute_mapping: BTreeMap<String, String>,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+    #[serde(tag = "change", rename_all = "snake_case")]
+    pub enum ImportChange {
+        CreateUser {
+            user_id: String,
+        },
+        /// `fields` are the names of the fields and attributes that change.
+        UpdateUser {
+            user_id: String,
+            fields: Vec<String>,
+        },
+        SkipUser {
+            user_id: String,
+        },
+        CreateGroup {
+            name: String,
+        },
+        UpdateGroup {
+            name: String,
+            fields: Vec<String>,
+        },
+        SkipGroup {
+            name: String,
+        },
+        AddMembership {
+            user_id: String,
+            group: String,
+        },
+    }
+
+    impl std::fmt::Display for ImportChange {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                ImportChange::CreateUser { user_id } => write!(f, "Create user {user_id}"),
+                ImportChange::UpdateUser { user_id, fields } => {
+                    write!(f, "Update user {user_id}: {}", fields.join(", "))
+                }
+                ImportChange::SkipUser { user_id } => write!(f, "Skip existing user {user_id}"),
+                ImportChange::CreateGroup { name } => write!(f, "Create group {name}"),
+                ImportChange::UpdateGroup { name, fields } => {
+                    write!(f, "Update group {name}: {}", fields.join(", "))
+                }
+                ImportChange::SkipGroup { name } => write!(f, "Skip existing group {name}"),
+                ImportChange::AddMembership { user_id, group } => {
+                    write!(f, "Add user {user_id} to group {group}")
+                }
+            }
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+    pub struct ServerImportResponse {
+        pub changes: Vec<ImportChange>,
+        /// If there is any error, nothing is applied.
+        pub errors: Vec<String>,
+        pub applied: bool,
+    }
+}
+
 pub mod types {
     use serde::{Deserialize, Serialize};
 
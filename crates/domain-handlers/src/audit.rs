@@ -0,0 +1,146 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use lldap_domain::types::UserId;
+use lldap_domain_model::error::Result;
+use std::net::IpAddr;
+
+/// The interface through which an audited operation was made.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum AuditInterface {
+    Ldap,
+    GraphQl,
+    #[default]
+    Http,
+}
+
+impl AuditInterface {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditInterface::Ldap => "ldap",
+            AuditInterface::GraphQl => "graphql",
+            AuditInterface::Http => "http",
+        }
+    }
+
+    pub fn from_str_opt(value: &str) -> Option<Self> {
+        Some(match value {
+            "ldap" => AuditInterface::Ldap,
+            "graphql" => AuditInterface::GraphQl,
+            "http" => AuditInterface::Http,
+            _ => return None,
+        })
+    }
+}
+
+/// Where a request comes from.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct AuditSource {
+    pub interface: AuditInterface,
+    pub ip: Option<IpAddr>,
+}
+
+impl AuditSource {
+    pub fn new(interface: AuditInterface, ip: Option<IpAddr>) -> Self {
+        Self { interface, ip }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum AuditAction {
+    CreateUser,
+    UpdateUser,
+    DeleteUser,
+    CreateGroup,
+    UpdateGroup,
+    DeleteGroup,
+    AddUserToGroup,
+    RemoveUserFromGroup,
+    UpdateSchema,
+    ChangePassword,
+    RequestPasswordReset,
+    Login,
+}
+
+impl AuditAction {
+    pub const ALL: [AuditAction; 12] = [
+        AuditAction::CreateUser,
+        AuditAction::UpdateUser,
+        AuditAction::DeleteUser,
+        AuditAction::CreateGroup,
+        AuditAction::UpdateGroup,
+        AuditAction::DeleteGroup,
+        AuditAction::AddUserToGroup,
+        AuditAction::RemoveUserFromGroup,
+        AuditAction::UpdateSchema,
+        AuditAction::ChangePassword,
+        AuditAction::RequestPasswordReset,
+        AuditAction::Login,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditAction::CreateUser => "create_user",
+            AuditAction::UpdateUser => "update_user",
+            AuditAction::DeleteUser => "delete_user",
+            AuditAction::CreateGroup => "create_group",
+            AuditAction::UpdateGroup => "update_group",
+            AuditAction::DeleteGroup => "delete_group",
+            AuditAction::AddUserToGroup => "add_user_to_group",
+            AuditAction::RemoveUserFromGroup => "remove_user_from_group",
+            AuditAction::UpdateSchema => "update_schema",
+            AuditAction::ChangePassword => "change_password",
+            AuditAction::RequestPasswordReset => "request_password_reset",
+            AuditAction::Login => "login",
+        }
+    }
+
+    pub fn from_str_opt(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.as_str() == value)
+    }
+}
+
+/// Something worth keeping a trace of: who did what, to what, and from where.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct AuditEvent {
+    /// The authenticated user, or the user trying to log in.
+    pub actor: Option<UserId>,
+    pub action: AuditAction,
+    /// The user ID, group ID or attribute name affected by the action.
+    pub target: Option<String>,
+    /// Free-form details, e.g. the modified attributes.
+    pub details: Option<String>,
+    pub source: AuditSource,
+    pub success: bool,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct AuditLogEntry {
+    pub id: i64,
+    pub timestamp: NaiveDateTime,
+    pub event: AuditEvent,
+}
+
+/// All the set fields must match.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct AuditLogFilter {
+    pub actor: Option<UserId>,
+    pub target: Option<String>,
+    pub action: Option<AuditAction>,
+    pub interface: Option<AuditInterface>,
+    pub success: Option<bool>,
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+}
+
+#[async_trait]
+pub trait AuditLogBackendHandler: Send + Sync {
+    async fn record_audit_event(&self, event: AuditEvent) -> Result<()>;
+    /// Returns the most recent events first.
+    async fn list_audit_events(
+        &self,
+        filter: AuditLogFilter,
+        limit: u64,
+    ) -> Result<Vec<AuditLogEntry>>;
+    /// Returns the number of deleted events.
+    async fn delete_audit_events_before(&self, date: NaiveDateTime) -> Result<u64>;
+}
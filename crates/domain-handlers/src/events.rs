@@ -0,0 +1,112 @@
+use async_trait::async_trait;
+use lldap_domain::types::{AttributeName, GroupId, GroupName, LdapObjectClass, UserId};
+use serde::Serialize;
+
+/// The kind of object a schema change applies to.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaObjectType {
+    User,
+    Group,
+}
+
+/// A change to the directory, emitted once the corresponding transaction is committed.
+#[derive(PartialEq, Eq, Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum DirectoryEvent {
+    UserCreated {
+        user_id: UserId,
+    },
+    UserUpdated {
+        user_id: UserId,
+    },
+    UserDeleted {
+        user_id: UserId,
+    },
+    GroupCreated {
+        group_id: GroupId,
+        display_name: GroupName,
+    },
+    GroupUpdated {
+        group_id: GroupId,
+    },
+    GroupDeleted {
+        group_id: GroupId,
+    },
+    UserAddedToGroup {
+        user_id: UserId,
+        group_id: GroupId,
+    },
+    UserRemovedFromGroup {
+        user_id: UserId,
+        group_id: GroupId,
+    },
+    AttributeAdded {
+        object_type: SchemaObjectType,
+        name: AttributeName,
+    },
+    AttributeDeleted {
+        object_type: SchemaObjectType,
+        name: AttributeName,
+    },
+    /// The permissions of the attribute changed.
+    AttributeUpdated {
+        object_type: SchemaObjectType,
+        name: AttributeName,
+    },
+    ObjectClassAdded {
+        object_type: SchemaObjectType,
+        name: LdapObjectClass,
+    },
+    ObjectClassDeleted {
+        object_type: SchemaObjectType,
+        name: LdapObjectClass,
+    },
+}
+
+impl DirectoryEvent {
+    /// All the values returned by `event_type`.
+    pub const EVENT_TYPES: [&'static str; 13] = [
+        "user_created",
+        "user_updated",
+        "user_deleted",
+        "group_created",
+        "group_updated",
+        "group_deleted",
+        "user_added_to_group",
+        "user_removed_from_group",
+        "attribute_added",
+        "attribute_deleted",
+        "attribute_updated",
+        "object_class_added",
+        "object_class_deleted",
+    ];
+
+    /// The name of the event, as found in the "event" field of the serialized event.
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            DirectoryEvent::UserCreated { .. } => "user_created",
+            DirectoryEvent::UserUpdated { .. } => "user_updated",
+            DirectoryEvent::UserDeleted { .. } => "user_deleted",
+            DirectoryEvent::GroupCreated { .. } => "group_created",
+            DirectoryEvent::GroupUpdated { .. } => "group_updated",
+            DirectoryEvent::GroupDeleted { .. } => "group_deleted",
+            DirectoryEvent::UserAddedToGroup { .. } => "user_added_to_group",
+            DirectoryEvent::UserRemovedFromGroup { .. } => "user_removed_from_group",
+            DirectoryEvent::AttributeAdded { .. } => "attribute_added",
+            DirectoryEvent::AttributeDeleted { .. } => "attribute_deleted",
+            DirectoryEvent::AttributeUpdated { .. } => "attribute_updated",
+            DirectoryEvent::ObjectClassAdded { .. } => "object_class_added",
+            DirectoryEvent::ObjectClassDeleted { .. } => "object_class_deleted",
+        }
+    }
+}
+
+/// Gets notified of the changes made to the directory.
+///
+/// This is awaited in the request path, right after the change is committed: implementations can
+/// store the event, but anything slow (e.g. network calls) should be handed off to another task.
+#[async_trait]
+pub trait DirectoryEventListener: Send + Sync {
+    async fn on_event(&self, event: DirectoryEvent);
+}
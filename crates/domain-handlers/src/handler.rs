@@ -1,14 +1,15 @@
 use async_trait::async_trait;
+use chrono::NaiveDateTime;
 use ldap3_proto::proto::LdapSubstringFilter;
 use lldap_domain::{
     requests::{
         CreateAttributeRequest, CreateGroupRequest, CreateUserRequest, UpdateGroupRequest,
         UpdateUserRequest,
     },
-    schema::Schema,
+    schema::{AttributePermissions, Schema},
     types::{
-        AttributeName, AttributeValue, Group, GroupDetails, GroupId, GroupName, LdapObjectClass,
-        User, UserAndGroups, UserId, Uuid,
+        AttributeName, AttributeValue, Group, GroupDetails, GroupId, GroupName, GroupOwner,
+        LdapObjectClass, User, UserAndGroups, UserId, Uuid,
     },
 };
 use lldap_domain_model::{error::Result, model::UserColumn};
@@ -68,6 +69,15 @@ impl From<LdapSubstringFilter> for SubStringFilter {
     }
 }
 
+/// How a column compares to the value of a filter, e.g. `GreaterThan` for `column > value`.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Copy)]
+pub enum ComparisonOperator {
+    LessThan,
+    LessOrEqual,
+    GreaterThan,
+    GreaterOrEqual,
+}
+
 #[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
 pub enum UserRequestFilter {
     True,
@@ -85,6 +95,9 @@ pub enum UserRequestFilter {
     // Same, by id.
     MemberOfId(GroupId),
     CustomAttributePresent(AttributeName),
+    EmailVerified(bool),
+    // Compares one of the date columns.
+    DateComparison(UserColumn, ComparisonOperator, NaiveDateTime),
 }
 
 impl From<bool> for UserRequestFilter {
@@ -93,6 +106,16 @@ impl From<bool> for UserRequestFilter {
     }
 }
 
+/// The members of a dynamic group: the users matching a filter.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub struct DynamicGroupFilter {
+    /// The filter as written by the admin, in LDAP syntax.
+    pub ldap_filter: String,
+    /// The same filter, converted for the backend. A `MemberOf` in it only matches the members
+    /// of static groups.
+    pub user_filter: UserRequestFilter,
+}
+
 #[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
 pub enum GroupRequestFilter {
     True,
@@ -106,8 +129,13 @@ pub enum GroupRequestFilter {
     GroupId(GroupId),
     // Check if the group contains a user identified by uid.
     Member(UserId),
+    // Check if the user is an owner of the group, directly or through one of their groups.
+    OwnedBy(UserId),
     AttributeEquality(AttributeName, AttributeValue),
     CustomAttributePresent(AttributeName),
+    CreationDateComparison(ComparisonOperator, NaiveDateTime),
+    ModifiedDateComparison(ComparisonOperator, NaiveDateTime),
+    GroupIdComparison(ComparisonOperator, GroupId),
 }
 
 impl From<bool> for GroupRequestFilter {
@@ -116,6 +144,86 @@ impl From<bool> for GroupRequestFilter {
     }
 }
 
+/// The fields a page of users can be sorted by.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub enum UserOrderField {
+    #[default]
+    UserId,
+    Email,
+    DisplayName,
+    CreationDate,
+}
+
+/// The fields a page of groups can be sorted by.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub enum GroupOrderField {
+    #[default]
+    DisplayName,
+    GroupId,
+    CreationDate,
+}
+
+/// Requests a page of a list sorted by `order_by`, then by id to break ties.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PageRequest<Field, Id> {
+    pub order_by: Field,
+    pub descending: bool,
+    /// Only return the entries sorted after this one. The position is looked up in the database
+    /// (keyset pagination), so entries added or removed before it don't shift the page. Fails
+    /// with a validation error if the entry no longer exists.
+    pub after: Option<Id>,
+    /// The maximum number of entries to return.
+    pub first: u64,
+}
+
+pub type UserPageRequest = PageRequest<UserOrderField, UserId>;
+pub type GroupPageRequest = PageRequest<GroupOrderField, GroupId>;
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Whether there are more entries after the last item.
+    pub has_next_page: bool,
+    /// The number of entries matching the filters, across all the pages.
+    pub total_count: u64,
+}
+
+/// A write operation, applied with the others of its batch in a single transaction.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub enum BatchOperation {
+    CreateUser(CreateUserRequest),
+    UpdateUser(UpdateUserRequest),
+    DeleteUser(UserId),
+    AddUserToGroup(UserId, GroupId),
+    /// Like `AddUserToGroup`, for a group that may be created earlier in the same batch.
+    AddUserToGroupByName(UserId, GroupName),
+    RemoveUserFromGroup(UserId, GroupId),
+    /// Adds and removes members so that the group contains exactly these users.
+    SetGroupMembers(GroupId, Vec<UserId>),
+    CreateGroup(CreateGroupRequest),
+    UpdateGroup(UpdateGroupRequest),
+    DeleteGroup(GroupId),
+}
+
+/// What happens to the rest of a batch when one of its operations fails.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub enum BatchMode {
+    /// Nothing is saved, and the operations after the failed one are not attempted.
+    #[default]
+    AllOrNothing,
+    /// Only the failed operations are rolled back.
+    ContinueOnError,
+}
+
+#[derive(Debug)]
+pub struct BatchResult {
+    /// Whether the successful operations were saved.
+    pub committed: bool,
+    /// The result of each attempted operation, in order. In `AllOrNothing` mode, the operations
+    /// after the first error are not attempted.
+    pub results: Vec<Result<()>>,
+}
+
 #[async_trait]
 pub trait LoginHandler: Send + Sync {
     async fn bind(&self, request: BindRequest) -> Result<()>;
@@ -124,6 +232,11 @@ pub trait LoginHandler: Send + Sync {
 #[async_trait]
 pub trait GroupListerBackendHandler: ReadSchemaBackendHandler {
     async fn list_groups(&self, filters: Option<GroupRequestFilter>) -> Result<Vec<Group>>;
+    async fn list_groups_page(
+        &self,
+        filters: Option<GroupRequestFilter>,
+        page: GroupPageRequest,
+    ) -> Result<Page<Group>>;
 }
 
 #[async_trait]
@@ -131,7 +244,16 @@ pub trait GroupBackendHandler: ReadSchemaBackendHandler {
     async fn get_group_details(&self, group_id: GroupId) -> Result<GroupDetails>;
     async fn update_group(&self, request: UpdateGroupRequest) -> Result<()>;
     async fn create_group(&self, request: CreateGroupRequest) -> Result<GroupId>;
+    /// Creates a group whose members are the users matching the filter. They can't be added or
+    /// removed directly.
+    async fn create_dynamic_group(
+        &self,
+        request: CreateGroupRequest,
+        filter: DynamicGroupFilter,
+    ) -> Result<GroupId>;
     async fn delete_group(&self, group_id: GroupId) -> Result<()>;
+    async fn add_group_owner(&self, group_id: GroupId, owner: GroupOwner) -> Result<()>;
+    async fn remove_group_owner(&self, group_id: GroupId, owner: GroupOwner) -> Result<()>;
 }
 
 #[async_trait]
@@ -141,6 +263,12 @@ pub trait UserListerBackendHandler: ReadSchemaBackendHandler {
         filters: Option<UserRequestFilter>,
         get_groups: bool,
     ) -> Result<Vec<UserAndGroups>>;
+    /// The users of the page come with their groups.
+    async fn list_users_page(
+        &self,
+        filters: Option<UserRequestFilter>,
+        page: UserPageRequest,
+    ) -> Result<Page<UserAndGroups>>;
 }
 
 #[async_trait]
@@ -152,6 +280,12 @@ pub trait UserBackendHandler: ReadSchemaBackendHandler {
     async fn add_user_to_group(&self, user_id: &UserId, group_id: GroupId) -> Result<()>;
     async fn remove_user_from_group(&self, user_id: &UserId, group_id: GroupId) -> Result<()>;
     async fn get_user_groups(&self, user_id: &UserId) -> Result<HashSet<GroupDetails>>;
+    /// Applies the operations in order, in a single transaction.
+    async fn apply_batch(
+        &self,
+        operations: Vec<BatchOperation>,
+        mode: BatchMode,
+    ) -> Result<BatchResult>;
 }
 
 #[async_trait]
@@ -166,6 +300,17 @@ pub trait SchemaBackendHandler: ReadSchemaBackendHandler {
     // Note: It's up to the caller to make sure that the attribute is not hardcoded.
     async fn delete_user_attribute(&self, name: &AttributeName) -> Result<()>;
     async fn delete_group_attribute(&self, name: &AttributeName) -> Result<()>;
+    /// Replaces the permissions of the attribute. The groups must exist.
+    async fn set_user_attribute_permissions(
+        &self,
+        name: &AttributeName,
+        permissions: AttributePermissions,
+    ) -> Result<()>;
+    async fn set_group_attribute_permissions(
+        &self,
+        name: &AttributeName,
+        permissions: AttributePermissions,
+    ) -> Result<()>;
 
     async fn add_user_object_class(&self, name: &LdapObjectClass) -> Result<()>;
     async fn add_group_object_class(&self, name: &LdapObjectClass) -> Result<()>;
@@ -0,0 +1,25 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use lldap_domain::types::UserId;
+
+/// One entry of the audit trail. See `AuditEvent` for the meaning of the fields.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "audit_log")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub timestamp: chrono::NaiveDateTime,
+    pub actor: Option<UserId>,
+    pub action: String,
+    pub target: Option<String>,
+    pub details: Option<String>,
+    pub source_ip: Option<String>,
+    pub interface: String,
+    pub success: bool,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,34 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use lldap_domain::types::AttributeName;
+
+/// One of the values a group attribute is restricted to.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "group_attribute_allowed_values")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub attribute_name: AttributeName,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub value: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::group_attribute_schema::Entity",
+        from = "Column::AttributeName",
+        to = "super::group_attribute_schema::Column::AttributeName",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    GroupAttributeSchema,
+}
+
+impl Related<super::group_attribute_schema::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::GroupAttributeSchema.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -2,7 +2,7 @@ use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use lldap_domain::{
-    schema::AttributeSchema,
+    schema::{AttributePermissions, AttributeSchema, AttributeValidation},
     types::{AttributeName, AttributeType},
 };
 
@@ -25,12 +25,32 @@ pub struct Model {
     pub is_group_editable: bool,
     #[sea_orm(column_name = "group_attribute_schema_is_hardcoded")]
     pub is_hardcoded: bool,
+    #[sea_orm(column_name = "group_attribute_schema_is_private")]
+    pub is_private: bool,
+    #[sea_orm(column_name = "group_attribute_schema_regex")]
+    pub regex: Option<String>,
+    #[sea_orm(column_name = "group_attribute_schema_min_length")]
+    pub min_length: Option<i64>,
+    #[sea_orm(column_name = "group_attribute_schema_max_length")]
+    pub max_length: Option<i64>,
+    #[sea_orm(column_name = "group_attribute_schema_min_value")]
+    pub min_value: Option<i64>,
+    #[sea_orm(column_name = "group_attribute_schema_max_value")]
+    pub max_value: Option<i64>,
+    #[sea_orm(column_name = "group_attribute_schema_is_unique")]
+    pub is_unique: bool,
+    #[sea_orm(column_name = "group_attribute_schema_is_required")]
+    pub is_required: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::group_attributes::Entity")]
     GroupAttributes,
+    #[sea_orm(has_many = "super::group_attribute_permissions::Entity")]
+    GroupAttributePermissions,
+    #[sea_orm(has_many = "super::group_attribute_allowed_values::Entity")]
+    GroupAttributeAllowedValues,
 }
 
 impl Related<super::GroupAttributes> for Entity {
@@ -51,6 +71,22 @@ impl From<Model> for AttributeSchema {
             is_editable: value.is_group_editable,
             is_hardcoded: value.is_hardcoded,
             is_readonly: false,
+            // The groups are stored in a separate table.
+            permissions: AttributePermissions {
+                is_private: value.is_private,
+                ..Default::default()
+            },
+            // The allowed values are stored in a separate table.
+            validation: AttributeValidation {
+                regex: value.regex,
+                min_length: value.min_length,
+                max_length: value.max_length,
+                min_value: value.min_value,
+                max_value: value.max_value,
+                allowed_values: Vec::new(),
+                is_unique: value.is_unique,
+                is_required: value.is_required,
+            },
         }
     }
 }
@@ -15,6 +15,10 @@ pub struct Model {
     pub creation_date: chrono::NaiveDateTime,
     pub uuid: Uuid,
     pub modified_date: chrono::NaiveDateTime,
+    /// For a dynamic group, the LDAP filter matching its members.
+    pub dynamic_filter: Option<String>,
+    /// The same filter, converted to a `UserRequestFilter` and serialized in JSON.
+    pub dynamic_user_filter: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -39,6 +43,8 @@ impl From<Model> for lldap_domain::types::Group {
             creation_date: group.creation_date,
             uuid: group.uuid,
             users: vec![],
+            owners: vec![],
+            dynamic_filter: group.dynamic_filter,
             attributes: Vec::new(),
             modified_date: group.modified_date,
         }
@@ -52,6 +58,7 @@ impl From<Model> for lldap_domain::types::GroupDetails {
             display_name: group.display_name,
             creation_date: group.creation_date,
             uuid: group.uuid,
+            dynamic_filter: group.dynamic_filter,
             attributes: Vec::new(),
             modified_date: group.modified_date,
         }
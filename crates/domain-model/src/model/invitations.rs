@@ -0,0 +1,27 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use lldap_domain::types::{Email, UserId};
+
+/// A single-use invitation to create an account.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "invitations")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub token: String,
+    /// The user ID imposed by the admin, if any.
+    pub user_id: Option<UserId>,
+    /// The email the invitation was sent to, if any. The new user gets this email.
+    pub email: Option<Email>,
+    /// Comma-separated IDs of the groups the new user is added to.
+    pub group_ids: String,
+    /// JSON-encoded attributes of the new user.
+    pub attributes: String,
+    pub created_by: UserId,
+    pub expiry_date: chrono::NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
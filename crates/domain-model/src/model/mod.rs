@@ -1,19 +1,29 @@
 pub mod prelude;
 
+pub mod audit_log;
 pub mod deserialize;
 pub mod groups;
+pub mod invitations;
 pub mod jwt_refresh_storage;
 pub mod jwt_storage;
 pub mod memberships;
 pub mod password_reset_tokens;
 pub mod users;
+pub mod webauthn_credentials;
+pub mod webhook_deliveries;
 
+pub mod user_attribute_allowed_values;
+pub mod user_attribute_permissions;
 pub mod user_attribute_schema;
 pub mod user_attributes;
 pub mod user_object_classes;
 
+pub mod group_attribute_allowed_values;
+pub mod group_attribute_permissions;
 pub mod group_attribute_schema;
 pub mod group_attributes;
 pub mod group_object_classes;
+pub mod group_owner_groups;
+pub mod group_owner_users;
 
 pub use prelude::*;
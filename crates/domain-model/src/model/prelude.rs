@@ -1,13 +1,25 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.10.3
 
+pub use super::audit_log::Column as AuditLogColumn;
+pub use super::audit_log::Entity as AuditLog;
+pub use super::group_attribute_allowed_values::Column as GroupAttributeAllowedValuesColumn;
+pub use super::group_attribute_allowed_values::Entity as GroupAttributeAllowedValues;
+pub use super::group_attribute_permissions::Column as GroupAttributePermissionsColumn;
+pub use super::group_attribute_permissions::Entity as GroupAttributePermissions;
 pub use super::group_attribute_schema::Column as GroupAttributeSchemaColumn;
 pub use super::group_attribute_schema::Entity as GroupAttributeSchema;
 pub use super::group_attributes::Column as GroupAttributesColumn;
 pub use super::group_attributes::Entity as GroupAttributes;
 pub use super::group_object_classes::Column as GroupObjectClassesColumn;
 pub use super::group_object_classes::Entity as GroupObjectClasses;
+pub use super::group_owner_groups::Column as GroupOwnerGroupsColumn;
+pub use super::group_owner_groups::Entity as GroupOwnerGroups;
+pub use super::group_owner_users::Column as GroupOwnerUsersColumn;
+pub use super::group_owner_users::Entity as GroupOwnerUsers;
 pub use super::groups::Column as GroupColumn;
 pub use super::groups::Entity as Group;
+pub use super::invitations::Column as InvitationsColumn;
+pub use super::invitations::Entity as Invitations;
 pub use super::jwt_refresh_storage::Column as JwtRefreshStorageColumn;
 pub use super::jwt_refresh_storage::Entity as JwtRefreshStorage;
 pub use super::jwt_storage::Column as JwtStorageColumn;
@@ -16,6 +28,10 @@ pub use super::memberships::Column as MembershipColumn;
 pub use super::memberships::Entity as Membership;
 pub use super::password_reset_tokens::Column as PasswordResetTokensColumn;
 pub use super::password_reset_tokens::Entity as PasswordResetTokens;
+pub use super::user_attribute_allowed_values::Column as UserAttributeAllowedValuesColumn;
+pub use super::user_attribute_allowed_values::Entity as UserAttributeAllowedValues;
+pub use super::user_attribute_permissions::Column as UserAttributePermissionsColumn;
+pub use super::user_attribute_permissions::Entity as UserAttributePermissions;
 pub use super::user_attribute_schema::Column as UserAttributeSchemaColumn;
 pub use super::user_attribute_schema::Entity as UserAttributeSchema;
 pub use super::user_attributes::Column as UserAttributesColumn;
@@ -24,3 +40,7 @@ pub use super::user_object_classes::Column as UserObjectClassesColumn;
 pub use super::user_object_classes::Entity as UserObjectClasses;
 pub use super::users::Column as UserColumn;
 pub use super::users::Entity as User;
+pub use super::webauthn_credentials::Column as WebauthnCredentialsColumn;
+pub use super::webauthn_credentials::Entity as WebauthnCredentials;
+pub use super::webhook_deliveries::Column as WebhookDeliveriesColumn;
+pub use super::webhook_deliveries::Entity as WebhookDeliveries;
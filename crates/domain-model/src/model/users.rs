@@ -23,6 +23,7 @@ pub struct Model {
     pub uuid: Uuid,
     pub modified_date: chrono::NaiveDateTime,
     pub password_modified_date: chrono::NaiveDateTime,
+    pub email_verified: bool,
 }
 
 impl EntityName for Entity {
@@ -44,6 +45,7 @@ pub enum Column {
     Uuid,
     ModifiedDate,
     PasswordModifiedDate,
+    EmailVerified,
 }
 
 impl ColumnTrait for Column {
@@ -62,6 +64,7 @@ impl ColumnTrait for Column {
             Column::Uuid => ColumnType::String(StringLen::N(36)),
             Column::ModifiedDate => ColumnType::DateTime,
             Column::PasswordModifiedDate => ColumnType::DateTime,
+            Column::EmailVerified => ColumnType::Boolean,
         }
         .def()
     }
@@ -77,6 +80,8 @@ pub enum Relation {
     JwtStorage,
     #[sea_orm(has_many = "super::password_reset_tokens::Entity")]
     PasswordResetTokens,
+    #[sea_orm(has_many = "super::webauthn_credentials::Entity")]
+    WebauthnCredentials,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
@@ -116,6 +121,12 @@ impl Related<super::password_reset_tokens::Entity> for Entity {
     }
 }
 
+impl Related<super::webauthn_credentials::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::WebauthnCredentials.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
 
 impl From<Model> for lldap_domain::types::User {
@@ -129,6 +140,7 @@ impl From<Model> for lldap_domain::types::User {
             attributes: Vec::new(),
             modified_date: user.modified_date,
             password_modified_date: user.password_modified_date,
+            email_verified: user.email_verified,
         }
     }
 }
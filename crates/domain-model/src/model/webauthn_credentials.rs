@@ -0,0 +1,40 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use lldap_domain::types::UserId;
+
+/// A WebAuthn authenticator (passkey) registered by a user.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "webauthn_credentials")]
+pub struct Model {
+    /// Base64url-encoded credential ID, as sent by the authenticator.
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub credential_id: String,
+    pub user_id: UserId,
+    /// Name given by the user to recognize the authenticator.
+    pub display_name: String,
+    /// The serialized (JSON) credential, including the public key and the signature counter.
+    pub credential: Vec<u8>,
+    pub creation_date: chrono::NaiveDateTime,
+    pub last_used_date: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::UserId",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Users,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Users.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,28 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A webhook call: pending ones form the retry queue, the others the delivery log.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[sea_orm(table_name = "webhook_deliveries")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub url: String,
+    pub event_type: String,
+    /// The JSON body of the request.
+    pub payload: String,
+    /// "pending", "delivered" or "failed".
+    pub status: String,
+    pub attempts: i32,
+    pub creation_date: chrono::NaiveDateTime,
+    /// When to try again, only set for pending deliveries.
+    pub next_attempt_date: Option<chrono::NaiveDateTime>,
+    pub last_attempt_date: Option<chrono::NaiveDateTime>,
+    /// The HTTP status of the last attempt, or the error that prevented it.
+    pub last_response: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -24,6 +24,8 @@ impl From<Schema> for PublicSchema {
                 is_editable: false,
                 is_hardcoded: true,
                 is_readonly: true,
+                permissions: Default::default(),
+                validation: Default::default(),
             },
             AttributeSchema {
                 name: "creation_date".into(),
@@ -33,6 +35,8 @@ impl From<Schema> for PublicSchema {
                 is_editable: false,
                 is_hardcoded: true,
                 is_readonly: true,
+                permissions: Default::default(),
+                validation: Default::default(),
             },
             AttributeSchema {
                 name: "modified_date".into(),
@@ -42,6 +46,8 @@ impl From<Schema> for PublicSchema {
                 is_editable: false,
                 is_hardcoded: true,
                 is_readonly: true,
+                permissions: Default::default(),
+                validation: Default::default(),
             },
             AttributeSchema {
                 name: "password_modified_date".into(),
@@ -51,6 +57,8 @@ impl From<Schema> for PublicSchema {
                 is_editable: false,
                 is_hardcoded: true,
                 is_readonly: true,
+                permissions: Default::default(),
+                validation: Default::default(),
             },
             AttributeSchema {
                 name: "mail".into(),
@@ -60,6 +68,8 @@ impl From<Schema> for PublicSchema {
                 is_editable: true,
                 is_hardcoded: true,
                 is_readonly: false,
+                permissions: Default::default(),
+                validation: Default::default(),
             },
             AttributeSchema {
                 name: "uuid".into(),
@@ -69,6 +79,8 @@ impl From<Schema> for PublicSchema {
                 is_editable: false,
                 is_hardcoded: true,
                 is_readonly: true,
+                permissions: Default::default(),
+                validation: Default::default(),
             },
             AttributeSchema {
                 name: "display_name".into(),
@@ -78,6 +90,8 @@ impl From<Schema> for PublicSchema {
                 is_editable: true,
                 is_hardcoded: true,
                 is_readonly: false,
+                permissions: Default::default(),
+                validation: Default::default(),
             },
         ]);
         schema
@@ -93,6 +107,8 @@ impl From<Schema> for PublicSchema {
                 is_editable: false,
                 is_hardcoded: true,
                 is_readonly: true,
+                permissions: Default::default(),
+                validation: Default::default(),
             },
             AttributeSchema {
                 name: "creation_date".into(),
@@ -102,6 +118,8 @@ impl From<Schema> for PublicSchema {
                 is_editable: false,
                 is_hardcoded: true,
                 is_readonly: true,
+                permissions: Default::default(),
+                validation: Default::default(),
             },
             AttributeSchema {
                 name: "modified_date".into(),
@@ -111,6 +129,8 @@ impl From<Schema> for PublicSchema {
                 is_editable: false,
                 is_hardcoded: true,
                 is_readonly: true,
+                permissions: Default::default(),
+                validation: Default::default(),
             },
             AttributeSchema {
                 name: "uuid".into(),
@@ -120,6 +140,8 @@ impl From<Schema> for PublicSchema {
                 is_editable: false,
                 is_hardcoded: true,
                 is_readonly: true,
+                permissions: Default::default(),
+                validation: Default::default(),
             },
             AttributeSchema {
                 name: "display_name".into(),
@@ -129,6 +151,8 @@ impl From<Schema> for PublicSchema {
                 is_editable: true,
                 is_hardcoded: true,
                 is_readonly: false,
+                permissions: Default::default(),
+                validation: Default::default(),
             },
         ]);
         schema
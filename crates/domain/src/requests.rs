@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 
+use crate::schema::AttributeValidation;
 use crate::types::{Attribute, AttributeName, AttributeType, Email, GroupId, GroupName, UserId};
 
 #[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Default)]
@@ -9,6 +10,10 @@ pub struct CreateUserRequest {
     pub email: Email,
     pub display_name: Option<String>,
     pub attributes: Vec<Attribute>,
+    /// The email was set by an admin, rather than chosen by the user: it is trusted for password
+    /// resets without a confirmation.
+    #[serde(default)]
+    pub email_verified: bool,
 }
 
 #[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Default)]
@@ -19,6 +24,9 @@ pub struct UpdateUserRequest {
     pub display_name: Option<String>,
     pub delete_attributes: Vec<AttributeName>,
     pub insert_attributes: Vec<Attribute>,
+    /// Same as for `CreateUserRequest`, for the new email if it changes.
+    #[serde(default)]
+    pub email_verified: bool,
 }
 
 #[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Default)]
@@ -42,4 +50,6 @@ pub struct CreateAttributeRequest {
     pub is_list: bool,
     pub is_visible: bool,
     pub is_editable: bool,
+    #[serde(default)]
+    pub validation: AttributeValidation,
 }
@@ -1,6 +1,12 @@
+use lldap_validation::attributes::{
+    AttributeValueRules, validate_attribute_regex, validate_attribute_value,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
-use crate::types::{AttributeName, AttributeType, LdapObjectClass};
+use crate::types::{
+    AttributeName, AttributeType, AttributeValue, Cardinality, GroupName, LdapObjectClass,
+};
 
 #[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
 pub struct Schema {
@@ -20,6 +26,120 @@ pub struct AttributeSchema {
     pub is_editable: bool,
     pub is_hardcoded: bool,
     pub is_readonly: bool,
+    #[serde(default)]
+    pub permissions: AttributePermissions,
+    #[serde(default)]
+    pub validation: AttributeValidation,
+}
+
+/// Access to an attribute beyond `is_visible` and `is_editable`, which only concern the user the
+/// attribute belongs to (or the members of the group). The admins can always read and edit it.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AttributePermissions {
+    /// Hidden from the read-only roles (`lldap_strict_readonly` and `lldap_password_manager`).
+    pub is_private: bool,
+    /// The members of these groups can read the attribute, even if it is private.
+    pub reader_groups: Vec<GroupName>,
+    /// The members of these groups can read and edit the attribute, on every user or group.
+    pub editor_groups: Vec<GroupName>,
+}
+
+impl AttributePermissions {
+    pub fn has_groups(&self) -> bool {
+        !self.reader_groups.is_empty() || !self.editor_groups.is_empty()
+    }
+
+    pub fn is_readable_by(&self, groups: &HashSet<GroupName>) -> bool {
+        self.reader_groups
+            .iter()
+            .chain(&self.editor_groups)
+            .any(|g| groups.contains(g))
+    }
+
+    pub fn is_editable_by(&self, groups: &HashSet<GroupName>) -> bool {
+        self.editor_groups.iter().any(|g| groups.contains(g))
+    }
+}
+
+/// The rules the values of an attribute must follow, on every write.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AttributeValidation {
+    /// The whole value must match this regular expression.
+    pub regex: Option<String>,
+    /// The length of the value, in characters.
+    pub min_length: Option<i64>,
+    pub max_length: Option<i64>,
+    /// The range of the value, which must be an integer.
+    pub min_value: Option<i64>,
+    pub max_value: Option<i64>,
+    /// When not empty, the value must be one of these.
+    pub allowed_values: Vec<String>,
+    /// No two users (or groups) can have the same value.
+    pub is_unique: bool,
+    /// The attribute has to be set when creating a user (or group), and can't be removed.
+    pub is_required: bool,
+}
+
+impl AttributeValidation {
+    fn has_value_rules(&self) -> bool {
+        self.regex.is_some()
+            || self.min_length.is_some()
+            || self.max_length.is_some()
+            || self.min_value.is_some()
+            || self.max_value.is_some()
+            || !self.allowed_values.is_empty()
+    }
+
+    fn value_rules(&self) -> AttributeValueRules<'_> {
+        AttributeValueRules {
+            regex: self.regex.as_deref(),
+            min_length: self.min_length,
+            max_length: self.max_length,
+            min_value: self.min_value,
+            max_value: self.max_value,
+            allowed_values: &self.allowed_values,
+        }
+    }
+
+    /// Checks that the rules can apply to an attribute of that type: only the strings and
+    /// integers have rules on their values.
+    pub fn validate_rules(&self, attribute_type: AttributeType) -> Result<(), String> {
+        if self.has_value_rules()
+            && !matches!(
+                attribute_type,
+                AttributeType::String | AttributeType::Integer
+            )
+        {
+            return Err(format!(
+                "{attribute_type:?} attributes can only be unique or required"
+            ));
+        }
+        self.regex
+            .as_deref()
+            .map(validate_attribute_regex)
+            .unwrap_or(Ok(()))
+    }
+
+    /// Checks each value of a list against the rules on the values.
+    pub fn validate_value(&self, value: &AttributeValue) -> Result<(), String> {
+        let rules = self.value_rules();
+        match value {
+            AttributeValue::String(Cardinality::Singleton(s)) => {
+                validate_attribute_value(s, &rules)
+            }
+            AttributeValue::String(Cardinality::Unbounded(l)) => l
+                .iter()
+                .try_for_each(|s| validate_attribute_value(s, &rules)),
+            AttributeValue::Integer(Cardinality::Singleton(i)) => {
+                validate_attribute_value(&i.to_string(), &rules)
+            }
+            AttributeValue::Integer(Cardinality::Unbounded(l)) => l
+                .iter()
+                .try_for_each(|i| validate_attribute_value(&i.to_string(), &rules)),
+            AttributeValue::JpegPhoto(_) | AttributeValue::DateTime(_) => Ok(()),
+        }
+    }
 }
 
 #[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
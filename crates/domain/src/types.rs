@@ -542,6 +542,8 @@ pub struct User {
     pub attributes: Vec<Attribute>,
     pub modified_date: NaiveDateTime,
     pub password_modified_date: NaiveDateTime,
+    /// Whether the current email was confirmed through a link sent to it.
+    pub email_verified: bool,
 }
 
 #[cfg(feature = "test")]
@@ -557,6 +559,7 @@ impl Default for User {
             attributes: Vec::new(),
             modified_date: epoch,
             password_modified_date: epoch,
+            email_verified: false,
         }
     }
 }
@@ -651,16 +654,28 @@ pub struct Group {
     pub creation_date: NaiveDateTime,
     pub uuid: Uuid,
     pub users: Vec<UserId>,
+    pub owners: Vec<GroupOwner>,
+    /// For a dynamic group, the LDAP filter matching its members.
+    pub dynamic_filter: Option<String>,
     pub attributes: Vec<Attribute>,
     pub modified_date: NaiveDateTime,
 }
 
+/// A user or the members of a group, allowed to manage the members of a group.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Serialize, Deserialize, Hash)]
+pub enum GroupOwner {
+    User(UserId),
+    Group(GroupName),
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct GroupDetails {
     pub group_id: GroupId,
     pub display_name: GroupName,
     pub creation_date: NaiveDateTime,
     pub uuid: Uuid,
+    /// For a dynamic group, the LDAP filter matching its members.
+    pub dynamic_filter: Option<String>,
     pub attributes: Vec<Attribute>,
     pub modified_date: NaiveDateTime,
 }
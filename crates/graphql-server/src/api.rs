@@ -1,16 +1,24 @@
-use crate::{mutation::Mutation, query::Query};
-use juniper::{EmptySubscription, FieldError, RootNode};
+use crate::{mutation::Mutation, query::Query, subscription::Subscription};
+use juniper::{FieldError, RootNode};
 use lldap_access_control::{
-    AccessControlledBackendHandler, AdminBackendHandler, ReadonlyBackendHandler,
-    UserReadableBackendHandler, UserWriteableBackendHandler,
+    AccessControlledBackendHandler, AdminBackendHandler, AttributeEditorBackendHandler,
+    OwnedGroupBackendHandler, ReadonlyBackendHandler, UserReadableBackendHandler,
 };
 use lldap_auth::{access_control::ValidationResults, types::UserId};
+use lldap_domain::types::GroupId;
 use lldap_domain_handlers::handler::BackendHandler;
+use lldap_domain_model::error::Result;
+use lldap_ldap::LdapInfo;
 use tracing::debug;
 
 pub struct Context<Handler: BackendHandler> {
     pub handler: AccessControlledBackendHandler<Handler>,
     pub validation_result: ValidationResults,
+    /// Used to parse the filters of the dynamic groups.
+    pub ldap_info: &'static LdapInfo,
+    /// When set, the users cannot change their own email directly: the new address has to be
+    /// confirmed through a link sent to it.
+    pub confirm_email_changes: bool,
 }
 
 pub fn field_error_callback<'a>(
@@ -29,10 +37,14 @@ impl<Handler: BackendHandler> Context<Handler> {
         Self {
             handler: AccessControlledBackendHandler::new(handler),
             validation_result,
+            ldap_info: Box::leak(Box::new(
+                LdapInfo::new("dc=example,dc=com", vec![], vec![]).unwrap(),
+            )),
+            confirm_email_changes: false,
         }
     }
 
-    pub fn get_admin_handler(&self) -> Option<&(impl AdminBackendHandler + use<Handler>)> {
+    pub fn get_admin_handler(&self) -> Option<impl AdminBackendHandler + use<'_, Handler>> {
         self.handler.get_admin_handler(&self.validation_result)
     }
 
@@ -40,33 +52,42 @@ impl<Handler: BackendHandler> Context<Handler> {
         self.handler.get_readonly_handler(&self.validation_result)
     }
 
-    pub fn get_writeable_handler(
+    pub fn get_readable_handler(
         &self,
         user_id: &UserId,
-    ) -> Option<&(impl UserWriteableBackendHandler + use<Handler>)> {
+    ) -> Option<&(impl UserReadableBackendHandler + use<Handler>)> {
         self.handler
-            .get_writeable_handler(&self.validation_result, user_id)
+            .get_readable_handler(&self.validation_result, user_id)
     }
 
-    pub fn get_readable_handler(
+    pub async fn get_owned_group_handler(
         &self,
-        user_id: &UserId,
-    ) -> Option<&(impl UserReadableBackendHandler + use<Handler>)> {
+        group_id: GroupId,
+    ) -> Result<Option<impl OwnedGroupBackendHandler + use<'_, Handler>>> {
         self.handler
-            .get_readable_handler(&self.validation_result, user_id)
+            .get_owned_group_handler(&self.validation_result, group_id)
+            .await
+    }
+
+    pub async fn get_attribute_editor_handler(
+        &self,
+    ) -> Result<impl AttributeEditorBackendHandler + use<'_, Handler>> {
+        self.handler
+            .get_attribute_editor_handler(&self.validation_result)
+            .await
     }
 }
 
 impl<Handler: BackendHandler> juniper::Context for Context<Handler> {}
 
-type Schema<Handler> =
-    RootNode<'static, Query<Handler>, Mutation<Handler>, EmptySubscription<Context<Handler>>>;
+pub type Schema<Handler> =
+    RootNode<'static, Query<Handler>, Mutation<Handler>, Subscription<Handler>>;
 
 pub fn schema<Handler: BackendHandler>() -> Schema<Handler> {
     Schema::new(
         Query::<Handler>::new(),
         Mutation::<Handler>::new(),
-        EmptySubscription::<Context<Handler>>::new(),
+        Subscription::<Handler>::new(),
     )
 }
 
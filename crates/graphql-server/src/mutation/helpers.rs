@@ -4,15 +4,24 @@ use lldap_access_control::{AdminBackendHandler, ReadonlyBackendHandler};
 use lldap_domain::{
     deserialize::deserialize_attribute_value,
     public_schema::PublicSchema,
-    requests::CreateGroupRequest,
-    schema::AttributeList,
-    types::{Attribute as DomainAttribute, AttributeName, Email},
+    requests::{CreateGroupRequest, CreateUserRequest, UpdateGroupRequest, UpdateUserRequest},
+    schema::{AttributeList, AttributePermissions, AttributeSchema, AttributeValidation},
+    types::{
+        Attribute as DomainAttribute, AttributeName, AttributeType, Email, GroupId, GroupOwner,
+        UserId,
+    },
 };
-use lldap_domain_handlers::handler::{BackendHandler, ReadSchemaBackendHandler};
+use lldap_domain_handlers::handler::{
+    BackendHandler, BatchMode, BatchOperation, DynamicGroupFilter, ReadSchemaBackendHandler,
+};
+use lldap_ldap::parse_user_filter;
 use std::{collections::BTreeMap, sync::Arc};
 use tracing::{Instrument, Span};
 
-use super::inputs::AttributeValue;
+use super::inputs::{
+    AttributeValidationInput, AttributeValue, BatchItemResult, BatchResult, CreateUserInput,
+    UpdateUserInput,
+};
 use crate::api::{Context, field_error_callback};
 
 pub struct UnpackedAttributes {
@@ -24,13 +33,12 @@ pub struct UnpackedAttributes {
 pub fn unpack_attributes(
     attributes: Vec<AttributeValue>,
     schema: &PublicSchema,
-    is_admin: bool,
 ) -> FieldResult<UnpackedAttributes> {
     let email = attributes
         .iter()
         .find(|attr| attr.name == "mail")
         .cloned()
-        .map(|attr| deserialize_attribute(&schema.get_schema().user_attributes, attr, is_admin))
+        .map(|attr| deserialize_attribute(&schema.get_schema().user_attributes, attr))
         .transpose()?
         .map(|attr| attr.value.into_string().unwrap())
         .map(Email::from);
@@ -38,13 +46,13 @@ pub fn unpack_attributes(
         .iter()
         .find(|attr| attr.name == "display_name")
         .cloned()
-        .map(|attr| deserialize_attribute(&schema.get_schema().user_attributes, attr, is_admin))
+        .map(|attr| deserialize_attribute(&schema.get_schema().user_attributes, attr))
         .transpose()?
         .map(|attr| attr.value.into_string().unwrap());
     let attributes = attributes
         .into_iter()
         .filter(|attr| attr.name != "mail" && attr.name != "display_name")
-        .map(|attr| deserialize_attribute(&schema.get_schema().user_attributes, attr, is_admin))
+        .map(|attr| deserialize_attribute(&schema.get_schema().user_attributes, attr))
         .collect::<Result<Vec<_>, _>>()?;
     Ok(UnpackedAttributes {
         email,
@@ -99,6 +107,158 @@ pub fn consolidate_attributes(
     provided_attributes.into_values().collect()
 }
 
+pub fn make_create_user_request(
+    user: CreateUserInput,
+    schema: &PublicSchema,
+) -> FieldResult<CreateUserRequest> {
+    let consolidated_attributes = consolidate_attributes(
+        user.attributes.unwrap_or_default(),
+        user.first_name,
+        user.last_name,
+        user.avatar,
+    );
+    let UnpackedAttributes {
+        email,
+        display_name,
+        attributes,
+    } = unpack_attributes(consolidated_attributes, schema)?;
+    Ok(CreateUserRequest {
+        user_id: UserId::new(&user.id),
+        email: user
+            .email
+            .map(Email::from)
+            .or(email)
+            .ok_or_else(|| anyhow!("Email is required when creating a new user"))?,
+        display_name: user.display_name.or(display_name),
+        attributes,
+        // Only admins create users.
+        email_verified: true,
+    })
+}
+
+/// With `set_by_admin`, a new email doesn't need to be confirmed.
+pub fn make_update_user_request(
+    user: UpdateUserInput,
+    schema: &PublicSchema,
+    set_by_admin: bool,
+) -> FieldResult<UpdateUserRequest> {
+    // Consolidate attributes and fields into a combined attribute list
+    let consolidated_attributes = consolidate_attributes(
+        user.insert_attributes.unwrap_or_default(),
+        user.first_name,
+        user.last_name,
+        user.avatar,
+    );
+    // Extract any empty attributes into a list of attributes for deletion
+    let (delete_attrs, insert_attrs): (Vec<_>, Vec<_>) = consolidated_attributes
+        .into_iter()
+        .partition(|a| a.value == vec!["".to_string()]);
+    // Combine lists of attributes for removal
+    let mut delete_attributes: Vec<String> =
+        delete_attrs.iter().map(|a| a.name.to_owned()).collect();
+    delete_attributes.extend(user.remove_attributes.unwrap_or_default());
+    // Unpack attributes for update
+    let UnpackedAttributes {
+        email,
+        display_name,
+        attributes: insert_attributes,
+    } = unpack_attributes(insert_attrs, schema)?;
+    let display_name = display_name.or_else(|| {
+        // If the display name is not inserted, but removed, reset it.
+        delete_attributes
+            .iter()
+            .find(|attr| *attr == "display_name")
+            .map(|_| String::new())
+    });
+    Ok(UpdateUserRequest {
+        user_id: UserId::new(&user.id),
+        email: user.email.map(Into::into).or(email),
+        display_name: user.display_name.or(display_name),
+        delete_attributes: delete_attributes
+            .into_iter()
+            .filter(|attr| attr != "mail" && attr != "display_name")
+            .map(Into::into)
+            .collect(),
+        insert_attributes,
+        email_verified: set_by_admin,
+    })
+}
+
+/// Sends the valid operations to the backend as one batch, and reports the outcome of each
+/// item, including the ones that were rejected before reaching the backend. With
+/// `all_or_nothing`, nothing is sent if any item is invalid.
+pub async fn run_batch(
+    handler: &impl AdminBackendHandler,
+    operations: Vec<FieldResult<BatchOperation>>,
+    all_or_nothing: bool,
+    span: Span,
+) -> FieldResult<BatchResult> {
+    let mut errors: Vec<Option<String>> = vec![None; operations.len()];
+    let mut valid_indices = Vec::new();
+    let mut valid_operations = Vec::new();
+    for (index, operation) in operations.into_iter().enumerate() {
+        match operation {
+            Ok(operation) => {
+                valid_indices.push(index);
+                valid_operations.push(operation);
+            }
+            Err(e) => errors[index] = Some(e.message().to_owned()),
+        }
+    }
+    let has_invalid_items = valid_operations.len() < errors.len();
+    let committed = if all_or_nothing && has_invalid_items {
+        false
+    } else {
+        let mode = if all_or_nothing {
+            BatchMode::AllOrNothing
+        } else {
+            BatchMode::ContinueOnError
+        };
+        let result = handler
+            .apply_batch(valid_operations, mode)
+            .instrument(span)
+            .await?;
+        for (index, item_result) in valid_indices.iter().zip(result.results) {
+            if let Err(e) = item_result {
+                errors[*index] = Some(e.to_string());
+            }
+        }
+        result.committed
+    };
+    Ok(BatchResult {
+        committed,
+        results: errors
+            .into_iter()
+            .enumerate()
+            .map(|(index, error)| BatchItemResult {
+                index: index as i32,
+                ok: committed && error.is_none(),
+                error: error.or_else(|| {
+                    (!committed).then(|| "Not saved because of another error".to_owned())
+                }),
+            })
+            .collect(),
+    })
+}
+
+/// The owner of a group given either as a user, or as a group by ID.
+pub async fn get_group_owner(
+    handler: &impl AdminBackendHandler,
+    user_id: Option<String>,
+    owner_group_id: Option<i32>,
+) -> FieldResult<GroupOwner> {
+    match (user_id, owner_group_id) {
+        (Some(user_id), None) => Ok(GroupOwner::User(UserId::new(&user_id))),
+        (None, Some(owner_group_id)) => Ok(GroupOwner::Group(
+            handler
+                .get_group_details(GroupId(owner_group_id))
+                .await?
+                .display_name,
+        )),
+        _ => Err("Exactly one of userId and ownerGroupId must be given".into()),
+    }
+}
+
 pub async fn create_group_with_details<Handler: BackendHandler>(
     context: &Context<Handler>,
     request: super::inputs::CreateGroupInput,
@@ -113,21 +273,146 @@ pub async fn create_group_with_details<Handler: BackendHandler>(
         .attributes
         .unwrap_or_default()
         .into_iter()
-        .map(|attr| deserialize_attribute(&public_schema.get_schema().group_attributes, attr, true))
+        .map(|attr| deserialize_attribute(&public_schema.get_schema().group_attributes, attr))
         .collect::<Result<Vec<_>, _>>()?;
+    let dynamic_filter = request.dynamic_filter;
     let request = CreateGroupRequest {
         display_name: request.display_name.into(),
         attributes,
     };
-    let group_id = handler.create_group(request).await?;
+    let group_id = match dynamic_filter {
+        Some(ldap_filter) => {
+            let user_filter = parse_user_filter(context.ldap_info, &ldap_filter, &public_schema)?;
+            handler
+                .create_dynamic_group(
+                    request,
+                    DynamicGroupFilter {
+                        ldap_filter,
+                        user_filter,
+                    },
+                )
+                .await?
+        }
+        None => handler.create_group(request).await?,
+    };
     let group_details = handler.get_group_details(group_id).instrument(span).await?;
     crate::query::Group::<Handler>::from_group_details(group_details, Arc::new(public_schema))
 }
 
+/// Checks that the attributes changed by a request can be edited. The permissions are checked
+/// separately from `deserialize_attribute`, since they also apply to the removed attributes.
+pub fn check_can_edit_attributes<'a>(
+    attribute_list: &AttributeList,
+    names: impl IntoIterator<Item = &'a AttributeName>,
+    can_edit: impl Fn(&AttributeSchema) -> bool,
+) -> FieldResult<()> {
+    match names.into_iter().find(|name| {
+        !attribute_list
+            .get_attribute_schema(name)
+            .is_some_and(&can_edit)
+    }) {
+        Some(name) => Err(anyhow!("Permission denied: Attribute {name} is not editable").into()),
+        None => Ok(()),
+    }
+}
+
+pub fn get_changed_user_attributes(request: &UpdateUserRequest) -> Vec<AttributeName> {
+    let mut names = Vec::new();
+    if request.email.is_some() {
+        names.push("mail".into());
+    }
+    if request.display_name.is_some() {
+        names.push("display_name".into());
+    }
+    names.extend(request.delete_attributes.iter().cloned());
+    names.extend(request.insert_attributes.iter().map(|a| a.name.clone()));
+    names
+}
+
+pub fn get_changed_group_attributes(request: &UpdateGroupRequest) -> Vec<AttributeName> {
+    let mut names = Vec::new();
+    if request.display_name.is_some() {
+        names.push("display_name".into());
+    }
+    names.extend(request.delete_attributes.iter().cloned());
+    names.extend(request.insert_attributes.iter().map(|a| a.name.clone()));
+    names
+}
+
+/// The permissions of an attribute, with the groups given by ID.
+pub async fn get_attribute_permissions(
+    handler: &impl AdminBackendHandler,
+    is_private: bool,
+    reader_group_ids: Vec<i32>,
+    editor_group_ids: Vec<i32>,
+) -> FieldResult<AttributePermissions> {
+    let mut reader_groups = Vec::new();
+    for group_id in reader_group_ids {
+        reader_groups.push(
+            handler
+                .get_group_details(GroupId(group_id))
+                .await?
+                .display_name,
+        );
+    }
+    let mut editor_groups = Vec::new();
+    for group_id in editor_group_ids {
+        editor_groups.push(
+            handler
+                .get_group_details(GroupId(group_id))
+                .await?
+                .display_name,
+        );
+    }
+    Ok(AttributePermissions {
+        is_private,
+        reader_groups,
+        editor_groups,
+    })
+}
+
+/// Converts the validation rules of a new attribute, checking that they make sense for its type.
+pub fn make_attribute_validation(
+    input: Option<AttributeValidationInput>,
+    attribute_type: AttributeType,
+) -> FieldResult<AttributeValidation> {
+    let input = input.unwrap_or_default();
+    let parse_length = |length: Option<i32>, field: &str| {
+        length
+            .map(|l| {
+                u32::try_from(l)
+                    .map(i64::from)
+                    .map_err(|_| anyhow!("Invalid {field}: {l} is negative"))
+            })
+            .transpose()
+    };
+    let parse_value = |value: Option<String>, field: &str| {
+        value
+            .map(|v| {
+                v.parse::<i64>()
+                    .with_context(|| format!("Invalid {field}: {v} is not an integer"))
+            })
+            .transpose()
+    };
+    let validation = AttributeValidation {
+        regex: input.regex,
+        min_length: parse_length(input.min_length, "minLength")?,
+        max_length: parse_length(input.max_length, "maxLength")?,
+        min_value: parse_value(input.min_value, "minValue")?,
+        max_value: parse_value(input.max_value, "maxValue")?,
+        allowed_values: input.allowed_values.unwrap_or_default(),
+        is_unique: input.is_unique.unwrap_or(false),
+        is_required: input.is_required.unwrap_or(false),
+    };
+    validation
+        .validate_rules(attribute_type)
+        .map_err(|e| anyhow!("Invalid validation rules: {e}"))?;
+    Ok(validation)
+}
+
 pub fn deserialize_attribute(
     attribute_schema: &AttributeList,
     attribute: AttributeValue,
-    is_admin: bool,
 ) -> FieldResult<DomainAttribute> {
     let attribute_name = AttributeName::from(attribute.name.as_str());
     let attribute_schema = attribute_schema
@@ -140,13 +425,6 @@ pub fn deserialize_attribute(
         )
         .into());
     }
-    if !is_admin && !attribute_schema.is_editable {
-        return Err(anyhow!(
-            "Permission denied: Attribute {} is not editable by regular users",
-            attribute.name
-        )
-        .into());
-    }
     let deserialized_values = deserialize_attribute_value(
         &attribute.value,
         attribute_schema.attribute_type,
@@ -41,6 +41,8 @@ pub struct CreateGroupInput {
     pub display_name: String,
     /// User-defined attributes.
     pub attributes: Option<Vec<AttributeValue>>,
+    /// If set, creates a dynamic group whose members are the users matching this LDAP filter.
+    pub dynamic_filter: Option<String>,
 }
 
 #[derive(PartialEq, Eq, Debug, GraphQLInputObject)]
@@ -81,6 +83,27 @@ pub struct UpdateGroupInput {
     pub insert_attributes: Option<Vec<AttributeValue>>,
 }
 
+#[derive(PartialEq, Eq, Debug, Default, GraphQLInputObject)]
+/// The rules the values of a new attribute must follow. Only the string and integer attributes
+/// can have rules on their values.
+pub struct AttributeValidationInput {
+    /// The whole value must match this regular expression.
+    pub regex: Option<String>,
+    /// The length of the value, in characters.
+    pub min_length: Option<i32>,
+    pub max_length: Option<i32>,
+    /// The range of the value, which must be an integer. Integers (signed 64 bits) are
+    /// represented as strings.
+    pub min_value: Option<String>,
+    pub max_value: Option<String>,
+    /// When set, the value must be one of these.
+    pub allowed_values: Option<Vec<String>>,
+    /// No two users (or groups) can have the same value.
+    pub is_unique: Option<bool>,
+    /// The attribute has to be set when creating a user (or group), and can't be removed.
+    pub is_required: Option<bool>,
+}
+
 #[derive(PartialEq, Eq, Debug, GraphQLObject)]
 pub struct Success {
     ok: bool,
@@ -97,3 +120,22 @@ impl Default for Success {
         Self::new()
     }
 }
+
+#[derive(PartialEq, Eq, Debug, GraphQLObject)]
+/// The outcome of one item of a batch mutation.
+pub struct BatchItemResult {
+    /// The position of the item in the input list.
+    pub index: i32,
+    /// Whether the change was saved.
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+#[derive(PartialEq, Eq, Debug, GraphQLObject)]
+/// The outcome of a batch mutation, with one result per input item.
+pub struct BatchResult {
+    /// Whether the successful items were saved. With `allOrNothing`, nothing is saved if any
+    /// item fails.
+    pub committed: bool,
+    pub results: Vec<BatchItemResult>,
+}
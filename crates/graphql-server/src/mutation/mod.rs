@@ -3,27 +3,32 @@ pub mod inputs;
 
 // Re-export public types
 pub use inputs::{
-    AttributeValue, CreateGroupInput, CreateUserInput, Success, UpdateGroupInput, UpdateUserInput,
+    AttributeValidationInput, AttributeValue, BatchItemResult, BatchResult, CreateGroupInput,
+    CreateUserInput, Success, UpdateGroupInput, UpdateUserInput,
 };
 
 use crate::api::{Context, field_error_callback};
 use anyhow::anyhow;
 use juniper::{FieldError, FieldResult, graphql_object};
 use lldap_access_control::{
-    AdminBackendHandler, UserReadableBackendHandler, UserWriteableBackendHandler,
+    AdminBackendHandler, AttributeEditorBackendHandler, OwnedGroupBackendHandler,
+    UserReadableBackendHandler,
 };
 use lldap_domain::{
-    requests::{CreateAttributeRequest, CreateUserRequest, UpdateGroupRequest, UpdateUserRequest},
-    types::{AttributeName, AttributeType, Email, GroupId, LdapObjectClass, UserId},
+    public_schema::PublicSchema,
+    requests::{CreateAttributeRequest, UpdateGroupRequest},
+    types::{AttributeName, AttributeType, GroupId, LdapObjectClass, UserId},
 };
-use lldap_domain_handlers::handler::BackendHandler;
+use lldap_domain_handlers::handler::{BackendHandler, BatchMode, BatchOperation};
 use lldap_validation::attributes::{ALLOWED_CHARACTERS_DESCRIPTION, validate_attribute_name};
 use std::sync::Arc;
 use tracing::{Instrument, debug, debug_span};
 
 use helpers::{
-    UnpackedAttributes, consolidate_attributes, create_group_with_details, deserialize_attribute,
-    unpack_attributes,
+    check_can_edit_attributes, create_group_with_details, deserialize_attribute,
+    get_attribute_permissions, get_changed_group_attributes, get_changed_user_attributes,
+    get_group_owner, make_attribute_validation, make_create_user_request, make_update_user_request,
+    run_batch,
 };
 
 #[derive(PartialEq, Eq, Debug)]
@@ -58,30 +63,11 @@ impl<Handler: BackendHandler> Mutation<Handler> {
         let handler = context
             .get_admin_handler()
             .ok_or_else(field_error_callback(&span, "Unauthorized user creation"))?;
-        let user_id = UserId::new(&user.id);
         let schema = handler.get_schema().await?;
-        let consolidated_attributes = consolidate_attributes(
-            user.attributes.unwrap_or_default(),
-            user.first_name,
-            user.last_name,
-            user.avatar,
-        );
-        let UnpackedAttributes {
-            email,
-            display_name,
-            attributes,
-        } = unpack_attributes(consolidated_attributes, &schema, true)?;
+        let request = make_create_user_request(user, &schema)?;
+        let user_id = request.user_id.clone();
         handler
-            .create_user(CreateUserRequest {
-                user_id: user_id.clone(),
-                email: user
-                    .email
-                    .map(Email::from)
-                    .or(email)
-                    .ok_or_else(|| anyhow!("Email is required when creating a new user"))?,
-                display_name: user.display_name.or(display_name),
-                attributes,
-            })
+            .create_user(request)
             .instrument(span.clone())
             .await?;
         let user_details = handler.get_user_details(&user_id).instrument(span).await?;
@@ -101,6 +87,7 @@ impl<Handler: BackendHandler> Mutation<Handler> {
             CreateGroupInput {
                 display_name: name,
                 attributes: Some(Vec::new()),
+                dynamic_filter: None,
             },
             span,
         )
@@ -127,52 +114,31 @@ impl<Handler: BackendHandler> Mutation<Handler> {
         });
         let user_id = UserId::new(&user.id);
         let handler = context
-            .get_writeable_handler(&user_id)
-            .ok_or_else(field_error_callback(&span, "Unauthorized user update"))?;
-        let is_admin = context.validation_result.is_admin();
-        let schema = handler.get_schema().await?;
-        // Consolidate attributes and fields into a combined attribute list
-        let consolidated_attributes = consolidate_attributes(
-            user.insert_attributes.unwrap_or_default(),
-            user.first_name,
-            user.last_name,
-            user.avatar,
-        );
-        // Extract any empty attributes into a list of attributes for deletion
-        let (delete_attrs, insert_attrs): (Vec<_>, Vec<_>) = consolidated_attributes
-            .into_iter()
-            .partition(|a| a.value == vec!["".to_string()]);
-        // Combine lists of attributes for removal
-        let mut delete_attributes: Vec<String> =
-            delete_attrs.iter().map(|a| a.name.to_owned()).collect();
-        delete_attributes.extend(user.remove_attributes.unwrap_or_default());
-        // Unpack attributes for update
-        let UnpackedAttributes {
-            email,
-            display_name,
-            attributes: insert_attributes,
-        } = unpack_attributes(insert_attrs, &schema, is_admin)?;
-        let display_name = display_name.or_else(|| {
-            // If the display name is not inserted, but removed, reset it.
-            delete_attributes
-                .iter()
-                .find(|attr| *attr == "display_name")
-                .map(|_| String::new())
-        });
-        handler
-            .update_user(UpdateUserRequest {
-                user_id,
-                email: user.email.map(Into::into).or(email),
-                display_name: user.display_name.or(display_name),
-                delete_attributes: delete_attributes
-                    .into_iter()
-                    .filter(|attr| attr != "mail" && attr != "display_name")
-                    .map(Into::into)
-                    .collect(),
-                insert_attributes,
-            })
-            .instrument(span)
+            .get_attribute_editor_handler()
+            .instrument(span.clone())
             .await?;
+        let schema = PublicSchema::from(
+            lldap_domain_handlers::handler::ReadSchemaBackendHandler::get_schema(&handler).await?,
+        );
+        let user_attributes = &schema.get_schema().user_attributes;
+        let can_edit = |attribute: &_| handler.can_edit_user_attribute(attribute, &user_id);
+        if !user_attributes.attributes.iter().any(can_edit) {
+            return Err(field_error_callback(&span, "Unauthorized user update")());
+        }
+        let request =
+            make_update_user_request(user, &schema, context.validation_result.is_admin())?;
+        if !context.validation_result.is_admin() {
+            check_can_edit_attributes(
+                user_attributes,
+                &get_changed_user_attributes(&request),
+                can_edit,
+            )?;
+            if context.confirm_email_changes && request.email.is_some() {
+                span.in_scope(|| debug!("Email change without confirmation"));
+                return Err("The new email address has to be confirmed through a link".into());
+            }
+        }
+        handler.update_user(request).instrument(span).await?;
         Ok(Success::new())
     }
 
@@ -185,8 +151,17 @@ impl<Handler: BackendHandler> Mutation<Handler> {
             debug!(?group.id);
         });
         let handler = context
-            .get_admin_handler()
-            .ok_or_else(field_error_callback(&span, "Unauthorized group update"))?;
+            .get_attribute_editor_handler()
+            .instrument(span.clone())
+            .await?;
+        let schema = PublicSchema::from(
+            lldap_domain_handlers::handler::ReadSchemaBackendHandler::get_schema(&handler).await?,
+        );
+        let group_attributes = &schema.get_schema().group_attributes;
+        let can_edit = |attribute: &_| handler.can_edit_group_attribute(attribute);
+        if !group_attributes.attributes.iter().any(can_edit) {
+            return Err(field_error_callback(&span, "Unauthorized group update")());
+        }
         let new_display_name = group.display_name.clone().or_else(|| {
             group.insert_attributes.as_ref().and_then(|a| {
                 a.iter()
@@ -198,29 +173,33 @@ impl<Handler: BackendHandler> Mutation<Handler> {
             span.in_scope(|| debug!("Cannot change lldap_admin group name"));
             return Err("Cannot change lldap_admin group name".into());
         }
-        let schema = handler.get_schema().await?;
         let insert_attributes = group
             .insert_attributes
             .unwrap_or_default()
             .into_iter()
             .filter(|attr| attr.name != "display_name")
-            .map(|attr| deserialize_attribute(&schema.get_schema().group_attributes, attr, true))
+            .map(|attr| deserialize_attribute(group_attributes, attr))
             .collect::<Result<Vec<_>, _>>()?;
-        handler
-            .update_group(UpdateGroupRequest {
-                group_id: GroupId(group.id),
-                display_name: new_display_name.map(|s| s.as_str().into()),
-                delete_attributes: group
-                    .remove_attributes
-                    .unwrap_or_default()
-                    .into_iter()
-                    .filter(|attr| attr != "display_name")
-                    .map(Into::into)
-                    .collect(),
-                insert_attributes,
-            })
-            .instrument(span)
-            .await?;
+        let request = UpdateGroupRequest {
+            group_id: GroupId(group.id),
+            display_name: new_display_name.map(|s| s.as_str().into()),
+            delete_attributes: group
+                .remove_attributes
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|attr| attr != "display_name")
+                .map(Into::into)
+                .collect(),
+            insert_attributes,
+        };
+        if !context.validation_result.is_admin() {
+            check_can_edit_attributes(
+                group_attributes,
+                &get_changed_group_attributes(&request),
+                can_edit,
+            )?;
+        }
+        handler.update_group(request).instrument(span).await?;
         Ok(Success::new())
     }
 
@@ -234,13 +213,15 @@ impl<Handler: BackendHandler> Mutation<Handler> {
             debug!(?user_id, ?group_id);
         });
         let handler = context
-            .get_admin_handler()
+            .get_owned_group_handler(GroupId(group_id))
+            .instrument(span.clone())
+            .await?
             .ok_or_else(field_error_callback(
                 &span,
                 "Unauthorized group membership modification",
             ))?;
         handler
-            .add_user_to_group(&UserId::new(&user_id), GroupId(group_id))
+            .add_member(&UserId::new(&user_id))
             .instrument(span)
             .await?;
         Ok(Success::new())
@@ -256,7 +237,9 @@ impl<Handler: BackendHandler> Mutation<Handler> {
             debug!(?user_id, ?group_id);
         });
         let handler = context
-            .get_admin_handler()
+            .get_owned_group_handler(GroupId(group_id))
+            .instrument(span.clone())
+            .await?
             .ok_or_else(field_error_callback(
                 &span,
                 "Unauthorized group membership modification",
@@ -266,8 +249,60 @@ impl<Handler: BackendHandler> Mutation<Handler> {
             span.in_scope(|| debug!("Cannot remove admin rights for current user"));
             return Err("Cannot remove admin rights for current user".into());
         }
+        handler.remove_member(&user_id).instrument(span).await?;
+        Ok(Success::new())
+    }
+
+    /// Allows a user, or the members of a group, to manage the members of the group. Exactly one
+    /// of `userId` and `ownerGroupId` must be given.
+    async fn add_group_owner(
+        context: &Context<Handler>,
+        group_id: i32,
+        user_id: Option<String>,
+        owner_group_id: Option<i32>,
+    ) -> FieldResult<Success> {
+        let span = debug_span!("[GraphQL mutation] add_group_owner");
+        span.in_scope(|| {
+            debug!(?group_id, ?user_id, ?owner_group_id);
+        });
+        let handler = context
+            .get_admin_handler()
+            .ok_or_else(field_error_callback(
+                &span,
+                "Unauthorized group owner modification",
+            ))?;
+        let owner = get_group_owner(&handler, user_id, owner_group_id)
+            .instrument(span.clone())
+            .await?;
+        handler
+            .add_group_owner(GroupId(group_id), owner)
+            .instrument(span)
+            .await?;
+        Ok(Success::new())
+    }
+
+    /// Exactly one of `userId` and `ownerGroupId` must be given.
+    async fn remove_group_owner(
+        context: &Context<Handler>,
+        group_id: i32,
+        user_id: Option<String>,
+        owner_group_id: Option<i32>,
+    ) -> FieldResult<Success> {
+        let span = debug_span!("[GraphQL mutation] remove_group_owner");
+        span.in_scope(|| {
+            debug!(?group_id, ?user_id, ?owner_group_id);
+        });
+        let handler = context
+            .get_admin_handler()
+            .ok_or_else(field_error_callback(
+                &span,
+                "Unauthorized group owner modification",
+            ))?;
+        let owner = get_group_owner(&handler, user_id, owner_group_id)
+            .instrument(span.clone())
+            .await?;
         handler
-            .remove_user_from_group(&user_id, GroupId(group_id))
+            .remove_group_owner(GroupId(group_id), owner)
             .instrument(span)
             .await?;
         Ok(Success::new())
@@ -290,6 +325,147 @@ impl<Handler: BackendHandler> Mutation<Handler> {
         Ok(Success::new())
     }
 
+    /// Creates all the users in a single transaction. Without `allOrNothing: false`, no user is
+    /// created if any of them fails.
+    async fn create_users(
+        context: &Context<Handler>,
+        users: Vec<CreateUserInput>,
+        all_or_nothing: Option<bool>,
+    ) -> FieldResult<BatchResult> {
+        let span = debug_span!("[GraphQL mutation] create_users");
+        span.in_scope(|| {
+            debug!(users = ?users.iter().map(|u| &u.id).collect::<Vec<_>>());
+        });
+        let handler = context
+            .get_admin_handler()
+            .ok_or_else(field_error_callback(&span, "Unauthorized user creation"))?;
+        let schema = handler.get_schema().await?;
+        let operations = users
+            .into_iter()
+            .map(|user| make_create_user_request(user, &schema).map(BatchOperation::CreateUser))
+            .collect();
+        run_batch(&handler, operations, all_or_nothing.unwrap_or(true), span).await
+    }
+
+    /// Updates all the users in a single transaction. Without `allOrNothing: false`, no user is
+    /// updated if any of them fails.
+    async fn update_users(
+        context: &Context<Handler>,
+        users: Vec<UpdateUserInput>,
+        all_or_nothing: Option<bool>,
+    ) -> FieldResult<BatchResult> {
+        let span = debug_span!("[GraphQL mutation] update_users");
+        span.in_scope(|| {
+            debug!(users = ?users.iter().map(|u| &u.id).collect::<Vec<_>>());
+        });
+        let handler = context
+            .get_admin_handler()
+            .ok_or_else(field_error_callback(&span, "Unauthorized user update"))?;
+        let schema = handler.get_schema().await?;
+        let operations = users
+            .into_iter()
+            .map(|user| {
+                make_update_user_request(user, &schema, true).map(BatchOperation::UpdateUser)
+            })
+            .collect();
+        run_batch(&handler, operations, all_or_nothing.unwrap_or(true), span).await
+    }
+
+    /// Deletes all the users in a single transaction. Without `allOrNothing: false`, no user is
+    /// deleted if any of them fails.
+    async fn delete_users(
+        context: &Context<Handler>,
+        user_ids: Vec<String>,
+        all_or_nothing: Option<bool>,
+    ) -> FieldResult<BatchResult> {
+        let span = debug_span!("[GraphQL mutation] delete_users");
+        span.in_scope(|| {
+            debug!(?user_ids);
+        });
+        let handler = context
+            .get_admin_handler()
+            .ok_or_else(field_error_callback(&span, "Unauthorized user deletion"))?;
+        let operations = user_ids
+            .iter()
+            .map(|user_id| -> FieldResult<BatchOperation> {
+                let user_id = UserId::new(user_id);
+                if context.validation_result.user == user_id {
+                    return Err("Cannot delete current user".into());
+                }
+                Ok(BatchOperation::DeleteUser(user_id))
+            })
+            .collect();
+        run_batch(&handler, operations, all_or_nothing.unwrap_or(true), span).await
+    }
+
+    /// Adds the user to all the groups in a single transaction. Without `allOrNothing: false`,
+    /// the user is added to no group if any of them fails.
+    async fn add_user_to_groups(
+        context: &Context<Handler>,
+        user_id: String,
+        group_ids: Vec<i32>,
+        all_or_nothing: Option<bool>,
+    ) -> FieldResult<BatchResult> {
+        let span = debug_span!("[GraphQL mutation] add_user_to_groups");
+        span.in_scope(|| {
+            debug!(?user_id, ?group_ids);
+        });
+        let handler = context
+            .get_admin_handler()
+            .ok_or_else(field_error_callback(
+                &span,
+                "Unauthorized group membership modification",
+            ))?;
+        let user_id = UserId::new(&user_id);
+        let operations = group_ids
+            .into_iter()
+            .map(|group_id| {
+                Ok(BatchOperation::AddUserToGroup(
+                    user_id.clone(),
+                    GroupId(group_id),
+                ))
+            })
+            .collect();
+        run_batch(&handler, operations, all_or_nothing.unwrap_or(true), span).await
+    }
+
+    /// Replaces the members of the group: the users not in the list are removed from it.
+    async fn set_group_members(
+        context: &Context<Handler>,
+        group_id: i32,
+        user_ids: Vec<String>,
+    ) -> FieldResult<Success> {
+        let span = debug_span!("[GraphQL mutation] set_group_members");
+        span.in_scope(|| {
+            debug!(?group_id, ?user_ids);
+        });
+        let handler = context
+            .get_admin_handler()
+            .ok_or_else(field_error_callback(
+                &span,
+                "Unauthorized group membership modification",
+            ))?;
+        let user_ids = user_ids
+            .iter()
+            .map(|id| UserId::new(id))
+            .collect::<Vec<_>>();
+        if group_id == 1 && !user_ids.contains(&context.validation_result.user) {
+            span.in_scope(|| debug!("Cannot remove admin rights for current user"));
+            return Err("Cannot remove admin rights for current user".into());
+        }
+        let result = handler
+            .apply_batch(
+                vec![BatchOperation::SetGroupMembers(GroupId(group_id), user_ids)],
+                BatchMode::AllOrNothing,
+            )
+            .instrument(span)
+            .await?;
+        for item_result in result.results {
+            item_result?;
+        }
+        Ok(Success::new())
+    }
+
     async fn delete_group(context: &Context<Handler>, group_id: i32) -> FieldResult<Success> {
         let span = debug_span!("[GraphQL mutation] delete_group");
         span.in_scope(|| {
@@ -316,6 +492,7 @@ impl<Handler: BackendHandler> Mutation<Handler> {
         is_list: bool,
         is_visible: bool,
         is_editable: bool,
+        validation: Option<AttributeValidationInput>,
     ) -> FieldResult<Success> {
         let span = debug_span!("[GraphQL mutation] add_user_attribute");
         span.in_scope(|| {
@@ -343,6 +520,7 @@ impl<Handler: BackendHandler> Mutation<Handler> {
                 &span,
                 "Unauthorized attribute creation",
             ))?;
+        let validation = make_attribute_validation(validation, attribute_type)?;
         handler
             .add_user_attribute(CreateAttributeRequest {
                 name: name.into(),
@@ -350,6 +528,7 @@ impl<Handler: BackendHandler> Mutation<Handler> {
                 is_list,
                 is_visible,
                 is_editable,
+                validation,
             })
             .instrument(span)
             .await?;
@@ -363,6 +542,7 @@ impl<Handler: BackendHandler> Mutation<Handler> {
         is_list: bool,
         is_visible: bool,
         is_editable: bool,
+        validation: Option<AttributeValidationInput>,
     ) -> FieldResult<Success> {
         let span = debug_span!("[GraphQL mutation] add_group_attribute");
         span.in_scope(|| {
@@ -388,6 +568,7 @@ impl<Handler: BackendHandler> Mutation<Handler> {
                 &span,
                 "Unauthorized attribute creation",
             ))?;
+        let validation = make_attribute_validation(validation, attribute_type)?;
         handler
             .add_group_attribute(CreateAttributeRequest {
                 name: name.into(),
@@ -395,6 +576,7 @@ impl<Handler: BackendHandler> Mutation<Handler> {
                 is_list,
                 is_visible,
                 is_editable,
+                validation,
             })
             .instrument(span)
             .await?;
@@ -463,6 +645,66 @@ impl<Handler: BackendHandler> Mutation<Handler> {
         Ok(Success::new())
     }
 
+    /// Replaces who can read and edit the attribute, besides the admins and the user it belongs to. The
+    /// members of the editor groups can also read it.
+    async fn set_user_attribute_permissions(
+        context: &Context<Handler>,
+        name: String,
+        is_private: bool,
+        reader_group_ids: Vec<i32>,
+        editor_group_ids: Vec<i32>,
+    ) -> FieldResult<Success> {
+        let span = debug_span!("[GraphQL mutation] set_user_attribute_permissions");
+        let name = AttributeName::from(name);
+        span.in_scope(|| {
+            debug!(?name, ?is_private, ?reader_group_ids, ?editor_group_ids);
+        });
+        let handler = context
+            .get_admin_handler()
+            .ok_or_else(field_error_callback(
+                &span,
+                "Unauthorized attribute permissions modification",
+            ))?;
+        let permissions =
+            get_attribute_permissions(&handler, is_private, reader_group_ids, editor_group_ids)
+                .await?;
+        handler
+            .set_user_attribute_permissions(&name, permissions)
+            .instrument(span)
+            .await?;
+        Ok(Success::new())
+    }
+
+    /// Replaces who can read and edit the attribute, besides the admins and the members of the group. The
+    /// members of the editor groups can also read it.
+    async fn set_group_attribute_permissions(
+        context: &Context<Handler>,
+        name: String,
+        is_private: bool,
+        reader_group_ids: Vec<i32>,
+        editor_group_ids: Vec<i32>,
+    ) -> FieldResult<Success> {
+        let span = debug_span!("[GraphQL mutation] set_group_attribute_permissions");
+        let name = AttributeName::from(name);
+        span.in_scope(|| {
+            debug!(?name, ?is_private, ?reader_group_ids, ?editor_group_ids);
+        });
+        let handler = context
+            .get_admin_handler()
+            .ok_or_else(field_error_callback(
+                &span,
+                "Unauthorized attribute permissions modification",
+            ))?;
+        let permissions =
+            get_attribute_permissions(&handler, is_private, reader_group_ids, editor_group_ids)
+                .await?;
+        handler
+            .set_group_attribute_permissions(&name, permissions)
+            .instrument(span)
+            .await?;
+        Ok(Success::new())
+    }
+
     async fn add_user_object_class(
         context: &Context<Handler>,
         name: String,
@@ -549,17 +791,30 @@ impl<Handler: BackendHandler> Mutation<Handler> {
 }
 #[cfg(test)]
 mod tests {
+    use super::helpers::consolidate_attributes;
     use super::*;
     use crate::query::Query;
+    use chrono::TimeZone;
     use juniper::{
         DefaultScalarValue, EmptySubscription, GraphQLType, InputValue, RootNode, Variables,
         execute, graphql_value,
     };
     use lldap_auth::access_control::{Permission, ValidationResults};
-    use lldap_domain::types::{AttributeName, AttributeType};
-    use lldap_test_utils::MockTestBackendHandler;
+    use lldap_domain::{
+        requests::{CreateGroupRequest, UpdateUserRequest},
+        schema::{
+            AttributeList, AttributePermissions, AttributeSchema, AttributeValidation, Schema,
+        },
+        types::{Attribute, AttributeName, AttributeType, Group, GroupDetails, GroupOwner, Uuid},
+    };
+    use lldap_domain_handlers::handler::{
+        BatchResult as DomainBatchResult, DynamicGroupFilter, GroupRequestFilter, UserRequestFilter,
+    };
+    use lldap_domain_model::error::DomainError;
+    use lldap_test_utils::{MockTestBackendHandler, setup_default_schema};
     use mockall::predicate::eq;
     use pretty_assertions::assert_eq;
+    use std::collections::HashSet;
 
     fn mutation_schema<'q, C, Q, M>(
         query_root: Q,
@@ -589,6 +844,7 @@ mod tests {
                 is_list: false,
                 is_visible: false,
                 is_editable: false,
+                validation: Default::default(),
             }))
             .return_once(|_| Ok(()));
         let context = Context::<MockTestBackendHandler>::new_for_tests(
@@ -677,6 +933,102 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_create_user_attribute_with_validation() {
+        const QUERY: &str = r#"
+            mutation {
+                addUserAttribute(
+                    name: "room",
+                    attributeType: STRING,
+                    isList: false,
+                    isVisible: true,
+                    isEditable: true,
+                    validation: {
+                        regex: "[A-Z][0-9]+",
+                        maxLength: 4,
+                        allowedValues: ["A1", "B2"],
+                        isRequired: true
+                    }
+                ) {
+                    ok
+                }
+            }
+        "#;
+        let mut mock = MockTestBackendHandler::new();
+        mock.expect_add_user_attribute()
+            .with(eq(CreateAttributeRequest {
+                name: AttributeName::new("room"),
+                attribute_type: AttributeType::String,
+                is_list: false,
+                is_visible: true,
+                is_editable: true,
+                validation: AttributeValidation {
+                    regex: Some("[A-Z][0-9]+".to_string()),
+                    max_length: Some(4),
+                    allowed_values: vec!["A1".to_string(), "B2".to_string()],
+                    is_required: true,
+                    ..Default::default()
+                },
+            }))
+            .return_once(|_| Ok(()));
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            mock,
+            ValidationResults {
+                user: UserId::new("bob"),
+                permission: Permission::Admin,
+            },
+        );
+        let schema = mutation_schema(
+            Query::<MockTestBackendHandler>::new(),
+            Mutation::<MockTestBackendHandler>::new(),
+        );
+        assert_eq!(
+            execute(QUERY, None, &schema, &Variables::new(), &context).await,
+            Ok((graphql_value!({"addUserAttribute": {"ok": true}}), vec![]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_user_attribute_invalid_validation() {
+        const QUERY: &str = r#"
+            mutation {
+                addUserAttribute(
+                    name: "photo",
+                    attributeType: JPEG_PHOTO,
+                    isList: false,
+                    isVisible: true,
+                    isEditable: true,
+                    validation: { maxLength: 4 }
+                ) {
+                    ok
+                }
+            }
+        "#;
+        let mock = MockTestBackendHandler::new();
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            mock,
+            ValidationResults {
+                user: UserId::new("bob"),
+                permission: Permission::Admin,
+            },
+        );
+        let schema = mutation_schema(
+            Query::<MockTestBackendHandler>::new(),
+            Mutation::<MockTestBackendHandler>::new(),
+        );
+        let (response, errors) = execute(QUERY, None, &schema, &Variables::new(), &context)
+            .await
+            .unwrap();
+        assert!(response.is_null());
+        assert_eq!(
+            errors
+                .iter()
+                .map(|e| e.error().message())
+                .collect::<Vec<_>>(),
+            vec!["Invalid validation rules: JpegPhoto attributes can only be unique or required"]
+        );
+    }
+
     #[tokio::test]
     async fn test_create_group_attribute_valid() {
         const QUERY: &str = r#"
@@ -694,6 +1046,7 @@ mod tests {
                 is_list: false,
                 is_visible: false,
                 is_editable: false,
+                validation: Default::default(),
             }))
             .return_once(|_| Ok(()));
         let context = Context::<MockTestBackendHandler>::new_for_tests(
@@ -782,6 +1135,491 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_create_dynamic_group() {
+        const QUERY: &str = r#"
+            mutation CreateDynamicGroup($filter: String!) {
+                createGroupWithDetails(request: {displayName: "Dynamic", dynamicFilter: $filter}) {
+                    id
+                    dynamicFilter
+                }
+            }
+        "#;
+        let mut mock = MockTestBackendHandler::new();
+        setup_default_schema(&mut mock);
+        mock.expect_create_dynamic_group()
+            .with(
+                eq(CreateGroupRequest {
+                    display_name: "Dynamic".into(),
+                    attributes: vec![],
+                }),
+                eq(DynamicGroupFilter {
+                    ldap_filter: "(uid=bob)".to_owned(),
+                    user_filter: UserRequestFilter::UserId(UserId::new("bob")),
+                }),
+            )
+            .return_once(|_, _| Ok(GroupId(3)));
+        mock.expect_get_group_details()
+            .with(eq(GroupId(3)))
+            .return_once(|_| {
+                Ok(GroupDetails {
+                    group_id: GroupId(3),
+                    display_name: "Dynamic".into(),
+                    creation_date: chrono::Utc.timestamp_nanos(42).naive_utc(),
+                    uuid: Uuid::from_name_and_date("Dynamic", &chrono::Utc::now().naive_utc()),
+                    dynamic_filter: Some("(uid=bob)".to_owned()),
+                    attributes: Vec::new(),
+                    modified_date: chrono::Utc.timestamp_nanos(42).naive_utc(),
+                })
+            });
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            mock,
+            ValidationResults {
+                user: UserId::new("admin"),
+                permission: Permission::Admin,
+            },
+        );
+        let schema = mutation_schema(
+            Query::<MockTestBackendHandler>::new(),
+            Mutation::<MockTestBackendHandler>::new(),
+        );
+        let vars = Variables::from([("filter".to_string(), InputValue::scalar("(uid=bob)"))]);
+        assert_eq!(
+            execute(QUERY, None, &schema, &vars, &context).await,
+            Ok((
+                graphql_value!(
+                {
+                    "createGroupWithDetails": {
+                        "id": 3,
+                        "dynamicFilter": "(uid=bob)",
+                    }
+                } ),
+                vec![]
+            ))
+        );
+        // Unknown attributes are rejected before reaching the backend.
+        let vars = Variables::from([("filter".to_string(), InputValue::scalar("(unknown=bob)"))]);
+        let (response, errors) = execute(QUERY, None, &schema, &vars, &context)
+            .await
+            .unwrap();
+        assert!(response.is_null());
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_delete_users_continue_on_error() {
+        const QUERY: &str = r#"
+            mutation DeleteUsers($userIds: [String!]!) {
+                deleteUsers(userIds: $userIds, allOrNothing: false) {
+                    committed
+                    results {
+                        index
+                        ok
+                        error
+                    }
+                }
+            }
+        "#;
+        let mut mock = MockTestBackendHandler::new();
+        mock.expect_apply_batch()
+            .with(
+                eq(vec![
+                    BatchOperation::DeleteUser(UserId::new("alice")),
+                    BatchOperation::DeleteUser(UserId::new("carol")),
+                ]),
+                eq(BatchMode::ContinueOnError),
+            )
+            .return_once(|_, _| {
+                Ok(DomainBatchResult {
+                    committed: true,
+                    results: vec![
+                        Ok(()),
+                        Err(DomainError::EntityNotFound(
+                            "No such user: 'carol'".to_owned(),
+                        )),
+                    ],
+                })
+            });
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            mock,
+            ValidationResults {
+                user: UserId::new("bob"),
+                permission: Permission::Admin,
+            },
+        );
+        let vars = Variables::from([(
+            "userIds".to_string(),
+            InputValue::list(vec![
+                InputValue::scalar("bob"),
+                InputValue::scalar("alice"),
+                InputValue::scalar("carol"),
+            ]),
+        )]);
+        let schema = mutation_schema(
+            Query::<MockTestBackendHandler>::new(),
+            Mutation::<MockTestBackendHandler>::new(),
+        );
+        assert_eq!(
+            execute(QUERY, None, &schema, &vars, &context).await,
+            Ok((
+                graphql_value!({
+                    "deleteUsers": {
+                        "committed": true,
+                        "results": [
+                            {"index": 0, "ok": false, "error": "Cannot delete current user"},
+                            {"index": 1, "ok": true, "error": None},
+                            {
+                                "index": 2,
+                                "ok": false,
+                                "error": "Entity not found: `No such user: 'carol'`",
+                            },
+                        ],
+                    }
+                }),
+                vec![]
+            ))
+        );
+    }
+
+    fn owned_group(group_id: i32, name: &str) -> Group {
+        let date = chrono::Utc.timestamp_nanos(0).naive_utc();
+        Group {
+            id: GroupId(group_id),
+            display_name: name.into(),
+            creation_date: date,
+            uuid: Uuid::from_name_and_date(name, &date),
+            users: vec![],
+            owners: vec![GroupOwner::User(UserId::new("bob"))],
+            dynamic_filter: None,
+            attributes: vec![],
+            modified_date: date,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_user_to_group_as_owner() {
+        const QUERY: &str = r#"
+            mutation {
+                addUserToGroup(userId: "alice", groupId: 3) {
+                    ok
+                }
+            }
+        "#;
+        let mut mock = MockTestBackendHandler::new();
+        mock.expect_list_groups()
+            .with(eq(Some(GroupRequestFilter::And(vec![
+                GroupRequestFilter::GroupId(GroupId(3)),
+                GroupRequestFilter::OwnedBy(UserId::new("bob")),
+            ]))))
+            .return_once(|_| Ok(vec![owned_group(3, "family")]));
+        setup_home_address_schema(&mut mock);
+        mock.expect_add_user_to_group()
+            .with(eq(UserId::new("alice")), eq(GroupId(3)))
+            .return_once(|_, _| Ok(()));
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            mock,
+            ValidationResults {
+                user: UserId::new("bob"),
+                permission: Permission::Regular,
+            },
+        );
+        let schema = mutation_schema(
+            Query::<MockTestBackendHandler>::new(),
+            Mutation::<MockTestBackendHandler>::new(),
+        );
+        assert_eq!(
+            execute(QUERY, None, &schema, &Variables::new(), &context).await,
+            Ok((graphql_value!({"addUserToGroup": {"ok": true}}), vec![]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_user_to_group_as_owner_of_permission_group() {
+        const QUERY: &str = r#"
+            mutation {
+                addUserToGroup(userId: "alice", groupId: 1) {
+                    ok
+                }
+            }
+        "#;
+        let mut mock = MockTestBackendHandler::new();
+        mock.expect_list_groups()
+            .return_once(|_| Ok(vec![owned_group(1, "lldap_admin")]));
+        setup_home_address_schema(&mut mock);
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            mock,
+            ValidationResults {
+                user: UserId::new("bob"),
+                permission: Permission::Regular,
+            },
+        );
+        let schema = mutation_schema(
+            Query::<MockTestBackendHandler>::new(),
+            Mutation::<MockTestBackendHandler>::new(),
+        );
+        let (response, errors) = execute(QUERY, None, &schema, &Variables::new(), &context)
+            .await
+            .unwrap();
+        assert!(response.is_null());
+        assert_eq!(
+            errors[0].error().message(),
+            "Unauthorized group membership modification"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_user_to_group_as_owner_of_attribute_editor_group() {
+        const QUERY: &str = r#"
+            mutation {
+                addUserToGroup(userId: "bob", groupId: 3) {
+                    ok
+                }
+            }
+        "#;
+        let mut mock = MockTestBackendHandler::new();
+        mock.expect_list_groups()
+            .return_once(|_| Ok(vec![owned_group(3, "hr")]));
+        setup_home_address_schema(&mut mock);
+        mock.expect_add_user_to_group().never();
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            mock,
+            ValidationResults {
+                user: UserId::new("bob"),
+                permission: Permission::Regular,
+            },
+        );
+        let schema = mutation_schema(
+            Query::<MockTestBackendHandler>::new(),
+            Mutation::<MockTestBackendHandler>::new(),
+        );
+        let (response, errors) = execute(QUERY, None, &schema, &Variables::new(), &context)
+            .await
+            .unwrap();
+        assert!(response.is_null());
+        assert_eq!(
+            errors[0].error().message(),
+            "Unauthorized group membership modification"
+        );
+    }
+
+    fn setup_home_address_schema(mock: &mut MockTestBackendHandler) {
+        mock.expect_get_user_groups()
+            .with(eq(UserId::new("bob")))
+            .returning(|_| {
+                let date = chrono::Utc.timestamp_nanos(0).naive_utc();
+                Ok(HashSet::from([GroupDetails {
+                    group_id: GroupId(3),
+                    display_name: "hr".into(),
+                    creation_date: date,
+                    uuid: Uuid::from_name_and_date("hr", &date),
+                    dynamic_filter: None,
+                    attributes: Vec::new(),
+                    modified_date: date,
+                }]))
+            });
+        mock.expect_get_schema().returning(|| {
+            Ok(Schema {
+                user_attributes: AttributeList {
+                    attributes: vec![AttributeSchema {
+                        name: "home_address".into(),
+                        attribute_type: AttributeType::String,
+                        is_list: false,
+                        is_visible: false,
+                        is_editable: false,
+                        is_hardcoded: false,
+                        is_readonly: false,
+                        permissions: AttributePermissions {
+                            is_private: true,
+                            reader_groups: Vec::new(),
+                            editor_groups: vec!["hr".into()],
+                        },
+                        validation: Default::default(),
+                    }],
+                },
+                group_attributes: AttributeList {
+                    attributes: Vec::new(),
+                },
+                extra_user_object_classes: Vec::new(),
+                extra_group_object_classes: Vec::new(),
+            })
+        });
+    }
+
+    #[tokio::test]
+    async fn test_update_user_attribute_as_editor_group_member() {
+        const QUERY: &str = r#"
+            mutation {
+                updateUser(user: {
+                    id: "alice",
+                    insertAttributes: [{name: "home_address", value: ["1 Main Street"]}]
+                }) {
+                    ok
+                }
+            }
+        "#;
+        let mut mock = MockTestBackendHandler::new();
+        setup_home_address_schema(&mut mock);
+        mock.expect_update_user()
+            .with(eq(UpdateUserRequest {
+                user_id: UserId::new("alice"),
+                email: None,
+                display_name: None,
+                delete_attributes: Vec::new(),
+                insert_attributes: vec![Attribute {
+                    name: "home_address".into(),
+                    value: "1 Main Street".to_string().into(),
+                }],
+                email_verified: false,
+            }))
+            .return_once(|_| Ok(()));
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            mock,
+            ValidationResults {
+                user: UserId::new("bob"),
+                permission: Permission::Regular,
+            },
+        );
+        let schema = mutation_schema(
+            Query::<MockTestBackendHandler>::new(),
+            Mutation::<MockTestBackendHandler>::new(),
+        );
+        assert_eq!(
+            execute(QUERY, None, &schema, &Variables::new(), &context).await,
+            Ok((graphql_value!({"updateUser": {"ok": true}}), vec![]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_user_attribute_not_editable_by_editor_group_member() {
+        const QUERY: &str = r#"
+            mutation {
+                updateUser(user: {id: "alice", displayName: "Alice"}) {
+                    ok
+                }
+            }
+        "#;
+        let mut mock = MockTestBackendHandler::new();
+        setup_home_address_schema(&mut mock);
+        mock.expect_update_user().never();
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            mock,
+            ValidationResults {
+                user: UserId::new("bob"),
+                permission: Permission::Regular,
+            },
+        );
+        let schema = mutation_schema(
+            Query::<MockTestBackendHandler>::new(),
+            Mutation::<MockTestBackendHandler>::new(),
+        );
+        let (response, errors) = execute(QUERY, None, &schema, &Variables::new(), &context)
+            .await
+            .unwrap();
+        assert!(response.is_null());
+        assert_eq!(
+            errors[0].error().message(),
+            "Permission denied: Attribute display_name is not editable"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_own_email_needs_confirmation() {
+        const QUERY: &str = r#"
+            mutation {
+                updateUser(user: {id: "bob", email: "new@bob.bob"}) {
+                    ok
+                }
+            }
+        "#;
+        let mut mock = MockTestBackendHandler::new();
+        setup_home_address_schema(&mut mock);
+        mock.expect_update_user().never();
+        let mut context = Context::<MockTestBackendHandler>::new_for_tests(
+            mock,
+            ValidationResults {
+                user: UserId::new("bob"),
+                permission: Permission::Regular,
+            },
+        );
+        context.confirm_email_changes = true;
+        let schema = mutation_schema(
+            Query::<MockTestBackendHandler>::new(),
+            Mutation::<MockTestBackendHandler>::new(),
+        );
+        let (response, errors) = execute(QUERY, None, &schema, &Variables::new(), &context)
+            .await
+            .unwrap();
+        assert!(response.is_null());
+        assert_eq!(
+            errors[0].error().message(),
+            "The new email address has to be confirmed through a link"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_users_all_or_nothing_with_invalid_item() {
+        const QUERY: &str = r#"
+            mutation CreateUsers {
+                createUsers(users: [{id: "alice", email: "alice@example.com"}, {id: "carol"}]) {
+                    committed
+                    results {
+                        index
+                        ok
+                        error
+                    }
+                }
+            }
+        "#;
+        let mut mock = MockTestBackendHandler::new();
+        mock.expect_get_schema().returning(|| {
+            Ok(Schema {
+                user_attributes: AttributeList {
+                    attributes: Vec::new(),
+                },
+                group_attributes: AttributeList {
+                    attributes: Vec::new(),
+                },
+                extra_user_object_classes: Vec::new(),
+                extra_group_object_classes: Vec::new(),
+            })
+        });
+        mock.expect_apply_batch().never();
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            mock,
+            ValidationResults {
+                user: UserId::new("bob"),
+                permission: Permission::Admin,
+            },
+        );
+        let schema = mutation_schema(
+            Query::<MockTestBackendHandler>::new(),
+            Mutation::<MockTestBackendHandler>::new(),
+        );
+        assert_eq!(
+            execute(QUERY, None, &schema, &Variables::new(), &context).await,
+            Ok((
+                graphql_value!({
+                    "createUsers": {
+                        "committed": false,
+                        "results": [
+                            {
+                                "index": 0,
+                                "ok": false,
+                                "error": "Not saved because of another error",
+                            },
+                            {
+                                "index": 1,
+                                "ok": false,
+                                "error": "Email is required when creating a new user",
+                            },
+                        ],
+                    }
+                }),
+                vec![]
+            ))
+        );
+    }
+
     #[tokio::test]
     async fn test_attribute_consolidation_attr_precedence() {
         let attributes = vec![
@@ -1,8 +1,9 @@
 use chrono::TimeZone;
-use juniper::{FieldResult, graphql_object};
+use juniper::{FieldResult, GraphQLObject, graphql_object};
 use lldap_domain::public_schema::PublicSchema;
 use lldap_domain::schema::AttributeList as DomainAttributeList;
 use lldap_domain::schema::AttributeSchema as DomainAttributeSchema;
+use lldap_domain::schema::AttributeValidation as DomainAttributeValidation;
 use lldap_domain::types::{Attribute as DomainAttribute, AttributeValue as DomainAttributeValue};
 use lldap_domain::types::{Cardinality, Group as DomainGroup, GroupDetails, User as DomainUser};
 use lldap_domain_handlers::handler::BackendHandler;
@@ -39,6 +40,68 @@ impl<Handler: BackendHandler> AttributeSchema<Handler> {
     fn is_readonly(&self) -> bool {
         self.schema.is_readonly
     }
+    /// Hidden from the read-only roles, unless they are in a reader or editor group.
+    fn is_private(&self) -> bool {
+        self.schema.permissions.is_private
+    }
+    /// The groups whose members can read the attribute on every user or group.
+    fn reader_groups(&self) -> Vec<String> {
+        self.schema
+            .permissions
+            .reader_groups
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+    /// The groups whose members can edit the attribute on every user or group.
+    fn editor_groups(&self) -> Vec<String> {
+        self.schema
+            .permissions
+            .editor_groups
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+    /// The rules the values of the attribute must follow.
+    fn validation(&self) -> AttributeValidation {
+        AttributeValidation::from(&self.schema.validation)
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, GraphQLObject)]
+/// The rules the values of an attribute must follow. The unset ones accept any value.
+pub struct AttributeValidation {
+    /// The whole value must match this regular expression.
+    pub regex: Option<String>,
+    /// The length of the value, in characters.
+    pub min_length: Option<i32>,
+    pub max_length: Option<i32>,
+    /// The range of the value, which must be an integer. Integers (signed 64 bits) are
+    /// represented as strings.
+    pub min_value: Option<String>,
+    pub max_value: Option<String>,
+    /// When not empty, the value must be one of these.
+    pub allowed_values: Vec<String>,
+    /// No two users (or groups) can have the same value.
+    pub is_unique: bool,
+    /// The attribute has to be set when creating a user (or group), and can't be removed.
+    pub is_required: bool,
+}
+
+impl From<&DomainAttributeValidation> for AttributeValidation {
+    fn from(value: &DomainAttributeValidation) -> Self {
+        let to_length = |length: i64| i32::try_from(length).unwrap_or(i32::MAX);
+        Self {
+            regex: value.regex.clone(),
+            min_length: value.min_length.map(to_length),
+            max_length: value.max_length.map(to_length),
+            min_value: value.min_value.map(|v| v.to_string()),
+            max_value: value.max_value.map(|v| v.to_string()),
+            allowed_values: value.allowed_values.clone(),
+            is_unique: value.is_unique,
+            is_required: value.is_required,
+        }
+    }
 }
 
 impl<Handler: BackendHandler> Clone for AttributeSchema<Handler> {
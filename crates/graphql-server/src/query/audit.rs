@@ -0,0 +1,93 @@
+use chrono::TimeZone;
+use juniper::{FieldResult, GraphQLInputObject, graphql_object};
+use lldap_domain::types::UserId;
+use lldap_domain_handlers::audit::{
+    AuditAction, AuditInterface, AuditLogEntry as DomainAuditLogEntry,
+    AuditLogFilter as DomainAuditLogFilter,
+};
+
+#[derive(PartialEq, Eq, Debug, GraphQLInputObject)]
+/// Restricts the audit log entries returned. All the set fields must match.
+pub struct AuditLogFilter {
+    actor: Option<String>,
+    target: Option<String>,
+    /// For instance "create_user", "update_schema" or "login".
+    action: Option<String>,
+    /// One of "ldap", "graphql" or "http".
+    interface: Option<String>,
+    success: Option<bool>,
+    since: Option<chrono::DateTime<chrono::Utc>>,
+    until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl AuditLogFilter {
+    pub fn try_into_domain_filter(self) -> FieldResult<DomainAuditLogFilter> {
+        Ok(DomainAuditLogFilter {
+            actor: self.actor.as_deref().map(UserId::new),
+            target: self.target,
+            action: self
+                .action
+                .map(|a| {
+                    AuditAction::from_str_opt(&a).ok_or_else(|| format!("Unknown action: {a}"))
+                })
+                .transpose()?,
+            interface: self
+                .interface
+                .map(|i| {
+                    AuditInterface::from_str_opt(&i)
+                        .ok_or_else(|| format!("Unknown interface: {i}"))
+                })
+                .transpose()?,
+            success: self.success,
+            since: self.since.map(|d| d.naive_utc()),
+            until: self.until.map(|d| d.naive_utc()),
+        })
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct AuditLogEntry {
+    entry: DomainAuditLogEntry,
+}
+
+impl From<DomainAuditLogEntry> for AuditLogEntry {
+    fn from(entry: DomainAuditLogEntry) -> Self {
+        Self { entry }
+    }
+}
+
+/// An entry of the audit log.
+#[graphql_object]
+impl AuditLogEntry {
+    fn timestamp(&self) -> chrono::DateTime<chrono::Utc> {
+        chrono::Utc.from_utc_datetime(&self.entry.timestamp)
+    }
+
+    fn actor(&self) -> Option<&str> {
+        self.entry.event.actor.as_ref().map(UserId::as_str)
+    }
+
+    fn action(&self) -> &str {
+        self.entry.event.action.as_str()
+    }
+
+    fn target(&self) -> Option<&str> {
+        self.entry.event.target.as_deref()
+    }
+
+    fn details(&self) -> Option<&str> {
+        self.entry.event.details.as_deref()
+    }
+
+    fn source_ip(&self) -> Option<String> {
+        self.entry.event.source.ip.map(|ip| ip.to_string())
+    }
+
+    fn interface(&self) -> &str {
+        self.entry.event.source.interface.as_str()
+    }
+
+    fn success(&self) -> bool {
+        self.entry.event.success
+    }
+}
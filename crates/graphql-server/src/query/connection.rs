@@ -0,0 +1,306 @@
+use juniper::{FieldResult, GraphQLEnum, GraphQLInputObject, graphql_object};
+use lldap_domain::public_schema::PublicSchema;
+use lldap_domain::types::{Group as DomainGroup, GroupId, UserAndGroups, UserId};
+use lldap_domain_handlers::handler::{
+    BackendHandler, GroupOrderField as DomainGroupOrderField, GroupPageRequest, Page, PageRequest,
+    UserOrderField as DomainUserOrderField, UserPageRequest,
+};
+use std::sync::Arc;
+
+use super::group::Group;
+use super::user::User;
+use crate::api::Context;
+
+const DEFAULT_PAGE_SIZE: i32 = 100;
+const MAX_PAGE_SIZE: i32 = 1000;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, GraphQLEnum)]
+pub enum OrderDirection {
+    Asc,
+    Desc,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, GraphQLEnum)]
+/// The fields the users can be sorted by.
+pub enum UserOrderField {
+    Id,
+    Email,
+    DisplayName,
+    CreationDate,
+}
+
+impl From<UserOrderField> for DomainUserOrderField {
+    fn from(field: UserOrderField) -> Self {
+        match field {
+            UserOrderField::Id => DomainUserOrderField::UserId,
+            UserOrderField::Email => DomainUserOrderField::Email,
+            UserOrderField::DisplayName => DomainUserOrderField::DisplayName,
+            UserOrderField::CreationDate => DomainUserOrderField::CreationDate,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, GraphQLEnum)]
+/// The fields the groups can be sorted by.
+pub enum GroupOrderField {
+    DisplayName,
+    Id,
+    CreationDate,
+}
+
+impl From<GroupOrderField> for DomainGroupOrderField {
+    fn from(field: GroupOrderField) -> Self {
+        match field {
+            GroupOrderField::DisplayName => DomainGroupOrderField::DisplayName,
+            GroupOrderField::Id => DomainGroupOrderField::GroupId,
+            GroupOrderField::CreationDate => DomainGroupOrderField::CreationDate,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, GraphQLInputObject)]
+/// The order of the users. Users with the same value are sorted by id.
+pub struct UserOrder {
+    field: UserOrderField,
+    /// Ascending by default.
+    direction: Option<OrderDirection>,
+}
+
+#[derive(PartialEq, Eq, Debug, GraphQLInputObject)]
+/// The order of the groups. Groups with the same value are sorted by id.
+pub struct GroupOrder {
+    field: GroupOrderField,
+    /// Ascending by default.
+    direction: Option<OrderDirection>,
+}
+
+fn make_page_request<Field, Id>(
+    order_by: Field,
+    direction: Option<OrderDirection>,
+    first: Option<i32>,
+    after: Option<Id>,
+) -> FieldResult<PageRequest<Field, Id>> {
+    let first = first.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(0..=MAX_PAGE_SIZE).contains(&first) {
+        return Err(format!("`first` must be between 0 and {MAX_PAGE_SIZE}").into());
+    }
+    Ok(PageRequest {
+        order_by,
+        descending: direction == Some(OrderDirection::Desc),
+        after,
+        first: first as u64,
+    })
+}
+
+pub fn make_user_page_request(
+    order_by: Option<UserOrder>,
+    first: Option<i32>,
+    after: Option<String>,
+) -> FieldResult<UserPageRequest> {
+    let (field, direction) =
+        order_by.map_or((UserOrderField::Id, None), |o| (o.field, o.direction));
+    make_page_request(
+        field.into(),
+        direction,
+        first,
+        after.as_deref().map(UserId::new),
+    )
+}
+
+pub fn make_group_page_request(
+    order_by: Option<GroupOrder>,
+    first: Option<i32>,
+    after: Option<String>,
+) -> FieldResult<GroupPageRequest> {
+    let (field, direction) = order_by.map_or((GroupOrderField::DisplayName, None), |o| {
+        (o.field, o.direction)
+    });
+    let after = after
+        .map(|cursor| {
+            cursor
+                .parse()
+                .map(GroupId)
+                .map_err(|_| format!("Invalid cursor: {cursor}"))
+        })
+        .transpose()?;
+    make_page_request(field.into(), direction, first, after)
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct PageInfo {
+    has_next_page: bool,
+    has_previous_page: bool,
+    start_cursor: Option<String>,
+    end_cursor: Option<String>,
+}
+
+impl PageInfo {
+    fn new(cursors: &[&str], has_next_page: bool, has_previous_page: bool) -> Self {
+        Self {
+            has_next_page,
+            has_previous_page,
+            start_cursor: cursors.first().map(|c| c.to_string()),
+            end_cursor: cursors.last().map(|c| c.to_string()),
+        }
+    }
+}
+
+/// Where a page is in the whole list.
+#[graphql_object]
+impl PageInfo {
+    fn has_next_page(&self) -> bool {
+        self.has_next_page
+    }
+
+    /// Whether the page was requested with a cursor. Only forward pagination is supported.
+    fn has_previous_page(&self) -> bool {
+        self.has_previous_page
+    }
+
+    fn start_cursor(&self) -> Option<&str> {
+        self.start_cursor.as_deref()
+    }
+
+    /// Pass it as `after` to get the next page.
+    fn end_cursor(&self) -> Option<&str> {
+        self.end_cursor.as_deref()
+    }
+}
+
+fn total_count(page_total: u64) -> i32 {
+    i32::try_from(page_total).unwrap_or(i32::MAX)
+}
+
+pub struct UserEdge<Handler: BackendHandler> {
+    node: User<Handler>,
+    cursor: String,
+}
+
+#[graphql_object(context = Context<Handler>)]
+impl<Handler: BackendHandler> UserEdge<Handler> {
+    fn node(&self) -> &User<Handler> {
+        &self.node
+    }
+
+    /// The ID of the user. Passed as `after`, it gives the users that follow it, as long as
+    /// the user still exists.
+    fn cursor(&self) -> &str {
+        &self.cursor
+    }
+}
+
+pub struct UserConnection<Handler: BackendHandler> {
+    edges: Vec<UserEdge<Handler>>,
+    page_info: PageInfo,
+    total_count: i32,
+}
+
+impl<Handler: BackendHandler> UserConnection<Handler> {
+    pub fn new(
+        page: Page<UserAndGroups>,
+        schema: Arc<PublicSchema>,
+        has_previous_page: bool,
+    ) -> FieldResult<Self> {
+        let edges = page
+            .items
+            .into_iter()
+            .map(|user| {
+                let cursor = user.user.user_id.to_string();
+                Ok(UserEdge {
+                    node: User::<Handler>::from_user_and_groups(user, schema.clone())?,
+                    cursor,
+                })
+            })
+            .collect::<FieldResult<Vec<_>>>()?;
+        let cursors = edges.iter().map(|e| e.cursor.as_str()).collect::<Vec<_>>();
+        Ok(Self {
+            page_info: PageInfo::new(&cursors, page.has_next_page, has_previous_page),
+            edges,
+            total_count: total_count(page.total_count),
+        })
+    }
+}
+
+/// A page of users.
+#[graphql_object(context = Context<Handler>)]
+impl<Handler: BackendHandler> UserConnection<Handler> {
+    fn edges(&self) -> &[UserEdge<Handler>] {
+        &self.edges
+    }
+
+    fn page_info(&self) -> &PageInfo {
+        &self.page_info
+    }
+
+    /// The number of users matching the filters, in all the pages.
+    fn total_count(&self) -> i32 {
+        self.total_count
+    }
+}
+
+pub struct GroupEdge<Handler: BackendHandler> {
+    node: Group<Handler>,
+    cursor: String,
+}
+
+#[graphql_object(context = Context<Handler>)]
+impl<Handler: BackendHandler> GroupEdge<Handler> {
+    fn node(&self) -> &Group<Handler> {
+        &self.node
+    }
+
+    /// The ID of the group. Passed as `after`, it gives the groups that follow it, as long as
+    /// the group still exists.
+    fn cursor(&self) -> &str {
+        &self.cursor
+    }
+}
+
+pub struct GroupConnection<Handler: BackendHandler> {
+    edges: Vec<GroupEdge<Handler>>,
+    page_info: PageInfo,
+    total_count: i32,
+}
+
+impl<Handler: BackendHandler> GroupConnection<Handler> {
+    pub fn new(
+        page: Page<DomainGroup>,
+        schema: Arc<PublicSchema>,
+        has_previous_page: bool,
+    ) -> FieldResult<Self> {
+        let edges = page
+            .items
+            .into_iter()
+            .map(|group| {
+                let cursor = group.id.0.to_string();
+                Ok(GroupEdge {
+                    node: Group::<Handler>::from_group(group, schema.clone())?,
+                    cursor,
+                })
+            })
+            .collect::<FieldResult<Vec<_>>>()?;
+        let cursors = edges.iter().map(|e| e.cursor.as_str()).collect::<Vec<_>>();
+        Ok(Self {
+            page_info: PageInfo::new(&cursors, page.has_next_page, has_previous_page),
+            edges,
+            total_count: total_count(page.total_count),
+        })
+    }
+}
+
+/// A page of groups.
+#[graphql_object(context = Context<Handler>)]
+impl<Handler: BackendHandler> GroupConnection<Handler> {
+    fn edges(&self) -> &[GroupEdge<Handler>] {
+        &self.edges
+    }
+
+    fn page_info(&self) -> &PageInfo {
+        &self.page_info
+    }
+
+    /// The number of groups, in all the pages.
+    fn total_count(&self) -> i32 {
+        self.total_count
+    }
+}
@@ -2,11 +2,13 @@ use anyhow::Context as AnyhowContext;
 use juniper::{FieldResult, GraphQLInputObject};
 use lldap_domain::deserialize::deserialize_attribute_value;
 use lldap_domain::public_schema::PublicSchema;
-use lldap_domain::types::GroupId;
-use lldap_domain::types::UserId;
-use lldap_domain_handlers::handler::UserRequestFilter as DomainRequestFilter;
+use lldap_domain::types::{GroupId, UserId, Uuid};
+use lldap_domain_handlers::handler::{
+    ComparisonOperator, GroupRequestFilter as DomainGroupRequestFilter, SubStringFilter,
+    UserRequestFilter as DomainRequestFilter,
+};
 use lldap_domain_model::model::UserColumn;
-use lldap_ldap::{UserFieldType, map_user_field};
+use lldap_ldap::{GroupFieldType, UserFieldType, map_group_field, map_user_field};
 
 #[derive(PartialEq, Eq, Debug, GraphQLInputObject)]
 /// A filter for requests, specifying a boolean expression based on field constraints. Only one of
@@ -16,68 +18,158 @@ pub struct RequestFilter {
     all: Option<Vec<RequestFilter>>,
     not: Option<Box<RequestFilter>>,
     eq: Option<EqualityConstraint>,
+    /// Case-insensitive, only for the id, email and display name.
+    contains: Option<SubstringConstraint>,
+    /// Case-insensitive, only for the id, email and display name.
+    starts_with: Option<SubstringConstraint>,
+    /// Only for the creation, modification and password modification dates, not for the custom
+    /// attributes.
+    range: Option<RangeConstraint>,
+    /// The name of a field that must be set.
+    present: Option<String>,
     member_of: Option<String>,
     member_of_id: Option<i32>,
 }
 
 impl RequestFilter {
     pub fn try_into_domain_filter(self, schema: &PublicSchema) -> FieldResult<DomainRequestFilter> {
-        match (
-            self.eq,
-            self.any,
-            self.all,
-            self.not,
-            self.member_of,
-            self.member_of_id,
-        ) {
-            (Some(eq), None, None, None, None, None) => {
-                match map_user_field(&eq.field.as_str().into(), schema) {
-                    UserFieldType::NoMatch => {
-                        Err(format!("Unknown request filter: {}", &eq.field).into())
-                    }
-                    UserFieldType::PrimaryField(UserColumn::UserId) => {
-                        Ok(DomainRequestFilter::UserId(UserId::new(&eq.value)))
-                    }
-                    UserFieldType::PrimaryField(column) => {
-                        Ok(DomainRequestFilter::Equality(column, eq.value))
-                    }
-                    UserFieldType::Attribute(name, typ, false) => {
-                        let value = deserialize_attribute_value(&[eq.value], typ, false)
-                            .context(format!("While deserializing attribute {}", &name))?;
-                        Ok(DomainRequestFilter::AttributeEquality(name, value))
-                    }
-                    UserFieldType::Attribute(_, _, true) => {
-                        Err("Equality not supported for list fields".into())
-                    }
-                    UserFieldType::MemberOf => Ok(DomainRequestFilter::MemberOf(eq.value.into())),
-                    UserFieldType::ObjectClass | UserFieldType::Dn | UserFieldType::EntryDn => {
-                        Err("Ldap fields not supported in request filter".into())
-                    }
-                }
-            }
-            (None, Some(any), None, None, None, None) => Ok(DomainRequestFilter::Or(
+        let Self {
+            any,
+            all,
+            not,
+            eq,
+            contains,
+            starts_with,
+            range,
+            present,
+            member_of,
+            member_of_id,
+        } = self;
+        check_single_field(&[
+            any.is_some(),
+            all.is_some(),
+            not.is_some(),
+            eq.is_some(),
+            contains.is_some(),
+            starts_with.is_some(),
+            range.is_some(),
+            present.is_some(),
+            member_of.is_some(),
+            member_of_id.is_some(),
+        ])?;
+        if let Some(eq) = eq {
+            user_equality_filter(eq, schema)
+        } else if let Some(any) = any {
+            Ok(DomainRequestFilter::Or(
                 any.into_iter()
                     .map(|f| f.try_into_domain_filter(schema))
                     .collect::<FieldResult<Vec<_>>>()?,
-            )),
-            (None, None, Some(all), None, None, None) => Ok(DomainRequestFilter::And(
+            ))
+        } else if let Some(all) = all {
+            Ok(DomainRequestFilter::And(
                 all.into_iter()
                     .map(|f| f.try_into_domain_filter(schema))
                     .collect::<FieldResult<Vec<_>>>()?,
-            )),
-            (None, None, None, Some(not), None, None) => Ok(DomainRequestFilter::Not(Box::new(
+            ))
+        } else if let Some(not) = not {
+            Ok(DomainRequestFilter::Not(Box::new(
                 (*not).try_into_domain_filter(schema)?,
-            ))),
-            (None, None, None, None, Some(group), None) => {
-                Ok(DomainRequestFilter::MemberOf(group.into()))
-            }
-            (None, None, None, None, None, Some(group_id)) => {
-                Ok(DomainRequestFilter::MemberOfId(GroupId(group_id)))
-            }
-            (None, None, None, None, None, None) => {
-                Err("No field specified in request filter".into())
-            }
-            _ => Err("Multiple fields specified in request filter".into()),
+            )))
+        } else if let Some(contains) = contains {
+            user_substring_filter(&contains.field, contains.contains_filter(), schema)
+        } else if let Some(starts_with) = starts_with {
+            user_substring_filter(&starts_with.field, starts_with.starts_with_filter(), schema)
+        } else if let Some(range) = range {
+            user_range_filter(range, schema)
+        } else if let Some(field) = present {
+            user_presence_filter(field, schema)
+        } else if let Some(group) = member_of {
+            Ok(DomainRequestFilter::MemberOf(group.into()))
+        } else if let Some(group_id) = member_of_id {
+            Ok(DomainRequestFilter::MemberOfId(GroupId(group_id)))
+        } else {
+            Err("No field specified in request filter".into())
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, GraphQLInputObject)]
+/// A filter for group requests, specifying a boolean expression based on field constraints. Only
+/// one of the fields can be set at a time.
+pub struct GroupRequestFilter {
+    any: Option<Vec<GroupRequestFilter>>,
+    all: Option<Vec<GroupRequestFilter>>,
+    not: Option<Box<GroupRequestFilter>>,
+    eq: Option<EqualityConstraint>,
+    /// Case-insensitive, only for the display name.
+    contains: Option<SubstringConstraint>,
+    /// Case-insensitive, only for the display name.
+    starts_with: Option<SubstringConstraint>,
+    /// Only for the id, creation and modification dates, not for the custom attributes.
+    range: Option<RangeConstraint>,
+    /// The name of a field that must be set.
+    present: Option<String>,
+    /// The id of a user that must be a member of the group.
+    member: Option<String>,
+}
+
+impl GroupRequestFilter {
+    pub fn try_into_domain_filter(
+        self,
+        schema: &PublicSchema,
+    ) -> FieldResult<DomainGroupRequestFilter> {
+        let Self {
+            any,
+            all,
+            not,
+            eq,
+            contains,
+            starts_with,
+            range,
+            present,
+            member,
+        } = self;
+        check_single_field(&[
+            any.is_some(),
+            all.is_some(),
+            not.is_some(),
+            eq.is_some(),
+            contains.is_some(),
+            starts_with.is_some(),
+            range.is_some(),
+            present.is_some(),
+            member.is_some(),
+        ])?;
+        if let Some(eq) = eq {
+            group_equality_filter(eq, schema)
+        } else if let Some(any) = any {
+            Ok(DomainGroupRequestFilter::Or(
+                any.into_iter()
+                    .map(|f| f.try_into_domain_filter(schema))
+                    .collect::<FieldResult<Vec<_>>>()?,
+            ))
+        } else if let Some(all) = all {
+            Ok(DomainGroupRequestFilter::And(
+                all.into_iter()
+                    .map(|f| f.try_into_domain_filter(schema))
+                    .collect::<FieldResult<Vec<_>>>()?,
+            ))
+        } else if let Some(not) = not {
+            Ok(DomainGroupRequestFilter::Not(Box::new(
+                (*not).try_into_domain_filter(schema)?,
+            )))
+        } else if let Some(contains) = contains {
+            group_substring_filter(&contains.field, contains.contains_filter(), schema)
+        } else if let Some(starts_with) = starts_with {
+            group_substring_filter(&starts_with.field, starts_with.starts_with_filter(), schema)
+        } else if let Some(range) = range {
+            group_range_filter(range, schema)
+        } else if let Some(field) = present {
+            group_presence_filter(field, schema)
+        } else if let Some(user) = member {
+            Ok(DomainGroupRequestFilter::Member(UserId::new(&user)))
+        } else {
+            Err("No field specified in request filter".into())
         }
     }
 }
@@ -87,3 +179,281 @@ pub struct EqualityConstraint {
     field: String,
     value: String,
 }
+
+#[derive(PartialEq, Eq, Debug, GraphQLInputObject)]
+pub struct SubstringConstraint {
+    field: String,
+    value: String,
+}
+
+impl SubstringConstraint {
+    fn contains_filter(&self) -> SubStringFilter {
+        SubStringFilter {
+            initial: None,
+            any: vec![self.value.clone()],
+            final_: None,
+        }
+    }
+
+    fn starts_with_filter(&self) -> SubStringFilter {
+        SubStringFilter {
+            initial: Some(self.value.clone()),
+            any: Vec::new(),
+            final_: None,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, GraphQLInputObject)]
+/// The bounds of a date field, or of the group id. At least one of them must be set. Dates are in
+/// the RFC 3339 format, e.g. "2024-01-31T00:00:00Z". The custom attributes are not supported.
+pub struct RangeConstraint {
+    field: String,
+    greater_than: Option<String>,
+    greater_or_equal: Option<String>,
+    less_than: Option<String>,
+    less_or_equal: Option<String>,
+}
+
+impl RangeConstraint {
+    /// Builds the filter for each bound, combined with `and` if there are several.
+    fn into_domain_filter<Filter>(
+        self,
+        make_filter: impl Fn(ComparisonOperator, &str) -> FieldResult<Filter>,
+        and: impl FnOnce(Vec<Filter>) -> Filter,
+    ) -> FieldResult<Filter> {
+        let mut filters = [
+            (ComparisonOperator::GreaterThan, self.greater_than),
+            (ComparisonOperator::GreaterOrEqual, self.greater_or_equal),
+            (ComparisonOperator::LessThan, self.less_than),
+            (ComparisonOperator::LessOrEqual, self.less_or_equal),
+        ]
+        .into_iter()
+        .filter_map(|(operator, value)| value.map(|value| make_filter(operator, &value)))
+        .collect::<FieldResult<Vec<_>>>()?;
+        match filters.len() {
+            0 => Err("No bound specified in range filter".into()),
+            1 => Ok(filters.remove(0)),
+            _ => Ok(and(filters)),
+        }
+    }
+}
+
+fn check_single_field(fields_set: &[bool]) -> FieldResult<()> {
+    if fields_set.iter().filter(|set| **set).count() > 1 {
+        Err("Multiple fields specified in request filter".into())
+    } else {
+        Ok(())
+    }
+}
+
+fn parse_date(value: &str) -> FieldResult<chrono::NaiveDateTime> {
+    Ok(chrono::DateTime::parse_from_rfc3339(value)
+        .with_context(|| format!("Invalid date: {value}"))?
+        .naive_utc())
+}
+
+fn user_equality_filter(
+    eq: EqualityConstraint,
+    schema: &PublicSchema,
+) -> FieldResult<DomainRequestFilter> {
+    match map_user_field(&eq.field.as_str().into(), schema) {
+        UserFieldType::NoMatch => Err(format!("Unknown request filter: {}", &eq.field).into()),
+        UserFieldType::PrimaryField(UserColumn::UserId) => {
+            Ok(DomainRequestFilter::UserId(UserId::new(&eq.value)))
+        }
+        UserFieldType::PrimaryField(UserColumn::EmailVerified) => {
+            match eq.value.to_ascii_lowercase().as_str() {
+                "true" => Ok(DomainRequestFilter::EmailVerified(true)),
+                "false" => Ok(DomainRequestFilter::EmailVerified(false)),
+                _ => Err(format!("Invalid boolean value: {}", &eq.value).into()),
+            }
+        }
+        UserFieldType::PrimaryField(column) => Ok(DomainRequestFilter::Equality(column, eq.value)),
+        UserFieldType::Attribute(name, typ, false) => {
+            let value = deserialize_attribute_value(&[eq.value], typ, false)
+                .context(format!("While deserializing attribute {}", &name))?;
+            Ok(DomainRequestFilter::AttributeEquality(name, value))
+        }
+        UserFieldType::Attribute(_, _, true) => {
+            Err("Equality not supported for list fields".into())
+        }
+        UserFieldType::MemberOf => Ok(DomainRequestFilter::MemberOf(eq.value.into())),
+        UserFieldType::ObjectClass | UserFieldType::Dn | UserFieldType::EntryDn => {
+            Err("Ldap fields not supported in request filter".into())
+        }
+    }
+}
+
+fn user_substring_filter(
+    field: &str,
+    filter: SubStringFilter,
+    schema: &PublicSchema,
+) -> FieldResult<DomainRequestFilter> {
+    match map_user_field(&field.into(), schema) {
+        UserFieldType::NoMatch => Err(format!("Unknown request filter: {}", &field).into()),
+        UserFieldType::PrimaryField(UserColumn::UserId) => {
+            Ok(DomainRequestFilter::UserIdSubString(filter))
+        }
+        UserFieldType::PrimaryField(UserColumn::Email) => Ok(DomainRequestFilter::SubString(
+            UserColumn::LowercaseEmail,
+            filter,
+        )),
+        UserFieldType::PrimaryField(UserColumn::DisplayName) => Ok(DomainRequestFilter::SubString(
+            UserColumn::DisplayName,
+            filter,
+        )),
+        _ => Err(format!("Substring filters are not supported on {}", &field).into()),
+    }
+}
+
+fn user_range_filter(
+    range: RangeConstraint,
+    schema: &PublicSchema,
+) -> FieldResult<DomainRequestFilter> {
+    match map_user_field(&range.field.as_str().into(), schema) {
+        UserFieldType::NoMatch => Err(format!("Unknown request filter: {}", &range.field).into()),
+        UserFieldType::PrimaryField(
+            column @ (UserColumn::CreationDate
+            | UserColumn::ModifiedDate
+            | UserColumn::PasswordModifiedDate),
+        ) => range.into_domain_filter(
+            |operator, value| {
+                Ok(DomainRequestFilter::DateComparison(
+                    column,
+                    operator,
+                    parse_date(value)?,
+                ))
+            },
+            DomainRequestFilter::And,
+        ),
+        UserFieldType::Attribute(..) => Err(format!(
+            "Range filters are not supported on custom attributes: {}",
+            &range.field
+        )
+        .into()),
+        _ => Err(format!("Range filters are not supported on {}", &range.field).into()),
+    }
+}
+
+fn user_presence_filter(field: String, schema: &PublicSchema) -> FieldResult<DomainRequestFilter> {
+    match map_user_field(&field.as_str().into(), schema) {
+        UserFieldType::NoMatch => Err(format!("Unknown request filter: {}", &field).into()),
+        UserFieldType::Attribute(name, _, _) => {
+            Ok(DomainRequestFilter::CustomAttributePresent(name))
+        }
+        // Empty display names are stored as NULL, which doesn't match the inner filter either.
+        UserFieldType::PrimaryField(UserColumn::DisplayName) => {
+            Ok(DomainRequestFilter::Not(Box::new(
+                DomainRequestFilter::Equality(UserColumn::DisplayName, String::new()),
+            )))
+        }
+        _ => Ok(DomainRequestFilter::True),
+    }
+}
+
+fn group_equality_filter(
+    eq: EqualityConstraint,
+    schema: &PublicSchema,
+) -> FieldResult<DomainGroupRequestFilter> {
+    match map_group_field(&eq.field.as_str().into(), schema) {
+        GroupFieldType::NoMatch => Err(format!("Unknown request filter: {}", &eq.field).into()),
+        GroupFieldType::GroupId => Ok(DomainGroupRequestFilter::GroupId(GroupId(
+            eq.value
+                .parse()
+                .with_context(|| format!("Invalid group id: {}", &eq.value))?,
+        ))),
+        GroupFieldType::DisplayName => Ok(DomainGroupRequestFilter::DisplayName(eq.value.into())),
+        GroupFieldType::Uuid => Ok(DomainGroupRequestFilter::Uuid(Uuid::try_from(
+            eq.value.as_str(),
+        )?)),
+        GroupFieldType::Member => Ok(DomainGroupRequestFilter::Member(UserId::new(&eq.value))),
+        GroupFieldType::Attribute(name, typ, false) => {
+            let value = deserialize_attribute_value(&[eq.value], typ, false)
+                .context(format!("While deserializing attribute {}", &name))?;
+            Ok(DomainGroupRequestFilter::AttributeEquality(name, value))
+        }
+        GroupFieldType::Attribute(_, _, true) => {
+            Err("Equality not supported for list fields".into())
+        }
+        GroupFieldType::CreationDate | GroupFieldType::ModifiedDate => {
+            Err("Equality not supported for dates, use a range".into())
+        }
+        GroupFieldType::ObjectClass
+        | GroupFieldType::Dn
+        | GroupFieldType::EntryDn
+        | GroupFieldType::Owner => Err("Ldap fields not supported in request filter".into()),
+    }
+}
+
+fn group_substring_filter(
+    field: &str,
+    filter: SubStringFilter,
+    schema: &PublicSchema,
+) -> FieldResult<DomainGroupRequestFilter> {
+    match map_group_field(&field.into(), schema) {
+        GroupFieldType::NoMatch => Err(format!("Unknown request filter: {}", &field).into()),
+        GroupFieldType::DisplayName => Ok(DomainGroupRequestFilter::DisplayNameSubString(filter)),
+        _ => Err(format!("Substring filters are not supported on {}", &field).into()),
+    }
+}
+
+fn group_range_filter(
+    range: RangeConstraint,
+    schema: &PublicSchema,
+) -> FieldResult<DomainGroupRequestFilter> {
+    let and = DomainGroupRequestFilter::And;
+    match map_group_field(&range.field.as_str().into(), schema) {
+        GroupFieldType::NoMatch => Err(format!("Unknown request filter: {}", &range.field).into()),
+        GroupFieldType::GroupId => range.into_domain_filter(
+            |operator, value| {
+                Ok(DomainGroupRequestFilter::GroupIdComparison(
+                    operator,
+                    GroupId(
+                        value
+                            .parse()
+                            .with_context(|| format!("Invalid group id: {value}"))?,
+                    ),
+                ))
+            },
+            and,
+        ),
+        GroupFieldType::CreationDate => range.into_domain_filter(
+            |operator, value| {
+                Ok(DomainGroupRequestFilter::CreationDateComparison(
+                    operator,
+                    parse_date(value)?,
+                ))
+            },
+            and,
+        ),
+        GroupFieldType::ModifiedDate => range.into_domain_filter(
+            |operator, value| {
+                Ok(DomainGroupRequestFilter::ModifiedDateComparison(
+                    operator,
+                    parse_date(value)?,
+                ))
+            },
+            and,
+        ),
+        GroupFieldType::Attribute(..) => Err(format!(
+            "Range filters are not supported on custom attributes: {}",
+            &range.field
+        )
+        .into()),
+        _ => Err(format!("Range filters are not supported on {}", &range.field).into()),
+    }
+}
+
+fn group_presence_filter(
+    field: String,
+    schema: &PublicSchema,
+) -> FieldResult<DomainGroupRequestFilter> {
+    match map_group_field(&field.as_str().into(), schema) {
+        GroupFieldType::NoMatch => Err(format!("Unknown request filter: {}", &field).into()),
+        GroupFieldType::Attribute(name, _, _) => {
+            Ok(DomainGroupRequestFilter::CustomAttributePresent(name))
+        }
+        _ => Ok(DomainGroupRequestFilter::True),
+    }
+}
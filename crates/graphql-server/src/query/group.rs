@@ -1,12 +1,15 @@
 use chrono::TimeZone;
 use juniper::{FieldResult, graphql_object};
-use lldap_access_control::ReadonlyBackendHandler;
+use lldap_access_control::{OwnedGroupBackendHandler, ReadonlyBackendHandler};
 use lldap_domain::public_schema::PublicSchema;
-use lldap_domain::types::{Group as DomainGroup, GroupDetails, GroupId};
-use lldap_domain_handlers::handler::{BackendHandler, UserRequestFilter as DomainRequestFilter};
+use lldap_domain::types::{Group as DomainGroup, GroupDetails, GroupId, GroupOwner};
+use lldap_domain_handlers::handler::{
+    BackendHandler, GroupRequestFilter as DomainGroupRequestFilter,
+    UserRequestFilter as DomainRequestFilter,
+};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tracing::{Instrument, debug, debug_span};
+use tracing::{Instrument, Span, debug, debug_span};
 
 use super::attribute::AttributeValue;
 use super::user::User;
@@ -19,6 +22,7 @@ pub struct Group<Handler: BackendHandler> {
     pub display_name: String,
     creation_date: chrono::NaiveDateTime,
     uuid: String,
+    dynamic_filter: Option<String>,
     attributes: Vec<AttributeValue<Handler>>,
     pub schema: Arc<PublicSchema>,
     _phantom: std::marker::PhantomData<Box<Handler>>,
@@ -36,6 +40,7 @@ impl<Handler: BackendHandler> Group<Handler> {
             display_name: group.display_name.to_string(),
             creation_date: group.creation_date,
             uuid: group.uuid.into_string(),
+            dynamic_filter: group.dynamic_filter,
             attributes,
             schema,
             _phantom: std::marker::PhantomData,
@@ -55,6 +60,7 @@ impl<Handler: BackendHandler> Group<Handler> {
             display_name: group_details.display_name.to_string(),
             creation_date: group_details.creation_date,
             uuid: group_details.uuid.into_string(),
+            dynamic_filter: group_details.dynamic_filter,
             attributes,
             schema,
             _phantom: std::marker::PhantomData,
@@ -62,6 +68,30 @@ impl<Handler: BackendHandler> Group<Handler> {
     }
 }
 
+impl<Handler: BackendHandler> Group<Handler> {
+    async fn get_owners(
+        &self,
+        context: &Context<Handler>,
+        span: &Span,
+    ) -> FieldResult<Vec<GroupOwner>> {
+        let handler = context
+            .get_readonly_handler()
+            .ok_or_else(field_error_callback(
+                span,
+                "Unauthorized access to group data",
+            ))?;
+        Ok(handler
+            .list_groups(Some(DomainGroupRequestFilter::GroupId(GroupId(
+                self.group_id,
+            ))))
+            .instrument(span.clone())
+            .await?
+            .into_iter()
+            .flat_map(|g| g.owners)
+            .collect())
+    }
+}
+
 impl<Handler: BackendHandler> Clone for Group<Handler> {
     fn clone(&self) -> Self {
         Self {
@@ -69,6 +99,7 @@ impl<Handler: BackendHandler> Clone for Group<Handler> {
             display_name: self.display_name.clone(),
             creation_date: self.creation_date,
             uuid: self.uuid.clone(),
+            dynamic_filter: self.dynamic_filter.clone(),
             attributes: self.attributes.clone(),
             schema: self.schema.clone(),
             _phantom: std::marker::PhantomData,
@@ -90,6 +121,10 @@ impl<Handler: BackendHandler> Group<Handler> {
     fn uuid(&self) -> String {
         self.uuid.clone()
     }
+    /// For a dynamic group, the LDAP filter matching its members.
+    fn dynamic_filter(&self) -> Option<String> {
+        self.dynamic_filter.clone()
+    }
 
     /// User-defined attributes.
     fn attributes(&self) -> &[AttributeValue<Handler>] {
@@ -102,22 +137,99 @@ impl<Handler: BackendHandler> Group<Handler> {
         span.in_scope(|| {
             debug!(name = %self.display_name);
         });
+        let domain_users = match context.get_readonly_handler() {
+            Some(handler) => {
+                handler
+                    .list_users(
+                        Some(DomainRequestFilter::MemberOfId(GroupId(self.group_id))),
+                        false,
+                    )
+                    .instrument(span)
+                    .await?
+            }
+            None => {
+                context
+                    .get_owned_group_handler(GroupId(self.group_id))
+                    .instrument(span.clone())
+                    .await?
+                    .ok_or_else(field_error_callback(
+                        &span,
+                        "Unauthorized access to group data",
+                    ))?
+                    .list_members()
+                    .instrument(span)
+                    .await?
+            }
+        };
+        domain_users
+            .into_iter()
+            .map(|u| User::<Handler>::from_user_and_groups(u, self.schema.clone()))
+            .collect()
+    }
+
+    /// The users allowed to manage the members of this group.
+    async fn owner_users(&self, context: &Context<Handler>) -> FieldResult<Vec<User<Handler>>> {
+        let span = debug_span!("[GraphQL query] group::owner_users");
+        span.in_scope(|| {
+            debug!(name = %self.display_name);
+        });
+        let user_ids = self
+            .get_owners(context, &span)
+            .await?
+            .into_iter()
+            .filter_map(|owner| match owner {
+                GroupOwner::User(user_id) => Some(DomainRequestFilter::UserId(user_id)),
+                GroupOwner::Group(_) => None,
+            })
+            .collect::<Vec<_>>();
+        if user_ids.is_empty() {
+            return Ok(Vec::new());
+        }
         let handler = context
             .get_readonly_handler()
             .ok_or_else(field_error_callback(
                 &span,
                 "Unauthorized access to group data",
             ))?;
-        let domain_users = handler
-            .list_users(
-                Some(DomainRequestFilter::MemberOfId(GroupId(self.group_id))),
-                false,
-            )
+        handler
+            .list_users(Some(DomainRequestFilter::Or(user_ids)), false)
             .instrument(span)
-            .await?;
-        domain_users
+            .await?
             .into_iter()
             .map(|u| User::<Handler>::from_user_and_groups(u, self.schema.clone()))
             .collect()
     }
+
+    /// The groups whose members are allowed to manage the members of this group.
+    async fn owner_groups(&self, context: &Context<Handler>) -> FieldResult<Vec<Group<Handler>>> {
+        let span = debug_span!("[GraphQL query] group::owner_groups");
+        span.in_scope(|| {
+            debug!(name = %self.display_name);
+        });
+        let names = self
+            .get_owners(context, &span)
+            .await?
+            .into_iter()
+            .filter_map(|owner| match owner {
+                GroupOwner::Group(name) => Some(DomainGroupRequestFilter::DisplayName(name)),
+                GroupOwner::User(_) => None,
+            })
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            return Ok(Vec::new());
+        }
+        let handler = context
+            .get_readonly_handler()
+            .ok_or_else(field_error_callback(
+                &span,
+                "Unauthorized access to group data",
+            ))?;
+        handler
+            .list_groups(Some(DomainGroupRequestFilter::Or(names)))
+            .instrument(span)
+            .await?
+            .into_iter()
+            .map(|g| Group::<Handler>::from_group(g, self.schema.clone()))
+            .collect()
+    }
 }
@@ -1,18 +1,28 @@
 pub mod attribute;
+pub mod audit;
+pub mod connection;
 pub mod filters;
 pub mod group;
 pub mod schema;
 pub mod user;
 
 // Re-export public types
-pub use attribute::{AttributeSchema, AttributeValue, serialize_attribute_to_graphql};
-pub use filters::{EqualityConstraint, RequestFilter};
+pub use attribute::{
+    AttributeSchema, AttributeValidation, AttributeValue, serialize_attribute_to_graphql,
+};
+pub use audit::{AuditLogEntry, AuditLogFilter};
+pub use connection::{GroupConnection, GroupOrder, UserConnection, UserOrder};
+pub use filters::{
+    EqualityConstraint, GroupRequestFilter, RangeConstraint, RequestFilter, SubstringConstraint,
+};
 pub use group::Group;
 pub use schema::{AttributeList, ObjectClassInfo, Schema};
 pub use user::User;
 
 use juniper::{FieldResult, graphql_object};
-use lldap_access_control::{ReadonlyBackendHandler, UserReadableBackendHandler};
+use lldap_access_control::{
+    OwnedGroupBackendHandler, ReadonlyBackendHandler, UserReadableBackendHandler,
+};
 use lldap_domain::public_schema::PublicSchema;
 use lldap_domain::types::{GroupId, UserId};
 use lldap_domain_handlers::handler::{BackendHandler, ReadSchemaBackendHandler};
@@ -21,6 +31,9 @@ use tracing::{Instrument, Span, debug, debug_span};
 
 use crate::api::{Context, field_error_callback};
 
+const DEFAULT_AUDIT_LOG_LIMIT: i32 = 100;
+const MAX_AUDIT_LOG_LIMIT: i32 = 1000;
+
 #[derive(PartialEq, Eq, Debug)]
 /// The top-level GraphQL query type.
 pub struct Query<Handler: BackendHandler> {
@@ -96,8 +109,14 @@ impl<Handler: BackendHandler> Query<Handler> {
             .collect()
     }
 
-    async fn groups(context: &Context<Handler>) -> FieldResult<Vec<Group<Handler>>> {
+    async fn groups(
+        context: &Context<Handler>,
+        #[graphql(name = "where")] filters: Option<GroupRequestFilter>,
+    ) -> FieldResult<Vec<Group<Handler>>> {
         let span = debug_span!("[GraphQL query] groups");
+        span.in_scope(|| {
+            debug!(?filters);
+        });
         let handler = context
             .get_readonly_handler()
             .ok_or_else(field_error_callback(
@@ -105,29 +124,115 @@ impl<Handler: BackendHandler> Query<Handler> {
                 "Unauthorized access to group list",
             ))?;
         let schema = Arc::new(self.get_schema(context, span.clone()).await?);
-        let domain_groups = handler.list_groups(None).instrument(span).await?;
+        let domain_groups = handler
+            .list_groups(
+                filters
+                    .map(|f| f.try_into_domain_filter(&schema))
+                    .transpose()?,
+            )
+            .instrument(span)
+            .await?;
         domain_groups
             .into_iter()
             .map(|g| Group::<Handler>::from_group(g, schema.clone()))
             .collect()
     }
 
-    async fn group(context: &Context<Handler>, group_id: i32) -> FieldResult<Group<Handler>> {
-        let span = debug_span!("[GraphQL query] group");
+    /// A page of the users, sorted by id by default. Pass the `endCursor` of a page as `after`
+    /// to get the next one. Fails if the user of the cursor was deleted in the meantime.
+    async fn users_connection(
+        context: &Context<Handler>,
+        filters: Option<RequestFilter>,
+        order_by: Option<UserOrder>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> FieldResult<UserConnection<Handler>> {
+        let span = debug_span!("[GraphQL query] users_connection");
         span.in_scope(|| {
-            debug!(?group_id);
+            debug!(?filters, ?order_by, ?first, ?after);
+        });
+        let handler = context
+            .get_readonly_handler()
+            .ok_or_else(field_error_callback(
+                &span,
+                "Unauthorized access to user list",
+            ))?;
+        let has_previous_page = after.is_some();
+        let page_request = connection::make_user_page_request(order_by, first, after)?;
+        let schema = Arc::new(self.get_schema(context, span.clone()).await?);
+        let page = handler
+            .list_users_page(
+                filters
+                    .map(|f| f.try_into_domain_filter(&schema))
+                    .transpose()?,
+                page_request,
+            )
+            .instrument(span)
+            .await?;
+        UserConnection::new(page, schema, has_previous_page)
+    }
+
+    /// A page of the groups, sorted by display name by default. Pass the `endCursor` of a page
+    /// as `after` to get the next one. Fails if the group of the cursor was deleted in the
+    /// meantime.
+    async fn groups_connection(
+        context: &Context<Handler>,
+        filters: Option<GroupRequestFilter>,
+        order_by: Option<GroupOrder>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> FieldResult<GroupConnection<Handler>> {
+        let span = debug_span!("[GraphQL query] groups_connection");
+        span.in_scope(|| {
+            debug!(?filters, ?order_by, ?first, ?after);
         });
         let handler = context
             .get_readonly_handler()
             .ok_or_else(field_error_callback(
                 &span,
-                "Unauthorized access to group data",
+                "Unauthorized access to group list",
             ))?;
+        let has_previous_page = after.is_some();
+        let page_request = connection::make_group_page_request(order_by, first, after)?;
         let schema = Arc::new(self.get_schema(context, span.clone()).await?);
-        let group_details = handler
-            .get_group_details(GroupId(group_id))
+        let filters = filters
+            .map(|f| f.try_into_domain_filter(&schema))
+            .transpose()?;
+        let page = handler
+            .list_groups_page(filters, page_request)
             .instrument(span)
             .await?;
+        GroupConnection::new(page, schema, has_previous_page)
+    }
+
+    async fn group(context: &Context<Handler>, group_id: i32) -> FieldResult<Group<Handler>> {
+        let span = debug_span!("[GraphQL query] group");
+        span.in_scope(|| {
+            debug!(?group_id);
+        });
+        // The owners of a group can see it, to manage its members.
+        let group_details = match context.get_readonly_handler() {
+            Some(handler) => {
+                handler
+                    .get_group_details(GroupId(group_id))
+                    .instrument(span.clone())
+                    .await?
+            }
+            None => {
+                context
+                    .get_owned_group_handler(GroupId(group_id))
+                    .instrument(span.clone())
+                    .await?
+                    .ok_or_else(field_error_callback(
+                        &span,
+                        "Unauthorized access to group data",
+                    ))?
+                    .get_group_details()
+                    .instrument(span.clone())
+                    .await?
+            }
+        };
+        let schema = Arc::new(self.get_schema(context, span).await?);
         Group::<Handler>::from_group_details(group_details, schema.clone())
     }
 
@@ -135,6 +240,40 @@ impl<Handler: BackendHandler> Query<Handler> {
         let span = debug_span!("[GraphQL query] get_schema");
         self.get_schema(context, span).await.map(Into::into)
     }
+
+    /// The most recent entries of the audit log first. Only available to admins.
+    async fn audit_log(
+        context: &Context<Handler>,
+        filter: Option<AuditLogFilter>,
+        limit: Option<i32>,
+    ) -> FieldResult<Vec<AuditLogEntry>> {
+        let span = debug_span!("[GraphQL query] audit_log");
+        span.in_scope(|| {
+            debug!(?filter, ?limit);
+        });
+        let handler = context
+            .handler
+            .get_audit_log_handler(&context.validation_result)
+            .ok_or_else(field_error_callback(
+                &span,
+                "Unauthorized access to the audit log",
+            ))?;
+        let limit = limit.unwrap_or(DEFAULT_AUDIT_LOG_LIMIT);
+        if !(1..=MAX_AUDIT_LOG_LIMIT).contains(&limit) {
+            return Err(format!("The limit must be between 1 and {MAX_AUDIT_LOG_LIMIT}").into());
+        }
+        let filter = filter
+            .map(AuditLogFilter::try_into_domain_filter)
+            .transpose()?
+            .unwrap_or_default();
+        Ok(handler
+            .list_audit_events(filter, limit as u64)
+            .instrument(span)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
 }
 
 impl<Handler: BackendHandler> Query<Handler> {
@@ -166,9 +305,13 @@ mod tests {
     use lldap_domain::schema::AttributeSchema as DomainAttributeSchema;
     use lldap_domain::types::{Attribute as DomainAttribute, GroupDetails, User as DomainUser};
     use lldap_domain::{
-        schema::{AttributeList, Schema},
+        schema::{AttributeList, AttributePermissions, Schema},
         types::{AttributeName, AttributeType, LdapObjectClass},
     };
+    use lldap_domain_handlers::handler::{
+        ComparisonOperator, GroupRequestFilter as DomainGroupRequestFilter, SubStringFilter,
+        UserRequestFilter as DomainRequestFilter,
+    };
     use lldap_domain_model::model::UserColumn;
     use lldap_test_utils::{MockTestBackendHandler, setup_default_schema};
     use mockall::predicate::eq;
@@ -192,6 +335,7 @@ mod tests {
           user(userId: "bob") {
             id
             email
+            emailVerified
             creationDate
             firstName
             lastName
@@ -226,6 +370,8 @@ mod tests {
                             is_editable: true,
                             is_hardcoded: true,
                             is_readonly: false,
+                            permissions: Default::default(),
+                            validation: Default::default(),
                         },
                         DomainAttributeSchema {
                             name: "last_name".into(),
@@ -235,6 +381,8 @@ mod tests {
                             is_editable: true,
                             is_hardcoded: true,
                             is_readonly: false,
+                            permissions: Default::default(),
+                            validation: Default::default(),
                         },
                     ],
                 },
@@ -247,6 +395,8 @@ mod tests {
                         is_editable: true,
                         is_hardcoded: false,
                         is_readonly: false,
+                        permissions: Default::default(),
+                        validation: Default::default(),
                     }],
                 },
                 extra_user_object_classes: vec![
@@ -266,6 +416,7 @@ mod tests {
                     creation_date: chrono::Utc.timestamp_millis_opt(42).unwrap().naive_utc(),
                     modified_date: chrono::Utc.timestamp_opt(0, 0).unwrap().naive_utc(),
                     password_modified_date: chrono::Utc.timestamp_opt(0, 0).unwrap().naive_utc(),
+                    email_verified: true,
                     uuid: lldap_domain::types::Uuid::from_name_and_date(
                         "bob",
                         &chrono::Utc.timestamp_millis_opt(42).unwrap().naive_utc(),
@@ -291,6 +442,7 @@ mod tests {
                 "Bobbersons",
                 &chrono::Utc.timestamp_nanos(42).naive_utc(),
             ),
+            dynamic_filter: None,
             attributes: vec![DomainAttribute {
                 name: "club_name".into(),
                 value: "Gang of Four".to_string().into(),
@@ -305,6 +457,7 @@ mod tests {
                 "Jefferees",
                 &chrono::Utc.timestamp_nanos(12).naive_utc(),
             ),
+            dynamic_filter: None,
             attributes: Vec::new(),
             modified_date: chrono::Utc.timestamp_nanos(12).naive_utc(),
         });
@@ -382,6 +535,7 @@ mod tests {
                                 .timestamp_opt(0, 0)
                                 .unwrap()
                                 .naive_utc(),
+                            email_verified: false,
                             uuid: lldap_domain::types::Uuid::from_name_and_date(
                                 "bob",
                                 &chrono::Utc.timestamp_opt(0, 0).unwrap().naive_utc(),
@@ -401,6 +555,7 @@ mod tests {
                                 .timestamp_opt(0, 0)
                                 .unwrap()
                                 .naive_utc(),
+                            email_verified: false,
                             uuid: lldap_domain::types::Uuid::from_name_and_date(
                                 "robert",
                                 &chrono::Utc.timestamp_opt(0, 0).unwrap().naive_utc(),
@@ -442,6 +597,272 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn list_users_with_substring_range_and_presence_filters() {
+        const QUERY: &str = r#"{
+          users(filters: {
+            all: [
+              {contains: {field: "email", value: "bobbers"}},
+              {startsWith: {field: "id", value: "b"}},
+              {range: {
+                field: "creationDate"
+                greaterOrEqual: "2024-01-01T00:00:00Z"
+                lessThan: "2025-01-01T00:00:00+02:00"
+              }},
+              {present: "first_name"}
+            ]}) {
+            id
+          }
+        }"#;
+
+        let mut mock = MockTestBackendHandler::new();
+        setup_default_schema(&mut mock);
+        let date = |y, m, d, h| {
+            chrono::Utc
+                .with_ymd_and_hms(y, m, d, h, 0, 0)
+                .unwrap()
+                .naive_utc()
+        };
+        mock.expect_list_users()
+            .with(
+                eq(Some(DomainRequestFilter::And(vec![
+                    DomainRequestFilter::SubString(
+                        UserColumn::LowercaseEmail,
+                        SubStringFilter {
+                            initial: None,
+                            any: vec!["bobbers".to_owned()],
+                            final_: None,
+                        },
+                    ),
+                    DomainRequestFilter::UserIdSubString(SubStringFilter {
+                        initial: Some("b".to_owned()),
+                        any: Vec::new(),
+                        final_: None,
+                    }),
+                    DomainRequestFilter::And(vec![
+                        DomainRequestFilter::DateComparison(
+                            UserColumn::CreationDate,
+                            ComparisonOperator::GreaterOrEqual,
+                            date(2024, 1, 1, 0),
+                        ),
+                        DomainRequestFilter::DateComparison(
+                            UserColumn::CreationDate,
+                            ComparisonOperator::LessThan,
+                            date(2024, 12, 31, 22),
+                        ),
+                    ]),
+                    DomainRequestFilter::CustomAttributePresent("first_name".into()),
+                ]))),
+                eq(false),
+            )
+            .return_once(|_, _| Ok(Vec::new()));
+
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            mock,
+            ValidationResults {
+                user: UserId::new("admin"),
+                permission: Permission::Admin,
+            },
+        );
+
+        let schema = schema(Query::<MockTestBackendHandler>::new());
+        assert_eq!(
+            execute(QUERY, None, &schema, &Variables::new(), &context).await,
+            Ok((graphql_value!({"users": []}), vec![]))
+        );
+    }
+
+    #[tokio::test]
+    async fn list_users_substring_filter_on_unsupported_field() {
+        const QUERY: &str = r#"{
+          users(filters: {contains: {field: "creationDate", value: "2024"}}) {
+            id
+          }
+        }"#;
+
+        let mut mock = MockTestBackendHandler::new();
+        setup_default_schema(&mut mock);
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            mock,
+            ValidationResults {
+                user: UserId::new("admin"),
+                permission: Permission::Admin,
+            },
+        );
+
+        let schema = schema(Query::<MockTestBackendHandler>::new());
+        let (_, errors) = execute(QUERY, None, &schema, &Variables::new(), &context)
+            .await
+            .unwrap();
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn list_users_range_filter_on_custom_attribute() {
+        const QUERY: &str = r#"{
+          users(filters: {range: {field: "first_name", greaterThan: "a"}}) {
+            id
+          }
+        }"#;
+
+        let mut mock = MockTestBackendHandler::new();
+        setup_default_schema(&mut mock);
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            mock,
+            ValidationResults {
+                user: UserId::new("admin"),
+                permission: Permission::Admin,
+            },
+        );
+
+        let schema = schema(Query::<MockTestBackendHandler>::new());
+        let (_, errors) = execute(QUERY, None, &schema, &Variables::new(), &context)
+            .await
+            .unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].error().message(),
+            "Range filters are not supported on custom attributes: first_name"
+        );
+    }
+
+    #[tokio::test]
+    async fn list_groups_with_filter() {
+        const QUERY: &str = r#"{
+          groups(filters: {
+            any: [
+              {contains: {field: "displayName", value: "admin"}},
+              {range: {field: "groupId", greaterThan: "3"}},
+              {member: "bob"}
+            ]}) {
+            id
+          }
+        }"#;
+
+        let mut mock = MockTestBackendHandler::new();
+        setup_default_schema(&mut mock);
+        mock.expect_list_groups()
+            .with(eq(Some(DomainGroupRequestFilter::Or(vec![
+                DomainGroupRequestFilter::DisplayNameSubString(SubStringFilter {
+                    initial: None,
+                    any: vec!["admin".to_owned()],
+                    final_: None,
+                }),
+                DomainGroupRequestFilter::GroupIdComparison(
+                    ComparisonOperator::GreaterThan,
+                    GroupId(3),
+                ),
+                DomainGroupRequestFilter::Member(UserId::new("bob")),
+            ]))))
+            .return_once(|_| Ok(Vec::new()));
+
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            mock,
+            ValidationResults {
+                user: UserId::new("admin"),
+                permission: Permission::Admin,
+            },
+        );
+
+        let schema = schema(Query::<MockTestBackendHandler>::new());
+        assert_eq!(
+            execute(QUERY, None, &schema, &Variables::new(), &context).await,
+            Ok((graphql_value!({"groups": []}), vec![]))
+        );
+    }
+
+    #[tokio::test]
+    async fn list_users_connection() {
+        const QUERY: &str = r#"{
+          usersConnection(first: 1, after: "alice", orderBy: {field: EMAIL, direction: DESC}) {
+            edges {
+              cursor
+              node {
+                id
+              }
+            }
+            pageInfo {
+              hasNextPage
+              hasPreviousPage
+              endCursor
+            }
+            totalCount
+          }
+        }"#;
+
+        let mut mock = MockTestBackendHandler::new();
+        setup_default_schema(&mut mock);
+        mock.expect_list_users_page()
+            .with(
+                eq(None),
+                eq(lldap_domain_handlers::handler::UserPageRequest {
+                    order_by: lldap_domain_handlers::handler::UserOrderField::Email,
+                    descending: true,
+                    after: Some(UserId::new("alice")),
+                    first: 1,
+                }),
+            )
+            .return_once(|_, _| {
+                Ok(lldap_domain_handlers::handler::Page {
+                    items: vec![lldap_domain::types::UserAndGroups {
+                        user: DomainUser {
+                            user_id: UserId::new("bob"),
+                            email: "bob@bobbers.on".into(),
+                            display_name: None,
+                            creation_date: chrono::Utc.timestamp_opt(0, 0).unwrap().naive_utc(),
+                            modified_date: chrono::Utc.timestamp_opt(0, 0).unwrap().naive_utc(),
+                            password_modified_date: chrono::Utc
+                                .timestamp_opt(0, 0)
+                                .unwrap()
+                                .naive_utc(),
+                            email_verified: false,
+                            uuid: lldap_domain::types::Uuid::from_name_and_date(
+                                "bob",
+                                &chrono::Utc.timestamp_opt(0, 0).unwrap().naive_utc(),
+                            ),
+                            attributes: Vec::new(),
+                        },
+                        groups: Some(Vec::new()),
+                    }],
+                    has_next_page: true,
+                    total_count: 3,
+                })
+            });
+
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            mock,
+            ValidationResults {
+                user: UserId::new("admin"),
+                permission: Permission::Admin,
+            },
+        );
+
+        let schema = schema(Query::<MockTestBackendHandler>::new());
+        assert_eq!(
+            execute(QUERY, None, &schema, &Variables::new(), &context).await,
+            Ok((
+                graphql_value!(
+                {
+                    "usersConnection": {
+                        "edges": [
+                            {
+                                "cursor": "bob",
+                                "node": {"id": "bob"}
+                            }
+                        ],
+                        "pageInfo": {
+                            "hasNextPage": true,
+                            "hasPreviousPage": true,
+                            "endCursor": "bob"
+                        },
+                        "totalCount": 3
+                    }
+                }),
+                vec![]
+            ))
+        );
+    }
+
     #[tokio::test]
     async fn get_schema() {
         const QUERY: &str = r#"{
@@ -514,6 +935,8 @@ mod tests {
                         is_editable: true,
                         is_hardcoded: true,
                         is_readonly: false,
+                        permissions: Default::default(),
+                        validation: Default::default(),
                     }],
                 },
                 group_attributes: AttributeList {
@@ -536,4 +959,213 @@ mod tests {
         let result = execute(QUERY, None, &schema, &Variables::new(), &context).await;
         assert!(result.is_ok(), "Query failed: {:?}", result);
     }
+
+    #[tokio::test]
+    async fn readonly_user_doesnt_see_private_attributes() {
+        const QUERY: &str = r#"{
+          schema {
+            userSchema {
+                attributes {
+                    name
+                }
+            }
+          }
+        }"#;
+
+        let mut mock = MockTestBackendHandler::new();
+
+        mock.expect_get_schema().times(1).return_once(|| {
+            let attribute = |name: &str, is_private| DomainAttributeSchema {
+                name: name.into(),
+                attribute_type: AttributeType::String,
+                is_list: false,
+                is_visible: true,
+                is_editable: true,
+                is_hardcoded: false,
+                is_readonly: false,
+                permissions: AttributePermissions {
+                    is_private,
+                    ..Default::default()
+                },
+                validation: Default::default(),
+            };
+            Ok(Schema {
+                user_attributes: AttributeList {
+                    attributes: vec![
+                        attribute("home_address", true),
+                        attribute("nickname", false),
+                    ],
+                },
+                group_attributes: AttributeList {
+                    attributes: Vec::new(),
+                },
+                extra_user_object_classes: Vec::new(),
+                extra_group_object_classes: Vec::new(),
+            })
+        });
+
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            mock,
+            ValidationResults {
+                user: UserId::new("bob"),
+                permission: Permission::Readonly,
+            },
+        );
+
+        let schema = schema(Query::<MockTestBackendHandler>::new());
+        assert_eq!(
+            execute(QUERY, None, &schema, &Variables::new(), &context).await,
+            Ok((
+                graphql_value!({
+                    "schema": {
+                        "userSchema": {
+                            "attributes": [
+                                {"name": "creation_date"},
+                                {"name": "display_name"},
+                                {"name": "mail"},
+                                {"name": "modified_date"},
+                                {"name": "nickname"},
+                                {"name": "password_modified_date"},
+                                {"name": "user_id"},
+                                {"name": "uuid"},
+                            ]
+                        }
+                    }
+                }),
+                vec![]
+            ))
+        );
+    }
+
+    /// The HR team is in the reader group of a private attribute. Reading it on the other users
+    /// also takes a role that sees every user.
+    #[tokio::test]
+    async fn hr_reader_group_sees_private_attributes() {
+        const QUERY: &str = r#"{
+          user(userId: "alice") {
+            attributes {
+              name
+            }
+          }
+        }"#;
+
+        let make_mock = || {
+            let mut mock = MockTestBackendHandler::new();
+            mock.expect_get_schema().returning(|| {
+                let attribute = |name: &str, permissions| DomainAttributeSchema {
+                    name: name.into(),
+                    attribute_type: AttributeType::String,
+                    is_list: false,
+                    is_visible: true,
+                    is_editable: false,
+                    is_hardcoded: false,
+                    is_readonly: false,
+                    permissions,
+                    validation: Default::default(),
+                };
+                Ok(Schema {
+                    user_attributes: AttributeList {
+                        attributes: vec![
+                            attribute(
+                                "home_address",
+                                AttributePermissions {
+                                    is_private: true,
+                                    reader_groups: vec!["hr".into()],
+                                    editor_groups: Vec::new(),
+                                },
+                            ),
+                            attribute("nickname", Default::default()),
+                        ],
+                    },
+                    group_attributes: AttributeList {
+                        attributes: Vec::new(),
+                    },
+                    extra_user_object_classes: Vec::new(),
+                    extra_group_object_classes: Vec::new(),
+                })
+            });
+            mock.expect_get_user_groups()
+                .with(eq(UserId::new("carol")))
+                .returning(|_| {
+                    Ok(HashSet::from([GroupDetails {
+                        group_id: GroupId(4),
+                        display_name: "hr".into(),
+                        creation_date: chrono::Utc.timestamp_nanos(42).naive_utc(),
+                        uuid: lldap_domain::types::Uuid::from_name_and_date(
+                            "hr",
+                            &chrono::Utc.timestamp_nanos(42).naive_utc(),
+                        ),
+                        dynamic_filter: None,
+                        attributes: Vec::new(),
+                        modified_date: chrono::Utc.timestamp_nanos(42).naive_utc(),
+                    }]))
+                });
+            mock.expect_get_user_details()
+                .with(eq(UserId::new("alice")))
+                .returning(|_| {
+                    Ok(DomainUser {
+                        user_id: UserId::new("alice"),
+                        attributes: vec![
+                            DomainAttribute {
+                                name: "home_address".into(),
+                                value: "1 Main Street".to_string().into(),
+                            },
+                            DomainAttribute {
+                                name: "nickname".into(),
+                                value: "Al".to_string().into(),
+                            },
+                        ],
+                        ..Default::default()
+                    })
+                });
+            mock
+        };
+        let schema = schema(Query::<MockTestBackendHandler>::new());
+
+        // A member of HR who is also in lldap_strict_readonly.
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            make_mock(),
+            ValidationResults {
+                user: UserId::new("carol"),
+                permission: Permission::Readonly,
+            },
+        );
+        assert_eq!(
+            execute(QUERY, None, &schema, &Variables::new(), &context).await,
+            Ok((
+                graphql_value!({
+                    "user": {
+                        "attributes": [
+                            {"name": "creation_date"},
+                            {"name": "mail"},
+                            {"name": "modified_date"},
+                            {"name": "password_modified_date"},
+                            {"name": "user_id"},
+                            {"name": "uuid"},
+                            {"name": "home_address"},
+                            {"name": "nickname"},
+                        ]
+                    }
+                }),
+                vec![]
+            ))
+        );
+
+        // The regular users only see themselves, even in the reader group.
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            make_mock(),
+            ValidationResults {
+                user: UserId::new("carol"),
+                permission: Permission::Regular,
+            },
+        );
+        let (_, errors) = execute(QUERY, None, &schema, &Variables::new(), &context)
+            .await
+            .unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].error().message(),
+            "Unauthorized access to user data"
+        );
+    }
 }
@@ -10,7 +10,7 @@ use tracing::{Instrument, debug, debug_span};
 
 use super::attribute::AttributeValue;
 use super::group::Group;
-use crate::api::Context;
+use crate::api::{Context, field_error_callback};
 
 #[derive(PartialEq, Eq, Debug, Serialize, Deserialize)]
 /// Represents a single user.
@@ -105,6 +105,11 @@ impl<Handler: BackendHandler> User<Handler> {
         self.user.uuid.as_str()
     }
 
+    /// Whether the email address was confirmed by the user.
+    fn email_verified(&self) -> bool {
+        self.user.email_verified
+    }
+
     /// User-defined attributes.
     fn attributes(&self) -> &[AttributeValue<Handler>] {
         &self.attributes
@@ -119,9 +124,13 @@ impl<Handler: BackendHandler> User<Handler> {
         span.in_scope(|| {
             debug!(user_id = ?self.user.user_id);
         });
+        // The owners of a group can see its members, but not their groups.
         let handler = context
             .get_readable_handler(&self.user.user_id)
-            .expect("We shouldn't be able to get there without readable permission");
+            .ok_or_else(field_error_callback(
+                &span,
+                "Unauthorized access to user data",
+            ))?;
         let domain_groups = handler
             .get_user_groups(&self.user.user_id)
             .instrument(span)
@@ -133,4 +142,25 @@ impl<Handler: BackendHandler> User<Handler> {
         groups.sort_by(|g1, g2| g1.display_name.cmp(&g2.display_name));
         Ok(groups)
     }
+
+    /// The groups whose members this user can manage, directly or through one of their groups.
+    async fn owned_groups(&self, context: &Context<Handler>) -> FieldResult<Vec<Group<Handler>>> {
+        let span = debug_span!("[GraphQL query] user::owned_groups");
+        span.in_scope(|| {
+            debug!(user_id = ?self.user.user_id);
+        });
+        let handler = context
+            .get_readable_handler(&self.user.user_id)
+            .ok_or_else(field_error_callback(
+                &span,
+                "Unauthorized access to user data",
+            ))?;
+        handler
+            .list_owned_groups(&self.user.user_id)
+            .instrument(span)
+            .await?
+            .into_iter()
+            .map(|g| Group::<Handler>::from_group(g, self.schema.clone()))
+            .collect()
+    }
 }
@@ -0,0 +1,307 @@
+use crate::api::{Context, field_error_callback};
+use futures::{Stream, StreamExt};
+use juniper::{FieldResult, GraphQLEnum, GraphQLObject, graphql_subscription};
+use lldap_domain_handlers::{
+    events::{DirectoryEvent, SchemaObjectType as DomainSchemaObjectType},
+    handler::BackendHandler,
+};
+use std::pin::Pin;
+use tracing::{debug, debug_span};
+
+type EventStream<T> = Pin<Box<dyn Stream<Item = T> + Send>>;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, GraphQLEnum)]
+pub enum ChangeKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, GraphQLObject)]
+/// A user was created, updated or deleted.
+pub struct UserEvent {
+    pub kind: ChangeKind,
+    pub user_id: String,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, GraphQLObject)]
+/// A group was created, updated or deleted.
+pub struct GroupEvent {
+    pub kind: ChangeKind,
+    pub group_id: i32,
+    /// Only set when the group is created.
+    pub display_name: Option<String>,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, GraphQLEnum)]
+pub enum MembershipChangeKind {
+    Added,
+    Removed,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, GraphQLObject)]
+/// A user was added to or removed from a group.
+pub struct MembershipEvent {
+    pub kind: MembershipChangeKind,
+    pub user_id: String,
+    pub group_id: i32,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, GraphQLEnum)]
+pub enum SchemaChangeKind {
+    AttributeAdded,
+    AttributeDeleted,
+    AttributeUpdated,
+    ObjectClassAdded,
+    ObjectClassDeleted,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, GraphQLEnum)]
+pub enum SchemaObjectType {
+    User,
+    Group,
+}
+
+impl From<DomainSchemaObjectType> for SchemaObjectType {
+    fn from(object_type: DomainSchemaObjectType) -> Self {
+        match object_type {
+            DomainSchemaObjectType::User => SchemaObjectType::User,
+            DomainSchemaObjectType::Group => SchemaObjectType::Group,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, GraphQLObject)]
+/// An attribute or object class was added to or removed from the schema.
+pub struct SchemaEvent {
+    pub kind: SchemaChangeKind,
+    pub object_type: SchemaObjectType,
+    /// The name of the attribute or object class.
+    pub name: String,
+}
+
+fn user_event(event: DirectoryEvent) -> Option<UserEvent> {
+    let (kind, user_id) = match event {
+        DirectoryEvent::UserCreated { user_id } => (ChangeKind::Created, user_id),
+        DirectoryEvent::UserUpdated { user_id } => (ChangeKind::Updated, user_id),
+        DirectoryEvent::UserDeleted { user_id } => (ChangeKind::Deleted, user_id),
+        _ => return None,
+    };
+    Some(UserEvent {
+        kind,
+        user_id: user_id.into_string(),
+    })
+}
+
+fn group_event(event: DirectoryEvent) -> Option<GroupEvent> {
+    let (kind, group_id, display_name) = match event {
+        DirectoryEvent::GroupCreated {
+            group_id,
+            display_name,
+        } => (
+            ChangeKind::Created,
+            group_id,
+            Some(display_name.into_string()),
+        ),
+        DirectoryEvent::GroupUpdated { group_id } => (ChangeKind::Updated, group_id, None),
+        DirectoryEvent::GroupDeleted { group_id } => (ChangeKind::Deleted, group_id, None),
+        _ => return None,
+    };
+    Some(GroupEvent {
+        kind,
+        group_id: group_id.0,
+        display_name,
+    })
+}
+
+fn membership_event(event: DirectoryEvent) -> Option<MembershipEvent> {
+    let (kind, user_id, group_id) = match event {
+        DirectoryEvent::UserAddedToGroup { user_id, group_id } => {
+            (MembershipChangeKind::Added, user_id, group_id)
+        }
+        DirectoryEvent::UserRemovedFromGroup { user_id, group_id } => {
+            (MembershipChangeKind::Removed, user_id, group_id)
+        }
+        _ => return None,
+    };
+    Some(MembershipEvent {
+        kind,
+        user_id: user_id.into_string(),
+        group_id: group_id.0,
+    })
+}
+
+fn schema_event(event: DirectoryEvent) -> Option<SchemaEvent> {
+    let (kind, object_type, name) = match event {
+        DirectoryEvent::AttributeAdded { object_type, name } => (
+            SchemaChangeKind::AttributeAdded,
+            object_type,
+            name.into_string(),
+        ),
+        DirectoryEvent::AttributeDeleted { object_type, name } => (
+            SchemaChangeKind::AttributeDeleted,
+            object_type,
+            name.into_string(),
+        ),
+        DirectoryEvent::AttributeUpdated { object_type, name } => (
+            SchemaChangeKind::AttributeUpdated,
+            object_type,
+            name.into_string(),
+        ),
+        DirectoryEvent::ObjectClassAdded { object_type, name } => (
+            SchemaChangeKind::ObjectClassAdded,
+            object_type,
+            name.into_string(),
+        ),
+        DirectoryEvent::ObjectClassDeleted { object_type, name } => (
+            SchemaChangeKind::ObjectClassDeleted,
+            object_type,
+            name.into_string(),
+        ),
+        _ => return None,
+    };
+    Some(SchemaEvent {
+        kind,
+        object_type: object_type.into(),
+        name,
+    })
+}
+
+/// The events the user is allowed to see, converted with `convert`. The other events are
+/// skipped.
+fn event_stream<Handler: BackendHandler, T: Send + 'static>(
+    context: &Context<Handler>,
+    span: tracing::Span,
+    convert: fn(DirectoryEvent) -> Option<T>,
+) -> FieldResult<EventStream<T>> {
+    let subscription = context
+        .handler
+        .subscribe_to_events(&context.validation_result)
+        .ok_or_else(field_error_callback(&span, "Subscriptions are not enabled"))?;
+    Ok(
+        futures::stream::unfold(subscription, |mut subscription| async move {
+            subscription.next().await.map(|event| (event, subscription))
+        })
+        .filter_map(move |event| std::future::ready(convert(event)))
+        .boxed(),
+    )
+}
+
+#[derive(PartialEq, Eq, Debug)]
+/// The top-level GraphQL subscription type. Regular users only receive the events about
+/// themselves.
+pub struct Subscription<Handler: BackendHandler> {
+    _phantom: std::marker::PhantomData<Box<Handler>>,
+}
+
+impl<Handler: BackendHandler> Default for Subscription<Handler> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Handler: BackendHandler> Subscription<Handler> {
+    pub fn new() -> Self {
+        Self {
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+#[graphql_subscription(context = Context<Handler>)]
+impl<Handler: BackendHandler> Subscription<Handler> {
+    async fn user_events(context: &Context<Handler>) -> FieldResult<EventStream<UserEvent>> {
+        let span = debug_span!("[GraphQL subscription] user_events");
+        span.in_scope(|| debug!(user = ?context.validation_result.user));
+        event_stream(context, span, user_event)
+    }
+
+    /// Only for the admins and the readonly users.
+    async fn group_events(context: &Context<Handler>) -> FieldResult<EventStream<GroupEvent>> {
+        let span = debug_span!("[GraphQL subscription] group_events");
+        span.in_scope(|| debug!(user = ?context.validation_result.user));
+        event_stream(context, span, group_event)
+    }
+
+    async fn membership_events(
+        context: &Context<Handler>,
+    ) -> FieldResult<EventStream<MembershipEvent>> {
+        let span = debug_span!("[GraphQL subscription] membership_events");
+        span.in_scope(|| debug!(user = ?context.validation_result.user));
+        event_stream(context, span, membership_event)
+    }
+
+    async fn schema_events(context: &Context<Handler>) -> FieldResult<EventStream<SchemaEvent>> {
+        let span = debug_span!("[GraphQL subscription] schema_events");
+        span.in_scope(|| debug!(user = ?context.validation_result.user));
+        event_stream(context, span, schema_event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lldap_access_control::{AccessControlledBackendHandler, events::DirectoryEventBroadcaster};
+    use lldap_auth::access_control::{Permission, ValidationResults};
+    use lldap_domain::types::{GroupId, UserId};
+    use lldap_domain_handlers::events::DirectoryEventListener;
+    use lldap_ldap::LdapInfo;
+    use lldap_test_utils::MockTestBackendHandler;
+    use pretty_assertions::assert_eq;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_regular_user_only_receives_own_events() {
+        let broadcaster = Arc::new(DirectoryEventBroadcaster::new());
+        let context = Context::<MockTestBackendHandler> {
+            handler: AccessControlledBackendHandler::new(MockTestBackendHandler::new())
+                .with_event_broadcaster(broadcaster.clone()),
+            validation_result: ValidationResults {
+                user: UserId::new("bob"),
+                permission: Permission::Regular,
+            },
+            ldap_info: Box::leak(Box::new(
+                LdapInfo::new("dc=example,dc=com", vec![], vec![]).unwrap(),
+            )),
+            confirm_email_changes: false,
+        };
+        let mut stream = event_stream(&context, tracing::Span::none(), membership_event).unwrap();
+        broadcaster
+            .on_event(DirectoryEvent::UserAddedToGroup {
+                user_id: UserId::new("patrick"),
+                group_id: GroupId(3),
+            })
+            .await;
+        broadcaster
+            .on_event(DirectoryEvent::UserCreated {
+                user_id: UserId::new("bob"),
+            })
+            .await;
+        broadcaster
+            .on_event(DirectoryEvent::UserRemovedFromGroup {
+                user_id: UserId::new("bob"),
+                group_id: GroupId(3),
+            })
+            .await;
+        assert_eq!(
+            stream.next().await,
+            Some(MembershipEvent {
+                kind: MembershipChangeKind::Removed,
+                user_id: "bob".to_owned(),
+                group_id: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn test_subscriptions_disabled() {
+        let context = Context::<MockTestBackendHandler>::new_for_tests(
+            MockTestBackendHandler::new(),
+            ValidationResults {
+                user: UserId::new("admin"),
+                permission: Permission::Admin,
+            },
+        );
+        assert!(event_stream(&context, tracing::Span::none(), user_event).is_err());
+    }
+}
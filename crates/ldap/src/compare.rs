@@ -122,6 +122,8 @@ mod tests {
                 display_name: "group".into(),
                 creation_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                 users: vec![UserId::new("bob")],
+                owners: vec![],
+                dynamic_filter: None,
                 uuid: uuid!("04ac75e0-2900-3e21-926c-2f732c26b3fc"),
                 attributes: Vec::new(),
                 modified_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
@@ -217,6 +219,8 @@ mod tests {
                 display_name: "group".into(),
                 creation_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                 users: vec![UserId::new("bob")],
+                owners: vec![],
+                dynamic_filter: None,
                 uuid: uuid!("04ac75e0-2900-3e21-926c-2f732c26b3fc"),
                 attributes: Vec::new(),
                 modified_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
@@ -13,7 +13,9 @@ use ldap3_proto::{
 use lldap_domain::{
     deserialize::deserialize_attribute_value,
     public_schema::PublicSchema,
-    types::{AttributeName, AttributeType, Group, GroupId, LdapObjectClass, UserId, Uuid},
+    types::{
+        AttributeName, AttributeType, Group, GroupId, GroupOwner, LdapObjectClass, UserId, Uuid,
+    },
 };
 use lldap_domain_handlers::handler::{GroupListerBackendHandler, GroupRequestFilter};
 use tracing::{debug, instrument, warn};
@@ -84,6 +86,21 @@ pub fn get_group_attribute(
             .filter(|u| user_filter.as_ref().map(|f| *u == f).unwrap_or(true))
             .map(|u| format!("uid={u},ou=people,{base_dn_str}").into_bytes())
             .collect(),
+        // Like the members, the restricted users only see themselves, and no group.
+        GroupFieldType::Owner => group
+            .owners
+            .iter()
+            .filter_map(|owner| match owner {
+                GroupOwner::User(u) => user_filter
+                    .as_ref()
+                    .is_none_or(|f| u == f)
+                    .then(|| format!("uid={u},ou=people,{base_dn_str}")),
+                GroupOwner::Group(g) => user_filter
+                    .is_none()
+                    .then(|| format!("cn={g},ou=groups,{base_dn_str}")),
+            })
+            .map(String::into_bytes)
+            .collect(),
         GroupFieldType::Uuid => vec![group.uuid.to_string().into_bytes()],
         GroupFieldType::Attribute(attr, _, _) => get_custom_attribute(&group.attributes, &attr)?,
         GroupFieldType::NoMatch => match attribute.as_str() {
@@ -99,13 +116,11 @@ pub fn get_group_attribute(
                 if ignored_group_attributes.contains(attribute) {
                     return None;
                 }
-                get_custom_attribute(
-                        &group.attributes,
-                        attribute,
-                    ).or_else(||{warn!(
-                            r#"Ignoring unrecognized group attribute: {}. To disable this warning, add it to "ignored_group_attributes" in the config."#,
-                            attribute
-                        );None})?
+                warn!(
+                    r#"Ignoring unrecognized group attribute: {}. To disable this warning, add it to "ignored_group_attributes" in the config."#,
+                    attribute
+                );
+                return None;
             }
         },
     };
@@ -142,6 +157,13 @@ fn make_ldap_search_group_result_entry(
             group
                 .attributes
                 .iter()
+                .filter(|a| {
+                    schema
+                        .get_schema()
+                        .group_attributes
+                        .get_attribute_schema(&a.name)
+                        .is_some()
+                })
                 .map(|a| (a.name.clone(), a.name.to_string())),
         );
     }
@@ -270,6 +292,10 @@ fn convert_group_filter(
                     code: LdapResultCode::UnwillingToPerform,
                     message: "Modified date filter for groups not supported".to_owned(),
                 }),
+                GroupFieldType::Owner => Err(LdapError {
+                    code: LdapResultCode::UnwillingToPerform,
+                    message: "Owner filter for groups not supported".to_owned(),
+                }),
             }
         }
         LdapFilter::And(filters) => {
@@ -419,6 +445,8 @@ mod tests {
                         display_name: "group_1".into(),
                         creation_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                         users: vec![UserId::new("bob"), UserId::new("john")],
+                        owners: vec![],
+                        dynamic_filter: None,
                         uuid: uuid!("04ac75e0-2900-3e21-926c-2f732c26b3fc"),
                         attributes: Vec::new(),
                         modified_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
@@ -428,6 +456,8 @@ mod tests {
                         display_name: "BestGroup".into(),
                         creation_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                         users: vec![UserId::new("john")],
+                        owners: vec![],
+                        dynamic_filter: None,
                         uuid: uuid!("04ac75e0-2900-3e21-926c-2f732c26b3fc"),
                         attributes: Vec::new(),
                         modified_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
@@ -519,6 +549,8 @@ mod tests {
                     id: GroupId(1),
                     creation_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                     users: vec![],
+                    owners: vec![],
+                    dynamic_filter: None,
                     uuid: uuid!("04ac75e0-2900-3e21-926c-2f732c26b3fc"),
                     attributes: Vec::new(),
                     modified_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
@@ -541,6 +573,62 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_search_groups_owner() {
+        let mut mock = MockTestBackendHandler::new();
+        mock.expect_list_groups()
+            .with(eq(Some(GroupRequestFilter::True)))
+            .times(1)
+            .return_once(|_| {
+                Ok(vec![Group {
+                    display_name: "group_1".into(),
+                    id: GroupId(1),
+                    creation_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
+                    users: vec![],
+                    owners: vec![
+                        GroupOwner::User(UserId::new("bob")),
+                        GroupOwner::Group("admins".into()),
+                    ],
+                    dynamic_filter: None,
+                    uuid: uuid!("04ac75e0-2900-3e21-926c-2f732c26b3fc"),
+                    attributes: Vec::new(),
+                    modified_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
+                }])
+            });
+        let ldap_handler = setup_bound_admin_handler(mock).await;
+        let request = make_group_search_request(LdapFilter::And(vec![]), vec!["owner"]);
+        assert_eq!(
+            ldap_handler.do_search_or_dse(&request).await,
+            Ok(vec![
+                LdapOp::SearchResultEntry(LdapSearchResultEntry {
+                    dn: "cn=group_1,ou=groups,dc=example,dc=com".to_string(),
+                    attributes: vec![LdapPartialAttribute {
+                        atype: "owner".to_string(),
+                        vals: vec![
+                            b"uid=bob,ou=people,dc=example,dc=com".to_vec(),
+                            b"cn=admins,ou=groups,dc=example,dc=com".to_vec(),
+                        ],
+                    }],
+                }),
+                make_search_success(),
+            ])
+        );
+        let request = make_group_search_request(
+            LdapFilter::Equality(
+                "owner".to_string(),
+                "uid=bob,ou=people,dc=example,dc=com".to_string(),
+            ),
+            vec!["dn"],
+        );
+        assert_eq!(
+            ldap_handler.do_search_or_dse(&request).await,
+            Err(LdapError {
+                code: LdapResultCode::UnwillingToPerform,
+                message: "Owner filter for groups not supported".to_owned(),
+            })
+        );
+    }
+
     #[tokio::test]
     async fn test_search_groups_filter() {
         let mut mock = MockTestBackendHandler::new();
@@ -565,6 +653,8 @@ mod tests {
                     id: GroupId(1),
                     creation_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                     users: vec![],
+                    owners: vec![],
+                    dynamic_filter: None,
                     uuid: uuid!("04ac75e0-2900-3e21-926c-2f732c26b3fc"),
                     attributes: Vec::new(),
                     modified_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
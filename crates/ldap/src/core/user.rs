@@ -96,6 +96,10 @@ pub fn get_user_attribute(
         UserFieldType::PrimaryField(UserColumn::PasswordModifiedDate) => {
             vec![to_generalized_time(&user.password_modified_date)]
         }
+        UserFieldType::PrimaryField(UserColumn::EmailVerified) => {
+            let value = if user.email_verified { "TRUE" } else { "FALSE" };
+            vec![value.as_bytes().to_vec()]
+        }
         UserFieldType::Attribute(attr, _, _) => get_custom_attribute(&user.attributes, &attr)?,
         UserFieldType::NoMatch => match attribute.as_str() {
             "1.1" => return None,
@@ -110,13 +114,11 @@ pub fn get_user_attribute(
                 if ignored_user_attributes.contains(attribute) {
                     return None;
                 }
-                get_custom_attribute(&user.attributes, attribute).or_else(|| {
-                    warn!(
-                        r#"Ignoring unrecognized user attribute: {}. To disable this warning, add it to "ignored_user_attributes" in the config."#,
-                        attribute
-                    );
-                    None
-                })?
+                warn!(
+                    r#"Ignoring unrecognized user attribute: {}. To disable this warning, add it to "ignored_user_attributes" in the config."#,
+                    attribute
+                );
+                return None;
             }
         },
     };
@@ -151,6 +153,13 @@ fn make_ldap_search_user_result_entry(
         expanded_attributes.attribute_keys.extend(
             user.attributes
                 .iter()
+                .filter(|a| {
+                    schema
+                        .get_schema()
+                        .user_attributes
+                        .get_attribute_schema(&a.name)
+                        .is_some()
+                })
                 .map(|a| (a.name.clone(), a.name.to_string())),
         );
     }
@@ -276,6 +285,16 @@ fn convert_user_filter(
                         ]))
                     }
                 }
+                UserFieldType::PrimaryField(UserColumn::EmailVerified) => {
+                    Ok(match value_lc.as_str() {
+                        "true" => UserRequestFilter::EmailVerified(true),
+                        "false" => UserRequestFilter::EmailVerified(false),
+                        _ => {
+                            warn!("Invalid boolean value for emailVerified: {}", value);
+                            UserRequestFilter::False
+                        }
+                    })
+                }
                 UserFieldType::PrimaryField(field) => {
                     Ok(UserRequestFilter::Equality(field, value_lc))
                 }
@@ -347,6 +366,7 @@ fn convert_user_filter(
                 | UserFieldType::Dn
                 | UserFieldType::EntryDn
                 | UserFieldType::PrimaryField(UserColumn::CreationDate)
+                | UserFieldType::PrimaryField(UserColumn::EmailVerified)
                 | UserFieldType::PrimaryField(UserColumn::Uuid) => Err(LdapError {
                     code: LdapResultCode::UnwillingToPerform,
                     message: format!("Unsupported user attribute for substring filter: {field:?}"),
@@ -369,6 +389,73 @@ fn convert_user_filter(
     }
 }
 
+fn check_user_filter_attributes(filter: &LdapFilter, schema: &PublicSchema) -> LdapResult<()> {
+    match filter {
+        LdapFilter::And(filters) | LdapFilter::Or(filters) => filters
+            .iter()
+            .try_for_each(|f| check_user_filter_attributes(f, schema)),
+        LdapFilter::Not(filter) => check_user_filter_attributes(filter, schema),
+        LdapFilter::Equality(field, _)
+        | LdapFilter::Present(field)
+        | LdapFilter::Substring(field, _) => {
+            match map_user_field(&AttributeName::from(field.as_str()), schema) {
+                UserFieldType::NoMatch => Err(LdapError {
+                    code: LdapResultCode::UndefinedAttributeType,
+                    message: format!("Unknown user attribute in filter: {field}"),
+                }),
+                _ => Ok(()),
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The filter parser only accepts the values containing an operator character (like the `=` of a
+/// DN, or a space) when they are quoted, so quote them.
+fn quote_filter_values(filter: &str) -> String {
+    let mut quoted = String::with_capacity(filter.len());
+    let mut rest = filter;
+    // Attribute names can't contain a '=', so the next one is always the operator of an item.
+    while let Some(operator) = rest.find('=') {
+        let (item_start, tail) = rest.split_at(operator + 1);
+        quoted.push_str(item_start);
+        let value_end = tail.find(')').unwrap_or(tail.len());
+        let value = &tail[..value_end];
+        if !value.contains('"')
+            && value
+                .trim()
+                .contains(['=', ' ', '\t', '\n', '!', '&', '|', '<', '>', '~'])
+        {
+            quoted.push('"');
+            quoted.push_str(value.trim());
+            quoted.push('"');
+        } else {
+            quoted.push_str(value);
+        }
+        rest = &tail[value_end..];
+    }
+    quoted.push_str(rest);
+    quoted
+}
+
+/// Parses the filter of a dynamic group. Unlike in a search, an unknown attribute is an error
+/// rather than a filter that matches nothing.
+pub fn parse_user_filter(
+    ldap_info: &LdapInfo,
+    filter: &str,
+    schema: &PublicSchema,
+) -> LdapResult<UserRequestFilter> {
+    let filter =
+        ldap3_proto::filter::parse_ldap_filter_str(&quote_filter_values(filter)).map_err(|e| {
+            LdapError {
+                code: LdapResultCode::ProtocolError,
+                message: format!("Invalid LDAP filter: {e}"),
+            }
+        })?;
+    check_user_filter_attributes(&filter, schema)?;
+    convert_user_filter(ldap_info, &filter, schema)
+}
+
 fn expand_user_attribute_wildcards(attributes: &[String]) -> ExpandedAttributes {
     expand_attribute_wildcards(attributes, ALL_USER_ATTRIBUTE_KEYS)
 }
@@ -427,7 +514,10 @@ mod tests {
         search::{make_search_request, make_search_success},
     };
     use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
-    use lldap_domain::types::{Attribute, GroupDetails, JpegPhoto};
+    use lldap_domain::{
+        schema::{AttributeList, Schema},
+        types::{Attribute, GroupDetails, GroupName, JpegPhoto},
+    };
     use lldap_test_utils::MockTestBackendHandler;
     use mockall::predicate::eq;
     use pretty_assertions::assert_eq;
@@ -523,6 +613,7 @@ mod tests {
                         display_name: "rockstars".into(),
                         creation_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                         uuid: lldap_domain::uuid!("a1a2a3a4b1b2c1c2d1d2d3d4d5d6d7d8"),
+                        dynamic_filter: None,
                         attributes: Vec::new(),
                         modified_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                     }]),
@@ -629,6 +720,7 @@ mod tests {
                             .with_ymd_and_hms(2014, 7, 8, 9, 10, 11)
                             .unwrap()
                             .naive_utc(),
+                        email_verified: false,
                     },
                     groups: None,
                 },
@@ -828,4 +920,56 @@ mod tests {
             panic!("Expected SearchResultEntry");
         }
     }
+
+    #[test]
+    fn test_quote_filter_values() {
+        assert_eq!(
+            quote_filter_values("(&(uid=bob)(memberOf=cn=admins,ou=groups,dc=example,dc=com))"),
+            "(&(uid=bob)(memberOf=\"cn=admins,ou=groups,dc=example,dc=com\"))"
+        );
+        assert_eq!(
+            quote_filter_values("(displayName=Bob Smith)"),
+            "(displayName=\"Bob Smith\")"
+        );
+        assert_eq!(
+            quote_filter_values("(memberOf=\"cn=admins,ou=groups,dc=example,dc=com\")"),
+            "(memberOf=\"cn=admins,ou=groups,dc=example,dc=com\")"
+        );
+        assert_eq!(quote_filter_values("(uid>=bob)"), "(uid>=bob)");
+    }
+
+    #[test]
+    fn test_parse_user_filter() {
+        let ldap_info = LdapInfo::new("dc=example,dc=com", vec![], vec![]).unwrap();
+        let schema = PublicSchema::from(Schema {
+            user_attributes: AttributeList { attributes: vec![] },
+            group_attributes: AttributeList { attributes: vec![] },
+            extra_user_object_classes: vec![],
+            extra_group_object_classes: vec![],
+        });
+        assert_eq!(
+            parse_user_filter(
+                &ldap_info,
+                "(|(uid=bob)(memberOf=cn=admins,ou=groups,dc=example,dc=com))",
+                &schema
+            )
+            .unwrap(),
+            UserRequestFilter::Or(vec![
+                UserRequestFilter::UserId(UserId::new("bob")),
+                UserRequestFilter::MemberOf(GroupName::from("admins")),
+            ])
+        );
+        assert_eq!(
+            parse_user_filter(&ldap_info, "(&(uid=bob)", &schema)
+                .unwrap_err()
+                .code,
+            LdapResultCode::ProtocolError
+        );
+        assert_eq!(
+            parse_user_filter(&ldap_info, "(unknown=bob)", &schema)
+                .unwrap_err()
+                .code,
+            LdapResultCode::UndefinedAttributeType
+        );
+    }
 }
@@ -224,7 +224,7 @@ pub enum UserFieldType {
 }
 
 pub fn map_user_field(field: &AttributeName, schema: &PublicSchema) -> UserFieldType {
-    match field.as_str() {
+    let field_type = match field.as_str() {
         "memberof" | "ismemberof" => UserFieldType::MemberOf,
         "objectclass" => UserFieldType::ObjectClass,
         "dn" | "distinguishedname" => UserFieldType::Dn,
@@ -259,12 +259,28 @@ pub fn map_user_field(field: &AttributeName, schema: &PublicSchema) -> UserField
             UserFieldType::PrimaryField(UserColumn::PasswordModifiedDate)
         }
         "entryuuid" | "uuid" => UserFieldType::PrimaryField(UserColumn::Uuid),
+        "emailverified" | "email_verified" => {
+            UserFieldType::PrimaryField(UserColumn::EmailVerified)
+        }
         _ => schema
             .get_schema()
             .user_attributes
             .get_attribute_type(field)
             .map(|(t, is_list)| UserFieldType::Attribute(field.clone(), t, is_list))
             .unwrap_or(UserFieldType::NoMatch),
+    };
+    match field_type {
+        // The schema only has the attributes the user can read, including the hardcoded ones.
+        UserFieldType::Attribute(ref name, _, _)
+            if schema
+                .get_schema()
+                .user_attributes
+                .get_attribute_schema(name)
+                .is_none() =>
+        {
+            UserFieldType::NoMatch
+        }
+        field_type => field_type,
     }
 }
 
@@ -279,6 +295,8 @@ pub enum GroupFieldType {
     // Like Dn, but returned as part of the attributes.
     EntryDn,
     Member,
+    // The users and groups allowed to manage the members.
+    Owner,
     Uuid,
     Attribute(AttributeName, AttributeType, bool),
 }
@@ -292,6 +310,7 @@ pub fn map_group_field(field: &AttributeName, schema: &PublicSchema) -> GroupFie
         "creationdate" | "createtimestamp" | "creation_date" => GroupFieldType::CreationDate,
         "modifytimestamp" | "modifydate" | "modified_date" => GroupFieldType::ModifiedDate,
         "member" | "uniquemember" => GroupFieldType::Member,
+        "owner" => GroupFieldType::Owner,
         "entryuuid" | "uuid" => GroupFieldType::Uuid,
         "group_id" | "groupid" => GroupFieldType::GroupId,
         _ => schema
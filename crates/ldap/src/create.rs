@@ -124,6 +124,8 @@ async fn create_user(
             ),
             display_name: get_attribute("cn").transpose()?,
             attributes: new_user_attributes,
+            // Only admins can add entries.
+            email_verified: true,
         })
         .await
         .map_err(|e| LdapError {
@@ -175,6 +177,7 @@ mod tests {
                 user_id: UserId::new("bob"),
                 email: "".into(),
                 display_name: Some("Bob".to_string()),
+                email_verified: true,
                 ..Default::default()
             }))
             .times(1)
@@ -231,6 +234,7 @@ mod tests {
                 user_id: UserId::new("bob"),
                 email: "".into(),
                 display_name: Some("Bob".to_string()),
+                email_verified: true,
                 ..Default::default()
             }))
             .times(1)
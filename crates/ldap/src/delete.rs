@@ -153,6 +153,8 @@ mod tests {
                     creation_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                     uuid: uuid!("a1a2a3a4b1b2c1c2d1d2d3d4d5d6d7d8"),
                     users: Vec::new(),
+                    owners: vec![],
+                    dynamic_filter: None,
                     attributes: Vec::new(),
                     modified_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                 }])
@@ -284,6 +286,8 @@ mod tests {
                     creation_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                     uuid: uuid!("a1a2a3a4b1b2c1c2d1d2d3d4d5d6d7d8"),
                     users: Vec::new(),
+                    owners: vec![],
+                    dynamic_filter: None,
                     attributes: Vec::new(),
                     modified_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                 }])
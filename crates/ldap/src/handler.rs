@@ -18,8 +18,11 @@ use ldap3_proto::proto::{
 };
 use lldap_access_control::AccessControlledBackendHandler;
 use lldap_auth::access_control::ValidationResults;
-use lldap_domain::public_schema::PublicSchema;
-use lldap_domain_handlers::handler::{BackendHandler, LoginHandler, ReadSchemaBackendHandler};
+use lldap_domain::{public_schema::PublicSchema, types::UserId};
+use lldap_domain_handlers::{
+    audit::{AuditAction, AuditEvent},
+    handler::{BackendHandler, LoginHandler, ReadSchemaBackendHandler},
+};
 use lldap_opaque_handler::OpaqueHandler;
 use tracing::{debug, instrument};
 
@@ -67,6 +70,11 @@ impl<Backend> LdapHandler<Backend> {
     pub fn session_uuid(&self) -> &uuid::Uuid {
         &self.session_uuid
     }
+
+    /// The user the session is bound as, if any.
+    pub fn bound_user(&self) -> Option<&UserId> {
+        self.user_info.as_ref().map(|user_info| &user_info.user)
+    }
 }
 
 impl<Backend: LoginHandler> LdapHandler<Backend> {
@@ -166,19 +174,29 @@ impl<Backend: BackendHandler + LoginHandler + OpaqueHandler> LdapHandler<Backend
 
     #[instrument(skip_all, level = "debug", fields(dn = %request.dn))]
     pub async fn do_bind(&mut self, request: &LdapBindRequest) -> Vec<LdapOp> {
-        let (code, message) =
-            match password::do_bind(self.ldap_info, request, self.get_login_handler()).await {
-                Ok(user_id) => {
-                    self.user_info = self
-                        .backend_handler
-                        .get_permissions_for_user(user_id)
-                        .await
-                        .ok();
-                    debug!("Success!");
-                    (LdapResultCode::Success, "".to_string())
-                }
-                Err(err) => (err.code, err.message),
-            };
+        let result = password::do_bind(self.ldap_info, request, self.get_login_handler()).await;
+        self.backend_handler
+            .record_audit_event(AuditEvent {
+                actor: result.as_ref().ok().cloned(),
+                action: AuditAction::Login,
+                target: Some(request.dn.clone()),
+                details: None,
+                source: self.backend_handler.audit_source().clone(),
+                success: result.is_ok(),
+            })
+            .await;
+        let (code, message) = match result {
+            Ok(user_id) => {
+                self.user_info = self
+                    .backend_handler
+                    .get_permissions_for_user(user_id)
+                    .await
+                    .ok();
+                debug!("Success!");
+                (LdapResultCode::Success, "".to_string())
+            }
+            Err(err) => (err.code, err.message),
+        };
         vec![LdapOp::BindResponse(LdapBindResponse {
             res: LdapResultOp {
                 code,
@@ -241,7 +259,7 @@ impl<Backend: BackendHandler + LoginHandler + OpaqueHandler> LdapHandler<Backend
             Credentials::Bound(cred) => cred,
             Credentials::Unbound(err) => return err,
         };
-        modify::handle_modify_request(
+        let result = modify::handle_modify_request(
             self.get_opaque_handler(),
             |credentials, user_id| {
                 self.backend_handler
@@ -251,8 +269,19 @@ impl<Backend: BackendHandler + LoginHandler + OpaqueHandler> LdapHandler<Backend
             credentials,
             request,
         )
-        .await
-        .unwrap_or_else(|e: LdapError| vec![make_modify_response(e.code, e.message)])
+        .await;
+        // The password is the only attribute that can be modified through LDAP.
+        self.backend_handler
+            .record_audit_event(AuditEvent {
+                actor: Some(credentials.user.clone()),
+                action: AuditAction::ChangePassword,
+                target: Some(request.dn.clone()),
+                details: None,
+                source: self.backend_handler.audit_source().clone(),
+                success: result.is_ok(),
+            })
+            .await;
+        result.unwrap_or_else(|e: LdapError| vec![make_modify_response(e.code, e.message)])
     }
 
     #[instrument(skip_all, level = "debug")]
@@ -265,7 +294,7 @@ impl<Backend: BackendHandler + LoginHandler + OpaqueHandler> LdapHandler<Backend
                 code: LdapResultCode::InsufficentAccessRights,
                 message: "Unauthorized write".to_string(),
             })?;
-        create::create_user_or_group(backend_handler, self.ldap_info, request).await
+        create::create_user_or_group(&backend_handler, self.ldap_info, request).await
     }
 
     #[instrument(skip_all, level = "debug")]
@@ -278,7 +307,7 @@ impl<Backend: BackendHandler + LoginHandler + OpaqueHandler> LdapHandler<Backend
                 code: LdapResultCode::InsufficentAccessRights,
                 message: "Unauthorized write".to_string(),
             })?;
-        delete::delete_user_or_group(backend_handler, self.ldap_info, request).await
+        delete::delete_user_or_group(&backend_handler, self.ldap_info, request).await
     }
 
     #[instrument(skip_all, level = "debug")]
@@ -387,6 +416,7 @@ pub mod tests {
                     display_name: group.into(),
                     creation_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                     uuid: uuid!("a1a2a3a4b1b2c1c2d1d2d3d4d5d6d7d8"),
+                    dynamic_filter: None,
                     attributes: Vec::new(),
                     modified_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                 });
@@ -7,8 +7,8 @@ pub(crate) mod modify;
 pub(crate) mod password;
 pub(crate) mod search;
 
-pub use core::utils::{LdapInfo, UserFieldType, map_group_field, map_user_field};
+pub use core::utils::{GroupFieldType, LdapInfo, UserFieldType, map_group_field, map_user_field};
 pub use handler::LdapHandler;
 
 pub use core::group::get_default_group_object_classes;
-pub use core::user::get_default_user_object_classes;
+pub use core::user::{get_default_user_object_classes, parse_user_filter};
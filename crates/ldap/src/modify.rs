@@ -157,6 +157,7 @@ mod tests {
                         display_name: GroupName::from(group),
                         creation_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                         uuid: uuid!("a1a2a3a4b1b2c1c2d1d2d3d4d5d6d7d8"),
+                        dynamic_filter: None,
                         attributes: Vec::new(),
                         modified_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                     });
@@ -12,7 +12,10 @@ use ldap3_proto::proto::{
 use lldap_access_control::{AccessControlledBackendHandler, UserReadableBackendHandler};
 use lldap_auth::access_control::ValidationResults;
 use lldap_domain::types::UserId;
-use lldap_domain_handlers::handler::{BackendHandler, BindRequest, LoginHandler};
+use lldap_domain_handlers::{
+    audit::{AuditAction, AuditEvent},
+    handler::{BackendHandler, BindRequest, LoginHandler},
+};
 use lldap_opaque_handler::OpaqueHandler;
 
 pub(crate) async fn do_bind(
@@ -125,18 +128,29 @@ pub(crate) async fn do_password_modification<Handler: BackendHandler>(
                                 &credentials.user, &uid
                             ),
                         })
-                    } else if let Err(e) =
-                        change_password(opaque_handler, uid, password.as_bytes()).await
-                    {
-                        Err(LdapError {
-                            code: LdapResultCode::Other,
-                            message: format!("Error while changing the password: {e:#?}"),
-                        })
                     } else {
-                        Ok(vec![make_extended_response(
-                            LdapResultCode::Success,
-                            "".to_string(),
-                        )])
+                        let result =
+                            change_password(opaque_handler, uid.clone(), password.as_bytes()).await;
+                        backend_handler
+                            .record_audit_event(AuditEvent {
+                                actor: Some(credentials.user.clone()),
+                                action: AuditAction::ChangePassword,
+                                target: Some(uid.to_string()),
+                                details: None,
+                                source: backend_handler.audit_source().clone(),
+                                success: result.is_ok(),
+                            })
+                            .await;
+                        match result {
+                            Err(e) => Err(LdapError {
+                                code: LdapResultCode::Other,
+                                message: format!("Error while changing the password: {e:#?}"),
+                            }),
+                            Ok(()) => Ok(vec![make_extended_response(
+                                LdapResultCode::Success,
+                                "".to_string(),
+                            )]),
+                        }
                     }
                 }
                 Err(e) => Err(LdapError {
@@ -213,9 +227,10 @@ pub mod tests {
                 registration_response: start_response.message,
             })
         });
+        let user_id = UserId::new(user);
         mock.expect_registration_finish()
             .times(1)
-            .return_once(|_| Ok(()));
+            .return_once(|_| Ok(user_id));
     }
 
     #[tokio::test]
@@ -262,6 +277,7 @@ pub mod tests {
                     display_name: "lldap_admin".into(),
                     creation_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                     uuid: uuid!("a1a2a3a4b1b2c1c2d1d2d3d4d5d6d7d8"),
+                    dynamic_filter: None,
                     attributes: Vec::new(),
                     modified_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                 });
@@ -392,7 +408,7 @@ pub mod tests {
         });
         mock.expect_registration_finish()
             .times(1)
-            .return_once(|_| Ok(()));
+            .return_once(|_| Ok(UserId::new("bob")));
         let mut ldap_handler = setup_bound_admin_handler(mock).await;
         let request = LdapOp::ModifyRequest(LdapModifyRequest {
             dn: "uid=bob,ou=people,dc=example,dc=com".to_string(),
@@ -442,7 +458,7 @@ pub mod tests {
         });
         mock.expect_registration_finish()
             .times(1)
-            .return_once(|_| Ok(()));
+            .return_once(|_| Ok(UserId::new("bob")));
         let mut ldap_handler = setup_bound_password_manager_handler(mock).await;
         let request = LdapOp::ExtendedRequest(
             LdapPasswordModifyRequest {
@@ -520,6 +536,7 @@ pub mod tests {
             display_name: "lldap_admin".into(),
             creation_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
             uuid: uuid!("a1a2a3a4b1b2c1c2d1d2d3d4d5d6d7d8"),
+            dynamic_filter: None,
             attributes: Vec::new(),
             modified_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
         });
@@ -448,7 +448,7 @@ mod tests {
     use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
     use ldap3_proto::proto::{LdapDerefAliases, LdapSearchScope, LdapSubstringFilter};
     use lldap_domain::{
-        schema::{AttributeList, AttributeSchema, Schema},
+        schema::{AttributeList, AttributePermissions, AttributeSchema, Schema},
         types::{
             Attribute, AttributeName, AttributeType, GroupId, JpegPhoto, LdapObjectClass, User,
             UserId,
@@ -1039,6 +1039,8 @@ mod tests {
                     display_name: "group_1".into(),
                     creation_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                     users: vec![UserId::new("bob"), UserId::new("john")],
+                    owners: vec![],
+                    dynamic_filter: None,
                     uuid: uuid!("04ac75e0-2900-3e21-926c-2f732c26b3fc"),
                     attributes: Vec::new(),
                     modified_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
@@ -1124,6 +1126,8 @@ mod tests {
                     display_name: "group_1".into(),
                     creation_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                     users: vec![UserId::new("bob"), UserId::new("john")],
+                    owners: vec![],
+                    dynamic_filter: None,
                     uuid: uuid!("04ac75e0-2900-3e21-926c-2f732c26b3fc"),
                     attributes: Vec::new(),
                     modified_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
@@ -1376,6 +1380,8 @@ mod tests {
                 display_name: "group".into(),
                 creation_date: chrono::Utc.timestamp_opt(42, 42).unwrap().naive_utc(),
                 users: vec![UserId::new("bob")],
+                owners: vec![],
+                dynamic_filter: None,
                 uuid: uuid!("04ac75e0-2900-3e21-926c-2f732c26b3fc"),
                 attributes: vec![Attribute {
                     name: "club_name".into(),
@@ -1395,6 +1401,8 @@ mod tests {
                         is_editable: true,
                         is_hardcoded: false,
                         is_readonly: false,
+                        permissions: Default::default(),
+                        validation: Default::default(),
                     }],
                 },
                 group_attributes: AttributeList {
@@ -1406,6 +1414,8 @@ mod tests {
                         is_editable: true,
                         is_hardcoded: false,
                         is_readonly: false,
+                        permissions: Default::default(),
+                        validation: Default::default(),
                     }],
                 },
                 extra_user_object_classes: vec![
@@ -1456,6 +1466,84 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_private_attribute_hidden_from_readonly() {
+        let mut mock = MockTestBackendHandler::new();
+        mock.expect_list_users().times(1).return_once(|_, _| {
+            Ok(vec![UserAndGroups {
+                user: User {
+                    user_id: UserId::new("test"),
+                    attributes: vec![
+                        Attribute {
+                            name: "nickname".into(),
+                            value: "Bob the Builder".to_string().into(),
+                        },
+                        Attribute {
+                            name: "home_address".into(),
+                            value: "1 Main Street".to_string().into(),
+                        },
+                    ],
+                    ..Default::default()
+                },
+                groups: None,
+            }])
+        });
+        mock.expect_get_schema().returning(|| {
+            let attribute = |name: &str, is_private| AttributeSchema {
+                name: name.into(),
+                attribute_type: AttributeType::String,
+                is_list: false,
+                is_visible: true,
+                is_editable: true,
+                is_hardcoded: false,
+                is_readonly: false,
+                permissions: AttributePermissions {
+                    is_private,
+                    ..Default::default()
+                },
+                validation: Default::default(),
+            };
+            Ok(Schema {
+                user_attributes: AttributeList {
+                    attributes: vec![
+                        attribute("home_address", true),
+                        attribute("nickname", false),
+                    ],
+                },
+                group_attributes: AttributeList {
+                    attributes: Vec::new(),
+                },
+                extra_user_object_classes: Vec::new(),
+                extra_group_object_classes: Vec::new(),
+            })
+        });
+        let ldap_handler = setup_bound_readonly_handler(mock).await;
+
+        let request = make_user_search_request(
+            LdapFilter::And(vec![]),
+            vec!["uid", "nickname", "home_address"],
+        );
+        assert_eq!(
+            ldap_handler.do_search_or_dse(&request).await,
+            Ok(vec![
+                LdapOp::SearchResultEntry(LdapSearchResultEntry {
+                    dn: "uid=test,ou=people,dc=example,dc=com".to_string(),
+                    attributes: vec![
+                        LdapPartialAttribute {
+                            atype: "nickname".to_owned(),
+                            vals: vec![b"Bob the Builder".to_vec()],
+                        },
+                        LdapPartialAttribute {
+                            atype: "uid".to_owned(),
+                            vals: vec![b"test".to_vec()],
+                        },
+                    ],
+                }),
+                make_search_success()
+            ]),
+        );
+    }
+
     #[tokio::test]
     async fn test_search_base_scope_non_existent_user() {
         let mut mock = MockTestBackendHandler::new();
@@ -18,7 +18,7 @@ pub trait OpaqueHandler: Send + Sync {
     async fn registration_finish(
         &self,
         request: registration::ClientRegistrationFinishRequest,
-    ) -> Result<()>;
+    ) -> Result<UserId>;
 }
 
 #[cfg(test)]
@@ -41,6 +41,6 @@ mockall::mock! {
         async fn registration_finish(
             &self,
             request: registration::ClientRegistrationFinishRequest
-        ) -> Result<()>;
+        ) -> Result<UserId>;
     }
 }
@@ -1,11 +1,15 @@
 pub(crate) mod logging;
+pub(crate) mod sql_audit_backend_handler;
 pub(crate) mod sql_backend_handler;
+pub(crate) mod sql_dynamic_groups;
 pub(crate) mod sql_group_backend_handler;
 pub(crate) mod sql_opaque_handler;
 pub(crate) mod sql_schema_backend_handler;
 pub(crate) mod sql_user_backend_handler;
+#[cfg(any(test, feature = "test"))]
+pub mod test_fixture;
 
 pub use sql_backend_handler::SqlBackendHandler;
-pub use sql_opaque_handler::register_password;
+pub use sql_opaque_handler::{register_password, set_password_file};
 pub mod sql_migrations;
 pub mod sql_tables;
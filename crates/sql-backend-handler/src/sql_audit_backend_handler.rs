@@ -0,0 +1,209 @@
+use crate::sql_backend_handler::SqlBackendHandler;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use lldap_domain_handlers::audit::{
+    AuditAction, AuditEvent, AuditInterface, AuditLogBackendHandler, AuditLogEntry, AuditLogFilter,
+    AuditSource,
+};
+use lldap_domain_model::{
+    error::{DomainError, Result},
+    model::{self, AuditLogColumn},
+};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    sea_query::Cond,
+};
+use tracing::instrument;
+
+fn get_audit_filter_cond(filter: AuditLogFilter) -> Cond {
+    let mut cond = Cond::all();
+    if let Some(actor) = filter.actor {
+        cond = cond.add(AuditLogColumn::Actor.eq(actor));
+    }
+    if let Some(target) = filter.target {
+        cond = cond.add(AuditLogColumn::Target.eq(target));
+    }
+    if let Some(action) = filter.action {
+        cond = cond.add(AuditLogColumn::Action.eq(action.as_str()));
+    }
+    if let Some(interface) = filter.interface {
+        cond = cond.add(AuditLogColumn::Interface.eq(interface.as_str()));
+    }
+    if let Some(success) = filter.success {
+        cond = cond.add(AuditLogColumn::Success.eq(success));
+    }
+    if let Some(since) = filter.since {
+        cond = cond.add(AuditLogColumn::Timestamp.gte(since));
+    }
+    if let Some(until) = filter.until {
+        cond = cond.add(AuditLogColumn::Timestamp.lt(until));
+    }
+    cond
+}
+
+fn to_entry(model: model::audit_log::Model) -> Result<AuditLogEntry> {
+    let action = AuditAction::from_str_opt(&model.action).ok_or_else(|| {
+        DomainError::InternalError(format!("Unknown audit action '{}'", model.action))
+    })?;
+    let interface = AuditInterface::from_str_opt(&model.interface).ok_or_else(|| {
+        DomainError::InternalError(format!("Unknown audit interface '{}'", model.interface))
+    })?;
+    Ok(AuditLogEntry {
+        id: model.id,
+        timestamp: model.timestamp,
+        event: AuditEvent {
+            actor: model.actor,
+            action,
+            target: model.target,
+            details: model.details,
+            source: AuditSource {
+                interface,
+                ip: model.source_ip.and_then(|ip| ip.parse().ok()),
+            },
+            success: model.success,
+        },
+    })
+}
+
+#[async_trait]
+impl AuditLogBackendHandler for SqlBackendHandler {
+    #[instrument(skip(self), level = "debug", err)]
+    async fn record_audit_event(&self, event: AuditEvent) -> Result<()> {
+        model::audit_log::ActiveModel {
+            timestamp: Set(chrono::Utc::now().naive_utc()),
+            actor: Set(event.actor),
+            action: Set(event.action.as_str().to_owned()),
+            target: Set(event.target),
+            details: Set(event.details),
+            source_ip: Set(event.source.ip.map(|ip| ip.to_string())),
+            interface: Set(event.source.interface.as_str().to_owned()),
+            success: Set(event.success),
+            ..Default::default()
+        }
+        .insert(&self.sql_pool)
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(skip(self), level = "debug", ret, err)]
+    async fn list_audit_events(
+        &self,
+        filter: AuditLogFilter,
+        limit: u64,
+    ) -> Result<Vec<AuditLogEntry>> {
+        model::AuditLog::find()
+            .filter(get_audit_filter_cond(filter))
+            .order_by_desc(AuditLogColumn::Id)
+            .limit(limit)
+            .all(&self.sql_pool)
+            .await?
+            .into_iter()
+            .map(to_entry)
+            .collect()
+    }
+
+    #[instrument(skip(self), level = "debug", ret, err)]
+    async fn delete_audit_events_before(&self, date: NaiveDateTime) -> Result<u64> {
+        Ok(model::AuditLog::delete_many()
+            .filter(AuditLogColumn::Timestamp.lt(date))
+            .exec(&self.sql_pool)
+            .await?
+            .rows_affected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql_backend_handler::tests::*;
+    use lldap_domain::types::UserId;
+    use pretty_assertions::assert_eq;
+
+    fn make_event(actor: &str, action: AuditAction, success: bool) -> AuditEvent {
+        AuditEvent {
+            actor: Some(UserId::new(actor)),
+            action,
+            target: Some("patrick".to_owned()),
+            details: None,
+            source: AuditSource::new(AuditInterface::Ldap, Some("10.0.0.1".parse().unwrap())),
+            success,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_and_list_audit_events() {
+        let fixture = TestFixture::new().await;
+        let handler = &fixture.handler;
+        handler
+            .record_audit_event(make_event("bob", AuditAction::Login, false))
+            .await
+            .unwrap();
+        handler
+            .record_audit_event(make_event("bob", AuditAction::Login, true))
+            .await
+            .unwrap();
+        handler
+            .record_audit_event(make_event("admin", AuditAction::DeleteUser, true))
+            .await
+            .unwrap();
+        let all = handler
+            .list_audit_events(AuditLogFilter::default(), 10)
+            .await
+            .unwrap();
+        assert_eq!(
+            all.into_iter().map(|e| e.event).collect::<Vec<_>>(),
+            vec![
+                make_event("admin", AuditAction::DeleteUser, true),
+                make_event("bob", AuditAction::Login, true),
+                make_event("bob", AuditAction::Login, false),
+            ]
+        );
+        let failed_logins = handler
+            .list_audit_events(
+                AuditLogFilter {
+                    action: Some(AuditAction::Login),
+                    success: Some(false),
+                    ..Default::default()
+                },
+                10,
+            )
+            .await
+            .unwrap();
+        assert_eq!(failed_logins.len(), 1);
+        let limited = handler
+            .list_audit_events(
+                AuditLogFilter {
+                    actor: Some(UserId::new("bob")),
+                    ..Default::default()
+                },
+                1,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            limited.into_iter().map(|e| e.event).collect::<Vec<_>>(),
+            vec![make_event("bob", AuditAction::Login, true)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_audit_events_before() {
+        let fixture = TestFixture::new().await;
+        let handler = &fixture.handler;
+        handler
+            .record_audit_event(make_event("bob", AuditAction::Login, true))
+            .await
+            .unwrap();
+        let past = chrono::Utc::now().naive_utc() - chrono::Duration::days(1);
+        assert_eq!(handler.delete_audit_events_before(past).await.unwrap(), 0);
+        let future = chrono::Utc::now().naive_utc() + chrono::Duration::days(1);
+        assert_eq!(handler.delete_audit_events_before(future).await.unwrap(), 1);
+        assert!(
+            handler
+                .list_audit_events(AuditLogFilter::default(), 10)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -1,12 +1,18 @@
 use crate::sql_tables::DbConnection;
 use async_trait::async_trait;
 use lldap_auth::opaque::server::ServerSetup;
-use lldap_domain_handlers::handler::BackendHandler;
+use lldap_domain_handlers::{
+    events::{DirectoryEvent, DirectoryEventListener},
+    handler::{BackendHandler, ComparisonOperator},
+};
+use sea_orm::sea_query::{Cond, Expr, IntoColumnRef, IntoCondition, SimpleExpr};
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct SqlBackendHandler {
     pub(crate) opaque_setup: ServerSetup,
     pub(crate) sql_pool: DbConnection,
+    event_listeners: Vec<Arc<dyn DirectoryEventListener>>,
 }
 
 impl SqlBackendHandler {
@@ -14,154 +20,80 @@ impl SqlBackendHandler {
         SqlBackendHandler {
             opaque_setup,
             sql_pool,
+            event_listeners: Vec::new(),
         }
     }
 
+    /// Notifies the listener of every successful change to the users, groups and schema. Can be
+    /// called several times, to add more listeners.
+    pub fn with_event_listener(mut self, listener: Arc<dyn DirectoryEventListener>) -> Self {
+        self.event_listeners.push(listener);
+        self
+    }
+
     pub fn pool(&self) -> &DbConnection {
         &self.sql_pool
     }
+
+    pub(crate) async fn emit_event(&self, event: DirectoryEvent) {
+        for listener in &self.event_listeners {
+            listener.on_event(event.clone()).await;
+        }
+    }
 }
 
 #[async_trait]
 impl BackendHandler for SqlBackendHandler {}
 
-#[cfg(test)]
-pub mod tests {
-    use super::*;
-    use crate::sql_tables::init_table;
-    use lldap_auth::{
-        opaque::{self, server::generate_random_private_key},
-        registration,
-    };
-    use lldap_domain::{
-        requests::{CreateGroupRequest, CreateUserRequest},
-        types::{Attribute as DomainAttribute, GroupId, UserId},
-    };
-    use lldap_domain_handlers::handler::{
-        GroupBackendHandler, UserBackendHandler, UserListerBackendHandler, UserRequestFilter,
+/// Selects the entries sorted after the cursor entry, by `sort` then by `id`. `cursor_sort` is
+/// the sort value of the cursor entry, usually a subquery.
+pub(crate) fn after_cursor_condition(
+    sort: SimpleExpr,
+    cursor_sort: SimpleExpr,
+    id: SimpleExpr,
+    cursor_id: SimpleExpr,
+    descending: bool,
+) -> Cond {
+    let after = |value: SimpleExpr, cursor: SimpleExpr| {
+        if descending {
+            Expr::expr(value).lt(cursor)
+        } else {
+            Expr::expr(value).gt(cursor)
+        }
     };
-    use pretty_assertions::assert_eq;
-    use sea_orm::Database;
-
-    pub async fn get_in_memory_db() -> DbConnection {
-        crate::logging::init_for_tests();
-        let mut sql_opt = sea_orm::ConnectOptions::new("sqlite::memory:".to_owned());
-        sql_opt
-            .max_connections(1)
-            .sqlx_logging(true)
-            .sqlx_logging_level(log::LevelFilter::Debug);
-        Database::connect(sql_opt).await.unwrap()
-    }
-
-    pub async fn get_initialized_db() -> DbConnection {
-        let sql_pool = get_in_memory_db().await;
-        init_table(&sql_pool).await.unwrap();
-        sql_pool
-    }
-
-    pub async fn insert_user(handler: &SqlBackendHandler, name: &str, pass: &str) {
-        use lldap_opaque_handler::OpaqueHandler;
-        insert_user_no_password(handler, name).await;
-        let mut rng = rand::rngs::OsRng;
-        let client_registration_start =
-            opaque::client::registration::start_registration(pass.as_bytes(), &mut rng).unwrap();
-        let response = handler
-            .registration_start(registration::ClientRegistrationStartRequest {
-                username: name.into(),
-                registration_start_request: client_registration_start.message,
-            })
-            .await
-            .unwrap();
-        let registration_upload = opaque::client::registration::finish_registration(
-            client_registration_start.state,
-            response.registration_response,
-            &mut rng,
+    Cond::any()
+        .add(after(sort.clone(), cursor_sort.clone()))
+        .add(
+            Cond::all()
+                .add(Expr::expr(sort).eq(cursor_sort))
+                .add(after(id, cursor_id)),
         )
-        .unwrap();
-        handler
-            .registration_finish(registration::ClientRegistrationFinishRequest {
-                server_data: response.server_data,
-                registration_upload: registration_upload.message,
-            })
-            .await
-            .unwrap();
-    }
-
-    pub async fn insert_user_no_password(handler: &SqlBackendHandler, name: &str) {
-        handler
-            .create_user(CreateUserRequest {
-                user_id: UserId::new(name),
-                email: format!("{name}@bob.bob").into(),
-                display_name: Some("display ".to_string() + name),
-                attributes: vec![
-                    DomainAttribute {
-                        name: "first_name".into(),
-                        value: ("first ".to_string() + name).into(),
-                    },
-                    DomainAttribute {
-                        name: "last_name".into(),
-                        value: ("last ".to_string() + name).into(),
-                    },
-                ],
-            })
-            .await
-            .unwrap();
-    }
-
-    pub async fn insert_group(handler: &SqlBackendHandler, name: &str) -> GroupId {
-        handler
-            .create_group(CreateGroupRequest {
-                display_name: name.into(),
-                ..Default::default()
-            })
-            .await
-            .unwrap()
-    }
-
-    pub async fn insert_membership(handler: &SqlBackendHandler, group_id: GroupId, user_id: &str) {
-        handler
-            .add_user_to_group(&UserId::new(user_id), group_id)
-            .await
-            .unwrap();
-    }
-
-    pub async fn get_user_names(
-        handler: &SqlBackendHandler,
-        filters: Option<UserRequestFilter>,
-    ) -> Vec<String> {
-        handler
-            .list_users(filters, false)
-            .await
-            .unwrap()
-            .into_iter()
-            .map(|u| u.user.user_id.to_string())
-            .collect::<Vec<_>>()
-    }
+}
 
-    pub struct TestFixture {
-        pub handler: SqlBackendHandler,
-        pub groups: Vec<GroupId>,
+/// `column <op> value`, for the comparison filters.
+pub(crate) fn comparison_condition(
+    column: impl IntoColumnRef,
+    operator: ComparisonOperator,
+    value: impl Into<SimpleExpr>,
+) -> Cond {
+    let column = Expr::col(column);
+    match operator {
+        ComparisonOperator::LessThan => column.lt(value),
+        ComparisonOperator::LessOrEqual => column.lte(value),
+        ComparisonOperator::GreaterThan => column.gt(value),
+        ComparisonOperator::GreaterOrEqual => column.gte(value),
     }
+    .into_condition()
+}
 
-    impl TestFixture {
-        pub async fn new() -> Self {
-            let sql_pool = get_initialized_db().await;
-            let handler = SqlBackendHandler::new(generate_random_private_key(), sql_pool);
-            insert_user_no_password(&handler, "bob").await;
-            insert_user_no_password(&handler, "patrick").await;
-            insert_user_no_password(&handler, "John").await;
-            insert_user_no_password(&handler, "NoGroup").await;
-            let mut groups = vec![];
-            groups.push(insert_group(&handler, "Best Group").await);
-            groups.push(insert_group(&handler, "Worst Group").await);
-            groups.push(insert_group(&handler, "Empty Group").await);
-            insert_membership(&handler, groups[0], "bob").await;
-            insert_membership(&handler, groups[0], "patrick").await;
-            insert_membership(&handler, groups[1], "patrick").await;
-            insert_membership(&handler, groups[1], "John").await;
-            Self { handler, groups }
-        }
-    }
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    pub use crate::test_fixture::*;
+    use lldap_auth::opaque::server::generate_random_private_key;
+    use lldap_domain::types::UserId;
+    use lldap_domain_handlers::handler::{UserBackendHandler, UserListerBackendHandler};
+    use pretty_assertions::assert_eq;
 
     #[tokio::test]
     async fn test_sql_injection() {
@@ -183,4 +115,61 @@ pub mod tests {
             assert_eq!(user.user_id, user_name);
         }
     }
+
+    #[derive(Default)]
+    struct RecordingListener {
+        events: std::sync::Mutex<Vec<DirectoryEvent>>,
+    }
+
+    #[async_trait]
+    impl DirectoryEventListener for RecordingListener {
+        async fn on_event(&self, event: DirectoryEvent) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_events_emitted_after_successful_changes() {
+        let sql_pool = get_initialized_db().await;
+        let listener = Arc::new(RecordingListener::default());
+        let handler = SqlBackendHandler::new(generate_random_private_key(), sql_pool)
+            .with_event_listener(listener.clone());
+        insert_user_no_password(&handler, "bob").await;
+        let group_id = insert_group(&handler, "Best Group").await;
+        insert_membership(&handler, group_id, "bob").await;
+        handler
+            .remove_user_from_group(&UserId::new("bob"), group_id)
+            .await
+            .unwrap();
+        // Failed changes don't emit anything.
+        handler
+            .remove_user_from_group(&UserId::new("bob"), group_id)
+            .await
+            .unwrap_err();
+        handler.delete_user(&UserId::new("bob")).await.unwrap();
+        handler.delete_user(&UserId::new("bob")).await.unwrap_err();
+        assert_eq!(
+            *listener.events.lock().unwrap(),
+            vec![
+                DirectoryEvent::UserCreated {
+                    user_id: UserId::new("bob")
+                },
+                DirectoryEvent::GroupCreated {
+                    group_id,
+                    display_name: "Best Group".into()
+                },
+                DirectoryEvent::UserAddedToGroup {
+                    user_id: UserId::new("bob"),
+                    group_id
+                },
+                DirectoryEvent::UserRemovedFromGroup {
+                    user_id: UserId::new("bob"),
+                    group_id
+                },
+                DirectoryEvent::UserDeleted {
+                    user_id: UserId::new("bob")
+                },
+            ]
+        );
+    }
 }
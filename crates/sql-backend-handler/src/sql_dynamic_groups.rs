@@ -0,0 +1,249 @@
+//! The members of a dynamic group are not stored: they are the users matching the filter of the
+//! group, evaluated whenever the memberships are read.
+
+use crate::{
+    sql_backend_handler::SqlBackendHandler, sql_user_backend_handler::get_user_filter_expr,
+};
+use lldap_domain::types::{AttributeName, GroupDetails, GroupId, UserId};
+use lldap_domain_handlers::handler::{GroupRequestFilter, UserRequestFilter};
+use lldap_domain_model::{
+    error::{DomainError, Result},
+    model::{self, GroupColumn, UserColumn},
+};
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseTransaction, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, sea_query::Cond,
+};
+use std::collections::HashMap;
+
+pub(crate) struct DynamicGroup {
+    pub details: GroupDetails,
+    pub filter: UserRequestFilter,
+}
+
+fn has_membership_filter(filter: &UserRequestFilter) -> bool {
+    use UserRequestFilter::*;
+    match filter {
+        And(fs) | Or(fs) => fs.iter().any(has_membership_filter),
+        Not(f) => has_membership_filter(f),
+        MemberOf(_) | MemberOfId(_) => true,
+        _ => false,
+    }
+}
+
+/// Replaces the memberships of the dynamic groups by the filters of these groups. The filters
+/// are not expanded themselves: a `MemberOf` in them only matches the static memberships.
+fn expand_membership_filters(
+    filter: UserRequestFilter,
+    dynamic_groups: &[DynamicGroup],
+) -> UserRequestFilter {
+    use UserRequestFilter::*;
+    let rec = |f| expand_membership_filters(f, dynamic_groups);
+    match filter {
+        And(fs) => And(fs.into_iter().map(rec).collect()),
+        Or(fs) => Or(fs.into_iter().map(rec).collect()),
+        Not(f) => Not(Box::new(rec(*f))),
+        MemberOf(name) => dynamic_groups
+            .iter()
+            .find(|g| g.details.display_name == name)
+            .map(|g| g.filter.clone())
+            .unwrap_or(MemberOf(name)),
+        MemberOfId(group_id) => dynamic_groups
+            .iter()
+            .find(|g| g.details.group_id == group_id)
+            .map(|g| g.filter.clone())
+            .unwrap_or(MemberOfId(group_id)),
+        f => f,
+    }
+}
+
+fn uses_attribute(filter: &UserRequestFilter, name: &AttributeName) -> bool {
+    use UserRequestFilter::*;
+    match filter {
+        And(fs) | Or(fs) => fs.iter().any(|f| uses_attribute(f, name)),
+        Not(f) => uses_attribute(f, name),
+        AttributeEquality(attribute, _) | CustomAttributePresent(attribute) => attribute == name,
+        _ => false,
+    }
+}
+
+fn collect_member_filters(filter: &GroupRequestFilter, users: &mut Vec<UserId>) {
+    use GroupRequestFilter::*;
+    match filter {
+        And(fs) | Or(fs) => fs.iter().for_each(|f| collect_member_filters(f, users)),
+        Not(f) => collect_member_filters(f, users),
+        Member(user_id) if !users.contains(user_id) => users.push(user_id.clone()),
+        _ => (),
+    }
+}
+
+/// Adds the dynamic groups of the users to their `Member` filters.
+fn expand_member_filters(
+    filter: GroupRequestFilter,
+    groups_by_user: &HashMap<UserId, Vec<GroupId>>,
+) -> GroupRequestFilter {
+    use GroupRequestFilter::*;
+    let rec = |f| expand_member_filters(f, groups_by_user);
+    match filter {
+        And(fs) => And(fs.into_iter().map(rec).collect()),
+        Or(fs) => Or(fs.into_iter().map(rec).collect()),
+        Not(f) => Not(Box::new(rec(*f))),
+        Member(user_id) => match groups_by_user.get(&user_id) {
+            Some(group_ids) if !group_ids.is_empty() => Or(group_ids
+                .iter()
+                .copied()
+                .map(GroupRequestFilter::GroupId)
+                .chain(std::iter::once(Member(user_id)))
+                .collect()),
+            _ => Member(user_id),
+        },
+        f => f,
+    }
+}
+
+impl SqlBackendHandler {
+    pub(crate) async fn get_dynamic_groups(
+        connection: &impl ConnectionTrait,
+    ) -> Result<Vec<DynamicGroup>> {
+        model::Group::find()
+            .filter(GroupColumn::DynamicUserFilter.is_not_null())
+            .order_by_asc(GroupColumn::GroupId)
+            .all(connection)
+            .await?
+            .into_iter()
+            .map(|group| {
+                let filter =
+                    serde_json::from_str(group.dynamic_user_filter.as_deref().unwrap_or_default())
+                        .map_err(|e| {
+                            DomainError::InternalError(format!(
+                                "Invalid filter for the dynamic group {:?}: {e}",
+                                group.group_id
+                            ))
+                        })?;
+                Ok(DynamicGroup {
+                    details: group.into(),
+                    filter,
+                })
+            })
+            .collect()
+    }
+
+    /// The members of the dynamic group among the users matching `users`, sorted by ID.
+    pub(crate) async fn get_dynamic_group_members(
+        &self,
+        group: &DynamicGroup,
+        users: Cond,
+    ) -> Result<Vec<UserId>> {
+        Ok(model::User::find()
+            .select_only()
+            .column(UserColumn::UserId)
+            .filter(users)
+            .filter(get_user_filter_expr(group.filter.clone()))
+            .order_by_asc(UserColumn::UserId)
+            .into_tuple()
+            .all(&self.sql_pool)
+            .await?)
+    }
+
+    /// The dynamic groups whose filter matches the user.
+    pub(crate) async fn get_dynamic_groups_of_user(
+        &self,
+        user_id: &UserId,
+        dynamic_groups: &[DynamicGroup],
+    ) -> Result<Vec<GroupDetails>> {
+        let mut groups = Vec::new();
+        for group in dynamic_groups {
+            if !self
+                .get_dynamic_group_members(
+                    group,
+                    Cond::all().add(ColumnTrait::eq(&UserColumn::UserId, user_id.clone())),
+                )
+                .await?
+                .is_empty()
+            {
+                groups.push(group.details.clone());
+            }
+        }
+        Ok(groups)
+    }
+
+    /// Replaces the `MemberOf` and `MemberOfId` filters of the dynamic groups by their filter.
+    pub(crate) async fn expand_user_filter(
+        &self,
+        filter: Option<UserRequestFilter>,
+    ) -> Result<Option<UserRequestFilter>> {
+        match filter {
+            Some(filter) if has_membership_filter(&filter) => {
+                let dynamic_groups = Self::get_dynamic_groups(&self.sql_pool).await?;
+                Ok(Some(expand_membership_filters(filter, &dynamic_groups)))
+            }
+            filter => Ok(filter),
+        }
+    }
+
+    /// Makes the `Member` filters match the dynamic groups of the user as well.
+    pub(crate) async fn expand_group_filter(
+        &self,
+        filter: Option<GroupRequestFilter>,
+    ) -> Result<Option<GroupRequestFilter>> {
+        let Some(filter) = filter else {
+            return Ok(None);
+        };
+        let mut users = Vec::new();
+        collect_member_filters(&filter, &mut users);
+        if users.is_empty() {
+            return Ok(Some(filter));
+        }
+        let dynamic_groups = Self::get_dynamic_groups(&self.sql_pool).await?;
+        let mut groups_by_user = HashMap::new();
+        for user_id in users {
+            let groups = self
+                .get_dynamic_groups_of_user(&user_id, &dynamic_groups)
+                .await?;
+            groups_by_user.insert(user_id, groups.into_iter().map(|g| g.group_id).collect());
+        }
+        Ok(Some(expand_member_filters(filter, &groups_by_user)))
+    }
+
+    /// Fails if the filter of a dynamic group uses the user attribute, since deleting it would
+    /// change the members of the group.
+    pub(crate) async fn check_attribute_not_in_dynamic_filters(
+        transaction: &DatabaseTransaction,
+        name: &AttributeName,
+    ) -> Result<()> {
+        match Self::get_dynamic_groups(transaction)
+            .await?
+            .into_iter()
+            .find(|g| uses_attribute(&g.filter, name))
+        {
+            Some(group) => Err(DomainError::ValidationError(format!(
+                "The attribute {name} is used by the filter of the dynamic group \"{}\"",
+                group.details.display_name
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) async fn is_dynamic_group(
+        transaction: &DatabaseTransaction,
+        group_id: GroupId,
+    ) -> Result<bool> {
+        Ok(model::Group::find_by_id(group_id)
+            .one(transaction)
+            .await?
+            .is_some_and(|g| g.dynamic_filter.is_some()))
+    }
+
+    /// The members of a dynamic group can't be added or removed directly.
+    pub(crate) async fn check_static_group(
+        transaction: &DatabaseTransaction,
+        group_id: GroupId,
+    ) -> Result<()> {
+        if Self::is_dynamic_group(transaction, group_id).await? {
+            return Err(DomainError::ValidationError(format!(
+                "The members of the dynamic group {group_id:?} can't be changed directly"
+            )));
+        }
+        Ok(())
+    }
+}
@@ -1,21 +1,41 @@
-use crate::sql_backend_handler::SqlBackendHandler;
+use crate::{
+    sql_backend_handler::{SqlBackendHandler, after_cursor_condition, comparison_condition},
+    sql_schema_backend_handler::{
+        check_attribute_values, check_removed_attributes, check_required_attributes,
+        get_unique_attributes,
+    },
+};
 use async_trait::async_trait;
 use lldap_access_control::UserReadableBackendHandler;
 use lldap_domain::{
     requests::{CreateGroupRequest, UpdateGroupRequest},
-    types::{AttributeName, Group, GroupDetails, GroupId, Serialized, Uuid},
+    schema::Schema,
+    types::{
+        Attribute, AttributeName, Group, GroupDetails, GroupId, GroupName, GroupOwner, Serialized,
+        Uuid,
+    },
 };
-use lldap_domain_handlers::handler::{
-    GroupBackendHandler, GroupListerBackendHandler, GroupRequestFilter,
+use lldap_domain_handlers::{
+    events::DirectoryEvent,
+    handler::{
+        DynamicGroupFilter, GroupBackendHandler, GroupListerBackendHandler, GroupOrderField,
+        GroupPageRequest, GroupRequestFilter, Page,
+    },
 };
 use lldap_domain_model::{
     error::{DomainError, Result},
-    model::{self, GroupColumn, MembershipColumn, deserialize},
+    model::{
+        self, GroupColumn, GroupOwnerGroupsColumn, GroupOwnerUsersColumn, MembershipColumn,
+        deserialize,
+    },
 };
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseTransaction, EntityTrait, QueryFilter, QueryOrder,
-    QuerySelect, QueryTrait, Set, TransactionTrait,
-    sea_query::{Alias, Cond, Expr, Func, IntoCondition, OnConflict, SimpleExpr},
+    ActiveModelTrait, ColumnTrait, DatabaseTransaction, EntityTrait, JoinType, Order,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, QueryTrait, RelationTrait, Set,
+    TransactionTrait,
+    sea_query::{
+        Alias, Cond, Expr, Func, IntoCondition, OnConflict, SimpleExpr, SubQueryStatement,
+    },
 };
 use tracing::instrument;
 
@@ -76,6 +96,36 @@ fn get_group_filter_expr(filter: GroupRequestFilter) -> Cond {
                     .into_query(),
             )
             .into_condition(),
+        // WHERE (group_id in (SELECT group_id FROM group_owner_users WHERE user_id = user)
+        //   OR group_id in (SELECT group_id FROM group_owner_groups WHERE owner_group_id in
+        //     (SELECT group_id FROM memberships WHERE user_id = user)))
+        OwnedBy(user) => Cond::any()
+            .add(
+                GroupColumn::GroupId.in_subquery(
+                    model::GroupOwnerUsers::find()
+                        .select_only()
+                        .column(GroupOwnerUsersColumn::GroupId)
+                        .filter(GroupOwnerUsersColumn::UserId.eq(user.clone()))
+                        .into_query(),
+                ),
+            )
+            .add(
+                GroupColumn::GroupId.in_subquery(
+                    model::GroupOwnerGroups::find()
+                        .select_only()
+                        .column(GroupOwnerGroupsColumn::GroupId)
+                        .filter(
+                            GroupOwnerGroupsColumn::OwnerGroupId.in_subquery(
+                                model::Membership::find()
+                                    .select_only()
+                                    .column(MembershipColumn::GroupId)
+                                    .filter(MembershipColumn::UserId.eq(user))
+                                    .into_query(),
+                            ),
+                        )
+                        .into_query(),
+                ),
+            ),
         DisplayNameSubString(filter) => SimpleExpr::FunctionCall(Func::lower(Expr::col((
             group_table,
             GroupColumn::LowercaseDisplayName,
@@ -84,27 +134,53 @@ fn get_group_filter_expr(filter: GroupRequestFilter) -> Cond {
         .into_condition(),
         AttributeEquality(name, value) => attribute_condition(name, Some(value.into())),
         CustomAttributePresent(name) => attribute_condition(name, None),
+        CreationDateComparison(operator, date) => {
+            comparison_condition(GroupColumn::CreationDate.as_column_ref(), operator, date)
+        }
+        ModifiedDateComparison(operator, date) => {
+            comparison_condition(GroupColumn::ModifiedDate.as_column_ref(), operator, date)
+        }
+        GroupIdComparison(operator, id) => {
+            comparison_condition(GroupColumn::GroupId.as_column_ref(), operator, id.0)
+        }
     }
 }
 
+// The filters can refer to the memberships, they are applied with a join in a subquery.
+fn get_group_filter_condition(filters: Option<GroupRequestFilter>) -> Cond {
+    filters
+        .map(|f| {
+            GroupColumn::GroupId
+                .in_subquery(
+                    model::Group::find()
+                        .find_also_linked(model::memberships::GroupToUser)
+                        .select_only()
+                        .column(GroupColumn::GroupId)
+                        .filter(get_group_filter_expr(f))
+                        .into_query(),
+                )
+                .into_condition()
+        })
+        .unwrap_or_else(|| SimpleExpr::Value(true.into()).into_condition())
+}
+
+fn get_group_order_expr(field: GroupOrderField) -> SimpleExpr {
+    Expr::col(
+        match field {
+            GroupOrderField::DisplayName => GroupColumn::LowercaseDisplayName,
+            GroupOrderField::GroupId => GroupColumn::GroupId,
+            GroupOrderField::CreationDate => GroupColumn::CreationDate,
+        }
+        .as_column_ref(),
+    )
+    .into()
+}
+
 #[async_trait]
 impl GroupListerBackendHandler for SqlBackendHandler {
     #[instrument(skip(self), level = "debug", ret, err)]
     async fn list_groups(&self, filters: Option<GroupRequestFilter>) -> Result<Vec<Group>> {
-        let filters = filters
-            .map(|f| {
-                GroupColumn::GroupId
-                    .in_subquery(
-                        model::Group::find()
-                            .find_also_linked(model::memberships::GroupToUser)
-                            .select_only()
-                            .column(GroupColumn::GroupId)
-                            .filter(get_group_filter_expr(f))
-                            .into_query(),
-                    )
-                    .into_condition()
-            })
-            .unwrap_or_else(|| SimpleExpr::Value(true.into()).into_condition());
+        let filters = get_group_filter_condition(self.expand_group_filter(filters).await?);
         let results = model::Group::find()
             .order_by_asc(GroupColumn::GroupId)
             .find_with_related(model::Membership)
@@ -127,7 +203,7 @@ impl GroupListerBackendHandler for SqlBackendHandler {
             .filter(
                 model::GroupAttributesColumn::GroupId.in_subquery(
                     model::Group::find()
-                        .filter(filters)
+                        .filter(filters.clone())
                         .select_only()
                         .column(model::groups::Column::GroupId)
                         .into_query(),
@@ -151,9 +227,131 @@ impl GroupListerBackendHandler for SqlBackendHandler {
                 })
                 .collect::<Result<Vec<_>>>()?;
         }
+        let group_ids_query = model::Group::find()
+            .filter(filters)
+            .select_only()
+            .column(GroupColumn::GroupId)
+            .into_query();
+        let owner_users = model::GroupOwnerUsers::find()
+            .filter(GroupOwnerUsersColumn::GroupId.in_subquery(group_ids_query.clone()))
+            .order_by_asc(GroupOwnerUsersColumn::GroupId)
+            .order_by_asc(GroupOwnerUsersColumn::UserId)
+            .all(&self.sql_pool)
+            .await?;
+        let owner_groups: Vec<(GroupId, GroupName)> = model::GroupOwnerGroups::find()
+            .select_only()
+            .column(GroupOwnerGroupsColumn::GroupId)
+            .column(GroupColumn::DisplayName)
+            .join(
+                JoinType::InnerJoin,
+                model::group_owner_groups::Relation::OwnerGroups.def(),
+            )
+            .filter(GroupOwnerGroupsColumn::GroupId.in_subquery(group_ids_query))
+            .order_by_asc(GroupOwnerGroupsColumn::GroupId)
+            .order_by_asc(GroupColumn::LowercaseDisplayName)
+            .into_tuple()
+            .all(&self.sql_pool)
+            .await?;
+        let mut owner_users_iter = owner_users.into_iter().peekable();
+        let mut owner_groups_iter = owner_groups.into_iter().peekable();
+        for group in groups.iter_mut() {
+            group.owners = owner_users_iter
+                .take_while_ref(|o| o.group_id == group.id)
+                .map(|o| GroupOwner::User(o.user_id))
+                .chain(
+                    owner_groups_iter
+                        .take_while_ref(|(group_id, _)| group_id == &group.id)
+                        .map(|(_, name)| GroupOwner::Group(name)),
+                )
+                .collect();
+        }
+        for group in Self::get_dynamic_groups(&self.sql_pool).await? {
+            if let Some(g) = groups.iter_mut().find(|g| g.id == group.details.group_id) {
+                g.users = self.get_dynamic_group_members(&group, Cond::all()).await?;
+            }
+        }
         groups.sort_by(|g1, g2| g1.display_name.cmp(&g2.display_name));
         Ok(groups)
     }
+
+    #[instrument(skip(self), level = "debug", ret, err)]
+    async fn list_groups_page(
+        &self,
+        filters: Option<GroupRequestFilter>,
+        page: GroupPageRequest,
+    ) -> Result<Page<Group>> {
+        let filters = get_group_filter_condition(self.expand_group_filter(filters).await?);
+        // The position of the cursor is read from the group, which must still exist.
+        if let Some(after) = page.after
+            && model::Group::find()
+                .filter(GroupColumn::GroupId.eq(after))
+                .count(&self.sql_pool)
+                .await?
+                == 0
+        {
+            return Err(DomainError::ValidationError(format!(
+                "Unknown cursor: {}",
+                after.0
+            )));
+        }
+        let total_count = model::Group::find()
+            .filter(filters.clone())
+            .count(&self.sql_pool)
+            .await?;
+        let sort = get_group_order_expr(page.order_by);
+        let order = if page.descending {
+            Order::Desc
+        } else {
+            Order::Asc
+        };
+        let mut query = model::Group::find()
+            .select_only()
+            .column(GroupColumn::GroupId)
+            .filter(filters);
+        if let Some(after) = page.after {
+            let cursor_sort = SimpleExpr::SubQuery(
+                None,
+                Box::new(SubQueryStatement::SelectStatement(
+                    model::Group::find()
+                        .select_only()
+                        .expr(sort.clone())
+                        .filter(GroupColumn::GroupId.eq(after))
+                        .into_query(),
+                )),
+            );
+            query = query.filter(after_cursor_condition(
+                sort.clone(),
+                cursor_sort,
+                Expr::col(GroupColumn::GroupId.as_column_ref()).into(),
+                SimpleExpr::Value(after.into()),
+                page.descending,
+            ));
+        }
+        let mut group_ids: Vec<GroupId> = query
+            .order_by(sort, order.clone())
+            .order_by(GroupColumn::GroupId, order)
+            .limit(page.first + 1)
+            .into_tuple()
+            .all(&self.sql_pool)
+            .await?;
+        let has_next_page = group_ids.len() as u64 > page.first;
+        group_ids.truncate(page.first as usize);
+        let mut groups = self
+            .list_groups(Some(GroupRequestFilter::Or(
+                group_ids
+                    .iter()
+                    .copied()
+                    .map(GroupRequestFilter::GroupId)
+                    .collect(),
+            )))
+            .await?;
+        groups.sort_by_key(|g| group_ids.iter().position(|id| id == &g.id));
+        Ok(Page {
+            items: groups,
+            has_next_page,
+            total_count,
+        })
+    }
 }
 
 #[async_trait]
@@ -186,70 +384,256 @@ impl GroupBackendHandler for SqlBackendHandler {
 
     #[instrument(skip(self), level = "debug", err, fields(group_id = ?request.group_id))]
     async fn update_group(&self, request: UpdateGroupRequest) -> Result<()> {
-        Ok(self
-            .sql_pool
+        let group_id = request.group_id;
+        self.sql_pool
             .transaction::<_, (), DomainError>(|transaction| {
                 Box::pin(
                     async move { Self::update_group_with_transaction(request, transaction).await },
                 )
             })
-            .await?)
+            .await?;
+        self.emit_event(DirectoryEvent::GroupUpdated { group_id })
+            .await;
+        Ok(())
     }
 
     #[instrument(skip(self), level = "debug", ret, err)]
     async fn create_group(&self, request: CreateGroupRequest) -> Result<GroupId> {
+        self.create_group_with_filter(request, None).await
+    }
+
+    #[instrument(skip(self), level = "debug", ret, err)]
+    async fn create_dynamic_group(
+        &self,
+        request: CreateGroupRequest,
+        filter: DynamicGroupFilter,
+    ) -> Result<GroupId> {
+        self.create_group_with_filter(request, Some(filter)).await
+    }
+
+    #[instrument(skip(self), level = "debug", err)]
+    async fn delete_group(&self, group_id: GroupId) -> Result<()> {
+        self.sql_pool
+            .transaction::<_, (), DomainError>(|transaction| {
+                Box::pin(
+                    async move { Self::delete_group_with_transaction(transaction, group_id).await },
+                )
+            })
+            .await?;
+        self.emit_event(DirectoryEvent::GroupDeleted { group_id })
+            .await;
+        Ok(())
+    }
+
+    #[instrument(skip(self), level = "debug", err)]
+    async fn add_group_owner(&self, group_id: GroupId, owner: GroupOwner) -> Result<()> {
+        self.sql_pool
+            .transaction::<_, (), DomainError>(|transaction| {
+                Box::pin(async move {
+                    Self::touch_group(transaction, group_id).await?;
+                    match owner {
+                        GroupOwner::User(user_id) => {
+                            model::group_owner_users::ActiveModel {
+                                group_id: Set(group_id),
+                                user_id: Set(user_id),
+                            }
+                            .insert(transaction)
+                            .await?;
+                        }
+                        GroupOwner::Group(name) => {
+                            let owner_group_id =
+                                Self::get_group_id_by_name(transaction, &name).await?;
+                            // The owners are resolved through the stored memberships.
+                            if Self::is_dynamic_group(transaction, owner_group_id).await? {
+                                return Err(DomainError::InternalError(format!(
+                                    "A dynamic group can't own a group: '{name}'"
+                                )));
+                            }
+                            model::group_owner_groups::ActiveModel {
+                                group_id: Set(group_id),
+                                owner_group_id: Set(owner_group_id),
+                            }
+                            .insert(transaction)
+                            .await?;
+                        }
+                    }
+                    Ok(())
+                })
+            })
+            .await?;
+        self.emit_event(DirectoryEvent::GroupUpdated { group_id })
+            .await;
+        Ok(())
+    }
+
+    #[instrument(skip(self), level = "debug", err)]
+    async fn remove_group_owner(&self, group_id: GroupId, owner: GroupOwner) -> Result<()> {
+        self.sql_pool
+            .transaction::<_, (), DomainError>(|transaction| {
+                Box::pin(async move {
+                    let rows_affected = match &owner {
+                        GroupOwner::User(user_id) => {
+                            model::GroupOwnerUsers::delete_by_id((group_id, user_id.clone()))
+                                .exec(transaction)
+                                .await?
+                                .rows_affected
+                        }
+                        GroupOwner::Group(name) => {
+                            let owner_group_id =
+                                Self::get_group_id_by_name(transaction, name).await?;
+                            model::GroupOwnerGroups::delete_by_id((group_id, owner_group_id))
+                                .exec(transaction)
+                                .await?
+                                .rows_affected
+                        }
+                    };
+                    if rows_affected == 0 {
+                        return Err(DomainError::EntityNotFound(format!(
+                            "No such owner: {owner:?} -> {group_id:?}"
+                        )));
+                    }
+                    Self::touch_group(transaction, group_id).await
+                })
+            })
+            .await?;
+        self.emit_event(DirectoryEvent::GroupUpdated { group_id })
+            .await;
+        Ok(())
+    }
+}
+
+impl SqlBackendHandler {
+    async fn create_group_with_filter(
+        &self,
+        request: CreateGroupRequest,
+        filter: Option<DynamicGroupFilter>,
+    ) -> Result<GroupId> {
+        let display_name = request.display_name.clone();
+        let group_id = self
+            .sql_pool
+            .transaction::<_, GroupId, DomainError>(|transaction| {
+                Box::pin(async move {
+                    Self::create_group_with_transaction(transaction, request, filter).await
+                })
+            })
+            .await?;
+        self.emit_event(DirectoryEvent::GroupCreated {
+            group_id,
+            display_name,
+        })
+        .await;
+        Ok(group_id)
+    }
+
+    pub(crate) async fn create_group_with_transaction(
+        transaction: &DatabaseTransaction,
+        request: CreateGroupRequest,
+        filter: Option<DynamicGroupFilter>,
+    ) -> Result<GroupId> {
         let now = chrono::Utc::now().naive_utc();
         let uuid = Uuid::from_name_and_date(request.display_name.as_str(), &now);
         let lower_display_name = request.display_name.as_str().to_lowercase();
+        let (dynamic_filter, dynamic_user_filter) = match filter {
+            Some(filter) => {
+                let user_filter = serde_json::to_string(&filter.user_filter).map_err(|e| {
+                    DomainError::InternalError(format!("Could not serialize the filter: {e}"))
+                })?;
+                (Some(filter.ldap_filter), Some(user_filter))
+            }
+            None => (None, None),
+        };
         let new_group = model::groups::ActiveModel {
             display_name: Set(request.display_name),
             lowercase_display_name: Set(lower_display_name),
             creation_date: Set(now),
             uuid: Set(uuid),
             modified_date: Set(now),
+            dynamic_filter: Set(dynamic_filter),
+            dynamic_user_filter: Set(dynamic_user_filter),
             ..Default::default()
         };
-        Ok(self
-            .sql_pool
-            .transaction::<_, GroupId, DomainError>(|transaction| {
-                Box::pin(async move {
-                    let schema = Self::get_schema_with_transaction(transaction).await?;
-                    let group_id = new_group.insert(transaction).await?.group_id;
-                    let mut new_group_attributes = Vec::new();
-                    for attribute in request.attributes {
-                        if schema
-                            .group_attributes
-                            .get_attribute_type(&attribute.name)
-                            .is_some()
-                        {
-                            new_group_attributes.push(model::group_attributes::ActiveModel {
-                                group_id: Set(group_id),
-                                attribute_name: Set(attribute.name),
-                                value: Set(attribute.value.into()),
-                            });
-                        } else {
-                            return Err(DomainError::InternalError(format!(
-                                "Attribute name {} doesn't exist in the group schema,
-                                    yet was attempted to be inserted in the database",
-                                &attribute.name
-                            )));
-                        }
-                    }
-                    if !new_group_attributes.is_empty() {
-                        model::GroupAttributes::insert_many(new_group_attributes)
-                            .exec(transaction)
-                            .await?;
-                    }
-                    Ok(group_id)
-                })
-            })
-            .await?)
+        let schema = Self::get_schema_with_transaction(transaction).await?;
+        check_required_attributes(&schema.group_attributes, &request.attributes)?;
+        let group_id = new_group.insert(transaction).await?.group_id;
+        Self::check_group_attributes(transaction, group_id, &request.attributes, &schema).await?;
+        let mut new_group_attributes = Vec::new();
+        for attribute in request.attributes {
+            if schema
+                .group_attributes
+                .get_attribute_type(&attribute.name)
+                .is_some()
+            {
+                new_group_attributes.push(model::group_attributes::ActiveModel {
+                    group_id: Set(group_id),
+                    attribute_name: Set(attribute.name),
+                    value: Set(attribute.value.into()),
+                });
+            } else {
+                return Err(DomainError::InternalError(format!(
+                    "Attribute name {} doesn't exist in the group schema,
+                        yet was attempted to be inserted in the database",
+                    &attribute.name
+                )));
+            }
+        }
+        if !new_group_attributes.is_empty() {
+            model::GroupAttributes::insert_many(new_group_attributes)
+                .exec(transaction)
+                .await?;
+        }
+        Ok(group_id)
     }
 
-    #[instrument(skip(self), level = "debug", err)]
-    async fn delete_group(&self, group_id: GroupId) -> Result<()> {
+    /// Checks the new attributes of a group against the rules of the schema, including that no
+    /// other group already has the value of a unique attribute.
+    async fn check_group_attributes(
+        transaction: &DatabaseTransaction,
+        group_id: GroupId,
+        attributes: &[Attribute],
+        schema: &Schema,
+    ) -> Result<()> {
+        check_attribute_values(&schema.group_attributes, attributes)?;
+        for attribute in get_unique_attributes(&schema.group_attributes, attributes) {
+            let is_taken = model::GroupAttributes::find()
+                .filter(model::GroupAttributesColumn::AttributeName.eq(attribute.name.clone()))
+                .filter(
+                    model::GroupAttributesColumn::Value
+                        .eq(Serialized::from(attribute.value.clone())),
+                )
+                .filter(model::GroupAttributesColumn::GroupId.ne(group_id))
+                .count(transaction)
+                .await?
+                > 0;
+            if is_taken {
+                return Err(DomainError::InternalError(format!(
+                    "Another group already has this value for the attribute {}",
+                    attribute.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn get_group_id_by_name(
+        transaction: &DatabaseTransaction,
+        name: &GroupName,
+    ) -> Result<GroupId> {
+        model::Group::find()
+            .select_only()
+            .column(GroupColumn::GroupId)
+            .filter(GroupColumn::LowercaseDisplayName.eq(name.as_str().to_lowercase()))
+            .into_tuple()
+            .one(transaction)
+            .await?
+            .ok_or_else(|| DomainError::EntityNotFound(format!("No such group: '{name}'")))
+    }
+
+    pub(crate) async fn delete_group_with_transaction(
+        transaction: &DatabaseTransaction,
+        group_id: GroupId,
+    ) -> Result<()> {
         let res = model::Group::delete_by_id(group_id)
-            .exec(&self.sql_pool)
+            .exec(transaction)
             .await?;
         if res.rows_affected == 0 {
             return Err(DomainError::EntityNotFound(format!(
@@ -258,10 +642,8 @@ impl GroupBackendHandler for SqlBackendHandler {
         }
         Ok(())
     }
-}
 
-impl SqlBackendHandler {
-    async fn update_group_with_transaction(
+    pub(crate) async fn update_group_with_transaction(
         request: UpdateGroupRequest,
         transaction: &DatabaseTransaction,
     ) -> Result<()> {
@@ -281,6 +663,18 @@ impl SqlBackendHandler {
         let mut update_group_attributes = Vec::new();
         let mut remove_group_attributes = Vec::new();
         let schema = Self::get_schema_with_transaction(transaction).await?;
+        check_removed_attributes(
+            &schema.group_attributes,
+            &request.delete_attributes,
+            &request.insert_attributes,
+        )?;
+        Self::check_group_attributes(
+            transaction,
+            request.group_id,
+            &request.insert_attributes,
+            &schema,
+        )
+        .await?;
         for attribute in request.insert_attributes {
             if schema
                 .group_attributes
@@ -344,7 +738,10 @@ mod tests {
         requests::CreateAttributeRequest,
         types::{Attribute, AttributeType, GroupName, UserId},
     };
-    use lldap_domain_handlers::handler::{SchemaBackendHandler, SubStringFilter};
+    use lldap_domain_handlers::handler::{
+        ComparisonOperator, SchemaBackendHandler, SubStringFilter, UserBackendHandler,
+        UserRequestFilter,
+    };
     use pretty_assertions::assert_eq;
 
     async fn get_group_ids(
@@ -386,6 +783,122 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_list_groups_page() {
+        let fixture = TestFixture::new().await;
+        let get_page = async |after: Option<GroupId>| {
+            fixture
+                .handler
+                .list_groups_page(
+                    None,
+                    GroupPageRequest {
+                        order_by: GroupOrderField::DisplayName,
+                        descending: false,
+                        after,
+                        first: 2,
+                    },
+                )
+                .await
+                .unwrap()
+        };
+        let page = get_page(None).await;
+        assert_eq!(
+            page.items
+                .iter()
+                .map(|g| g.display_name.clone())
+                .collect::<Vec<_>>(),
+            vec!["Best Group".into(), "Empty Group".into()]
+        );
+        assert!(page.has_next_page);
+        assert_eq!(page.total_count, 3);
+        let page = get_page(Some(page.items[1].id)).await;
+        assert_eq!(
+            page.items
+                .iter()
+                .map(|g| g.display_name.clone())
+                .collect::<Vec<_>>(),
+            vec!["Worst Group".into()]
+        );
+        assert!(!page.has_next_page);
+    }
+
+    #[tokio::test]
+    async fn test_list_groups_page_unknown_cursor() {
+        let fixture = TestFixture::new().await;
+        let result = fixture
+            .handler
+            .list_groups_page(
+                None,
+                GroupPageRequest {
+                    order_by: GroupOrderField::DisplayName,
+                    descending: false,
+                    after: Some(GroupId(42)),
+                    first: 2,
+                },
+            )
+            .await;
+        assert!(matches!(result, Err(DomainError::ValidationError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_list_groups_page_with_filter() {
+        let fixture = TestFixture::new().await;
+        let page = fixture
+            .handler
+            .list_groups_page(
+                Some(GroupRequestFilter::Member(UserId::new("patrick"))),
+                GroupPageRequest {
+                    order_by: GroupOrderField::GroupId,
+                    descending: true,
+                    after: None,
+                    first: 10,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            page.items.iter().map(|g| g.id).collect::<Vec<_>>(),
+            vec![fixture.groups[1], fixture.groups[0]]
+        );
+        assert!(!page.has_next_page);
+        assert_eq!(page.total_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_list_groups_comparison_filter() {
+        let fixture = TestFixture::new().await;
+        let now = chrono::Utc::now().naive_utc();
+        assert_eq!(
+            get_group_ids(
+                &fixture.handler,
+                Some(GroupRequestFilter::And(vec![
+                    GroupRequestFilter::GroupIdComparison(
+                        ComparisonOperator::GreaterThan,
+                        fixture.groups[0],
+                    ),
+                    GroupRequestFilter::CreationDateComparison(
+                        ComparisonOperator::LessOrEqual,
+                        now
+                    ),
+                ]))
+            )
+            .await,
+            // Sorted by display name.
+            vec![fixture.groups[2], fixture.groups[1]]
+        );
+        assert_eq!(
+            get_group_ids(
+                &fixture.handler,
+                Some(GroupRequestFilter::ModifiedDateComparison(
+                    ComparisonOperator::GreaterThan,
+                    now
+                ))
+            )
+            .await,
+            Vec::<GroupId>::new()
+        );
+    }
+
     #[tokio::test]
     async fn test_list_groups_simple_filter() {
         let fixture = TestFixture::new().await;
@@ -462,6 +975,7 @@ mod tests {
                 is_list: false,
                 is_visible: true,
                 is_editable: true,
+                validation: Default::default(),
             })
             .await
             .unwrap();
@@ -565,6 +1079,7 @@ mod tests {
                 is_list: false,
                 is_visible: true,
                 is_editable: true,
+                validation: Default::default(),
             })
             .await
             .unwrap();
@@ -605,6 +1120,7 @@ mod tests {
                 is_list: false,
                 is_visible: true,
                 is_editable: true,
+                validation: Default::default(),
             })
             .await
             .unwrap();
@@ -659,4 +1175,162 @@ mod tests {
             .await
             .unwrap_err();
     }
+
+    #[tokio::test]
+    async fn test_group_owners() {
+        let fixture = TestFixture::new().await;
+        let handler = &fixture.handler;
+        handler
+            .add_group_owner(fixture.groups[1], GroupOwner::User(UserId::new("bob")))
+            .await
+            .unwrap();
+        handler
+            .add_group_owner(fixture.groups[2], GroupOwner::Group("best group".into()))
+            .await
+            .unwrap();
+        handler
+            .add_group_owner(fixture.groups[2], GroupOwner::Group("No Group".into()))
+            .await
+            .unwrap_err();
+        let owners = handler
+            .list_groups(None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|g| (g.display_name, g.owners))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            owners,
+            vec![
+                ("Best Group".into(), vec![]),
+                (
+                    "Empty Group".into(),
+                    vec![GroupOwner::Group("Best Group".into())]
+                ),
+                (
+                    "Worst Group".into(),
+                    vec![GroupOwner::User(UserId::new("bob"))]
+                ),
+            ]
+        );
+        assert_eq!(
+            get_group_names(
+                handler,
+                Some(GroupRequestFilter::OwnedBy(UserId::new("bob")))
+            )
+            .await,
+            vec!["Empty Group".into(), "Worst Group".into()]
+        );
+        assert_eq!(
+            get_group_names(
+                handler,
+                Some(GroupRequestFilter::OwnedBy(UserId::new("patrick")))
+            )
+            .await,
+            vec!["Empty Group".into()]
+        );
+        assert_eq!(
+            get_group_names(
+                handler,
+                Some(GroupRequestFilter::OwnedBy(UserId::new("John")))
+            )
+            .await,
+            Vec::<GroupName>::new()
+        );
+        handler
+            .remove_group_owner(fixture.groups[1], GroupOwner::User(UserId::new("bob")))
+            .await
+            .unwrap();
+        assert!(matches!(
+            handler
+                .remove_group_owner(fixture.groups[1], GroupOwner::User(UserId::new("bob")))
+                .await,
+            Err(DomainError::EntityNotFound(_))
+        ));
+        assert_eq!(
+            get_group_names(
+                handler,
+                Some(GroupRequestFilter::OwnedBy(UserId::new("bob")))
+            )
+            .await,
+            vec!["Empty Group".into()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_group() {
+        let fixture = TestFixture::new().await;
+        let handler = &fixture.handler;
+        let user_filter = UserRequestFilter::Or(vec![
+            UserRequestFilter::UserId(UserId::new("bob")),
+            UserRequestFilter::UserId(UserId::new("John")),
+        ]);
+        let dynamic_group = handler
+            .create_dynamic_group(
+                CreateGroupRequest {
+                    display_name: "Dynamic Group".into(),
+                    ..Default::default()
+                },
+                DynamicGroupFilter {
+                    ldap_filter: "(|(uid=bob)(uid=john))".to_owned(),
+                    user_filter,
+                },
+            )
+            .await
+            .unwrap();
+        let group = handler
+            .list_groups(Some(GroupRequestFilter::GroupId(dynamic_group)))
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+        assert_eq!(group.users, vec![UserId::new("bob"), UserId::new("John")]);
+        assert_eq!(
+            group.dynamic_filter.as_deref(),
+            Some("(|(uid=bob)(uid=john))")
+        );
+        assert_eq!(
+            get_group_names(
+                handler,
+                Some(GroupRequestFilter::Member(UserId::new("John")))
+            )
+            .await,
+            vec!["Dynamic Group".into(), "Worst Group".into()]
+        );
+        assert_eq!(
+            get_user_names(
+                handler,
+                Some(UserRequestFilter::MemberOf("dynamic group".into()))
+            )
+            .await,
+            vec!["bob", "john"]
+        );
+        assert_eq!(
+            get_user_names(
+                handler,
+                Some(UserRequestFilter::Not(Box::new(
+                    UserRequestFilter::MemberOfId(dynamic_group)
+                )))
+            )
+            .await,
+            vec!["nogroup", "patrick"]
+        );
+        assert!(
+            UserBackendHandler::get_user_groups(handler, &UserId::new("bob"))
+                .await
+                .unwrap()
+                .iter()
+                .any(|g| g.group_id == dynamic_group)
+        );
+        assert!(matches!(
+            handler
+                .add_user_to_group(&UserId::new("patrick"), dynamic_group)
+                .await,
+            Err(DomainError::ValidationError(_))
+        ));
+        handler
+            .add_group_owner(fixture.groups[2], GroupOwner::Group("Dynamic Group".into()))
+            .await
+            .unwrap_err();
+    }
 }
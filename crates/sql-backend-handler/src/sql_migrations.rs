@@ -29,6 +29,7 @@ pub enum Users {
     Uuid,
     ModifiedDate,
     PasswordModifiedDate,
+    EmailVerified,
 }
 
 #[derive(DeriveIden, PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Copy)]
@@ -40,6 +41,8 @@ pub(crate) enum Groups {
     CreationDate,
     Uuid,
     ModifiedDate,
+    DynamicFilter,
+    DynamicUserFilter,
 }
 
 #[derive(DeriveIden, Clone, Copy)]
@@ -59,6 +62,14 @@ pub(crate) enum UserAttributeSchema {
     UserAttributeSchemaIsUserVisible,
     UserAttributeSchemaIsUserEditable,
     UserAttributeSchemaIsHardcoded,
+    UserAttributeSchemaIsPrivate,
+    UserAttributeSchemaRegex,
+    UserAttributeSchemaMinLength,
+    UserAttributeSchemaMaxLength,
+    UserAttributeSchemaMinValue,
+    UserAttributeSchemaMaxValue,
+    UserAttributeSchemaIsUnique,
+    UserAttributeSchemaIsRequired,
 }
 
 #[derive(DeriveIden, PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Copy)]
@@ -79,6 +90,14 @@ pub(crate) enum GroupAttributeSchema {
     GroupAttributeSchemaIsGroupVisible,
     GroupAttributeSchemaIsGroupEditable,
     GroupAttributeSchemaIsHardcoded,
+    GroupAttributeSchemaIsPrivate,
+    GroupAttributeSchemaRegex,
+    GroupAttributeSchemaMinLength,
+    GroupAttributeSchemaMaxLength,
+    GroupAttributeSchemaMinValue,
+    GroupAttributeSchemaMaxValue,
+    GroupAttributeSchemaIsUnique,
+    GroupAttributeSchemaIsRequired,
 }
 
 #[derive(DeriveIden, PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Copy)]
@@ -103,6 +122,64 @@ pub(crate) enum GroupObjectClasses {
     ObjectClass,
 }
 
+#[derive(DeriveIden, Clone, Copy)]
+pub(crate) enum GroupOwnerUsers {
+    Table,
+    GroupId,
+    UserId,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+pub(crate) enum GroupOwnerGroups {
+    Table,
+    GroupId,
+    OwnerGroupId,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+pub(crate) enum UserAttributePermissions {
+    Table,
+    AttributeName,
+    GroupId,
+    CanEdit,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+pub(crate) enum GroupAttributePermissions {
+    Table,
+    AttributeName,
+    GroupId,
+    CanEdit,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+pub(crate) enum UserAttributeAllowedValues {
+    Table,
+    AttributeName,
+    Value,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+pub(crate) enum GroupAttributeAllowedValues {
+    Table,
+    AttributeName,
+    Value,
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+pub(crate) enum AuditLog {
+    Table,
+    Id,
+    Timestamp,
+    Actor,
+    Action,
+    Target,
+    Details,
+    SourceIp,
+    Interface,
+    Success,
+}
+
 // Metadata about the SQL DB.
 #[derive(DeriveIden)]
 pub(crate) enum Metadata {
@@ -119,7 +196,7 @@ pub(crate) struct JustSchemaVersion {
 }
 
 #[instrument(skip_all, level = "debug", ret)]
-pub(crate) async fn get_schema_version(pool: &DbConnection) -> Option<SchemaVersion> {
+pub async fn get_schema_version(pool: &DbConnection) -> Option<SchemaVersion> {
     JustSchemaVersion::find_by_statement(
         pool.get_database_backend().build(
             Query::select()
@@ -1162,6 +1239,495 @@ async fn migrate_to_v11(transaction: DatabaseTransaction) -> Result<DatabaseTran
     Ok(transaction)
 }
 
+async fn migrate_to_v12(transaction: DatabaseTransaction) -> Result<DatabaseTransaction, DbErr> {
+    let builder = transaction.get_database_backend();
+    // Add email_verified to users table
+    transaction
+        .execute(
+            builder.build(
+                Table::alter().table(Users::Table).add_column(
+                    ColumnDef::new(Users::EmailVerified)
+                        .boolean()
+                        .not_null()
+                        .default(false),
+                ),
+            ),
+        )
+        .await?;
+    // Existing emails were trusted until now: keep password resets working for them.
+    transaction
+        .execute(
+            builder.build(
+                Query::update()
+                    .table(Users::Table)
+                    .value(Users::EmailVerified, true),
+            ),
+        )
+        .await?;
+    Ok(transaction)
+}
+
+async fn migrate_to_v13(transaction: DatabaseTransaction) -> Result<DatabaseTransaction, DbErr> {
+    let builder = transaction.get_database_backend();
+    transaction
+        .execute(
+            builder.build(
+                Table::create()
+                    .table(AuditLog::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AuditLog::Id)
+                            .big_integer()
+                            .auto_increment()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(AuditLog::Timestamp).date_time().not_null())
+                    .col(ColumnDef::new(AuditLog::Actor).string_len(255))
+                    .col(ColumnDef::new(AuditLog::Action).string_len(64).not_null())
+                    .col(ColumnDef::new(AuditLog::Target).string_len(255))
+                    .col(ColumnDef::new(AuditLog::Details).text())
+                    .col(ColumnDef::new(AuditLog::SourceIp).string_len(64))
+                    .col(
+                        ColumnDef::new(AuditLog::Interface)
+                            .string_len(16)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(AuditLog::Success).boolean().not_null()),
+            ),
+        )
+        .await?;
+    // For the retention cleanup and to list the most recent events.
+    transaction
+        .execute(
+            builder.build(
+                Index::create()
+                    .if_not_exists()
+                    .name("audit-log-timestamp")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::Timestamp),
+            ),
+        )
+        .await?;
+    Ok(transaction)
+}
+
+async fn migrate_to_v14(transaction: DatabaseTransaction) -> Result<DatabaseTransaction, DbErr> {
+    let builder = transaction.get_database_backend();
+    transaction
+        .execute(
+            builder.build(
+                Table::create()
+                    .table(GroupOwnerUsers::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(GroupOwnerUsers::GroupId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(GroupOwnerUsers::UserId)
+                            .string_len(255)
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("GroupOwnerUsersGroupForeignKey")
+                            .from(GroupOwnerUsers::Table, GroupOwnerUsers::GroupId)
+                            .to(Groups::Table, Groups::GroupId)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("GroupOwnerUsersUserForeignKey")
+                            .from(GroupOwnerUsers::Table, GroupOwnerUsers::UserId)
+                            .to(Users::Table, Users::UserId)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(GroupOwnerUsers::GroupId)
+                            .col(GroupOwnerUsers::UserId),
+                    ),
+            ),
+        )
+        .await?;
+    transaction
+        .execute(
+            builder.build(
+                Table::create()
+                    .table(GroupOwnerGroups::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(GroupOwnerGroups::GroupId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(GroupOwnerGroups::OwnerGroupId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("GroupOwnerGroupsGroupForeignKey")
+                            .from(GroupOwnerGroups::Table, GroupOwnerGroups::GroupId)
+                            .to(Groups::Table, Groups::GroupId)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("GroupOwnerGroupsOwnerForeignKey")
+                            .from(GroupOwnerGroups::Table, GroupOwnerGroups::OwnerGroupId)
+                            .to(Groups::Table, Groups::GroupId)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(GroupOwnerGroups::GroupId)
+                            .col(GroupOwnerGroups::OwnerGroupId),
+                    ),
+            ),
+        )
+        .await?;
+    Ok(transaction)
+}
+
+async fn migrate_to_v15(transaction: DatabaseTransaction) -> Result<DatabaseTransaction, DbErr> {
+    let builder = transaction.get_database_backend();
+    // The filter of a dynamic group, as written by the admin and as a serialized request.
+    transaction
+        .execute(
+            builder.build(
+                Table::alter()
+                    .table(Groups::Table)
+                    .add_column(ColumnDef::new(Groups::DynamicFilter).text().null()),
+            ),
+        )
+        .await?;
+    transaction
+        .execute(
+            builder.build(
+                Table::alter()
+                    .table(Groups::Table)
+                    .add_column(ColumnDef::new(Groups::DynamicUserFilter).text().null()),
+            ),
+        )
+        .await?;
+    Ok(transaction)
+}
+
+async fn migrate_to_v16(transaction: DatabaseTransaction) -> Result<DatabaseTransaction, DbErr> {
+    let builder = transaction.get_database_backend();
+    transaction
+        .execute(
+            builder.build(
+                Table::alter().table(UserAttributeSchema::Table).add_column(
+                    ColumnDef::new(UserAttributeSchema::UserAttributeSchemaIsPrivate)
+                        .boolean()
+                        .not_null()
+                        .default(false),
+                ),
+            ),
+        )
+        .await?;
+    transaction
+        .execute(
+            builder.build(
+                Table::alter()
+                    .table(GroupAttributeSchema::Table)
+                    .add_column(
+                        ColumnDef::new(GroupAttributeSchema::GroupAttributeSchemaIsPrivate)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    ),
+            ),
+        )
+        .await?;
+    // The groups whose members can read, or also edit, an attribute.
+    transaction
+        .execute(
+            builder.build(
+                Table::create()
+                    .table(UserAttributePermissions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UserAttributePermissions::AttributeName)
+                            .string_len(64)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UserAttributePermissions::GroupId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UserAttributePermissions::CanEdit)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("UserAttributePermissionsAttributeForeignKey")
+                            .from(
+                                UserAttributePermissions::Table,
+                                UserAttributePermissions::AttributeName,
+                            )
+                            .to(
+                                UserAttributeSchema::Table,
+                                UserAttributeSchema::UserAttributeSchemaName,
+                            )
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("UserAttributePermissionsGroupForeignKey")
+                            .from(
+                                UserAttributePermissions::Table,
+                                UserAttributePermissions::GroupId,
+                            )
+                            .to(Groups::Table, Groups::GroupId)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(UserAttributePermissions::AttributeName)
+                            .col(UserAttributePermissions::GroupId),
+                    ),
+            ),
+        )
+        .await?;
+    transaction
+        .execute(
+            builder.build(
+                Table::create()
+                    .table(GroupAttributePermissions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(GroupAttributePermissions::AttributeName)
+                            .string_len(64)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(GroupAttributePermissions::GroupId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(GroupAttributePermissions::CanEdit)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("GroupAttributePermissionsAttributeForeignKey")
+                            .from(
+                                GroupAttributePermissions::Table,
+                                GroupAttributePermissions::AttributeName,
+                            )
+                            .to(
+                                GroupAttributeSchema::Table,
+                                GroupAttributeSchema::GroupAttributeSchemaName,
+                            )
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("GroupAttributePermissionsGroupForeignKey")
+                            .from(
+                                GroupAttributePermissions::Table,
+                                GroupAttributePermissions::GroupId,
+                            )
+                            .to(Groups::Table, Groups::GroupId)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(GroupAttributePermissions::AttributeName)
+                            .col(GroupAttributePermissions::GroupId),
+                    ),
+            ),
+        )
+        .await?;
+    Ok(transaction)
+}
+
+async fn migrate_to_v17(transaction: DatabaseTransaction) -> Result<DatabaseTransaction, DbErr> {
+    let builder = transaction.get_database_backend();
+    // The rules on the values of the attributes, one column at a time for SQLite.
+    for column in [
+        ColumnDef::new(UserAttributeSchema::UserAttributeSchemaRegex)
+            .text()
+            .null()
+            .to_owned(),
+        ColumnDef::new(UserAttributeSchema::UserAttributeSchemaMinLength)
+            .big_integer()
+            .null()
+            .to_owned(),
+        ColumnDef::new(UserAttributeSchema::UserAttributeSchemaMaxLength)
+            .big_integer()
+            .null()
+            .to_owned(),
+        ColumnDef::new(UserAttributeSchema::UserAttributeSchemaMinValue)
+            .big_integer()
+            .null()
+            .to_owned(),
+        ColumnDef::new(UserAttributeSchema::UserAttributeSchemaMaxValue)
+            .big_integer()
+            .null()
+            .to_owned(),
+        ColumnDef::new(UserAttributeSchema::UserAttributeSchemaIsUnique)
+            .boolean()
+            .not_null()
+            .default(false)
+            .to_owned(),
+        ColumnDef::new(UserAttributeSchema::UserAttributeSchemaIsRequired)
+            .boolean()
+            .not_null()
+            .default(false)
+            .to_owned(),
+    ] {
+        transaction
+            .execute(
+                builder.build(
+                    Table::alter()
+                        .table(UserAttributeSchema::Table)
+                        .add_column(column),
+                ),
+            )
+            .await?;
+    }
+    for column in [
+        ColumnDef::new(GroupAttributeSchema::GroupAttributeSchemaRegex)
+            .text()
+            .null()
+            .to_owned(),
+        ColumnDef::new(GroupAttributeSchema::GroupAttributeSchemaMinLength)
+            .big_integer()
+            .null()
+            .to_owned(),
+        ColumnDef::new(GroupAttributeSchema::GroupAttributeSchemaMaxLength)
+            .big_integer()
+            .null()
+            .to_owned(),
+        ColumnDef::new(GroupAttributeSchema::GroupAttributeSchemaMinValue)
+            .big_integer()
+            .null()
+            .to_owned(),
+        ColumnDef::new(GroupAttributeSchema::GroupAttributeSchemaMaxValue)
+            .big_integer()
+            .null()
+            .to_owned(),
+        ColumnDef::new(GroupAttributeSchema::GroupAttributeSchemaIsUnique)
+            .boolean()
+            .not_null()
+            .default(false)
+            .to_owned(),
+        ColumnDef::new(GroupAttributeSchema::GroupAttributeSchemaIsRequired)
+            .boolean()
+            .not_null()
+            .default(false)
+            .to_owned(),
+    ] {
+        transaction
+            .execute(
+                builder.build(
+                    Table::alter()
+                        .table(GroupAttributeSchema::Table)
+                        .add_column(column),
+                ),
+            )
+            .await?;
+    }
+    // The values an attribute is restricted to, if any.
+    transaction
+        .execute(
+            builder.build(
+                Table::create()
+                    .table(UserAttributeAllowedValues::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UserAttributeAllowedValues::AttributeName)
+                            .string_len(64)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UserAttributeAllowedValues::Value)
+                            .string_len(255)
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("UserAttributeAllowedValuesForeignKey")
+                            .from(
+                                UserAttributeAllowedValues::Table,
+                                UserAttributeAllowedValues::AttributeName,
+                            )
+                            .to(
+                                UserAttributeSchema::Table,
+                                UserAttributeSchema::UserAttributeSchemaName,
+                            )
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(UserAttributeAllowedValues::AttributeName)
+                            .col(UserAttributeAllowedValues::Value),
+                    ),
+            ),
+        )
+        .await?;
+    transaction
+        .execute(
+            builder.build(
+                Table::create()
+                    .table(GroupAttributeAllowedValues::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(GroupAttributeAllowedValues::AttributeName)
+                            .string_len(64)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(GroupAttributeAllowedValues::Value)
+                            .string_len(255)
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("GroupAttributeAllowedValuesForeignKey")
+                            .from(
+                                GroupAttributeAllowedValues::Table,
+                                GroupAttributeAllowedValues::AttributeName,
+                            )
+                            .to(
+                                GroupAttributeSchema::Table,
+                                GroupAttributeSchema::GroupAttributeSchemaName,
+                            )
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(GroupAttributeAllowedValues::AttributeName)
+                            .col(GroupAttributeAllowedValues::Value),
+                    ),
+            ),
+        )
+        .await?;
+    Ok(transaction)
+}
+
 // This is needed to make an array of async functions.
 macro_rules! to_sync {
     ($l:ident) => {
@@ -1193,6 +1759,12 @@ pub(crate) async fn migrate_from_version(
         to_sync!(migrate_to_v9),
         to_sync!(migrate_to_v10),
         to_sync!(migrate_to_v11),
+        to_sync!(migrate_to_v12),
+        to_sync!(migrate_to_v13),
+        to_sync!(migrate_to_v14),
+        to_sync!(migrate_to_v15),
+        to_sync!(migrate_to_v16),
+        to_sync!(migrate_to_v17),
     ];
     assert_eq!(migrations.len(), (LAST_SCHEMA_VERSION.0 - 1) as usize);
     for migration in 2..=last_version.0 {
@@ -50,7 +50,7 @@ impl SqlBackendHandler {
     }
 
     #[instrument(skip(self), level = "debug", err)]
-    async fn get_password_file_for_user(&self, user_id: UserId) -> Result<Option<Vec<u8>>> {
+    pub async fn get_password_file_for_user(&self, user_id: UserId) -> Result<Option<Vec<u8>>> {
         // Fetch the previously registered password file from the DB.
         Ok(model::User::find_by_id(user_id)
             .select_only()
@@ -60,6 +60,16 @@ impl SqlBackendHandler {
             .await?
             .and_then(|u| u.0))
     }
+
+    /// Whether the password is the current one of the user. False if the user has no password.
+    pub async fn password_matches(&self, user_id: &UserId, password: &str) -> Result<bool> {
+        Ok(self
+            .get_password_file_for_user(user_id.clone())
+            .await?
+            .is_some_and(|password_file| {
+                passwords_match(&password_file, password, &self.opaque_setup, user_id).is_ok()
+            }))
+    }
 }
 
 #[async_trait]
@@ -187,7 +197,7 @@ impl OpaqueHandler for SqlOpaqueHandler {
     async fn registration_finish(
         &self,
         request: registration::ClientRegistrationFinishRequest,
-    ) -> Result<()> {
+    ) -> Result<UserId> {
         let secret_key = self.get_orion_secret_key()?;
         let registration::ServerData { username } = bincode::deserialize(&orion::aead::open(
             &secret_key,
@@ -207,7 +217,7 @@ impl OpaqueHandler for SqlOpaqueHandler {
         };
         user_update.update(&self.sql_pool).await?;
         info!(r#"Successfully (re)set password for "{}""#, &username);
-        Ok(())
+        Ok(username)
     }
 }
 
@@ -238,7 +248,32 @@ pub async fn register_password(
             server_data: start_response.server_data,
             registration_upload: registration_finish.message,
         })
-        .await
+        .await?;
+    Ok(())
+}
+
+/// Sets a user's password from a password file (the serialized OPAQUE registration), as stored
+/// in the database. It only works with the private key the password was registered with.
+#[instrument(skip_all, level = "debug", err, fields(username = %username.as_str()))]
+pub async fn set_password_file(
+    opaque_handler: &SqlOpaqueHandler,
+    username: UserId,
+    password_file: &[u8],
+) -> Result<()> {
+    opaque::server::ServerRegistration::deserialize(password_file)
+        .map_err(opaque::AuthenticationError::ProtocolError)?;
+    let now = chrono::Utc::now().naive_utc();
+    model::users::ActiveModel {
+        user_id: ActiveValue::Set(username.clone()),
+        password_hash: ActiveValue::Set(Some(password_file.to_vec())),
+        password_modified_date: ActiveValue::Set(now),
+        modified_date: ActiveValue::Set(now),
+        ..Default::default()
+    }
+    .update(&opaque_handler.sql_pool)
+    .await?;
+    info!(r#"Successfully set the password file of "{}""#, &username);
+    Ok(())
 }
 
 #[cfg(test)]
@@ -343,4 +378,38 @@ mod tests {
             .await
             .unwrap_err();
     }
+
+    #[tokio::test]
+    async fn test_set_password_file() {
+        let sql_pool = get_initialized_db().await;
+        let handler = SqlBackendHandler::new(generate_random_private_key(), sql_pool.clone());
+        insert_user(&handler, "bob", "bob00").await;
+        let password_file = handler
+            .get_password_file_for_user(UserId::new("bob"))
+            .await
+            .unwrap()
+            .unwrap();
+        register_password(&handler, UserId::new("bob"), &SecUtf8::from("bob01"))
+            .await
+            .unwrap();
+
+        set_password_file(&handler, UserId::new("bob"), &[1, 2, 3])
+            .await
+            .unwrap_err();
+        set_password_file(&handler, UserId::new("bob"), &password_file)
+            .await
+            .unwrap();
+        assert!(
+            handler
+                .password_matches(&UserId::new("bob"), "bob00")
+                .await
+                .unwrap()
+        );
+        assert!(
+            !handler
+                .password_matches(&UserId::new("bob"), "bob01")
+                .await
+                .unwrap()
+        );
+    }
 }
@@ -2,17 +2,22 @@ use crate::sql_backend_handler::SqlBackendHandler;
 use async_trait::async_trait;
 use lldap_domain::{
     requests::CreateAttributeRequest,
-    schema::{AttributeList, AttributeSchema, Schema},
-    types::{AttributeName, LdapObjectClass},
+    schema::{AttributeList, AttributePermissions, AttributeSchema, Schema},
+    types::{Attribute, AttributeName, GroupId, GroupName, LdapObjectClass},
+};
+use lldap_domain_handlers::{
+    events::{DirectoryEvent, SchemaObjectType},
+    handler::{ReadSchemaBackendHandler, SchemaBackendHandler},
 };
-use lldap_domain_handlers::handler::{ReadSchemaBackendHandler, SchemaBackendHandler};
 use lldap_domain_model::{
     error::{DomainError, Result},
     model,
 };
 use sea_orm::{
-    ActiveModelTrait, DatabaseTransaction, EntityTrait, QueryOrder, Set, TransactionTrait,
+    ActiveModelTrait, ColumnTrait, DatabaseTransaction, EntityTrait, JoinType, QueryFilter,
+    QueryOrder, QuerySelect, RelationTrait, Set, TransactionTrait,
 };
+use std::collections::{BTreeMap, BTreeSet};
 
 #[async_trait]
 impl ReadSchemaBackendHandler for SqlBackendHandler {
@@ -29,6 +34,8 @@ impl ReadSchemaBackendHandler for SqlBackendHandler {
 #[async_trait]
 impl SchemaBackendHandler for SqlBackendHandler {
     async fn add_user_attribute(&self, request: CreateAttributeRequest) -> Result<()> {
+        let name = request.name.clone();
+        let validation = request.validation;
         let new_attribute = model::user_attribute_schema::ActiveModel {
             attribute_name: Set(request.name),
             attribute_type: Set(request.attribute_type),
@@ -36,12 +43,49 @@ impl SchemaBackendHandler for SqlBackendHandler {
             is_user_visible: Set(request.is_visible),
             is_user_editable: Set(request.is_editable),
             is_hardcoded: Set(false),
+            is_private: Set(false),
+            regex: Set(validation.regex),
+            min_length: Set(validation.min_length),
+            max_length: Set(validation.max_length),
+            min_value: Set(validation.min_value),
+            max_value: Set(validation.max_value),
+            is_unique: Set(validation.is_unique),
+            is_required: Set(validation.is_required),
         };
-        new_attribute.insert(&self.sql_pool).await?;
+        let allowed_values = validation
+            .allowed_values
+            .into_iter()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|value| model::user_attribute_allowed_values::ActiveModel {
+                attribute_name: Set(name.clone()),
+                value: Set(value),
+            })
+            .collect::<Vec<_>>();
+        self.sql_pool
+            .transaction::<_, (), DomainError>(|transaction| {
+                Box::pin(async move {
+                    new_attribute.insert(transaction).await?;
+                    if !allowed_values.is_empty() {
+                        model::UserAttributeAllowedValues::insert_many(allowed_values)
+                            .exec(transaction)
+                            .await?;
+                    }
+                    Ok(())
+                })
+            })
+            .await?;
+        self.emit_event(DirectoryEvent::AttributeAdded {
+            object_type: SchemaObjectType::User,
+            name,
+        })
+        .await;
         Ok(())
     }
 
     async fn add_group_attribute(&self, request: CreateAttributeRequest) -> Result<()> {
+        let name = request.name.clone();
+        let validation = request.validation;
         let new_attribute = model::group_attribute_schema::ActiveModel {
             attribute_name: Set(request.name),
             attribute_type: Set(request.attribute_type),
@@ -49,22 +93,190 @@ impl SchemaBackendHandler for SqlBackendHandler {
             is_group_visible: Set(request.is_visible),
             is_group_editable: Set(request.is_editable),
             is_hardcoded: Set(false),
+            is_private: Set(false),
+            regex: Set(validation.regex),
+            min_length: Set(validation.min_length),
+            max_length: Set(validation.max_length),
+            min_value: Set(validation.min_value),
+            max_value: Set(validation.max_value),
+            is_unique: Set(validation.is_unique),
+            is_required: Set(validation.is_required),
         };
-        new_attribute.insert(&self.sql_pool).await?;
+        let allowed_values = validation
+            .allowed_values
+            .into_iter()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|value| model::group_attribute_allowed_values::ActiveModel {
+                attribute_name: Set(name.clone()),
+                value: Set(value),
+            })
+            .collect::<Vec<_>>();
+        self.sql_pool
+            .transaction::<_, (), DomainError>(|transaction| {
+                Box::pin(async move {
+                    new_attribute.insert(transaction).await?;
+                    if !allowed_values.is_empty() {
+                        model::GroupAttributeAllowedValues::insert_many(allowed_values)
+                            .exec(transaction)
+                            .await?;
+                    }
+                    Ok(())
+                })
+            })
+            .await?;
+        self.emit_event(DirectoryEvent::AttributeAdded {
+            object_type: SchemaObjectType::Group,
+            name,
+        })
+        .await;
         Ok(())
     }
 
     async fn delete_user_attribute(&self, name: &AttributeName) -> Result<()> {
-        model::UserAttributeSchema::delete_by_id(name.clone())
-            .exec(&self.sql_pool)
+        let attribute_name = name.clone();
+        let rows_affected = self
+            .sql_pool
+            .transaction::<_, u64, DomainError>(|transaction| {
+                Box::pin(async move {
+                    Self::check_attribute_not_in_dynamic_filters(transaction, &attribute_name)
+                        .await?;
+                    Ok(model::UserAttributeSchema::delete_by_id(attribute_name)
+                        .exec(transaction)
+                        .await?
+                        .rows_affected)
+                })
+            })
             .await?;
+        if rows_affected > 0 {
+            self.emit_event(DirectoryEvent::AttributeDeleted {
+                object_type: SchemaObjectType::User,
+                name: name.clone(),
+            })
+            .await;
+        }
         Ok(())
     }
 
     async fn delete_group_attribute(&self, name: &AttributeName) -> Result<()> {
-        model::GroupAttributeSchema::delete_by_id(name.clone())
+        let res = model::GroupAttributeSchema::delete_by_id(name.clone())
             .exec(&self.sql_pool)
             .await?;
+        if res.rows_affected > 0 {
+            self.emit_event(DirectoryEvent::AttributeDeleted {
+                object_type: SchemaObjectType::Group,
+                name: name.clone(),
+            })
+            .await;
+        }
+        Ok(())
+    }
+
+    async fn set_user_attribute_permissions(
+        &self,
+        name: &AttributeName,
+        permissions: AttributePermissions,
+    ) -> Result<()> {
+        let attribute_name = name.clone();
+        self.sql_pool
+            .transaction::<_, (), DomainError>(|transaction| {
+                Box::pin(async move {
+                    if model::UserAttributeSchema::find_by_id(attribute_name.clone())
+                        .one(transaction)
+                        .await?
+                        .is_none()
+                    {
+                        return Err(DomainError::EntityNotFound(format!(
+                            "No such user attribute: '{attribute_name}'"
+                        )));
+                    }
+                    let groups = Self::get_permission_group_ids(transaction, &permissions).await?;
+                    model::user_attribute_schema::ActiveModel {
+                        attribute_name: Set(attribute_name.clone()),
+                        is_private: Set(permissions.is_private),
+                        ..Default::default()
+                    }
+                    .update(transaction)
+                    .await?;
+                    model::UserAttributePermissions::delete_many()
+                        .filter(
+                            model::UserAttributePermissionsColumn::AttributeName
+                                .eq(attribute_name.clone()),
+                        )
+                        .exec(transaction)
+                        .await?;
+                    for (group_id, can_edit) in groups {
+                        model::user_attribute_permissions::ActiveModel {
+                            attribute_name: Set(attribute_name.clone()),
+                            group_id: Set(group_id),
+                            can_edit: Set(can_edit),
+                        }
+                        .insert(transaction)
+                        .await?;
+                    }
+                    Ok(())
+                })
+            })
+            .await?;
+        self.emit_event(DirectoryEvent::AttributeUpdated {
+            object_type: SchemaObjectType::User,
+            name: name.clone(),
+        })
+        .await;
+        Ok(())
+    }
+
+    async fn set_group_attribute_permissions(
+        &self,
+        name: &AttributeName,
+        permissions: AttributePermissions,
+    ) -> Result<()> {
+        let attribute_name = name.clone();
+        self.sql_pool
+            .transaction::<_, (), DomainError>(|transaction| {
+                Box::pin(async move {
+                    if model::GroupAttributeSchema::find_by_id(attribute_name.clone())
+                        .one(transaction)
+                        .await?
+                        .is_none()
+                    {
+                        return Err(DomainError::EntityNotFound(format!(
+                            "No such group attribute: '{attribute_name}'"
+                        )));
+                    }
+                    let groups = Self::get_permission_group_ids(transaction, &permissions).await?;
+                    model::group_attribute_schema::ActiveModel {
+                        attribute_name: Set(attribute_name.clone()),
+                        is_private: Set(permissions.is_private),
+                        ..Default::default()
+                    }
+                    .update(transaction)
+                    .await?;
+                    model::GroupAttributePermissions::delete_many()
+                        .filter(
+                            model::GroupAttributePermissionsColumn::AttributeName
+                                .eq(attribute_name.clone()),
+                        )
+                        .exec(transaction)
+                        .await?;
+                    for (group_id, can_edit) in groups {
+                        model::group_attribute_permissions::ActiveModel {
+                            attribute_name: Set(attribute_name.clone()),
+                            group_id: Set(group_id),
+                            can_edit: Set(can_edit),
+                        }
+                        .insert(transaction)
+                        .await?;
+                    }
+                    Ok(())
+                })
+            })
+            .await?;
+        self.emit_event(DirectoryEvent::AttributeUpdated {
+            object_type: SchemaObjectType::Group,
+            name: name.clone(),
+        })
+        .await;
         Ok(())
     }
 
@@ -77,6 +289,11 @@ impl SchemaBackendHandler for SqlBackendHandler {
         }
         .insert(&self.sql_pool)
         .await?;
+        self.emit_event(DirectoryEvent::ObjectClassAdded {
+            object_type: SchemaObjectType::User,
+            name: name.clone(),
+        })
+        .await;
         Ok(())
     }
 
@@ -89,20 +306,39 @@ impl SchemaBackendHandler for SqlBackendHandler {
         }
         .insert(&self.sql_pool)
         .await?;
+        self.emit_event(DirectoryEvent::ObjectClassAdded {
+            object_type: SchemaObjectType::Group,
+            name: name.clone(),
+        })
+        .await;
         Ok(())
     }
 
     async fn delete_user_object_class(&self, name: &LdapObjectClass) -> Result<()> {
-        model::UserObjectClasses::delete_by_id(name.as_str().to_ascii_lowercase())
+        let res = model::UserObjectClasses::delete_by_id(name.as_str().to_ascii_lowercase())
             .exec(&self.sql_pool)
             .await?;
+        if res.rows_affected > 0 {
+            self.emit_event(DirectoryEvent::ObjectClassDeleted {
+                object_type: SchemaObjectType::User,
+                name: name.clone(),
+            })
+            .await;
+        }
         Ok(())
     }
 
     async fn delete_group_object_class(&self, name: &LdapObjectClass) -> Result<()> {
-        model::GroupObjectClasses::delete_by_id(name.as_str().to_ascii_lowercase())
+        let res = model::GroupObjectClasses::delete_by_id(name.as_str().to_ascii_lowercase())
             .exec(&self.sql_pool)
             .await?;
+        if res.rows_affected > 0 {
+            self.emit_event(DirectoryEvent::ObjectClassDeleted {
+                object_type: SchemaObjectType::Group,
+                name: name.clone(),
+            })
+            .await;
+        }
         Ok(())
     }
 }
@@ -126,25 +362,89 @@ impl SqlBackendHandler {
     async fn get_user_attributes(
         transaction: &DatabaseTransaction,
     ) -> Result<Vec<AttributeSchema>> {
-        Ok(model::UserAttributeSchema::find()
+        let mut attributes = model::UserAttributeSchema::find()
             .order_by_asc(model::UserAttributeSchemaColumn::AttributeName)
             .all(transaction)
             .await?
             .into_iter()
             .map(|m| m.into())
-            .collect())
+            .collect::<Vec<_>>();
+        let permission_groups = model::UserAttributePermissions::find()
+            .select_only()
+            .column(model::UserAttributePermissionsColumn::AttributeName)
+            .column(model::GroupColumn::DisplayName)
+            .column(model::UserAttributePermissionsColumn::CanEdit)
+            .join(
+                JoinType::InnerJoin,
+                model::user_attribute_permissions::Relation::Groups.def(),
+            )
+            .order_by_asc(model::GroupColumn::LowercaseDisplayName)
+            .into_tuple()
+            .all(transaction)
+            .await?;
+        set_permission_groups(&mut attributes, permission_groups);
+        let allowed_values = model::UserAttributeAllowedValues::find()
+            .select_only()
+            .column(model::UserAttributeAllowedValuesColumn::AttributeName)
+            .column(model::UserAttributeAllowedValuesColumn::Value)
+            .order_by_asc(model::UserAttributeAllowedValuesColumn::Value)
+            .into_tuple()
+            .all(transaction)
+            .await?;
+        set_allowed_values(&mut attributes, allowed_values);
+        Ok(attributes)
     }
 
     async fn get_group_attributes(
         transaction: &DatabaseTransaction,
     ) -> Result<Vec<AttributeSchema>> {
-        Ok(model::GroupAttributeSchema::find()
+        let mut attributes = model::GroupAttributeSchema::find()
             .order_by_asc(model::GroupAttributeSchemaColumn::AttributeName)
             .all(transaction)
             .await?
             .into_iter()
             .map(|m| m.into())
-            .collect())
+            .collect::<Vec<_>>();
+        let permission_groups = model::GroupAttributePermissions::find()
+            .select_only()
+            .column(model::GroupAttributePermissionsColumn::AttributeName)
+            .column(model::GroupColumn::DisplayName)
+            .column(model::GroupAttributePermissionsColumn::CanEdit)
+            .join(
+                JoinType::InnerJoin,
+                model::group_attribute_permissions::Relation::Groups.def(),
+            )
+            .order_by_asc(model::GroupColumn::LowercaseDisplayName)
+            .into_tuple()
+            .all(transaction)
+            .await?;
+        set_permission_groups(&mut attributes, permission_groups);
+        let allowed_values = model::GroupAttributeAllowedValues::find()
+            .select_only()
+            .column(model::GroupAttributeAllowedValuesColumn::AttributeName)
+            .column(model::GroupAttributeAllowedValuesColumn::Value)
+            .order_by_asc(model::GroupAttributeAllowedValuesColumn::Value)
+            .into_tuple()
+            .all(transaction)
+            .await?;
+        set_allowed_values(&mut attributes, allowed_values);
+        Ok(attributes)
+    }
+
+    /// The groups granted the permissions, and whether they can edit the attribute. A group
+    /// that can edit the attribute can also read it, so it is only stored once.
+    async fn get_permission_group_ids(
+        transaction: &DatabaseTransaction,
+        permissions: &AttributePermissions,
+    ) -> Result<BTreeMap<GroupId, bool>> {
+        let mut groups = BTreeMap::new();
+        for name in &permissions.reader_groups {
+            groups.insert(Self::get_group_id_by_name(transaction, name).await?, false);
+        }
+        for name in &permissions.editor_groups {
+            groups.insert(Self::get_group_id_by_name(transaction, name).await?, true);
+        }
+        Ok(groups)
     }
 
     async fn get_user_object_classes(
@@ -172,14 +472,110 @@ impl SqlBackendHandler {
     }
 }
 
+fn set_permission_groups(
+    attributes: &mut [AttributeSchema],
+    permission_groups: Vec<(AttributeName, GroupName, bool)>,
+) {
+    for (name, group, can_edit) in permission_groups {
+        if let Some(attribute) = attributes.iter_mut().find(|a| a.name == name) {
+            if can_edit {
+                attribute.permissions.editor_groups.push(group);
+            } else {
+                attribute.permissions.reader_groups.push(group);
+            }
+        }
+    }
+}
+
+/// Checks the values of the new attributes of a user or a group against the rules of the schema.
+pub(crate) fn check_attribute_values(
+    attribute_list: &AttributeList,
+    attributes: &[Attribute],
+) -> Result<()> {
+    for attribute in attributes {
+        if let Some(attribute_schema) = attribute_list.get_attribute_schema(&attribute.name) {
+            attribute_schema
+                .validation
+                .validate_value(&attribute.value)
+                .map_err(|e| {
+                    DomainError::InternalError(format!(
+                        "Invalid value for attribute {}: {e}",
+                        attribute.name
+                    ))
+                })?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks that a new user or group has all the required attributes.
+pub(crate) fn check_required_attributes(
+    attribute_list: &AttributeList,
+    attributes: &[Attribute],
+) -> Result<()> {
+    match attribute_list.attributes.iter().find(|a| {
+        a.validation.is_required && !attributes.iter().any(|attribute| attribute.name == a.name)
+    }) {
+        Some(a) => Err(DomainError::InternalError(format!(
+            "Attribute {} is required",
+            a.name
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Checks that an update doesn't remove a required attribute, unless it replaces it.
+pub(crate) fn check_removed_attributes(
+    attribute_list: &AttributeList,
+    delete_attributes: &[AttributeName],
+    insert_attributes: &[Attribute],
+) -> Result<()> {
+    match delete_attributes.iter().find(|name| {
+        attribute_list
+            .get_attribute_schema(name)
+            .is_some_and(|a| a.validation.is_required)
+            && !insert_attributes.iter().any(|a| &a.name == *name)
+    }) {
+        Some(name) => Err(DomainError::InternalError(format!(
+            "Attribute {name} is required, it can't be removed"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// The new attributes whose value must not be shared with another user or group.
+pub(crate) fn get_unique_attributes<'a>(
+    attribute_list: &'a AttributeList,
+    attributes: &'a [Attribute],
+) -> impl Iterator<Item = &'a Attribute> {
+    attributes.iter().filter(|attribute| {
+        attribute_list
+            .get_attribute_schema(&attribute.name)
+            .is_some_and(|a| a.validation.is_unique)
+    })
+}
+
+fn set_allowed_values(
+    attributes: &mut [AttributeSchema],
+    allowed_values: Vec<(AttributeName, String)>,
+) {
+    for (name, value) in allowed_values {
+        if let Some(attribute) = attributes.iter_mut().find(|a| a.name == name) {
+            attribute.validation.allowed_values.push(value);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::sql_backend_handler::tests::*;
-    use lldap_domain::requests::UpdateUserRequest;
-    use lldap_domain::schema::AttributeList;
+    use lldap_domain::requests::{CreateGroupRequest, UpdateUserRequest};
+    use lldap_domain::schema::{AttributeList, AttributeValidation};
     use lldap_domain::types::{Attribute, AttributeType};
-    use lldap_domain_handlers::handler::{UserBackendHandler, UserRequestFilter};
+    use lldap_domain_handlers::handler::{
+        DynamicGroupFilter, GroupBackendHandler, UserBackendHandler, UserRequestFilter,
+    };
     use pretty_assertions::assert_eq;
 
     #[tokio::test]
@@ -198,6 +594,8 @@ mod tests {
                             is_editable: true,
                             is_hardcoded: true,
                             is_readonly: false,
+                            permissions: Default::default(),
+                            validation: Default::default(),
                         },
                         AttributeSchema {
                             name: "first_name".into(),
@@ -207,6 +605,8 @@ mod tests {
                             is_editable: true,
                             is_hardcoded: true,
                             is_readonly: false,
+                            permissions: Default::default(),
+                            validation: Default::default(),
                         },
                         AttributeSchema {
                             name: "last_name".into(),
@@ -216,6 +616,8 @@ mod tests {
                             is_editable: true,
                             is_hardcoded: true,
                             is_readonly: false,
+                            permissions: Default::default(),
+                            validation: Default::default(),
                         }
                     ]
                 },
@@ -237,6 +639,7 @@ mod tests {
             is_list: true,
             is_visible: false,
             is_editable: false,
+            validation: Default::default(),
         };
         fixture
             .handler
@@ -251,6 +654,8 @@ mod tests {
             is_editable: false,
             is_hardcoded: false,
             is_readonly: false,
+            permissions: Default::default(),
+            validation: Default::default(),
         };
         assert!(
             fixture
@@ -279,6 +684,131 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_user_attribute_used_by_dynamic_group() {
+        let fixture = TestFixture::new().await;
+        fixture
+            .handler
+            .add_user_attribute(CreateAttributeRequest {
+                name: "department".into(),
+                attribute_type: AttributeType::String,
+                is_list: false,
+                is_visible: true,
+                is_editable: false,
+                validation: Default::default(),
+            })
+            .await
+            .unwrap();
+        let group = fixture
+            .handler
+            .create_dynamic_group(
+                CreateGroupRequest {
+                    display_name: "Staff".into(),
+                    ..Default::default()
+                },
+                DynamicGroupFilter {
+                    ldap_filter: "(&(objectClass=person)(department=*))".to_owned(),
+                    user_filter: UserRequestFilter::And(vec![
+                        UserRequestFilter::True,
+                        UserRequestFilter::CustomAttributePresent("department".into()),
+                    ]),
+                },
+            )
+            .await
+            .unwrap();
+        let error = fixture
+            .handler
+            .delete_user_attribute(&"department".into())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&error, DomainError::ValidationError(e) if e.contains("\"Staff\"")),
+            "{error}"
+        );
+        assert!(
+            fixture
+                .handler
+                .get_schema()
+                .await
+                .unwrap()
+                .user_attributes
+                .get_attribute_schema(&"department".into())
+                .is_some()
+        );
+        // Once the group is gone, the attribute can be deleted.
+        fixture.handler.delete_group(group).await.unwrap();
+        fixture
+            .handler
+            .delete_user_attribute(&"department".into())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_group_attribute_validation_rules() {
+        let fixture = TestFixture::new().await;
+        let new_attribute = CreateAttributeRequest {
+            name: "team".into(),
+            attribute_type: AttributeType::String,
+            is_list: false,
+            is_visible: true,
+            is_editable: false,
+            validation: AttributeValidation {
+                regex: Some("[a-z]+".to_string()),
+                min_length: Some(2),
+                max_length: Some(10),
+                allowed_values: vec!["ops".to_string(), "dev".to_string(), "ops".to_string()],
+                is_unique: true,
+                is_required: true,
+                ..Default::default()
+            },
+        };
+        fixture
+            .handler
+            .add_group_attribute(new_attribute.clone())
+            .await
+            .unwrap();
+        let handler = &fixture.handler;
+        let get_validation = || async move {
+            handler
+                .get_schema()
+                .await
+                .unwrap()
+                .group_attributes
+                .get_attribute_schema(&"team".into())
+                .unwrap()
+                .validation
+                .clone()
+        };
+        assert_eq!(
+            get_validation().await,
+            AttributeValidation {
+                regex: Some("[a-z]+".to_string()),
+                min_length: Some(2),
+                max_length: Some(10),
+                allowed_values: vec!["dev".to_string(), "ops".to_string()],
+                is_unique: true,
+                is_required: true,
+                ..Default::default()
+            }
+        );
+        // The allowed values are deleted with the attribute.
+        fixture
+            .handler
+            .delete_group_attribute(&"team".into())
+            .await
+            .unwrap();
+        fixture
+            .handler
+            .add_group_attribute(CreateAttributeRequest {
+                validation: Default::default(),
+                ..new_attribute
+            })
+            .await
+            .unwrap();
+        assert_eq!(get_validation().await, AttributeValidation::default());
+    }
+
     #[tokio::test]
     async fn test_user_attribute_present_filter() {
         let fixture = TestFixture::new().await;
@@ -288,6 +818,7 @@ mod tests {
             is_list: true,
             is_visible: false,
             is_editable: false,
+            validation: Default::default(),
         };
         fixture
             .handler
@@ -325,6 +856,7 @@ mod tests {
             is_list: false,
             is_visible: true,
             is_editable: false,
+            validation: Default::default(),
         };
         fixture
             .handler
@@ -339,6 +871,8 @@ mod tests {
             is_editable: false,
             is_hardcoded: false,
             is_readonly: false,
+            permissions: Default::default(),
+            validation: Default::default(),
         };
         assert!(
             fixture
@@ -367,6 +901,67 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_user_attribute_permissions() {
+        let fixture = TestFixture::new().await;
+        let get_permissions = || async {
+            fixture
+                .handler
+                .get_schema()
+                .await
+                .unwrap()
+                .user_attributes
+                .get_attribute_schema(&"first_name".into())
+                .unwrap()
+                .permissions
+                .clone()
+        };
+        fixture
+            .handler
+            .set_user_attribute_permissions(
+                &"first_name".into(),
+                AttributePermissions {
+                    is_private: true,
+                    reader_groups: vec!["Best Group".into(), "Worst Group".into()],
+                    editor_groups: vec!["Worst Group".into()],
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            get_permissions().await,
+            AttributePermissions {
+                is_private: true,
+                reader_groups: vec!["Best Group".into()],
+                editor_groups: vec!["Worst Group".into()],
+            }
+        );
+        fixture
+            .handler
+            .delete_group(fixture.groups[1])
+            .await
+            .unwrap();
+        assert_eq!(
+            get_permissions().await,
+            AttributePermissions {
+                is_private: true,
+                reader_groups: vec!["Best Group".into()],
+                editor_groups: Vec::new(),
+            }
+        );
+        fixture
+            .handler
+            .set_user_attribute_permissions(&"first_name".into(), AttributePermissions::default())
+            .await
+            .unwrap();
+        assert_eq!(get_permissions().await, AttributePermissions::default());
+        fixture
+            .handler
+            .set_user_attribute_permissions(&"mail".into(), AttributePermissions::default())
+            .await
+            .unwrap_err();
+    }
+
     #[tokio::test]
     async fn test_user_object_class_add_and_delete() {
         let fixture = TestFixture::new().await;
@@ -1,4 +1,10 @@
-use crate::sql_backend_handler::SqlBackendHandler;
+use crate::{
+    sql_backend_handler::{SqlBackendHandler, after_cursor_condition, comparison_condition},
+    sql_schema_backend_handler::{
+        check_attribute_values, check_removed_attributes, check_required_attributes,
+        get_unique_attributes,
+    },
+};
 use async_trait::async_trait;
 use lldap_domain::{
     requests::{CreateUserRequest, UpdateUserRequest},
@@ -8,18 +14,23 @@ use lldap_domain::{
         Uuid,
     },
 };
-use lldap_domain_handlers::handler::{
-    ReadSchemaBackendHandler, UserBackendHandler, UserListerBackendHandler, UserRequestFilter,
+use lldap_domain_handlers::{
+    events::DirectoryEvent,
+    handler::{
+        BatchMode, BatchOperation, BatchResult, Page, ReadSchemaBackendHandler, UserBackendHandler,
+        UserListerBackendHandler, UserOrderField, UserPageRequest, UserRequestFilter,
+    },
 };
 use lldap_domain_model::{
     error::{DomainError, Result},
-    model::{self, GroupColumn, UserColumn, deserialize},
+    model::{self, GroupColumn, MembershipColumn, UserColumn, deserialize},
 };
 use sea_orm::{
     ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseTransaction, EntityTrait, ModelTrait,
-    QueryFilter, QueryOrder, QuerySelect, QueryTrait, Set, TransactionTrait,
+    Order, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, QueryTrait, Set, TransactionTrait,
     sea_query::{
-        Alias, Cond, Expr, Func, IntoColumnRef, IntoCondition, SimpleExpr, query::OnConflict,
+        Alias, Cond, Expr, Func, IntoColumnRef, IntoCondition, SimpleExpr, SubQueryStatement,
+        query::OnConflict,
     },
 };
 use std::collections::HashSet;
@@ -55,7 +66,7 @@ fn user_id_subcondition(filter: Cond) -> Cond {
     .into_condition()
 }
 
-fn get_user_filter_expr(filter: UserRequestFilter) -> Cond {
+pub(crate) fn get_user_filter_expr(filter: UserRequestFilter) -> Cond {
     use UserRequestFilter::*;
     let group_table = Alias::new("r1");
     fn bool_to_expr(b: bool) -> Cond {
@@ -111,6 +122,26 @@ fn get_user_filter_expr(filter: UserRequestFilter) -> Cond {
                 .into_condition()
         }
         CustomAttributePresent(name) => attribute_condition(name, None),
+        EmailVerified(verified) => {
+            ColumnTrait::eq(&UserColumn::EmailVerified, verified).into_condition()
+        }
+        DateComparison(column, operator, date) => {
+            comparison_condition(column.as_column_ref(), operator, date)
+        }
+    }
+}
+
+fn get_user_order_expr(field: UserOrderField) -> SimpleExpr {
+    let column = |column: UserColumn| -> SimpleExpr { Expr::col(column.as_column_ref()).into() };
+    match field {
+        UserOrderField::UserId => column(UserColumn::UserId),
+        UserOrderField::Email => column(UserColumn::LowercaseEmail),
+        // Users without a display name come first.
+        UserOrderField::DisplayName => SimpleExpr::FunctionCall(Func::coalesce([
+            SimpleExpr::FunctionCall(Func::lower(column(UserColumn::DisplayName))),
+            Expr::val("").into(),
+        ])),
+        UserOrderField::CreationDate => column(UserColumn::CreationDate),
     }
 }
 
@@ -134,7 +165,9 @@ impl UserListerBackendHandler for SqlBackendHandler {
         // To simplify the query, we always fetch groups. TODO: cleanup.
         _get_groups: bool,
     ) -> Result<Vec<UserAndGroups>> {
-        let filters = filters
+        let filters = self
+            .expand_user_filter(filters)
+            .await?
             .map(get_user_filter_expr)
             .unwrap_or_else(|| SimpleExpr::Value(true.into()).into_condition());
         let mut users: Vec<_> = model::User::find()
@@ -152,6 +185,27 @@ impl UserListerBackendHandler for SqlBackendHandler {
                 groups: Some(groups.into_iter().map(Into::<GroupDetails>::into).collect()),
             })
             .collect();
+        let dynamic_groups = Self::get_dynamic_groups(&self.sql_pool).await?;
+        for group in &dynamic_groups {
+            let members: HashSet<UserId> = self
+                .get_dynamic_group_members(group, filters.clone())
+                .await?
+                .into_iter()
+                .collect();
+            for user in users
+                .iter_mut()
+                .filter(|u| members.contains(&u.user.user_id))
+            {
+                user.groups
+                    .get_or_insert_with(Vec::new)
+                    .push(group.details.clone());
+            }
+        }
+        if !dynamic_groups.is_empty() {
+            for groups in users.iter_mut().filter_map(|u| u.groups.as_mut()) {
+                groups.sort_by(|g1, g2| g1.display_name.cmp(&g2.display_name));
+            }
+        }
 
         // At this point, the users don't have attributes, we need to populate it with another query.
         let attributes = model::UserAttributes::find()
@@ -186,9 +240,123 @@ impl UserListerBackendHandler for SqlBackendHandler {
         }
         Ok(users)
     }
+
+    #[instrument(skip(self), level = "debug", ret, err)]
+    async fn list_users_page(
+        &self,
+        filters: Option<UserRequestFilter>,
+        page: UserPageRequest,
+    ) -> Result<Page<UserAndGroups>> {
+        let filters = self
+            .expand_user_filter(filters)
+            .await?
+            .map(get_user_filter_expr)
+            .unwrap_or_else(|| SimpleExpr::Value(true.into()).into_condition());
+        // The position of the cursor is read from the user, which must still exist.
+        if let Some(after) = &page.after
+            && model::User::find_by_id(after.clone())
+                .count(&self.sql_pool)
+                .await?
+                == 0
+        {
+            return Err(DomainError::ValidationError(format!(
+                "Unknown cursor: {after}"
+            )));
+        }
+        let total_count = model::User::find()
+            .filter(filters.clone())
+            .count(&self.sql_pool)
+            .await?;
+        let sort = get_user_order_expr(page.order_by);
+        let order = if page.descending {
+            Order::Desc
+        } else {
+            Order::Asc
+        };
+        let mut query = model::User::find()
+            .select_only()
+            .column(UserColumn::UserId)
+            .filter(filters);
+        if let Some(after) = page.after {
+            let cursor_sort = SimpleExpr::SubQuery(
+                None,
+                Box::new(SubQueryStatement::SelectStatement(
+                    model::User::find()
+                        .select_only()
+                        .expr(sort.clone())
+                        .filter(ColumnTrait::eq(&UserColumn::UserId, after.clone()))
+                        .into_query(),
+                )),
+            );
+            query = query.filter(after_cursor_condition(
+                sort.clone(),
+                cursor_sort,
+                Expr::col(UserColumn::UserId.as_column_ref()).into(),
+                SimpleExpr::Value(after.into()),
+                page.descending,
+            ));
+        }
+        let mut user_ids: Vec<UserId> = query
+            .order_by(sort, order.clone())
+            .order_by(UserColumn::UserId, order)
+            .limit(page.first + 1)
+            .into_tuple()
+            .all(&self.sql_pool)
+            .await?;
+        let has_next_page = user_ids.len() as u64 > page.first;
+        user_ids.truncate(page.first as usize);
+        let mut users = self
+            .list_users(
+                Some(UserRequestFilter::Or(
+                    user_ids
+                        .iter()
+                        .cloned()
+                        .map(UserRequestFilter::UserId)
+                        .collect(),
+                )),
+                true,
+            )
+            .await?;
+        users.sort_by_key(|u| user_ids.iter().position(|id| id == &u.user.user_id));
+        Ok(Page {
+            items: users,
+            has_next_page,
+            total_count,
+        })
+    }
 }
 
 impl SqlBackendHandler {
+    /// Checks the new attributes of a user against the rules of the schema, including that no
+    /// other user already has the value of a unique attribute.
+    async fn check_user_attributes(
+        transaction: &DatabaseTransaction,
+        user_id: &UserId,
+        attributes: &[Attribute],
+        schema: &Schema,
+    ) -> Result<()> {
+        check_attribute_values(&schema.user_attributes, attributes)?;
+        for attribute in get_unique_attributes(&schema.user_attributes, attributes) {
+            let is_taken = model::UserAttributes::find()
+                .filter(model::UserAttributesColumn::AttributeName.eq(attribute.name.clone()))
+                .filter(
+                    model::UserAttributesColumn::Value
+                        .eq(Serialized::from(attribute.value.clone())),
+                )
+                .filter(model::UserAttributesColumn::UserId.ne(user_id))
+                .count(transaction)
+                .await?
+                > 0;
+            if is_taken {
+                return Err(DomainError::InternalError(format!(
+                    "Another user already has this value for the attribute {}",
+                    attribute.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
     fn compute_user_attribute_changes(
         user_id: &UserId,
         insert_attributes: Vec<Attribute>,
@@ -241,11 +409,251 @@ impl SqlBackendHandler {
         Ok((update_user_attributes, remove_user_attributes))
     }
 
+    async fn create_user_with_transaction(
+        transaction: &DatabaseTransaction,
+        request: CreateUserRequest,
+        schema: &Schema,
+    ) -> Result<()> {
+        let now = chrono::Utc::now().naive_utc();
+        let uuid = Uuid::from_name_and_date(request.user_id.as_str(), &now);
+        let lower_email = request.email.as_str().to_lowercase();
+        let new_user = model::users::ActiveModel {
+            user_id: Set(request.user_id.clone()),
+            email: Set(request.email),
+            lowercase_email: Set(lower_email),
+            display_name: to_value(&request.display_name),
+            creation_date: ActiveValue::Set(now),
+            uuid: ActiveValue::Set(uuid),
+            modified_date: ActiveValue::Set(now),
+            password_modified_date: ActiveValue::Set(now),
+            email_verified: ActiveValue::Set(request.email_verified),
+            ..Default::default()
+        };
+        check_required_attributes(&schema.user_attributes, &request.attributes)?;
+        Self::check_user_attributes(transaction, &request.user_id, &request.attributes, schema)
+            .await?;
+        let mut new_user_attributes = Vec::new();
+        for attribute in request.attributes {
+            if schema
+                .user_attributes
+                .get_attribute_type(&attribute.name)
+                .is_some()
+            {
+                new_user_attributes.push(model::user_attributes::ActiveModel {
+                    user_id: Set(request.user_id.clone()),
+                    attribute_name: Set(attribute.name),
+                    value: Set(attribute.value.into()),
+                });
+            } else {
+                return Err(DomainError::InternalError(format!(
+                    "Attribute name {} doesn't exist in the user schema,
+                        yet was attempted to be inserted in the database",
+                    &attribute.name
+                )));
+            }
+        }
+        new_user.insert(transaction).await?;
+        if !new_user_attributes.is_empty() {
+            model::UserAttributes::insert_many(new_user_attributes)
+                .exec(transaction)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_user_with_transaction(
+        transaction: &DatabaseTransaction,
+        user_id: &UserId,
+    ) -> Result<()> {
+        let res = model::User::delete_by_id(user_id.clone())
+            .exec(transaction)
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(DomainError::EntityNotFound(format!(
+                "No such user: '{user_id}'"
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn touch_group(
+        transaction: &DatabaseTransaction,
+        group_id: GroupId,
+    ) -> Result<()> {
+        let now = chrono::Utc::now().naive_utc();
+        model::groups::ActiveModel {
+            group_id: Set(group_id),
+            modified_date: Set(now),
+            ..Default::default()
+        }
+        .update(transaction)
+        .await?;
+        Ok(())
+    }
+
+    async fn add_user_to_group_with_transaction(
+        transaction: &DatabaseTransaction,
+        user_id: &UserId,
+        group_id: GroupId,
+    ) -> Result<()> {
+        Self::check_static_group(transaction, group_id).await?;
+        model::memberships::ActiveModel {
+            user_id: Set(user_id.clone()),
+            group_id: Set(group_id),
+        }
+        .insert(transaction)
+        .await?;
+        Self::touch_group(transaction, group_id).await
+    }
+
+    async fn remove_user_from_group_with_transaction(
+        transaction: &DatabaseTransaction,
+        user_id: &UserId,
+        group_id: GroupId,
+    ) -> Result<()> {
+        let res = model::Membership::delete_by_id((user_id.clone(), group_id))
+            .exec(transaction)
+            .await?;
+        if res.rows_affected == 0 {
+            return Err(DomainError::EntityNotFound(format!(
+                "No such membership: '{user_id}' -> {group_id:?}"
+            )));
+        }
+        Self::touch_group(transaction, group_id).await
+    }
+
+    /// Adds and removes members, and returns the corresponding events.
+    async fn set_group_members_with_transaction(
+        transaction: &DatabaseTransaction,
+        group_id: GroupId,
+        user_ids: Vec<UserId>,
+    ) -> Result<Vec<DirectoryEvent>> {
+        // Fails if the group doesn't exist, even if there is nothing to change.
+        Self::touch_group(transaction, group_id).await?;
+        Self::check_static_group(transaction, group_id).await?;
+        let current_members: Vec<UserId> = model::Membership::find()
+            .select_only()
+            .column(MembershipColumn::UserId)
+            .filter(MembershipColumn::GroupId.eq(group_id))
+            .order_by_asc(MembershipColumn::UserId)
+            .into_tuple()
+            .all(transaction)
+            .await?;
+        let removed = current_members
+            .iter()
+            .filter(|user_id| !user_ids.contains(user_id))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut added = Vec::new();
+        for user_id in user_ids {
+            if !current_members.contains(&user_id) && !added.contains(&user_id) {
+                added.push(user_id);
+            }
+        }
+        if !removed.is_empty() {
+            model::Membership::delete_many()
+                .filter(MembershipColumn::GroupId.eq(group_id))
+                .filter(MembershipColumn::UserId.is_in(removed.clone()))
+                .exec(transaction)
+                .await?;
+        }
+        if !added.is_empty() {
+            model::Membership::insert_many(added.iter().map(|user_id| {
+                model::memberships::ActiveModel {
+                    user_id: Set(user_id.clone()),
+                    group_id: Set(group_id),
+                }
+            }))
+            .exec(transaction)
+            .await?;
+        }
+        Ok(removed
+            .into_iter()
+            .map(|user_id| DirectoryEvent::UserRemovedFromGroup { user_id, group_id })
+            .chain(
+                added
+                    .into_iter()
+                    .map(|user_id| DirectoryEvent::UserAddedToGroup { user_id, group_id }),
+            )
+            .collect())
+    }
+
+    /// Applies one operation of a batch, and returns the events to emit once it is committed.
+    async fn apply_batch_operation(
+        transaction: &DatabaseTransaction,
+        operation: BatchOperation,
+        schema: &Schema,
+    ) -> Result<Vec<DirectoryEvent>> {
+        Ok(match operation {
+            BatchOperation::CreateUser(request) => {
+                let user_id = request.user_id.clone();
+                Self::create_user_with_transaction(transaction, request, schema).await?;
+                vec![DirectoryEvent::UserCreated { user_id }]
+            }
+            BatchOperation::UpdateUser(request) => {
+                let user_id = request.user_id.clone();
+                Self::update_user_with_transaction(transaction, request).await?;
+                vec![DirectoryEvent::UserUpdated { user_id }]
+            }
+            BatchOperation::DeleteUser(user_id) => {
+                Self::delete_user_with_transaction(transaction, &user_id).await?;
+                vec![DirectoryEvent::UserDeleted { user_id }]
+            }
+            BatchOperation::AddUserToGroup(user_id, group_id) => {
+                Self::add_user_to_group_with_transaction(transaction, &user_id, group_id).await?;
+                vec![DirectoryEvent::UserAddedToGroup { user_id, group_id }]
+            }
+            BatchOperation::AddUserToGroupByName(user_id, name) => {
+                let group_id = Self::get_group_id_by_name(transaction, &name).await?;
+                Self::add_user_to_group_with_transaction(transaction, &user_id, group_id).await?;
+                vec![DirectoryEvent::UserAddedToGroup { user_id, group_id }]
+            }
+            BatchOperation::RemoveUserFromGroup(user_id, group_id) => {
+                Self::remove_user_from_group_with_transaction(transaction, &user_id, group_id)
+                    .await?;
+                vec![DirectoryEvent::UserRemovedFromGroup { user_id, group_id }]
+            }
+            BatchOperation::SetGroupMembers(group_id, user_ids) => {
+                Self::set_group_members_with_transaction(transaction, group_id, user_ids).await?
+            }
+            BatchOperation::CreateGroup(request) => {
+                let display_name = request.display_name.clone();
+                let group_id =
+                    Self::create_group_with_transaction(transaction, request, None).await?;
+                vec![DirectoryEvent::GroupCreated {
+                    group_id,
+                    display_name,
+                }]
+            }
+            BatchOperation::UpdateGroup(request) => {
+                let group_id = request.group_id;
+                Self::update_group_with_transaction(request, transaction).await?;
+                vec![DirectoryEvent::GroupUpdated { group_id }]
+            }
+            BatchOperation::DeleteGroup(group_id) => {
+                Self::delete_group_with_transaction(transaction, group_id).await?;
+                vec![DirectoryEvent::GroupDeleted { group_id }]
+            }
+        })
+    }
+
     async fn update_user_with_transaction(
         transaction: &DatabaseTransaction,
         request: UpdateUserRequest,
     ) -> Result<()> {
         let schema = Self::get_schema_with_transaction(transaction).await?;
+        check_removed_attributes(
+            &schema.user_attributes,
+            &request.delete_attributes,
+            &request.insert_attributes,
+        )?;
+        Self::check_user_attributes(
+            transaction,
+            &request.user_id,
+            &request.insert_attributes,
+            &schema,
+        )
+        .await?;
         let (update_user_attributes, remove_user_attributes) =
             Self::compute_user_attribute_changes(
                 &request.user_id,
@@ -254,6 +662,14 @@ impl SqlBackendHandler {
                 &schema,
             )?;
         let lower_email = request.email.as_ref().map(|s| s.as_str().to_lowercase());
+        // A new email address has to be verified again, unless an admin set it.
+        let email_changed = match &lower_email {
+            None => false,
+            Some(lower_email) => model::User::find_by_id(request.user_id.clone())
+                .one(transaction)
+                .await?
+                .is_some_and(|u| &u.lowercase_email != lower_email),
+        };
         let now = chrono::Utc::now().naive_utc();
         let update_user = model::users::ActiveModel {
             user_id: ActiveValue::Set(request.user_id.clone()),
@@ -261,6 +677,11 @@ impl SqlBackendHandler {
             lowercase_email: lower_email.map(ActiveValue::Set).unwrap_or_default(),
             display_name: to_value(&request.display_name),
             modified_date: ActiveValue::Set(now),
+            email_verified: if email_changed {
+                ActiveValue::Set(request.email_verified)
+            } else {
+                ActiveValue::NotSet
+            },
             ..Default::default()
         };
         update_user.update(transaction).await?;
@@ -323,70 +744,39 @@ impl UserBackendHandler for SqlBackendHandler {
             .one(&self.sql_pool)
             .await?
             .ok_or_else(|| DomainError::EntityNotFound(user_id.to_string()))?;
+        let dynamic_groups = Self::get_dynamic_groups(&self.sql_pool).await?;
+        let dynamic_groups = self
+            .get_dynamic_groups_of_user(user_id, &dynamic_groups)
+            .await?;
         Ok(HashSet::from_iter(
             user.find_linked(model::memberships::UserToGroup)
                 .all(&self.sql_pool)
                 .await?
                 .into_iter()
-                .map(Into::<GroupDetails>::into),
+                .map(Into::<GroupDetails>::into)
+                .chain(dynamic_groups),
         ))
     }
 
     #[instrument(skip(self), level = "debug", err, fields(user_id = ?request.user_id.as_str()))]
     async fn create_user(&self, request: CreateUserRequest) -> Result<()> {
-        let now = chrono::Utc::now().naive_utc();
-        let uuid = Uuid::from_name_and_date(request.user_id.as_str(), &now);
-        let lower_email = request.email.as_str().to_lowercase();
-        let new_user = model::users::ActiveModel {
-            user_id: Set(request.user_id.clone()),
-            email: Set(request.email),
-            lowercase_email: Set(lower_email),
-            display_name: to_value(&request.display_name),
-            creation_date: ActiveValue::Set(now),
-            uuid: ActiveValue::Set(uuid),
-            modified_date: ActiveValue::Set(now),
-            password_modified_date: ActiveValue::Set(now),
-            ..Default::default()
-        };
-        let mut new_user_attributes = Vec::new();
+        let user_id = request.user_id.clone();
         self.sql_pool
             .transaction::<_, (), DomainError>(|transaction| {
                 Box::pin(async move {
                     let schema = Self::get_schema_with_transaction(transaction).await?;
-                    for attribute in request.attributes {
-                        if schema
-                            .user_attributes
-                            .get_attribute_type(&attribute.name)
-                            .is_some()
-                        {
-                            new_user_attributes.push(model::user_attributes::ActiveModel {
-                                user_id: Set(request.user_id.clone()),
-                                attribute_name: Set(attribute.name),
-                                value: Set(attribute.value.into()),
-                            });
-                        } else {
-                            return Err(DomainError::InternalError(format!(
-                                "Attribute name {} doesn't exist in the user schema,
-                                    yet was attempted to be inserted in the database",
-                                &attribute.name
-                            )));
-                        }
-                    }
-                    new_user.insert(transaction).await?;
-                    if !new_user_attributes.is_empty() {
-                        model::UserAttributes::insert_many(new_user_attributes)
-                            .exec(transaction)
-                            .await?;
-                    }
-                    Ok(())
+                    Self::create_user_with_transaction(transaction, request, &schema).await
                 })
             })
             .await?;
+        self.emit_event(DirectoryEvent::UserCreated { user_id })
+            .await;
         Ok(())
     }
 
     #[instrument(skip(self), level = "debug", err, fields(user_id = ?request.user_id.as_str()))]
     async fn update_user(&self, request: UpdateUserRequest) -> Result<()> {
+        let user_id = request.user_id.clone();
         self.sql_pool
             .transaction::<_, (), DomainError>(|transaction| {
                 Box::pin(
@@ -394,89 +784,107 @@ impl UserBackendHandler for SqlBackendHandler {
                 )
             })
             .await?;
+        self.emit_event(DirectoryEvent::UserUpdated { user_id })
+            .await;
         Ok(())
     }
 
     #[instrument(skip_all, level = "debug", err, fields(user_id = ?user_id.as_str()))]
     async fn delete_user(&self, user_id: &UserId) -> Result<()> {
-        let res = model::User::delete_by_id(user_id.clone())
-            .exec(&self.sql_pool)
+        let user_id = user_id.clone();
+        let deleted_user_id = user_id.clone();
+        self.sql_pool
+            .transaction::<_, (), DomainError>(|transaction| {
+                Box::pin(async move {
+                    Self::delete_user_with_transaction(transaction, &deleted_user_id).await
+                })
+            })
             .await?;
-        if res.rows_affected == 0 {
-            return Err(DomainError::EntityNotFound(format!(
-                "No such user: '{user_id}'"
-            )));
-        }
+        self.emit_event(DirectoryEvent::UserDeleted { user_id })
+            .await;
         Ok(())
     }
 
     #[instrument(skip_all, level = "debug", err, fields(user_id = ?user_id.as_str(), group_id))]
     async fn add_user_to_group(&self, user_id: &UserId, group_id: GroupId) -> Result<()> {
         let user_id = user_id.clone();
+        let membership_user_id = user_id.clone();
         self.sql_pool
-            .transaction::<_, _, sea_orm::DbErr>(|transaction| {
+            .transaction::<_, (), DomainError>(|transaction| {
                 Box::pin(async move {
-                    let new_membership = model::memberships::ActiveModel {
-                        user_id: ActiveValue::Set(user_id),
-                        group_id: ActiveValue::Set(group_id),
-                    };
-                    new_membership.insert(transaction).await?;
-
-                    // Update group modification time
-                    let now = chrono::Utc::now().naive_utc();
-                    let update_group = model::groups::ActiveModel {
-                        group_id: Set(group_id),
-                        modified_date: Set(now),
-                        ..Default::default()
-                    };
-                    update_group.update(transaction).await?;
-
-                    Ok(())
+                    Self::add_user_to_group_with_transaction(
+                        transaction,
+                        &membership_user_id,
+                        group_id,
+                    )
+                    .await
                 })
             })
             .await?;
+        self.emit_event(DirectoryEvent::UserAddedToGroup { user_id, group_id })
+            .await;
         Ok(())
     }
 
     #[instrument(skip_all, level = "debug", err, fields(user_id = ?user_id.as_str(), group_id))]
     async fn remove_user_from_group(&self, user_id: &UserId, group_id: GroupId) -> Result<()> {
         let user_id = user_id.clone();
+        let membership_user_id = user_id.clone();
         self.sql_pool
-            .transaction::<_, _, sea_orm::DbErr>(|transaction| {
+            .transaction::<_, (), DomainError>(|transaction| {
                 Box::pin(async move {
-                    let res = model::Membership::delete_by_id((user_id.clone(), group_id))
-                        .exec(transaction)
-                        .await?;
-                    if res.rows_affected == 0 {
-                        return Err(sea_orm::DbErr::Custom(format!(
-                            "No such membership: '{user_id}' -> {group_id:?}"
-                        )));
-                    }
-
-                    // Update group modification time
-                    let now = chrono::Utc::now().naive_utc();
-                    let update_group = model::groups::ActiveModel {
-                        group_id: Set(group_id),
-                        modified_date: Set(now),
-                        ..Default::default()
-                    };
-                    update_group.update(transaction).await?;
-
-                    Ok(())
+                    Self::remove_user_from_group_with_transaction(
+                        transaction,
+                        &membership_user_id,
+                        group_id,
+                    )
+                    .await
                 })
             })
-            .await
-            .map_err(|e| match e {
-                sea_orm::TransactionError::Connection(sea_orm::DbErr::Custom(msg)) => {
-                    DomainError::EntityNotFound(msg)
+            .await?;
+        self.emit_event(DirectoryEvent::UserRemovedFromGroup { user_id, group_id })
+            .await;
+        Ok(())
+    }
+
+    #[instrument(skip_all, level = "debug", err, fields(operations = operations.len(), ?mode))]
+    async fn apply_batch(
+        &self,
+        operations: Vec<BatchOperation>,
+        mode: BatchMode,
+    ) -> Result<BatchResult> {
+        let transaction = self.sql_pool.begin().await?;
+        let schema = Self::get_schema_with_transaction(&transaction).await?;
+        let mut results = Vec::with_capacity(operations.len());
+        let mut events = Vec::new();
+        for operation in operations {
+            // Each operation gets a savepoint, so that a failed one can be rolled back alone.
+            let savepoint = transaction.begin().await?;
+            match Self::apply_batch_operation(&savepoint, operation, &schema).await {
+                Ok(operation_events) => {
+                    savepoint.commit().await?;
+                    events.extend(operation_events);
+                    results.push(Ok(()));
                 }
-                sea_orm::TransactionError::Transaction(sea_orm::DbErr::Custom(msg)) => {
-                    DomainError::EntityNotFound(msg)
+                Err(e) => {
+                    savepoint.rollback().await?;
+                    results.push(Err(e));
+                    if mode == BatchMode::AllOrNothing {
+                        break;
+                    }
                 }
-                sea_orm::TransactionError::Connection(e) => DomainError::DatabaseError(e),
-                sea_orm::TransactionError::Transaction(e) => DomainError::DatabaseError(e),
-            })?;
-        Ok(())
+            }
+        }
+        let committed = mode == BatchMode::ContinueOnError || results.iter().all(Result::is_ok);
+        if committed {
+            transaction.commit().await?;
+            for event in events {
+                self.emit_event(event).await;
+            }
+        } else {
+            transaction.rollback().await?;
+        }
+        Ok(BatchResult { committed, results })
     }
 }
 
@@ -485,8 +893,14 @@ mod tests {
     use super::*;
     use crate::sql_backend_handler::tests::*;
     use lldap_auth::opaque::server::generate_random_private_key;
-    use lldap_domain::types::{Attribute, JpegPhoto};
-    use lldap_domain_handlers::handler::SubStringFilter;
+    use lldap_domain::{
+        requests::{CreateAttributeRequest, CreateGroupRequest, UpdateGroupRequest},
+        schema::AttributeValidation,
+        types::{Attribute, AttributeType, AttributeValue, GroupName, JpegPhoto},
+    };
+    use lldap_domain_handlers::handler::{
+        ComparisonOperator, GroupListerBackendHandler, SchemaBackendHandler, SubStringFilter,
+    };
     use lldap_domain_model::model::UserColumn;
     use pretty_assertions::{assert_eq, assert_ne};
 
@@ -585,6 +999,47 @@ mod tests {
         assert_eq!(users, vec!["patrick"]);
     }
 
+    #[tokio::test]
+    async fn test_list_users_date_comparison_filter() {
+        let fixture = TestFixture::new().await;
+        let old_date = chrono::NaiveDate::from_ymd_opt(2000, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        model::users::ActiveModel {
+            user_id: Set(UserId::new("bob")),
+            creation_date: Set(old_date),
+            ..Default::default()
+        }
+        .update(&fixture.handler.sql_pool)
+        .await
+        .unwrap();
+        let cutoff = chrono::NaiveDate::from_ymd_opt(2010, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let users = get_user_names(
+            &fixture.handler,
+            Some(UserRequestFilter::DateComparison(
+                UserColumn::CreationDate,
+                ComparisonOperator::LessThan,
+                cutoff,
+            )),
+        )
+        .await;
+        assert_eq!(users, vec!["bob"]);
+        let users = get_user_names(
+            &fixture.handler,
+            Some(UserRequestFilter::DateComparison(
+                UserColumn::CreationDate,
+                ComparisonOperator::GreaterOrEqual,
+                cutoff,
+            )),
+        )
+        .await;
+        assert_eq!(users, vec!["john", "nogroup", "patrick"]);
+    }
+
     #[tokio::test]
     async fn test_list_users_false_filter() {
         let fixture = TestFixture::new().await;
@@ -708,17 +1163,142 @@ mod tests {
         assert_eq!(users, vec!["bob", "john"]);
     }
 
-    #[tokio::test]
-    async fn test_list_users_filter_not() {
-        let fixture = TestFixture::new().await;
-        let users = get_user_names(
-            &fixture.handler,
-            Some(UserRequestFilter::Not(Box::new(UserRequestFilter::UserId(
-                UserId::new("bob"),
-            )))),
+    async fn get_user_page(
+        handler: &SqlBackendHandler,
+        filters: Option<UserRequestFilter>,
+        order_by: UserOrderField,
+        descending: bool,
+        after: Option<&str>,
+    ) -> (Vec<String>, bool, u64) {
+        let page = handler
+            .list_users_page(
+                filters,
+                UserPageRequest {
+                    order_by,
+                    descending,
+                    after: after.map(UserId::new),
+                    first: 3,
+                },
+            )
+            .await
+            .unwrap();
+        (
+            page.items
+                .into_iter()
+                .map(|u| u.user.user_id.to_string())
+                .collect(),
+            page.has_next_page,
+            page.total_count,
         )
-        .await;
-        assert_eq!(users, vec!["john", "nogroup", "patrick"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_users_page() {
+        let fixture = TestFixture::new().await;
+        assert_eq!(
+            get_user_page(&fixture.handler, None, UserOrderField::UserId, false, None).await,
+            (vec!["bob".into(), "john".into(), "nogroup".into()], true, 4)
+        );
+        assert_eq!(
+            get_user_page(
+                &fixture.handler,
+                None,
+                UserOrderField::UserId,
+                false,
+                Some("nogroup")
+            )
+            .await,
+            (vec!["patrick".into()], false, 4)
+        );
+        let page = fixture
+            .handler
+            .list_users_page(None, UserPageRequest::default())
+            .await
+            .unwrap();
+        assert_eq!(page.items, vec![]);
+        assert!(page.has_next_page);
+    }
+
+    #[tokio::test]
+    async fn test_list_users_page_unknown_cursor() {
+        let fixture = TestFixture::new().await;
+        let result = fixture
+            .handler
+            .list_users_page(
+                None,
+                UserPageRequest {
+                    after: Some(UserId::new("deleted")),
+                    first: 2,
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(matches!(result, Err(DomainError::ValidationError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_list_users_page_sorted_with_filter() {
+        let fixture = TestFixture::new().await;
+        let filter =
+            UserRequestFilter::Not(Box::new(UserRequestFilter::UserId(UserId::new("bob"))));
+        assert_eq!(
+            get_user_page(
+                &fixture.handler,
+                Some(filter.clone()),
+                UserOrderField::DisplayName,
+                true,
+                None
+            )
+            .await,
+            (
+                vec!["patrick".into(), "nogroup".into(), "john".into()],
+                false,
+                3
+            )
+        );
+        assert_eq!(
+            get_user_page(
+                &fixture.handler,
+                Some(filter),
+                UserOrderField::DisplayName,
+                true,
+                Some("patrick")
+            )
+            .await,
+            (vec!["nogroup".into(), "john".into()], false, 3)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_users_page_has_groups() {
+        let fixture = TestFixture::new().await;
+        let page = fixture
+            .handler
+            .list_users_page(
+                Some(UserRequestFilter::UserId(UserId::new("bob"))),
+                UserPageRequest {
+                    first: 1,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        let groups = page.items[0].groups.as_ref().unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].display_name, "Best Group".into());
+    }
+
+    #[tokio::test]
+    async fn test_list_users_filter_not() {
+        let fixture = TestFixture::new().await;
+        let users = get_user_names(
+            &fixture.handler,
+            Some(UserRequestFilter::Not(Box::new(UserRequestFilter::UserId(
+                UserId::new("bob"),
+            )))),
+        )
+        .await;
+        assert_eq!(users, vec!["john", "nogroup", "patrick"]);
     }
 
     #[tokio::test]
@@ -911,6 +1491,7 @@ mod tests {
                         value: JpegPhoto::for_tests().into(),
                     },
                 ],
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -941,6 +1522,70 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_update_user_email_resets_verification() {
+        let fixture = TestFixture::new().await;
+        model::users::ActiveModel {
+            user_id: ActiveValue::Set(UserId::new("bob")),
+            email_verified: ActiveValue::Set(true),
+            ..Default::default()
+        }
+        .update(&fixture.handler.sql_pool)
+        .await
+        .unwrap();
+
+        // Same address, different case: still verified.
+        fixture
+            .handler
+            .update_user(UpdateUserRequest {
+                user_id: UserId::new("bob"),
+                email: Some("Bob@bob.bob".into()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let user = fixture
+            .handler
+            .get_user_details(&UserId::new("bob"))
+            .await
+            .unwrap();
+        assert!(user.email_verified);
+
+        fixture
+            .handler
+            .update_user(UpdateUserRequest {
+                user_id: UserId::new("bob"),
+                email: Some("new@bob.bob".into()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let user = fixture
+            .handler
+            .get_user_details(&UserId::new("bob"))
+            .await
+            .unwrap();
+        assert!(!user.email_verified);
+
+        // An address set by an admin doesn't need a confirmation.
+        fixture
+            .handler
+            .update_user(UpdateUserRequest {
+                user_id: UserId::new("bob"),
+                email: Some("admin-set@bob.bob".into()),
+                email_verified: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let user = fixture
+            .handler
+            .get_user_details(&UserId::new("bob"))
+            .await
+            .unwrap();
+        assert!(user.email_verified);
+    }
+
     #[tokio::test]
     async fn test_update_user_some_values() {
         let fixture = TestFixture::new().await;
@@ -1155,6 +1800,7 @@ mod tests {
                         value: JpegPhoto::for_tests().into(),
                     },
                 ],
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -1257,4 +1903,436 @@ mod tests {
             .await
             .unwrap_err();
     }
+
+    #[tokio::test]
+    async fn test_user_attribute_value_rules() {
+        let fixture = TestFixture::new().await;
+        fixture
+            .handler
+            .add_user_attribute(CreateAttributeRequest {
+                name: "color".into(),
+                attribute_type: AttributeType::String,
+                is_list: true,
+                is_visible: true,
+                is_editable: true,
+                validation: AttributeValidation {
+                    allowed_values: vec!["green".to_string(), "red".to_string()],
+                    ..Default::default()
+                },
+            })
+            .await
+            .unwrap();
+        fixture
+            .handler
+            .add_user_attribute(CreateAttributeRequest {
+                name: "room".into(),
+                attribute_type: AttributeType::String,
+                is_list: false,
+                is_visible: true,
+                is_editable: true,
+                validation: AttributeValidation {
+                    regex: Some("[A-Z][0-9]+".to_string()),
+                    max_length: Some(4),
+                    ..Default::default()
+                },
+            })
+            .await
+            .unwrap();
+        let create_with = |name: &str, value: AttributeValue| CreateUserRequest {
+            user_id: UserId::new("james"),
+            email: "james@example.com".into(),
+            attributes: vec![Attribute {
+                name: name.into(),
+                value,
+            }],
+            ..Default::default()
+        };
+
+        let err = fixture
+            .handler
+            .create_user(create_with(
+                "color",
+                vec!["red".to_string(), "blue".to_string()].into(),
+            ))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Internal error: `Invalid value for attribute color: must be one of: green, red`"
+        );
+        fixture
+            .handler
+            .create_user(create_with("room", "B12345".to_string().into()))
+            .await
+            .unwrap_err();
+        fixture
+            .handler
+            .create_user(create_with("room", "b12".to_string().into()))
+            .await
+            .unwrap_err();
+        fixture
+            .handler
+            .create_user(create_with("room", "B12".to_string().into()))
+            .await
+            .unwrap();
+
+        fixture
+            .handler
+            .update_user(UpdateUserRequest {
+                user_id: UserId::new("james"),
+                insert_attributes: vec![Attribute {
+                    name: "room".into(),
+                    value: "12".to_string().into(),
+                }],
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        let user = fixture
+            .handler
+            .get_user_details(&UserId::new("james"))
+            .await
+            .unwrap();
+        assert_eq!(
+            user.attributes,
+            vec![Attribute {
+                name: "room".into(),
+                value: "B12".to_string().into()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_user_attribute_unique_and_required() {
+        let fixture = TestFixture::new().await;
+        fixture
+            .handler
+            .add_user_attribute(CreateAttributeRequest {
+                name: "employee_number".into(),
+                attribute_type: AttributeType::Integer,
+                is_list: false,
+                is_visible: true,
+                is_editable: false,
+                validation: AttributeValidation {
+                    min_value: Some(1),
+                    is_unique: true,
+                    is_required: true,
+                    ..Default::default()
+                },
+            })
+            .await
+            .unwrap();
+        let create_with = |user_id: &str, number: Option<i64>| CreateUserRequest {
+            user_id: UserId::new(user_id),
+            email: format!("{user_id}@example.com").into(),
+            attributes: number
+                .map(|n| Attribute {
+                    name: "employee_number".into(),
+                    value: n.into(),
+                })
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+
+        let err = fixture
+            .handler
+            .create_user(create_with("james", None))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Internal error: `Attribute employee_number is required`"
+        );
+        fixture
+            .handler
+            .create_user(create_with("james", Some(0)))
+            .await
+            .unwrap_err();
+        fixture
+            .handler
+            .create_user(create_with("james", Some(12)))
+            .await
+            .unwrap();
+        let err = fixture
+            .handler
+            .create_user(create_with("jim", Some(12)))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Internal error: `Another user already has this value for the attribute employee_number`"
+        );
+
+        // The existing users are not affected until they are updated.
+        fixture
+            .handler
+            .update_user(UpdateUserRequest {
+                user_id: UserId::new("bob"),
+                insert_attributes: vec![Attribute {
+                    name: "employee_number".into(),
+                    value: 12i64.into(),
+                }],
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        // Setting the same value again is not a conflict with itself.
+        fixture
+            .handler
+            .update_user(UpdateUserRequest {
+                user_id: UserId::new("james"),
+                delete_attributes: vec!["employee_number".into()],
+                insert_attributes: vec![Attribute {
+                    name: "employee_number".into(),
+                    value: 12i64.into(),
+                }],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let err = fixture
+            .handler
+            .update_user(UpdateUserRequest {
+                user_id: UserId::new("james"),
+                delete_attributes: vec!["employee_number".into()],
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Internal error: `Attribute employee_number is required, it can't be removed`"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_batch_all_or_nothing() {
+        let fixture = TestFixture::new().await;
+
+        let result = fixture
+            .handler
+            .apply_batch(
+                vec![
+                    BatchOperation::CreateUser(CreateUserRequest {
+                        user_id: UserId::new("james"),
+                        email: "james@example.com".into(),
+                        ..Default::default()
+                    }),
+                    BatchOperation::DeleteUser(UserId::new("not found")),
+                    BatchOperation::DeleteUser(UserId::new("bob")),
+                ],
+                BatchMode::AllOrNothing,
+            )
+            .await
+            .unwrap();
+
+        assert!(!result.committed);
+        assert_eq!(result.results.len(), 2);
+        assert!(result.results[0].is_ok());
+        assert!(matches!(
+            result.results[1],
+            Err(DomainError::EntityNotFound(_))
+        ));
+        assert_eq!(
+            get_user_names(&fixture.handler, None).await,
+            vec!["bob", "john", "nogroup", "patrick"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_batch_continue_on_error() {
+        let fixture = TestFixture::new().await;
+
+        let result = fixture
+            .handler
+            .apply_batch(
+                vec![
+                    BatchOperation::CreateUser(CreateUserRequest {
+                        user_id: UserId::new("james"),
+                        email: "james@example.com".into(),
+                        ..Default::default()
+                    }),
+                    BatchOperation::AddUserToGroup(UserId::new("james"), GroupId(16242)),
+                    BatchOperation::AddUserToGroup(UserId::new("james"), fixture.groups[1]),
+                    BatchOperation::RemoveUserFromGroup(UserId::new("nogroup"), fixture.groups[0]),
+                    BatchOperation::DeleteUser(UserId::new("bob")),
+                ],
+                BatchMode::ContinueOnError,
+            )
+            .await
+            .unwrap();
+
+        assert!(result.committed);
+        assert_eq!(
+            result.results.iter().map(Result::is_ok).collect::<Vec<_>>(),
+            vec![true, false, true, false, true]
+        );
+        assert_eq!(
+            get_user_names(&fixture.handler, None).await,
+            vec!["james", "john", "nogroup", "patrick"]
+        );
+        assert_eq!(
+            get_user_names(
+                &fixture.handler,
+                Some(UserRequestFilter::MemberOfId(fixture.groups[1])),
+            )
+            .await,
+            vec!["james", "john", "patrick"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_batch_set_group_members() {
+        let fixture = TestFixture::new().await;
+
+        let result = fixture
+            .handler
+            .apply_batch(
+                vec![
+                    BatchOperation::SetGroupMembers(
+                        fixture.groups[0],
+                        vec![UserId::new("patrick"), UserId::new("nogroup")],
+                    ),
+                    BatchOperation::SetGroupMembers(fixture.groups[2], vec![]),
+                    BatchOperation::SetGroupMembers(GroupId(16242), vec![]),
+                ],
+                BatchMode::ContinueOnError,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            result.results.iter().map(Result::is_ok).collect::<Vec<_>>(),
+            vec![true, true, false]
+        );
+        assert_eq!(
+            get_user_names(
+                &fixture.handler,
+                Some(UserRequestFilter::MemberOfId(fixture.groups[0])),
+            )
+            .await,
+            vec!["nogroup", "patrick"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_batch_group_operations() {
+        let fixture = TestFixture::new().await;
+
+        let result = fixture
+            .handler
+            .apply_batch(
+                vec![
+                    BatchOperation::UpdateGroup(UpdateGroupRequest {
+                        group_id: fixture.groups[0],
+                        display_name: Some("Better Group".into()),
+                        delete_attributes: Vec::new(),
+                        insert_attributes: Vec::new(),
+                    }),
+                    BatchOperation::DeleteGroup(fixture.groups[2]),
+                    BatchOperation::DeleteGroup(GroupId(16242)),
+                ],
+                BatchMode::AllOrNothing,
+            )
+            .await
+            .unwrap();
+
+        assert!(!result.committed);
+        assert!(matches!(
+            result.results[2],
+            Err(DomainError::EntityNotFound(_))
+        ));
+        let result = fixture
+            .handler
+            .apply_batch(
+                vec![
+                    BatchOperation::UpdateGroup(UpdateGroupRequest {
+                        group_id: fixture.groups[0],
+                        display_name: Some("Better Group".into()),
+                        delete_attributes: Vec::new(),
+                        insert_attributes: Vec::new(),
+                    }),
+                    BatchOperation::DeleteGroup(fixture.groups[2]),
+                ],
+                BatchMode::AllOrNothing,
+            )
+            .await
+            .unwrap();
+
+        assert!(result.committed);
+        assert_eq!(
+            fixture
+                .handler
+                .list_groups(None)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|g| g.display_name.to_string())
+                .collect::<Vec<_>>(),
+            vec!["Better Group", "Worst Group"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_batch_create_group() {
+        let fixture = TestFixture::new().await;
+
+        let result = fixture
+            .handler
+            .apply_batch(
+                vec![
+                    BatchOperation::CreateGroup(CreateGroupRequest {
+                        display_name: "New Group".into(),
+                        attributes: Vec::new(),
+                    }),
+                    BatchOperation::AddUserToGroupByName(
+                        UserId::new("bob"),
+                        GroupName::from("new group"),
+                    ),
+                    BatchOperation::AddUserToGroupByName(
+                        UserId::new("bob"),
+                        GroupName::from("Unknown Group"),
+                    ),
+                ],
+                BatchMode::AllOrNothing,
+            )
+            .await
+            .unwrap();
+
+        assert!(!result.committed);
+        assert!(matches!(
+            result.results[2],
+            Err(DomainError::EntityNotFound(_))
+        ));
+        assert_eq!(fixture.handler.list_groups(None).await.unwrap().len(), 3);
+        let result = fixture
+            .handler
+            .apply_batch(
+                vec![
+                    BatchOperation::CreateGroup(CreateGroupRequest {
+                        display_name: "New Group".into(),
+                        attributes: Vec::new(),
+                    }),
+                    BatchOperation::AddUserToGroupByName(
+                        UserId::new("bob"),
+                        GroupName::from("new group"),
+                    ),
+                ],
+                BatchMode::AllOrNothing,
+            )
+            .await
+            .unwrap();
+
+        assert!(result.committed);
+        assert_eq!(
+            UserBackendHandler::get_user_groups(&fixture.handler, &UserId::new("bob"))
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|g| g.display_name.to_string())
+                .collect::<HashSet<_>>(),
+            HashSet::from(["Best Group".to_owned(), "New Group".to_owned()])
+        );
+    }
 }
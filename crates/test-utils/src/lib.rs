@@ -4,16 +4,17 @@ use lldap_domain::{
         CreateAttributeRequest, CreateGroupRequest, CreateUserRequest, UpdateGroupRequest,
         UpdateUserRequest,
     },
-    schema::{AttributeList, AttributeSchema, Schema},
+    schema::{AttributeList, AttributePermissions, AttributeSchema, Schema},
     types::{
-        AttributeName, AttributeType, Group, GroupDetails, GroupId, LdapObjectClass, User,
-        UserAndGroups, UserId,
+        AttributeName, AttributeType, Group, GroupDetails, GroupId, GroupOwner, LdapObjectClass,
+        User, UserAndGroups, UserId,
     },
 };
 use lldap_domain_handlers::handler::{
-    BackendHandler, BindRequest, GroupBackendHandler, GroupListerBackendHandler,
-    GroupRequestFilter, LoginHandler, ReadSchemaBackendHandler, SchemaBackendHandler,
-    UserBackendHandler, UserListerBackendHandler, UserRequestFilter,
+    BackendHandler, BatchMode, BatchOperation, BatchResult, BindRequest, DynamicGroupFilter,
+    GroupBackendHandler, GroupListerBackendHandler, GroupPageRequest, GroupRequestFilter,
+    LoginHandler, Page, ReadSchemaBackendHandler, SchemaBackendHandler, UserBackendHandler,
+    UserListerBackendHandler, UserPageRequest, UserRequestFilter,
 };
 use lldap_domain_model::error::Result;
 use lldap_opaque_handler::{OpaqueHandler, login, registration};
@@ -31,17 +32,22 @@ mockall::mock! {
     #[async_trait]
     impl GroupListerBackendHandler for TestBackendHandler {
         async fn list_groups(&self, filters: Option<GroupRequestFilter>) -> Result<Vec<Group>>;
+        async fn list_groups_page(&self, filters: Option<GroupRequestFilter>, page: GroupPageRequest) -> Result<Page<Group>>;
     }
     #[async_trait]
     impl GroupBackendHandler for TestBackendHandler {
         async fn get_group_details(&self, group_id: GroupId) -> Result<GroupDetails>;
         async fn update_group(&self, request: UpdateGroupRequest) -> Result<()>;
         async fn create_group(&self, request: CreateGroupRequest) -> Result<GroupId>;
+        async fn create_dynamic_group(&self, request: CreateGroupRequest, filter: DynamicGroupFilter) -> Result<GroupId>;
         async fn delete_group(&self, group_id: GroupId) -> Result<()>;
+        async fn add_group_owner(&self, group_id: GroupId, owner: GroupOwner) -> Result<()>;
+        async fn remove_group_owner(&self, group_id: GroupId, owner: GroupOwner) -> Result<()>;
     }
     #[async_trait]
     impl UserListerBackendHandler for TestBackendHandler {
         async fn list_users(&self, filters: Option<UserRequestFilter>, get_groups: bool) -> Result<Vec<UserAndGroups>>;
+        async fn list_users_page(&self, filters: Option<UserRequestFilter>, page: UserPageRequest) -> Result<Page<UserAndGroups>>;
     }
     #[async_trait]
     impl UserBackendHandler for TestBackendHandler {
@@ -52,6 +58,7 @@ mockall::mock! {
         async fn get_user_groups(&self, user_id: &UserId) -> Result<HashSet<GroupDetails>>;
         async fn add_user_to_group(&self, user_id: &UserId, group_id: GroupId) -> Result<()>;
         async fn remove_user_from_group(&self, user_id: &UserId, group_id: GroupId) -> Result<()>;
+        async fn apply_batch(&self, operations: Vec<BatchOperation>, mode: BatchMode) -> Result<BatchResult>;
     }
     #[async_trait]
     impl ReadSchemaBackendHandler for TestBackendHandler {
@@ -63,6 +70,8 @@ mockall::mock! {
         async fn add_group_attribute(&self, request: CreateAttributeRequest) -> Result<()>;
         async fn delete_user_attribute(&self, name: &AttributeName) -> Result<()>;
         async fn delete_group_attribute(&self, name: &AttributeName) -> Result<()>;
+        async fn set_user_attribute_permissions(&self, name: &AttributeName, permissions: AttributePermissions) -> Result<()>;
+        async fn set_group_attribute_permissions(&self, name: &AttributeName, permissions: AttributePermissions) -> Result<()>;
         async fn add_user_object_class(&self, request: &LdapObjectClass) -> Result<()>;
         async fn add_group_object_class(&self, request: &LdapObjectClass) -> Result<()>;
         async fn delete_user_object_class(&self, name: &LdapObjectClass) -> Result<()>;
@@ -84,7 +93,7 @@ mockall::mock! {
         async fn registration_finish(
             &self,
             request: registration::ClientRegistrationFinishRequest
-        ) -> Result<()>;
+        ) -> Result<UserId>;
     }
 }
 
@@ -101,6 +110,8 @@ pub fn setup_default_schema(mock: &mut MockTestBackendHandler) {
                         is_editable: true,
                         is_hardcoded: true,
                         is_readonly: false,
+                        permissions: Default::default(),
+                        validation: Default::default(),
                     },
                     AttributeSchema {
                         name: "first_name".into(),
@@ -110,6 +121,8 @@ pub fn setup_default_schema(mock: &mut MockTestBackendHandler) {
                         is_editable: true,
                         is_hardcoded: true,
                         is_readonly: false,
+                        permissions: Default::default(),
+                        validation: Default::default(),
                     },
                     AttributeSchema {
                         name: "last_name".into(),
@@ -119,6 +132,8 @@ pub fn setup_default_schema(mock: &mut MockTestBackendHandler) {
                         is_editable: true,
                         is_hardcoded: true,
                         is_readonly: false,
+                        permissions: Default::default(),
+                        validation: Default::default(),
                     },
                 ],
             },
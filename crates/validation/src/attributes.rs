@@ -13,6 +13,65 @@ pub fn validate_attribute_name(attribute_name: &str) -> Result<(), Vec<char>> {
     }
 }
 
+/// The constraints on the values of an attribute. The unset ones accept any value, and the
+/// lengths are counted in characters.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AttributeValueRules<'a> {
+    /// The whole value must match this regular expression.
+    pub regex: Option<&'a str>,
+    pub min_length: Option<i64>,
+    pub max_length: Option<i64>,
+    /// When set, the value must be an integer.
+    pub min_value: Option<i64>,
+    pub max_value: Option<i64>,
+    /// When not empty, the value must be one of these.
+    pub allowed_values: &'a [String],
+}
+
+fn compile_regex(regex: &str) -> Result<regex::Regex, String> {
+    regex::Regex::new(&format!("^(?:{regex})$"))
+        .map_err(|e| format!("invalid regular expression: {e}"))
+}
+
+/// Checks that a regular expression can be used as a rule.
+pub fn validate_attribute_regex(regex: &str) -> Result<(), String> {
+    compile_regex(regex).map(|_| ())
+}
+
+/// Checks a single value against the rules, and describes the first one it breaks.
+pub fn validate_attribute_value(value: &str, rules: &AttributeValueRules) -> Result<(), String> {
+    let length = value.chars().count() as i64;
+    if let Some(min_length) = rules.min_length.filter(|&min| length < min) {
+        return Err(format!("must be at least {min_length} characters long"));
+    }
+    if let Some(max_length) = rules.max_length.filter(|&max| length > max) {
+        return Err(format!("must be at most {max_length} characters long"));
+    }
+    if rules.min_value.is_some() || rules.max_value.is_some() {
+        let number = value
+            .parse::<i64>()
+            .map_err(|_| "must be an integer".to_string())?;
+        if let Some(min_value) = rules.min_value.filter(|&min| number < min) {
+            return Err(format!("must be at least {min_value}"));
+        }
+        if let Some(max_value) = rules.max_value.filter(|&max| number > max) {
+            return Err(format!("must be at most {max_value}"));
+        }
+    }
+    if !rules.allowed_values.is_empty() && !rules.allowed_values.iter().any(|v| v == value) {
+        return Err(format!(
+            "must be one of: {}",
+            rules.allowed_values.join(", ")
+        ));
+    }
+    match rules.regex {
+        Some(regex) if !compile_regex(regex)?.is_match(value) => {
+            Err(format!("must match the pattern {regex}"))
+        }
+        _ => Ok(()),
+    }
+}
+
 mod tests {
 
     #[test]
@@ -42,4 +101,65 @@ mod tests {
         test_invalid_char('_');
         test_invalid_char('#');
     }
+
+    #[test]
+    fn test_attribute_value_rules() {
+        use super::{AttributeValueRules, validate_attribute_value};
+        let allowed_values = ["red".to_string(), "green".to_string()];
+        let rules = AttributeValueRules {
+            regex: Some("[a-z]+"),
+            min_length: Some(3),
+            max_length: Some(5),
+            allowed_values: &allowed_values,
+            ..Default::default()
+        };
+        assert_eq!(validate_attribute_value("red", &rules), Ok(()));
+        assert_eq!(
+            validate_attribute_value("re", &rules),
+            Err("must be at least 3 characters long".to_string())
+        );
+        assert_eq!(
+            validate_attribute_value("yellow", &rules),
+            Err("must be at most 5 characters long".to_string())
+        );
+        assert_eq!(
+            validate_attribute_value("blue", &rules),
+            Err("must be one of: red, green".to_string())
+        );
+        assert_eq!(
+            validate_attribute_value(
+                "red1",
+                &AttributeValueRules {
+                    regex: Some("[a-z]+"),
+                    ..Default::default()
+                }
+            ),
+            Err("must match the pattern [a-z]+".to_string())
+        );
+    }
+
+    #[test]
+    fn test_attribute_value_range() {
+        use super::{AttributeValueRules, validate_attribute_value};
+        let rules = AttributeValueRules {
+            min_value: Some(1),
+            max_value: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(validate_attribute_value("10", &rules), Ok(()));
+        assert_eq!(
+            validate_attribute_value("11", &rules),
+            Err("must be at most 10".to_string())
+        );
+        assert_eq!(
+            validate_attribute_value("ten", &rules),
+            Err("must be an integer".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invalid_attribute_regex() {
+        assert!(super::validate_attribute_regex("[a-z").is_err());
+        assert_eq!(super::validate_attribute_regex("[a-z]+"), Ok(()));
+    }
 }
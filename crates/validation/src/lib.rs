@@ -1,3 +1,4 @@
 #![forbid(non_ascii_idents)]
 
 pub mod attributes;
+pub mod users;
@@ -0,0 +1,35 @@
+// Description of allowed characters. Intended for error messages.
+pub const USER_ID_ALLOWED_CHARACTERS_DESCRIPTION: &str =
+    "a-z, A-Z, 0-9, dot (.), underscore (_) and dash (-)";
+
+pub const USER_ID_MAX_LENGTH: usize = 64;
+
+/// Validates a user ID chosen by an end user (e.g. through an invitation). Admins are not
+/// restricted by this.
+pub fn validate_user_id(user_id: &str) -> Result<(), Vec<char>> {
+    let invalid_chars: Vec<char> = user_id
+        .chars()
+        .filter(|c| !(c.is_ascii_alphanumeric() || *c == '.' || *c == '_' || *c == '-'))
+        .collect();
+    if invalid_chars.is_empty() {
+        Ok(())
+    } else {
+        Err(invalid_chars)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_user_id() {
+        assert_eq!(validate_user_id("john.doe_01-a"), Ok(()));
+    }
+
+    #[test]
+    fn test_invalid_user_id_chars() {
+        assert_eq!(validate_user_id("john doe"), Err(vec![' ']));
+        assert_eq!(validate_user_id("jöhn@doe"), Err(vec!['ö', '@']));
+    }
+}
@@ -596,6 +596,7 @@ create_update_user() {
 }
 
 main() {
+  echo '[WARNING] bootstrap.sh is deprecated and will be removed in a future version, use `lldap bootstrap` instead: https://github.com/lldap/lldap/blob/main/docs/bootstrap.md' >&2
   check_install_dependencies
   check_required_env_vars
 
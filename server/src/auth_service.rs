@@ -21,8 +21,9 @@ use lldap_auth::{
     JWTClaims, access_control::ValidationResults, login, password_reset, registration,
 };
 use lldap_domain::types::{GroupDetails, GroupName, UserId};
-use lldap_domain_handlers::handler::{
-    BackendHandler, BindRequest, LoginHandler, UserRequestFilter,
+use lldap_domain_handlers::{
+    audit::AuditAction,
+    handler::{BackendHandler, BindRequest, LoginHandler, UserRequestFilter},
 };
 use lldap_domain_model::{error::DomainError, model::UserColumn};
 use lldap_opaque_handler::OpaqueHandler;
@@ -34,7 +35,7 @@ use std::{
     task::{Context, Poll},
 };
 use time::ext::NumericalDuration;
-use tracing::{debug, info, instrument, warn};
+use tracing::{Instrument, debug, info, instrument, warn};
 use uuid::Uuid;
 
 type Token<S> = jwt::Token<jwt::Header, JWTClaims, S>;
@@ -48,7 +49,7 @@ fn default_hash<T: Hash + ?Sized>(token: &T) -> u64 {
     s.finish()
 }
 
-async fn create_jwt<Handler: TcpBackendHandler>(
+pub(crate) async fn create_jwt<Handler: TcpBackendHandler>(
     handler: &Handler,
     key: &Hmac<Sha512>,
     user: &UserId,
@@ -149,18 +150,14 @@ where
         .unwrap_or_else(error_to_http_response)
 }
 
-#[instrument(skip_all, level = "debug")]
-async fn get_password_reset_step1<Backend>(
-    data: web::Data<AppState<Backend>>,
-    request: HttpRequest,
+/// Looks up the user by ID or email, and sends them a reset link if allowed.
+async fn send_password_reset_email<Backend>(
+    data: &AppState<Backend>,
+    user_string: &str,
 ) -> TcpResult<()>
 where
     Backend: TcpBackendHandler + BackendHandler + 'static,
 {
-    let user_string = request
-        .match_info()
-        .get("user_id")
-        .ok_or_else(|| TcpError::BadRequest("Missing user ID".to_string()))?;
     let user_results = data
         .get_readonly_handler()
         .list_users(
@@ -172,6 +169,7 @@ where
         )
         .await?;
     if user_results.is_empty() {
+        debug!("User not found");
         return Ok(());
     } else if user_results.len() > 1 {
         return Err(TcpError::InternalServerError(
@@ -179,6 +177,17 @@ where
         ));
     }
     let user = &user_results[0].user;
+    if !user.email_verified {
+        warn!(
+            "Not sending a password reset email to '{}': the email is not verified",
+            &user.user_id
+        );
+        return Ok(());
+    }
+    if !data.password_reset_limiters.per_user.check(&user.user_id) {
+        warn!("Too many password reset requests for '{}'", &user.user_id);
+        return Ok(());
+    }
     let token = match data
         .get_tcp_handler()
         .start_password_reset(&user.user_id)
@@ -194,17 +203,71 @@ where
         user.user_id.as_str(),
         user.email.as_str(),
         &token,
+        super::mail::get_user_locale(user, &data.mail_options()),
         &data.server_url,
-        &data.mail_options,
+        &data.mail_options(),
     )
     .await
     {
+        data.metrics.record_password_reset_email(false);
         warn!("Error sending email: {:#?}", e);
         info!("Reset token: {}", token);
         return Err(TcpError::InternalServerError(format!(
             "Could not send email: {e}"
         )));
     }
+    data.metrics.record_password_reset_email(true);
+    Ok(())
+}
+
+#[instrument(skip_all, level = "debug")]
+async fn get_password_reset_step1<Backend>(
+    data: web::Data<AppState<Backend>>,
+    request: HttpRequest,
+) -> TcpResult<()>
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    let user_string = request
+        .match_info()
+        .get("user_id")
+        .ok_or_else(|| TcpError::BadRequest("Missing user ID".to_string()))?
+        .to_owned();
+    // The forwarded IP is only used for the trusted proxies: otherwise it's under the control of
+    // the client.
+    if let Some(client_ip) = data.client_ip(&request)
+        && !data.password_reset_limiters.per_ip.check(&client_ip)
+    {
+        data.record_audit_event(
+            &request,
+            None,
+            AuditAction::RequestPasswordReset,
+            Some(user_string),
+            false,
+        )
+        .await;
+        return Err(TcpError::TooManyRequestsError(
+            "Too many password reset requests, try again later".to_owned(),
+        ));
+    }
+    data.record_audit_event(
+        &request,
+        None,
+        AuditAction::RequestPasswordReset,
+        Some(user_string.clone()),
+        true,
+    )
+    .await;
+    // The response is the same, and as fast, whether the user exists or not: the rest happens in
+    // the background.
+    actix_web::rt::spawn(
+        async move {
+            if let Err(e) = send_password_reset_email(&data, &user_string).await {
+                warn!("Error during password reset: {e:#}");
+            }
+        }
+        .instrument(tracing::Span::current()),
+    );
     Ok(())
 }
 
@@ -332,6 +395,97 @@ where
         .unwrap_or_else(error_to_http_response)
 }
 
+fn get_verify_token(request: &HttpRequest) -> TcpResult<String> {
+    if let Some(cookie) = request.cookie("token") {
+        return Ok(cookie.value().to_owned());
+    }
+    request
+        .headers()
+        .get(actix_http::header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(str::to_owned)
+        .ok_or_else(|| TcpError::UnauthorizedError("Missing token".to_string()))
+}
+
+/// Groups required by the caller, given as repeated `group` parameters or as a comma-separated
+/// `groups` parameter.
+fn get_verify_required_groups(request: &HttpRequest) -> Vec<GroupName> {
+    url::form_urlencoded::parse(request.query_string().as_bytes())
+        .filter(|(key, _)| key == "group" || key == "groups")
+        .flat_map(|(_, value)| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|g| !g.is_empty())
+                .map(GroupName::from)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Header values can only hold ASCII: the other characters, the control characters and `%` are
+/// percent-encoded, e.g. "Équipe" is sent as "%C3%89quipe".
+fn encode_header_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b' '..=b'~' if b != b'%' => char::from(b).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[instrument(skip_all, level = "debug")]
+async fn get_verify<Backend>(
+    data: web::Data<AppState<Backend>>,
+    request: HttpRequest,
+) -> TcpResult<HttpResponse>
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    let token = get_verify_token(&request)?;
+    let validation_result = check_if_token_is_valid(&data, &token)
+        .map_err(|e| TcpError::UnauthorizedError(e.to_string()))?;
+    let user_id = validation_result.user;
+    let handler = data.get_readonly_handler();
+    let user = handler.get_user_details(&user_id).await?;
+    let groups = handler.get_user_groups(&user_id).await?;
+    let group_names: HashSet<GroupName> = groups.into_iter().map(|g| g.display_name).collect();
+    if let Some(missing) = get_verify_required_groups(&request)
+        .into_iter()
+        .find(|g| !group_names.contains(g))
+    {
+        debug!(?user_id, ?missing, "User is missing a required group");
+        return Err(TcpError::ForbiddenError(format!(
+            "User is not a member of the group '{missing}'"
+        )));
+    }
+    let mut group_names: Vec<_> = group_names.into_iter().map(|g| g.into_string()).collect();
+    group_names.sort();
+    let mut response = HttpResponse::Ok();
+    response
+        .insert_header(("Remote-User", encode_header_value(user_id.as_str())))
+        .insert_header(("Remote-Groups", encode_header_value(&group_names.join(","))))
+        .insert_header(("Remote-Email", encode_header_value(user.email.as_str())));
+    if let Some(display_name) = user.display_name.as_deref().filter(|n| !n.is_empty()) {
+        response.insert_header(("Remote-Name", encode_header_value(display_name)));
+    }
+    Ok(response.finish())
+}
+
+async fn get_verify_handler<Backend>(
+    data: web::Data<AppState<Backend>>,
+    request: HttpRequest,
+) -> HttpResponse
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    get_verify(data, request)
+        .await
+        .unwrap_or_else(error_to_http_response)
+}
+
 pub(crate) fn error_to_api_response<T, E: Into<TcpError>>(error: E) -> ApiResult<T> {
     ApiResult::Right(error_to_http_response(error.into()))
 }
@@ -354,7 +508,7 @@ where
 }
 
 #[instrument(skip_all, level = "debug")]
-async fn get_login_successful_response<Backend>(
+pub(crate) async fn get_login_successful_response<Backend>(
     data: &web::Data<AppState<Backend>>,
     name: &UserId,
 ) -> TcpResult<HttpResponse>
@@ -397,6 +551,7 @@ where
 #[instrument(skip_all, level = "debug")]
 async fn opaque_login_finish<Backend>(
     data: web::Data<AppState<Backend>>,
+    http_request: HttpRequest,
     request: web::Json<login::ClientLoginFinishRequest>,
 ) -> TcpResult<HttpResponse>
 where
@@ -407,19 +562,35 @@ where
         .login_finish(request.into_inner())
         .await
     {
-        Ok(name) => get_login_successful_response(&data, &name).await,
-        Err(e) => Err(e.into()),
+        Ok(name) => {
+            data.record_audit_event(
+                &http_request,
+                Some(&name),
+                AuditAction::Login,
+                Some(name.to_string()),
+                true,
+            )
+            .await;
+            get_login_successful_response(&data, &name).await
+        }
+        Err(e) => {
+            // The user name is only known to the OPAQUE state, which failed.
+            data.record_audit_event(&http_request, None, AuditAction::Login, None, false)
+                .await;
+            Err(e.into())
+        }
     }
 }
 
 async fn opaque_login_finish_handler<Backend>(
     data: web::Data<AppState<Backend>>,
+    http_request: HttpRequest,
     request: web::Json<login::ClientLoginFinishRequest>,
 ) -> HttpResponse
 where
     Backend: TcpBackendHandler + BackendHandler + OpaqueHandler + 'static,
 {
-    opaque_login_finish(data, request)
+    opaque_login_finish(data, http_request, request)
         .await
         .unwrap_or_else(error_to_http_response)
 }
@@ -427,6 +598,7 @@ where
 #[instrument(skip_all, level = "debug")]
 async fn simple_login<Backend>(
     data: web::Data<AppState<Backend>>,
+    http_request: HttpRequest,
     request: web::Json<login::ClientSimpleLoginRequest>,
 ) -> TcpResult<HttpResponse>
 where
@@ -437,18 +609,28 @@ where
         name: username.clone(),
         password,
     };
-    data.get_login_handler().bind(bind_request).await?;
+    let result = data.get_login_handler().bind(bind_request).await;
+    data.record_audit_event(
+        &http_request,
+        Some(&username),
+        AuditAction::Login,
+        Some(username.to_string()),
+        result.is_ok(),
+    )
+    .await;
+    result?;
     get_login_successful_response(&data, &username).await
 }
 
 async fn simple_login_handler<Backend>(
     data: web::Data<AppState<Backend>>,
+    http_request: HttpRequest,
     request: web::Json<login::ClientSimpleLoginRequest>,
 ) -> HttpResponse
 where
     Backend: TcpBackendHandler + BackendHandler + OpaqueHandler + LoginHandler + 'static,
 {
-    simple_login(data, request)
+    simple_login(data, http_request, request)
         .await
         .unwrap_or_else(error_to_http_response)
 }
@@ -487,6 +669,15 @@ where
         .iter()
         .any(|g| g.display_name == "lldap_admin".into());
     if !validation_result.can_change_password(user_id, user_is_admin) {
+        // The change is recorded at the next step if it's allowed.
+        data.record_audit_event(
+            &request,
+            Some(&validation_result.user),
+            AuditAction::ChangePassword,
+            Some(user_id.to_string()),
+            false,
+        )
+        .await;
         return Err(TcpError::UnauthorizedError(
             "Not authorized to change the user's password".to_string(),
         ));
@@ -513,26 +704,45 @@ where
 
 #[instrument(skip_all, level = "debug")]
 async fn opaque_register_finish<Backend>(
+    http_request: HttpRequest,
     data: web::Data<AppState<Backend>>,
     request: web::Json<registration::ClientRegistrationFinishRequest>,
 ) -> TcpResult<HttpResponse>
 where
     Backend: TcpBackendHandler + BackendHandler + OpaqueHandler + 'static,
 {
-    data.get_opaque_handler()
+    use actix_web::FromRequest;
+    // This step is authenticated by the server data, the token only tells who made the change.
+    let actor = BearerAuth::extract(&http_request)
+        .await
+        .ok()
+        .and_then(|bearer| check_if_token_is_valid(&data, bearer.token()).ok())
+        .map(|validation_result| validation_result.user);
+    let result = data
+        .get_opaque_handler()
         .registration_finish(request.into_inner())
-        .await?;
+        .await;
+    data.record_audit_event(
+        &http_request,
+        actor.as_ref(),
+        AuditAction::ChangePassword,
+        result.as_ref().ok().map(ToString::to_string),
+        result.is_ok(),
+    )
+    .await;
+    result?;
     Ok(HttpResponse::Ok().finish())
 }
 
 async fn opaque_register_finish_handler<Backend>(
+    http_request: HttpRequest,
     data: web::Data<AppState<Backend>>,
     request: web::Json<registration::ClientRegistrationFinishRequest>,
 ) -> HttpResponse
 where
     Backend: TcpBackendHandler + BackendHandler + OpaqueHandler + 'static,
 {
-    opaque_register_finish(data, request)
+    opaque_register_finish(http_request, data, request)
         .await
         .unwrap_or_else(error_to_http_response)
 }
@@ -637,6 +847,7 @@ where
     .service(web::resource("/simple/login").route(web::post().to(simple_login_handler::<Backend>)))
     .service(web::resource("/refresh").route(web::get().to(get_refresh_handler::<Backend>)))
     .service(web::resource("/logout").route(web::get().to(get_logout_handler::<Backend>)))
+    .service(web::resource("/verify").to(get_verify_handler::<Backend>))
     .service(
         web::scope("/opaque/register")
             .wrap(CookieToHeaderTranslatorFactory)
@@ -648,6 +859,23 @@ where
                 web::resource("/finish")
                     .route(web::post().to(opaque_register_finish_handler::<Backend>)),
             ),
+    )
+    .service(
+        web::scope("/webauthn")
+            .wrap(CookieToHeaderTranslatorFactory)
+            .configure(crate::webauthn::configure_server::<Backend>),
+    )
+    .service(
+        web::scope("/invitation")
+            .wrap(CookieToHeaderTranslatorFactory)
+            .configure(crate::invitation::configure_server::<Backend>),
+    )
+    .service(
+        web::scope("/email")
+            .wrap(CookieToHeaderTranslatorFactory)
+            .configure(|cfg| {
+                crate::email_verification::configure_server::<Backend>(cfg, enable_password_reset)
+            }),
     );
     if enable_password_reset {
         cfg.service(
@@ -664,3 +892,219 @@ where
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        configuration::PasswordResetLimitOptions, rate_limit::PasswordResetLimiters,
+        tcp_server::tests::get_app_state,
+    };
+    use actix_web::{http::StatusCode, test::TestRequest};
+    use lldap_domain::requests::{CreateGroupRequest, CreateUserRequest, UpdateUserRequest};
+    use lldap_domain_handlers::handler::{GroupBackendHandler, UserBackendHandler};
+    use lldap_domain_model::model::{self, PasswordResetTokensColumn};
+    use lldap_sql_backend_handler::SqlBackendHandler;
+    use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+    use std::sync::Arc;
+
+    async fn get_token(data: &AppState<SqlBackendHandler>, user: &str) -> String {
+        create_jwt(
+            data.get_tcp_handler(),
+            &data.jwt_key,
+            &UserId::new(user),
+            HashSet::new(),
+        )
+        .await
+        .as_str()
+        .to_owned()
+    }
+
+    async fn verify(
+        data: &web::Data<AppState<SqlBackendHandler>>,
+        uri: &str,
+        token: Option<&str>,
+    ) -> HttpResponse {
+        let mut request = TestRequest::default().uri(uri);
+        if let Some(token) = token {
+            request = request.insert_header(("Authorization", format!("Bearer {token}")));
+        }
+        get_verify_handler(data.clone(), request.to_http_request()).await
+    }
+
+    fn get_header<'a>(response: &'a HttpResponse, name: &str) -> Option<&'a str> {
+        response
+            .headers()
+            .get(name)
+            .map(|value| value.to_str().unwrap())
+    }
+
+    #[test]
+    fn test_encode_header_value() {
+        assert_eq!(encode_header_value("bob@bob.bob"), "bob@bob.bob");
+        assert_eq!(encode_header_value("Équipe 100%"), "%C3%89quipe 100%25");
+        assert_eq!(encode_header_value("a\r\nb"), "a%0D%0Ab");
+    }
+
+    #[tokio::test]
+    async fn test_verify_allowed() {
+        let data = get_app_state().await;
+        let token = get_token(&data, "patrick").await;
+        let response = verify(&data, "/auth/verify", Some(&token)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(get_header(&response, "Remote-User"), Some("patrick"));
+        assert_eq!(
+            get_header(&response, "Remote-Groups"),
+            Some("Best Group,Worst Group")
+        );
+        assert_eq!(
+            get_header(&response, "Remote-Email"),
+            Some("patrick@bob.bob")
+        );
+        assert_eq!(
+            get_header(&response, "Remote-Name"),
+            Some("display patrick")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify_token_from_cookie() {
+        let data = get_app_state().await;
+        let token = get_token(&data, "bob").await;
+        let request = TestRequest::default()
+            .uri("/auth/verify")
+            .cookie(Cookie::new("token", token))
+            .to_http_request();
+        let response = get_verify_handler(data, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(get_header(&response, "Remote-User"), Some("bob"));
+    }
+
+    #[tokio::test]
+    async fn test_verify_missing_or_invalid_token() {
+        let data = get_app_state().await;
+        let response = verify(&data, "/auth/verify", None).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = verify(&data, "/auth/verify", Some("not.a.token")).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_verify_required_groups() {
+        let data = get_app_state().await;
+        let token = get_token(&data, "patrick").await;
+        for uri in [
+            "/auth/verify?group=Best%20Group",
+            "/auth/verify?group=Best%20Group&group=Worst%20Group",
+            "/auth/verify?groups=Best%20Group,%20Worst%20Group",
+        ] {
+            let response = verify(&data, uri, Some(&token)).await;
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+        }
+        let token = get_token(&data, "bob").await;
+        for uri in [
+            "/auth/verify?groups=Worst%20Group",
+            "/auth/verify?group=Best%20Group&group=Worst%20Group",
+            "/auth/verify?groups=unknown",
+        ] {
+            let response = verify(&data, uri, Some(&token)).await;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{uri}");
+            assert_eq!(get_header(&response, "Remote-User"), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_non_ascii_values() {
+        let data = get_app_state().await;
+        let handler = data.backend_handler.unsafe_get_handler();
+        let bob = UserId::new("bob");
+        handler
+            .update_user(UpdateUserRequest {
+                user_id: bob.clone(),
+                display_name: Some("Bób".to_owned()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let group = handler
+            .create_group(CreateGroupRequest {
+                display_name: "Équipe".into(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler.add_user_to_group(&bob, group).await.unwrap();
+        let token = get_token(&data, "bob").await;
+        let response = verify(&data, "/auth/verify?group=%C3%89quipe", Some(&token)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            get_header(&response, "Remote-Groups"),
+            Some("Best Group,%C3%89quipe")
+        );
+        assert_eq!(get_header(&response, "Remote-Name"), Some("B%C3%B3b"));
+    }
+
+    #[actix_rt::test]
+    async fn test_password_reset_limit_per_ip() {
+        let data = get_app_state().await;
+        let data = web::Data::new(AppState {
+            password_reset_limiters: Arc::new(PasswordResetLimiters::new(
+                &PasswordResetLimitOptions {
+                    per_ip: 2,
+                    ..Default::default()
+                },
+            )),
+            ..Arc::try_unwrap(data.into_inner()).ok().unwrap()
+        });
+        let request = |user: &str, peer: &str| {
+            TestRequest::default()
+                .param("user_id", user.to_owned())
+                .peer_addr(std::net::SocketAddr::new(peer.parse().unwrap(), 1234))
+                .to_http_request()
+        };
+        for user in ["bob", "patrick"] {
+            get_password_reset_step1(data.clone(), request(user, "1.2.3.4"))
+                .await
+                .unwrap();
+        }
+        assert!(matches!(
+            get_password_reset_step1(data.clone(), request("John", "1.2.3.4")).await,
+            Err(TcpError::TooManyRequestsError(_))
+        ));
+        get_password_reset_step1(data, request("John", "5.6.7.8"))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_password_reset_for_admin_created_user() {
+        let data = get_app_state().await;
+        let handler = data.backend_handler.unsafe_get_handler();
+        for (user_id, email_verified) in [("alice", true), ("mallory", false)] {
+            handler
+                .create_user(CreateUserRequest {
+                    user_id: UserId::new(user_id),
+                    email: format!("{user_id}@example.com").into(),
+                    email_verified,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        let count_reset_tokens = |user_id: &str| {
+            model::PasswordResetTokens::find()
+                .filter(PasswordResetTokensColumn::UserId.eq(UserId::new(user_id)))
+                .count(handler.pool())
+        };
+
+        // The reset link is generated, only sending it fails since there is no mail server.
+        assert!(matches!(
+            send_password_reset_email(&data, "alice@example.com").await,
+            Err(TcpError::InternalServerError(_))
+        ));
+        assert_eq!(count_reset_tokens("alice").await.unwrap(), 1);
+        // The address chosen by the user was never confirmed.
+        send_password_reset_email(&data, "mallory").await.unwrap();
+        assert_eq!(count_reset_tokens("mallory").await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,402 @@
+use crate::jwt_sql_tables::DbConnection;
+use anyhow::{Context, Result, ensure};
+use lldap_domain_model::model;
+use lldap_sql_backend_handler::{
+    sql_migrations::get_schema_version,
+    sql_tables::{LAST_SCHEMA_VERSION, PrivateKeyHash, get_private_key_info},
+};
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, DatabaseTransaction, DbBackend, EntityTrait,
+    IntoActiveModel, IsolationLevel, PaginatorTrait, Statement, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+/// Version of the archive format, changed when older versions can't read it anymore.
+const FORMAT_VERSION: u32 = 1;
+
+/// Rows inserted per statement, to stay below the limit of bound parameters.
+const INSERT_BATCH_SIZE: usize = 100;
+
+/// A copy of the database, independent of the database backend.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Backup {
+    pub format_version: u32,
+    pub lldap_version: String,
+    pub creation_date: chrono::NaiveDateTime,
+    /// The version of the database schema the tables follow.
+    pub schema_version: i16,
+    /// Hex-encoded hash of the server private key. The password files can only be used with
+    /// the same key.
+    pub private_key_hash: Option<String>,
+    pub tables: BackupTables,
+}
+
+/// The sessions, password reset tokens and pending webhook deliveries are not backed up.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct BackupTables {
+    pub user_attribute_schema: Vec<model::user_attribute_schema::Model>,
+    pub group_attribute_schema: Vec<model::group_attribute_schema::Model>,
+    pub user_object_classes: Vec<model::user_object_classes::Model>,
+    pub group_object_classes: Vec<model::group_object_classes::Model>,
+    pub users: Vec<model::users::Model>,
+    pub groups: Vec<model::groups::Model>,
+    pub memberships: Vec<model::memberships::Model>,
+    pub group_owner_users: Vec<model::group_owner_users::Model>,
+    pub group_owner_groups: Vec<model::group_owner_groups::Model>,
+    pub user_attribute_permissions: Vec<model::user_attribute_permissions::Model>,
+    pub group_attribute_permissions: Vec<model::group_attribute_permissions::Model>,
+    pub user_attribute_allowed_values: Vec<model::user_attribute_allowed_values::Model>,
+    pub group_attribute_allowed_values: Vec<model::group_attribute_allowed_values::Model>,
+    pub user_attributes: Vec<model::user_attributes::Model>,
+    pub group_attributes: Vec<model::group_attributes::Model>,
+    pub webauthn_credentials: Vec<model::webauthn_credentials::Model>,
+    pub invitations: Vec<model::invitations::Model>,
+    pub audit_log: Vec<model::audit_log::Model>,
+}
+
+fn to_hex(hash: &PrivateKeyHash) -> String {
+    hash.0.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Checks that the database is initialized and up to date, so that the models match the tables.
+pub async fn ensure_last_schema_version(pool: &DbConnection) -> Result<()> {
+    let schema_version = get_schema_version(pool)
+        .await
+        .context("The database is not initialized")?;
+    ensure!(
+        schema_version == LAST_SCHEMA_VERSION,
+        "The database schema is at version {}, but this version of LLDAP expects version {}. Start the server once to upgrade it.",
+        schema_version.0,
+        LAST_SCHEMA_VERSION.0
+    );
+    Ok(())
+}
+
+/// Starts a transaction in which all the tables are read from the same snapshot, even if the
+/// server is writing to them. SQLite and MySQL (InnoDB) transactions already do by default.
+pub async fn begin_snapshot(pool: &DbConnection) -> Result<DatabaseTransaction> {
+    let isolation_level = match pool.get_database_backend() {
+        DbBackend::Postgres => Some(IsolationLevel::RepeatableRead),
+        _ => None,
+    };
+    Ok(pool.begin_with_config(isolation_level, None).await?)
+}
+
+pub async fn make_backup(pool: &DbConnection) -> Result<Backup> {
+    ensure_last_schema_version(pool).await?;
+    let private_key_hash = get_private_key_info(pool)
+        .await?
+        .map(|info| to_hex(&info.private_key_hash));
+    let transaction = begin_snapshot(pool).await?;
+    let tables = BackupTables {
+        user_attribute_schema: model::UserAttributeSchema::find().all(&transaction).await?,
+        group_attribute_schema: model::GroupAttributeSchema::find()
+            .all(&transaction)
+            .await?,
+        user_object_classes: model::UserObjectClasses::find().all(&transaction).await?,
+        group_object_classes: model::GroupObjectClasses::find().all(&transaction).await?,
+        users: model::User::find().all(&transaction).await?,
+        groups: model::Group::find().all(&transaction).await?,
+        memberships: model::Membership::find().all(&transaction).await?,
+        group_owner_users: model::GroupOwnerUsers::find().all(&transaction).await?,
+        group_owner_groups: model::GroupOwnerGroups::find().all(&transaction).await?,
+        user_attribute_permissions: model::UserAttributePermissions::find()
+            .all(&transaction)
+            .await?,
+        group_attribute_permissions: model::GroupAttributePermissions::find()
+            .all(&transaction)
+            .await?,
+        user_attribute_allowed_values: model::UserAttributeAllowedValues::find()
+            .all(&transaction)
+            .await?,
+        group_attribute_allowed_values: model::GroupAttributeAllowedValues::find()
+            .all(&transaction)
+            .await?,
+        user_attributes: model::UserAttributes::find().all(&transaction).await?,
+        group_attributes: model::GroupAttributes::find().all(&transaction).await?,
+        webauthn_credentials: model::WebauthnCredentials::find().all(&transaction).await?,
+        invitations: model::Invitations::find().all(&transaction).await?,
+        audit_log: model::AuditLog::find().all(&transaction).await?,
+    };
+    transaction.commit().await?;
+    Ok(Backup {
+        format_version: FORMAT_VERSION,
+        lldap_version: env!("CARGO_PKG_VERSION").to_owned(),
+        creation_date: chrono::Utc::now().naive_utc(),
+        schema_version: LAST_SCHEMA_VERSION.0,
+        private_key_hash,
+        tables,
+    })
+}
+
+pub async fn insert_all<E>(transaction: &DatabaseTransaction, rows: Vec<E::Model>) -> Result<()>
+where
+    E: EntityTrait,
+    E::Model: IntoActiveModel<E::ActiveModel>,
+    E::ActiveModel: ActiveModelTrait<Entity = E>,
+{
+    for batch in rows.chunks(INSERT_BATCH_SIZE) {
+        E::insert_many(
+            batch
+                .iter()
+                .cloned()
+                .map(IntoActiveModel::into_active_model),
+        )
+        .exec_without_returning(transaction)
+        .await?;
+    }
+    Ok(())
+}
+
+/// The rows are inserted with their original IDs: the Postgres sequences have to catch up.
+pub async fn reset_postgres_sequences(transaction: &DatabaseTransaction) -> Result<()> {
+    for (table, column) in [
+        ("groups", "group_id"),
+        ("audit_log", "id"),
+        ("webhook_deliveries", "id"),
+    ] {
+        transaction
+            .execute(Statement::from_string(
+                DbBackend::Postgres,
+                format!(
+                    "SELECT setval(pg_get_serial_sequence('{table}', '{column}'), \
+                     COALESCE((SELECT MAX({column}) FROM {table}), 0) + 1, false)"
+                ),
+            ))
+            .await?;
+    }
+    Ok(())
+}
+
+/// Checks that the database has no users or groups, and removes the hardcoded attributes and
+/// object classes created with the schema, to be replaced with the copied ones.
+pub async fn clear_new_database(transaction: &DatabaseTransaction) -> Result<()> {
+    ensure!(
+        model::User::find().count(transaction).await? == 0
+            && model::Group::find().count(transaction).await? == 0,
+        "The database is not empty"
+    );
+    model::UserAttributeSchema::delete_many()
+        .exec(transaction)
+        .await?;
+    model::GroupAttributeSchema::delete_many()
+        .exec(transaction)
+        .await?;
+    model::UserObjectClasses::delete_many()
+        .exec(transaction)
+        .await?;
+    model::GroupObjectClasses::delete_many()
+        .exec(transaction)
+        .await?;
+    Ok(())
+}
+
+/// Restores the backup into an empty database, with the schema already created.
+///
+/// `private_key_hash` is the hash of the key of the server, which must be the one the backup
+/// was made with.
+pub async fn restore_backup(
+    pool: &DbConnection,
+    backup: Backup,
+    private_key_hash: &PrivateKeyHash,
+) -> Result<()> {
+    ensure!(
+        backup.format_version == FORMAT_VERSION,
+        "Unsupported backup format version {}, expected {}",
+        backup.format_version,
+        FORMAT_VERSION
+    );
+    ensure!(
+        backup.schema_version == LAST_SCHEMA_VERSION.0,
+        "The backup has the database schema version {}, but this version of LLDAP expects version {}. Restore it with LLDAP {}, then upgrade.",
+        backup.schema_version,
+        LAST_SCHEMA_VERSION.0,
+        backup.lldap_version
+    );
+    if let Some(backup_hash) = &backup.private_key_hash {
+        ensure!(
+            backup_hash == &to_hex(private_key_hash),
+            "The backup was made with a different server private key, the passwords would not work. Configure the same key_file or key_seed as the server that was backed up."
+        );
+    }
+    let transaction = pool.begin().await?;
+    clear_new_database(&transaction)
+        .await
+        .context("Only restore into a new database")?;
+    let tables = backup.tables;
+    insert_all::<model::UserAttributeSchema>(&transaction, tables.user_attribute_schema).await?;
+    insert_all::<model::GroupAttributeSchema>(&transaction, tables.group_attribute_schema).await?;
+    insert_all::<model::UserObjectClasses>(&transaction, tables.user_object_classes).await?;
+    insert_all::<model::GroupObjectClasses>(&transaction, tables.group_object_classes).await?;
+    insert_all::<model::User>(&transaction, tables.users).await?;
+    insert_all::<model::Group>(&transaction, tables.groups).await?;
+    insert_all::<model::Membership>(&transaction, tables.memberships).await?;
+    insert_all::<model::GroupOwnerUsers>(&transaction, tables.group_owner_users).await?;
+    insert_all::<model::GroupOwnerGroups>(&transaction, tables.group_owner_groups).await?;
+    insert_all::<model::UserAttributePermissions>(&transaction, tables.user_attribute_permissions)
+        .await?;
+    insert_all::<model::GroupAttributePermissions>(
+        &transaction,
+        tables.group_attribute_permissions,
+    )
+    .await?;
+    insert_all::<model::UserAttributeAllowedValues>(
+        &transaction,
+        tables.user_attribute_allowed_values,
+    )
+    .await?;
+    insert_all::<model::GroupAttributeAllowedValues>(
+        &transaction,
+        tables.group_attribute_allowed_values,
+    )
+    .await?;
+    insert_all::<model::UserAttributes>(&transaction, tables.user_attributes).await?;
+    insert_all::<model::GroupAttributes>(&transaction, tables.group_attributes).await?;
+    insert_all::<model::WebauthnCredentials>(&transaction, tables.webauthn_credentials).await?;
+    insert_all::<model::Invitations>(&transaction, tables.invitations).await?;
+    insert_all::<model::AuditLog>(&transaction, tables.audit_log).await?;
+    if transaction.get_database_backend() == DbBackend::Postgres {
+        reset_postgres_sequences(&transaction).await?;
+    }
+    transaction.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jwt_sql_tables;
+    use lldap_domain::types::{AttributeName, GroupId, GroupName, Serialized, UserId, Uuid};
+    use lldap_sql_backend_handler::sql_tables::{self, PrivateKeyInfo, PrivateKeyLocation};
+    use pretty_assertions::assert_eq;
+    use sea_orm::ActiveValue::Set;
+
+    async fn get_in_memory_db() -> DbConnection {
+        let mut sql_opt = sea_orm::ConnectOptions::new("sqlite::memory:".to_owned());
+        sql_opt.max_connections(1);
+        let sql_pool = sea_orm::Database::connect(sql_opt).await.unwrap();
+        sql_tables::init_table(&sql_pool).await.unwrap();
+        jwt_sql_tables::init_table(&sql_pool).await.unwrap();
+        sql_pool
+    }
+
+    async fn fill_db(pool: &DbConnection) {
+        let date = chrono::Utc::now().naive_utc();
+        model::users::ActiveModel {
+            user_id: Set(UserId::new("bob")),
+            email: Set("bob@example.com".into()),
+            lowercase_email: Set("bob@example.com".to_owned()),
+            display_name: Set(Some("Bob".to_owned())),
+            creation_date: Set(date),
+            password_hash: Set(Some(vec![1, 2, 3])),
+            totp_secret: Set(None),
+            mfa_type: Set(None),
+            uuid: Set(Uuid::from_name_and_date("bob", &date)),
+            modified_date: Set(date),
+            password_modified_date: Set(date),
+            email_verified: Set(true),
+        }
+        .insert(pool)
+        .await
+        .unwrap();
+        model::groups::ActiveModel {
+            group_id: Set(GroupId(7)),
+            display_name: Set(GroupName::from("Admins")),
+            lowercase_display_name: Set("admins".to_owned()),
+            creation_date: Set(date),
+            uuid: Set(Uuid::from_name_and_date("Admins", &date)),
+            modified_date: Set(date),
+            ..Default::default()
+        }
+        .insert(pool)
+        .await
+        .unwrap();
+        model::memberships::ActiveModel {
+            user_id: Set(UserId::new("bob")),
+            group_id: Set(GroupId(7)),
+        }
+        .insert(pool)
+        .await
+        .unwrap();
+        model::group_owner_users::ActiveModel {
+            group_id: Set(GroupId(7)),
+            user_id: Set(UserId::new("bob")),
+        }
+        .insert(pool)
+        .await
+        .unwrap();
+        model::user_attributes::ActiveModel {
+            user_id: Set(UserId::new("bob")),
+            attribute_name: Set(AttributeName::from("first_name")),
+            value: Set(Serialized::from("Bob")),
+        }
+        .insert(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_backup_and_restore() {
+        let private_key_hash = PrivateKeyHash([3; 32]);
+        let source = get_in_memory_db().await;
+        fill_db(&source).await;
+        sql_tables::set_private_key_info(
+            &source,
+            PrivateKeyInfo {
+                private_key_hash,
+                private_key_location: PrivateKeyLocation::Tests,
+            },
+        )
+        .await
+        .unwrap();
+        let backup = make_backup(&source).await.unwrap();
+        assert_eq!(backup.tables.users.len(), 1);
+        assert_eq!(backup.tables.memberships.len(), 1);
+        assert_eq!(backup.tables.group_owner_users.len(), 1);
+        assert!(!backup.tables.user_attribute_schema.is_empty());
+        let archive = serde_json::to_string(&backup).unwrap();
+
+        let destination = get_in_memory_db().await;
+        restore_backup(
+            &destination,
+            serde_json::from_str(&archive).unwrap(),
+            &private_key_hash,
+        )
+        .await
+        .unwrap();
+        let mut restored = make_backup(&destination).await.unwrap();
+        // Not copied by the restore.
+        restored.private_key_hash = backup.private_key_hash.clone();
+        restored.creation_date = backup.creation_date;
+        assert_eq!(restored, backup);
+
+        // Only into an empty database.
+        restore_backup(
+            &destination,
+            serde_json::from_str(&archive).unwrap(),
+            &private_key_hash,
+        )
+        .await
+        .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_restore_with_another_key() {
+        let source = get_in_memory_db().await;
+        sql_tables::set_private_key_info(
+            &source,
+            PrivateKeyInfo {
+                private_key_hash: PrivateKeyHash([3; 32]),
+                private_key_location: PrivateKeyLocation::Tests,
+            },
+        )
+        .await
+        .unwrap();
+        let backup = make_backup(&source).await.unwrap();
+        let destination = get_in_memory_db().await;
+        let error = restore_backup(&destination, backup, &PrivateKeyHash([4; 32]))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("different server private key"));
+    }
+}
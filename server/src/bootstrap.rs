@@ -0,0 +1,970 @@
+use crate::{
+    directory_plan::{
+        DesiredDirectory, DirectoryPlan, ExistingDirectory, GroupEntry, PlanOptions, UserEntry,
+        plan_directory,
+    },
+    import_export::parse_attributes,
+};
+use anyhow::{Context, Result, bail};
+use base64::Engine;
+use lldap_auth::import::{ConflictPolicy, ImportChange};
+use lldap_domain::{
+    requests::CreateAttributeRequest,
+    schema::{AttributeList, AttributeSchema, AttributeValidation},
+    types::{Attribute, AttributeName, AttributeType, GroupName, LdapObjectClass, UserId},
+};
+use lldap_domain_handlers::handler::{
+    BatchMode, BatchOperation, ReadSchemaBackendHandler, SchemaBackendHandler, UserBackendHandler,
+};
+use lldap_sql_backend_handler::{SqlBackendHandler, register_password, set_password_file};
+use lldap_validation::{
+    attributes::{ALLOWED_CHARACTERS_DESCRIPTION, validate_attribute_name},
+    users::USER_ID_MAX_LENGTH,
+};
+use secstr::SecUtf8;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+use tracing::instrument;
+
+/// The groups created by the server, which always exist.
+const BUILTIN_GROUPS: [&str; 3] = [
+    "lldap_admin",
+    "lldap_password_manager",
+    "lldap_strict_readonly",
+];
+
+/// The desired state of the directory, as described in the bootstrap file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DirectoryState {
+    #[serde(default)]
+    pub user_attributes: Vec<AttributeState>,
+    #[serde(default)]
+    pub group_attributes: Vec<AttributeState>,
+    #[serde(default)]
+    pub user_object_classes: Vec<String>,
+    #[serde(default)]
+    pub group_object_classes: Vec<String>,
+    #[serde(default)]
+    pub groups: Vec<GroupState>,
+    #[serde(default)]
+    pub users: Vec<UserState>,
+}
+
+/// A custom attribute of the schema.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AttributeState {
+    pub name: String,
+    pub attribute_type: AttributeType,
+    #[serde(default)]
+    pub is_list: bool,
+    #[serde(default = "default_true")]
+    pub is_visible: bool,
+    #[serde(default)]
+    pub is_editable: bool,
+    #[serde(default)]
+    pub validation: AttributeValidation,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroupState {
+    pub name: String,
+    #[serde(default)]
+    pub attributes: BTreeMap<String, AttributeValues>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserState {
+    pub id: String,
+    pub email: String,
+    pub display_name: Option<String>,
+    pub password: Option<SecUtf8>,
+    /// A file containing the password, relative to the bootstrap file.
+    pub password_file: Option<PathBuf>,
+    /// The password file of the user (the serialized OPAQUE registration, in base64), e.g. taken
+    /// from a backup. It only works with the same private key.
+    pub password_hash: Option<String>,
+    /// The names of the groups of the user.
+    #[serde(default)]
+    pub groups: Vec<String>,
+    #[serde(default)]
+    pub attributes: BTreeMap<String, AttributeValues>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ScalarValue {
+    Integer(i64),
+    String(String),
+}
+
+/// The value of an attribute, written as in the GraphQL API. Numbers are accepted as well.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum AttributeValues {
+    Single(ScalarValue),
+    List(Vec<ScalarValue>),
+}
+
+impl ScalarValue {
+    fn to_value_string(&self) -> String {
+        match self {
+            ScalarValue::Integer(i) => i.to_string(),
+            ScalarValue::String(s) => s.clone(),
+        }
+    }
+}
+
+impl AttributeValues {
+    fn to_strings(&self) -> Vec<String> {
+        match self {
+            AttributeValues::Single(value) => vec![value.to_value_string()],
+            AttributeValues::List(values) => values.iter().map(|v| v.to_value_string()).collect(),
+        }
+    }
+}
+
+/// Reads the state from a YAML or JSON file, depending on its extension.
+pub fn read_state(path: &Path) -> Result<DirectoryState> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("while reading {}", path.display()))?;
+    let mut state: DirectoryState = if path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"))
+    {
+        serde_json::from_str(&content)
+            .with_context(|| format!("while parsing {}", path.display()))?
+    } else {
+        serde_yaml::from_str(&content)
+            .with_context(|| format!("while parsing {}", path.display()))?
+    };
+    if let Some(directory) = path.parent() {
+        for user in &mut state.users {
+            if let Some(password_file) = &mut user.password_file {
+                *password_file = directory.join(&password_file);
+            }
+        }
+    }
+    Ok(state)
+}
+
+pub struct BootstrapOptions {
+    /// Only compute the changes, without applying them.
+    pub dry_run: bool,
+    /// Also delete the users, groups, memberships, attributes and object classes that are not in
+    /// the state.
+    pub prune: bool,
+    /// The admin user from the configuration, which is never deleted nor removed from the admin
+    /// group.
+    pub admin_user_id: UserId,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BootstrapReport {
+    pub changes: Vec<String>,
+    pub errors: Vec<String>,
+    pub applied: bool,
+}
+
+enum Password {
+    Clear(SecUtf8),
+    File(Vec<u8>),
+}
+
+#[derive(Default)]
+struct SchemaChanges {
+    new_attributes: Vec<CreateAttributeRequest>,
+    deleted_attributes: Vec<AttributeName>,
+    new_object_classes: Vec<LdapObjectClass>,
+    deleted_object_classes: Vec<LdapObjectClass>,
+}
+
+#[derive(Default)]
+struct BootstrapPlan {
+    changes: Vec<String>,
+    errors: Vec<String>,
+    user_schema: SchemaChanges,
+    group_schema: SchemaChanges,
+    /// The declared users, groups and memberships.
+    desired: DesiredDirectory,
+    /// The memberships removed when pruning.
+    removed_memberships: Vec<(String, BatchOperation)>,
+    /// The users and groups deleted when pruning.
+    deletions: Vec<(String, BatchOperation)>,
+    passwords: Vec<(UserId, Password)>,
+}
+
+/// Describes a change of the users and groups for the report. The unchanged ones are left out.
+fn describe_change(change: &ImportChange) -> Option<String> {
+    Some(match change {
+        ImportChange::CreateUser { user_id } => format!("Create user \"{user_id}\""),
+        ImportChange::UpdateUser { user_id, fields } => {
+            format!("Update user \"{user_id}\": {}", fields.join(", "))
+        }
+        ImportChange::CreateGroup { name } => format!("Create group \"{name}\""),
+        ImportChange::UpdateGroup { name, fields } => {
+            format!("Update group \"{name}\": {}", fields.join(", "))
+        }
+        ImportChange::AddMembership { user_id, group } => {
+            format!("Add user \"{user_id}\" to group \"{group}\"")
+        }
+        ImportChange::SkipUser { .. } | ImportChange::SkipGroup { .. } => return None,
+    })
+}
+
+/// Converts the attributes of a user or a group to the types of the schema.
+fn parse_state_attributes(
+    object: &str,
+    kind: &str,
+    attributes: &BTreeMap<String, AttributeValues>,
+    schema: &AttributeList,
+    errors: &mut Vec<String>,
+) -> Vec<Attribute> {
+    let mut known = BTreeMap::new();
+    for (name, values) in attributes {
+        if schema
+            .get_attribute_schema(&AttributeName::from(name.as_str()))
+            .is_some()
+        {
+            known.insert(name.to_ascii_lowercase(), values.to_strings());
+        } else {
+            errors.push(format!(
+                "{object}: unknown attribute \"{name}\", declare it in {kind}_attributes"
+            ));
+        }
+    }
+    parse_attributes(object, &known, schema, None, errors)
+}
+
+/// Plans the changes to the custom attributes and object classes of the users or the groups.
+/// Returns the attributes they will have.
+fn plan_schema(
+    kind: &str,
+    existing_attributes: &AttributeList,
+    existing_object_classes: &[LdapObjectClass],
+    declared_attributes: &[AttributeState],
+    declared_object_classes: &[String],
+    prune: bool,
+    plan: &mut BootstrapPlan,
+) -> (AttributeList, SchemaChanges) {
+    let mut changes = SchemaChanges::default();
+    let mut target = AttributeList {
+        attributes: existing_attributes
+            .attributes
+            .iter()
+            .filter(|a| {
+                !prune
+                    || a.is_hardcoded
+                    || declared_attributes
+                        .iter()
+                        .any(|d| AttributeName::from(d.name.as_str()) == a.name)
+            })
+            .cloned()
+            .collect(),
+    };
+    let mut seen = BTreeSet::new();
+    for declared in declared_attributes {
+        let object = format!("The {kind} attribute \"{}\"", declared.name);
+        let name = AttributeName::from(declared.name.as_str());
+        // Checked first: some built-in names wouldn't be valid for a custom attribute.
+        if existing_attributes
+            .get_attribute_schema(&name)
+            .is_some_and(|a| a.is_hardcoded)
+        {
+            plan.errors
+                .push(format!("{object} is built-in, it can't be declared"));
+            continue;
+        }
+        if let Err(invalid_chars) = validate_attribute_name(&declared.name) {
+            plan.errors.push(format!(
+                "{object} has invalid characters {invalid_chars:?}, only {ALLOWED_CHARACTERS_DESCRIPTION} are allowed"
+            ));
+            continue;
+        }
+        if let Err(e) = declared.validation.validate_rules(declared.attribute_type) {
+            plan.errors.push(format!("{object} has invalid rules: {e}"));
+            continue;
+        }
+        if !seen.insert(name.clone()) {
+            plan.errors
+                .push(format!("{object} is declared more than once"));
+            continue;
+        }
+        match existing_attributes.get_attribute_schema(&name) {
+            Some(existing) => {
+                if existing.attribute_type != declared.attribute_type
+                    || existing.is_list != declared.is_list
+                    || existing.is_visible != declared.is_visible
+                    || existing.is_editable != declared.is_editable
+                    || existing.validation != declared.validation
+                {
+                    plan.errors.push(format!(
+                        "{object} already exists with different settings, it can't be changed in place"
+                    ));
+                }
+            }
+            None => {
+                plan.changes
+                    .push(format!("Add {kind} attribute \"{}\"", declared.name));
+                changes.new_attributes.push(CreateAttributeRequest {
+                    name: name.clone(),
+                    attribute_type: declared.attribute_type,
+                    is_list: declared.is_list,
+                    is_visible: declared.is_visible,
+                    is_editable: declared.is_editable,
+                    validation: declared.validation.clone(),
+                });
+                target.attributes.push(AttributeSchema {
+                    name,
+                    attribute_type: declared.attribute_type,
+                    is_list: declared.is_list,
+                    is_visible: declared.is_visible,
+                    is_editable: declared.is_editable,
+                    is_hardcoded: false,
+                    is_readonly: false,
+                    permissions: Default::default(),
+                    validation: declared.validation.clone(),
+                });
+            }
+        }
+    }
+    if prune {
+        for existing in &existing_attributes.attributes {
+            if !existing.is_hardcoded && !seen.contains(&existing.name) {
+                plan.changes
+                    .push(format!("Delete {kind} attribute \"{}\"", existing.name));
+                changes.deleted_attributes.push(existing.name.clone());
+            }
+        }
+    }
+    let declared_object_classes: BTreeSet<LdapObjectClass> = declared_object_classes
+        .iter()
+        .map(|c| LdapObjectClass::from(c.as_str()))
+        .collect();
+    for object_class in &declared_object_classes {
+        if !existing_object_classes.contains(object_class) {
+            plan.changes
+                .push(format!("Add {kind} object class \"{object_class}\""));
+            changes.new_object_classes.push(object_class.clone());
+        }
+    }
+    if prune {
+        for object_class in existing_object_classes {
+            if !declared_object_classes.contains(object_class) {
+                plan.changes
+                    .push(format!("Delete {kind} object class \"{object_class}\""));
+                changes.deleted_object_classes.push(object_class.clone());
+            }
+        }
+    }
+    (target, changes)
+}
+
+/// Declares the groups, and the missing built-in ones. Returns the names of the groups that will
+/// exist.
+fn declare_groups(
+    groups: &[GroupState],
+    existing: &ExistingDirectory,
+    schema: &AttributeList,
+    prune: bool,
+    plan: &mut BootstrapPlan,
+) -> BTreeSet<GroupName> {
+    let mut declared = BTreeSet::new();
+    for group in groups {
+        let object = format!("Group \"{}\"", group.name);
+        let name = GroupName::from(group.name.as_str());
+        if !declared.insert(name.clone()) {
+            plan.errors
+                .push(format!("{object} is declared more than once"));
+            continue;
+        }
+        let attributes = parse_state_attributes(
+            &object,
+            "group",
+            &group.attributes,
+            schema,
+            &mut plan.errors,
+        );
+        plan.desired.groups.push(GroupEntry { name, attributes });
+    }
+    for builtin in BUILTIN_GROUPS {
+        let name = GroupName::from(builtin);
+        // The existing built-in groups are left as they are.
+        if declared.insert(name.clone()) && !existing.groups.contains_key(&name) {
+            plan.desired.groups.push(GroupEntry {
+                name,
+                attributes: Vec::new(),
+            });
+        }
+    }
+    if prune {
+        for (name, group) in &existing.groups {
+            if !declared.contains(name) {
+                plan.deletions.push((
+                    format!("Delete group \"{name}\""),
+                    BatchOperation::DeleteGroup(group.id),
+                ));
+            }
+        }
+    }
+    declared
+}
+
+fn read_password(user: &UserState) -> Result<Option<Password>> {
+    Ok(
+        match (&user.password, &user.password_file, &user.password_hash) {
+            (None, None, None) => None,
+            (Some(password), None, None) => Some(Password::Clear(password.clone())),
+            (None, Some(path), None) => {
+                let password = std::fs::read_to_string(path)
+                    .with_context(|| format!("while reading {}", path.display()))?;
+                Some(Password::Clear(SecUtf8::from(
+                    password.trim_end_matches(['\r', '\n']),
+                )))
+            }
+            (None, None, Some(hash)) => Some(Password::File(
+                base64::engine::general_purpose::STANDARD
+                    .decode(hash)
+                    .context("invalid password_hash")?,
+            )),
+            _ => bail!("only one of password, password_file and password_hash can be set"),
+        },
+    )
+}
+
+async fn is_password_up_to_date(
+    handler: &SqlBackendHandler,
+    user_id: &UserId,
+    password: &Password,
+) -> Result<bool> {
+    Ok(match password {
+        Password::Clear(password) => {
+            handler
+                .password_matches(user_id, password.unsecure())
+                .await?
+        }
+        Password::File(password_file) => {
+            handler
+                .get_password_file_for_user(user_id.clone())
+                .await?
+                .as_ref()
+                == Some(password_file)
+        }
+    })
+}
+
+async fn declare_users(
+    handler: &SqlBackendHandler,
+    users: &[UserState],
+    existing: &ExistingDirectory,
+    schema: &AttributeList,
+    groups: &BTreeSet<GroupName>,
+    options: &BootstrapOptions,
+    plan: &mut BootstrapPlan,
+) -> Result<()> {
+    let mut declared = BTreeSet::new();
+    for user in users {
+        let object = format!("User \"{}\"", user.id);
+        if user.id.len() > USER_ID_MAX_LENGTH {
+            plan.errors.push(format!(
+                "{object}: the ID is longer than {USER_ID_MAX_LENGTH} characters"
+            ));
+            continue;
+        }
+        let user_id = UserId::new(&user.id);
+        if !declared.insert(user_id.clone()) {
+            plan.errors
+                .push(format!("{object} is declared more than once"));
+            continue;
+        }
+        if !user.email.contains('@') {
+            plan.errors.push(format!("{object}: invalid email address"));
+        }
+        let attributes =
+            parse_state_attributes(&object, "user", &user.attributes, schema, &mut plan.errors);
+        let user_groups: BTreeSet<GroupName> = user
+            .groups
+            .iter()
+            .map(|g| GroupName::from(g.as_str()))
+            .collect();
+        for group in &user_groups {
+            if !groups.contains(group) {
+                plan.errors
+                    .push(format!("{object}: the group \"{group}\" is not declared"));
+            }
+        }
+        let password = match read_password(user) {
+            Ok(password) => password,
+            Err(e) => {
+                plan.errors.push(format!("{object}: {e:#}"));
+                None
+            }
+        };
+        plan.desired.memberships.extend(
+            user_groups
+                .iter()
+                .map(|group| (user_id.clone(), group.clone())),
+        );
+        plan.desired.users.push(UserEntry {
+            user_id: user_id.clone(),
+            email: Some(user.email.clone()),
+            display_name: user.display_name.clone(),
+            attributes,
+        });
+        let Some(existing_user) = existing.users.get(&user_id) else {
+            if let Some(password) = password {
+                plan.passwords.push((user_id, password));
+            }
+            continue;
+        };
+        if options.prune {
+            for group in existing_user.groups.as_deref().unwrap_or_default() {
+                let is_admin_membership = user_id == options.admin_user_id
+                    && group.display_name == GroupName::from(BUILTIN_GROUPS[0]);
+                if !user_groups.contains(&group.display_name) && !is_admin_membership {
+                    plan.removed_memberships.push((
+                        format!(
+                            "Remove user \"{user_id}\" from group \"{}\"",
+                            group.display_name
+                        ),
+                        BatchOperation::RemoveUserFromGroup(user_id.clone(), group.group_id),
+                    ));
+                }
+            }
+        }
+        if let Some(password) = password
+            && !is_password_up_to_date(handler, &user_id, &password).await?
+        {
+            plan.passwords.push((user_id, password));
+        }
+    }
+    if options.prune {
+        for user_id in existing.users.keys() {
+            if !declared.contains(user_id) && *user_id != options.admin_user_id {
+                plan.deletions.push((
+                    format!("Delete user \"{user_id}\""),
+                    BatchOperation::DeleteUser(user_id.clone()),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn apply_schema_additions(
+    handler: &SqlBackendHandler,
+    plan: &mut BootstrapPlan,
+) -> Result<()> {
+    for request in plan.user_schema.new_attributes.drain(..) {
+        handler.add_user_attribute(request).await?;
+    }
+    for request in plan.group_schema.new_attributes.drain(..) {
+        handler.add_group_attribute(request).await?;
+    }
+    for object_class in &plan.user_schema.new_object_classes {
+        handler.add_user_object_class(object_class).await?;
+    }
+    for object_class in &plan.group_schema.new_object_classes {
+        handler.add_group_object_class(object_class).await?;
+    }
+    Ok(())
+}
+
+async fn apply_schema_deletions(handler: &SqlBackendHandler, plan: &BootstrapPlan) -> Result<()> {
+    for name in &plan.user_schema.deleted_attributes {
+        handler.delete_user_attribute(name).await?;
+    }
+    for name in &plan.group_schema.deleted_attributes {
+        handler.delete_group_attribute(name).await?;
+    }
+    for object_class in &plan.user_schema.deleted_object_classes {
+        handler.delete_user_object_class(object_class).await?;
+    }
+    for object_class in &plan.group_schema.deleted_object_classes {
+        handler.delete_group_object_class(object_class).await?;
+    }
+    Ok(())
+}
+
+/// Applies the plan. All the changes to the users, groups and memberships are made in a single
+/// transaction, but the schema and the passwords can't be part of it: the new attributes and
+/// object classes are added before that transaction, the passwords are set and the pruned
+/// attributes and object classes are deleted after it.
+///
+/// This is not atomic: if a step fails, the previous ones are kept. Running the bootstrap again
+/// is safe, it only applies what is still missing.
+async fn apply_plan(
+    handler: &SqlBackendHandler,
+    mut plan: BootstrapPlan,
+    operations: Vec<BatchOperation>,
+) -> Result<()> {
+    apply_schema_additions(handler, &mut plan).await?;
+    if !operations.is_empty() {
+        let result = handler
+            .apply_batch(operations, BatchMode::AllOrNothing)
+            .await?;
+        if !result.committed {
+            let error = result
+                .results
+                .into_iter()
+                .find_map(Result::err)
+                .map(|e| e.to_string())
+                .unwrap_or_default();
+            bail!("{error}, none of the changes to the users and groups were saved");
+        }
+    }
+    for (user_id, password) in plan.passwords.drain(..) {
+        match password {
+            Password::Clear(password) => {
+                register_password(handler, user_id.clone(), &password).await
+            }
+            Password::File(password_file) => {
+                set_password_file(handler, user_id.clone(), &password_file).await
+            }
+        }
+        .with_context(|| format!("while setting the password of \"{user_id}\""))?;
+    }
+    apply_schema_deletions(handler, &plan).await
+}
+
+/// Brings the database to the state: computes the differences, and applies them unless there
+/// is an error or it's a dry run.
+#[instrument(skip_all, level = "debug")]
+pub async fn bootstrap(
+    handler: &SqlBackendHandler,
+    state: DirectoryState,
+    options: &BootstrapOptions,
+) -> Result<BootstrapReport> {
+    let schema = ReadSchemaBackendHandler::get_schema(handler).await?;
+    let existing = ExistingDirectory::load(handler).await?;
+    let mut plan = BootstrapPlan::default();
+    let (user_attributes, user_schema) = plan_schema(
+        "user",
+        &schema.user_attributes,
+        &schema.extra_user_object_classes,
+        &state.user_attributes,
+        &state.user_object_classes,
+        options.prune,
+        &mut plan,
+    );
+    plan.user_schema = user_schema;
+    let (group_attributes, group_schema) = plan_schema(
+        "group",
+        &schema.group_attributes,
+        &schema.extra_group_object_classes,
+        &state.group_attributes,
+        &state.group_object_classes,
+        options.prune,
+        &mut plan,
+    );
+    plan.group_schema = group_schema;
+    let groups = declare_groups(
+        &state.groups,
+        &existing,
+        &group_attributes,
+        options.prune,
+        &mut plan,
+    );
+    declare_users(
+        handler,
+        &state.users,
+        &existing,
+        &user_attributes,
+        &groups,
+        options,
+        &mut plan,
+    )
+    .await?;
+    let DirectoryPlan {
+        changes,
+        errors,
+        mut operations,
+    } = plan_directory(
+        std::mem::take(&mut plan.desired),
+        &existing,
+        PlanOptions {
+            policy: ConflictPolicy::Update,
+            prune: options.prune,
+        },
+    );
+    plan.errors.extend(errors);
+    plan.changes
+        .extend(changes.iter().filter_map(describe_change));
+    for (change, operation) in plan
+        .removed_memberships
+        .drain(..)
+        .chain(std::mem::take(&mut plan.deletions))
+    {
+        plan.changes.push(change);
+        operations.push(operation);
+    }
+    plan.changes.extend(
+        plan.passwords
+            .iter()
+            .map(|(user_id, _)| format!("Set the password of user \"{user_id}\"")),
+    );
+    let mut report = BootstrapReport {
+        changes: std::mem::take(&mut plan.changes),
+        errors: std::mem::take(&mut plan.errors),
+        applied: false,
+    };
+    if report.errors.is_empty() && !options.dry_run && !report.changes.is_empty() {
+        apply_plan(handler, plan, operations).await?;
+        report.applied = true;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lldap_auth::opaque::server::generate_random_private_key;
+    use lldap_domain_handlers::handler::{
+        GroupListerBackendHandler, UserBackendHandler, UserListerBackendHandler,
+    };
+    use lldap_sql_backend_handler::test_fixture::{
+        get_initialized_db, insert_group, insert_membership, insert_user_no_password,
+    };
+    use pretty_assertions::assert_eq;
+
+    async fn get_handler() -> SqlBackendHandler {
+        let handler =
+            SqlBackendHandler::new(generate_random_private_key(), get_initialized_db().await);
+        insert_user_no_password(&handler, "admin").await;
+        let admin_group = insert_group(&handler, "lldap_admin").await;
+        insert_membership(&handler, admin_group, "admin").await;
+        handler
+    }
+
+    fn options(dry_run: bool, prune: bool) -> BootstrapOptions {
+        BootstrapOptions {
+            dry_run,
+            prune,
+            admin_user_id: UserId::new("admin"),
+        }
+    }
+
+    const STATE: &str = r#"
+user_attributes:
+  - name: nicknames
+    attribute_type: String
+    is_list: true
+group_attributes:
+  - name: code
+    attribute_type: Integer
+user_object_classes: [mailAccount]
+groups:
+  - name: family
+    attributes:
+      code: 12
+users:
+  - id: bob
+    email: bob@example.com
+    display_name: Bob
+    password: bob_password
+    groups: [family]
+    attributes:
+      first_name: Bob
+      nicknames: [b, bobby]
+"#;
+
+    async fn run(
+        handler: &SqlBackendHandler,
+        state: &str,
+        options: &BootstrapOptions,
+    ) -> BootstrapReport {
+        bootstrap(handler, serde_yaml::from_str(state).unwrap(), options)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_dry_run_then_apply() {
+        let handler = get_handler().await;
+        let expected_changes = vec![
+            "Add user attribute \"nicknames\"",
+            "Add user object class \"mailAccount\"",
+            "Add group attribute \"code\"",
+            "Create group \"family\"",
+            "Create group \"lldap_password_manager\"",
+            "Create group \"lldap_strict_readonly\"",
+            "Create user \"bob\"",
+            "Add user \"bob\" to group \"family\"",
+            "Set the password of user \"bob\"",
+        ];
+        let report = run(&handler, STATE, &options(true, false)).await;
+        assert_eq!(report.errors, Vec::<String>::new());
+        assert_eq!(report.changes, expected_changes);
+        assert!(!report.applied);
+        assert!(
+            UserBackendHandler::get_user_details(&handler, &UserId::new("bob"))
+                .await
+                .is_err()
+        );
+
+        let report = run(&handler, STATE, &options(false, false)).await;
+        assert_eq!(report.changes, expected_changes);
+        assert!(report.applied);
+        let bob = UserBackendHandler::get_user_details(&handler, &UserId::new("bob"))
+            .await
+            .unwrap();
+        assert_eq!(bob.display_name.as_deref(), Some("Bob"));
+        assert_eq!(bob.attributes.len(), 2);
+        assert!(
+            handler
+                .password_matches(&UserId::new("bob"), "bob_password")
+                .await
+                .unwrap()
+        );
+        let groups = UserBackendHandler::get_user_groups(&handler, &UserId::new("bob"))
+            .await
+            .unwrap();
+        assert_eq!(groups.len(), 1);
+
+        // The second run has nothing left to do.
+        let report = run(&handler, STATE, &options(false, false)).await;
+        assert_eq!(report, BootstrapReport::default());
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_prune() {
+        let handler = get_handler().await;
+        run(&handler, STATE, &options(false, false)).await;
+        let state = r#"
+groups:
+  - name: Family
+users:
+  - id: bob
+    email: bob@example.com
+    password: bob_password
+    attributes:
+      first_name: Bob
+"#;
+        let report = run(&handler, state, &options(false, true)).await;
+        assert_eq!(report.errors, Vec::<String>::new());
+        assert_eq!(
+            report.changes,
+            vec![
+                "Delete user attribute \"nicknames\"",
+                "Delete user object class \"mailAccount\"",
+                "Delete group attribute \"code\"",
+                "Update group \"Family\": display_name, -code",
+                "Update user \"bob\": display_name, -nicknames",
+                "Remove user \"bob\" from group \"family\"",
+            ]
+        );
+        let schema = ReadSchemaBackendHandler::get_schema(&handler)
+            .await
+            .unwrap();
+        assert!(schema.extra_user_object_classes.is_empty());
+        // The admin is kept, even if it isn't declared.
+        assert_eq!(
+            handler
+                .list_users(None, false)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|u| u.user.user_id.to_string())
+                .collect::<Vec<_>>(),
+            vec!["admin", "bob"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_errors_apply_nothing() {
+        let handler = get_handler().await;
+        let state = r#"
+user_attributes:
+  - name: first_name
+    attribute_type: String
+users:
+  - id: bob
+    email: bob.example.com
+    groups: [missing]
+    password: a
+    password_hash: b
+    attributes:
+      shoe_size: 42
+"#;
+        let report = run(&handler, state, &options(false, false)).await;
+        assert_eq!(
+            report.errors,
+            vec![
+                "The user attribute \"first_name\" is built-in, it can't be declared",
+                "User \"bob\": invalid email address",
+                "User \"bob\": unknown attribute \"shoe_size\", declare it in user_attributes",
+                "User \"bob\": the group \"missing\" is not declared",
+                "User \"bob\": only one of password, password_file and password_hash can be set",
+            ]
+        );
+        assert!(!report.applied);
+        assert_eq!(
+            GroupListerBackendHandler::list_groups(&handler, None)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_rerun_after_failure() {
+        let handler = get_handler().await;
+        let state = |second_code: i64| {
+            format!(
+                r#"
+group_attributes:
+  - name: code
+    attribute_type: Integer
+    validation:
+      is_unique: true
+groups:
+  - name: family
+    attributes:
+      code: 1
+  - name: friends
+    attributes:
+      code: {second_code}
+"#
+            )
+        };
+        // The second group can't have the same code, so none of the groups are created.
+        bootstrap(
+            &handler,
+            serde_yaml::from_str(&state(1)).unwrap(),
+            &options(false, false),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            GroupListerBackendHandler::list_groups(&handler, None)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // The attribute was already added, the rest is applied.
+        let report = run(&handler, &state(2), &options(false, false)).await;
+        assert_eq!(
+            report.changes,
+            vec![
+                "Create group \"family\"",
+                "Create group \"friends\"",
+                "Create group \"lldap_password_manager\"",
+                "Create group \"lldap_strict_readonly\"",
+            ]
+        );
+        assert!(report.applied);
+    }
+}
@@ -1,4 +1,4 @@
-use std::str::FromStr;
+use std::{path::PathBuf, str::FromStr};
 
 use clap::{Parser, builder::EnumValueParser};
 use lettre::message::Mailbox;
@@ -6,7 +6,10 @@ use serde::{Deserialize, Serialize};
 use strum::{EnumString, IntoStaticStr};
 use url::Url;
 
-use crate::database_string::DatabaseUrl;
+use crate::{
+    database_string::DatabaseUrl, import_export::ExportFormat, mail_templates::EmailTemplate,
+};
+use lldap_auth::import::{ConflictPolicy, ImportFormat};
 
 // Can be deserialized from either a boolean or a string, to facilitate migration.
 #[derive(Copy, Clone, Debug, Serialize, Default, EnumString, IntoStaticStr)]
@@ -100,6 +103,27 @@ pub enum Command {
     /// Create database schema.
     #[clap(name = "create_schema")]
     CreateSchema(RunOpts),
+    /// Render an email template with sample data.
+    #[clap(name = "preview_email")]
+    PreviewEmail(PreviewEmailOpts),
+    /// Save the content of the database to a file, while the server is running.
+    #[clap(name = "backup")]
+    Backup(BackupOpts),
+    /// Load a backup into a new, empty database.
+    #[clap(name = "restore")]
+    Restore(RestoreOpts),
+    /// Copy the database to a new database, e.g. from SQLite to PostgreSQL or MySQL.
+    #[clap(name = "migrate-db")]
+    MigrateDb(MigrateDbOpts),
+    /// Export the users and groups to a CSV or LDIF file.
+    #[clap(name = "export")]
+    Export(ExportOpts),
+    /// Import users and groups from a CSV or LDIF file.
+    #[clap(name = "import")]
+    Import(ImportOpts),
+    /// Bring the users, groups and schema to the state described in a YAML or JSON file.
+    #[clap(name = "bootstrap")]
+    Bootstrap(BootstrapOpts),
 }
 
 #[derive(Debug, Parser, Clone)]
@@ -192,6 +216,189 @@ pub struct TestEmailOpts {
     pub smtp_opts: SmtpOpts,
 }
 
+#[derive(Debug, Parser, Clone)]
+pub struct PreviewEmailOpts {
+    #[clap(flatten)]
+    pub general_config: GeneralConfigOpts,
+
+    /// The email to render.
+    #[clap(long, value_enum)]
+    pub template: EmailTemplate,
+
+    /// The language to render the email in, e.g. "fr" or "de-CH".
+    #[clap(long)]
+    pub locale: Option<String>,
+
+    #[clap(flatten)]
+    pub smtp_opts: SmtpOpts,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct BackupOpts {
+    #[clap(flatten)]
+    pub general_config: GeneralConfigOpts,
+
+    /// Database connection URL
+    #[clap(short, long, env = "LLDAP_DATABASE_URL")]
+    pub database_url: Option<DatabaseUrl>,
+
+    /// File to write the backup to.
+    #[clap(short, long)]
+    pub output_file: PathBuf,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct RestoreOpts {
+    #[clap(flatten)]
+    pub general_config: GeneralConfigOpts,
+
+    /// Path to the file that contains the private server key. It must be the key of the server
+    /// that was backed up.
+    #[clap(long, env = "LLDAP_SERVER_KEY_FILE")]
+    pub server_key_file: Option<String>,
+
+    /// Seed used to generate the private server key.
+    /// Takes precedence over `server_key_file`.
+    #[clap(long, env = "LLDAP_SERVER_KEY_SEED")]
+    pub server_key_seed: Option<String>,
+
+    /// Database connection URL
+    #[clap(short, long, env = "LLDAP_DATABASE_URL")]
+    pub database_url: Option<DatabaseUrl>,
+
+    /// Backup file to restore.
+    #[clap(short, long)]
+    pub input_file: PathBuf,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct MigrateDbOpts {
+    #[clap(flatten)]
+    pub general_config: GeneralConfigOpts,
+
+    /// URL of the database to copy. It is not modified.
+    #[clap(long)]
+    pub from: DatabaseUrl,
+
+    /// URL of the new database. It must be empty.
+    #[clap(long)]
+    pub to: DatabaseUrl,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct ExportOpts {
+    #[clap(flatten)]
+    pub general_config: GeneralConfigOpts,
+
+    /// Database connection URL
+    #[clap(short, long, env = "LLDAP_DATABASE_URL")]
+    pub database_url: Option<DatabaseUrl>,
+
+    /// What to export.
+    #[clap(long, value_enum)]
+    pub format: ExportFormat,
+
+    /// File to write the export to.
+    #[clap(short, long)]
+    pub output_file: PathBuf,
+}
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum ImportFileFormat {
+    Csv,
+    Ldif,
+}
+
+impl From<ImportFileFormat> for ImportFormat {
+    fn from(format: ImportFileFormat) -> Self {
+        match format {
+            ImportFileFormat::Csv => ImportFormat::Csv,
+            ImportFileFormat::Ldif => ImportFormat::Ldif,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum ImportConflictPolicy {
+    /// Leave the existing users and groups as they are.
+    Skip,
+    /// Overwrite the fields and attributes of the file.
+    Update,
+    /// Import nothing if a user or group already exists.
+    Fail,
+}
+
+impl From<ImportConflictPolicy> for ConflictPolicy {
+    fn from(policy: ImportConflictPolicy) -> Self {
+        match policy {
+            ImportConflictPolicy::Skip => ConflictPolicy::Skip,
+            ImportConflictPolicy::Update => ConflictPolicy::Update,
+            ImportConflictPolicy::Fail => ConflictPolicy::Fail,
+        }
+    }
+}
+
+fn parse_attribute_mapping(mapping: &str) -> Result<(String, String), String> {
+    mapping
+        .split_once('=')
+        .map(|(from, to)| (from.trim().to_owned(), to.trim().to_owned()))
+        .ok_or_else(|| format!("expected `file_attribute=schema_attribute`, got `{mapping}`"))
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct ImportOpts {
+    #[clap(flatten)]
+    pub general_config: GeneralConfigOpts,
+
+    /// Database connection URL
+    #[clap(short, long, env = "LLDAP_DATABASE_URL")]
+    pub database_url: Option<DatabaseUrl>,
+
+    /// File to import.
+    #[clap(short, long)]
+    pub input_file: PathBuf,
+
+    /// Format of the file. CSV files contain either users or groups.
+    #[clap(long, value_enum)]
+    pub format: ImportFileFormat,
+
+    /// What to do with the users and groups that already exist.
+    #[clap(long, value_enum, default_value = "skip")]
+    pub conflict_policy: ImportConflictPolicy,
+
+    /// Only print the changes, without applying them.
+    #[clap(long)]
+    pub dry_run: bool,
+
+    /// Rename an attribute of the file to an attribute of the schema, e.g.
+    /// `--map telephoneNumber=phone`. Map an attribute to nothing to ignore it. Can be repeated.
+    #[clap(long = "map", value_parser = parse_attribute_mapping)]
+    pub attribute_mapping: Vec<(String, String)>,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct BootstrapOpts {
+    #[clap(flatten)]
+    pub general_config: GeneralConfigOpts,
+
+    /// Database connection URL
+    #[clap(short, long, env = "LLDAP_DATABASE_URL")]
+    pub database_url: Option<DatabaseUrl>,
+
+    /// File describing the users, groups and schema, in YAML or JSON (with a .json extension).
+    #[clap(short, long)]
+    pub input_file: PathBuf,
+
+    /// Only print the changes, without applying them.
+    #[clap(long)]
+    pub dry_run: bool,
+
+    /// Also delete the users, groups, memberships, attributes and object classes that are not in
+    /// the file. The admin user and the built-in groups are always kept.
+    #[clap(long)]
+    pub prune: bool,
+}
+
 #[derive(Debug, Parser, Clone)]
 #[clap(next_help_heading = Some("LDAPS"))]
 pub struct LdapsOpts {
@@ -258,6 +465,14 @@ pub struct SmtpOpts {
 
     #[clap(long, env = "LLDAP_SMTP_OPTIONS__SMTP_ENCRYPTION", value_parser = EnumValueParser::<SmtpEncryption>::new(), ignore_case = true)]
     pub smtp_encryption: Option<SmtpEncryption>,
+
+    /// Directory with custom email templates.
+    #[clap(long, env = "LLDAP_SMTP_OPTIONS__TEMPLATES_DIR")]
+    pub smtp_templates_dir: Option<PathBuf>,
+
+    /// User attribute holding the preferred language for emails.
+    #[clap(long, env = "LLDAP_SMTP_OPTIONS__LOCALE_ATTRIBUTE")]
+    pub smtp_locale_attribute: Option<String>,
 }
 
 #[derive(Debug, Parser, Clone)]
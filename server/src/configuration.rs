@@ -1,6 +1,7 @@
 use crate::{
     cli::{
-        GeneralConfigOpts, HealthcheckOpts, LdapsOpts, RunOpts, SmtpEncryption, SmtpOpts,
+        BackupOpts, BootstrapOpts, ExportOpts, GeneralConfigOpts, HealthcheckOpts, ImportOpts,
+        LdapsOpts, MigrateDbOpts, PreviewEmailOpts, RestoreOpts, RunOpts, SmtpEncryption, SmtpOpts,
         TestEmailOpts, TrueFalseAlways,
     },
     database_string::DatabaseUrl,
@@ -16,12 +17,14 @@ use lldap_auth::opaque::{
     server::{ServerSetup, generate_random_private_key},
 };
 use lldap_domain::types::{AttributeName, UserId};
+use lldap_domain_handlers::events::DirectoryEvent;
 use lldap_sql_backend_handler::sql_tables::{
     ConfigLocation, PrivateKeyHash, PrivateKeyInfo, PrivateKeyLocation,
 };
 use secstr::SecUtf8;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use std::net::IpAddr;
 use std::path::PathBuf;
 use url::Url;
 
@@ -32,6 +35,17 @@ use url::Url;
 #[display("{_0}")]
 pub struct Mailbox(pub lettre::message::Mailbox);
 
+/// How the logs are written to the standard output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable, with the nested spans printed as a tree.
+    #[default]
+    Tree,
+    /// One JSON object per line, for log collectors.
+    Json,
+}
+
 #[derive(Clone, derive_more::Debug, Deserialize, Serialize, derive_builder::Builder)]
 #[builder(pattern = "owned")]
 pub struct MailOptions {
@@ -51,6 +65,12 @@ pub struct MailOptions {
     pub password: SecUtf8,
     #[builder(default = "SmtpEncryption::Tls")]
     pub smtp_encryption: SmtpEncryption,
+    /// Directory with the email templates that replace the built-in ones.
+    #[builder(default)]
+    pub templates_dir: Option<PathBuf>,
+    /// User attribute holding the language of the emails sent to them, e.g. "fr" or "de-CH".
+    #[builder(default)]
+    pub locale_attribute: Option<AttributeName>,
     /// Deprecated.
     #[debug(skip)]
     #[serde(skip)]
@@ -83,6 +103,82 @@ impl std::default::Default for LdapsOptions {
     }
 }
 
+/// Serves the web interface and the API over HTTPS, on `http_port`.
+#[derive(Clone, Debug, Deserialize, Serialize, derive_builder::Builder)]
+#[builder(pattern = "owned")]
+pub struct HttpsOptions {
+    #[builder(default = "false")]
+    pub enabled: bool,
+    #[builder(default = r#"String::from("cert.pem")"#)]
+    pub cert_file: String,
+    #[builder(default = r#"String::from("key.pem")"#)]
+    pub key_file: String,
+}
+
+impl std::default::Default for HttpsOptions {
+    fn default() -> Self {
+        HttpsOptionsBuilder::default().build().unwrap()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, derive_builder::Builder)]
+#[builder(pattern = "owned")]
+pub struct OpenTelemetryOptions {
+    #[builder(default = "false")]
+    pub enabled: bool,
+    /// OTLP/HTTP traces endpoint of the collector.
+    #[builder(default = r#"String::from("http://localhost:4318/v1/traces")"#)]
+    pub endpoint: String,
+    #[builder(default = r#"String::from("lldap")"#)]
+    pub service_name: String,
+    /// Fraction of the new traces to export, between 0 and 1. Traces started upstream (with a
+    /// `traceparent` header) follow the decision of the caller.
+    #[builder(default = "1.0")]
+    pub sampling_ratio: f64,
+}
+
+impl std::default::Default for OpenTelemetryOptions {
+    fn default() -> Self {
+        OpenTelemetryOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/// The limits on the password reset requests, per user and per client IP.
+#[derive(Clone, Debug, Deserialize, Serialize, derive_builder::Builder)]
+#[builder(pattern = "owned")]
+pub struct PasswordResetLimitOptions {
+    #[builder(default = "3")]
+    pub per_user: usize,
+    #[builder(default = "10")]
+    pub per_ip: usize,
+    /// The requests older than that are not counted anymore.
+    #[builder(default = "60")]
+    pub window_minutes: u64,
+}
+
+impl std::default::Default for PasswordResetLimitOptions {
+    fn default() -> Self {
+        PasswordResetLimitOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/// The Prometheus metrics, served on `/metrics` of the HTTP server.
+#[derive(Clone, Debug, Deserialize, Serialize, derive_builder::Builder)]
+#[builder(pattern = "owned")]
+pub struct MetricsOptions {
+    #[builder(default = "false")]
+    pub enabled: bool,
+    /// If set, the scrapers must send it as a bearer token.
+    #[builder(default = "None")]
+    pub token: Option<SecUtf8>,
+}
+
+impl std::default::Default for MetricsOptions {
+    fn default() -> Self {
+        MetricsOptionsBuilder::default().build().unwrap()
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, derive_builder::Builder)]
 #[builder(pattern = "owned")]
 pub struct HealthcheckOptions {
@@ -102,6 +198,24 @@ impl std::default::Default for HealthcheckOptions {
 #[debug(r#""{_0}""#)]
 pub struct HttpUrl(pub Url);
 
+/// An endpoint notified of the changes to the directory.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WebhookOptions {
+    pub url: Url,
+    /// The types of events to send, e.g. "user_created". All of them if empty.
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Key to sign the payloads with (HMAC-SHA256).
+    #[serde(default)]
+    pub secret: Option<SecUtf8>,
+}
+
+impl WebhookOptions {
+    pub fn wants(&self, event_type: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|e| e == event_type)
+    }
+}
+
 #[derive(Clone, Deserialize, Serialize, derive_builder::Builder, derive_more::Debug)]
 #[builder(pattern = "owned", build_fn(name = "private_build"))]
 pub struct Configuration {
@@ -135,6 +249,14 @@ pub struct Configuration {
     pub ignored_group_attributes: Vec<AttributeName>,
     #[builder(default = "false")]
     pub verbose: bool,
+    #[builder(default)]
+    pub log_format: LogFormat,
+    /// Reload the configuration when the configuration file changes, as on SIGHUP.
+    #[builder(default = "false")]
+    pub watch_config_file: bool,
+    /// Log a line for each LDAP operation, like OpenLDAP's "stats" log level.
+    #[builder(default = "false")]
+    pub ldap_access_log: bool,
     #[builder(default = r#"String::from("server_key")"#)]
     pub key_file: String,
     // We want an Option to see whether there is a value or not, since the value is printed as
@@ -147,14 +269,34 @@ pub struct Configuration {
     pub smtp_options: MailOptions,
     #[builder(default)]
     pub ldaps_options: LdapsOptions,
+    #[builder(default)]
+    pub https_options: HttpsOptions,
     #[builder(default = r#"HttpUrl(Url::parse("http://localhost").unwrap())"#)]
     pub http_url: HttpUrl,
+    /// The reverse proxies in front of the HTTP server: for their requests, the client IP is
+    /// read from the `X-Forwarded-For` header.
+    #[builder(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+    /// Audit log entries older than this are deleted. 0 keeps them forever.
+    #[builder(default = "365")]
+    pub audit_log_retention_days: u32,
+    #[builder(default)]
+    pub webhooks: Vec<WebhookOptions>,
+    /// Finished webhook deliveries older than this are deleted. 0 keeps them forever.
+    #[builder(default = "30")]
+    pub webhook_delivery_retention_days: u32,
     #[debug(skip)]
     #[serde(skip)]
     #[builder(field(private), default = "None")]
     server_setup: Option<ServerSetupConfig>,
     #[builder(default)]
     pub healthcheck_options: HealthcheckOptions,
+    #[builder(default)]
+    pub opentelemetry_options: OpenTelemetryOptions,
+    #[builder(default)]
+    pub metrics_options: MetricsOptions,
+    #[builder(default)]
+    pub password_reset_limits: PasswordResetLimitOptions,
 }
 
 impl std::default::Default for Configuration {
@@ -429,6 +571,48 @@ impl TopLevelCommandOpts for TestEmailOpts {
     }
 }
 
+impl TopLevelCommandOpts for PreviewEmailOpts {
+    fn general_config(&self) -> &GeneralConfigOpts {
+        &self.general_config
+    }
+}
+
+impl TopLevelCommandOpts for BackupOpts {
+    fn general_config(&self) -> &GeneralConfigOpts {
+        &self.general_config
+    }
+}
+
+impl TopLevelCommandOpts for RestoreOpts {
+    fn general_config(&self) -> &GeneralConfigOpts {
+        &self.general_config
+    }
+}
+
+impl TopLevelCommandOpts for MigrateDbOpts {
+    fn general_config(&self) -> &GeneralConfigOpts {
+        &self.general_config
+    }
+}
+
+impl TopLevelCommandOpts for ExportOpts {
+    fn general_config(&self) -> &GeneralConfigOpts {
+        &self.general_config
+    }
+}
+
+impl TopLevelCommandOpts for ImportOpts {
+    fn general_config(&self) -> &GeneralConfigOpts {
+        &self.general_config
+    }
+}
+
+impl TopLevelCommandOpts for BootstrapOpts {
+    fn general_config(&self) -> &GeneralConfigOpts {
+        &self.general_config
+    }
+}
+
 impl ConfigOverrider for RunOpts {
     fn override_config(&self, config: &mut Configuration) {
         self.general_config.override_config(config);
@@ -475,6 +659,70 @@ impl ConfigOverrider for TestEmailOpts {
     }
 }
 
+impl ConfigOverrider for PreviewEmailOpts {
+    fn override_config(&self, config: &mut Configuration) {
+        self.general_config.override_config(config);
+        self.smtp_opts.override_config(config);
+    }
+}
+
+impl ConfigOverrider for BackupOpts {
+    fn override_config(&self, config: &mut Configuration) {
+        self.general_config.override_config(config);
+        self.database_url
+            .as_ref()
+            .inspect(|&database_url| config.database_url = database_url.clone());
+    }
+}
+
+impl ConfigOverrider for RestoreOpts {
+    fn override_config(&self, config: &mut Configuration) {
+        self.general_config.override_config(config);
+        self.server_key_file
+            .as_ref()
+            .inspect(|path| config.key_file = path.to_string());
+        self.server_key_seed
+            .as_ref()
+            .inspect(|seed| config.key_seed = Some(SecUtf8::from(seed.as_str())));
+        self.database_url
+            .as_ref()
+            .inspect(|&database_url| config.database_url = database_url.clone());
+    }
+}
+
+impl ConfigOverrider for MigrateDbOpts {
+    fn override_config(&self, config: &mut Configuration) {
+        self.general_config.override_config(config);
+    }
+}
+
+impl ConfigOverrider for ExportOpts {
+    fn override_config(&self, config: &mut Configuration) {
+        self.general_config.override_config(config);
+        self.database_url
+            .as_ref()
+            .inspect(|&database_url| config.database_url = database_url.clone());
+    }
+}
+
+impl ConfigOverrider for ImportOpts {
+    fn override_config(&self, config: &mut Configuration) {
+        self.general_config.override_config(config);
+        self.database_url
+            .as_ref()
+            .inspect(|&database_url| config.database_url = database_url.clone());
+    }
+}
+
+impl ConfigOverrider for BootstrapOpts {
+    fn override_config(&self, config: &mut Configuration) {
+        self.general_config.override_config(config);
+        self.database_url
+            .as_ref()
+            .inspect(|&database_url| config.database_url = database_url.clone());
+    }
+}
+
 impl ConfigOverrider for LdapsOpts {
     fn override_config(&self, config: &mut Configuration) {
         self.ldaps_enabled
@@ -533,6 +781,14 @@ impl ConfigOverrider for SmtpOpts {
         self.smtp_tls_required
             .inspect(|&tls_required| config.smtp_options.tls_required = Some(tls_required));
 
+        self.smtp_templates_dir
+            .as_ref()
+            .inspect(|&dir| config.smtp_options.templates_dir = Some(dir.clone()));
+
+        self.smtp_locale_attribute.as_ref().inspect(|&attribute| {
+            config.smtp_options.locale_attribute = Some(AttributeName::from(attribute.as_str()))
+        });
+
         self.smtp_enable_password_reset
             .inspect(|&enable_password_reset| {
                 config.smtp_options.enable_password_reset = enable_password_reset;
@@ -631,15 +887,11 @@ fn generate_jwt_sample_error() -> String {
     )
 }
 
-pub fn init<C>(overrides: C) -> Result<Configuration>
+/// Reads the configuration file and the environment, and applies the command line overrides.
+fn extract<C>(overrides: &C) -> Result<(Configuration, Figment)>
 where
     C: TopLevelCommandOpts + ConfigOverrider,
 {
-    println!(
-        "Loading configuration from {}",
-        &overrides.general_config().config_file
-    );
-
     let ignore_keys = ["key_file", "cert_file"];
     let env_variable_provider =
         || FileAdapter::wrap(Env::prefixed("LLDAP_").split("__")).ignore(&ignore_keys);
@@ -657,6 +909,53 @@ where
         println!("Configuration: {:#?}", &config);
     }
     check_for_unexpected_env_variables(env_variable_provider());
+    Ok((config, figment_config))
+}
+
+fn validate(config: &Configuration) -> Result<()> {
+    config
+        .jwt_secret
+        .as_ref()
+        .ok_or_else(|| anyhow!("{}", generate_jwt_sample_error()))?;
+    if !(0.0..=1.0).contains(&config.opentelemetry_options.sampling_ratio) {
+        bail!(
+            "opentelemetry_options.sampling_ratio must be between 0 and 1, got {}",
+            config.opentelemetry_options.sampling_ratio
+        );
+    }
+    if config.password_reset_limits.window_minutes == 0 {
+        bail!("password_reset_limits.window_minutes must be positive");
+    }
+    for (i, webhook) in config.webhooks.iter().enumerate() {
+        // Each endpoint gets its own delivery task, which finds its calls by URL.
+        if config.webhooks[..i].iter().any(|w| w.url == webhook.url) {
+            bail!("The webhook {} is configured twice", webhook.url);
+        }
+        if let Some(event) = webhook
+            .events
+            .iter()
+            .find(|e| !DirectoryEvent::EVENT_TYPES.contains(&e.as_str()))
+        {
+            bail!(
+                "Unknown event type \"{event}\" for the webhook {}, expected one of {:?}",
+                webhook.url,
+                DirectoryEvent::EVENT_TYPES
+            );
+        }
+    }
+    Ok(())
+}
+
+pub fn init<C>(overrides: C) -> Result<Configuration>
+where
+    C: TopLevelCommandOpts + ConfigOverrider,
+{
+    println!(
+        "Loading configuration from {}",
+        &overrides.general_config().config_file
+    );
+
+    let (mut config, figment_config) = extract(&overrides)?;
     config.server_setup = Some(get_server_setup(
         &config.key_file,
         config
@@ -666,10 +965,7 @@ where
             .unwrap_or_default(),
         figment_config,
     )?);
-    config
-        .jwt_secret
-        .as_ref()
-        .ok_or_else(|| anyhow!("{}", generate_jwt_sample_error()))?;
+    validate(&config)?;
     if config.smtp_options.tls_required.is_some() {
         println!(
             "DEPRECATED: smtp_options.tls_required field is deprecated, it never did anything. You can replace it with smtp_options.smtp_encryption."
@@ -678,6 +974,18 @@ where
     Ok(config)
 }
 
+/// Reads the configuration again, for a running server.
+///
+/// The private key is not loaded: it cannot change without a restart.
+pub fn reload<C>(overrides: &C) -> Result<Configuration>
+where
+    C: TopLevelCommandOpts + ConfigOverrider,
+{
+    let (config, _) = extract(overrides)?;
+    validate(&config)?;
+    Ok(config)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -875,4 +1183,95 @@ mod tests {
             Ok(())
         });
     }
+
+    #[test]
+    fn check_webhooks_config() {
+        Jail::expect_with(|jail| {
+            jail.clear_env();
+            jail.set_env("LLDAP_JWT_SECRET", "secret");
+            jail.create_file(
+                "lldap_config.toml",
+                r#"
+[[webhooks]]
+url = "http://localhost:8080/hook"
+events = ["user_created", "user_added_to_group"]
+secret = "hook secret"
+
+[[webhooks]]
+url = "http://localhost:9090/all"
+"#,
+            )?;
+            let config = init(default_run_opts()).unwrap();
+            assert_eq!(config.webhooks.len(), 2);
+            assert!(config.webhooks[0].wants("user_created"));
+            assert!(!config.webhooks[0].wants("group_deleted"));
+            assert!(config.webhooks[1].wants("group_deleted"));
+            jail.create_file(
+                "lldap_config.toml",
+                r#"
+[[webhooks]]
+url = "http://localhost:8080/hook"
+events = ["user_renamed"]
+"#,
+            )?;
+            let error_message = init(default_run_opts()).unwrap_err().to_string();
+            assert!(
+                error_message.contains(r#"Unknown event type "user_renamed""#),
+                "{error_message}"
+            );
+            jail.create_file(
+                "lldap_config.toml",
+                r#"
+[[webhooks]]
+url = "http://localhost:8080/hook"
+
+[[webhooks]]
+url = "http://localhost:8080/hook"
+events = ["user_created"]
+"#,
+            )?;
+            let error_message = init(default_run_opts()).unwrap_err().to_string();
+            assert!(
+                error_message.contains("is configured twice"),
+                "{error_message}"
+            );
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn check_password_reset_limits_config() {
+        Jail::expect_with(|jail| {
+            jail.clear_env();
+            jail.set_env("LLDAP_JWT_SECRET", "secret");
+            jail.set_env("LLDAP_PASSWORD_RESET_LIMITS__PER_IP", "50");
+            jail.create_file(
+                "lldap_config.toml",
+                r#"
+trusted_proxies = ["10.0.0.1", "::1"]
+
+[password_reset_limits]
+window_minutes = 10
+"#,
+            )?;
+            let config = init(default_run_opts()).unwrap();
+            assert_eq!(
+                config.trusted_proxies,
+                vec![
+                    "10.0.0.1".parse::<IpAddr>().unwrap(),
+                    "::1".parse::<IpAddr>().unwrap()
+                ]
+            );
+            assert_eq!(config.password_reset_limits.per_ip, 50);
+            assert_eq!(config.password_reset_limits.per_user, 3);
+            assert_eq!(config.password_reset_limits.window_minutes, 10);
+            jail.set_env("LLDAP_PASSWORD_RESET_LIMITS__WINDOW_MINUTES", "0");
+            let error_message = init(default_run_opts()).unwrap_err().to_string();
+            assert!(
+                error_message.contains("window_minutes must be positive"),
+                "{error_message}"
+            );
+            Ok(())
+        });
+    }
 }
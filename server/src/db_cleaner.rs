@@ -1,8 +1,9 @@
-use crate::sql_tables::DbConnection;
+use crate::{sql_tables::DbConnection, webhooks::DeliveryStatus};
 use actix::prelude::{Actor, AsyncContext, Context};
 use cron::Schedule;
 use lldap_domain_model::model::{
-    self, JwtRefreshStorageColumn, JwtStorageColumn, PasswordResetTokensColumn,
+    self, AuditLogColumn, InvitationsColumn, JwtRefreshStorageColumn, JwtStorageColumn,
+    PasswordResetTokensColumn, WebhookDeliveriesColumn,
 };
 use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 use std::{str::FromStr, time::Duration};
@@ -12,6 +13,10 @@ use tracing::{error, info, instrument};
 pub struct Scheduler {
     schedule: Schedule,
     sql_pool: DbConnection,
+    /// How long to keep the audit log entries, 0 to keep them forever.
+    audit_log_retention_days: u32,
+    /// How long to keep the finished webhook deliveries, 0 to keep them forever.
+    webhook_delivery_retention_days: u32,
 }
 
 // Provide Actor implementation for our actor
@@ -32,13 +37,27 @@ impl Actor for Scheduler {
 }
 
 impl Scheduler {
-    pub fn new(cron_expression: &str, sql_pool: DbConnection) -> Self {
+    pub fn new(
+        cron_expression: &str,
+        sql_pool: DbConnection,
+        audit_log_retention_days: u32,
+        webhook_delivery_retention_days: u32,
+    ) -> Self {
         let schedule = Schedule::from_str(cron_expression).unwrap();
-        Self { schedule, sql_pool }
+        Self {
+            schedule,
+            sql_pool,
+            audit_log_retention_days,
+            webhook_delivery_retention_days,
+        }
     }
 
     fn schedule_task(&self, ctx: &mut Context<Self>) {
-        let future = actix::fut::wrap_future::<_, Self>(Self::cleanup_db(self.sql_pool.clone()));
+        let future = actix::fut::wrap_future::<_, Self>(Self::cleanup_db(
+            self.sql_pool.clone(),
+            self.audit_log_retention_days,
+            self.webhook_delivery_retention_days,
+        ));
         ctx.spawn(future);
 
         ctx.run_later(self.duration_until_next(), move |this, ctx| {
@@ -47,7 +66,11 @@ impl Scheduler {
     }
 
     #[instrument(skip_all)]
-    async fn cleanup_db(sql_pool: DbConnection) {
+    async fn cleanup_db(
+        sql_pool: DbConnection,
+        audit_log_retention_days: u32,
+        webhook_delivery_retention_days: u32,
+    ) {
         if let Err(e) = model::JwtRefreshStorage::delete_many()
             .filter(JwtRefreshStorageColumn::ExpiryDate.lt(chrono::Utc::now().naive_utc()))
             .exec(&sql_pool)
@@ -69,6 +92,36 @@ impl Scheduler {
         {
             error!("DB error while cleaning up password reset tokens: {}", e);
         };
+        if let Err(e) = model::Invitations::delete_many()
+            .filter(InvitationsColumn::ExpiryDate.lt(chrono::Utc::now().naive_utc()))
+            .exec(&sql_pool)
+            .await
+        {
+            error!("DB error while cleaning up invitations: {}", e);
+        };
+        if audit_log_retention_days > 0 {
+            let oldest = chrono::Utc::now().naive_utc()
+                - chrono::Duration::days(audit_log_retention_days.into());
+            if let Err(e) = model::AuditLog::delete_many()
+                .filter(AuditLogColumn::Timestamp.lt(oldest))
+                .exec(&sql_pool)
+                .await
+            {
+                error!("DB error while cleaning up the audit log: {}", e);
+            };
+        }
+        if webhook_delivery_retention_days > 0 {
+            let oldest = chrono::Utc::now().naive_utc()
+                - chrono::Duration::days(webhook_delivery_retention_days.into());
+            if let Err(e) = model::WebhookDeliveries::delete_many()
+                .filter(WebhookDeliveriesColumn::Status.ne(DeliveryStatus::Pending.as_str()))
+                .filter(WebhookDeliveriesColumn::CreationDate.lt(oldest))
+                .exec(&sql_pool)
+                .await
+            {
+                error!("DB error while cleaning up the webhook deliveries: {}", e);
+            };
+        }
     }
 
     fn duration_until_next(&self) -> Duration {
@@ -0,0 +1,298 @@
+use crate::import_export::{DirectoryRecords, GroupRecord, UserRecord, map_attribute_name};
+use anyhow::{Context, Result, bail};
+use lldap_domain::types::{Attribute, Group, UserAndGroups};
+use lldap_graphql_server::query::attribute::serialize_attribute_to_graphql;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Separates the values of the lists, e.g. the groups of a user, in a single cell.
+pub const LIST_SEPARATOR: char = ';';
+
+fn join_values<'a>(values: impl IntoIterator<Item = &'a str>) -> String {
+    values
+        .into_iter()
+        .collect::<Vec<_>>()
+        .join(&LIST_SEPARATOR.to_string())
+}
+
+fn split_values(cell: &str) -> Vec<String> {
+    cell.split(LIST_SEPARATOR)
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// The names of all the attributes, to use as columns.
+fn attribute_columns<'a>(attributes: impl Iterator<Item = &'a Attribute>) -> Vec<String> {
+    attributes
+        .map(|a| a.name.to_string())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+fn attribute_cells(attributes: &[Attribute], columns: &[String]) -> Vec<String> {
+    columns
+        .iter()
+        .map(|column| {
+            attributes
+                .iter()
+                .find(|a| a.name.as_str() == column)
+                .map(|a| {
+                    let values = serialize_attribute_to_graphql(&a.value);
+                    join_values(values.iter().map(String::as_str))
+                })
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+fn into_string(writer: csv::Writer<Vec<u8>>) -> Result<String> {
+    let bytes = writer.into_inner().context("while writing the CSV file")?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// One line per user, with the names of their groups. The users must come with their groups.
+pub fn write_users(users: &[UserAndGroups]) -> Result<String> {
+    let columns = attribute_columns(users.iter().flat_map(|u| &u.user.attributes));
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(
+        ["id", "email", "display_name", "groups"]
+            .into_iter()
+            .chain(columns.iter().map(String::as_str)),
+    )?;
+    for UserAndGroups { user, groups } in users {
+        let groups = join_values(groups.iter().flatten().map(|g| g.display_name.as_str()));
+        writer.write_record(
+            [
+                user.user_id.to_string(),
+                user.email.to_string(),
+                user.display_name.clone().unwrap_or_default(),
+                groups,
+            ]
+            .into_iter()
+            .chain(attribute_cells(&user.attributes, &columns)),
+        )?;
+    }
+    into_string(writer)
+}
+
+/// One line per group, with the IDs of their members.
+pub fn write_groups(groups: &[Group]) -> Result<String> {
+    let columns = attribute_columns(groups.iter().flat_map(|g| &g.attributes));
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(
+        ["name", "members"]
+            .into_iter()
+            .chain(columns.iter().map(String::as_str)),
+    )?;
+    for group in groups {
+        writer.write_record(
+            [
+                group.display_name.to_string(),
+                join_values(group.users.iter().map(|u| u.as_str())),
+            ]
+            .into_iter()
+            .chain(attribute_cells(&group.attributes, &columns)),
+        )?;
+    }
+    into_string(writer)
+}
+
+enum Column {
+    Id,
+    Email,
+    DisplayName,
+    /// The groups of a user, or the members of a group.
+    Memberships,
+    Attribute(String),
+    Ignored,
+}
+
+fn user_column(name: Option<String>) -> Column {
+    match name.as_deref() {
+        None => Column::Ignored,
+        Some("id" | "user_id" | "uid") => Column::Id,
+        Some("email" | "mail") => Column::Email,
+        Some("display_name" | "cn") => Column::DisplayName,
+        Some("groups") => Column::Memberships,
+        Some(name) => Column::Attribute(name.to_owned()),
+    }
+}
+
+fn group_column(name: Option<String>) -> Column {
+    match name.as_deref() {
+        None => Column::Ignored,
+        Some("name" | "display_name" | "cn") => Column::Id,
+        Some("members") => Column::Memberships,
+        Some(name) => Column::Attribute(name.to_owned()),
+    }
+}
+
+/// Reads a file of users or of groups, depending on its header: a file of users has an "id"
+/// column, a file of groups a "name" column. Empty cells are ignored.
+pub fn read(
+    content: &str,
+    attribute_mapping: &BTreeMap<String, String>,
+) -> Result<DirectoryRecords> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+    let header: Vec<String> = reader
+        .headers()
+        .context("while reading the CSV header")?
+        .iter()
+        .map(str::to_owned)
+        .collect();
+    let mapped: Vec<Option<String>> = header
+        .iter()
+        .map(|name| map_attribute_name(attribute_mapping, name))
+        .collect();
+    let user_columns: Vec<Column> = mapped.iter().cloned().map(user_column).collect();
+    let is_users = user_columns.iter().any(|c| matches!(c, Column::Id));
+    let columns = if is_users {
+        user_columns
+    } else {
+        let group_columns: Vec<Column> = mapped.into_iter().map(group_column).collect();
+        if !group_columns.iter().any(|c| matches!(c, Column::Id)) {
+            bail!("The CSV file needs an \"id\" column for users, or a \"name\" column for groups");
+        }
+        group_columns
+    };
+    let mut seen_columns = BTreeSet::new();
+    for (column, name) in columns.iter().zip(&header) {
+        let key = match column {
+            Column::Id => "id",
+            Column::Email => "email",
+            Column::DisplayName => "display_name",
+            Column::Memberships => "memberships",
+            Column::Attribute(name) => name.as_str(),
+            Column::Ignored => continue,
+        };
+        if !seen_columns.insert(key.to_owned()) {
+            bail!("The column \"{name}\" is a duplicate of another column");
+        }
+    }
+    let mut records = DirectoryRecords {
+        list_separator: Some(LIST_SEPARATOR),
+        ..Default::default()
+    };
+    for row in reader.records() {
+        let row = row.context("while reading the CSV file")?;
+        let cells = columns
+            .iter()
+            .zip(row.iter())
+            .filter(|(_, cell)| !cell.is_empty());
+        if is_users {
+            let mut user = UserRecord::default();
+            for (column, cell) in cells {
+                match column {
+                    Column::Id => user.id = Some(cell.to_owned()),
+                    Column::Email => user.email = Some(cell.to_owned()),
+                    Column::DisplayName => user.display_name = Some(cell.to_owned()),
+                    Column::Memberships => user.groups = split_values(cell),
+                    Column::Attribute(name) => user.add_attribute(name, cell.to_owned()),
+                    Column::Ignored => {}
+                }
+            }
+            records.users.push(user);
+        } else {
+            let mut group = GroupRecord::default();
+            for (column, cell) in cells {
+                match column {
+                    Column::Id => group.name = Some(cell.to_owned()),
+                    Column::Memberships => group.members = split_values(cell),
+                    Column::Attribute(name) => group.add_attribute(name, cell.to_owned()),
+                    Column::Email | Column::DisplayName | Column::Ignored => {}
+                }
+            }
+            records.groups.push(group);
+        }
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lldap_domain::types::{GroupDetails, GroupId, User, UserId, Uuid};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_users_round_trip() {
+        let date = chrono::Utc::now().naive_utc();
+        let users = vec![UserAndGroups {
+            user: User {
+                user_id: UserId::new("bob"),
+                email: "bob@example.com".into(),
+                display_name: Some("Bob, \"the\" builder".to_owned()),
+                attributes: vec![
+                    Attribute {
+                        name: "first_name".into(),
+                        value: "Bob".to_owned().into(),
+                    },
+                    Attribute {
+                        name: "nicknames".into(),
+                        value: vec!["b".to_owned(), "bobby".to_owned()].into(),
+                    },
+                ],
+                ..Default::default()
+            },
+            groups: Some(vec![GroupDetails {
+                group_id: GroupId(3),
+                display_name: "Best".into(),
+                creation_date: date,
+                uuid: Uuid::from_name_and_date("Best", &date),
+                dynamic_filter: None,
+                attributes: vec![],
+                modified_date: date,
+            }]),
+        }];
+        let csv = write_users(&users).unwrap();
+        assert_eq!(
+            csv,
+            "id,email,display_name,groups,first_name,nicknames\n\
+             bob,bob@example.com,\"Bob, \"\"the\"\" builder\",Best,Bob,b;bobby\n"
+        );
+        assert_eq!(
+            read(&csv, &BTreeMap::new()).unwrap(),
+            DirectoryRecords {
+                users: vec![UserRecord {
+                    id: Some("bob".to_owned()),
+                    email: Some("bob@example.com".to_owned()),
+                    display_name: Some("Bob, \"the\" builder".to_owned()),
+                    groups: vec!["Best".to_owned()],
+                    attributes: BTreeMap::from([
+                        ("first_name".to_owned(), vec!["Bob".to_owned()]),
+                        ("nicknames".to_owned(), vec!["b;bobby".to_owned()]),
+                    ]),
+                }],
+                groups: vec![],
+                list_separator: Some(LIST_SEPARATOR),
+            }
+        );
+    }
+
+    #[test]
+    fn test_read_groups_with_mapping() {
+        let csv = "Group Name,Members,Notes,Code\nBest,bob; patrick,,12\n";
+        let mapping = BTreeMap::from([
+            ("group name".to_owned(), "name".to_owned()),
+            ("notes".to_owned(), String::new()),
+        ]);
+        assert_eq!(
+            read(csv, &mapping).unwrap().groups,
+            vec![GroupRecord {
+                name: Some("Best".to_owned()),
+                members: vec!["bob".to_owned(), "patrick".to_owned()],
+                attributes: BTreeMap::from([("code".to_owned(), vec!["12".to_owned()])]),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_read_without_id_column() {
+        assert!(read("email,groups\n", &BTreeMap::new()).is_err());
+        assert!(read("id,email,mail\n", &BTreeMap::new()).is_err());
+    }
+}
@@ -0,0 +1,308 @@
+//! Computes the changes that bring the users, groups and memberships of the directory to a
+//! desired state. Used by both the import and the bootstrap.
+
+use lldap_access_control::ReadonlyBackendHandler;
+use lldap_auth::import::{ConflictPolicy, ImportChange};
+use lldap_domain::{
+    requests::{CreateGroupRequest, CreateUserRequest, UpdateGroupRequest, UpdateUserRequest},
+    types::{Attribute, AttributeName, Group, GroupName, UserAndGroups, UserId},
+};
+use lldap_domain_handlers::handler::BatchOperation;
+use lldap_domain_model::error::Result;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The users (with their groups) and the groups currently in the directory.
+pub struct ExistingDirectory {
+    pub users: BTreeMap<UserId, UserAndGroups>,
+    pub groups: BTreeMap<GroupName, Group>,
+}
+
+impl ExistingDirectory {
+    pub async fn load(handler: &impl ReadonlyBackendHandler) -> Result<Self> {
+        Ok(Self {
+            users: handler
+                .list_users(None, true)
+                .await?
+                .into_iter()
+                .map(|u| (u.user.user_id.clone(), u))
+                .collect(),
+            groups: handler
+                .list_groups(None)
+                .await?
+                .into_iter()
+                .map(|g| (g.display_name.clone(), g))
+                .collect(),
+        })
+    }
+}
+
+pub struct GroupEntry {
+    pub name: GroupName,
+    pub attributes: Vec<Attribute>,
+}
+
+pub struct UserEntry {
+    pub user_id: UserId,
+    /// Only required to create the user.
+    pub email: Option<String>,
+    pub display_name: Option<String>,
+    pub attributes: Vec<Attribute>,
+}
+
+/// The desired state. The entries are already validated against the schema, and each user or
+/// group appears only once.
+#[derive(Default)]
+pub struct DesiredDirectory {
+    pub groups: Vec<GroupEntry>,
+    pub users: Vec<UserEntry>,
+    /// The memberships to add. The groups must exist or be in `groups`.
+    pub memberships: BTreeSet<(UserId, GroupName)>,
+}
+
+#[derive(Clone, Copy)]
+pub struct PlanOptions {
+    pub policy: ConflictPolicy,
+    /// When updating, also remove the display name and the attributes that are not in the
+    /// entries.
+    pub prune: bool,
+}
+
+#[derive(Default)]
+pub struct DirectoryPlan {
+    pub changes: Vec<ImportChange>,
+    pub errors: Vec<String>,
+    /// The operations to apply in a single batch.
+    pub operations: Vec<BatchOperation>,
+}
+
+/// Describes the fields of an update, with the deleted attributes prefixed with "-".
+fn describe_fields(
+    fields: &[(&str, bool)],
+    delete_attributes: &[AttributeName],
+    insert_attributes: &[Attribute],
+) -> Vec<String> {
+    fields
+        .iter()
+        .filter(|(_, changed)| *changed)
+        .map(|(name, _)| name.to_string())
+        .chain(delete_attributes.iter().map(|a| format!("-{a}")))
+        .chain(insert_attributes.iter().map(|a| a.name.to_string()))
+        .collect()
+}
+
+/// The attributes to insert (new or changed values) and, when pruning, the ones to delete.
+fn attribute_changes(
+    existing: &[Attribute],
+    declared: Vec<Attribute>,
+    prune: bool,
+) -> (Vec<Attribute>, Vec<AttributeName>) {
+    let deleted = if prune {
+        existing
+            .iter()
+            .filter(|e| !declared.iter().any(|a| a.name == e.name))
+            .map(|e| e.name.clone())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let inserted = declared
+        .into_iter()
+        .filter(|a| {
+            !existing
+                .iter()
+                .any(|e| e.name == a.name && e.value == a.value)
+        })
+        .collect();
+    (inserted, deleted)
+}
+
+fn plan_groups(
+    groups: Vec<GroupEntry>,
+    existing: &ExistingDirectory,
+    options: PlanOptions,
+    plan: &mut DirectoryPlan,
+) {
+    for group in groups {
+        let name = group.name.to_string();
+        match (existing.groups.get(&group.name), options.policy) {
+            (None, _) => {
+                plan.changes.push(ImportChange::CreateGroup { name });
+                plan.operations
+                    .push(BatchOperation::CreateGroup(CreateGroupRequest {
+                        display_name: group.name,
+                        attributes: group.attributes,
+                    }));
+            }
+            (Some(_), ConflictPolicy::Fail) => {
+                plan.errors.push(format!("Group \"{name}\" already exists"));
+            }
+            (Some(existing_group), ConflictPolicy::Update) => {
+                let (insert_attributes, delete_attributes) =
+                    attribute_changes(&existing_group.attributes, group.attributes, options.prune);
+                // Only the case of the name can differ.
+                let display_name =
+                    (existing_group.display_name.as_str() != name).then_some(group.name);
+                let fields = describe_fields(
+                    &[("display_name", display_name.is_some())],
+                    &delete_attributes,
+                    &insert_attributes,
+                );
+                if fields.is_empty() {
+                    plan.changes.push(ImportChange::SkipGroup { name });
+                } else {
+                    plan.changes
+                        .push(ImportChange::UpdateGroup { name, fields });
+                    plan.operations
+                        .push(BatchOperation::UpdateGroup(UpdateGroupRequest {
+                            group_id: existing_group.id,
+                            display_name,
+                            delete_attributes,
+                            insert_attributes,
+                        }));
+                }
+            }
+            (Some(_), ConflictPolicy::Skip) => {
+                plan.changes.push(ImportChange::SkipGroup { name });
+            }
+        }
+    }
+}
+
+/// Returns the IDs of all the users of the entries.
+fn plan_users(
+    users: Vec<UserEntry>,
+    existing: &ExistingDirectory,
+    options: PlanOptions,
+    plan: &mut DirectoryPlan,
+) -> BTreeSet<UserId> {
+    let mut user_ids = BTreeSet::new();
+    for user in users {
+        user_ids.insert(user.user_id.clone());
+        let object = format!("User \"{}\"", user.user_id);
+        match (existing.users.get(&user.user_id), options.policy) {
+            (None, _) => {
+                let Some(email) = user.email else {
+                    plan.errors.push(format!("{object}: the email is missing"));
+                    continue;
+                };
+                plan.changes.push(ImportChange::CreateUser {
+                    user_id: user.user_id.to_string(),
+                });
+                plan.operations
+                    .push(BatchOperation::CreateUser(CreateUserRequest {
+                        user_id: user.user_id,
+                        email: email.into(),
+                        display_name: user.display_name,
+                        attributes: user.attributes,
+                        // The file comes from an admin.
+                        email_verified: true,
+                    }));
+            }
+            (Some(_), ConflictPolicy::Fail) => {
+                plan.errors.push(format!("{object} already exists"));
+            }
+            (Some(UserAndGroups { user: current, .. }), ConflictPolicy::Update) => {
+                let email = user
+                    .email
+                    .filter(|e| !e.eq_ignore_ascii_case(current.email.as_str()));
+                let display_name = match user.display_name {
+                    Some(display_name) => (current.display_name.as_ref() != Some(&display_name))
+                        .then_some(display_name),
+                    // An empty display name removes it.
+                    None => (options.prune && current.display_name.is_some()).then(String::new),
+                };
+                let (insert_attributes, delete_attributes) =
+                    attribute_changes(&current.attributes, user.attributes, options.prune);
+                let fields = describe_fields(
+                    &[
+                        ("email", email.is_some()),
+                        ("display_name", display_name.is_some()),
+                    ],
+                    &delete_attributes,
+                    &insert_attributes,
+                );
+                if fields.is_empty() {
+                    plan.changes.push(ImportChange::SkipUser {
+                        user_id: user.user_id.to_string(),
+                    });
+                } else {
+                    plan.changes.push(ImportChange::UpdateUser {
+                        user_id: user.user_id.to_string(),
+                        fields,
+                    });
+                    plan.operations
+                        .push(BatchOperation::UpdateUser(UpdateUserRequest {
+                            user_id: user.user_id,
+                            email: email.map(Into::into),
+                            display_name,
+                            delete_attributes,
+                            insert_attributes,
+                            email_verified: true,
+                        }));
+                }
+            }
+            (Some(_), ConflictPolicy::Skip) => {
+                plan.changes.push(ImportChange::SkipUser {
+                    user_id: user.user_id.to_string(),
+                });
+            }
+        }
+    }
+    user_ids
+}
+
+/// Adds the missing memberships. The existing memberships are never removed.
+fn plan_memberships(
+    memberships: BTreeSet<(UserId, GroupName)>,
+    existing: &ExistingDirectory,
+    user_ids: &BTreeSet<UserId>,
+    policy: ConflictPolicy,
+    plan: &mut DirectoryPlan,
+) {
+    for (user_id, group_name) in memberships {
+        let existing_user = existing.users.get(&user_id);
+        if existing_user.is_none() && !user_ids.contains(&user_id) {
+            plan.errors.push(format!(
+                "Group \"{group_name}\": unknown member \"{user_id}\""
+            ));
+            continue;
+        }
+        let is_member = existing_user
+            .and_then(|u| u.groups.as_ref())
+            .is_some_and(|groups| groups.iter().any(|g| g.display_name == group_name));
+        // With the skip policy, the memberships between existing users and groups are left
+        // as they are.
+        let is_skipped = policy == ConflictPolicy::Skip
+            && existing_user.is_some()
+            && existing.groups.contains_key(&group_name);
+        if is_member || is_skipped {
+            continue;
+        }
+        plan.changes.push(ImportChange::AddMembership {
+            user_id: user_id.to_string(),
+            group: group_name.to_string(),
+        });
+        // The group may be created earlier in the batch.
+        plan.operations
+            .push(BatchOperation::AddUserToGroupByName(user_id, group_name));
+    }
+}
+
+/// Computes the changes: the groups first, then the users, then the memberships.
+pub fn plan_directory(
+    desired: DesiredDirectory,
+    existing: &ExistingDirectory,
+    options: PlanOptions,
+) -> DirectoryPlan {
+    let mut plan = DirectoryPlan::default();
+    plan_groups(desired.groups, existing, options, &mut plan);
+    let user_ids = plan_users(desired.users, existing, options, &mut plan);
+    plan_memberships(
+        desired.memberships,
+        existing,
+        &user_ids,
+        options.policy,
+        &mut plan,
+    );
+    plan
+}
@@ -0,0 +1,541 @@
+use crate::{
+    auth_service::check_if_token_is_valid,
+    tcp_backend_handler::TcpBackendHandler,
+    tcp_server::{AppState, TcpError, TcpResult, error_to_http_response},
+};
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use chrono::{DateTime, Utc};
+use jwt::{SignWithKey, VerifyWithKey};
+use lldap_access_control::{
+    ReadonlyBackendHandler, UserReadableBackendHandler, UserWriteableBackendHandler,
+};
+use lldap_auth::email_verification::{EmailChangeRequest, ServerEmailVerificationResponse};
+use lldap_domain::{
+    requests::UpdateUserRequest,
+    types::{Email, User, UserId},
+};
+use lldap_domain_handlers::{
+    audit::AuditAction,
+    handler::{BackendHandler, UserRequestFilter},
+};
+use lldap_domain_model::model::UserColumn;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, instrument, warn};
+
+/// How long the link sent by email stays valid.
+const VERIFICATION_LINK_VALIDITY: chrono::Duration = chrono::Duration::days(2);
+
+/// The content of the signed verification link. It doesn't have the `groups` of the login JWTs,
+/// so one cannot be used in place of the other.
+#[derive(Serialize, Deserialize, Debug)]
+struct EmailVerificationClaims {
+    exp: DateTime<Utc>,
+    user: String,
+    email: String,
+    /// Set when the link confirms a change: the address that `email` replaces.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous_email: Option<String>,
+}
+
+fn get_verification_url<Backend>(
+    data: &AppState<Backend>,
+    claims: &EmailVerificationClaims,
+) -> TcpResult<url::Url> {
+    let token = claims
+        .sign_with_key(&data.jwt_key)
+        .map_err(|e| TcpError::InternalServerError(format!("Could not sign the link: {e}")))?;
+    let mut url = data.server_url.clone();
+    url.path_segments_mut()
+        .unwrap()
+        .pop_if_empty()
+        .extend(["verify-email", &token]);
+    Ok(url)
+}
+
+async fn send_verification_link<Backend>(
+    data: &AppState<Backend>,
+    user: &User,
+    claims: EmailVerificationClaims,
+) -> TcpResult<()> {
+    let url = get_verification_url(data, &claims)?;
+    crate::mail::send_email_verification_email(
+        user.display_name
+            .as_deref()
+            .unwrap_or_else(|| user.user_id.as_str()),
+        &claims.email,
+        &url,
+        crate::mail::get_user_locale(user, &data.mail_options()),
+        &data.server_url,
+        &data.mail_options(),
+    )
+    .await
+    .map_err(|e| {
+        warn!("Error sending email: {:#?}", e);
+        TcpError::InternalServerError(format!("Could not send email: {e}"))
+    })
+}
+
+/// Sends a link to the current email of the user, to confirm it.
+pub(crate) async fn send_verification_email<Backend>(
+    data: &AppState<Backend>,
+    user: &User,
+) -> TcpResult<()> {
+    let claims = EmailVerificationClaims {
+        exp: Utc::now() + VERIFICATION_LINK_VALIDITY,
+        user: user.user_id.to_string(),
+        email: user.email.to_string(),
+        previous_email: None,
+    };
+    send_verification_link(data, user, claims).await
+}
+
+/// Sends a link to the new address. The current one is kept until the link is followed.
+async fn send_email_change_email<Backend>(
+    data: &AppState<Backend>,
+    user: &User,
+    new_email: &Email,
+) -> TcpResult<()> {
+    let claims = EmailVerificationClaims {
+        exp: Utc::now() + VERIFICATION_LINK_VALIDITY,
+        user: user.user_id.to_string(),
+        email: new_email.to_string(),
+        previous_email: Some(user.email.to_string()),
+    };
+    send_verification_link(data, user, claims).await
+}
+
+fn get_user_id_from_path(request: &HttpRequest) -> TcpResult<UserId> {
+    request
+        .match_info()
+        .get("user_id")
+        .map(UserId::new)
+        .ok_or_else(|| TcpError::BadRequest("Missing user ID".to_owned()))
+}
+
+#[instrument(skip_all, level = "debug")]
+async fn start_verification<Backend>(
+    data: web::Data<AppState<Backend>>,
+    credentials: BearerAuth,
+    request: HttpRequest,
+) -> TcpResult<()>
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    let validation_result = check_if_token_is_valid(&data, credentials.token())
+        .map_err(|e| TcpError::UnauthorizedError(e.to_string()))?;
+    let user_id = get_user_id_from_path(&request)?;
+    if !validation_result.can_write(&user_id) {
+        return Err(TcpError::UnauthorizedError(
+            "Not authorized to verify the user's email".to_owned(),
+        ));
+    }
+    let user = data
+        .get_readonly_handler()
+        .get_user_details(&user_id)
+        .await?;
+    if user.email_verified {
+        debug!("Email already verified");
+        return Ok(());
+    }
+    send_verification_email(&data, &user).await
+}
+
+async fn start_verification_handler<Backend>(
+    data: web::Data<AppState<Backend>>,
+    credentials: BearerAuth,
+    request: HttpRequest,
+) -> HttpResponse
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    start_verification(data, credentials, request)
+        .await
+        .map(|()| HttpResponse::Ok().finish())
+        .unwrap_or_else(error_to_http_response)
+}
+
+#[instrument(skip_all, level = "debug")]
+async fn start_email_change<Backend>(
+    data: web::Data<AppState<Backend>>,
+    credentials: BearerAuth,
+    request: HttpRequest,
+    payload: web::Json<EmailChangeRequest>,
+) -> TcpResult<()>
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    let validation_result = check_if_token_is_valid(&data, credentials.token())
+        .map_err(|e| TcpError::UnauthorizedError(e.to_string()))?;
+    let user_id = get_user_id_from_path(&request)?;
+    if !validation_result.can_write(&user_id) {
+        return Err(TcpError::UnauthorizedError(
+            "Not authorized to change the user's email".to_owned(),
+        ));
+    }
+    let new_email = Email::from(payload.email.trim());
+    if !new_email.as_str().contains('@') {
+        return Err(TcpError::BadRequest("Invalid email address".to_owned()));
+    }
+    let user = data
+        .get_readonly_handler()
+        .get_user_details(&user_id)
+        .await?;
+    if user.email.as_str().eq_ignore_ascii_case(new_email.as_str()) {
+        return Err(TcpError::BadRequest(
+            "The email address is unchanged".to_owned(),
+        ));
+    }
+    // No point in sending a link that cannot be used.
+    let lowercase_email = new_email.as_str().to_lowercase();
+    if !data
+        .get_readonly_handler()
+        .list_users(
+            Some(UserRequestFilter::Equality(
+                UserColumn::LowercaseEmail,
+                lowercase_email,
+            )),
+            false,
+        )
+        .await?
+        .is_empty()
+    {
+        return Err(TcpError::BadRequest(
+            "The email address is already in use".to_owned(),
+        ));
+    }
+    send_email_change_email(&data, &user, &new_email).await
+}
+
+async fn start_email_change_handler<Backend>(
+    data: web::Data<AppState<Backend>>,
+    credentials: BearerAuth,
+    request: HttpRequest,
+    payload: web::Json<EmailChangeRequest>,
+) -> HttpResponse
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    start_email_change(data, credentials, request, payload)
+        .await
+        .map(|()| HttpResponse::Ok().finish())
+        .unwrap_or_else(error_to_http_response)
+}
+
+/// Replaces the email of the user with the confirmed one, if it is still the address the link
+/// was sent from.
+async fn apply_email_change<Backend>(
+    data: &AppState<Backend>,
+    request: &HttpRequest,
+    user_id: &UserId,
+    email: &Email,
+    previous_email: &str,
+) -> TcpResult<()>
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    let user = data
+        .get_readonly_handler()
+        .get_user_details(user_id)
+        .await?;
+    // Following the link again only confirms the address.
+    if user.email.as_str().eq_ignore_ascii_case(email.as_str()) {
+        return Ok(());
+    }
+    if !user.email.as_str().eq_ignore_ascii_case(previous_email) {
+        return Err(TcpError::UnauthorizedError(
+            "The email was changed since the link was sent".to_owned(),
+        ));
+    }
+    let result = data
+        .get_admin_handler()
+        .update_user(UpdateUserRequest {
+            user_id: user_id.clone(),
+            email: Some(email.clone()),
+            ..Default::default()
+        })
+        .await;
+    data.record_audit_event(
+        request,
+        Some(user_id),
+        AuditAction::UpdateUser,
+        Some(user_id.to_string()),
+        result.is_ok(),
+    )
+    .await;
+    result?;
+    info!("Email of '{user_id}' changed to '{email}'");
+    Ok(())
+}
+
+#[instrument(skip_all, level = "debug")]
+async fn finish_verification<Backend>(
+    data: web::Data<AppState<Backend>>,
+    request: HttpRequest,
+) -> TcpResult<ServerEmailVerificationResponse>
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    let token = request
+        .match_info()
+        .get("token")
+        .ok_or_else(|| TcpError::BadRequest("Missing verification token".to_owned()))?;
+    let claims: EmailVerificationClaims = token
+        .verify_with_key(&data.jwt_key)
+        .map_err(|_| TcpError::UnauthorizedError("Invalid verification link".to_owned()))?;
+    if claims.exp < Utc::now() {
+        return Err(TcpError::UnauthorizedError(
+            "Expired verification link".to_owned(),
+        ));
+    }
+    let user_id = UserId::new(&claims.user);
+    let email = Email::from(claims.email.as_str());
+    if let Some(previous_email) = &claims.previous_email {
+        apply_email_change(&data, &request, &user_id, &email, previous_email).await?;
+    }
+    // Fails if the email was changed since the link was sent.
+    data.get_tcp_handler()
+        .mark_email_verified(&user_id, &email)
+        .await?;
+    info!("Email of '{user_id}' verified");
+    Ok(ServerEmailVerificationResponse {
+        user_id: claims.user,
+        email: claims.email,
+    })
+}
+
+async fn finish_verification_handler<Backend>(
+    data: web::Data<AppState<Backend>>,
+    request: HttpRequest,
+) -> HttpResponse
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    finish_verification(data, request)
+        .await
+        .map(|res| HttpResponse::Ok().json(res))
+        .unwrap_or_else(error_to_http_response)
+}
+
+pub fn configure_server<Backend>(cfg: &mut web::ServiceConfig, enable_email_sending: bool)
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    cfg.service(
+        web::resource("/verify/{token}")
+            .route(web::post().to(finish_verification_handler::<Backend>)),
+    );
+    if enable_email_sending {
+        cfg.service(
+            web::resource("/verification/{user_id}")
+                .route(web::post().to(start_verification_handler::<Backend>)),
+        )
+        .service(
+            web::resource("/change/{user_id}")
+                .route(web::post().to(start_email_change_handler::<Backend>)),
+        );
+    } else {
+        cfg.service(
+            web::resource("/verification/{user_id}").route(web::post().to(HttpResponse::NotFound)),
+        )
+        .service(web::resource("/change/{user_id}").route(web::post().to(HttpResponse::NotFound)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{auth_service::create_jwt, tcp_server::tests::get_app_state};
+    use actix_web::{FromRequest, test::TestRequest};
+    use lldap_sql_backend_handler::SqlBackendHandler;
+    use std::collections::HashSet;
+
+    fn sign(
+        data: &AppState<SqlBackendHandler>,
+        email: &str,
+        previous_email: Option<&str>,
+        exp: DateTime<Utc>,
+    ) -> String {
+        EmailVerificationClaims {
+            exp,
+            user: "bob".to_owned(),
+            email: email.to_owned(),
+            previous_email: previous_email.map(str::to_owned),
+        }
+        .sign_with_key(&data.jwt_key)
+        .unwrap()
+    }
+
+    async fn finish(
+        data: &web::Data<AppState<SqlBackendHandler>>,
+        token: &str,
+    ) -> TcpResult<ServerEmailVerificationResponse> {
+        let request = TestRequest::default()
+            .param("token", token.to_owned())
+            .to_http_request();
+        finish_verification(data.clone(), request).await
+    }
+
+    async fn start_change(
+        data: &web::Data<AppState<SqlBackendHandler>>,
+        actor: &str,
+        email: &str,
+    ) -> TcpResult<()> {
+        let token = create_jwt(
+            data.get_tcp_handler(),
+            &data.jwt_key,
+            &UserId::new(actor),
+            HashSet::new(),
+        )
+        .await;
+        let request = TestRequest::default()
+            .insert_header(("Authorization", format!("Bearer {}", token.as_str())))
+            .param("user_id", "bob")
+            .to_http_request();
+        let credentials = BearerAuth::extract(&request).await.unwrap();
+        let payload = web::Json(EmailChangeRequest {
+            email: email.to_owned(),
+        });
+        start_email_change(data.clone(), credentials, request, payload).await
+    }
+
+    async fn get_bob(data: &AppState<SqlBackendHandler>) -> User {
+        data.get_readonly_handler()
+            .get_user_details(&UserId::new("bob"))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_verification_url() {
+        let data = get_app_state().await;
+        let claims = EmailVerificationClaims {
+            exp: Utc::now(),
+            user: "bob".to_owned(),
+            email: "new@bob.bob".to_owned(),
+            previous_email: Some("bob@bob.bob".to_owned()),
+        };
+        let url = get_verification_url(&data, &claims).unwrap();
+        let mut segments = url.path_segments().unwrap();
+        let token = segments.next_back().unwrap();
+        assert_eq!(segments.next_back(), Some("verify-email"));
+        let decoded: EmailVerificationClaims = token.verify_with_key(&data.jwt_key).unwrap();
+        assert_eq!(decoded.email, "new@bob.bob");
+        assert_eq!(decoded.previous_email.as_deref(), Some("bob@bob.bob"));
+    }
+
+    #[tokio::test]
+    async fn test_finish_verification() {
+        let data = get_app_state().await;
+        assert!(!get_bob(&data).await.email_verified);
+        let token = sign(
+            &data,
+            "BOB@bob.bob",
+            None,
+            Utc::now() + chrono::Duration::hours(1),
+        );
+        let response = finish(&data, &token).await.unwrap();
+        assert_eq!(response.user_id, "bob");
+        assert!(get_bob(&data).await.email_verified);
+    }
+
+    #[tokio::test]
+    async fn test_finish_verification_invalid_link() {
+        let data = get_app_state().await;
+        let expired = sign(
+            &data,
+            "bob@bob.bob",
+            None,
+            Utc::now() - chrono::Duration::hours(1),
+        );
+        assert!(matches!(
+            finish(&data, &expired).await,
+            Err(TcpError::UnauthorizedError(_))
+        ));
+        assert!(matches!(
+            finish(&data, "not.a.token").await,
+            Err(TcpError::UnauthorizedError(_))
+        ));
+        assert!(!get_bob(&data).await.email_verified);
+    }
+
+    #[tokio::test]
+    async fn test_finish_verification_after_email_changed() {
+        let data = get_app_state().await;
+        let token = sign(
+            &data,
+            "bob@bob.bob",
+            None,
+            Utc::now() + chrono::Duration::hours(1),
+        );
+        data.get_admin_handler()
+            .update_user(UpdateUserRequest {
+                user_id: UserId::new("bob"),
+                email: Some("other@bob.bob".into()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(finish(&data, &token).await.is_err());
+        assert!(!get_bob(&data).await.email_verified);
+    }
+
+    #[tokio::test]
+    async fn test_email_change() {
+        let data = get_app_state().await;
+        let token = sign(
+            &data,
+            "new@bob.bob",
+            Some("bob@bob.bob"),
+            Utc::now() + chrono::Duration::hours(1),
+        );
+        // Nothing changes until the link is followed.
+        assert_eq!(get_bob(&data).await.email.as_str(), "bob@bob.bob");
+        let response = finish(&data, &token).await.unwrap();
+        assert_eq!(response.email, "new@bob.bob");
+        let bob = get_bob(&data).await;
+        assert_eq!(bob.email.as_str(), "new@bob.bob");
+        assert!(bob.email_verified);
+        // Following the link again is harmless.
+        finish(&data, &token).await.unwrap();
+        assert_eq!(get_bob(&data).await.email.as_str(), "new@bob.bob");
+    }
+
+    #[tokio::test]
+    async fn test_email_change_outdated_link() {
+        let data = get_app_state().await;
+        let token = sign(
+            &data,
+            "new@bob.bob",
+            Some("old@bob.bob"),
+            Utc::now() + chrono::Duration::hours(1),
+        );
+        assert!(matches!(
+            finish(&data, &token).await,
+            Err(TcpError::UnauthorizedError(_))
+        ));
+        assert_eq!(get_bob(&data).await.email.as_str(), "bob@bob.bob");
+    }
+
+    #[tokio::test]
+    async fn test_start_email_change_rejected() {
+        let data = get_app_state().await;
+        assert!(matches!(
+            start_change(&data, "patrick", "new@bob.bob").await,
+            Err(TcpError::UnauthorizedError(_))
+        ));
+        assert!(matches!(
+            start_change(&data, "bob", "not an email").await,
+            Err(TcpError::BadRequest(_))
+        ));
+        assert!(matches!(
+            start_change(&data, "bob", "BOB@bob.bob").await,
+            Err(TcpError::BadRequest(_))
+        ));
+        assert!(matches!(
+            start_change(&data, "bob", "patrick@bob.bob").await,
+            Err(TcpError::BadRequest(_))
+        ));
+        assert_eq!(get_bob(&data).await.email.as_str(), "bob@bob.bob");
+    }
+}
@@ -1,18 +1,36 @@
 use crate::{auth_service::check_if_token_is_valid, tcp_server::AppState};
 use actix_web::FromRequest;
 use actix_web::HttpMessage;
-use actix_web::{Error, HttpRequest, HttpResponse, error::JsonPayloadError, web};
+use actix_web::{Error, HttpRequest, HttpResponse, error::JsonPayloadError, http::header, web};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
+use actix_ws::{CloseCode, CloseReason, Message, MessageStream, Session};
 use juniper::{
-    ScalarValue,
+    DefaultScalarValue, ScalarValue, Variables,
+    futures::{SinkExt, StreamExt},
     http::{
         GraphQLBatchRequest, GraphQLRequest, graphiql::graphiql_source,
         playground::playground_source,
     },
 };
-use lldap_domain_handlers::handler::BackendHandler;
+use juniper_graphql_ws::{ClientMessage, Connection, ConnectionConfig};
+use lldap_auth::access_control::ValidationResults;
+use lldap_domain_handlers::{
+    audit::{AuditInterface, AuditSource},
+    handler::BackendHandler,
+};
 use lldap_graphql_server::api::Context;
 use lldap_graphql_server::api::schema;
+use std::{
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// How often the subscription connections are pinged, so that proxies don't close them.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+/// How often the token of a subscription connection is checked again, to close the connection
+/// once the token expires or is logged out.
+const TOKEN_CHECK_INTERVAL: Duration = Duration::from_secs(10);
 
 async fn graphiql_route() -> Result<HttpResponse, Error> {
     let html = graphiql_source("/api/graphql", None);
@@ -119,30 +137,188 @@ where
     Ok(response.content_type("application/json").body(gql_response))
 }
 
-async fn graphql_route<Handler: BackendHandler + Clone>(
+fn is_websocket_upgrade(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(header::UPGRADE)
+        .is_some_and(|upgrade| upgrade.as_bytes().eq_ignore_ascii_case(b"websocket"))
+}
+
+fn make_context<Handler: BackendHandler + Clone>(
+    data: &AppState<Handler>,
+    validation_result: ValidationResults,
+    peer_ip: Option<IpAddr>,
+) -> Context<Handler> {
+    Context::<Handler> {
+        handler: data
+            .backend_handler
+            .clone()
+            .with_audit_source(AuditSource::new(AuditInterface::GraphQl, peer_ip)),
+        validation_result,
+        ldap_info: data.reloadable_config.ldap_info(),
+        confirm_email_changes: data.mail_options().enable_password_reset,
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+struct SubscriptionAuthError(String);
+
+/// Reads the token from the payload of the `connection_init` message, given either as
+/// `{"Authorization": "Bearer <token>"}` or as `{"token": "<token>"}`.
+fn get_init_payload_token(params: &Variables<DefaultScalarValue>) -> Option<String> {
+    params
+        .iter()
+        .find_map(|(key, value)| match key.to_ascii_lowercase().as_str() {
+            "authorization" => value
+                .as_string_value()
+                .and_then(|v| v.strip_prefix("Bearer ")),
+            "token" => value.as_string_value(),
+            _ => None,
+        })
+        .map(str::to_owned)
+}
+
+/// Serves the subscriptions with the graphql-ws protocol. Since browsers can't set headers on
+/// WebSocket connections, the token can be given either in the upgrade request (header or
+/// cookie) or in the `connection_init` message. The token is checked again regularly, and the
+/// connection is closed once it expires or is logged out.
+async fn subscriptions_route<Handler: BackendHandler + Clone + Unpin + 'static>(
+    req: HttpRequest,
+    payload: web::Payload,
+    data: web::Data<AppState<Handler>>,
+) -> Result<HttpResponse, Error> {
+    let header_token = match BearerAuth::extract(&req).await {
+        Ok(bearer) => {
+            // Reject a bad token before upgrading the connection.
+            check_if_token_is_valid(&data, bearer.token())?;
+            Some(bearer.token().to_owned())
+        }
+        Err(_) => None,
+    };
+    let (mut response, session, client_messages) = actix_ws::handle(&req, payload)?;
+    response.headers_mut().insert(
+        header::SEC_WEBSOCKET_PROTOCOL,
+        header::HeaderValue::from_static("graphql-ws"),
+    );
+    let peer_ip = data.client_ip(&req);
+    let token = Arc::new(Mutex::new(None));
+    let init = {
+        let data = data.clone();
+        let token = token.clone();
+        move |params: Variables<DefaultScalarValue>| async move {
+            let init_token = header_token
+                .or_else(|| get_init_payload_token(&params))
+                .ok_or_else(|| SubscriptionAuthError("Missing token".to_owned()))?;
+            let validation_result = check_if_token_is_valid(&data, &init_token)
+                .map_err(|e| SubscriptionAuthError(e.to_string()))?;
+            *token.lock().unwrap() = Some(init_token);
+            Ok::<_, SubscriptionAuthError>(
+                ConnectionConfig::new(make_context(&data, validation_result, peer_ip))
+                    .with_keep_alive_interval(KEEP_ALIVE_INTERVAL),
+            )
+        }
+    };
+    let connection = Connection::new(Arc::new(schema()), init);
+    actix_web::rt::spawn(run_subscriptions(
+        data,
+        token,
+        connection,
+        session,
+        client_messages,
+    ));
+    Ok(response)
+}
+
+async fn run_subscriptions<Handler, S, I>(
+    data: web::Data<AppState<Handler>>,
+    token: Arc<Mutex<Option<String>>>,
+    connection: Connection<S, I>,
+    mut session: Session,
+    mut client_messages: MessageStream,
+) where
+    Handler: BackendHandler,
+    S: juniper_graphql_ws::Schema<ScalarValue = DefaultScalarValue>,
+    I: juniper_graphql_ws::Init<DefaultScalarValue, S::Context> + Send,
+{
+    let (mut sink, mut server_messages) = connection.split();
+    let mut token_check = tokio::time::interval(TOKEN_CHECK_INTERVAL);
+    let close_reason = loop {
+        tokio::select! {
+            message = client_messages.recv() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    match serde_json::from_str::<ClientMessage<DefaultScalarValue>>(&text) {
+                        Ok(message) => {
+                            // The connection never fails to accept a message.
+                            let _ = sink.send(message).await;
+                        }
+                        Err(e) => {
+                            break Some(CloseReason {
+                                code: CloseCode::Protocol,
+                                description: Some(format!("Invalid message: {e}")),
+                            });
+                        }
+                    }
+                }
+                Some(Ok(Message::Ping(bytes))) => {
+                    if session.pong(&bytes).await.is_err() {
+                        return;
+                    }
+                }
+                Some(Ok(Message::Close(reason))) => break reason,
+                Some(Ok(_)) => {}
+                Some(Err(_)) | None => break None,
+            },
+            message = server_messages.next() => match message {
+                Some(message) => {
+                    let text = serde_json::to_string(&message)
+                        .expect("Server messages are always serializable");
+                    if session.text(text).await.is_err() {
+                        return;
+                    }
+                }
+                None => break None,
+            },
+            _ = token_check.tick() => {
+                let current_token = token.lock().unwrap().clone();
+                if let Some(Err(e)) = current_token.map(|t| check_if_token_is_valid(&data, &t)) {
+                    break Some(CloseReason {
+                        code: CloseCode::Policy,
+                        description: Some(e.to_string()),
+                    });
+                }
+            }
+        }
+    };
+    let _ = session.close(close_reason).await;
+}
+
+async fn graphql_route<Handler: BackendHandler + Clone + Unpin + 'static>(
     req: actix_web::HttpRequest,
     payload: actix_web::web::Payload,
     data: web::Data<AppState<Handler>>,
 ) -> Result<HttpResponse, Error> {
-    let mut inner_payload = payload.into_inner();
-    let bearer = BearerAuth::from_request(&req, &mut inner_payload).await?;
+    if is_websocket_upgrade(&req) {
+        return subscriptions_route(req, payload, data).await;
+    }
+    let bearer = BearerAuth::extract(&req).await?;
     let validation_result = check_if_token_is_valid(&data, bearer.token())?;
-    let context = Context::<Handler> {
-        handler: data.backend_handler.clone(),
-        validation_result,
-    };
+    let context = make_context(&data, validation_result, data.client_ip(&req));
+    let inner_payload = payload.into_inner();
     let schema = &schema();
     let context = &context;
-    match *req.method() {
+    let start = std::time::Instant::now();
+    let response = match *req.method() {
         actix_http::Method::POST => post_graphql_handler(schema, context, req, inner_payload).await,
         actix_http::Method::GET => get_graphql_handler(schema, context, req).await,
         _ => Err(actix_web::error::UrlGenerationError::ResourceNotFound.into()),
-    }
+    };
+    data.metrics.observe_graphql_request(start.elapsed());
+    response
 }
 
 pub fn configure_endpoint<Backend>(cfg: &mut web::ServiceConfig)
 where
-    Backend: BackendHandler + Clone + 'static,
+    Backend: BackendHandler + Clone + Unpin + 'static,
 {
     let json_config = web::JsonConfig::default()
         .limit(4096)
@@ -166,3 +342,36 @@ where
     cfg.service(web::resource("/graphql/playground").route(web::get().to(playground_route)));
     cfg.service(web::resource("/graphql/graphiql").route(web::get().to(graphiql_route)));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use juniper::InputValue;
+
+    fn payload(key: &str, value: &str) -> Variables<DefaultScalarValue> {
+        [(key.to_owned(), InputValue::scalar(value.to_owned()))]
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_get_init_payload_token() {
+        assert_eq!(
+            get_init_payload_token(&payload("Authorization", "Bearer abc")),
+            Some("abc".to_owned())
+        );
+        assert_eq!(
+            get_init_payload_token(&payload("authorization", "Bearer abc")),
+            Some("abc".to_owned())
+        );
+        assert_eq!(
+            get_init_payload_token(&payload("token", "abc")),
+            Some("abc".to_owned())
+        );
+        assert_eq!(
+            get_init_payload_token(&payload("Authorization", "Basic abc")),
+            None
+        );
+        assert_eq!(get_init_payload_token(&Variables::new()), None);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{configuration::LdapsOptions, tls};
+use crate::{
+    configuration::{HttpsOptions, LdapsOptions},
+    tls,
+};
 use anyhow::{Context, Result, anyhow, bail, ensure};
 use futures_util::SinkExt;
 use ldap3_proto::{
@@ -13,7 +16,10 @@ use rustls::crypto::{verify_tls12_signature, verify_tls13_signature};
 use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
 use rustls::{DigitallySignedStruct, SignatureScheme};
 use std::sync::Arc;
-use tokio::net::TcpStream;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
 use tokio_rustls::TlsConnector as RustlsTlsConnector;
 use tokio_util::codec::{FramedRead, FramedWrite};
 use tracing::{debug, info, instrument};
@@ -79,8 +85,9 @@ pub async fn check_ldap(host: &str, port: u16) -> Result<()> {
     check_ldap_endpoint(TcpStream::connect((host, port)).await?).await
 }
 
-fn get_tls_connector(ldaps_options: &LdapsOptions) -> Result<RustlsTlsConnector> {
-    let certs = tls::load_certificates(&ldaps_options.cert_file)?;
+/// Only accepts the certificate from `cert_file`, whatever the host name.
+fn get_tls_connector(cert_file: &str) -> Result<RustlsTlsConnector> {
+    let certs = tls::load_certificates(cert_file)?;
     let target_cert = certs.first().expect("empty certificate chain").clone();
 
     #[derive(Debug)]
@@ -156,25 +163,27 @@ fn get_tls_connector(ldaps_options: &LdapsOptions) -> Result<RustlsTlsConnector>
     Ok(Arc::new(client_config).into())
 }
 
+fn get_server_name(host: &str) -> Result<ServerName<'static>> {
+    Ok(match host.parse::<std::net::IpAddr>() {
+        Ok(ip) => ServerName::IpAddress(ip.into()),
+        Err(_) => ServerName::try_from(host.to_string())
+            .map_err(|_| anyhow!("Invalid DNS name: {}", host))?,
+    })
+}
+
 #[instrument(skip_all, level = "info", err, fields(host = %host, port = %ldaps_options.port))]
 pub async fn check_ldaps(host: &str, ldaps_options: &LdapsOptions) -> Result<()> {
     if !ldaps_options.enabled {
         info!("LDAPS not enabled");
         return Ok(());
     };
-    let tls_connector =
-        get_tls_connector(ldaps_options).context("while preparing the tls connection")?;
-
-    let domain = match host.parse::<std::net::IpAddr>() {
-        Ok(ip) => ServerName::IpAddress(ip.into()),
-        Err(_) => ServerName::try_from(host.to_string())
-            .map_err(|_| anyhow!("Invalid DNS name: {}", host))?,
-    };
+    let tls_connector = get_tls_connector(&ldaps_options.cert_file)
+        .context("while preparing the tls connection")?;
 
     check_ldap_endpoint(
         tls_connector
             .connect(
-                domain,
+                get_server_name(host)?,
                 TcpStream::connect((host, ldaps_options.port))
                     .await
                     .context("while connecting TCP")?,
@@ -185,11 +194,42 @@ pub async fn check_ldaps(host: &str, ldaps_options: &LdapsOptions) -> Result<()>
     .await
 }
 
+async fn check_https_api(host: &str, port: u16, cert_file: &str) -> Result<()> {
+    let tls_connector =
+        get_tls_connector(cert_file).context("while preparing the tls connection")?;
+    let mut stream = tls_connector
+        .connect(
+            get_server_name(host)?,
+            TcpStream::connect((host, port))
+                .await
+                .context("while connecting TCP")?,
+        )
+        .await
+        .context("while connecting TLS")?;
+    stream
+        .write_all(
+            format!("GET /health HTTP/1.1\r\nHost: {host}\r\nConnection: close\r\n\r\n").as_bytes(),
+        )
+        .await?;
+    let mut status_line = String::new();
+    BufReader::new(stream).read_line(&mut status_line).await?;
+    ensure!(
+        status_line.starts_with("HTTP/1.1 200 "),
+        "Unexpected answer from the API: {}",
+        status_line.trim_end()
+    );
+    Ok(())
+}
+
 #[instrument(level = "info", err)]
-pub async fn check_api(host: &str, port: u16) -> Result<()> {
-    reqwest::get(format!("http://{host}:{port}/health"))
-        .await?
-        .error_for_status()?;
+pub async fn check_api(host: &str, port: u16, https_options: &HttpsOptions) -> Result<()> {
+    if https_options.enabled {
+        check_https_api(host, port, &https_options.cert_file).await?;
+    } else {
+        reqwest::get(format!("http://{host}:{port}/health"))
+            .await?
+            .error_for_status()?;
+    }
     info!("Success");
     Ok(())
 }
@@ -0,0 +1,595 @@
+use crate::{
+    auth_service::check_if_token_is_valid,
+    directory_csv,
+    directory_plan::{
+        DesiredDirectory, DirectoryPlan, ExistingDirectory, GroupEntry, PlanOptions, UserEntry,
+        plan_directory,
+    },
+    ldif,
+    tcp_backend_handler::TcpBackendHandler,
+    tcp_server::{AppState, TcpError, TcpResult, error_to_http_response},
+};
+use actix_http::header;
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use lldap_access_control::{
+    AdminBackendHandler, ReadonlyBackendHandler, UserReadableBackendHandler,
+};
+use lldap_auth::import::{ClientImportRequest, ConflictPolicy, ImportFormat, ServerImportResponse};
+use lldap_domain::{
+    deserialize::deserialize_attribute_value,
+    schema::{AttributeList, Schema},
+    types::{Attribute, AttributeName, GroupName, UserId},
+};
+use lldap_domain_handlers::handler::{BackendHandler, BatchMode};
+use lldap_domain_model::error::Result;
+use lldap_validation::users::USER_ID_MAX_LENGTH;
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::{info, instrument};
+
+/// The maximum size of an import request, including the content of the file.
+const IMPORT_SIZE_LIMIT: usize = 1 << 24;
+
+/// A user read from an import file. The values are in the same format as in the GraphQL API.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct UserRecord {
+    pub id: Option<String>,
+    pub email: Option<String>,
+    pub display_name: Option<String>,
+    /// The names of the groups of the user.
+    pub groups: Vec<String>,
+    /// The other attributes, by lowercase name.
+    pub attributes: BTreeMap<String, Vec<String>>,
+}
+
+impl UserRecord {
+    pub fn add_attribute(&mut self, name: &str, value: String) {
+        self.attributes
+            .entry(name.to_ascii_lowercase())
+            .or_default()
+            .push(value);
+    }
+}
+
+/// A group read from an import file.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GroupRecord {
+    pub name: Option<String>,
+    /// The IDs of the members of the group.
+    pub members: Vec<String>,
+    /// The other attributes, by lowercase name.
+    pub attributes: BTreeMap<String, Vec<String>>,
+}
+
+impl GroupRecord {
+    pub fn add_attribute(&mut self, name: &str, value: String) {
+        self.attributes
+            .entry(name.to_ascii_lowercase())
+            .or_default()
+            .push(value);
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DirectoryRecords {
+    pub users: Vec<UserRecord>,
+    pub groups: Vec<GroupRecord>,
+    /// Set when the values of a list attribute are all in a single value, like in a CSV cell.
+    pub list_separator: Option<char>,
+}
+
+/// Applies the attribute mapping of an import to the name of an attribute of the file. Returns
+/// None for the attributes mapped to an empty name, which are ignored.
+pub fn map_attribute_name(mapping: &BTreeMap<String, String>, name: &str) -> Option<String> {
+    let mapped = mapping
+        .iter()
+        .find(|(from, _)| from.eq_ignore_ascii_case(name))
+        .map_or(name, |(_, to)| to.as_str());
+    (!mapped.is_empty()).then(|| mapped.to_ascii_lowercase())
+}
+
+pub fn read_records(
+    format: ImportFormat,
+    content: &str,
+    attribute_mapping: &BTreeMap<String, String>,
+) -> anyhow::Result<DirectoryRecords> {
+    match format {
+        ImportFormat::Csv => directory_csv::read(content, attribute_mapping),
+        ImportFormat::Ldif => ldif::read(content, attribute_mapping),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// The users, with their attributes and the names of their groups.
+    UsersCsv,
+    /// The groups, with their attributes and the IDs of their members.
+    GroupsCsv,
+    /// The users and the groups, as LDIF entries.
+    Ldif,
+}
+
+impl ExportFormat {
+    fn file_name(self) -> &'static str {
+        match self {
+            ExportFormat::UsersCsv => "users.csv",
+            ExportFormat::GroupsCsv => "groups.csv",
+            ExportFormat::Ldif => "directory.ldif",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::UsersCsv | ExportFormat::GroupsCsv => "text/csv; charset=utf-8",
+            ExportFormat::Ldif => "text/x-ldif; charset=utf-8",
+        }
+    }
+}
+
+/// Exports all the users and groups, with their custom attributes. `base_dn` is only used for
+/// the DNs of the LDIF entries.
+pub async fn export_directory(
+    handler: &impl ReadonlyBackendHandler,
+    format: ExportFormat,
+    base_dn: &str,
+) -> anyhow::Result<String> {
+    match format {
+        ExportFormat::UsersCsv => {
+            directory_csv::write_users(&handler.list_users(None, true).await?)
+        }
+        ExportFormat::GroupsCsv => directory_csv::write_groups(&handler.list_groups(None).await?),
+        ExportFormat::Ldif => Ok(ldif::write(
+            &handler.list_users(None, false).await?,
+            &handler.list_groups(None).await?,
+            base_dn,
+        )),
+    }
+}
+
+/// Converts the attributes of a record to the types of the schema.
+pub(crate) fn parse_attributes(
+    object: &str,
+    attributes: &BTreeMap<String, Vec<String>>,
+    schema: &AttributeList,
+    list_separator: Option<char>,
+    errors: &mut Vec<String>,
+) -> Vec<Attribute> {
+    let mut parsed = Vec::new();
+    for (name, values) in attributes {
+        let name = AttributeName::from(name.as_str());
+        let Some(attribute_schema) = schema.get_attribute_schema(&name) else {
+            errors.push(format!(
+                "{object}: unknown attribute \"{name}\", map it to an attribute of the schema or to an empty name to ignore it"
+            ));
+            continue;
+        };
+        if attribute_schema.is_readonly {
+            errors.push(format!("{object}: the attribute \"{name}\" is read-only"));
+            continue;
+        }
+        let values: Vec<String> = match list_separator {
+            Some(separator) if attribute_schema.is_list => values
+                .iter()
+                .flat_map(|v| v.split(separator))
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            _ => values.clone(),
+        };
+        match deserialize_attribute_value(
+            &values,
+            attribute_schema.attribute_type,
+            attribute_schema.is_list,
+        ) {
+            Ok(value) => match attribute_schema.validation.validate_value(&value) {
+                Ok(()) => parsed.push(Attribute { name, value }),
+                Err(e) => errors.push(format!("{object}: invalid value for \"{name}\": {e}")),
+            },
+            Err(e) => errors.push(format!("{object}: invalid value for \"{name}\": {e:#}")),
+        }
+    }
+    parsed
+}
+
+/// Validates the records and converts them to the desired state of the directory. The groups of
+/// the users that don't exist yet are added to the groups.
+fn read_desired_directory(
+    records: &DirectoryRecords,
+    existing: &ExistingDirectory,
+    schema: &Schema,
+    errors: &mut Vec<String>,
+) -> DesiredDirectory {
+    let mut desired = DesiredDirectory::default();
+    let mut seen = BTreeSet::new();
+    for (index, record) in records.groups.iter().enumerate() {
+        let Some(name) = record.name.as_deref() else {
+            errors.push(format!("Group #{}: the name is missing", index + 1));
+            continue;
+        };
+        let group_name = GroupName::from(name);
+        if !seen.insert(group_name.clone()) {
+            errors.push(format!("Group \"{name}\" appears more than once"));
+            continue;
+        }
+        let attributes = parse_attributes(
+            &format!("Group \"{name}\""),
+            &record.attributes,
+            &schema.group_attributes,
+            records.list_separator,
+            errors,
+        );
+        desired.groups.push(GroupEntry {
+            name: group_name,
+            attributes,
+        });
+        desired.memberships.extend(
+            record
+                .members
+                .iter()
+                .map(|m| (UserId::new(m), GroupName::from(name))),
+        );
+    }
+    for group in records.users.iter().flat_map(|u| &u.groups) {
+        let group_name = GroupName::from(group.as_str());
+        if !existing.groups.contains_key(&group_name) && seen.insert(group_name.clone()) {
+            desired.groups.push(GroupEntry {
+                name: group_name,
+                attributes: Vec::new(),
+            });
+        }
+    }
+    let mut seen = BTreeSet::new();
+    for (index, record) in records.users.iter().enumerate() {
+        let Some(id) = record.id.as_deref() else {
+            errors.push(format!("User #{}: the ID is missing", index + 1));
+            continue;
+        };
+        if id.len() > USER_ID_MAX_LENGTH {
+            errors.push(format!(
+                "User \"{id}\": the ID is longer than {USER_ID_MAX_LENGTH} characters"
+            ));
+            continue;
+        }
+        let user_id = UserId::new(id);
+        if !seen.insert(user_id.clone()) {
+            errors.push(format!("User \"{id}\" appears more than once"));
+            continue;
+        }
+        let object = format!("User \"{id}\"");
+        if record.email.as_ref().is_some_and(|e| !e.contains('@')) {
+            errors.push(format!("{object}: invalid email address"));
+        }
+        let attributes = parse_attributes(
+            &object,
+            &record.attributes,
+            &schema.user_attributes,
+            records.list_separator,
+            errors,
+        );
+        desired.memberships.extend(
+            record
+                .groups
+                .iter()
+                .map(|g| (user_id.clone(), GroupName::from(g.as_str()))),
+        );
+        desired.users.push(UserEntry {
+            user_id,
+            email: record.email.clone(),
+            display_name: record.display_name.clone(),
+            attributes,
+        });
+    }
+    desired
+}
+
+/// Imports the users, groups and memberships of the records. Nothing is applied if there is any
+/// error, or for a dry run.
+///
+/// The groups, the users and the memberships are all applied in a single transaction.
+#[instrument(skip_all, level = "debug")]
+pub async fn import_records(
+    handler: &impl AdminBackendHandler,
+    records: DirectoryRecords,
+    policy: ConflictPolicy,
+    dry_run: bool,
+) -> Result<ServerImportResponse> {
+    let schema = UserReadableBackendHandler::get_schema(handler).await?;
+    let existing = ExistingDirectory::load(handler).await?;
+    let mut errors = Vec::new();
+    let desired = read_desired_directory(&records, &existing, schema.get_schema(), &mut errors);
+    let DirectoryPlan {
+        changes,
+        errors: plan_errors,
+        operations,
+    } = plan_directory(
+        desired,
+        &existing,
+        PlanOptions {
+            policy,
+            prune: false,
+        },
+    );
+    errors.extend(plan_errors);
+    if !errors.is_empty() || dry_run {
+        return Ok(ServerImportResponse {
+            changes,
+            errors,
+            applied: false,
+        });
+    }
+    let result = handler
+        .apply_batch(operations, BatchMode::AllOrNothing)
+        .await?;
+    if !result.committed {
+        errors.extend(
+            result
+                .results
+                .into_iter()
+                .filter_map(|r| r.err())
+                .map(|e| format!("{e:#}, nothing was imported")),
+        );
+    }
+    info!(
+        "Imported {} changes{}",
+        changes.len(),
+        if result.committed { "" } else { " (failed)" }
+    );
+    Ok(ServerImportResponse {
+        changes,
+        errors,
+        applied: result.committed,
+    })
+}
+
+#[instrument(skip_all, level = "debug")]
+async fn import<Backend>(
+    data: web::Data<AppState<Backend>>,
+    credentials: BearerAuth,
+    request: web::Json<ClientImportRequest>,
+) -> TcpResult<ServerImportResponse>
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    let validation_result = check_if_token_is_valid(&data, credentials.token())
+        .map_err(|e| TcpError::UnauthorizedError(e.to_string()))?;
+    let admin_handler = data
+        .backend_handler
+        .get_admin_handler(&validation_result)
+        .ok_or_else(|| TcpError::ForbiddenError("Only admins can import users".to_owned()))?;
+    let request = request.into_inner();
+    let records = read_records(request.format, &request.content, &request.attribute_mapping)
+        .map_err(|e| TcpError::BadRequest(format!("{e:#}")))?;
+    Ok(import_records(
+        &admin_handler,
+        records,
+        request.conflict_policy,
+        request.dry_run,
+    )
+    .await?)
+}
+
+async fn import_handler<Backend>(
+    data: web::Data<AppState<Backend>>,
+    credentials: BearerAuth,
+    request: web::Json<ClientImportRequest>,
+) -> HttpResponse
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    import(data, credentials, request)
+        .await
+        .map(|res| HttpResponse::Ok().json(res))
+        .unwrap_or_else(error_to_http_response)
+}
+
+#[instrument(skip_all, level = "debug")]
+async fn export<Backend>(
+    data: web::Data<AppState<Backend>>,
+    credentials: BearerAuth,
+    request: HttpRequest,
+) -> TcpResult<HttpResponse>
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    let format = match request.match_info().get("file") {
+        Some("users.csv") => ExportFormat::UsersCsv,
+        Some("groups.csv") => ExportFormat::GroupsCsv,
+        Some("directory.ldif") => ExportFormat::Ldif,
+        _ => return Err(TcpError::NotFoundError("Unknown export file".to_owned())),
+    };
+    let validation_result = check_if_token_is_valid(&data, credentials.token())
+        .map_err(|e| TcpError::UnauthorizedError(e.to_string()))?;
+    let admin_handler = data
+        .backend_handler
+        .get_admin_handler(&validation_result)
+        .ok_or_else(|| TcpError::ForbiddenError("Only admins can export users".to_owned()))?;
+    let content = export_directory(
+        &admin_handler,
+        format,
+        &data.reloadable_config.ldap_info().base_dn_str,
+    )
+    .await
+    .map_err(|e| TcpError::InternalServerError(format!("{e:#}")))?;
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, format.content_type()))
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", format.file_name()),
+        ))
+        .body(content))
+}
+
+async fn export_handler<Backend>(
+    data: web::Data<AppState<Backend>>,
+    credentials: BearerAuth,
+    request: HttpRequest,
+) -> HttpResponse
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    export(data, credentials, request)
+        .await
+        .unwrap_or_else(error_to_http_response)
+}
+
+pub fn configure_endpoint<Backend>(cfg: &mut web::ServiceConfig)
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    cfg.service(
+        web::resource("/import")
+            .app_data(web::JsonConfig::default().limit(IMPORT_SIZE_LIMIT))
+            .route(web::post().to(import_handler::<Backend>)),
+    )
+    .service(web::resource("/export/{file}").route(web::get().to(export_handler::<Backend>)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lldap_auth::import::ImportChange;
+    use lldap_domain_handlers::handler::{GroupListerBackendHandler, UserBackendHandler};
+    use lldap_sql_backend_handler::test_fixture::TestFixture;
+    use pretty_assertions::assert_eq;
+
+    fn read_csv(content: &str) -> DirectoryRecords {
+        read_records(ImportFormat::Csv, content, &BTreeMap::new()).unwrap()
+    }
+
+    const USERS: &str = "id,email,display_name,groups,first_name\n\
+                         bob,bob@bob.bob,Bobby,Best Group,\n\
+                         alice,alice@example.com,,Best Group;New,Alice\n";
+
+    #[tokio::test]
+    async fn test_import_dry_run_then_apply() {
+        let handler = TestFixture::new().await.handler;
+        let expected_changes = vec![
+            ImportChange::CreateGroup {
+                name: "New".to_owned(),
+            },
+            ImportChange::UpdateUser {
+                user_id: "bob".to_owned(),
+                fields: vec!["display_name".to_owned()],
+            },
+            ImportChange::CreateUser {
+                user_id: "alice".to_owned(),
+            },
+            ImportChange::AddMembership {
+                user_id: "alice".to_owned(),
+                group: "Best Group".to_owned(),
+            },
+            ImportChange::AddMembership {
+                user_id: "alice".to_owned(),
+                group: "New".to_owned(),
+            },
+        ];
+        let response = import_records(&handler, read_csv(USERS), ConflictPolicy::Update, true)
+            .await
+            .unwrap();
+        assert_eq!(
+            response,
+            ServerImportResponse {
+                changes: expected_changes.clone(),
+                errors: Vec::new(),
+                applied: false,
+            }
+        );
+        assert!(
+            UserBackendHandler::get_user_details(&handler, &UserId::new("alice"))
+                .await
+                .is_err()
+        );
+
+        let response = import_records(&handler, read_csv(USERS), ConflictPolicy::Update, false)
+            .await
+            .unwrap();
+        assert_eq!(response.changes, expected_changes);
+        assert!(response.applied);
+        let bob = UserBackendHandler::get_user_details(&handler, &UserId::new("bob"))
+            .await
+            .unwrap();
+        assert_eq!(bob.display_name.as_deref(), Some("Bobby"));
+        let mut alice_groups: Vec<_> =
+            UserBackendHandler::get_user_groups(&handler, &UserId::new("alice"))
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|g| g.display_name.to_string())
+                .collect();
+        alice_groups.sort();
+        assert_eq!(
+            alice_groups,
+            vec!["Best Group".to_owned(), "New".to_owned()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_skip_and_fail_policies() {
+        let handler = TestFixture::new().await.handler;
+        let content = "id,email,groups\nbob,other@example.com,Best Group\n";
+        let response = import_records(&handler, read_csv(content), ConflictPolicy::Skip, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            response.changes,
+            vec![ImportChange::SkipUser {
+                user_id: "bob".to_owned()
+            }]
+        );
+        let response = import_records(&handler, read_csv(content), ConflictPolicy::Fail, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            response.errors,
+            vec!["User \"bob\" already exists".to_owned()]
+        );
+        assert!(!response.applied);
+    }
+
+    #[tokio::test]
+    async fn test_import_errors_apply_nothing() {
+        let handler = TestFixture::new().await.handler;
+        let content = "id,email,creation_date,shoe_size\n\
+                       alice,alice@example.com,,\n\
+                       carol,,2020-01-01T00:00:00Z,42\n";
+        let response = import_records(&handler, read_csv(content), ConflictPolicy::Skip, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            response.errors,
+            vec![
+                "User \"carol\": the attribute \"creation_date\" is read-only".to_owned(),
+                "User \"carol\": unknown attribute \"shoe_size\", map it to an attribute of the schema or to an empty name to ignore it".to_owned(),
+                "User \"carol\": the email is missing".to_owned(),
+            ]
+        );
+        assert!(!response.applied);
+        assert!(
+            UserBackendHandler::get_user_details(&handler, &UserId::new("alice"))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_failure_creates_no_group() {
+        let handler = TestFixture::new().await.handler;
+        // The email is already taken, so the user can't be created.
+        let content = "id,email,groups\nalice,bob@bob.bob,New\n";
+        let response = import_records(&handler, read_csv(content), ConflictPolicy::Skip, false)
+            .await
+            .unwrap();
+        assert!(!response.applied);
+        assert_eq!(response.errors.len(), 1);
+        assert_eq!(
+            GroupListerBackendHandler::list_groups(&handler, None)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|g| g.display_name.to_string())
+                .collect::<Vec<_>>(),
+            vec!["Best Group", "Empty Group", "Worst Group"]
+        );
+    }
+}
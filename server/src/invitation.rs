@@ -0,0 +1,943 @@
+use crate::{
+    auth_service::check_if_token_is_valid,
+    sql_tcp_backend_handler::gen_random_string,
+    tcp_backend_handler::{Invitation, TcpBackendHandler},
+    tcp_server::{AppState, TcpError, TcpResult, error_to_http_response},
+};
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use base64::Engine;
+use hmac::Mac;
+use lldap_access_control::{
+    AdminBackendHandler, ReadonlyBackendHandler, UserReadableBackendHandler,
+};
+use lldap_auth::{invitation, registration};
+use lldap_domain::{
+    deserialize::deserialize_attribute_value,
+    requests::CreateUserRequest,
+    schema::AttributeList,
+    types::{Attribute, AttributeName, AttributeValue, Cardinality, Email, GroupId, UserId},
+};
+use lldap_domain_handlers::{
+    audit::AuditAction,
+    handler::{BackendHandler, ReadSchemaBackendHandler},
+};
+use lldap_domain_model::error::DomainError;
+use lldap_opaque_handler::OpaqueHandler;
+use lldap_validation::users::{
+    USER_ID_ALLOWED_CHARACTERS_DESCRIPTION, USER_ID_MAX_LENGTH, validate_user_id,
+};
+use tracing::{debug, info, instrument, warn};
+
+const DEFAULT_VALIDITY_DAYS: u32 = 7;
+const MAX_VALIDITY_DAYS: u32 = 30;
+
+/// The link sent to the invitee, pointing to the registration page of the frontend.
+fn get_invitation_url(server_url: &url::Url, token: &str) -> url::Url {
+    let mut invitation_url = server_url.clone();
+    invitation_url
+        .path_segments_mut()
+        .unwrap()
+        .pop_if_empty()
+        .extend(["invitation", token]);
+    invitation_url
+}
+
+fn get_token_from_path(request: &HttpRequest) -> TcpResult<String> {
+    request
+        .match_info()
+        .get("token")
+        .map(str::to_owned)
+        .ok_or_else(|| TcpError::BadRequest("Missing invitation token".to_owned()))
+}
+
+/// Signs the details of a registration, so that the finish request cannot swap the user or the
+/// (opaque to us) OPAQUE server data for another one.
+fn get_registration_mac<Backend>(
+    data: &AppState<Backend>,
+    token: &str,
+    user_id: &UserId,
+    email: &Email,
+    display_name: Option<&str>,
+    server_data: &str,
+) -> hmac::Hmac<sha2::Sha512> {
+    let mut mac = data.jwt_key.clone();
+    for part in [
+        "invitation",
+        token,
+        user_id.as_str(),
+        email.as_str(),
+        display_name.unwrap_or_default(),
+        server_data,
+    ] {
+        mac.update(&(part.len() as u64).to_le_bytes());
+        mac.update(part.as_bytes());
+    }
+    mac
+}
+
+/// Checks the user details chosen by the invitee against the invitation and the existing users.
+async fn validate_registration<Backend>(
+    data: &AppState<Backend>,
+    invitation: &Invitation,
+    user_id: &str,
+    email: &str,
+) -> TcpResult<(UserId, Email)>
+where
+    Backend: BackendHandler,
+{
+    if user_id.is_empty() || user_id.len() > USER_ID_MAX_LENGTH {
+        return Err(TcpError::BadRequest(format!(
+            "The user ID must be between 1 and {USER_ID_MAX_LENGTH} characters long"
+        )));
+    }
+    let user_id = UserId::new(user_id);
+    match &invitation.user_id {
+        Some(imposed) if imposed != &user_id => {
+            return Err(TcpError::BadRequest(format!(
+                "This invitation is for the user ID '{imposed}'"
+            )));
+        }
+        Some(_) => (),
+        // User IDs imposed by the admin are not restricted.
+        None => validate_user_id(user_id.as_str()).map_err(|invalid_chars| {
+            TcpError::BadRequest(format!(
+                "Invalid characters in the user ID: {invalid_chars:?}. Allowed characters: {USER_ID_ALLOWED_CHARACTERS_DESCRIPTION}"
+            ))
+        })?,
+    }
+    let email = match &invitation.email {
+        Some(imposed) => imposed.clone(),
+        None if email.contains('@') => Email::from(email),
+        None => {
+            return Err(TcpError::BadRequest(
+                "A valid email address is required".to_owned(),
+            ));
+        }
+    };
+    match data.get_readonly_handler().get_user_details(&user_id).await {
+        Ok(_) => Err(TcpError::BadRequest(format!(
+            "The user ID '{user_id}' is already taken"
+        ))),
+        Err(DomainError::EntityNotFound(_)) => Ok((user_id, email)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The invitee can't fill in the attributes, so the invitation must set the required ones.
+fn find_missing_required_attribute<'a>(
+    attribute_list: &'a AttributeList,
+    attributes: &[Attribute],
+) -> Option<&'a AttributeName> {
+    attribute_list
+        .attributes
+        .iter()
+        .find(|a| a.validation.is_required && !attributes.iter().any(|attr| attr.name == a.name))
+        .map(|a| &a.name)
+}
+
+/// Parses the attributes set by the admin, with the same rules as when creating a user.
+fn parse_invitation_attributes(
+    attribute_list: &AttributeList,
+    attributes: Vec<invitation::InvitationAttribute>,
+) -> TcpResult<Vec<Attribute>> {
+    let attributes = attributes
+        .into_iter()
+        .map(|attribute| {
+            let name = AttributeName::from(attribute.name.as_str());
+            let attribute_schema = attribute_list
+                .get_attribute_schema(&name)
+                .filter(|a| !a.is_readonly)
+                .ok_or_else(|| {
+                    TcpError::BadRequest(format!(
+                        "Attribute {name} is not defined in the schema, or is read-only"
+                    ))
+                })?;
+            let value = deserialize_attribute_value(
+                &attribute.value,
+                attribute_schema.attribute_type,
+                attribute_schema.is_list,
+            )
+            .map_err(|e| {
+                TcpError::BadRequest(format!("Invalid value for attribute {name}: {e:#}"))
+            })?;
+            attribute_schema
+                .validation
+                .validate_value(&value)
+                .map_err(|e| {
+                    TcpError::BadRequest(format!("Invalid value for attribute {name}: {e}"))
+                })?;
+            Ok(Attribute { name, value })
+        })
+        .collect::<TcpResult<Vec<_>>>()?;
+    if let Some(name) = find_missing_required_attribute(attribute_list, &attributes) {
+        return Err(TcpError::BadRequest(format!(
+            "Attribute {name} is required, the invitation must set it"
+        )));
+    }
+    Ok(attributes)
+}
+
+fn is_list(value: &AttributeValue) -> bool {
+    matches!(
+        value,
+        AttributeValue::String(Cardinality::Unbounded(_))
+            | AttributeValue::Integer(Cardinality::Unbounded(_))
+            | AttributeValue::JpegPhoto(Cardinality::Unbounded(_))
+            | AttributeValue::DateTime(Cardinality::Unbounded(_))
+    )
+}
+
+/// The attributes of the invitation still in the schema, which may have changed since the
+/// invitation was created.
+async fn get_registration_attributes<Backend>(
+    data: &AppState<Backend>,
+    invitation: &Invitation,
+) -> TcpResult<Vec<Attribute>>
+where
+    Backend: BackendHandler,
+{
+    let schema = ReadSchemaBackendHandler::get_schema(data.get_readonly_handler()).await?;
+    let attribute_list = &schema.user_attributes;
+    let attributes: Vec<_> = invitation
+        .attributes
+        .iter()
+        .filter(|a| {
+            attribute_list.get_attribute_type(&a.name)
+                == Some((a.value.get_attribute_type(), is_list(&a.value)))
+        })
+        .cloned()
+        .collect();
+    if let Some(name) = find_missing_required_attribute(attribute_list, &attributes) {
+        return Err(TcpError::BadRequest(format!(
+            "Attribute {name} became required after this invitation was created, ask for a new invitation"
+        )));
+    }
+    Ok(attributes)
+}
+
+#[instrument(skip_all, level = "debug")]
+async fn create_invitation<Backend>(
+    data: web::Data<AppState<Backend>>,
+    credentials: BearerAuth,
+    request: web::Json<invitation::ClientCreateInvitationRequest>,
+) -> TcpResult<invitation::ServerCreateInvitationResponse>
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    let validation_result = check_if_token_is_valid(&data, credentials.token())
+        .map_err(|e| TcpError::UnauthorizedError(e.to_string()))?;
+    let admin_handler = data
+        .backend_handler
+        .get_admin_handler(&validation_result)
+        .ok_or_else(|| TcpError::ForbiddenError("Only admins can invite users".to_owned()))?;
+    let request = request.into_inner();
+    let validity_days = request.validity_days.unwrap_or(DEFAULT_VALIDITY_DAYS);
+    if !(1..=MAX_VALIDITY_DAYS).contains(&validity_days) {
+        return Err(TcpError::BadRequest(format!(
+            "The invitation must be valid for 1 to {MAX_VALIDITY_DAYS} days"
+        )));
+    }
+    let user_id = request
+        .user_id
+        .filter(|u| !u.is_empty())
+        .map(|u| UserId::new(&u));
+    if let Some(user_id) = &user_id
+        && admin_handler.get_user_details(user_id).await.is_ok()
+    {
+        return Err(TcpError::BadRequest(format!(
+            "The user ID '{user_id}' is already taken"
+        )));
+    }
+    let email = request.email.filter(|e| !e.is_empty());
+    if email.as_ref().is_some_and(|e| !e.contains('@')) {
+        return Err(TcpError::BadRequest("Invalid email address".to_owned()));
+    }
+    let group_ids: Vec<_> = request.group_ids.into_iter().map(GroupId).collect();
+    for group_id in &group_ids {
+        admin_handler.get_group_details(*group_id).await?;
+    }
+    let schema = ReadSchemaBackendHandler::get_schema(&admin_handler).await?;
+    let attributes = parse_invitation_attributes(&schema.user_attributes, request.attributes)?;
+    let token = gen_random_string(100);
+    let expiry_date = chrono::Utc::now().naive_utc() + chrono::Duration::days(validity_days.into());
+    data.get_tcp_handler()
+        .create_invitation(Invitation {
+            token: token.clone(),
+            user_id,
+            email: email.as_deref().map(Email::from),
+            group_ids,
+            attributes,
+            created_by: validation_result.user.clone(),
+            expiry_date,
+        })
+        .await?;
+    info!(
+        "Invitation created by '{}', valid for {validity_days} days",
+        &validation_result.user
+    );
+    let url = get_invitation_url(&data.server_url, &token);
+    let email_sent = match email {
+        Some(email) if data.mail_options().enable_password_reset => {
+            match crate::mail::send_invitation_email(
+                &email,
+                validation_result.user.as_str(),
+                &url,
+                expiry_date,
+                &data.server_url,
+                &data.mail_options(),
+            )
+            .await
+            {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!("Error sending the invitation email: {:#?}", e);
+                    false
+                }
+            }
+        }
+        _ => false,
+    };
+    Ok(invitation::ServerCreateInvitationResponse {
+        token,
+        url: url.to_string(),
+        email_sent,
+    })
+}
+
+async fn create_invitation_handler<Backend>(
+    data: web::Data<AppState<Backend>>,
+    credentials: BearerAuth,
+    request: web::Json<invitation::ClientCreateInvitationRequest>,
+) -> HttpResponse
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    create_invitation(data, credentials, request)
+        .await
+        .map(|res| HttpResponse::Ok().json(res))
+        .unwrap_or_else(error_to_http_response)
+}
+
+#[instrument(skip_all, level = "debug")]
+async fn get_invitation<Backend>(
+    data: web::Data<AppState<Backend>>,
+    request: HttpRequest,
+) -> TcpResult<invitation::ServerInvitationResponse>
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    let token = get_token_from_path(&request)?;
+    let invitation = data.get_tcp_handler().get_invitation(&token).await?;
+    Ok(invitation::ServerInvitationResponse {
+        user_id: invitation.user_id.map(UserId::into_string),
+        email: invitation.email.map(|e| e.as_str().to_owned()),
+        expiry_date: invitation.expiry_date.and_utc(),
+    })
+}
+
+async fn get_invitation_handler<Backend>(
+    data: web::Data<AppState<Backend>>,
+    request: HttpRequest,
+) -> HttpResponse
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    get_invitation(data, request)
+        .await
+        .map(|res| HttpResponse::Ok().json(res))
+        .unwrap_or_else(error_to_http_response)
+}
+
+#[instrument(skip_all, level = "debug")]
+async fn register_start<Backend>(
+    data: web::Data<AppState<Backend>>,
+    request: HttpRequest,
+    payload: web::Json<invitation::ClientInvitationRegistrationStartRequest>,
+) -> TcpResult<invitation::ServerInvitationRegistrationStartResponse>
+where
+    Backend: TcpBackendHandler + BackendHandler + OpaqueHandler + 'static,
+{
+    let token = get_token_from_path(&request)?;
+    let invitation = data.get_tcp_handler().get_invitation(&token).await?;
+    let payload = payload.into_inner();
+    let (user_id, email) =
+        validate_registration(&data, &invitation, &payload.user_id, &payload.email).await?;
+    get_registration_attributes(&data, &invitation).await?;
+    let registration = data
+        .get_opaque_handler()
+        .registration_start(registration::ClientRegistrationStartRequest {
+            username: user_id.clone(),
+            registration_start_request: payload.registration_start_request,
+        })
+        .await?;
+    let signature = get_registration_mac(
+        &data,
+        &token,
+        &user_id,
+        &email,
+        payload.display_name.as_deref(),
+        &registration.server_data,
+    )
+    .finalize()
+    .into_bytes();
+    Ok(invitation::ServerInvitationRegistrationStartResponse {
+        registration,
+        signature: base64::engine::general_purpose::STANDARD.encode(signature),
+    })
+}
+
+async fn register_start_handler<Backend>(
+    data: web::Data<AppState<Backend>>,
+    request: HttpRequest,
+    payload: web::Json<invitation::ClientInvitationRegistrationStartRequest>,
+) -> HttpResponse
+where
+    Backend: TcpBackendHandler + BackendHandler + OpaqueHandler + 'static,
+{
+    register_start(data, request, payload)
+        .await
+        .map(|res| HttpResponse::Ok().json(res))
+        .unwrap_or_else(error_to_http_response)
+}
+
+/// Creates the user, adds it to the groups and sets its password.
+async fn create_invited_user<Backend>(
+    data: &AppState<Backend>,
+    invitation: &Invitation,
+    request: CreateUserRequest,
+    registration: registration::ClientRegistrationFinishRequest,
+) -> TcpResult<()>
+where
+    Backend: BackendHandler + OpaqueHandler,
+{
+    let user_id = request.user_id.clone();
+    let handler = data.get_admin_handler();
+    handler.create_user(request).await?;
+    let result: TcpResult<()> = async {
+        for group_id in &invitation.group_ids {
+            handler.add_user_to_group(&user_id, *group_id).await?;
+        }
+        data.get_opaque_handler()
+            .registration_finish(registration)
+            .await?;
+        Ok(())
+    }
+    .await;
+    if result.is_err()
+        && let Err(e) = handler.delete_user(&user_id).await
+    {
+        warn!("Could not clean up the partially created user '{user_id}': {e:#}");
+    }
+    result
+}
+
+#[instrument(skip_all, level = "debug")]
+async fn register_finish<Backend>(
+    data: web::Data<AppState<Backend>>,
+    request: HttpRequest,
+    payload: web::Json<invitation::ClientInvitationRegistrationFinishRequest>,
+) -> TcpResult<HttpResponse>
+where
+    Backend: TcpBackendHandler + BackendHandler + OpaqueHandler + 'static,
+{
+    let token = get_token_from_path(&request)?;
+    let invitation = data.get_tcp_handler().get_invitation(&token).await?;
+    let payload = payload.into_inner();
+    let (user_id, email) =
+        validate_registration(&data, &invitation, &payload.user_id, &payload.email).await?;
+    let signature = base64::engine::general_purpose::STANDARD
+        .decode(&payload.signature)
+        .map_err(|_| TcpError::BadRequest("Invalid registration signature".to_owned()))?;
+    get_registration_mac(
+        &data,
+        &token,
+        &user_id,
+        &email,
+        payload.display_name.as_deref(),
+        &payload.registration.server_data,
+    )
+    .verify_slice(&signature)
+    .map_err(|_| TcpError::BadRequest("Invalid registration signature".to_owned()))?;
+    let attributes = get_registration_attributes(&data, &invitation).await?;
+    // Consume the invitation before creating the user, so that it cannot be used twice.
+    data.get_tcp_handler().delete_invitation(&token).await?;
+    let create_request = CreateUserRequest {
+        user_id: user_id.clone(),
+        email,
+        display_name: payload.display_name.filter(|n| !n.is_empty()),
+        attributes,
+        // The admin chose that address, there is nothing more to confirm.
+        email_verified: invitation.email.is_some(),
+    };
+    if let Err(e) =
+        create_invited_user(&data, &invitation, create_request, payload.registration).await
+    {
+        // Give the invitee another chance.
+        if let Err(e) = data.get_tcp_handler().create_invitation(invitation).await {
+            warn!("Could not restore the invitation: {e:#}");
+        }
+        return Err(e);
+    }
+    // Recorded on behalf of the admin who created the invitation.
+    data.record_audit_event(
+        &request,
+        Some(&invitation.created_by),
+        AuditAction::CreateUser,
+        Some(user_id.to_string()),
+        true,
+    )
+    .await;
+    info!(
+        "User '{user_id}' registered through an invitation from '{}'",
+        &invitation.created_by
+    );
+    debug!(groups = ?invitation.group_ids);
+    // The invitee chose the address: it has to be confirmed before password resets are sent to it.
+    if invitation.email.is_none() && data.mail_options().enable_password_reset {
+        let result = match data.get_readonly_handler().get_user_details(&user_id).await {
+            Ok(user) => crate::email_verification::send_verification_email(&data, &user).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            warn!("Could not send the verification link to '{user_id}': {e:#}");
+        }
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn register_finish_handler<Backend>(
+    data: web::Data<AppState<Backend>>,
+    request: HttpRequest,
+    payload: web::Json<invitation::ClientInvitationRegistrationFinishRequest>,
+) -> HttpResponse
+where
+    Backend: TcpBackendHandler + BackendHandler + OpaqueHandler + 'static,
+{
+    register_finish(data, request, payload)
+        .await
+        .unwrap_or_else(error_to_http_response)
+}
+
+pub fn configure_server<Backend>(cfg: &mut web::ServiceConfig)
+where
+    Backend: TcpBackendHandler + BackendHandler + OpaqueHandler + 'static,
+{
+    cfg.service(
+        web::resource("/create").route(web::post().to(create_invitation_handler::<Backend>)),
+    )
+    .service(web::resource("/{token}").route(web::get().to(get_invitation_handler::<Backend>)))
+    .service(
+        web::resource("/{token}/register/start")
+            .route(web::post().to(register_start_handler::<Backend>)),
+    )
+    .service(
+        web::resource("/{token}/register/finish")
+            .route(web::post().to(register_finish_handler::<Backend>)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{auth_service::create_jwt, tcp_server::tests::get_app_state};
+    use actix_web::{FromRequest, test::TestRequest};
+    use lldap_auth::opaque;
+    use lldap_domain::{
+        requests::CreateAttributeRequest,
+        schema::AttributeValidation,
+        types::{AttributeType, GroupDetails, Uuid},
+    };
+    use lldap_sql_backend_handler::SqlBackendHandler;
+    use std::collections::HashSet;
+
+    type Data = web::Data<AppState<SqlBackendHandler>>;
+
+    async fn get_credentials(data: &Data, user: &str, is_admin: bool) -> BearerAuth {
+        let date = chrono::Utc::now().naive_utc();
+        let groups = if is_admin {
+            HashSet::from([GroupDetails {
+                group_id: GroupId(1),
+                display_name: "lldap_admin".into(),
+                creation_date: date,
+                uuid: Uuid::from_name_and_date("lldap_admin", &date),
+                dynamic_filter: None,
+                attributes: Vec::new(),
+                modified_date: date,
+            }])
+        } else {
+            HashSet::new()
+        };
+        let token = create_jwt(
+            data.get_tcp_handler(),
+            &data.jwt_key,
+            &UserId::new(user),
+            groups,
+        )
+        .await;
+        let request = TestRequest::default()
+            .insert_header(("Authorization", format!("Bearer {}", token.as_str())))
+            .to_http_request();
+        BearerAuth::extract(&request).await.unwrap()
+    }
+
+    fn invitation_request(
+        user_id: Option<&str>,
+        email: Option<&str>,
+        group_ids: Vec<i32>,
+    ) -> invitation::ClientCreateInvitationRequest {
+        invitation::ClientCreateInvitationRequest {
+            user_id: user_id.map(str::to_owned),
+            email: email.map(str::to_owned),
+            group_ids,
+            validity_days: None,
+            attributes: Vec::new(),
+        }
+    }
+
+    async fn invite(
+        data: &Data,
+        is_admin: bool,
+        request: invitation::ClientCreateInvitationRequest,
+    ) -> TcpResult<invitation::ServerCreateInvitationResponse> {
+        let credentials = get_credentials(data, "bob", is_admin).await;
+        create_invitation(data.clone(), credentials, web::Json(request)).await
+    }
+
+    fn token_request(token: &str) -> HttpRequest {
+        TestRequest::default()
+            .param("token", token.to_owned())
+            .to_http_request()
+    }
+
+    /// Stores an invitation directly, without the checks of the endpoint.
+    async fn store_invitation(data: &Data, token: &str, group_ids: Vec<GroupId>) {
+        data.get_tcp_handler()
+            .create_invitation(Invitation {
+                token: token.to_owned(),
+                user_id: None,
+                email: None,
+                group_ids,
+                attributes: Vec::new(),
+                created_by: UserId::new("bob"),
+                expiry_date: chrono::Utc::now().naive_utc() + chrono::Duration::days(1),
+            })
+            .await
+            .unwrap();
+    }
+
+    /// Goes through the OPAQUE registration as "alice", but sends `finish_user_id` with the finish
+    /// request.
+    async fn register(data: &Data, token: &str, finish_user_id: &str) -> TcpResult<HttpResponse> {
+        let mut rng = rand::rngs::OsRng;
+        let client_start =
+            opaque::client::registration::start_registration(b"password", &mut rng).unwrap();
+        let start = register_start(
+            data.clone(),
+            token_request(token),
+            web::Json(invitation::ClientInvitationRegistrationStartRequest {
+                user_id: "alice".to_owned(),
+                email: "alice@example.com".to_owned(),
+                display_name: Some("Alice".to_owned()),
+                registration_start_request: client_start.message,
+            }),
+        )
+        .await?;
+        let upload = opaque::client::registration::finish_registration(
+            client_start.state,
+            start.registration.registration_response,
+            &mut rng,
+        )
+        .unwrap();
+        register_finish(
+            data.clone(),
+            token_request(token),
+            web::Json(invitation::ClientInvitationRegistrationFinishRequest {
+                user_id: finish_user_id.to_owned(),
+                email: "alice@example.com".to_owned(),
+                display_name: Some("Alice".to_owned()),
+                signature: start.signature,
+                registration: registration::ClientRegistrationFinishRequest {
+                    server_data: start.registration.server_data,
+                    registration_upload: upload.message,
+                },
+            }),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_create_and_get_invitation() {
+        let data = get_app_state().await;
+        let response = invite(
+            &data,
+            true,
+            invitation_request(Some("alice"), Some("alice@example.com"), vec![1]),
+        )
+        .await
+        .unwrap();
+        assert!(!response.email_sent);
+        assert!(
+            response
+                .url
+                .ends_with(&format!("/invitation/{}", response.token))
+        );
+        let invitation = get_invitation(data.clone(), token_request(&response.token))
+            .await
+            .unwrap();
+        assert_eq!(invitation.user_id.as_deref(), Some("alice"));
+        assert_eq!(invitation.email.as_deref(), Some("alice@example.com"));
+        assert!(invitation.expiry_date > chrono::Utc::now() + chrono::Duration::days(6));
+        assert!(
+            get_invitation(data.clone(), token_request("unknown"))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_invitation_rejected() {
+        let data = get_app_state().await;
+        assert!(matches!(
+            invite(&data, false, invitation_request(None, None, vec![])).await,
+            Err(TcpError::ForbiddenError(_))
+        ));
+        for validity_days in [0, MAX_VALIDITY_DAYS + 1] {
+            let request = invitation::ClientCreateInvitationRequest {
+                validity_days: Some(validity_days),
+                ..invitation_request(None, None, vec![])
+            };
+            assert!(matches!(
+                invite(&data, true, request).await,
+                Err(TcpError::BadRequest(_))
+            ));
+        }
+        assert!(matches!(
+            invite(
+                &data,
+                true,
+                invitation_request(Some("patrick"), None, vec![])
+            )
+            .await,
+            Err(TcpError::BadRequest(_))
+        ));
+        assert!(matches!(
+            invite(&data, true, invitation_request(None, Some("alice"), vec![])).await,
+            Err(TcpError::BadRequest(_))
+        ));
+        assert!(
+            invite(&data, true, invitation_request(None, None, vec![42]))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_invitation() {
+        let data = get_app_state().await;
+        data.get_tcp_handler()
+            .create_invitation(Invitation {
+                token: "expired".to_owned(),
+                user_id: None,
+                email: None,
+                group_ids: Vec::new(),
+                attributes: Vec::new(),
+                created_by: UserId::new("bob"),
+                expiry_date: chrono::Utc::now().naive_utc() - chrono::Duration::minutes(1),
+            })
+            .await
+            .unwrap();
+        assert!(
+            get_invitation(data.clone(), token_request("expired"))
+                .await
+                .is_err()
+        );
+        assert!(register(&data, "expired", "alice").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_registration() {
+        let data = get_app_state().await;
+        let open = Invitation {
+            token: "open".to_owned(),
+            user_id: None,
+            email: None,
+            group_ids: Vec::new(),
+            attributes: Vec::new(),
+            created_by: UserId::new("bob"),
+            expiry_date: chrono::Utc::now().naive_utc(),
+        };
+        let imposed = Invitation {
+            user_id: Some(UserId::new("alice")),
+            email: Some("alice@example.com".into()),
+            ..open.clone()
+        };
+        assert_eq!(
+            validate_registration(&data, &imposed, "alice", "other@example.com")
+                .await
+                .unwrap(),
+            (UserId::new("alice"), Email::from("alice@example.com"))
+        );
+        for (invitation, user_id, email) in [
+            (&imposed, "carol", "carol@example.com"),
+            (&open, "", "alice@example.com"),
+            (&open, "al ice", "alice@example.com"),
+            (&open, "alice", "no email"),
+            (&open, "patrick", "patrick@example.com"),
+        ] {
+            assert!(
+                matches!(
+                    validate_registration(&data, invitation, user_id, email).await,
+                    Err(TcpError::BadRequest(_))
+                ),
+                "{user_id} {email}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_register_with_invitation() {
+        let data = get_app_state().await;
+        let response = invite(
+            &data,
+            true,
+            invitation_request(Some("alice"), Some("alice@example.com"), vec![1]),
+        )
+        .await
+        .unwrap();
+        register(&data, &response.token, "alice").await.unwrap();
+        let alice = UserId::new("alice");
+        let user = data
+            .get_readonly_handler()
+            .get_user_details(&alice)
+            .await
+            .unwrap();
+        assert_eq!(user.email.as_str(), "alice@example.com");
+        assert_eq!(user.display_name.as_deref(), Some("Alice"));
+        assert!(user.email_verified);
+        let groups = data
+            .get_readonly_handler()
+            .get_user_groups(&alice)
+            .await
+            .unwrap();
+        assert_eq!(
+            groups.into_iter().map(|g| g.group_id).collect::<Vec<_>>(),
+            vec![GroupId(1)]
+        );
+        // The invitation can only be used once.
+        assert!(
+            get_invitation(data.clone(), token_request(&response.token))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_register_with_tampered_details() {
+        let data = get_app_state().await;
+        store_invitation(&data, "open", Vec::new()).await;
+        assert!(matches!(
+            register(&data, "open", "mallory").await,
+            Err(TcpError::BadRequest(_))
+        ));
+        assert!(
+            data.get_readonly_handler()
+                .get_user_details(&UserId::new("mallory"))
+                .await
+                .is_err()
+        );
+        data.get_tcp_handler().get_invitation("open").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_register_failure_restores_invitation() {
+        let data = get_app_state().await;
+        // The group was deleted after the invitation was created.
+        let group_id = data
+            .get_admin_handler()
+            .create_group(lldap_domain::requests::CreateGroupRequest {
+                display_name: "Deleted Group".into(),
+                attributes: Vec::new(),
+            })
+            .await
+            .unwrap();
+        store_invitation(&data, "open", vec![group_id]).await;
+        data.get_admin_handler()
+            .delete_group(group_id)
+            .await
+            .unwrap();
+
+        assert!(register(&data, "open", "alice").await.is_err());
+        // The partially created user is removed, and the invitee can try again.
+        assert!(
+            data.get_readonly_handler()
+                .get_user_details(&UserId::new("alice"))
+                .await
+                .is_err()
+        );
+        data.get_tcp_handler().get_invitation("open").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_invitation_with_required_attribute() {
+        let data = get_app_state().await;
+        data.get_admin_handler()
+            .add_user_attribute(CreateAttributeRequest {
+                name: "employee_id".into(),
+                attribute_type: AttributeType::Integer,
+                is_list: false,
+                is_visible: true,
+                is_editable: false,
+                validation: AttributeValidation {
+                    is_required: true,
+                    ..Default::default()
+                },
+            })
+            .await
+            .unwrap();
+        let with_attribute = |name: &str, value: &str| invitation::ClientCreateInvitationRequest {
+            attributes: vec![invitation::InvitationAttribute {
+                name: name.to_owned(),
+                value: vec![value.to_owned()],
+            }],
+            ..invitation_request(None, None, vec![])
+        };
+        for request in [
+            invitation_request(None, None, vec![]),
+            with_attribute("employee_id", "not a number"),
+            with_attribute("unknown", "42"),
+        ] {
+            assert!(matches!(
+                invite(&data, true, request).await,
+                Err(TcpError::BadRequest(_))
+            ));
+        }
+
+        // An invitation created before the attribute was required can't be used anymore.
+        store_invitation(&data, "old", Vec::new()).await;
+        assert!(matches!(
+            register(&data, "old", "alice").await,
+            Err(TcpError::BadRequest(_))
+        ));
+        assert!(
+            data.get_readonly_handler()
+                .get_user_details(&UserId::new("alice"))
+                .await
+                .is_err()
+        );
+
+        let response = invite(&data, true, with_attribute("employee_id", "42"))
+            .await
+            .unwrap();
+        register(&data, &response.token, "alice").await.unwrap();
+        let user = data
+            .get_readonly_handler()
+            .get_user_details(&UserId::new("alice"))
+            .await
+            .unwrap();
+        assert!(user.attributes.contains(&Attribute {
+            name: "employee_id".into(),
+            value: 42i64.into(),
+        }));
+    }
+}
@@ -33,6 +33,47 @@ pub enum PasswordResetTokens {
     ExpiryDate,
 }
 
+/// Contains the invitations to create an account, sent by email or shared by an admin.
+#[derive(DeriveIden)]
+pub enum Invitations {
+    Table,
+    Token,
+    UserId,
+    Email,
+    GroupIds,
+    Attributes,
+    CreatedBy,
+    ExpiryDate,
+}
+
+/// Contains the WebAuthn authenticators (passkeys) registered by the users.
+#[derive(DeriveIden)]
+pub enum WebauthnCredentials {
+    Table,
+    CredentialId,
+    UserId,
+    DisplayName,
+    Credential,
+    CreationDate,
+    LastUsedDate,
+}
+
+/// Contains the webhook calls, both the ones to be retried and the log of the past ones.
+#[derive(DeriveIden)]
+pub enum WebhookDeliveries {
+    Table,
+    Id,
+    Url,
+    EventType,
+    Payload,
+    Status,
+    Attempts,
+    CreationDate,
+    NextAttemptDate,
+    LastAttemptDate,
+    LastResponse,
+}
+
 /// This needs to be initialized after the domain tables are.
 pub async fn init_table(pool: &DbConnection) -> std::result::Result<(), sea_orm::DbErr> {
     let builder = pool.get_database_backend();
@@ -142,5 +183,120 @@ pub async fn init_table(pool: &DbConnection) -> std::result::Result<(), sea_orm:
     )
     .await?;
 
+    pool.execute(
+        builder.build(
+            Table::create()
+                .table(WebauthnCredentials::Table)
+                .if_not_exists()
+                .col(
+                    ColumnDef::new(WebauthnCredentials::CredentialId)
+                        .string_len(512)
+                        .not_null()
+                        .primary_key(),
+                )
+                .col(
+                    ColumnDef::new(WebauthnCredentials::UserId)
+                        .string_len(255)
+                        .not_null(),
+                )
+                .col(
+                    ColumnDef::new(WebauthnCredentials::DisplayName)
+                        .string_len(255)
+                        .not_null(),
+                )
+                .col(
+                    ColumnDef::new(WebauthnCredentials::Credential)
+                        .blob()
+                        .not_null(),
+                )
+                .col(
+                    ColumnDef::new(WebauthnCredentials::CreationDate)
+                        .date_time()
+                        .not_null(),
+                )
+                .col(ColumnDef::new(WebauthnCredentials::LastUsedDate).date_time())
+                .foreign_key(
+                    ForeignKey::create()
+                        .name("WebauthnCredentialsUserForeignKey")
+                        .from(WebauthnCredentials::Table, WebauthnCredentials::UserId)
+                        .to(Users::Table, Users::UserId)
+                        .on_delete(ForeignKeyAction::Cascade)
+                        .on_update(ForeignKeyAction::Cascade),
+                ),
+        ),
+    )
+    .await?;
+
+    pool.execute(
+        builder.build(
+            Table::create()
+                .table(Invitations::Table)
+                .if_not_exists()
+                .col(
+                    ColumnDef::new(Invitations::Token)
+                        .string_len(255)
+                        .not_null()
+                        .primary_key(),
+                )
+                .col(ColumnDef::new(Invitations::UserId).string_len(255))
+                .col(ColumnDef::new(Invitations::Email).string_len(255))
+                .col(ColumnDef::new(Invitations::GroupIds).text().not_null())
+                .col(ColumnDef::new(Invitations::Attributes).text().not_null())
+                .col(
+                    ColumnDef::new(Invitations::CreatedBy)
+                        .string_len(255)
+                        .not_null(),
+                )
+                .col(
+                    ColumnDef::new(Invitations::ExpiryDate)
+                        .date_time()
+                        .not_null(),
+                ),
+        ),
+    )
+    .await?;
+
+    pool.execute(
+        builder.build(
+            Table::create()
+                .table(WebhookDeliveries::Table)
+                .if_not_exists()
+                .col(
+                    ColumnDef::new(WebhookDeliveries::Id)
+                        .big_integer()
+                        .auto_increment()
+                        .not_null()
+                        .primary_key(),
+                )
+                .col(ColumnDef::new(WebhookDeliveries::Url).text().not_null())
+                .col(
+                    ColumnDef::new(WebhookDeliveries::EventType)
+                        .string_len(64)
+                        .not_null(),
+                )
+                .col(ColumnDef::new(WebhookDeliveries::Payload).text().not_null())
+                .col(
+                    ColumnDef::new(WebhookDeliveries::Status)
+                        .string_len(16)
+                        .not_null(),
+                )
+                .col(
+                    ColumnDef::new(WebhookDeliveries::Attempts)
+                        .integer()
+                        .default(0)
+                        .not_null(),
+                )
+                .col(
+                    ColumnDef::new(WebhookDeliveries::CreationDate)
+                        .date_time()
+                        .not_null(),
+                )
+                .col(ColumnDef::new(WebhookDeliveries::NextAttemptDate).date_time())
+                .col(ColumnDef::new(WebhookDeliveries::LastAttemptDate).date_time())
+                .col(ColumnDef::new(WebhookDeliveries::LastResponse).text()),
+        ),
+    )
+    .await?;
+
     Ok(())
 }
@@ -1,24 +1,184 @@
-use crate::configuration::{Configuration, LdapsOptions};
+use crate::configuration::Configuration;
+use crate::metrics::{Metrics, get_ldap_operation_name, get_ldap_result_code};
+use crate::reload::ReloadableConfig;
 use crate::tls;
 use actix_rt::net::TcpStream;
 use actix_server::ServerBuilder;
 use actix_service::{ServiceFactoryExt, fn_service};
 use anyhow::{Context, Result};
-use ldap3_proto::{LdapCodec, control::LdapControl, proto::LdapMsg, proto::LdapOp};
+use ldap3_proto::{
+    LdapCodec,
+    control::LdapControl,
+    proto::{LdapFilter, LdapMsg, LdapOp},
+};
 use lldap_access_control::AccessControlledBackendHandler;
-use lldap_domain_handlers::handler::{BackendHandler, LoginHandler};
+use lldap_domain_handlers::{
+    audit::{AuditInterface, AuditSource},
+    handler::{BackendHandler, LoginHandler},
+};
 use lldap_ldap::{LdapHandler, LdapInfo};
 use lldap_opaque_handler::OpaqueHandler;
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use tokio_rustls::TlsAcceptor as RustlsTlsAcceptor;
 use tokio_util::codec::{FramedRead, FramedWrite};
 use tracing::{debug, error, info, instrument};
 use uuid::Uuid;
 
-#[instrument(skip_all, level = "info", name = "LDAP request", fields(session_id = %session.session_uuid()))]
+/// The parts of a request that go in the access log.
+enum AccessLogRequest {
+    Search {
+        base: String,
+        scope: String,
+        filter: String,
+    },
+    Other {
+        dn: String,
+    },
+}
+
+impl AccessLogRequest {
+    fn new(op: &LdapOp) -> Self {
+        let dn = match op {
+            LdapOp::SearchRequest(request) => {
+                return Self::Search {
+                    base: request.base.clone(),
+                    scope: format!("{:?}", request.scope).to_ascii_lowercase(),
+                    filter: filter_to_string(&request.filter),
+                };
+            }
+            LdapOp::BindRequest(request) => request.dn.clone(),
+            LdapOp::ModifyRequest(request) => request.dn.clone(),
+            LdapOp::AddRequest(request) => request.dn.clone(),
+            LdapOp::DelRequest(dn) => dn.clone(),
+            LdapOp::CompareRequest(request) => request.dn.clone(),
+            // The OID of the extended operation.
+            LdapOp::ExtendedRequest(request) => request.name.clone(),
+            _ => String::new(),
+        };
+        Self::Other { dn }
+    }
+}
+
+/// Escapes the characters that have a meaning in the string representation of a filter.
+fn escape_filter_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '*' => escaped.push_str("\\2a"),
+            '(' => escaped.push_str("\\28"),
+            ')' => escaped.push_str("\\29"),
+            '\\' => escaped.push_str("\\5c"),
+            '\0' => escaped.push_str("\\00"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Formats the filter in the RFC 4515 string representation.
+fn filter_to_string(filter: &LdapFilter) -> String {
+    let join = |filters: &[LdapFilter]| filters.iter().map(filter_to_string).collect::<String>();
+    let escape = escape_filter_value;
+    match filter {
+        LdapFilter::And(filters) => format!("(&{})", join(filters)),
+        LdapFilter::Or(filters) => format!("(|{})", join(filters)),
+        LdapFilter::Not(filter) => format!("(!{})", filter_to_string(filter)),
+        LdapFilter::Equality(attribute, value) => format!("({attribute}={})", escape(value)),
+        LdapFilter::Approx(attribute, value) => format!("({attribute}~={})", escape(value)),
+        LdapFilter::GreaterOrEqual(attribute, value) => {
+            format!("({attribute}>={})", escape(value))
+        }
+        LdapFilter::LessOrEqual(attribute, value) => format!("({attribute}<={})", escape(value)),
+        LdapFilter::Present(attribute) => format!("({attribute}=*)"),
+        LdapFilter::Substring(attribute, substring) => {
+            let mut value = substring.initial.as_deref().map(escape).unwrap_or_default();
+            for any in &substring.any {
+                value.push('*');
+                value.push_str(&escape(any));
+            }
+            value.push('*');
+            value.push_str(&substring.final_.as_deref().map(escape).unwrap_or_default());
+            format!("({attribute}={value})")
+        }
+        _ => format!("{filter:?}"),
+    }
+}
+
+/// Logs one line per operation, similar to OpenLDAP's "stats" log level.
+fn log_ldap_access(
+    session_id: &Uuid,
+    msgid: i32,
+    user: Option<&str>,
+    operation: &str,
+    request: AccessLogRequest,
+    responses: &[LdapOp],
+    duration: Duration,
+) {
+    let user = user.unwrap_or("-");
+    let result = responses
+        .last()
+        .and_then(get_ldap_result_code)
+        .map_or_else(|| "none".to_owned(), |code| format!("{code:?}"));
+    let duration_ms = duration.as_secs_f64() * 1000.0;
+    match request {
+        AccessLogRequest::Search {
+            base,
+            scope,
+            filter,
+        } => {
+            let entries = responses
+                .iter()
+                .filter(|response| matches!(response, LdapOp::SearchResultEntry(_)))
+                .count();
+            info!(
+                target: "lldap::ldap_access",
+                %session_id,
+                msgid,
+                user,
+                operation,
+                %base,
+                %scope,
+                %filter,
+                %result,
+                entries,
+                duration_ms,
+                "LDAP access"
+            );
+        }
+        AccessLogRequest::Other { dn } => info!(
+            target: "lldap::ldap_access",
+            %session_id,
+            msgid,
+            user,
+            operation,
+            %dn,
+            %result,
+            duration_ms,
+            "LDAP access"
+        ),
+    }
+}
+
+#[instrument(
+    skip_all,
+    level = "info",
+    name = "LDAP request",
+    fields(
+        session_id = %session.session_uuid(),
+        message_id = tracing::field::Empty,
+        operation = tracing::field::Empty,
+    )
+)]
 async fn handle_ldap_message<Backend, Writer>(
     msg: Result<LdapMsg, std::io::Error>,
     resp: &mut Writer,
     session: &mut LdapHandler<Backend>,
+    metrics: &Metrics,
+    access_log: bool,
 ) -> Result<bool>
 where
     Backend: BackendHandler + LoginHandler + OpaqueHandler,
@@ -27,15 +187,43 @@ where
 {
     use futures_util::SinkExt;
     let msg = msg.context("while receiving LDAP op")?;
+    let operation = get_ldap_operation_name(&msg.op);
+    tracing::Span::current()
+        .record("message_id", msg.msgid)
+        .record("operation", operation);
     for control in msg.ctrl.iter() {
         if let LdapControl::Unknown { oid, .. } = control {
             info!("Received unknown control: {}, ignoring", oid);
         }
     }
     debug!(?msg);
-    match session.handle_ldap_message(msg.op).await {
-        None => return Ok(false),
+    // The user the operation runs as, before it possibly changes with a bind.
+    let access_log_request = access_log.then(|| {
+        (
+            AccessLogRequest::new(&msg.op),
+            session.bound_user().map(|user| user.to_string()),
+        )
+    });
+    let start = Instant::now();
+    let result = session.handle_ldap_message(msg.op).await;
+    if let Some((request, user)) = access_log_request {
+        log_ldap_access(
+            session.session_uuid(),
+            msg.msgid,
+            user.as_deref(),
+            operation,
+            request,
+            result.as_deref().unwrap_or_default(),
+            start.elapsed(),
+        );
+    }
+    match result {
+        None => {
+            metrics.record_ldap_operation(operation, &[]);
+            return Ok(false);
+        }
         Some(result) => {
+            metrics.record_ldap_operation(operation, &result);
             if result.is_empty() {
                 debug!("No response");
             }
@@ -69,8 +257,11 @@ where
 
 async fn handle_ldap_stream<Stream, Backend>(
     stream: Stream,
-    backend_handler: Backend,
+    backend_handler: AccessControlledBackendHandler<Backend>,
     ldap_info: &'static LdapInfo,
+    peer_addr: Option<SocketAddr>,
+    metrics: &Metrics,
+    access_log: bool,
 ) -> Result<Stream>
 where
     Backend: BackendHandler + LoginHandler + OpaqueHandler + 'static,
@@ -84,14 +275,18 @@ where
 
     let session_uuid = Uuid::new_v4();
     let mut session = LdapHandler::new(
-        AccessControlledBackendHandler::new(backend_handler),
+        backend_handler.with_audit_source(AuditSource::new(
+            AuditInterface::Ldap,
+            peer_addr.map(|addr| addr.ip()),
+        )),
         ldap_info,
         session_uuid,
     );
 
     info!("LDAP session start: {}", session_uuid);
+    let _session_guard = metrics.start_ldap_session();
     while let Some(msg) = requests.next().await {
-        if !handle_ldap_message(msg, &mut resp, &mut session)
+        if !handle_ldap_message(msg, &mut resp, &mut session, metrics, access_log)
             .await
             .context("while handling incoming messages")?
         {
@@ -102,25 +297,25 @@ where
     Ok(requests.into_inner().unsplit(resp.into_inner()))
 }
 
-fn get_tls_acceptor(ldaps_options: &LdapsOptions) -> Result<RustlsTlsAcceptor> {
-    let certs = tls::load_certificates(&ldaps_options.cert_file)?;
-    let private_key = tls::load_private_key(&ldaps_options.key_file)?;
-
-    let server_config = std::sync::Arc::new(
-        rustls::ServerConfig::builder_with_provider(
-            rustls::crypto::ring::default_provider().into(),
+pub fn make_ldap_info(config: &Configuration) -> Result<LdapInfo> {
+    LdapInfo::new(
+        &config.ldap_base_dn,
+        config.ignored_user_attributes.clone(),
+        config.ignored_group_attributes.clone(),
+    )
+    .with_context(|| {
+        format!(
+            "Invalid value for ldap_base_dn in configuration: {}",
+            &config.ldap_base_dn
         )
-        .with_safe_default_protocol_versions()
-        .context("Failed to set default protocol versions")?
-        .with_no_client_auth()
-        .with_single_cert(certs, private_key)?,
-    );
-    Ok(server_config.into())
+    })
 }
 
 pub fn build_ldap_server<Backend>(
     config: &Configuration,
-    backend_handler: Backend,
+    backend_handler: AccessControlledBackendHandler<Backend>,
+    metrics: Arc<Metrics>,
+    reloadable_config: Arc<ReloadableConfig>,
     server_builder: ServerBuilder,
 ) -> Result<ServerBuilder>
 where
@@ -128,19 +323,9 @@ where
 {
     let context = (
         backend_handler,
-        Box::leak(Box::new(
-            LdapInfo::new(
-                &config.ldap_base_dn,
-                config.ignored_user_attributes.clone(),
-                config.ignored_group_attributes.clone(),
-            )
-            .with_context(|| {
-                format!(
-                    "Invalid value for ldap_base_dn in configuration: {}",
-                    &config.ldap_base_dn
-                )
-            })?,
-        )) as &'static LdapInfo,
+        metrics,
+        config.ldap_access_log,
+        reloadable_config.clone(),
     );
 
     let context_for_tls = context.clone();
@@ -150,8 +335,11 @@ where
         fn_service(move |stream: TcpStream| {
             let context = context.clone();
             async move {
-                let (handler, ldap_info) = context;
-                handle_ldap_stream(stream, handler, ldap_info).await
+                let (handler, metrics, access_log, reloadable_config) = context;
+                let peer_addr = stream.peer_addr().ok();
+                let ldap_info = reloadable_config.ldap_info();
+                handle_ldap_stream(stream, handler, ldap_info, peer_addr, &metrics, access_log)
+                    .await
             }
         })
         .map_err(|err: anyhow::Error| error!("[LDAP] Service Error: {:#}", err))
@@ -162,19 +350,30 @@ where
         .bind("ldap", (config.ldap_host.clone(), config.ldap_port), binder)
         .with_context(|| format!("while binding to the port {}", config.ldap_port));
     if config.ldaps_options.enabled {
-        let tls_context = (
-            context_for_tls,
-            get_tls_acceptor(&config.ldaps_options)
-                .context("while setting up the SSL certificate")?,
-        );
+        let tls_acceptor: RustlsTlsAcceptor = Arc::new(
+            tls::make_server_config(
+                reloadable_config
+                    .ldaps_certificate()
+                    .context("LDAPS is enabled but has no certificate")?,
+            )
+            .context("while setting up the SSL certificate")?,
+        )
+        .into();
+        let tls_context = (context_for_tls, tls_acceptor);
         let tls_binder = move || {
             let tls_context = tls_context.clone();
             fn_service(move |stream: TcpStream| {
                 let tls_context = tls_context.clone();
                 async move {
-                    let ((handler, ldap_info), tls_acceptor) = tls_context;
+                    let ((handler, metrics, access_log, reloadable_config), tls_acceptor) =
+                        tls_context;
+                    let peer_addr = stream.peer_addr().ok();
+                    let ldap_info = reloadable_config.ldap_info();
                     let tls_stream = tls_acceptor.accept(stream).await?;
-                    handle_ldap_stream(tls_stream, handler, ldap_info).await
+                    handle_ldap_stream(
+                        tls_stream, handler, ldap_info, peer_addr, &metrics, access_log,
+                    )
+                    .await
                 }
             })
             .map_err(|err: anyhow::Error| error!("[LDAPS] Service Error: {:#}", err))
@@ -196,3 +395,55 @@ where
         server_builder
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ldap3_proto::proto::LdapSubstringFilter;
+
+    #[test]
+    fn test_filter_to_string() {
+        let filter = LdapFilter::And(vec![
+            LdapFilter::Equality("objectClass".to_owned(), "person".to_owned()),
+            LdapFilter::Or(vec![
+                LdapFilter::Present("mail".to_owned()),
+                LdapFilter::Not(Box::new(LdapFilter::Approx(
+                    "cn".to_owned(),
+                    "bob".to_owned(),
+                ))),
+            ]),
+            LdapFilter::Substring(
+                "uid".to_owned(),
+                LdapSubstringFilter {
+                    initial: Some("a".to_owned()),
+                    any: vec!["b".to_owned(), "c".to_owned()],
+                    final_: None,
+                },
+            ),
+        ]);
+        assert_eq!(
+            filter_to_string(&filter),
+            "(&(objectClass=person)(|(mail=*)(!(cn~=bob)))(uid=a*b*c*))"
+        );
+    }
+
+    #[test]
+    fn test_filter_to_string_escapes_values() {
+        let filter = LdapFilter::And(vec![
+            LdapFilter::Equality("cn".to_owned(), "a*(b)\\c\0".to_owned()),
+            LdapFilter::GreaterOrEqual("uidNumber".to_owned(), "10".to_owned()),
+            LdapFilter::Substring(
+                "uid".to_owned(),
+                LdapSubstringFilter {
+                    initial: Some("(".to_owned()),
+                    any: vec!["*".to_owned()],
+                    final_: Some(")".to_owned()),
+                },
+            ),
+        ]);
+        assert_eq!(
+            filter_to_string(&filter),
+            r"(&(cn=a\2a\28b\29\5cc\00)(uidNumber>=10)(uid=\28*\2a*\29))"
+        );
+    }
+}
@@ -0,0 +1,439 @@
+use crate::import_export::{DirectoryRecords, GroupRecord, UserRecord, map_attribute_name};
+use anyhow::{Context, Result, bail};
+use base64::Engine;
+use lldap_domain::types::{AttributeValue, Group, UserAndGroups};
+use lldap_graphql_server::query::attribute::serialize_attribute_to_graphql;
+use std::collections::BTreeMap;
+
+/// The maximum length of a line, after which it is folded.
+const MAX_LINE_LENGTH: usize = 76;
+
+/// The LDAP names of the hardcoded attributes, as exposed by the LDAP server.
+const LDAP_ATTRIBUTE_NAMES: &[(&str, &str)] = &[
+    ("first_name", "givenName"),
+    ("last_name", "sn"),
+    ("avatar", "jpegPhoto"),
+];
+
+/// Attributes maintained by the server, ignored on import.
+const IGNORED_ATTRIBUTES: &[&str] = &[
+    "objectclass",
+    "memberof",
+    "entryuuid",
+    "createtimestamp",
+    "modifytimestamp",
+    "userpassword",
+];
+
+const USER_OBJECT_CLASSES: &[&str] = &[
+    "person",
+    "organizationalperson",
+    "inetorgperson",
+    "posixaccount",
+];
+const GROUP_OBJECT_CLASSES: &[&str] = &["groupofnames", "groupofuniquenames", "posixgroup"];
+
+fn ldap_attribute_name(name: &str) -> &str {
+    LDAP_ATTRIBUTE_NAMES
+        .iter()
+        .find(|(lldap, _)| *lldap == name)
+        .map(|(_, ldap)| *ldap)
+        .unwrap_or(name)
+}
+
+fn lldap_attribute_name(name: &str) -> &str {
+    LDAP_ATTRIBUTE_NAMES
+        .iter()
+        .find(|(_, ldap)| ldap.eq_ignore_ascii_case(name))
+        .map(|(lldap, _)| *lldap)
+        .unwrap_or(name)
+}
+
+/// Escapes a value to be used in a DN, following RFC 4514.
+fn escape_dn_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    let last = value.chars().count().saturating_sub(1);
+    for (i, c) in value.chars().enumerate() {
+        let needs_escape = matches!(c, '\\' | ',' | '+' | '"' | '<' | '>' | ';' | '=')
+            || (i == 0 && (c == '#' || c == ' '))
+            || (i == last && c == ' ');
+        if needs_escape {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// The value of the first RDN of a DN, e.g. "bob" for "uid=bob,ou=people,dc=example,dc=com".
+fn first_rdn_value(dn: &str) -> Option<String> {
+    let (_, rest) = dn.split_once('=')?;
+    let mut value = String::new();
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => value.push(chars.next()?),
+            ',' | '+' => break,
+            c => value.push(c),
+        }
+    }
+    Some(value.trim().to_owned())
+}
+
+/// Whether the value can be written as is, according to the SAFE-STRING rule of RFC 2849.
+fn is_safe_string(value: &str) -> bool {
+    !value.starts_with([' ', ':', '<'])
+        && !value.ends_with(' ')
+        && value
+            .chars()
+            .all(|c| c.is_ascii() && !matches!(c, '\0' | '\r' | '\n'))
+}
+
+fn write_line(out: &mut String, line: &str) {
+    // The lines only contain ASCII characters, they can be split anywhere.
+    let mut rest = line;
+    let mut first = true;
+    while !rest.is_empty() || first {
+        let max = if first {
+            MAX_LINE_LENGTH
+        } else {
+            MAX_LINE_LENGTH - 1
+        };
+        let (chunk, remainder) = rest.split_at(rest.len().min(max));
+        if !first {
+            out.push(' ');
+        }
+        out.push_str(chunk);
+        out.push('\n');
+        rest = remainder;
+        first = false;
+    }
+}
+
+fn write_value(out: &mut String, name: &str, value: &str) {
+    if is_safe_string(value) {
+        write_line(out, &format!("{name}: {value}"));
+    } else {
+        write_line(
+            out,
+            &format!(
+                "{name}:: {}",
+                base64::engine::general_purpose::STANDARD.encode(value)
+            ),
+        );
+    }
+}
+
+fn write_attribute(out: &mut String, name: &str, value: &AttributeValue) {
+    let name = ldap_attribute_name(name);
+    let is_binary = matches!(value, AttributeValue::JpegPhoto(_));
+    for value in serialize_attribute_to_graphql(value) {
+        if is_binary {
+            // Photos are already serialized in base64.
+            write_line(out, &format!("{name}:: {value}"));
+        } else {
+            write_value(out, name, &value);
+        }
+    }
+}
+
+/// Exports the users and groups as LDIF entries, with the same DNs as the LDAP server.
+pub fn write(users: &[UserAndGroups], groups: &[Group], base_dn: &str) -> String {
+    let mut out = String::from("version: 1\n");
+    for user in users.iter().map(|u| &u.user) {
+        out.push('\n');
+        write_value(
+            &mut out,
+            "dn",
+            &format!(
+                "uid={},ou=people,{base_dn}",
+                escape_dn_value(user.user_id.as_str())
+            ),
+        );
+        write_line(&mut out, "objectClass: inetOrgPerson");
+        write_value(&mut out, "uid", user.user_id.as_str());
+        write_value(&mut out, "mail", user.email.as_str());
+        if let Some(display_name) = &user.display_name {
+            write_value(&mut out, "cn", display_name);
+        }
+        for attribute in &user.attributes {
+            write_attribute(&mut out, attribute.name.as_str(), &attribute.value);
+        }
+    }
+    for group in groups {
+        out.push('\n');
+        write_value(
+            &mut out,
+            "dn",
+            &format!(
+                "cn={},ou=groups,{base_dn}",
+                escape_dn_value(group.display_name.as_str())
+            ),
+        );
+        write_line(&mut out, "objectClass: groupOfUniqueNames");
+        write_value(&mut out, "cn", group.display_name.as_str());
+        for member in &group.users {
+            write_value(
+                &mut out,
+                "uniqueMember",
+                &format!(
+                    "uid={},ou=people,{base_dn}",
+                    escape_dn_value(member.as_str())
+                ),
+            );
+        }
+        for attribute in &group.attributes {
+            write_attribute(&mut out, attribute.name.as_str(), &attribute.value);
+        }
+    }
+    out
+}
+
+/// The lines of the file with the folded lines joined, and the comments removed. An empty
+/// string separates the records.
+fn unfold_lines(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_comment = false;
+    for line in content.lines().map(|l| l.strip_suffix('\r').unwrap_or(l)) {
+        if let Some(continuation) = line.strip_prefix(' ') {
+            if !in_comment && let Some(last) = lines.last_mut() {
+                last.push_str(continuation);
+            }
+        } else if line.starts_with('#') {
+            in_comment = true;
+        } else {
+            in_comment = false;
+            lines.push(line.to_owned());
+        }
+    }
+    lines
+}
+
+/// Parses an "attribute: value" line. Base64 values that are not valid UTF-8, like photos, are
+/// kept encoded.
+fn parse_line(line: &str) -> Result<(String, String)> {
+    let (name, value) = line
+        .split_once(':')
+        .with_context(|| format!("Expected `attribute: value`, found `{line}`"))?;
+    let name = name.trim().to_owned();
+    let value = if let Some(encoded) = value.strip_prefix(':') {
+        let encoded = encoded.trim();
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .with_context(|| format!("Invalid base64 value for {name}"))?;
+        String::from_utf8(bytes).unwrap_or_else(|_| encoded.to_owned())
+    } else if value.starts_with('<') {
+        bail!("Values from URLs are not supported, found `{line}`");
+    } else {
+        value.trim_start_matches(' ').to_owned()
+    };
+    Ok((name, value))
+}
+
+/// Reads the user and group entries of an LDIF file. The other entries, like the organizational
+/// units, are ignored.
+pub fn read(
+    content: &str,
+    attribute_mapping: &BTreeMap<String, String>,
+) -> Result<DirectoryRecords> {
+    let mut records = DirectoryRecords::default();
+    let mut entry: Vec<(String, String)> = Vec::new();
+    for line in unfold_lines(content) {
+        if line.is_empty() {
+            read_entry(std::mem::take(&mut entry), attribute_mapping, &mut records)?;
+            continue;
+        }
+        let (name, value) = parse_line(&line)?;
+        if name.eq_ignore_ascii_case("changetype") {
+            bail!("Change records are not supported, only entries");
+        }
+        if !(entry.is_empty() && name.eq_ignore_ascii_case("version")) {
+            entry.push((name, value));
+        }
+    }
+    read_entry(entry, attribute_mapping, &mut records)?;
+    Ok(records)
+}
+
+fn read_entry(
+    entry: Vec<(String, String)>,
+    attribute_mapping: &BTreeMap<String, String>,
+    records: &mut DirectoryRecords,
+) -> Result<()> {
+    let Some((first, dn)) = entry.first() else {
+        return Ok(());
+    };
+    if !first.eq_ignore_ascii_case("dn") {
+        bail!("Entries must start with a dn, found {first}");
+    }
+    let has_object_class = |classes: &[&str]| {
+        entry.iter().any(|(name, value)| {
+            name.eq_ignore_ascii_case("objectclass")
+                && classes.contains(&value.to_ascii_lowercase().as_str())
+        })
+    };
+    let is_user = has_object_class(USER_OBJECT_CLASSES);
+    let is_group = has_object_class(GROUP_OBJECT_CLASSES);
+    let attributes = entry[1..].iter().filter_map(|(name, value)| {
+        map_attribute_name(attribute_mapping, name).map(|name| (name, value))
+    });
+    if is_user {
+        let mut user = UserRecord::default();
+        for (name, value) in attributes {
+            match name.as_str() {
+                "uid" | "id" | "user_id" => {
+                    user.id.get_or_insert_with(|| value.clone());
+                }
+                "mail" | "email" => {
+                    user.email.get_or_insert_with(|| value.clone());
+                }
+                "cn" | "displayname" | "display_name" => {
+                    user.display_name.get_or_insert_with(|| value.clone());
+                }
+                name if IGNORED_ATTRIBUTES.contains(&name) => {}
+                name => user.add_attribute(lldap_attribute_name(name), value.clone()),
+            }
+        }
+        if user.id.is_none() {
+            user.id = first_rdn_value(dn);
+        }
+        records.users.push(user);
+    } else if is_group {
+        let mut group = GroupRecord::default();
+        for (name, value) in attributes {
+            match name.as_str() {
+                "cn" | "name" | "display_name" => {
+                    group.name.get_or_insert_with(|| value.clone());
+                }
+                "member" | "uniquemember" => group.members.extend(first_rdn_value(value)),
+                "memberuid" => group.members.push(value.clone()),
+                name if IGNORED_ATTRIBUTES.contains(&name) => {}
+                name => group.add_attribute(lldap_attribute_name(name), value.clone()),
+            }
+        }
+        if group.name.is_none() {
+            group.name = first_rdn_value(dn);
+        }
+        records.groups.push(group);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lldap_domain::types::{Attribute, GroupId, User, UserId};
+    use pretty_assertions::assert_eq;
+
+    fn make_user(id: &str, display_name: &str) -> UserAndGroups {
+        UserAndGroups {
+            user: User {
+                user_id: UserId::new(id),
+                email: format!("{id}@example.com").into(),
+                display_name: Some(display_name.to_owned()),
+                attributes: vec![Attribute {
+                    name: "first_name".into(),
+                    value: "Été".to_owned().into(),
+                }],
+                ..Default::default()
+            },
+            groups: None,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let users = vec![
+            make_user("bob", "Bob, the builder"),
+            make_user("john", " John"),
+        ];
+        let groups = vec![Group {
+            id: GroupId(3),
+            display_name: "Best, really".into(),
+            creation_date: chrono::Utc::now().naive_utc(),
+            uuid: lldap_domain::types::Uuid::from_name_and_date(
+                "Best",
+                &chrono::Utc::now().naive_utc(),
+            ),
+            users: vec![UserId::new("bob"), UserId::new("john")],
+            owners: vec![],
+            dynamic_filter: None,
+            attributes: vec![Attribute {
+                name: "club".into(),
+                value: vec!["a".to_owned(), "b".to_owned()].into(),
+            }],
+            modified_date: chrono::Utc::now().naive_utc(),
+        }];
+        let ldif = write(&users, &groups, "dc=example,dc=com");
+        assert!(ldif.contains("dn: uid=bob,ou=people,dc=example,dc=com\n"));
+        assert!(ldif.contains("givenName:: w4l0w6k=\n"));
+        assert!(ldif.contains("cn:: IEpvaG4=\n"));
+        assert!(ldif.contains("dn: cn=Best\\, really,ou=groups,dc=example,dc=com\n"));
+        let records = read(&ldif, &BTreeMap::new()).unwrap();
+        assert_eq!(
+            records.users[0],
+            UserRecord {
+                id: Some("bob".to_owned()),
+                email: Some("bob@example.com".to_owned()),
+                display_name: Some("Bob, the builder".to_owned()),
+                attributes: BTreeMap::from([("first_name".to_owned(), vec!["Été".to_owned()])]),
+                groups: vec![],
+            }
+        );
+        assert_eq!(records.users[1].display_name.as_deref(), Some(" John"));
+        assert_eq!(
+            records.groups,
+            vec![GroupRecord {
+                name: Some("Best, really".to_owned()),
+                members: vec!["bob".to_owned(), "john".to_owned()],
+                attributes: BTreeMap::from([(
+                    "club".to_owned(),
+                    vec!["a".to_owned(), "b".to_owned()]
+                )]),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_read_folded_lines_and_mapping() {
+        let ldif = "version: 1\n\
+                    # A comment\n\
+                    \n\
+                    dn: ou=people,dc=example,dc=com\n\
+                    objectClass: organizationalUnit\n\
+                    \n\
+                    dn: uid=alice,ou=people,dc=example,dc=com\n\
+                    objectClass: person\n\
+                    mail: alice@exa\n mple.com\n\
+                    telephoneNumber: 1234\n\
+                    description: ignored\n\
+                    \n";
+        let mapping = BTreeMap::from([
+            ("telephonenumber".to_owned(), "phone".to_owned()),
+            ("description".to_owned(), String::new()),
+        ]);
+        assert_eq!(
+            read(ldif, &mapping).unwrap(),
+            DirectoryRecords {
+                users: vec![UserRecord {
+                    id: Some("alice".to_owned()),
+                    email: Some("alice@example.com".to_owned()),
+                    attributes: BTreeMap::from([("phone".to_owned(), vec!["1234".to_owned()])]),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_long_lines_are_folded() {
+        let mut out = String::new();
+        write_value(&mut out, "description", &"a".repeat(200));
+        assert!(out.lines().all(|l| l.len() <= MAX_LINE_LENGTH));
+        assert_eq!(
+            unfold_lines(&out),
+            vec![format!("description: {}", "a".repeat(200))]
+        );
+    }
+}
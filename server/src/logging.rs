@@ -1,12 +1,47 @@
-use crate::configuration::Configuration;
+use crate::configuration::{Configuration, LogFormat, OpenTelemetryOptions};
+use actix_http::header::HeaderMap;
 use actix_web::{
     Error,
     dev::{ServiceRequest, ServiceResponse},
 };
+use anyhow::Context;
+use opentelemetry::{
+    KeyValue,
+    propagation::{Extractor, TextMapPropagator},
+    trace::TracerProvider as _,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    Resource,
+    propagation::TraceContextPropagator,
+    runtime,
+    trace::{Sampler, TracerProvider},
+};
 use std::env;
 use tracing::{Span, debug, error};
 use tracing_actix_web::RootSpanBuilder;
-use tracing_subscriber::{filter::EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{
+    Layer, Registry, filter::EnvFilter, layer::SubscriberExt, reload, util::SubscriberInitExt,
+};
+
+/// What is exported with OpenTelemetry, regardless of the verbosity of the logs. The HTTP clients
+/// are left out, since the exporter uses one.
+const OPENTELEMETRY_FILTER: &str =
+    "debug,sqlx=warn,reqwest=warn,hyper=warn,hyper_util=warn,h2=warn,opentelemetry=warn";
+
+/// Reads the W3C trace context (`traceparent`) of the incoming requests.
+struct RequestHeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for RequestHeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
 
 /// We will define a custom root span builder to capture additional fields, specific
 /// to our application, on top of the ones provided by `DefaultRootSpanBuilder` out of the box.
@@ -14,11 +49,16 @@ pub struct CustomRootSpanBuilder;
 
 impl RootSpanBuilder for CustomRootSpanBuilder {
     fn on_request_start(request: &ServiceRequest) -> Span {
-        tracing::debug_span!(
+        let span = tracing::debug_span!(
             "HTTP request",
             method = request.method().to_string(),
             uri = request.uri().to_string()
-        )
+        );
+        // Attach to the trace of the caller (e.g. a reverse proxy), if any.
+        span.set_parent(
+            TraceContextPropagator::new().extract(&RequestHeaderExtractor(request.headers())),
+        );
+        span
     }
 
     fn on_request_end<B>(_: Span, outcome: &Result<ServiceResponse<B>, Error>) {
@@ -35,21 +75,104 @@ impl RootSpanBuilder for CustomRootSpanBuilder {
     }
 }
 
-pub fn init(config: &Configuration) -> anyhow::Result<()> {
-    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-        EnvFilter::new(if config.verbose {
+/// Changes the log filter of the running server, see [`make_env_filter`].
+pub type LogFilterHandle = reload::Handle<EnvFilter, Registry>;
+
+/// The log filter, from `RUST_LOG` if set, otherwise from the `verbose` setting.
+pub fn make_env_filter(verbose: bool) -> EnvFilter {
+    EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        EnvFilter::new(if verbose {
             "sqlx=warn,reqwest=warn,debug"
         } else {
             "sqlx=warn,reqwest=warn,info"
         })
-    });
-    let registry = tracing_subscriber::registry()
-        .with(env_filter)
-        .with(tracing_forest::ForestLayer::default());
-    if env::var("LLDAP_RAW_LOG").is_ok() {
-        registry.with(tracing_subscriber::fmt::layer()).init();
+    })
+}
+
+pub fn init(config: &Configuration) -> anyhow::Result<LogFilterHandle> {
+    let (env_filter, log_filter_handle) = reload::Layer::new(make_env_filter(config.verbose));
+    let log_layer = match config.log_format {
+        LogFormat::Tree => {
+            let raw_log_layer = env::var("LLDAP_RAW_LOG")
+                .is_ok()
+                .then(tracing_subscriber::fmt::layer);
+            tracing_forest::ForestLayer::default()
+                .and_then(raw_log_layer)
+                .boxed()
+        }
+        // One object per line, with the fields of the event at the top level and the fields of
+        // the enclosing span (e.g. the LDAP session) under "span".
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+    };
+    let opentelemetry_layer = if config.opentelemetry_options.enabled {
+        let tracer = init_tracer_provider(&config.opentelemetry_options)
+            .context("while setting up the OpenTelemetry export")?
+            .tracer("lldap");
+        Some(
+            tracing_opentelemetry::layer()
+                .with_tracer(tracer)
+                .with_filter(EnvFilter::new(OPENTELEMETRY_FILTER)),
+        )
     } else {
-        registry.init();
+        None
+    };
+    tracing_subscriber::registry()
+        .with(log_layer.with_filter(env_filter))
+        .with(opentelemetry_layer)
+        .init();
+    Ok(log_filter_handle)
+}
+
+fn init_tracer_provider(options: &OpenTelemetryOptions) -> anyhow::Result<TracerProvider> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(&options.endpoint)
+        .build()?;
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::TokioCurrentThread)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            options.sampling_ratio,
+        ))))
+        .with_resource(Resource::new(vec![KeyValue::new(
+            "service.name",
+            options.service_name.clone(),
+        )]))
+        .build();
+    opentelemetry::global::set_tracer_provider(provider.clone());
+    Ok(provider)
+}
+
+/// Flushes the spans that haven't been exported yet.
+pub fn shutdown() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::TraceContextExt;
+
+    #[test]
+    fn test_extract_trace_context() {
+        let request = actix_web::test::TestRequest::default()
+            .insert_header((
+                "traceparent",
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            ))
+            .to_srv_request();
+        let context =
+            TraceContextPropagator::new().extract(&RequestHeaderExtractor(request.headers()));
+        let span_context = context.span().span_context().clone();
+        assert_eq!(
+            span_context.trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+        assert_eq!(span_context.span_id().to_string(), "00f067aa0ba902b7");
+        assert!(span_context.is_sampled());
     }
-    Ok(())
 }
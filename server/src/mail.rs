@@ -1,17 +1,20 @@
-use crate::{cli::SmtpEncryption, configuration::MailOptions};
+use crate::{
+    cli::SmtpEncryption,
+    configuration::MailOptions,
+    mail_templates::{EmailTemplate, RenderedEmail, render_email},
+};
 use anyhow::{Ok, Result, anyhow};
 use lettre::{
-    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor, message::Mailbox,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    message::{Mailbox, MultiPart, SinglePart},
     transport::smtp::authentication::Credentials,
 };
-use std::time::Duration;
-use tokio::time::sleep;
+use lldap_domain::types::User;
 use tracing::debug;
 
 async fn send_email(
     to: Mailbox,
-    subject: &str,
-    body: String,
+    email: RenderedEmail,
     options: &MailOptions,
     server_url: &url::Url,
 ) -> Result<()> {
@@ -24,7 +27,7 @@ async fn send_email(
         "Sending email to '{}' as '{}' via '{}'@'{}':'{}'",
         &to, &from, &options.user, &options.server, options.port
     );
-    let email = Message::builder()
+    let message = Message::builder()
         .message_id(Some(format!(
             "<{}@{}>",
             uuid::Uuid::new_v1(
@@ -36,12 +39,15 @@ async fn send_email(
         .from(from.0)
         .reply_to(reply_to.0)
         .to(to)
-        .subject(subject)
-        .singlepart(
-            lettre::message::SinglePart::builder()
+        .subject(email.subject);
+    let message = match email.html {
+        Some(html) => message.multipart(MultiPart::alternative_plain_html(email.text, html))?,
+        None => message.singlepart(
+            SinglePart::builder()
                 .header(lettre::message::header::ContentType::TEXT_PLAIN)
-                .body(body),
-        )?;
+                .body(email.text),
+        )?,
+    };
     let mut mailer = match options.smtp_encryption {
         SmtpEncryption::None => {
             AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&options.server)
@@ -59,7 +65,7 @@ async fn send_email(
         mailer = mailer.credentials(creds)
     }
 
-    if let Err(e) = mailer.port(options.port).build().send(email).await {
+    if let Err(e) = mailer.port(options.port).build().send(message).await {
         debug!("Error sending email: {:?}", e);
         let message = e.to_string();
         Err(anyhow!(
@@ -79,11 +85,21 @@ async fn send_email(
     }
 }
 
+/// The language of the emails sent to the user, from the attribute set in the options.
+pub fn get_user_locale<'a>(user: &'a User, options: &MailOptions) -> Option<&'a str> {
+    let attribute = options.locale_attribute.as_ref()?;
+    user.attributes
+        .iter()
+        .find(|a| &a.name == attribute)
+        .and_then(|a| a.value.as_str())
+}
+
 pub async fn send_password_reset_email(
     display_name: &str,
     username: &str,
     to: &str,
     token: &str,
+    locale: Option<&str>,
     server_url: &url::Url,
     options: &MailOptions,
 ) -> Result<()> {
@@ -93,40 +109,75 @@ pub async fn send_password_reset_email(
         .path_segments_mut()
         .unwrap()
         .extend(["reset-password", "step2", token]);
-    let body = format!(
-        "Hello {display_name},
-
-Your username is: \"{username}\"
-
-This email has been sent to you in order to validate your identity.
-If you did not initiate the process your credentials might have been
-compromised. You should reset your password and contact an administrator.
+    let email = render_email(
+        options,
+        EmailTemplate::PasswordReset,
+        locale,
+        &[
+            ("display_name", display_name.to_owned()),
+            ("username", username.to_owned()),
+            ("url", reset_url.to_string()),
+            ("server_url", server_url.to_string()),
+        ],
+    )?;
+    send_email(to, email, options, server_url).await
+}
 
-To reset your password please visit the following URL: {reset_url}
+pub async fn send_email_verification_email(
+    display_name: &str,
+    to: &str,
+    verification_url: &url::Url,
+    locale: Option<&str>,
+    server_url: &url::Url,
+    options: &MailOptions,
+) -> Result<()> {
+    let to = to.parse()?;
+    let email = render_email(
+        options,
+        EmailTemplate::EmailVerification,
+        locale,
+        &[
+            ("display_name", display_name.to_owned()),
+            ("url", verification_url.to_string()),
+            ("server_url", server_url.to_string()),
+        ],
+    )?;
+    send_email(to, email, options, server_url).await
+}
 
-Please contact an administrator if you did not initiate the process."
-    );
-    let res = send_email(
-        to,
-        "[LLDAP] Password reset requested",
-        body,
+pub async fn send_invitation_email(
+    to: &str,
+    invited_by: &str,
+    invitation_url: &url::Url,
+    expiry_date: chrono::NaiveDateTime,
+    server_url: &url::Url,
+    options: &MailOptions,
+) -> Result<()> {
+    let to = to.parse()?;
+    let email = render_email(
         options,
-        server_url,
-    )
-    .await;
-    if res.is_err() {
-        sleep(Duration::from_secs(3)).await;
-    }
-    res
+        EmailTemplate::Invitation,
+        None,
+        &[
+            ("invited_by", invited_by.to_owned()),
+            ("url", invitation_url.to_string()),
+            (
+                "expiry_date",
+                expiry_date.format("%Y-%m-%d %H:%M").to_string(),
+            ),
+            ("server_url", server_url.to_string()),
+        ],
+    )?;
+    send_email(to, email, options, server_url).await
 }
 
 pub async fn send_test_email(to: Mailbox, options: &MailOptions) -> Result<()> {
-    send_email(
-        to,
-        "LLDAP test email",
-        "The test is successful! You can send emails from LLDAP".to_string(),
+    let server_url = url::Url::parse("http://localhost").unwrap();
+    let email = render_email(
         options,
-        &url::Url::parse("http://localhost").unwrap(),
-    )
-    .await
+        EmailTemplate::TestEmail,
+        None,
+        &[("server_url", server_url.to_string())],
+    )?;
+    send_email(to, email, options, &server_url).await
 }
@@ -0,0 +1,263 @@
+use crate::configuration::MailOptions;
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+
+/// The emails sent by LLDAP. The name of each one is also the base name of its template files:
+/// `<name>.subject.txt`, `<name>.txt` and optionally `<name>.html`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+#[clap(rename_all = "snake_case")]
+pub enum EmailTemplate {
+    PasswordReset,
+    EmailVerification,
+    Invitation,
+    TestEmail,
+}
+
+macro_rules! builtin_template {
+    ($name:literal, html) => {
+        Template {
+            subject: include_str!(concat!("../email_templates/", $name, ".subject.txt")).into(),
+            text: include_str!(concat!("../email_templates/", $name, ".txt")).into(),
+            html: Some(include_str!(concat!("../email_templates/", $name, ".html")).into()),
+        }
+    };
+    ($name:literal) => {
+        Template {
+            subject: include_str!(concat!("../email_templates/", $name, ".subject.txt")).into(),
+            text: include_str!(concat!("../email_templates/", $name, ".txt")).into(),
+            html: None,
+        }
+    };
+}
+
+impl EmailTemplate {
+    pub fn name(self) -> &'static str {
+        match self {
+            EmailTemplate::PasswordReset => "password_reset",
+            EmailTemplate::EmailVerification => "email_verification",
+            EmailTemplate::Invitation => "invitation",
+            EmailTemplate::TestEmail => "test_email",
+        }
+    }
+
+    fn builtin(self) -> Template {
+        match self {
+            EmailTemplate::PasswordReset => builtin_template!("password_reset", html),
+            EmailTemplate::EmailVerification => builtin_template!("email_verification", html),
+            EmailTemplate::Invitation => builtin_template!("invitation", html),
+            EmailTemplate::TestEmail => builtin_template!("test_email"),
+        }
+    }
+
+    /// Values for all the variables of the template, to preview it.
+    pub fn sample_variables(self) -> Vec<(&'static str, String)> {
+        let server_url = ("server_url", "https://ldap.example.com/".to_owned());
+        match self {
+            EmailTemplate::PasswordReset => vec![
+                ("display_name", "Jane Doe".to_owned()),
+                ("username", "jdoe".to_owned()),
+                (
+                    "url",
+                    "https://ldap.example.com/reset-password/step2/TOKEN".to_owned(),
+                ),
+                server_url,
+            ],
+            EmailTemplate::EmailVerification => vec![
+                ("display_name", "Jane Doe".to_owned()),
+                (
+                    "url",
+                    "https://ldap.example.com/verify-email/TOKEN".to_owned(),
+                ),
+                server_url,
+            ],
+            EmailTemplate::Invitation => vec![
+                ("invited_by", "admin".to_owned()),
+                (
+                    "url",
+                    "https://ldap.example.com/invitation/TOKEN".to_owned(),
+                ),
+                ("expiry_date", "2030-01-01 00:00".to_owned()),
+                server_url,
+            ],
+            EmailTemplate::TestEmail => vec![server_url],
+        }
+    }
+}
+
+struct Template {
+    subject: String,
+    text: String,
+    html: Option<String>,
+}
+
+/// An email ready to be sent, with the variables replaced.
+#[derive(Debug)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub text: String,
+    pub html: Option<String>,
+}
+
+/// Returns the locale, then the language alone: "de-CH" gives ["de-CH", "de"].
+/// Anything that doesn't look like a locale is ignored, since it becomes part of a path.
+fn locale_candidates(locale: &str) -> Vec<&str> {
+    let is_valid = !locale.is_empty()
+        && locale.len() <= 35
+        && locale
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !is_valid {
+        return Vec::new();
+    }
+    let mut candidates = vec![locale];
+    if let Some((language, _)) = locale.split_once(['-', '_']) {
+        candidates.push(language);
+    }
+    candidates
+}
+
+fn read_template(dir: &Path, template: EmailTemplate) -> Result<Option<Template>> {
+    let name = template.name();
+    let text_path = dir.join(format!("{name}.txt"));
+    if !text_path.exists() {
+        return Ok(None);
+    }
+    let read = |path: &Path| {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read the template {}", path.display()))
+    };
+    let html_path = dir.join(format!("{name}.html"));
+    Ok(Some(Template {
+        subject: read(&dir.join(format!("{name}.subject.txt")))?,
+        text: read(&text_path)?,
+        html: if html_path.exists() {
+            Some(read(&html_path)?)
+        } else {
+            None
+        },
+    }))
+}
+
+/// Finds the most specific template: `<dir>/<locale>/`, `<dir>/<language>/`, `<dir>/`, then the
+/// built-in one.
+fn load_template(
+    dir: Option<&Path>,
+    template: EmailTemplate,
+    locale: Option<&str>,
+) -> Result<Template> {
+    if let Some(dir) = dir {
+        for candidate in locale.map(locale_candidates).unwrap_or_default() {
+            if let Some(t) = read_template(&dir.join(candidate), template)? {
+                return Ok(t);
+            }
+        }
+        if let Some(t) = read_template(dir, template)? {
+            return Ok(t);
+        }
+    }
+    Ok(template.builtin())
+}
+
+fn escape_html(value: &str, output: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            c => output.push(c),
+        }
+    }
+}
+
+/// Replaces the `{{ variable }}` placeholders with their value.
+fn render(template: &str, variables: &[(&str, String)], is_html: bool) -> Result<String> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after_start = &rest[start + 2..];
+        let Some(end) = after_start.find("}}") else {
+            bail!("Unclosed '{{{{' in the template");
+        };
+        let name = after_start[..end].trim();
+        let Some((_, value)) = variables.iter().find(|(n, _)| *n == name) else {
+            bail!("Unknown variable '{name}' in the template");
+        };
+        if is_html {
+            escape_html(value, &mut output);
+        } else {
+            output.push_str(value);
+        }
+        rest = &after_start[end + 2..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+pub fn render_email(
+    options: &MailOptions,
+    template: EmailTemplate,
+    locale: Option<&str>,
+    variables: &[(&str, String)],
+) -> Result<RenderedEmail> {
+    let t = load_template(options.templates_dir.as_deref(), template, locale)?;
+    let context = || format!("While rendering the email template '{}'", template.name());
+    Ok(RenderedEmail {
+        subject: render(t.subject.trim(), variables, false).with_context(context)?,
+        text: render(&t.text, variables, false).with_context(context)?,
+        html: t
+            .html
+            .map(|html| render(&html, variables, true))
+            .transpose()
+            .with_context(context)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_render() {
+        let variables = [("name", "<Bob> & co".to_owned())];
+        assert_eq!(
+            render("Hi {{ name }}, {{name}}!", &variables, false).unwrap(),
+            "Hi <Bob> & co, <Bob> & co!"
+        );
+        assert_eq!(
+            render("<p>{{ name }}</p>", &variables, true).unwrap(),
+            "<p>&lt;Bob&gt; &amp; co</p>"
+        );
+        render("{{ other }}", &variables, false).unwrap_err();
+        render("{{ name", &variables, false).unwrap_err();
+    }
+
+    #[test]
+    fn test_locale_candidates() {
+        assert_eq!(locale_candidates("de-CH"), vec!["de-CH", "de"]);
+        assert_eq!(locale_candidates("fr"), vec!["fr"]);
+        assert!(locale_candidates("../fr").is_empty());
+        assert!(locale_candidates("").is_empty());
+    }
+
+    #[test]
+    fn test_builtin_templates_render() {
+        for template in [
+            EmailTemplate::PasswordReset,
+            EmailTemplate::EmailVerification,
+            EmailTemplate::Invitation,
+            EmailTemplate::TestEmail,
+        ] {
+            render_email(
+                &MailOptions::default(),
+                template,
+                None,
+                &template.sample_variables(),
+            )
+            .unwrap();
+        }
+    }
+}
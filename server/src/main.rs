@@ -4,37 +4,58 @@
 #![allow(clippy::blocks_in_conditions)]
 
 mod auth_service;
+mod backup;
+mod bootstrap;
 mod cli;
 mod configuration;
 mod database_string;
 mod db_cleaner;
+mod directory_csv;
+mod directory_plan;
+mod email_verification;
 mod graphql_server;
 mod healthcheck;
+mod import_export;
+mod invitation;
 mod jwt_sql_tables;
 mod ldap_server;
+mod ldif;
 mod logging;
 mod mail;
+mod mail_templates;
+mod metrics;
+mod migrate_db;
+mod rate_limit;
+mod reload;
 mod sql_tcp_backend_handler;
 mod tcp_backend_handler;
 mod tcp_server;
 mod tls;
+mod webauthn;
+mod webhooks;
 
 use crate::{
-    cli::{Command, RunOpts, TestEmailOpts},
+    cli::{
+        BackupOpts, BootstrapOpts, Command, ExportOpts, ImportOpts, MigrateDbOpts,
+        PreviewEmailOpts, RestoreOpts, RunOpts, TestEmailOpts,
+    },
     configuration::{Configuration, compare_private_key_hashes},
     database_string::DatabaseUrl,
     db_cleaner::Scheduler,
+    metrics::Metrics,
+    reload::ReloadableConfig,
 };
 use actix::Actor;
 use actix_server::ServerBuilder;
 use anyhow::{Context, Result, anyhow, bail};
 use futures_util::TryFutureExt;
+use lldap_access_control::{AccessControlledBackendHandler, events::DirectoryEventBroadcaster};
 use lldap_sql_backend_handler::{
     SqlBackendHandler, register_password,
     sql_tables::{self, get_private_key_info, set_private_key_info},
 };
 use sea_orm::{Database, DatabaseConnection};
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 use tracing::{Instrument, Level, debug, error, info, instrument, span, warn};
 
 use lldap_domain::requests::{CreateGroupRequest, CreateUserRequest};
@@ -63,6 +84,7 @@ async fn create_admin_user(handler: &SqlBackendHandler, config: &Configuration)
             user_id: config.ldap_user_dn.clone(),
             email: config.ldap_user_email.clone().into(),
             display_name: Some("Administrator".to_string()),
+            email_verified: true,
             ..Default::default()
         })
         .and_then(|_| {
@@ -102,20 +124,22 @@ async fn ensure_group_exists(handler: &SqlBackendHandler, group_name: &str) -> R
     Ok(())
 }
 
-async fn setup_sql_tables(database_url: &DatabaseUrl) -> Result<DatabaseConnection> {
-    let sql_pool = {
-        let num_connections = if database_url.db_type() == "sqlite" {
-            1
-        } else {
-            5
-        };
-        let mut sql_opt = sea_orm::ConnectOptions::new(database_url.to_string());
-        sql_opt
-            .max_connections(num_connections)
-            .sqlx_logging(true)
-            .sqlx_logging_level(log::LevelFilter::Debug);
-        Database::connect(sql_opt).await?
+async fn connect_sql_pool(database_url: &DatabaseUrl) -> Result<DatabaseConnection> {
+    let num_connections = if database_url.db_type() == "sqlite" {
+        1
+    } else {
+        5
     };
+    let mut sql_opt = sea_orm::ConnectOptions::new(database_url.to_string());
+    sql_opt
+        .max_connections(num_connections)
+        .sqlx_logging(true)
+        .sqlx_logging_level(log::LevelFilter::Debug);
+    Ok(Database::connect(sql_opt).await?)
+}
+
+async fn setup_sql_tables(database_url: &DatabaseUrl) -> Result<DatabaseConnection> {
+    let sql_pool = connect_sql_pool(database_url).await?;
     sql_tables::init_table(&sql_pool)
         .await
         .context("while creating base tables")?;
@@ -126,10 +150,19 @@ async fn setup_sql_tables(database_url: &DatabaseUrl) -> Result<DatabaseConnecti
 }
 
 #[instrument(skip_all)]
-async fn set_up_server(config: Configuration) -> Result<(ServerBuilder, DatabaseConnection)> {
+async fn set_up_server(
+    config: Configuration,
+    reloadable_config: Arc<ReloadableConfig>,
+) -> Result<(ServerBuilder, DatabaseConnection)> {
     info!("Starting LLDAP version {}", env!("CARGO_PKG_VERSION"));
 
-    let sql_pool = setup_sql_tables(&config.database_url).await?;
+    let mut sql_pool = setup_sql_tables(&config.database_url).await?;
+    let metrics = Arc::new(Metrics::new(sql_pool.clone()).context("while setting up the metrics")?);
+    // Set before cloning the pool: the clones don't share the callback.
+    {
+        let metrics = metrics.clone();
+        sql_pool.set_metric_callback(move |info| metrics.observe_sql_query(info));
+    }
     let private_key_info = config.get_private_key_info();
     let force_update_private_key = config.force_update_private_key;
     match (
@@ -205,17 +238,45 @@ async fn set_up_server(config: Configuration) -> Result<(ServerBuilder, Database
             "Restart the server without --force-update-private-key or --force-ldap-user-pass-reset to continue."
         );
     }
+    let backend_handler = if config.webhooks.is_empty() {
+        backend_handler
+    } else {
+        backend_handler.with_event_listener(Arc::new(
+            webhooks::start(config.webhooks.clone(), sql_pool.clone())
+                .await
+                .context("while starting the webhooks")?,
+        ))
+    };
+    // For the GraphQL subscriptions.
+    let events = Arc::new(DirectoryEventBroadcaster::new());
+    let backend_handler = backend_handler.with_event_listener(events.clone());
+    let backend_handler = AccessControlledBackendHandler::new(backend_handler.clone())
+        .with_audit_log(Arc::new(backend_handler))
+        .with_event_broadcaster(events);
     let server_builder = ldap_server::build_ldap_server(
         &config,
         backend_handler.clone(),
+        metrics.clone(),
+        reloadable_config.clone(),
         actix_server::Server::build(),
     )
     .context("while binding the LDAP server")?;
-    let server_builder = tcp_server::build_tcp_server(&config, backend_handler, server_builder)
-        .await
-        .context("while binding the TCP server")?;
+    let server_builder = tcp_server::build_tcp_server(
+        &config,
+        backend_handler,
+        metrics,
+        reloadable_config,
+        server_builder,
+    )
+    .await
+    .context("while binding the TCP server")?;
     // Run every hour.
-    let scheduler = Scheduler::new("0 0 * * * * *", sql_pool.clone());
+    let scheduler = Scheduler::new(
+        "0 0 * * * * *",
+        sql_pool.clone(),
+        config.audit_log_retention_days,
+        config.webhook_delivery_retention_days,
+    );
     scheduler.start();
     Ok((server_builder, sql_pool))
 }
@@ -223,19 +284,37 @@ async fn set_up_server(config: Configuration) -> Result<(ServerBuilder, Database
 async fn run_server_command(opts: RunOpts) -> Result<()> {
     debug!("CLI: {:#?}", &opts);
 
-    let config = configuration::init(opts)?;
-    logging::init(&config)?;
+    let config = configuration::init(opts.clone())?;
+    let log_filter = logging::init(&config)?;
 
-    let (server, sql_pool) = set_up_server(config).await?;
+    let reloadable_config = Arc::new(ReloadableConfig::new(&config)?);
+    let (server, sql_pool) = set_up_server(config.clone(), reloadable_config.clone()).await?;
     let server = server.workers(1);
+    reload::start(opts, config, reloadable_config, log_filter);
 
     let result = server.run().await.context("while starting the server");
     if let Err(e) = sql_pool.close().await {
         error!("Error closing database connection pool: {}", e);
     }
+    logging::shutdown();
     result
 }
 
+fn preview_email_command(opts: PreviewEmailOpts) -> Result<()> {
+    let config = configuration::init(opts.clone())?;
+    let email = mail_templates::render_email(
+        &config.smtp_options,
+        opts.template,
+        opts.locale.as_deref(),
+        &opts.template.sample_variables(),
+    )?;
+    println!("Subject: {}\n\n{}", email.subject, email.text);
+    if let Some(html) = email.html {
+        println!("\n---- HTML ----\n\n{html}");
+    }
+    Ok(())
+}
+
 async fn send_test_email_command(opts: TestEmailOpts) -> Result<()> {
     let to = opts.to.parse()?;
     let config = configuration::init(opts)?;
@@ -266,7 +345,11 @@ async fn run_healthcheck(opts: RunOpts) -> Result<()> {
         ),
         timeout(
             delay,
-            healthcheck::check_api(&config.healthcheck_options.http_host, config.http_port)
+            healthcheck::check_api(
+                &config.healthcheck_options.http_host,
+                config.http_port,
+                &config.https_options,
+            )
         ),
     );
 
@@ -298,6 +381,180 @@ async fn create_schema_command(opts: RunOpts) -> Result<()> {
     Ok(())
 }
 
+async fn backup_command(opts: BackupOpts) -> Result<()> {
+    debug!("CLI: {:#?}", &opts);
+    let output_file = opts.output_file.clone();
+    let config = configuration::init(opts)?;
+    logging::init(&config)?;
+    // The schema is not upgraded: the backup is taken from the database as the server uses it.
+    let sql_pool = connect_sql_pool(&config.database_url).await?;
+    let backup = backup::make_backup(&sql_pool).await?;
+    let file = std::fs::File::create(&output_file)
+        .with_context(|| format!("while creating {}", output_file.display()))?;
+    let mut writer = std::io::BufWriter::new(file);
+    serde_json::to_writer(&mut writer, &backup)?;
+    std::io::Write::flush(&mut writer)?;
+    info!(
+        "Backed up {} users and {} groups to {}",
+        backup.tables.users.len(),
+        backup.tables.groups.len(),
+        output_file.display()
+    );
+    if let Err(e) = sql_pool.close().await {
+        error!("Error closing database connection pool: {}", e);
+    }
+    Ok(())
+}
+
+async fn restore_command(opts: RestoreOpts) -> Result<()> {
+    debug!("CLI: {:#?}", &opts);
+    let input_file = opts.input_file.clone();
+    let config = configuration::init(opts)?;
+    logging::init(&config)?;
+    let file = std::fs::File::open(&input_file)
+        .with_context(|| format!("while opening {}", input_file.display()))?;
+    let backup: backup::Backup = serde_json::from_reader(std::io::BufReader::new(file))
+        .with_context(|| format!("while reading the backup {}", input_file.display()))?;
+    let sql_pool = setup_sql_tables(&config.database_url).await?;
+    let private_key_info = config.get_private_key_info();
+    backup::restore_backup(&sql_pool, backup, &private_key_info.private_key_hash).await?;
+    set_private_key_info(&sql_pool, private_key_info).await?;
+    info!("Restored the backup from {}", input_file.display());
+    if let Err(e) = sql_pool.close().await {
+        error!("Error closing database connection pool: {}", e);
+    }
+    Ok(())
+}
+
+async fn migrate_db_command(opts: MigrateDbOpts) -> Result<()> {
+    debug!("CLI: {:#?}", &opts);
+    let (from, to) = (opts.from.clone(), opts.to.clone());
+    let config = configuration::init(opts)?;
+    logging::init(&config)?;
+    let source = connect_sql_pool(&from)
+        .await
+        .context("while connecting to the source database")?;
+    let target = connect_sql_pool(&to)
+        .await
+        .context("while connecting to the target database")?;
+    migrate_db::migrate_db(&source, &target).await?;
+    info!("Database migrated successfully, point `database_url` to the new database.");
+    for pool in [source, target] {
+        if let Err(e) = pool.close().await {
+            error!("Error closing database connection pool: {}", e);
+        }
+    }
+    Ok(())
+}
+
+async fn export_command(opts: ExportOpts) -> Result<()> {
+    debug!("CLI: {:#?}", &opts);
+    let (format, output_file) = (opts.format, opts.output_file.clone());
+    let config = configuration::init(opts)?;
+    logging::init(&config)?;
+    let sql_pool = connect_sql_pool(&config.database_url).await?;
+    let backend_handler =
+        SqlBackendHandler::new(config.get_server_setup().clone(), sql_pool.clone());
+    let content =
+        import_export::export_directory(&backend_handler, format, &config.ldap_base_dn).await?;
+    std::fs::write(&output_file, content)
+        .with_context(|| format!("while writing {}", output_file.display()))?;
+    info!("Exported to {}", output_file.display());
+    if let Err(e) = sql_pool.close().await {
+        error!("Error closing database connection pool: {}", e);
+    }
+    Ok(())
+}
+
+async fn import_command(opts: ImportOpts) -> Result<()> {
+    debug!("CLI: {:#?}", &opts);
+    let input_file = opts.input_file.clone();
+    let (format, conflict_policy, dry_run) = (
+        opts.format.into(),
+        opts.conflict_policy.into(),
+        opts.dry_run,
+    );
+    let attribute_mapping = opts.attribute_mapping.iter().cloned().collect();
+    let config = configuration::init(opts)?;
+    logging::init(&config)?;
+    let content = std::fs::read_to_string(&input_file)
+        .with_context(|| format!("while reading {}", input_file.display()))?;
+    let records = import_export::read_records(format, &content, &attribute_mapping)
+        .with_context(|| format!("while reading {}", input_file.display()))?;
+    let sql_pool = setup_sql_tables(&config.database_url).await?;
+    let backend_handler =
+        SqlBackendHandler::new(config.get_server_setup().clone(), sql_pool.clone());
+    let response =
+        import_export::import_records(&backend_handler, records, conflict_policy, dry_run).await?;
+    for change in &response.changes {
+        info!("{change}");
+    }
+    for error in &response.errors {
+        error!("{error}");
+    }
+    if let Err(e) = sql_pool.close().await {
+        error!("Error closing database connection pool: {}", e);
+    }
+    if !response.errors.is_empty() {
+        bail!("The import failed with {} errors", response.errors.len());
+    }
+    if response.applied {
+        info!("Applied {} changes", response.changes.len());
+    } else {
+        info!("Dry run, nothing was applied");
+    }
+    Ok(())
+}
+
+async fn bootstrap_command(opts: BootstrapOpts) -> Result<()> {
+    debug!("CLI: {:#?}", &opts);
+    let input_file = opts.input_file.clone();
+    let (dry_run, prune) = (opts.dry_run, opts.prune);
+    let config = configuration::init(opts)?;
+    logging::init(&config)?;
+    let state = bootstrap::read_state(&input_file)?;
+    let sql_pool = setup_sql_tables(&config.database_url).await?;
+    let private_key_info = config.get_private_key_info();
+    // The passwords are stored encrypted with the private key, it must be the one of the server.
+    let is_new_database = compare_private_key_hashes(
+        get_private_key_info(&sql_pool).await?.as_ref(),
+        &private_key_info,
+    )
+    .context("The private key is not the one used by the server, the passwords would not work")?;
+    if is_new_database && !dry_run {
+        set_private_key_info(&sql_pool, private_key_info).await?;
+    }
+    let backend_handler =
+        SqlBackendHandler::new(config.get_server_setup().clone(), sql_pool.clone());
+    let options = bootstrap::BootstrapOptions {
+        dry_run,
+        prune,
+        admin_user_id: config.ldap_user_dn.clone(),
+    };
+    let report = bootstrap::bootstrap(&backend_handler, state, &options).await;
+    if let Err(e) = sql_pool.close().await {
+        error!("Error closing database connection pool: {}", e);
+    }
+    let report = report?;
+    for change in &report.changes {
+        info!("{change}");
+    }
+    for error in &report.errors {
+        error!("{error}");
+    }
+    if !report.errors.is_empty() {
+        bail!("The bootstrap failed with {} errors", report.errors.len());
+    }
+    if report.applied {
+        info!("Applied {} changes", report.changes.len());
+    } else if report.changes.is_empty() {
+        info!("Nothing to do, the directory is up to date");
+    } else {
+        info!("Dry run, nothing was applied");
+    }
+    Ok(())
+}
+
 #[actix::main]
 async fn main() -> Result<()> {
     let cli_opts = cli::init();
@@ -309,5 +566,12 @@ async fn main() -> Result<()> {
         Command::HealthCheck(opts) => run_healthcheck(opts).await,
         Command::SendTestEmail(opts) => send_test_email_command(opts).await,
         Command::CreateSchema(opts) => create_schema_command(opts).await,
+        Command::PreviewEmail(opts) => preview_email_command(opts),
+        Command::Backup(opts) => backup_command(opts).await,
+        Command::Restore(opts) => restore_command(opts).await,
+        Command::MigrateDb(opts) => migrate_db_command(opts).await,
+        Command::Export(opts) => export_command(opts).await,
+        Command::Import(opts) => import_command(opts).await,
+        Command::Bootstrap(opts) => bootstrap_command(opts).await,
     }
 }
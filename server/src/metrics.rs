@@ -0,0 +1,245 @@
+use crate::jwt_sql_tables::DbConnection;
+use anyhow::Result;
+use ldap3_proto::proto::{LdapOp, LdapResultCode};
+use lldap_domain_model::model;
+use prometheus::{
+    Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use sea_orm::{EntityTrait, PaginatorTrait};
+use std::time::Duration;
+
+/// The Prometheus metrics, served on `/metrics`.
+pub struct Metrics {
+    registry: Registry,
+    sql_pool: DbConnection,
+    ldap_operations: IntCounterVec,
+    ldap_binds: IntCounterVec,
+    ldap_sessions: IntGauge,
+    graphql_request_duration: Histogram,
+    sql_query_duration: HistogramVec,
+    password_reset_emails: IntCounterVec,
+    users: IntGauge,
+    groups: IntGauge,
+    memberships: IntGauge,
+}
+
+/// Keeps the LDAP session counted as active until dropped.
+pub struct LdapSessionGuard(IntGauge);
+
+impl Drop for LdapSessionGuard {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+impl Metrics {
+    /// The database sizes are queried from `sql_pool` when the metrics are rendered.
+    pub fn new(sql_pool: DbConnection) -> Result<Self> {
+        let registry = Registry::new_custom(Some("lldap".to_owned()), None)?;
+        let ldap_operations = IntCounterVec::new(
+            Opts::new(
+                "ldap_operations_total",
+                "LDAP operations by type and result",
+            ),
+            &["operation", "result"],
+        )?;
+        let ldap_binds = IntCounterVec::new(
+            Opts::new("ldap_binds_total", "LDAP binds by outcome"),
+            &["result"],
+        )?;
+        let ldap_sessions = IntGauge::new("ldap_sessions", "Currently open LDAP sessions")?;
+        let graphql_request_duration = Histogram::with_opts(HistogramOpts::new(
+            "graphql_request_duration_seconds",
+            "Time to answer a GraphQL request",
+        ))?;
+        let sql_query_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "sql_query_duration_seconds",
+                "Time to run an SQL query, by statement type",
+            )
+            .buckets(vec![
+                0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0,
+            ]),
+            &["statement"],
+        )?;
+        let password_reset_emails = IntCounterVec::new(
+            Opts::new(
+                "password_reset_emails_total",
+                "Password reset emails by outcome",
+            ),
+            &["result"],
+        )?;
+        let users = IntGauge::new("users", "Number of users")?;
+        let groups = IntGauge::new("groups", "Number of groups")?;
+        let memberships = IntGauge::new("memberships", "Number of group memberships")?;
+        registry.register(Box::new(ldap_operations.clone()))?;
+        registry.register(Box::new(ldap_binds.clone()))?;
+        registry.register(Box::new(ldap_sessions.clone()))?;
+        registry.register(Box::new(graphql_request_duration.clone()))?;
+        registry.register(Box::new(sql_query_duration.clone()))?;
+        registry.register(Box::new(password_reset_emails.clone()))?;
+        registry.register(Box::new(users.clone()))?;
+        registry.register(Box::new(groups.clone()))?;
+        registry.register(Box::new(memberships.clone()))?;
+        Ok(Self {
+            registry,
+            sql_pool,
+            ldap_operations,
+            ldap_binds,
+            ldap_sessions,
+            graphql_request_duration,
+            sql_query_duration,
+            password_reset_emails,
+            users,
+            groups,
+            memberships,
+        })
+    }
+
+    pub fn start_ldap_session(&self) -> LdapSessionGuard {
+        self.ldap_sessions.inc();
+        LdapSessionGuard(self.ldap_sessions.clone())
+    }
+
+    /// Counts an LDAP operation, with the result code of its last response.
+    pub fn record_ldap_operation(&self, operation: &str, responses: &[LdapOp]) {
+        let code = responses.last().and_then(get_ldap_result_code);
+        let result = code.map_or_else(|| "none".to_owned(), |c| format!("{c:?}"));
+        self.ldap_operations
+            .with_label_values(&[operation, result.as_str()])
+            .inc();
+        if operation == "bind" {
+            let outcome = if code == Some(&LdapResultCode::Success) {
+                "success"
+            } else {
+                "failure"
+            };
+            self.ldap_binds.with_label_values(&[outcome]).inc();
+        }
+    }
+
+    pub fn observe_graphql_request(&self, duration: Duration) {
+        self.graphql_request_duration
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn observe_sql_query(&self, info: &sea_orm::metric::Info<'_>) {
+        let statement = info
+            .statement
+            .sql
+            .split_whitespace()
+            .next()
+            .map(str::to_ascii_lowercase);
+        // Keep the number of label values bounded.
+        let statement = statement
+            .as_deref()
+            .filter(|s| matches!(*s, "select" | "insert" | "update" | "delete"))
+            .unwrap_or("other");
+        self.sql_query_duration
+            .with_label_values(&[statement])
+            .observe(info.elapsed.as_secs_f64());
+    }
+
+    pub fn record_password_reset_email(&self, sent: bool) {
+        self.password_reset_emails
+            .with_label_values(&[if sent { "sent" } else { "failed" }])
+            .inc();
+    }
+
+    /// Renders all the metrics in the Prometheus text format.
+    pub async fn render(&self) -> Result<String> {
+        self.users
+            .set(model::User::find().count(&self.sql_pool).await? as i64);
+        self.groups
+            .set(model::Group::find().count(&self.sql_pool).await? as i64);
+        self.memberships
+            .set(model::Membership::find().count(&self.sql_pool).await? as i64);
+        Ok(TextEncoder::new().encode_to_string(&self.registry.gather())?)
+    }
+}
+
+/// The name of the operation, used as a label.
+pub fn get_ldap_operation_name(op: &LdapOp) -> &'static str {
+    match op {
+        LdapOp::BindRequest(_) => "bind",
+        LdapOp::SearchRequest(_) => "search",
+        LdapOp::UnbindRequest => "unbind",
+        LdapOp::ModifyRequest(_) => "modify",
+        LdapOp::ExtendedRequest(_) => "extended",
+        LdapOp::AddRequest(_) => "add",
+        LdapOp::DelRequest(_) => "delete",
+        LdapOp::CompareRequest(_) => "compare",
+        _ => "other",
+    }
+}
+
+/// The result code carried by a response, if it has one.
+pub fn get_ldap_result_code(op: &LdapOp) -> Option<&LdapResultCode> {
+    match op {
+        LdapOp::BindResponse(response) => Some(&response.res.code),
+        LdapOp::ExtendedResponse(response) => Some(&response.res.code),
+        LdapOp::SearchResultDone(result)
+        | LdapOp::ModifyResponse(result)
+        | LdapOp::AddResponse(result)
+        | LdapOp::DelResponse(result)
+        | LdapOp::CompareResult(result) => Some(&result.code),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jwt_sql_tables;
+    use ldap3_proto::proto::{LdapBindResponse, LdapResult};
+    use lldap_sql_backend_handler::sql_tables;
+
+    fn make_bind_response(code: LdapResultCode) -> LdapOp {
+        LdapOp::BindResponse(LdapBindResponse {
+            res: LdapResult {
+                code,
+                matcheddn: "".to_owned(),
+                message: "".to_owned(),
+                referral: vec![],
+            },
+            saslcreds: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_render_metrics() {
+        let mut sql_opt = sea_orm::ConnectOptions::new("sqlite::memory:".to_owned());
+        sql_opt.max_connections(1);
+        let sql_pool = sea_orm::Database::connect(sql_opt).await.unwrap();
+        sql_tables::init_table(&sql_pool).await.unwrap();
+        jwt_sql_tables::init_table(&sql_pool).await.unwrap();
+        let metrics = Metrics::new(sql_pool).unwrap();
+
+        metrics.record_ldap_operation("bind", &[make_bind_response(LdapResultCode::Success)]);
+        metrics.record_ldap_operation(
+            "bind",
+            &[make_bind_response(LdapResultCode::InvalidCredentials)],
+        );
+        metrics.record_ldap_operation("unbind", &[]);
+        metrics.record_password_reset_email(false);
+        let session = metrics.start_ldap_session();
+        let _other_session = metrics.start_ldap_session();
+        drop(session);
+
+        let text = metrics.render().await.unwrap();
+        for line in [
+            r#"lldap_ldap_operations_total{operation="bind",result="Success"} 1"#,
+            r#"lldap_ldap_operations_total{operation="bind",result="InvalidCredentials"} 1"#,
+            r#"lldap_ldap_operations_total{operation="unbind",result="none"} 1"#,
+            r#"lldap_ldap_binds_total{result="failure"} 1"#,
+            r#"lldap_ldap_binds_total{result="success"} 1"#,
+            r#"lldap_password_reset_emails_total{result="failed"} 1"#,
+            "lldap_ldap_sessions 1",
+            "lldap_users 0",
+            "lldap_groups 0",
+            "lldap_memberships 0",
+        ] {
+            assert!(text.lines().any(|l| l == line), "{line} not in:\n{text}");
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{
+    backup::{
+        begin_snapshot, clear_new_database, ensure_last_schema_version, insert_all,
+        reset_postgres_sequences,
+    },
+    jwt_sql_tables::{self, DbConnection},
+};
+use anyhow::{Context, Result, bail, ensure};
+use lldap_domain_model::model;
+use lldap_sql_backend_handler::{
+    sql_migrations::get_schema_version,
+    sql_tables::{self, LAST_SCHEMA_VERSION, get_private_key_info, set_private_key_info},
+};
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, DatabaseTransaction, DbBackend, EntityTrait,
+    IntoActiveModel, PaginatorTrait, TransactionTrait,
+};
+use tracing::info;
+
+/// Copies all the rows of a table, and checks that they all made it.
+async fn copy_table<E>(source: &DatabaseTransaction, target: &DatabaseTransaction) -> Result<()>
+where
+    E: EntityTrait,
+    E::Model: IntoActiveModel<E::ActiveModel> + Sync,
+    E::ActiveModel: ActiveModelTrait<Entity = E>,
+{
+    let table = E::default().table_name().to_owned();
+    let rows = E::find()
+        .all(source)
+        .await
+        .with_context(|| format!("while reading {table}"))?;
+    let expected = rows.len() as u64;
+    insert_all::<E>(target, rows)
+        .await
+        .with_context(|| format!("while writing {table}"))?;
+    let copied = E::find().count(target).await?;
+    ensure!(
+        copied == expected,
+        "Copied {copied} rows of {table}, but the source has {expected}"
+    );
+    info!("Copied {expected} rows of {table}");
+    Ok(())
+}
+
+/// Copies the whole database from `source` to `target`, which can use a different backend. The
+/// target schema is created if needed, and it must not contain any user or group.
+pub async fn migrate_db(source: &DbConnection, target: &DbConnection) -> Result<()> {
+    ensure_last_schema_version(source)
+        .await
+        .context("while checking the source database")?;
+    if let Some(version) = get_schema_version(target).await
+        && version != LAST_SCHEMA_VERSION
+    {
+        bail!(
+            "The target database already contains an LLDAP database (schema version {}), only migrate to a new database",
+            version.0
+        );
+    }
+    sql_tables::init_table(target)
+        .await
+        .context("while creating the target tables")?;
+    jwt_sql_tables::init_table(target)
+        .await
+        .context("while creating the target jwt tables")?;
+
+    let source_transaction = begin_snapshot(source).await?;
+    let target_transaction = target.begin().await?;
+    clear_new_database(&target_transaction)
+        .await
+        .context("Only migrate to a new database")?;
+    // In the order of the foreign keys.
+    let (s, t) = (&source_transaction, &target_transaction);
+    copy_table::<model::UserAttributeSchema>(s, t).await?;
+    copy_table::<model::GroupAttributeSchema>(s, t).await?;
+    copy_table::<model::UserObjectClasses>(s, t).await?;
+    copy_table::<model::GroupObjectClasses>(s, t).await?;
+    copy_table::<model::User>(s, t).await?;
+    copy_table::<model::Group>(s, t).await?;
+    copy_table::<model::Membership>(s, t).await?;
+    copy_table::<model::GroupOwnerUsers>(s, t).await?;
+    copy_table::<model::GroupOwnerGroups>(s, t).await?;
+    copy_table::<model::UserAttributePermissions>(s, t).await?;
+    copy_table::<model::GroupAttributePermissions>(s, t).await?;
+    copy_table::<model::UserAttributeAllowedValues>(s, t).await?;
+    copy_table::<model::GroupAttributeAllowedValues>(s, t).await?;
+    copy_table::<model::UserAttributes>(s, t).await?;
+    copy_table::<model::GroupAttributes>(s, t).await?;
+    copy_table::<model::WebauthnCredentials>(s, t).await?;
+    copy_table::<model::Invitations>(s, t).await?;
+    copy_table::<model::AuditLog>(s, t).await?;
+    copy_table::<model::JwtStorage>(s, t).await?;
+    copy_table::<model::JwtRefreshStorage>(s, t).await?;
+    copy_table::<model::PasswordResetTokens>(s, t).await?;
+    copy_table::<model::WebhookDeliveries>(s, t).await?;
+    if target_transaction.get_database_backend() == DbBackend::Postgres {
+        reset_postgres_sequences(&target_transaction).await?;
+    }
+    target_transaction.commit().await?;
+    source_transaction.commit().await?;
+
+    // The passwords are only valid with the same server key, keep the check at startup.
+    if let Some(private_key_info) = get_private_key_info(source).await? {
+        set_private_key_info(target, private_key_info).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lldap_domain::types::{GroupId, GroupName, UserId, Uuid};
+    use lldap_sql_backend_handler::sql_tables::{
+        PrivateKeyHash, PrivateKeyInfo, PrivateKeyLocation,
+    };
+    use pretty_assertions::assert_eq;
+    use sea_orm::ActiveValue::Set;
+
+    async fn get_in_memory_db() -> DbConnection {
+        let mut sql_opt = sea_orm::ConnectOptions::new("sqlite::memory:".to_owned());
+        sql_opt.max_connections(1);
+        sea_orm::Database::connect(sql_opt).await.unwrap()
+    }
+
+    async fn get_source_db() -> DbConnection {
+        let pool = get_in_memory_db().await;
+        sql_tables::init_table(&pool).await.unwrap();
+        jwt_sql_tables::init_table(&pool).await.unwrap();
+        let date = chrono::Utc::now().naive_utc();
+        model::users::ActiveModel {
+            user_id: Set(UserId::new("bob")),
+            email: Set("bob@example.com".into()),
+            lowercase_email: Set("bob@example.com".to_owned()),
+            display_name: Set(None),
+            creation_date: Set(date),
+            password_hash: Set(Some(vec![0, 255, 7])),
+            totp_secret: Set(None),
+            mfa_type: Set(None),
+            uuid: Set(Uuid::from_name_and_date("bob", &date)),
+            modified_date: Set(date),
+            password_modified_date: Set(date),
+            email_verified: Set(false),
+        }
+        .insert(&pool)
+        .await
+        .unwrap();
+        model::groups::ActiveModel {
+            group_id: Set(GroupId(3)),
+            display_name: Set(GroupName::from("Friends")),
+            lowercase_display_name: Set("friends".to_owned()),
+            creation_date: Set(date),
+            uuid: Set(Uuid::from_name_and_date("Friends", &date)),
+            modified_date: Set(date),
+            ..Default::default()
+        }
+        .insert(&pool)
+        .await
+        .unwrap();
+        model::memberships::ActiveModel {
+            user_id: Set(UserId::new("bob")),
+            group_id: Set(GroupId(3)),
+        }
+        .insert(&pool)
+        .await
+        .unwrap();
+        model::jwt_refresh_storage::ActiveModel {
+            refresh_token_hash: Set(42),
+            user_id: Set(UserId::new("bob")),
+            expiry_date: Set(date),
+        }
+        .insert(&pool)
+        .await
+        .unwrap();
+        set_private_key_info(
+            &pool,
+            PrivateKeyInfo {
+                private_key_hash: PrivateKeyHash([5; 32]),
+                private_key_location: PrivateKeyLocation::Tests,
+            },
+        )
+        .await
+        .unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_migrate_db() {
+        let source = get_source_db().await;
+        let target = get_in_memory_db().await;
+        migrate_db(&source, &target).await.unwrap();
+        assert_eq!(
+            model::User::find().all(&target).await.unwrap(),
+            model::User::find().all(&source).await.unwrap()
+        );
+        assert_eq!(
+            model::Membership::find().all(&target).await.unwrap(),
+            model::Membership::find().all(&source).await.unwrap()
+        );
+        assert_eq!(
+            model::UserAttributeSchema::find()
+                .all(&target)
+                .await
+                .unwrap(),
+            model::UserAttributeSchema::find()
+                .all(&source)
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            model::JwtRefreshStorage::find()
+                .count(&target)
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            get_private_key_info(&target)
+                .await
+                .unwrap()
+                .unwrap()
+                .private_key_hash,
+            PrivateKeyHash([5; 32])
+        );
+
+        // The target is not empty anymore.
+        let error = migrate_db(&source, &target).await.unwrap_err();
+        assert!(format!("{error:#}").contains("not empty"));
+    }
+}
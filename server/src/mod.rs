@@ -1,15 +1,29 @@
 pub mod auth_service;
+pub mod backup;
+pub mod bootstrap;
 pub mod cli;
 pub mod configuration;
 pub mod database_string;
 pub mod db_cleaner;
+pub mod directory_csv;
+pub mod email_verification;
 pub mod graphql_server;
 pub mod healthcheck;
+pub mod import_export;
+pub mod invitation;
 pub mod jwt_sql_tables;
 pub mod ldap_server;
+pub mod ldif;
 pub mod logging;
 pub mod mail;
+pub mod mail_templates;
+pub mod metrics;
+pub mod migrate_db;
+pub mod rate_limit;
+pub mod reload;
 pub mod sql_tcp_backend_handler;
 pub mod tcp_backend_handler;
 pub mod tcp_server;
 pub mod tls;
+pub mod webauthn;
+pub mod webhooks;
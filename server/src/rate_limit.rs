@@ -0,0 +1,87 @@
+use crate::configuration::PasswordResetLimitOptions;
+use lldap_domain::types::UserId;
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Counts the recent requests for each key, and refuses the ones over the limit.
+pub struct RateLimiter<Key> {
+    max_requests: usize,
+    window: Duration,
+    requests: Mutex<HashMap<Key, VecDeque<Instant>>>,
+}
+
+impl<Key: Eq + Hash + Clone> RateLimiter<Key> {
+    pub fn new(max_requests: usize, window: Duration) -> Self {
+        Self {
+            max_requests,
+            window,
+            requests: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records a request for the key, returns false if it's over the limit.
+    ///
+    /// Refused requests are not recorded, so that a client hammering the endpoint is let through
+    /// again once the window has passed.
+    pub fn check(&self, key: &Key) -> bool {
+        self.check_at(key, Instant::now())
+    }
+
+    fn check_at(&self, key: &Key, now: Instant) -> bool {
+        let mut requests = self.requests.lock().unwrap();
+        let window = self.window;
+        let is_recent = |t: &Instant| now.saturating_duration_since(*t) < window;
+        // Keep the map from growing with keys that are not used anymore.
+        requests.retain(|_, times| times.back().is_some_and(is_recent));
+        let times = requests.entry(key.clone()).or_default();
+        while times.front().is_some_and(|t| !is_recent(t)) {
+            times.pop_front();
+        }
+        if times.len() >= self.max_requests {
+            return false;
+        }
+        times.push_back(now);
+        true
+    }
+}
+
+/// The limits on password reset requests, shared between all the HTTP workers.
+pub struct PasswordResetLimiters {
+    pub per_ip: RateLimiter<IpAddr>,
+    pub per_user: RateLimiter<UserId>,
+}
+
+impl PasswordResetLimiters {
+    pub fn new(options: &PasswordResetLimitOptions) -> Self {
+        let window = Duration::from_secs(options.window_minutes * 60);
+        Self {
+            per_ip: RateLimiter::new(options.per_ip, window),
+            per_user: RateLimiter::new(options.per_user, window),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let start = Instant::now();
+        assert!(limiter.check_at(&"bob", start));
+        assert!(limiter.check_at(&"bob", start + Duration::from_secs(1)));
+        assert!(!limiter.check_at(&"bob", start + Duration::from_secs(2)));
+        // Other keys are independent.
+        assert!(limiter.check_at(&"john", start + Duration::from_secs(2)));
+        // The first request is out of the window.
+        assert!(limiter.check_at(&"bob", start + Duration::from_secs(60)));
+        assert!(!limiter.check_at(&"bob", start + Duration::from_secs(60)));
+        assert!(limiter.check_at(&"bob", start + Duration::from_secs(200)));
+    }
+}
@@ -0,0 +1,372 @@
+use crate::{
+    cli::RunOpts,
+    configuration::{self, Configuration, MailOptions},
+    ldap_server,
+    logging::{self, LogFilterHandle},
+    tls::CertificateResolver,
+};
+use anyhow::{Context, Result};
+use lldap_ldap::LdapInfo;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+use tracing::{error, info, warn};
+
+/// How often the configuration file is checked for changes, with `watch_config_file`.
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The settings that can be changed without restarting the server. Prefixes match whole
+/// sections.
+const RELOADABLE_SETTINGS: [&str; 9] = [
+    "verbose",
+    "watch_config_file",
+    "ignored_user_attributes",
+    "ignored_group_attributes",
+    "smtp_options",
+    "ldaps_options.cert_file",
+    "ldaps_options.key_file",
+    "https_options.cert_file",
+    "https_options.key_file",
+];
+
+/// Exceptions to `RELOADABLE_SETTINGS`. The password reset routes are only registered at
+/// startup.
+const RESTART_ONLY_SETTINGS: [&str; 1] = ["smtp_options.enable_password_reset"];
+
+/// The part of the configuration that is read for each new LDAP session or HTTP request, so
+/// that it can be replaced while the server is running. Ongoing LDAP sessions keep the values
+/// they started with.
+pub struct ReloadableConfig {
+    ldap_info: RwLock<&'static LdapInfo>,
+    mail_options: RwLock<Arc<MailOptions>>,
+    ldaps_certificate: Option<Arc<CertificateResolver>>,
+    https_certificate: Option<Arc<CertificateResolver>>,
+}
+
+impl ReloadableConfig {
+    pub fn new(config: &Configuration) -> Result<Self> {
+        let ldaps_certificate = if config.ldaps_options.enabled {
+            Some(Arc::new(
+                CertificateResolver::new(
+                    &config.ldaps_options.cert_file,
+                    &config.ldaps_options.key_file,
+                )
+                .context("while setting up the SSL certificate")?,
+            ))
+        } else {
+            None
+        };
+        let https_certificate = if config.https_options.enabled {
+            Some(Arc::new(
+                CertificateResolver::new(
+                    &config.https_options.cert_file,
+                    &config.https_options.key_file,
+                )
+                .context("while setting up the HTTPS certificate")?,
+            ))
+        } else {
+            None
+        };
+        Ok(Self {
+            ldap_info: RwLock::new(Box::leak(Box::new(ldap_server::make_ldap_info(config)?))),
+            mail_options: RwLock::new(Arc::new(config.smtp_options.clone())),
+            ldaps_certificate,
+            https_certificate,
+        })
+    }
+
+    pub fn ldap_info(&self) -> &'static LdapInfo {
+        *self.ldap_info.read().unwrap()
+    }
+
+    pub fn mail_options(&self) -> Arc<MailOptions> {
+        self.mail_options.read().unwrap().clone()
+    }
+
+    /// None if LDAPS is disabled.
+    pub fn ldaps_certificate(&self) -> Option<Arc<CertificateResolver>> {
+        self.ldaps_certificate.clone()
+    }
+
+    /// None if HTTPS is disabled.
+    pub fn https_certificate(&self) -> Option<Arc<CertificateResolver>> {
+        self.https_certificate.clone()
+    }
+
+    /// Applies the reloadable settings of `config`. Settings that need a restart keep their
+    /// current value.
+    fn apply(&self, config: &Configuration) -> Result<()> {
+        let ldap_info = self.ldap_info();
+        if ldap_info.ignored_user_attributes != config.ignored_user_attributes
+            || ldap_info.ignored_group_attributes != config.ignored_group_attributes
+        {
+            let new_ldap_info = LdapInfo::new(
+                &ldap_info.base_dn_str,
+                config.ignored_user_attributes.clone(),
+                config.ignored_group_attributes.clone(),
+            )?;
+            // Ongoing sessions may still hold the previous one, so it is never freed. This only
+            // leaks when the ignored attributes actually change.
+            *self.ldap_info.write().unwrap() = Box::leak(Box::new(new_ldap_info));
+        }
+        let mut mail_options = config.smtp_options.clone();
+        mail_options.enable_password_reset = self.mail_options().enable_password_reset;
+        *self.mail_options.write().unwrap() = Arc::new(mail_options);
+        // The certificate files are read again even if their path didn't change, since they are
+        // usually renewed in place.
+        if let Some(certificate) = &self.ldaps_certificate {
+            certificate
+                .reload(
+                    &config.ldaps_options.cert_file,
+                    &config.ldaps_options.key_file,
+                )
+                .context("while reloading the LDAPS certificate")?;
+        }
+        if let Some(certificate) = &self.https_certificate {
+            certificate
+                .reload(
+                    &config.https_options.cert_file,
+                    &config.https_options.key_file,
+                )
+                .context("while reloading the HTTPS certificate")?;
+        }
+        Ok(())
+    }
+}
+
+fn is_reloadable(setting: &str) -> bool {
+    let matches = |prefix: &&str| {
+        setting
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+    };
+    RELOADABLE_SETTINGS.iter().any(matches) && !RESTART_ONLY_SETTINGS.iter().any(matches)
+}
+
+fn flatten_settings(
+    prefix: &str,
+    value: serde_json::Value,
+    settings: &mut BTreeMap<String, serde_json::Value>,
+) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten_settings(&key, value, settings);
+            }
+        }
+        value => {
+            settings.insert(prefix.to_owned(), value);
+        }
+    }
+}
+
+/// The names of the settings that differ, e.g. "smtp_options.server".
+fn changed_settings(old: &Configuration, new: &Configuration) -> Result<Vec<String>> {
+    let flatten = |config: &Configuration| -> Result<BTreeMap<String, serde_json::Value>> {
+        let mut settings = BTreeMap::new();
+        flatten_settings("", serde_json::to_value(config)?, &mut settings);
+        Ok(settings)
+    };
+    let (old, new) = (flatten(old)?, flatten(new)?);
+    Ok(old
+        .keys()
+        .chain(new.keys().filter(|key| !old.contains_key(*key)))
+        .filter(|key| old.get(*key) != new.get(*key))
+        .cloned()
+        .collect())
+}
+
+struct Reloader {
+    opts: RunOpts,
+    config: Configuration,
+    reloadable: Arc<ReloadableConfig>,
+    log_filter: LogFilterHandle,
+}
+
+impl Reloader {
+    fn reload(&mut self) {
+        info!("Reloading the configuration");
+        let config = match configuration::reload(&self.opts) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("Could not reload the configuration, keeping the current one: {e:#}");
+                return;
+            }
+        };
+        if let Err(e) = self
+            .log_filter
+            .reload(logging::make_env_filter(config.verbose))
+        {
+            error!("Could not change the log level: {e:#}");
+        }
+        if let Err(e) = self.reloadable.apply(&config) {
+            error!("Could not apply the new configuration: {e:#}");
+            return;
+        }
+        match changed_settings(&self.config, &config) {
+            Ok(changed) => {
+                let (applied, restart): (Vec<_>, Vec<_>) = changed
+                    .into_iter()
+                    .partition(|setting| is_reloadable(setting));
+                if !applied.is_empty() {
+                    info!("Applied the new value of: {}", applied.join(", "));
+                }
+                if !restart.is_empty() {
+                    warn!(
+                        "These settings changed but require a restart to take effect: {}",
+                        restart.join(", ")
+                    );
+                }
+            }
+            Err(e) => error!("Could not compare the configurations: {e:#}"),
+        }
+        self.config = config;
+    }
+
+    async fn run(mut self) {
+        let config_file = self.opts.general_config.config_file.clone();
+        let modified = || {
+            std::fs::metadata(&config_file)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        };
+        let mut last_modified: Option<SystemTime> = modified();
+        let mut watch_interval = tokio::time::interval(WATCH_INTERVAL);
+        let mut hangup = hangup_signal();
+        loop {
+            tokio::select! {
+                _ = recv_hangup(&mut hangup) => self.reload(),
+                _ = watch_interval.tick(), if self.config.watch_config_file => {
+                    let current = modified();
+                    if current != last_modified {
+                        last_modified = current;
+                        self.reload();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+type HangupSignal = Option<tokio::signal::unix::Signal>;
+#[cfg(not(unix))]
+type HangupSignal = ();
+
+#[cfg(unix)]
+fn hangup_signal() -> HangupSignal {
+    use tokio::signal::unix::{SignalKind, signal};
+    signal(SignalKind::hangup())
+        .inspect_err(|e| error!("Could not listen to SIGHUP: {e:#}"))
+        .ok()
+}
+
+#[cfg(not(unix))]
+fn hangup_signal() -> HangupSignal {}
+
+#[cfg(unix)]
+async fn recv_hangup(signal: &mut HangupSignal) {
+    match signal {
+        Some(signal) => {
+            signal.recv().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(not(unix))]
+async fn recv_hangup(_: &mut HangupSignal) {
+    std::future::pending().await
+}
+
+/// Reloads the configuration on SIGHUP and, if `watch_config_file` is set, when the
+/// configuration file changes. The certificates are reloaded when their files change.
+pub fn start(
+    opts: RunOpts,
+    config: Configuration,
+    reloadable: Arc<ReloadableConfig>,
+    log_filter: LogFilterHandle,
+) {
+    for certificate in [
+        reloadable.ldaps_certificate(),
+        reloadable.https_certificate(),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        certificate.watch();
+    }
+    tokio::spawn(
+        Reloader {
+            opts,
+            config,
+            reloadable,
+            log_filter,
+        }
+        .run(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::ConfigurationBuilder;
+    use lldap_domain::types::AttributeName;
+
+    #[test]
+    fn test_is_reloadable() {
+        assert!(is_reloadable("verbose"));
+        assert!(is_reloadable("smtp_options.server"));
+        assert!(is_reloadable("ldaps_options.cert_file"));
+        assert!(!is_reloadable("smtp_options.enable_password_reset"));
+        assert!(!is_reloadable("ldaps_options.port"));
+        assert!(!is_reloadable("verbose_logs"));
+        assert!(!is_reloadable("ldap_port"));
+    }
+
+    #[test]
+    fn test_changed_settings() {
+        let old = ConfigurationBuilder::default().private_build().unwrap();
+        let mut new = old.clone();
+        new.ldap_port = 1389;
+        new.smtp_options.server = "mail.example.com".to_owned();
+        new.ignored_user_attributes = vec![AttributeName::from("mail")];
+        assert_eq!(
+            changed_settings(&old, &new).unwrap(),
+            vec![
+                "ignored_user_attributes",
+                "ldap_port",
+                "smtp_options.server"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_keeps_restart_only_settings() {
+        let old = ConfigurationBuilder::default().private_build().unwrap();
+        let reloadable = ReloadableConfig::new(&old).unwrap();
+        let mut new = old.clone();
+        new.ldap_base_dn = "dc=other,dc=org".to_owned();
+        new.ignored_group_attributes = vec![AttributeName::from("description")];
+        new.smtp_options.server = "mail.example.com".to_owned();
+        new.smtp_options.enable_password_reset = true;
+        reloadable.apply(&new).unwrap();
+        let ldap_info = reloadable.ldap_info();
+        assert_eq!(ldap_info.base_dn_str, "dc=example,dc=com");
+        assert_eq!(
+            ldap_info.ignored_group_attributes,
+            vec![AttributeName::from("description")]
+        );
+        let mail_options = reloadable.mail_options();
+        assert_eq!(mail_options.server, "mail.example.com");
+        assert!(!mail_options.enable_password_reset);
+        assert!(reloadable.ldaps_certificate().is_none());
+    }
+}
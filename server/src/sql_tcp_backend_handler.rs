@@ -1,20 +1,27 @@
-use crate::tcp_backend_handler::TcpBackendHandler;
+use crate::tcp_backend_handler::{Invitation, TcpBackendHandler, WebauthnCredential};
 use async_trait::async_trait;
 use chrono::NaiveDateTime;
-use lldap_domain::types::UserId;
+use lldap_domain::types::{Email, GroupId, UserId};
 use lldap_domain_model::{
     error::*,
-    model::{self, JwtRefreshStorageColumn, JwtStorageColumn, PasswordResetTokensColumn},
+    model::{
+        self, InvitationsColumn, JwtRefreshStorageColumn, JwtStorageColumn,
+        PasswordResetTokensColumn, UserColumn, WebauthnCredentialsColumn,
+    },
 };
 use lldap_sql_backend_handler::SqlBackendHandler;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, QuerySelect,
+    ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, IntoActiveModel, PaginatorTrait,
+    QueryFilter, QueryOrder, QuerySelect,
     sea_query::{Cond, Expr},
 };
 use std::collections::HashSet;
 use tracing::{debug, instrument};
 
-fn gen_random_string(len: usize) -> String {
+/// Beyond that, new reset requests are ignored until the previous links expire.
+const MAX_PENDING_PASSWORD_RESETS: u64 = 3;
+
+pub(crate) fn gen_random_string(len: usize) -> String {
     use rand::{Rng, SeedableRng, distributions::Alphanumeric, rngs::SmallRng};
     let mut rng = SmallRng::from_entropy();
     std::iter::repeat(())
@@ -24,6 +31,47 @@ fn gen_random_string(len: usize) -> String {
         .collect()
 }
 
+impl From<model::webauthn_credentials::Model> for WebauthnCredential {
+    fn from(model: model::webauthn_credentials::Model) -> Self {
+        Self {
+            credential_id: model.credential_id,
+            user_id: model.user_id,
+            display_name: model.display_name,
+            credential: model.credential,
+            creation_date: model.creation_date,
+            last_used_date: model.last_used_date,
+        }
+    }
+}
+
+impl TryFrom<model::invitations::Model> for Invitation {
+    type Error = DomainError;
+    fn try_from(model: model::invitations::Model) -> Result<Self> {
+        let group_ids = model
+            .group_ids
+            .split(',')
+            .filter(|id| !id.is_empty())
+            .map(|id| {
+                id.parse().map(GroupId).map_err(|_| {
+                    DomainError::InternalError(format!("Invalid group ID in invitation: '{id}'"))
+                })
+            })
+            .collect::<Result<_>>()?;
+        let attributes = serde_json::from_str(&model.attributes).map_err(|e| {
+            DomainError::InternalError(format!("Invalid attributes in invitation: {e}"))
+        })?;
+        Ok(Self {
+            token: model.token,
+            user_id: model.user_id,
+            email: model.email,
+            group_ids,
+            attributes,
+            created_by: model.created_by,
+            expiry_date: model.expiry_date,
+        })
+    }
+}
+
 #[async_trait]
 impl TcpBackendHandler for SqlBackendHandler {
     #[instrument(skip_all, level = "debug")]
@@ -138,6 +186,15 @@ impl TcpBackendHandler for SqlBackendHandler {
             debug!("User not found");
             return Ok(None);
         }
+        let pending_tokens = model::PasswordResetTokens::find()
+            .filter(PasswordResetTokensColumn::UserId.eq(user))
+            .filter(PasswordResetTokensColumn::ExpiryDate.gt(chrono::Utc::now().naive_utc()))
+            .count(self.pool())
+            .await?;
+        if pending_tokens >= MAX_PENDING_PASSWORD_RESETS {
+            debug!("Too many pending password resets");
+            return Ok(None);
+        }
 
         let token = gen_random_string(100);
         let duration = chrono::Duration::minutes(10);
@@ -174,4 +231,319 @@ impl TcpBackendHandler for SqlBackendHandler {
         }
         Ok(())
     }
+
+    #[instrument(skip_all, level = "debug")]
+    async fn get_webauthn_credentials(&self, user: &UserId) -> Result<Vec<WebauthnCredential>> {
+        debug!(?user);
+        Ok(model::WebauthnCredentials::find()
+            .filter(WebauthnCredentialsColumn::UserId.eq(user))
+            .order_by_asc(WebauthnCredentialsColumn::CreationDate)
+            .all(self.pool())
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    async fn get_webauthn_credential(
+        &self,
+        credential_id: &str,
+    ) -> Result<Option<WebauthnCredential>> {
+        Ok(
+            model::WebauthnCredentials::find_by_id(credential_id.to_owned())
+                .one(self.pool())
+                .await?
+                .map(Into::into),
+        )
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    async fn add_webauthn_credential(&self, credential: WebauthnCredential) -> Result<()> {
+        debug!(user = ?credential.user_id, name = ?credential.display_name);
+        model::webauthn_credentials::Model {
+            credential_id: credential.credential_id,
+            user_id: credential.user_id,
+            display_name: credential.display_name,
+            credential: credential.credential,
+            creation_date: credential.creation_date,
+            last_used_date: credential.last_used_date,
+        }
+        .into_active_model()
+        .insert(self.pool())
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    async fn update_webauthn_credential(
+        &self,
+        credential_id: &str,
+        credential: Vec<u8>,
+    ) -> Result<()> {
+        model::webauthn_credentials::ActiveModel {
+            credential_id: ActiveValue::Set(credential_id.to_owned()),
+            credential: ActiveValue::Set(credential),
+            last_used_date: ActiveValue::Set(Some(chrono::Utc::now().naive_utc())),
+            ..Default::default()
+        }
+        .update(self.pool())
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    async fn delete_webauthn_credential(&self, user: &UserId, credential_id: &str) -> Result<()> {
+        debug!(?user, ?credential_id);
+        let result = model::WebauthnCredentials::delete_many()
+            .filter(WebauthnCredentialsColumn::CredentialId.eq(credential_id))
+            .filter(WebauthnCredentialsColumn::UserId.eq(user))
+            .exec(self.pool())
+            .await?;
+        if result.rows_affected == 0 {
+            return Err(DomainError::EntityNotFound(format!(
+                "No such WebAuthn credential for user '{user}': '{credential_id}'"
+            )));
+        }
+        Ok(())
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    async fn mark_email_verified(&self, user: &UserId, email: &Email) -> Result<()> {
+        debug!(?user, ?email);
+        let result = model::User::update_many()
+            .col_expr(UserColumn::EmailVerified, Expr::value(true))
+            .filter(ColumnTrait::eq(&UserColumn::UserId, user))
+            .filter(ColumnTrait::eq(
+                &UserColumn::LowercaseEmail,
+                email.as_str().to_lowercase(),
+            ))
+            .exec(self.pool())
+            .await?;
+        if result.rows_affected == 0 {
+            return Err(DomainError::EntityNotFound(format!(
+                "No user '{user}' with the email '{email}'"
+            )));
+        }
+        Ok(())
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    async fn create_invitation(&self, invitation: Invitation) -> Result<()> {
+        debug!(user = ?invitation.user_id, email = ?invitation.email, by = ?invitation.created_by);
+        model::invitations::Model {
+            token: invitation.token,
+            user_id: invitation.user_id,
+            email: invitation.email,
+            group_ids: invitation
+                .group_ids
+                .iter()
+                .map(|id| id.0.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            attributes: serde_json::to_string(&invitation.attributes).map_err(|e| {
+                DomainError::InternalError(format!("Could not encode the attributes: {e}"))
+            })?,
+            created_by: invitation.created_by,
+            expiry_date: invitation.expiry_date,
+        }
+        .into_active_model()
+        .insert(self.pool())
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    async fn get_invitation(&self, token: &str) -> Result<Invitation> {
+        model::Invitations::find_by_id(token.to_owned())
+            .filter(InvitationsColumn::ExpiryDate.gt(chrono::Utc::now().naive_utc()))
+            .one(self.pool())
+            .await?
+            .ok_or_else(|| DomainError::EntityNotFound("Invalid invitation".to_owned()))?
+            .try_into()
+    }
+
+    #[instrument(skip_all, level = "debug")]
+    async fn delete_invitation(&self, token: &str) -> Result<()> {
+        let result = model::Invitations::delete_by_id(token.to_owned())
+            .exec(self.pool())
+            .await?;
+        if result.rows_affected == 0 {
+            return Err(DomainError::EntityNotFound("No such invitation".to_owned()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jwt_sql_tables;
+    use lldap_domain::types::Attribute;
+    use lldap_sql_backend_handler::test_fixture::TestFixture;
+
+    async fn get_handler() -> SqlBackendHandler {
+        let handler = TestFixture::new().await.handler;
+        jwt_sql_tables::init_table(handler.pool()).await.unwrap();
+        handler
+    }
+
+    fn credential(credential_id: &str, user: &str, day: u32) -> WebauthnCredential {
+        WebauthnCredential {
+            credential_id: credential_id.to_owned(),
+            user_id: UserId::new(user),
+            display_name: format!("{credential_id} key"),
+            credential: credential_id.as_bytes().to_vec(),
+            creation_date: chrono::NaiveDate::from_ymd_opt(2024, 1, day)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+            last_used_date: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_webauthn_credentials() {
+        let handler = get_handler().await;
+        handler
+            .add_webauthn_credential(credential("yubikey", "bob", 2))
+            .await
+            .unwrap();
+        handler
+            .add_webauthn_credential(credential("phone", "bob", 1))
+            .await
+            .unwrap();
+        handler
+            .add_webauthn_credential(credential("laptop", "patrick", 1))
+            .await
+            .unwrap();
+        // The credential IDs are unique.
+        handler
+            .add_webauthn_credential(credential("phone", "patrick", 3))
+            .await
+            .unwrap_err();
+
+        let bob = UserId::new("bob");
+        assert_eq!(
+            handler.get_webauthn_credentials(&bob).await.unwrap(),
+            vec![
+                credential("phone", "bob", 1),
+                credential("yubikey", "bob", 2)
+            ]
+        );
+        assert_eq!(
+            handler.get_webauthn_credential("laptop").await.unwrap(),
+            Some(credential("laptop", "patrick", 1))
+        );
+        assert_eq!(
+            handler.get_webauthn_credential("tablet").await.unwrap(),
+            None
+        );
+
+        handler
+            .update_webauthn_credential("phone", b"updated".to_vec())
+            .await
+            .unwrap();
+        let updated = handler
+            .get_webauthn_credential("phone")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.credential, b"updated".to_vec());
+        assert!(updated.last_used_date.is_some());
+        assert_eq!(updated.display_name, "phone key");
+
+        // Users can only remove their own credentials.
+        assert!(matches!(
+            handler.delete_webauthn_credential(&bob, "laptop").await,
+            Err(DomainError::EntityNotFound(_))
+        ));
+        handler
+            .delete_webauthn_credential(&bob, "yubikey")
+            .await
+            .unwrap();
+        assert_eq!(
+            handler
+                .get_webauthn_credentials(&bob)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|c| c.credential_id)
+                .collect::<Vec<_>>(),
+            vec!["phone".to_owned()]
+        );
+        assert!(
+            handler
+                .get_webauthn_credential("laptop")
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    fn invitation(token: &str, expiry_date: NaiveDateTime) -> Invitation {
+        Invitation {
+            token: token.to_owned(),
+            user_id: Some(UserId::new("alice")),
+            email: Some("alice@example.com".into()),
+            group_ids: vec![GroupId(1), GroupId(3)],
+            attributes: vec![Attribute {
+                name: "first_name".into(),
+                value: "Alice".to_owned().into(),
+            }],
+            created_by: UserId::new("bob"),
+            expiry_date,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invitations() {
+        let handler = get_handler().await;
+        let now = chrono::Utc::now().naive_utc();
+        let tomorrow = now + chrono::Duration::days(1);
+        handler
+            .create_invitation(invitation("valid", tomorrow))
+            .await
+            .unwrap();
+        handler
+            .create_invitation(invitation("expired", now - chrono::Duration::days(1)))
+            .await
+            .unwrap();
+        handler
+            .create_invitation(Invitation {
+                user_id: None,
+                email: None,
+                group_ids: Vec::new(),
+                attributes: Vec::new(),
+                ..invitation("open", tomorrow)
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            handler.get_invitation("valid").await.unwrap(),
+            invitation("valid", tomorrow)
+        );
+        let open = handler.get_invitation("open").await.unwrap();
+        assert_eq!((open.user_id, open.email), (None, None));
+        assert!(open.group_ids.is_empty() && open.attributes.is_empty());
+        for token in ["expired", "unknown"] {
+            assert!(matches!(
+                handler.get_invitation(token).await,
+                Err(DomainError::EntityNotFound(_))
+            ));
+        }
+
+        handler.delete_invitation("valid").await.unwrap();
+        assert!(matches!(
+            handler.get_invitation("valid").await,
+            Err(DomainError::EntityNotFound(_))
+        ));
+        assert!(matches!(
+            handler.delete_invitation("valid").await,
+            Err(DomainError::EntityNotFound(_))
+        ));
+        // Expired invitations can still be deleted.
+        handler.delete_invitation("expired").await.unwrap();
+    }
 }
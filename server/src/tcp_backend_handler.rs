@@ -1,9 +1,38 @@
 use async_trait::async_trait;
 use chrono::NaiveDateTime;
-use lldap_domain::types::UserId;
+use lldap_domain::types::{Attribute, Email, GroupId, UserId};
 use lldap_domain_model::error::Result;
 use std::collections::HashSet;
 
+/// A WebAuthn authenticator (passkey) registered by a user.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WebauthnCredential {
+    /// Base64url-encoded credential ID.
+    pub credential_id: String,
+    pub user_id: UserId,
+    pub display_name: String,
+    /// Serialized `webauthn_rs::prelude::Passkey`.
+    pub credential: Vec<u8>,
+    pub creation_date: NaiveDateTime,
+    pub last_used_date: Option<NaiveDateTime>,
+}
+
+/// An admin-issued, single-use invitation to create an account.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Invitation {
+    pub token: String,
+    /// If set, the invitee must register with this user ID.
+    pub user_id: Option<UserId>,
+    /// If set, the new user gets this email and the invitee cannot change it.
+    pub email: Option<Email>,
+    /// Groups the new user is added to.
+    pub group_ids: Vec<GroupId>,
+    /// Attributes of the new user, checked against the schema when the invitation was created.
+    pub attributes: Vec<Attribute>,
+    pub created_by: UserId,
+    pub expiry_date: NaiveDateTime,
+}
+
 #[async_trait]
 pub trait TcpBackendHandler: Sync {
     async fn get_jwt_blacklist(&self) -> anyhow::Result<HashSet<u64>>;
@@ -19,11 +48,43 @@ pub trait TcpBackendHandler: Sync {
     async fn delete_refresh_token(&self, refresh_token_hash: u64) -> Result<()>;
 
     /// Request a token to reset a user's password.
-    /// If the user doesn't exist, returns `Ok(None)`, otherwise `Ok(Some(token))`.
+    /// If the user doesn't exist or has too many pending reset links, returns `Ok(None)`,
+    /// otherwise `Ok(Some(token))`.
     async fn start_password_reset(&self, user: &UserId) -> Result<Option<String>>;
 
     /// Get the user ID associated with a password reset token.
     async fn get_user_id_for_password_reset_token(&self, token: &str) -> Result<UserId>;
 
     async fn delete_password_reset_token(&self, token: &str) -> Result<()>;
+
+    /// List the WebAuthn credentials registered by a user.
+    async fn get_webauthn_credentials(&self, user: &UserId) -> Result<Vec<WebauthnCredential>>;
+
+    /// Find a WebAuthn credential by its ID, regardless of the user.
+    async fn get_webauthn_credential(
+        &self,
+        credential_id: &str,
+    ) -> Result<Option<WebauthnCredential>>;
+
+    async fn add_webauthn_credential(&self, credential: WebauthnCredential) -> Result<()>;
+
+    /// Store the updated credential (e.g. signature counter) after a successful login, and mark
+    /// it as used.
+    async fn update_webauthn_credential(
+        &self,
+        credential_id: &str,
+        credential: Vec<u8>,
+    ) -> Result<()>;
+
+    async fn delete_webauthn_credential(&self, user: &UserId, credential_id: &str) -> Result<()>;
+
+    /// Mark the email of the user as verified, if it is still the given address.
+    async fn mark_email_verified(&self, user: &UserId, email: &Email) -> Result<()>;
+
+    async fn create_invitation(&self, invitation: Invitation) -> Result<()>;
+
+    /// Get a valid (non-expired) invitation by its token.
+    async fn get_invitation(&self, token: &str) -> Result<Invitation>;
+
+    async fn delete_invitation(&self, token: &str) -> Result<()>;
 }
@@ -1,25 +1,37 @@
 use crate::{
     auth_service,
-    configuration::{Configuration, MailOptions},
+    configuration::{Configuration, MailOptions, MetricsOptions},
     logging::CustomRootSpanBuilder,
+    metrics::Metrics,
+    rate_limit::PasswordResetLimiters,
+    reload::ReloadableConfig,
     tcp_backend_handler::*,
+    tls,
+    webauthn::WebauthnState,
 };
 use actix_files::Files;
 use actix_http::{HttpServiceBuilder, header};
 use actix_server::ServerBuilder;
 use actix_service::map_config;
-use actix_web::{App, HttpResponse, Responder, dev::AppConfig, guard, web};
+use actix_web::{App, HttpRequest, HttpResponse, Responder, dev::AppConfig, guard, web};
 use anyhow::{Context, Result};
 use hmac::Hmac;
-use lldap_access_control::{AccessControlledBackendHandler, ReadonlyBackendHandler};
-use lldap_domain_handlers::handler::{BackendHandler, LoginHandler};
+use lldap_access_control::{
+    AccessControlledBackendHandler, AdminBackendHandler, ReadonlyBackendHandler,
+};
+use lldap_domain::types::UserId;
+use lldap_domain_handlers::{
+    audit::{AuditAction, AuditEvent, AuditInterface, AuditSource},
+    handler::{BackendHandler, LoginHandler},
+};
 use lldap_domain_model::error::DomainError;
 use lldap_opaque_handler::OpaqueHandler;
 use sha2::Sha512;
 use std::collections::HashSet;
+use std::net::IpAddr;
 use std::path::PathBuf;
-use std::sync::RwLock;
-use tracing::{info, warn};
+use std::sync::{Arc, RwLock};
+use tracing::{error, info, warn};
 
 async fn index<Backend>(data: web::Data<AppState<Backend>>) -> actix_web::Result<impl Responder> {
     let mut file = std::fs::read_to_string(data.assets_path.join("index.html"))?;
@@ -48,6 +60,10 @@ pub enum TcpError {
     NotFoundError(String),
     #[error("Unauthorized: `{0}`")]
     UnauthorizedError(String),
+    #[error("Forbidden: `{0}`")]
+    ForbiddenError(String),
+    #[error("Too many requests: `{0}`")]
+    TooManyRequestsError(String),
 }
 
 pub type TcpResult<T> = std::result::Result<T, TcpError>;
@@ -64,12 +80,15 @@ pub(crate) fn error_to_http_response(error: TcpError) -> HttpResponse {
             | DomainError::UnknownCryptoError(_) => HttpResponse::InternalServerError(),
             DomainError::Base64DecodeError(_)
             | DomainError::BinarySerializationError(_)
-            | DomainError::EntityNotFound(_) => HttpResponse::BadRequest(),
+            | DomainError::EntityNotFound(_)
+            | DomainError::ValidationError(_) => HttpResponse::BadRequest(),
         },
         TcpError::BadRequest(_) => HttpResponse::BadRequest(),
         TcpError::NotFoundError(_) => HttpResponse::NotFound(),
         TcpError::InternalServerError(_) => HttpResponse::InternalServerError(),
         TcpError::UnauthorizedError(_) => HttpResponse::Unauthorized(),
+        TcpError::ForbiddenError(_) => HttpResponse::Forbidden(),
+        TcpError::TooManyRequestsError(_) => HttpResponse::TooManyRequests(),
     }
     .body(error.to_string())
 }
@@ -108,34 +127,77 @@ async fn wasm_handler_compressed<Backend>(
 
 async fn get_settings<Backend>(data: web::Data<AppState<Backend>>) -> HttpResponse {
     HttpResponse::Ok().json(lldap_frontend_options::Options {
-        password_reset_enabled: data.mail_options.enable_password_reset,
+        password_reset_enabled: data.mail_options().enable_password_reset,
     })
 }
 
+async fn get_metrics<Backend>(
+    data: web::Data<AppState<Backend>>,
+    request: HttpRequest,
+) -> HttpResponse {
+    if let Some(token) = &data.metrics_token {
+        let bearer = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "));
+        // The comparison of `SecUtf8` is constant-time.
+        if bearer.map(secstr::SecUtf8::from).as_ref() != Some(token) {
+            return HttpResponse::Unauthorized().finish();
+        }
+    }
+    match data.metrics.render().await {
+        Ok(metrics) => HttpResponse::Ok()
+            .insert_header((header::CONTENT_TYPE, prometheus::TEXT_FORMAT))
+            .body(metrics),
+        Err(e) => {
+            error!("Could not render the metrics: {e:#}");
+            HttpResponse::InternalServerError().body(e.to_string())
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn http_config<Backend>(
     cfg: &mut web::ServiceConfig,
-    backend_handler: Backend,
+    backend_handler: AccessControlledBackendHandler<Backend>,
     jwt_secret: secstr::SecUtf8,
     jwt_blacklist: HashSet<u64>,
     server_url: url::Url,
     assets_path: PathBuf,
-    mail_options: MailOptions,
+    reloadable_config: Arc<ReloadableConfig>,
+    webauthn: Option<Arc<WebauthnState>>,
+    password_reset_limiters: Arc<PasswordResetLimiters>,
+    metrics: Arc<Metrics>,
+    metrics_options: MetricsOptions,
+    trusted_proxies: Vec<IpAddr>,
 ) where
-    Backend: TcpBackendHandler + BackendHandler + LoginHandler + OpaqueHandler + Clone + 'static,
+    Backend:
+        TcpBackendHandler + BackendHandler + LoginHandler + OpaqueHandler + Clone + Unpin + 'static,
 {
-    let enable_password_reset = mail_options.enable_password_reset;
+    let enable_password_reset = reloadable_config.mail_options().enable_password_reset;
     cfg.app_data(web::Data::new(AppState::<Backend> {
-        backend_handler: AccessControlledBackendHandler::new(backend_handler),
+        backend_handler,
         jwt_key: hmac::Mac::new_from_slice(jwt_secret.unsecure().as_bytes()).unwrap(),
         jwt_blacklist: RwLock::new(jwt_blacklist),
         server_url,
         assets_path: assets_path.clone(),
-        mail_options,
+        reloadable_config,
+        webauthn,
+        password_reset_limiters,
+        metrics,
+        metrics_token: metrics_options.token,
+        trusted_proxies,
     }))
     .route(
         "/health",
         web::get().to(async || HttpResponse::Ok().finish()),
     )
+    .configure(|cfg| {
+        if metrics_options.enabled {
+            cfg.route("/metrics", web::get().to(get_metrics::<Backend>));
+        }
+    })
     .route("/settings", web::get().to(get_settings::<Backend>))
     .service(
         web::scope("/auth")
@@ -145,7 +207,8 @@ fn http_config<Backend>(
     .service(
         web::scope("/api")
             .wrap(auth_service::CookieToHeaderTranslatorFactory)
-            .configure(crate::graphql_server::configure_endpoint::<Backend>),
+            .configure(crate::graphql_server::configure_endpoint::<Backend>)
+            .configure(crate::import_export::configure_endpoint::<Backend>),
     )
     .service(
         web::resource("/pkg/lldap_app_bg.wasm.gz")
@@ -174,13 +237,74 @@ pub(crate) struct AppState<Backend> {
     pub jwt_blacklist: RwLock<HashSet<u64>>,
     pub server_url: url::Url,
     pub assets_path: PathBuf,
-    pub mail_options: MailOptions,
+    pub reloadable_config: Arc<ReloadableConfig>,
+    pub webauthn: Option<Arc<WebauthnState>>,
+    pub password_reset_limiters: Arc<PasswordResetLimiters>,
+    pub metrics: Arc<Metrics>,
+    /// If set, required to read the metrics.
+    pub metrics_token: Option<secstr::SecUtf8>,
+    pub trusted_proxies: Vec<IpAddr>,
 }
 
+impl<Backend> AppState<Backend> {
+    /// The current mail settings, which can change when the configuration is reloaded.
+    pub fn mail_options(&self) -> Arc<MailOptions> {
+        self.reloadable_config.mail_options()
+    }
+
+    /// The IP of the client. If the peer is a trusted proxy, it is read from the `X-Forwarded-For`
+    /// header: the last address in it that is not a trusted proxy itself.
+    pub fn client_ip(&self, request: &HttpRequest) -> Option<IpAddr> {
+        let mut client_ip = request.peer_addr()?.ip();
+        let forwarded_for = request
+            .headers()
+            .get_all("X-Forwarded-For")
+            .filter_map(|h| h.to_str().ok())
+            .flat_map(|h| h.split(','))
+            .collect::<Vec<_>>();
+        for address in forwarded_for.into_iter().rev() {
+            if !self.trusted_proxies.contains(&client_ip) {
+                break;
+            }
+            match address.trim().parse() {
+                Ok(address) => client_ip = address,
+                // Addresses before an invalid one can't be trusted.
+                Err(_) => break,
+            }
+        }
+        Some(client_ip)
+    }
+
+    /// Records an event of the HTTP interface in the audit log.
+    pub async fn record_audit_event(
+        &self,
+        request: &HttpRequest,
+        actor: Option<&UserId>,
+        action: AuditAction,
+        target: Option<String>,
+        success: bool,
+    ) {
+        self.backend_handler
+            .record_audit_event(AuditEvent {
+                actor: actor.cloned(),
+                action,
+                target,
+                details: None,
+                source: AuditSource::new(AuditInterface::Http, self.client_ip(request)),
+                success,
+            })
+            .await
+    }
+}
 impl<Backend: BackendHandler> AppState<Backend> {
     pub fn get_readonly_handler(&self) -> &(impl ReadonlyBackendHandler + use<Backend>) {
         self.backend_handler.unsafe_get_handler()
     }
+    /// Bypasses the access control: only for requests authorized by other means, like an
+    /// invitation token.
+    pub fn get_admin_handler(&self) -> &(impl AdminBackendHandler + use<Backend>) {
+        self.backend_handler.unsafe_get_handler()
+    }
 }
 impl<Backend: TcpBackendHandler> AppState<Backend> {
     pub fn get_tcp_handler(&self) -> &(impl TcpBackendHandler + use<Backend>) {
@@ -200,66 +324,236 @@ impl<Backend: LoginHandler> AppState<Backend> {
 
 pub async fn build_tcp_server<Backend>(
     config: &Configuration,
-    backend_handler: Backend,
+    backend_handler: AccessControlledBackendHandler<Backend>,
+    metrics: Arc<Metrics>,
+    reloadable_config: Arc<ReloadableConfig>,
     server_builder: ServerBuilder,
 ) -> Result<ServerBuilder>
 where
-    Backend: TcpBackendHandler + BackendHandler + LoginHandler + OpaqueHandler + Clone + 'static,
+    Backend:
+        TcpBackendHandler + BackendHandler + LoginHandler + OpaqueHandler + Clone + Unpin + 'static,
 {
     let jwt_secret = config.jwt_secret.clone().unwrap();
     let jwt_blacklist = backend_handler
+        .unsafe_get_handler()
         .get_jwt_blacklist()
         .await
         .context("while getting the jwt blacklist")?;
     let server_url = config.http_url.0.clone();
     let assets_path = config.assets_path.clone();
-    let mail_options = config.smtp_options.clone();
-    let verbose = config.verbose;
+    // The request spans are also needed to export the traces.
+    let trace_requests = config.verbose || config.opentelemetry_options.enabled;
+    let webauthn = match WebauthnState::new(&server_url) {
+        Ok(state) => Some(Arc::new(state)),
+        Err(e) => {
+            warn!("WebAuthn (passkey) login is disabled: {e:#}");
+            None
+        }
+    };
+    let password_reset_limiters =
+        Arc::new(PasswordResetLimiters::new(&config.password_reset_limits));
+    let metrics_options = config.metrics_options.clone();
+    let trusted_proxies = config.trusted_proxies.clone();
     if !assets_path.join("index.html").exists() {
         warn!(
             "Cannot find {}, please ensure that assets_path is set correctly and that the front-end files exist.",
             assets_path.to_string_lossy()
         )
     }
-    info!("Starting the API/web server on port {}", config.http_port);
-    server_builder
-        .bind(
-            "http",
-            (config.http_host.clone(), config.http_port),
-            move || {
-                let backend_handler = backend_handler.clone();
-                let jwt_secret = jwt_secret.clone();
-                let jwt_blacklist = jwt_blacklist.clone();
-                let server_url = server_url.clone();
-                let assets_path = assets_path.clone();
-                let mail_options = mail_options.clone();
-                HttpServiceBuilder::default()
-                    .finish(map_config(
-                        App::new()
-                            .wrap(actix_web::middleware::Condition::new(
-                                verbose,
-                                tracing_actix_web::TracingLogger::<CustomRootSpanBuilder>::new(),
-                            ))
-                            .configure(move |cfg| {
-                                http_config(
-                                    cfg,
-                                    backend_handler,
-                                    jwt_secret,
-                                    jwt_blacklist,
-                                    server_url,
-                                    assets_path,
-                                    mail_options,
-                                )
-                            }),
-                        |_| AppConfig::default(),
-                    ))
-                    .tcp()
-            },
+    let https_config = if config.https_options.enabled {
+        Some(
+            tls::make_server_config(
+                reloadable_config
+                    .https_certificate()
+                    .context("HTTPS is enabled but has no certificate")?,
+            )
+            .context("while setting up the HTTPS certificate")?,
         )
-        .with_context(|| {
-            format!(
-                "While bringing up the TCP server with port {}",
+    } else {
+        None
+    };
+    let make_app = move || {
+        let backend_handler = backend_handler.clone();
+        let jwt_secret = jwt_secret.clone();
+        let jwt_blacklist = jwt_blacklist.clone();
+        let server_url = server_url.clone();
+        let assets_path = assets_path.clone();
+        let reloadable_config = reloadable_config.clone();
+        let webauthn = webauthn.clone();
+        let password_reset_limiters = password_reset_limiters.clone();
+        let metrics = metrics.clone();
+        let metrics_options = metrics_options.clone();
+        let trusted_proxies = trusted_proxies.clone();
+        map_config(
+            App::new()
+                .wrap(actix_web::middleware::Condition::new(
+                    trace_requests,
+                    tracing_actix_web::TracingLogger::<CustomRootSpanBuilder>::new(),
+                ))
+                .configure(move |cfg| {
+                    http_config(
+                        cfg,
+                        backend_handler,
+                        jwt_secret,
+                        jwt_blacklist,
+                        server_url,
+                        assets_path,
+                        reloadable_config,
+                        webauthn,
+                        password_reset_limiters,
+                        metrics,
+                        metrics_options,
+                        trusted_proxies,
+                    )
+                }),
+            |_| AppConfig::default(),
+        )
+    };
+    let address = (config.http_host.clone(), config.http_port);
+    match https_config {
+        Some(https_config) => {
+            info!(
+                "Starting the API/web server on port {} (HTTPS)",
                 config.http_port
-            )
+            );
+            server_builder.bind("https", address, move || {
+                HttpServiceBuilder::default()
+                    .finish(make_app())
+                    .rustls_0_23(https_config.clone())
+            })
+        }
+        None => {
+            info!("Starting the API/web server on port {}", config.http_port);
+            server_builder.bind("http", address, move || {
+                HttpServiceBuilder::default().finish(make_app()).tcp()
+            })
+        }
+    }
+    .with_context(|| {
+        format!(
+            "While bringing up the TCP server with port {}",
+            config.http_port
+        )
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{configuration::ConfigurationBuilder, jwt_sql_tables};
+    use lldap_sql_backend_handler::{SqlBackendHandler, test_fixture::TestFixture};
+
+    /// The state of the HTTP server, with the users and groups of the `TestFixture`.
+    pub async fn get_app_state() -> web::Data<AppState<SqlBackendHandler>> {
+        let handler = TestFixture::new().await.handler;
+        jwt_sql_tables::init_table(handler.pool()).await.unwrap();
+        let config = ConfigurationBuilder::default().private_build().unwrap();
+        web::Data::new(AppState {
+            metrics: Arc::new(Metrics::new(handler.pool().clone()).unwrap()),
+            backend_handler: AccessControlledBackendHandler::new(handler),
+            jwt_key: hmac::Mac::new_from_slice(b"test_jwt_secret").unwrap(),
+            jwt_blacklist: RwLock::new(HashSet::new()),
+            server_url: config.http_url.0.clone(),
+            assets_path: config.assets_path.clone(),
+            reloadable_config: Arc::new(ReloadableConfig::new(&config).unwrap()),
+            webauthn: None,
+            password_reset_limiters: Arc::new(PasswordResetLimiters::new(&Default::default())),
+            metrics_token: None,
+            trusted_proxies: Vec::new(),
         })
+    }
+
+    #[tokio::test]
+    async fn test_metrics_token() {
+        let data = get_app_state().await;
+        let data = web::Data::new(AppState {
+            metrics_token: Some(secstr::SecUtf8::from("scraper")),
+            ..Arc::try_unwrap(data.into_inner()).ok().unwrap()
+        });
+        let request = |token: Option<&str>| {
+            let mut request = actix_web::test::TestRequest::default();
+            if let Some(token) = token {
+                request = request.insert_header((header::AUTHORIZATION, token));
+            }
+            request.to_http_request()
+        };
+        for token in [None, Some("Bearer other"), Some("scraper")] {
+            let response = get_metrics(data.clone(), request(token)).await;
+            assert_eq!(response.status(), 401, "{token:?}");
+        }
+        let response = get_metrics(data, request(Some("Bearer scraper"))).await;
+        assert_eq!(response.status(), 200);
+    }
+
+    async fn get_metrics_status(metrics_options: MetricsOptions) -> actix_http::StatusCode {
+        let handler = TestFixture::new().await.handler;
+        jwt_sql_tables::init_table(handler.pool()).await.unwrap();
+        let config = ConfigurationBuilder::default().private_build().unwrap();
+        let metrics = Arc::new(Metrics::new(handler.pool().clone()).unwrap());
+        let app = actix_web::test::init_service(App::new().configure(|cfg| {
+            http_config(
+                cfg,
+                AccessControlledBackendHandler::new(handler),
+                secstr::SecUtf8::from("test_jwt_secret"),
+                HashSet::new(),
+                config.http_url.0.clone(),
+                config.assets_path.clone(),
+                Arc::new(ReloadableConfig::new(&config).unwrap()),
+                None,
+                Arc::new(PasswordResetLimiters::new(&Default::default())),
+                metrics,
+                metrics_options,
+                Vec::new(),
+            )
+        }))
+        .await;
+        actix_web::test::call_service(
+            &app,
+            actix_web::test::TestRequest::get()
+                .uri("/metrics")
+                .to_request(),
+        )
+        .await
+        .status()
+    }
+
+    #[tokio::test]
+    async fn test_metrics_route() {
+        assert_eq!(get_metrics_status(MetricsOptions::default()).await, 404);
+        let enabled = MetricsOptions {
+            enabled: true,
+            token: None,
+        };
+        assert_eq!(get_metrics_status(enabled).await, 200);
+    }
+
+    #[tokio::test]
+    async fn test_client_ip() {
+        let data = get_app_state().await;
+        let data = AppState {
+            trusted_proxies: vec!["10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap()],
+            ..Arc::try_unwrap(data.into_inner()).ok().unwrap()
+        };
+        let client_ip = |peer: &str, forwarded_for: Option<&str>| {
+            let mut request = actix_web::test::TestRequest::default()
+                .peer_addr(std::net::SocketAddr::new(peer.parse().unwrap(), 1234));
+            if let Some(forwarded_for) = forwarded_for {
+                request = request.insert_header(("X-Forwarded-For", forwarded_for));
+            }
+            data.client_ip(&request.to_http_request())
+                .unwrap()
+                .to_string()
+        };
+        // The header is ignored for the other peers.
+        assert_eq!(client_ip("192.168.1.1", Some("1.2.3.4")), "192.168.1.1");
+        assert_eq!(client_ip("10.0.0.1", None), "10.0.0.1");
+        assert_eq!(client_ip("10.0.0.1", Some("1.2.3.4")), "1.2.3.4");
+        // The addresses added by the client itself are ignored.
+        assert_eq!(
+            client_ip("10.0.0.1", Some("6.6.6.6, 1.2.3.4, 10.0.0.2")),
+            "1.2.3.4"
+        );
+        assert_eq!(client_ip("10.0.0.1", Some("10.0.0.2")), "10.0.0.2");
+        assert_eq!(client_ip("10.0.0.1", Some("1.2.3.4, garbage")), "10.0.0.1");
+    }
 }
@@ -0,0 +1,572 @@
+use crate::{
+    auth_service::{check_if_token_is_valid, get_login_successful_response},
+    tcp_backend_handler::{TcpBackendHandler, WebauthnCredential},
+    tcp_server::{AppState, TcpError, TcpResult, error_to_http_response},
+};
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use anyhow::{Context, Result};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use lldap_access_control::UserReadableBackendHandler;
+use lldap_auth::{access_control::ValidationResults, webauthn};
+use lldap_domain::types::UserId;
+use lldap_domain_handlers::{audit::AuditAction, handler::BackendHandler};
+use std::{collections::HashMap, sync::Mutex};
+use tracing::{debug, info, instrument, warn};
+use webauthn_rs::prelude::{
+    DiscoverableAuthentication, DiscoverableKey, Passkey, PasskeyRegistration, Webauthn,
+    WebauthnBuilder,
+};
+
+/// How long a client has to answer a registration or login challenge.
+const CEREMONY_TIMEOUT: chrono::Duration = chrono::Duration::minutes(5);
+/// The login ceremonies can be started without authentication: past that many pending
+/// ceremonies, new ones are refused until the older ones expire.
+const MAX_PENDING_CEREMONIES: usize = 10_000;
+
+enum CeremonyState {
+    Registration {
+        user_id: UserId,
+        state: PasskeyRegistration,
+    },
+    Authentication(DiscoverableAuthentication),
+}
+
+/// The relying party configuration, and the pending registration/login ceremonies.
+///
+/// It is shared between all the HTTP workers, since the start and finish requests of a ceremony
+/// can be served by different workers.
+pub(crate) struct WebauthnState {
+    webauthn: Webauthn,
+    ceremonies: Mutex<HashMap<String, (CeremonyState, DateTime<Utc>)>>,
+    max_ceremonies: usize,
+}
+
+impl WebauthnState {
+    /// The relying party ID is the host of the server URL, and only the server URL is accepted
+    /// as an origin.
+    pub fn new(server_url: &url::Url) -> Result<Self> {
+        let rp_id = server_url
+            .host_str()
+            .context("The HTTP URL has no host, cannot use it as a WebAuthn relying party")?;
+        let webauthn = WebauthnBuilder::new(rp_id, server_url)
+            .context("Invalid WebAuthn relying party")?
+            .rp_name("LLDAP")
+            .build()
+            .context("Could not build the WebAuthn relying party")?;
+        Ok(Self {
+            webauthn,
+            ceremonies: Mutex::new(HashMap::new()),
+            max_ceremonies: MAX_PENDING_CEREMONIES,
+        })
+    }
+
+    fn start_ceremony(&self, state: CeremonyState) -> TcpResult<String> {
+        self.start_ceremony_at(state, Utc::now())
+    }
+
+    fn start_ceremony_at(&self, state: CeremonyState, now: DateTime<Utc>) -> TcpResult<String> {
+        let mut ceremonies = self.ceremonies.lock().unwrap();
+        ceremonies.retain(|_, (_, expiry)| *expiry > now);
+        if ceremonies.len() >= self.max_ceremonies {
+            warn!("Too many pending WebAuthn challenges, refusing a new one");
+            return Err(TcpError::TooManyRequestsError(
+                "Too many pending WebAuthn challenges, try again later".to_owned(),
+            ));
+        }
+        let state_id = uuid::Uuid::new_v4().to_string();
+        ceremonies.insert(state_id.clone(), (state, now + CEREMONY_TIMEOUT));
+        Ok(state_id)
+    }
+
+    /// Removes the ceremony: each challenge can only be answered once.
+    fn finish_ceremony(&self, state_id: &str) -> TcpResult<CeremonyState> {
+        self.finish_ceremony_at(state_id, Utc::now())
+    }
+
+    fn finish_ceremony_at(&self, state_id: &str, now: DateTime<Utc>) -> TcpResult<CeremonyState> {
+        match self.ceremonies.lock().unwrap().remove(state_id) {
+            Some((state, expiry)) if expiry > now => Ok(state),
+            _ => Err(TcpError::UnauthorizedError(
+                "Unknown or expired WebAuthn challenge".to_owned(),
+            )),
+        }
+    }
+}
+
+fn encode_credential_id(id: impl AsRef<[u8]>) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(id.as_ref())
+}
+
+fn deserialize_passkey(credential: &WebauthnCredential) -> TcpResult<Passkey> {
+    serde_json::from_slice(&credential.credential).map_err(|e| {
+        TcpError::InternalServerError(format!(
+            "Corrupted WebAuthn credential '{}': {e}",
+            credential.credential_id
+        ))
+    })
+}
+
+fn serialize_passkey(passkey: &Passkey) -> TcpResult<Vec<u8>> {
+    serde_json::to_vec(passkey).map_err(|e| {
+        TcpError::InternalServerError(format!("Could not serialize WebAuthn credential: {e}"))
+    })
+}
+
+fn get_webauthn_state<Backend>(data: &AppState<Backend>) -> TcpResult<&WebauthnState> {
+    data.webauthn
+        .as_deref()
+        .ok_or_else(|| TcpError::NotFoundError("WebAuthn is not available".to_owned()))
+}
+
+fn validate_bearer<Backend: BackendHandler>(
+    data: &AppState<Backend>,
+    credentials: &BearerAuth,
+) -> TcpResult<ValidationResults> {
+    check_if_token_is_valid(data, credentials.token())
+        .map_err(|e| TcpError::UnauthorizedError(e.to_string()))
+}
+
+fn get_user_id_from_path(request: &HttpRequest) -> TcpResult<UserId> {
+    request
+        .match_info()
+        .get("user_id")
+        .map(UserId::new)
+        .ok_or_else(|| TcpError::BadRequest("Missing user ID".to_owned()))
+}
+
+#[instrument(skip_all, level = "debug")]
+async fn register_start<Backend>(
+    data: web::Data<AppState<Backend>>,
+    credentials: BearerAuth,
+    request: web::Json<webauthn::ClientRegistrationStartRequest>,
+) -> TcpResult<webauthn::ServerRegistrationStartResponse>
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    let webauthn_state = get_webauthn_state(&data)?;
+    // Authenticators can only be registered by the user holding them.
+    let user_id = validate_bearer(&data, &credentials)?.user;
+    if request.name.trim().is_empty() {
+        return Err(TcpError::BadRequest(
+            "Missing authenticator name".to_owned(),
+        ));
+    }
+    let user = data
+        .get_readonly_handler()
+        .get_user_details(&user_id)
+        .await?;
+    let user_uuid = uuid::Uuid::parse_str(user.uuid.as_str())
+        .map_err(|e| TcpError::InternalServerError(format!("Invalid user UUID: {e}")))?;
+    let exclude_credentials = data
+        .get_tcp_handler()
+        .get_webauthn_credentials(&user_id)
+        .await?
+        .iter()
+        .map(|c| deserialize_passkey(c).map(|p| p.cred_id().clone()))
+        .collect::<TcpResult<Vec<_>>>()?;
+    let (challenge, state) = webauthn_state
+        .webauthn
+        .start_passkey_registration(
+            user_uuid,
+            user_id.as_str(),
+            user.display_name.as_deref().unwrap_or(user_id.as_str()),
+            Some(exclude_credentials),
+        )
+        .map_err(|e| TcpError::InternalServerError(format!("WebAuthn error: {e}")))?;
+    let state_id = webauthn_state.start_ceremony(CeremonyState::Registration { user_id, state })?;
+    Ok(webauthn::ServerRegistrationStartResponse {
+        state_id,
+        challenge,
+    })
+}
+
+async fn register_start_handler<Backend>(
+    data: web::Data<AppState<Backend>>,
+    credentials: BearerAuth,
+    request: web::Json<webauthn::ClientRegistrationStartRequest>,
+) -> HttpResponse
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    register_start(data, credentials, request)
+        .await
+        .map(|res| HttpResponse::Ok().json(res))
+        .unwrap_or_else(error_to_http_response)
+}
+
+#[instrument(skip_all, level = "debug")]
+async fn register_finish<Backend>(
+    data: web::Data<AppState<Backend>>,
+    credentials: BearerAuth,
+    request: web::Json<webauthn::ClientRegistrationFinishRequest>,
+) -> TcpResult<webauthn::PasskeyInfo>
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    let webauthn_state = get_webauthn_state(&data)?;
+    let token_user = validate_bearer(&data, &credentials)?.user;
+    let request = request.into_inner();
+    let (user_id, state) = match webauthn_state.finish_ceremony(&request.state_id)? {
+        CeremonyState::Registration { user_id, state } => (user_id, state),
+        CeremonyState::Authentication(_) => {
+            return Err(TcpError::BadRequest(
+                "Not a WebAuthn registration challenge".to_owned(),
+            ));
+        }
+    };
+    if user_id != token_user {
+        return Err(TcpError::UnauthorizedError(
+            "WebAuthn challenge was issued for another user".to_owned(),
+        ));
+    }
+    let passkey = webauthn_state
+        .webauthn
+        .finish_passkey_registration(&request.credential, &state)
+        .map_err(|e| {
+            debug!("WebAuthn registration error: {e}");
+            TcpError::BadRequest(format!("Could not register the authenticator: {e}"))
+        })?;
+    let credential = WebauthnCredential {
+        credential_id: encode_credential_id(passkey.cred_id()),
+        user_id,
+        display_name: request.name.trim().to_owned(),
+        credential: serialize_passkey(&passkey)?,
+        creation_date: Utc::now().naive_utc(),
+        last_used_date: None,
+    };
+    info!(
+        r#"Registered WebAuthn authenticator "{}" for "{}""#,
+        &credential.display_name, &credential.user_id
+    );
+    data.get_tcp_handler()
+        .add_webauthn_credential(credential.clone())
+        .await?;
+    Ok(to_passkey_info(credential))
+}
+
+async fn register_finish_handler<Backend>(
+    data: web::Data<AppState<Backend>>,
+    credentials: BearerAuth,
+    request: web::Json<webauthn::ClientRegistrationFinishRequest>,
+) -> HttpResponse
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    register_finish(data, credentials, request)
+        .await
+        .map(|res| HttpResponse::Ok().json(res))
+        .unwrap_or_else(error_to_http_response)
+}
+
+#[instrument(skip_all, level = "debug")]
+async fn login_start<Backend>(
+    data: web::Data<AppState<Backend>>,
+) -> TcpResult<webauthn::ServerLoginStartResponse>
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    let webauthn_state = get_webauthn_state(&data)?;
+    let (challenge, state) = webauthn_state
+        .webauthn
+        .start_discoverable_authentication()
+        .map_err(|e| TcpError::InternalServerError(format!("WebAuthn error: {e}")))?;
+    let state_id = webauthn_state.start_ceremony(CeremonyState::Authentication(state))?;
+    Ok(webauthn::ServerLoginStartResponse {
+        state_id,
+        challenge,
+    })
+}
+
+async fn login_start_handler<Backend>(data: web::Data<AppState<Backend>>) -> HttpResponse
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    login_start(data)
+        .await
+        .map(|res| HttpResponse::Ok().json(res))
+        .unwrap_or_else(error_to_http_response)
+}
+
+#[instrument(skip_all, level = "debug")]
+async fn login_finish<Backend>(
+    data: web::Data<AppState<Backend>>,
+    http_request: HttpRequest,
+    request: web::Json<webauthn::ClientLoginFinishRequest>,
+) -> TcpResult<HttpResponse>
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    let webauthn_state = get_webauthn_state(&data)?;
+    let request = request.into_inner();
+    let state = match webauthn_state.finish_ceremony(&request.state_id)? {
+        CeremonyState::Authentication(state) => state,
+        CeremonyState::Registration { .. } => {
+            return Err(TcpError::BadRequest(
+                "Not a WebAuthn login challenge".to_owned(),
+            ));
+        }
+    };
+    let invalid_credential =
+        || TcpError::UnauthorizedError("Unknown or invalid WebAuthn credential".to_owned());
+    let (user_uuid, credential_id) = webauthn_state
+        .webauthn
+        .identify_discoverable_authentication(&request.credential)
+        .map_err(|e| {
+            debug!("WebAuthn identification error: {e}");
+            invalid_credential()
+        })?;
+    let credential_id = encode_credential_id(credential_id);
+    let stored_credential = data
+        .get_tcp_handler()
+        .get_webauthn_credential(&credential_id)
+        .await?
+        .ok_or_else(invalid_credential)?;
+    let user_id = stored_credential.user_id.clone();
+    let user = data
+        .get_readonly_handler()
+        .get_user_details(&user_id)
+        .await?;
+    if user.uuid.as_str() != user_uuid.to_string() {
+        warn!(
+            r#"WebAuthn credential "{}" presented with the user handle of another user"#,
+            &credential_id
+        );
+        return Err(invalid_credential());
+    }
+    let mut passkey = deserialize_passkey(&stored_credential)?;
+    let result = webauthn_state.webauthn.finish_discoverable_authentication(
+        &request.credential,
+        state,
+        &[DiscoverableKey::from(&passkey)],
+    );
+    data.record_audit_event(
+        &http_request,
+        Some(&user_id),
+        AuditAction::Login,
+        Some(user_id.to_string()),
+        result.is_ok(),
+    )
+    .await;
+    let result = result.map_err(|e| {
+        warn!(r#"WebAuthn login attempt failed for "{}": {e}"#, &user_id);
+        invalid_credential()
+    })?;
+    info!(r#"WebAuthn login successful for "{}""#, &user_id);
+    passkey.update_credential(&result);
+    data.get_tcp_handler()
+        .update_webauthn_credential(&credential_id, serialize_passkey(&passkey)?)
+        .await?;
+    get_login_successful_response(&data, &user_id).await
+}
+
+async fn login_finish_handler<Backend>(
+    data: web::Data<AppState<Backend>>,
+    http_request: HttpRequest,
+    request: web::Json<webauthn::ClientLoginFinishRequest>,
+) -> HttpResponse
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    login_finish(data, http_request, request)
+        .await
+        .unwrap_or_else(error_to_http_response)
+}
+
+fn to_passkey_info(credential: WebauthnCredential) -> webauthn::PasskeyInfo {
+    webauthn::PasskeyInfo {
+        credential_id: credential.credential_id,
+        name: credential.display_name,
+        creation_date: credential.creation_date.and_utc(),
+        last_used_date: credential.last_used_date.map(|d| d.and_utc()),
+    }
+}
+
+#[instrument(skip_all, level = "debug")]
+async fn list_credentials<Backend>(
+    data: web::Data<AppState<Backend>>,
+    credentials: BearerAuth,
+    request: HttpRequest,
+) -> TcpResult<Vec<webauthn::PasskeyInfo>>
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    let user_id = get_user_id_from_path(&request)?;
+    if !validate_bearer(&data, &credentials)?.can_read(&user_id) {
+        return Err(TcpError::UnauthorizedError(
+            "Not authorized to list the user's authenticators".to_owned(),
+        ));
+    }
+    Ok(data
+        .get_tcp_handler()
+        .get_webauthn_credentials(&user_id)
+        .await?
+        .into_iter()
+        .map(to_passkey_info)
+        .collect())
+}
+
+async fn list_credentials_handler<Backend>(
+    data: web::Data<AppState<Backend>>,
+    credentials: BearerAuth,
+    request: HttpRequest,
+) -> HttpResponse
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    list_credentials(data, credentials, request)
+        .await
+        .map(|res| HttpResponse::Ok().json(res))
+        .unwrap_or_else(error_to_http_response)
+}
+
+#[instrument(skip_all, level = "debug")]
+async fn delete_credential<Backend>(
+    data: web::Data<AppState<Backend>>,
+    credentials: BearerAuth,
+    request: HttpRequest,
+) -> TcpResult<()>
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    let user_id = get_user_id_from_path(&request)?;
+    let credential_id = request
+        .match_info()
+        .get("credential_id")
+        .ok_or_else(|| TcpError::BadRequest("Missing credential ID".to_owned()))?;
+    if !validate_bearer(&data, &credentials)?.can_write(&user_id) {
+        return Err(TcpError::UnauthorizedError(
+            "Not authorized to remove the user's authenticators".to_owned(),
+        ));
+    }
+    info!(
+        r#"Removing WebAuthn authenticator "{}" of "{}""#,
+        credential_id, &user_id
+    );
+    Ok(data
+        .get_tcp_handler()
+        .delete_webauthn_credential(&user_id, credential_id)
+        .await?)
+}
+
+async fn delete_credential_handler<Backend>(
+    data: web::Data<AppState<Backend>>,
+    credentials: BearerAuth,
+    request: HttpRequest,
+) -> HttpResponse
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    delete_credential(data, credentials, request)
+        .await
+        .map(|()| HttpResponse::Ok().finish())
+        .unwrap_or_else(error_to_http_response)
+}
+
+pub fn configure_server<Backend>(cfg: &mut web::ServiceConfig)
+where
+    Backend: TcpBackendHandler + BackendHandler + 'static,
+{
+    cfg.service(
+        web::resource("/login/start").route(web::post().to(login_start_handler::<Backend>)),
+    )
+    .service(web::resource("/login/finish").route(web::post().to(login_finish_handler::<Backend>)))
+    .service(
+        web::resource("/register/start").route(web::post().to(register_start_handler::<Backend>)),
+    )
+    .service(
+        web::resource("/register/finish").route(web::post().to(register_finish_handler::<Backend>)),
+    )
+    .service(
+        web::resource("/credentials/{user_id}")
+            .route(web::get().to(list_credentials_handler::<Backend>)),
+    )
+    .service(
+        web::resource("/credentials/{user_id}/{credential_id}")
+            .route(web::delete().to(delete_credential_handler::<Backend>)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_state() -> WebauthnState {
+        WebauthnState::new(&url::Url::parse("http://localhost:17170").unwrap()).unwrap()
+    }
+
+    fn authentication(state: &WebauthnState) -> CeremonyState {
+        CeremonyState::Authentication(
+            state
+                .webauthn
+                .start_discoverable_authentication()
+                .unwrap()
+                .1,
+        )
+    }
+
+    #[test]
+    fn test_relying_party_needs_a_host() {
+        assert!(WebauthnState::new(&url::Url::parse("unix:/run/lldap.sock").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_ceremony_can_only_be_finished_once() {
+        let state = get_state();
+        let state_id = state.start_ceremony(authentication(&state)).unwrap();
+        assert!(matches!(
+            state.finish_ceremony(&state_id),
+            Ok(CeremonyState::Authentication(_))
+        ));
+        assert!(matches!(
+            state.finish_ceremony(&state_id),
+            Err(TcpError::UnauthorizedError(_))
+        ));
+        assert!(matches!(
+            state.finish_ceremony("unknown"),
+            Err(TcpError::UnauthorizedError(_))
+        ));
+    }
+
+    #[test]
+    fn test_ceremony_expires() {
+        let state = get_state();
+        let start = Utc::now();
+        let state_id = state
+            .start_ceremony_at(authentication(&state), start)
+            .unwrap();
+        assert!(matches!(
+            state.finish_ceremony_at(&state_id, start + CEREMONY_TIMEOUT),
+            Err(TcpError::UnauthorizedError(_))
+        ));
+    }
+
+    #[test]
+    fn test_pending_ceremonies_are_capped() {
+        let state = WebauthnState {
+            max_ceremonies: 2,
+            ..get_state()
+        };
+        let start = Utc::now();
+        let first = state
+            .start_ceremony_at(authentication(&state), start)
+            .unwrap();
+        state
+            .start_ceremony_at(authentication(&state), start)
+            .unwrap();
+        assert!(matches!(
+            state.start_ceremony_at(authentication(&state), start),
+            Err(TcpError::TooManyRequestsError(_))
+        ));
+        // Finishing a ceremony frees its slot.
+        state.finish_ceremony_at(&first, start).unwrap();
+        state
+            .start_ceremony_at(authentication(&state), start)
+            .unwrap();
+        // Once the ceremonies expire, new ones are accepted again.
+        state
+            .start_ceremony_at(authentication(&state), start + CEREMONY_TIMEOUT)
+            .unwrap();
+    }
+}
@@ -0,0 +1,609 @@
+use crate::{configuration::WebhookOptions, jwt_sql_tables::DbConnection};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use hmac::{Hmac, Mac};
+use lldap_domain_handlers::events::{DirectoryEvent, DirectoryEventListener};
+use lldap_domain_model::model::{self, WebhookDeliveriesColumn};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    sea_query::Expr,
+};
+use sha2::Sha256;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::Notify;
+use tracing::{debug, error, instrument, warn};
+
+/// After that many failed attempts, a delivery is abandoned.
+const MAX_ATTEMPTS: i32 = 10;
+/// How often to look for deliveries to retry when nothing happens.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const BATCH_SIZE: u64 = 50;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+/// Stores the calls to the interested webhooks as soon as an event is emitted, then wakes up the
+/// corresponding delivery tasks.
+pub struct WebhookListener {
+    sql_pool: DbConnection,
+    webhooks: Vec<(WebhookOptions, Arc<Notify>)>,
+}
+
+#[async_trait]
+impl DirectoryEventListener for WebhookListener {
+    async fn on_event(&self, event: DirectoryEvent) {
+        let webhooks = self
+            .webhooks
+            .iter()
+            .filter(|(w, _)| w.wants(event.event_type()))
+            .collect::<Vec<_>>();
+        if webhooks.is_empty() {
+            return;
+        }
+        if let Err(e) = enqueue(
+            &self.sql_pool,
+            webhooks.iter().map(|(w, _)| w),
+            Utc::now(),
+            &event,
+        )
+        .await
+        {
+            error!(
+                "Could not queue the webhook calls for {}: {:#}",
+                event.event_type(),
+                e
+            );
+            return;
+        }
+        for (_, notify) in webhooks {
+            notify.notify_one();
+        }
+    }
+}
+
+/// Starts one delivery task per configured webhook, and returns the listener that queues their
+/// calls.
+pub async fn start(
+    webhooks: Vec<WebhookOptions>,
+    sql_pool: DbConnection,
+) -> Result<WebhookListener> {
+    abandon_unconfigured(&webhooks, &sql_pool).await?;
+    let (listener, workers) = new(webhooks, sql_pool)?;
+    for worker in workers {
+        tokio::spawn(worker.run());
+    }
+    Ok(listener)
+}
+
+fn new(
+    webhooks: Vec<WebhookOptions>,
+    sql_pool: DbConnection,
+) -> Result<(WebhookListener, Vec<WebhookWorker>)> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .context("while building the webhook HTTP client")?;
+    let webhooks = webhooks
+        .into_iter()
+        .map(|w| (w, Arc::new(Notify::new())))
+        .collect::<Vec<_>>();
+    let workers = webhooks
+        .iter()
+        .map(|(webhook, notify)| WebhookWorker {
+            webhook: webhook.clone(),
+            notify: notify.clone(),
+            sql_pool: sql_pool.clone(),
+            client: client.clone(),
+        })
+        .collect();
+    Ok((WebhookListener { sql_pool, webhooks }, workers))
+}
+
+/// Stores one pending delivery per webhook.
+#[instrument(skip(sql_pool, webhooks), level = "debug")]
+async fn enqueue<'a>(
+    sql_pool: &DbConnection,
+    webhooks: impl Iterator<Item = &'a WebhookOptions>,
+    timestamp: DateTime<Utc>,
+    event: &DirectoryEvent,
+) -> Result<()> {
+    let payload = make_payload(timestamp, event)?;
+    model::WebhookDeliveries::insert_many(webhooks.map(|webhook| {
+        model::webhook_deliveries::ActiveModel {
+            url: Set(webhook.url.to_string()),
+            event_type: Set(event.event_type().to_owned()),
+            payload: Set(payload.clone()),
+            status: Set(DeliveryStatus::Pending.as_str().to_owned()),
+            attempts: Set(0),
+            creation_date: Set(timestamp.naive_utc()),
+            next_attempt_date: Set(Some(timestamp.naive_utc())),
+            ..Default::default()
+        }
+    }))
+    .exec(sql_pool)
+    .await?;
+    Ok(())
+}
+
+/// Gives up on the pending calls to the webhooks removed from the configuration.
+async fn abandon_unconfigured(webhooks: &[WebhookOptions], sql_pool: &DbConnection) -> Result<()> {
+    let abandoned = model::WebhookDeliveries::update_many()
+        .col_expr(
+            WebhookDeliveriesColumn::Status,
+            Expr::value(DeliveryStatus::Failed.as_str()),
+        )
+        .col_expr(
+            WebhookDeliveriesColumn::NextAttemptDate,
+            Expr::value(Option::<NaiveDateTime>::None),
+        )
+        .col_expr(
+            WebhookDeliveriesColumn::LastResponse,
+            Expr::value("webhook no longer configured"),
+        )
+        .filter(WebhookDeliveriesColumn::Status.eq(DeliveryStatus::Pending.as_str()))
+        .filter(WebhookDeliveriesColumn::Url.is_not_in(webhooks.iter().map(|w| w.url.to_string())))
+        .exec(sql_pool)
+        .await
+        .context("while dropping the calls to the removed webhooks")?
+        .rows_affected;
+    if abandoned > 0 {
+        warn!(
+            "Dropped {} pending calls to webhooks that are no longer configured",
+            abandoned
+        );
+    }
+    Ok(())
+}
+
+/// Delivers the calls to a single webhook, in order, so that a slow endpoint only delays its own
+/// calls.
+struct WebhookWorker {
+    webhook: WebhookOptions,
+    notify: Arc<Notify>,
+    sql_pool: DbConnection,
+    client: reqwest::Client,
+}
+
+impl WebhookWorker {
+    async fn run(self) {
+        loop {
+            // This also picks up the deliveries left over by a previous run.
+            self.deliver_pending().await;
+            tokio::select! {
+                _ = self.notify.notified() => {}
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+        }
+    }
+
+    async fn deliver_pending(&self) {
+        loop {
+            let due = match model::WebhookDeliveries::find()
+                .filter(WebhookDeliveriesColumn::Url.eq(self.webhook.url.as_str()))
+                .filter(WebhookDeliveriesColumn::Status.eq(DeliveryStatus::Pending.as_str()))
+                .filter(WebhookDeliveriesColumn::NextAttemptDate.lte(Utc::now().naive_utc()))
+                .order_by_asc(WebhookDeliveriesColumn::Id)
+                .limit(BATCH_SIZE)
+                .all(&self.sql_pool)
+                .await
+            {
+                Ok(due) => due,
+                Err(e) => {
+                    error!(
+                        "DB error while listing the pending calls to {}: {}",
+                        self.webhook.url, e
+                    );
+                    return;
+                }
+            };
+            let batch_len = due.len();
+            for delivery in due {
+                self.deliver(delivery).await;
+            }
+            // Failed deliveries are pushed back in time, so this eventually stops.
+            if batch_len < BATCH_SIZE as usize {
+                return;
+            }
+        }
+    }
+
+    #[instrument(skip_all, fields(id = delivery.id, url = %delivery.url, event = %delivery.event_type))]
+    async fn deliver(&self, delivery: model::webhook_deliveries::Model) {
+        let now = Utc::now().naive_utc();
+        let attempts = delivery.attempts + 1;
+        match self.send(&delivery).await {
+            Ok(response) => {
+                debug!("Delivered: {}", response);
+                self.save_attempt(
+                    delivery.id,
+                    DeliveryStatus::Delivered,
+                    attempts,
+                    None,
+                    response,
+                )
+                .await;
+            }
+            Err(error) if attempts < MAX_ATTEMPTS => {
+                let next_attempt_date = now + retry_delay(attempts);
+                warn!(
+                    "Attempt {} failed ({}), retrying at {}",
+                    attempts, error, next_attempt_date
+                );
+                self.save_attempt(
+                    delivery.id,
+                    DeliveryStatus::Pending,
+                    attempts,
+                    Some(next_attempt_date),
+                    error,
+                )
+                .await;
+            }
+            Err(error) => {
+                error!("Attempt {} failed ({}), giving up", attempts, error);
+                self.save_attempt(delivery.id, DeliveryStatus::Failed, attempts, None, error)
+                    .await;
+            }
+        }
+    }
+
+    /// Returns the response status on success, or what went wrong.
+    async fn send(
+        &self,
+        delivery: &model::webhook_deliveries::Model,
+    ) -> std::result::Result<String, String> {
+        let mut request = self
+            .client
+            .post(self.webhook.url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header("X-LLDAP-Event", &delivery.event_type)
+            .header("X-LLDAP-Delivery", delivery.id.to_string())
+            .body(delivery.payload.clone());
+        if let Some(secret) = &self.webhook.secret {
+            request = request.header(
+                "X-LLDAP-Signature",
+                format!("sha256={}", sign(secret.unsecure(), &delivery.payload)),
+            );
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(status.to_string())
+        } else {
+            Err(status.to_string())
+        }
+    }
+
+    async fn save_attempt(
+        &self,
+        id: i64,
+        status: DeliveryStatus,
+        attempts: i32,
+        next_attempt_date: Option<NaiveDateTime>,
+        response: String,
+    ) {
+        if let Err(e) = (model::webhook_deliveries::ActiveModel {
+            id: Set(id),
+            status: Set(status.as_str().to_owned()),
+            attempts: Set(attempts),
+            next_attempt_date: Set(next_attempt_date),
+            last_attempt_date: Set(Some(Utc::now().naive_utc())),
+            last_response: Set(Some(response)),
+            ..Default::default()
+        })
+        .update(&self.sql_pool)
+        .await
+        {
+            error!("DB error while saving the webhook call: {}", e);
+        }
+    }
+}
+
+/// The serialized event, with the time at which it happened.
+fn make_payload(timestamp: DateTime<Utc>, event: &DirectoryEvent) -> Result<String> {
+    let mut payload = serde_json::to_value(event)?;
+    payload
+        .as_object_mut()
+        .context("events are serialized as objects")?
+        .insert("timestamp".to_owned(), serde_json::to_value(timestamp)?);
+    Ok(serde_json::to_string(&payload)?)
+}
+
+/// Hex-encoded HMAC-SHA256 of the payload.
+fn sign(secret: &str, payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(payload.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Exponential backoff: 30s, 1min, 2min... capped at 6 hours.
+fn retry_delay(attempts: i32) -> chrono::Duration {
+    let delay = chrono::Duration::seconds(30 * 2_i64.pow(attempts.clamp(1, 20) as u32 - 1));
+    delay.min(chrono::Duration::hours(6))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jwt_sql_tables;
+    use lldap_domain::types::{GroupId, UserId};
+    use lldap_sql_backend_handler::sql_tables;
+    use pretty_assertions::assert_eq;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    async fn get_initialized_db() -> DbConnection {
+        let mut sql_opt = sea_orm::ConnectOptions::new("sqlite::memory:".to_owned());
+        sql_opt.max_connections(1);
+        let sql_pool = sea_orm::Database::connect(sql_opt).await.unwrap();
+        sql_tables::init_table(&sql_pool).await.unwrap();
+        jwt_sql_tables::init_table(&sql_pool).await.unwrap();
+        sql_pool
+    }
+
+    /// Answers a single HTTP request with the given status, and returns the raw request.
+    async fn answer_one_request(listener: &TcpListener, status: &str) -> String {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0u8; 4096];
+        loop {
+            let read = stream.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&request);
+            if let Some((headers, body)) = text.split_once("\r\n\r\n") {
+                let content_length = headers
+                    .lines()
+                    .find_map(|l| {
+                        l.to_ascii_lowercase()
+                            .strip_prefix("content-length:")
+                            .map(|v| v.trim().parse::<usize>().unwrap())
+                    })
+                    .unwrap_or(0);
+                if body.len() >= content_length {
+                    break;
+                }
+            }
+            if read == 0 {
+                break;
+            }
+        }
+        stream
+            .write_all(
+                format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                    .as_bytes(),
+            )
+            .await
+            .unwrap();
+        String::from_utf8(request).unwrap()
+    }
+
+    fn make_webhook(
+        port: u16,
+        path: &str,
+        events: &[&str],
+        secret: Option<&str>,
+    ) -> WebhookOptions {
+        WebhookOptions {
+            url: format!("http://127.0.0.1:{port}{path}").parse().unwrap(),
+            events: events.iter().map(|e| e.to_string()).collect(),
+            secret: secret.map(Into::into),
+        }
+    }
+
+    async fn get_deliveries(sql_pool: &DbConnection) -> Vec<model::webhook_deliveries::Model> {
+        model::WebhookDeliveries::find()
+            .order_by_asc(WebhookDeliveriesColumn::Id)
+            .all(sql_pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_payload() {
+        let timestamp = DateTime::parse_from_rfc3339("2024-01-02T03:04:05Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let payload: serde_json::Value = serde_json::from_str(
+            &make_payload(
+                timestamp,
+                &DirectoryEvent::UserAddedToGroup {
+                    user_id: UserId::new("bob"),
+                    group_id: GroupId(3),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            payload,
+            serde_json::json!({
+                "event": "user_added_to_group",
+                "user_id": "bob",
+                "group_id": 3,
+                "timestamp": "2024-01-02T03:04:05Z",
+            })
+        );
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), chrono::Duration::seconds(30));
+        assert_eq!(retry_delay(3), chrono::Duration::minutes(2));
+        assert_eq!(retry_delay(15), chrono::Duration::hours(6));
+    }
+
+    #[tokio::test]
+    async fn test_delivery_with_retry() {
+        let sql_pool = get_initialized_db().await;
+        let endpoint = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = endpoint.local_addr().unwrap().port();
+        let (listener, workers) = new(
+            vec![make_webhook(
+                port,
+                "/hook",
+                &["user_created"],
+                Some("hook secret"),
+            )],
+            sql_pool.clone(),
+        )
+        .unwrap();
+        let worker = &workers[0];
+        listener
+            .on_event(DirectoryEvent::UserCreated {
+                user_id: UserId::new("bob"),
+            })
+            .await;
+        // Not subscribed to.
+        listener
+            .on_event(DirectoryEvent::GroupDeleted {
+                group_id: GroupId(3),
+            })
+            .await;
+        let deliveries = get_deliveries(&sql_pool).await;
+        assert_eq!(deliveries.len(), 1);
+        let payload = deliveries[0].payload.clone();
+
+        // The first attempt fails, and is scheduled for later.
+        let (request, _) = tokio::join!(
+            answer_one_request(&endpoint, "500 Internal Server Error"),
+            worker.deliver_pending()
+        );
+        assert!(request.starts_with("POST /hook "), "{request}");
+        let deliveries = get_deliveries(&sql_pool).await;
+        assert_eq!(deliveries[0].status, "pending");
+        assert_eq!(deliveries[0].attempts, 1);
+        assert_eq!(
+            deliveries[0].last_response.as_deref(),
+            Some("500 Internal Server Error")
+        );
+        assert!(deliveries[0].next_attempt_date.unwrap() > Utc::now().naive_utc());
+        // Nothing is due yet.
+        worker.deliver_pending().await;
+        assert_eq!(get_deliveries(&sql_pool).await[0].attempts, 1);
+
+        model::webhook_deliveries::ActiveModel {
+            id: Set(deliveries[0].id),
+            next_attempt_date: Set(Some(Utc::now().naive_utc())),
+            ..Default::default()
+        }
+        .update(&sql_pool)
+        .await
+        .unwrap();
+        let (request, _) = tokio::join!(
+            answer_one_request(&endpoint, "200 OK"),
+            worker.deliver_pending()
+        );
+        let lowercase_request = request.to_ascii_lowercase();
+        assert!(
+            lowercase_request.contains("x-lldap-event: user_created"),
+            "{request}"
+        );
+        assert!(
+            lowercase_request.contains(&format!(
+                "x-lldap-signature: sha256={}",
+                sign("hook secret", &payload)
+            )),
+            "{request}"
+        );
+        assert!(request.ends_with(&payload), "{request}");
+        let deliveries = get_deliveries(&sql_pool).await;
+        assert_eq!(deliveries[0].status, "delivered");
+        assert_eq!(deliveries[0].attempts, 2);
+        assert_eq!(deliveries[0].next_attempt_date, None);
+    }
+
+    #[tokio::test]
+    async fn test_no_event_lost_while_an_endpoint_hangs() {
+        let sql_pool = get_initialized_db().await;
+        let slow_endpoint = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let slow_port = slow_endpoint.local_addr().unwrap().port();
+        let fast_endpoint = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let fast_port = fast_endpoint.local_addr().unwrap().port();
+        let webhooks = vec![
+            make_webhook(slow_port, "/slow", &[], None),
+            make_webhook(fast_port, "/fast", &[], None),
+        ];
+        let (listener, workers) = new(webhooks.clone(), sql_pool.clone()).unwrap();
+        let tasks = workers
+            .into_iter()
+            .map(|w| tokio::spawn(w.run()))
+            .collect::<Vec<_>>();
+
+        listener
+            .on_event(DirectoryEvent::UserCreated {
+                user_id: UserId::new("bob"),
+            })
+            .await;
+        // The slow endpoint accepts the call, but never answers.
+        let _hanging_connection = slow_endpoint.accept().await.unwrap();
+        // That doesn't delay the calls to the other endpoint.
+        answer_one_request(&fast_endpoint, "200 OK").await;
+        listener
+            .on_event(DirectoryEvent::UserDeleted {
+                user_id: UserId::new("bob"),
+            })
+            .await;
+        answer_one_request(&fast_endpoint, "200 OK").await;
+
+        // The server stops while the call is still hanging.
+        for task in tasks {
+            task.abort();
+            let _ = task.await;
+        }
+        let pending_slow_calls = get_deliveries(&sql_pool)
+            .await
+            .into_iter()
+            .filter(|d| d.url.ends_with("/slow"))
+            .map(|d| (d.event_type, d.status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pending_slow_calls,
+            vec![
+                ("user_created".to_owned(), "pending".to_owned()),
+                ("user_deleted".to_owned(), "pending".to_owned()),
+            ]
+        );
+
+        // The next run delivers them.
+        let (_, workers) = new(webhooks, sql_pool.clone()).unwrap();
+        let (requests, _) = tokio::join!(
+            async {
+                vec![
+                    answer_one_request(&slow_endpoint, "200 OK").await,
+                    answer_one_request(&slow_endpoint, "200 OK").await,
+                ]
+            },
+            workers[0].deliver_pending()
+        );
+        assert!(requests[0].contains("user_created"), "{}", requests[0]);
+        assert!(requests[1].contains("user_deleted"), "{}", requests[1]);
+        assert!(
+            get_deliveries(&sql_pool)
+                .await
+                .iter()
+                .filter(|d| d.url.ends_with("/slow"))
+                .all(|d| d.status == "delivered")
+        );
+    }
+}